@@ -0,0 +1,859 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Implementation of `cargo wdk build`, which builds drivers and lays out
+//! signed driver packages. The packaging steps mirror the tasks in
+//! `rust-driver-makefile.toml`.
+
+use std::{
+    ffi::OsString,
+    io::BufReader,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, bail, Context};
+use cargo_metadata::{camino::Utf8PathBuf, Artifact, Message, Metadata, MetadataCommand, Package};
+use wdk_build::{
+    inf_template::InfTemplateVariables,
+    metadata::{DriverModel, WDKMetadata},
+    CPUArchitecture,
+};
+
+use crate::run_command;
+
+/// Name of the metadata table that marks a package as a driver
+const WDK_METADATA_KEY: &str = "wdk";
+
+/// Space-delimited flags that are passed to `infverif` in addition to `/v /w`
+/// (ex. `/msft`). This is the same environment variable that is used by
+/// `rust-driver-makefile.toml`.
+const WDK_BUILD_ADDITIONAL_INFVERIF_FLAGS_ENV_VAR: &str = "WDK_BUILD_ADDITIONAL_INFVERIF_FLAGS";
+
+/// Timestamp server used when signing the driver binary and catalog file
+pub const TIMESTAMP_SERVER_URL: &str = "http://timestamp.digicert.com";
+
+/// Arguments of `cargo wdk build`. Arguments that are common with `cargo build`
+/// are forwarded to it.
+#[derive(Debug, clap::Args)]
+pub struct BuildArgs {
+    #[command(flatten)]
+    base: BaseOptions,
+
+    #[command(flatten)]
+    #[command(next_help_heading = "Package Selection")]
+    workspace: clap_cargo::Workspace,
+
+    #[command(flatten)]
+    #[command(next_help_heading = "Feature Selection")]
+    features: clap_cargo::Features,
+
+    #[command(flatten)]
+    compilation_options: CompilationOptions,
+
+    #[command(flatten)]
+    manifest_options: ManifestOptions,
+
+    #[command(flatten)]
+    signing_options: SigningOptions,
+}
+
+#[derive(Debug, clap::Args)]
+struct BaseOptions {
+    #[arg(long, help = "Do not print cargo log messages")]
+    quiet: bool,
+
+    #[arg(short, long, action = clap::ArgAction::Count, help = "Use verbose output (-vv very verbose/build.rs output)")]
+    verbose: u8,
+}
+
+/// Options selecting the profile and target that drivers are built for. These
+/// are shared with `cargo wdk info`.
+#[derive(Debug, clap::Args)]
+#[command(next_help_heading = "Compilation Options")]
+pub struct CompilationOptions {
+    #[arg(
+        short,
+        long,
+        help = "Build artifacts in release mode, with optimizations"
+    )]
+    release: bool,
+
+    #[arg(
+        long,
+        value_name = "PROFILE-NAME",
+        help = "Build artifacts with the specified profile"
+    )]
+    profile: Option<String>,
+
+    #[arg(long, value_name = "TRIPLE", help = "Build for a target triple")]
+    target: Option<String>,
+}
+
+#[derive(Debug, clap::Args)]
+#[command(next_help_heading = "Manifest Options")]
+struct ManifestOptions {
+    #[arg(long, help = "Require Cargo.lock and cache are up to date")]
+    frozen: bool,
+
+    #[arg(long, help = "Require Cargo.lock is up to date")]
+    locked: bool,
+
+    #[arg(long, help = "Run without accessing the network")]
+    offline: bool,
+}
+
+#[derive(Debug, clap::Args)]
+#[command(next_help_heading = "Signing Options")]
+struct SigningOptions {
+    #[arg(
+        long,
+        value_name = "STORE",
+        default_value = "WDRTestCertStore",
+        help = "Certificate store containing the test certificate used to sign the driver package"
+    )]
+    cert_store: String,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        default_value = "WDRLocalTestCert",
+        help = "Name of the test certificate used to sign the driver package. It is generated if \
+                it does not exist in the certificate store."
+    )]
+    cert_name: String,
+
+    #[arg(
+        long,
+        help = "Verify the signatures of the driver binary and catalog file. This requires the \
+                test certificate to be trusted by the local machine."
+    )]
+    verify_signature: bool,
+}
+
+/// A driver built by `cargo build`, and the locations used to package it
+pub struct DriverPackage {
+    /// File name of the driver binary without its extension (ex.
+    /// `sample_kmdf_driver`)
+    pub name: String,
+    /// Version of the driver's package
+    pub version: String,
+    /// Directory containing the driver's `Cargo.toml` and `.inx` file
+    source_directory: PathBuf,
+    /// Directory containing the driver binary generated by `cargo build`
+    output_directory: PathBuf,
+    /// Directory that the driver package is laid out in
+    pub package_directory: PathBuf,
+    /// Extension of the driver binary in the driver package (ie. `sys` for
+    /// kernel-mode drivers and `dll` for user-mode drivers)
+    pub binary_extension: &'static str,
+    /// Driver model resolved from the `wdk` metadata of the driver's package
+    /// and its dependencies
+    driver_model: Option<DriverModel>,
+}
+
+impl BuildArgs {
+    /// Arguments that are forwarded to `cargo build`
+    fn cargo_build_args(&self) -> Vec<String> {
+        let mut cargo_build_args = Vec::new();
+
+        if self.base.quiet {
+            cargo_build_args.push("--quiet".to_string());
+        }
+        for _ in 0..self.base.verbose {
+            cargo_build_args.push("--verbose".to_string());
+        }
+
+        for package in &self.workspace.package {
+            cargo_build_args.push("--package".to_string());
+            cargo_build_args.push(package.clone());
+        }
+        if self.workspace.workspace || self.workspace.all {
+            cargo_build_args.push("--workspace".to_string());
+        }
+        for exclude in &self.workspace.exclude {
+            cargo_build_args.push("--exclude".to_string());
+            cargo_build_args.push(exclude.clone());
+        }
+
+        if self.features.all_features {
+            cargo_build_args.push("--all-features".to_string());
+        }
+        if self.features.no_default_features {
+            cargo_build_args.push("--no-default-features".to_string());
+        }
+        if !self.features.features.is_empty() {
+            cargo_build_args.push("--features".to_string());
+            cargo_build_args.push(self.features.features.join(","));
+        }
+
+        if self.compilation_options.release {
+            cargo_build_args.push("--release".to_string());
+        }
+        if let Some(profile) = &self.compilation_options.profile {
+            cargo_build_args.push("--profile".to_string());
+            cargo_build_args.push(profile.clone());
+        }
+        if let Some(target) = &self.compilation_options.target {
+            cargo_build_args.push("--target".to_string());
+            cargo_build_args.push(target.clone());
+        }
+
+        if self.manifest_options.frozen {
+            cargo_build_args.push("--frozen".to_string());
+        }
+        if self.manifest_options.locked {
+            cargo_build_args.push("--locked".to_string());
+        }
+        if self.manifest_options.offline {
+            cargo_build_args.push("--offline".to_string());
+        }
+
+        cargo_build_args
+    }
+
+    /// Architecture of the drivers being built, based on `--target` or the
+    /// host architecture if no target is provided
+    pub fn target_architecture(&self) -> anyhow::Result<CPUArchitecture> {
+        self.compilation_options.target_architecture()
+    }
+}
+
+impl CompilationOptions {
+    /// Architecture of the drivers being built, based on `--target` or the
+    /// host architecture if no target is provided
+    pub fn target_architecture(&self) -> anyhow::Result<CPUArchitecture> {
+        self.target.as_ref().map_or_else(
+            || {
+                Ok(CPUArchitecture::try_from_cargo_str(std::env::consts::ARCH)
+                    .expect("The rust standard library should always set std::env::consts::ARCH"))
+            },
+            |target| {
+                CPUArchitecture::try_from_target_triple(target).ok_or_else(|| {
+                    anyhow!(
+                        "target triple {target} is not supported. Supported targets are \
+                         x86_64-pc-windows-msvc and aarch64-pc-windows-msvc."
+                    )
+                })
+            },
+        )
+    }
+
+    /// Directory that `cargo build` places the driver binaries in, within
+    /// `target_directory`. This mirrors the layout of Cargo's target
+    /// directory, where the `dev` profile is built in `debug`.
+    pub fn output_directory(&self, target_directory: &Path) -> PathBuf {
+        let profile_directory = match (self.release, self.profile.as_deref()) {
+            (_, Some("dev" | "test")) | (false, None) => "debug",
+            (_, Some("release" | "bench")) | (true, None) => "release",
+            (_, Some(profile)) => profile,
+        };
+
+        let mut output_directory = target_directory.to_path_buf();
+        if let Some(target) = &self.target {
+            output_directory.push(target);
+        }
+        output_directory.join(profile_directory)
+    }
+}
+
+impl DriverPackage {
+    fn new(
+        package: &Package,
+        artifact: &Artifact,
+        driver_model: Option<DriverModel>,
+    ) -> Option<Self> {
+        let driver_binary = artifact
+            .filenames
+            .iter()
+            .find(|filename| filename.extension() == Some("dll"))?;
+        let name = driver_binary.file_stem()?.to_string();
+        let output_directory = driver_binary.parent()?.as_std_path().to_path_buf();
+
+        Some(Self {
+            package_directory: output_directory.join(format!("{name}_package")),
+            source_directory: package.manifest_path.parent()?.as_std_path().to_path_buf(),
+            output_directory,
+            name,
+            version: package.version.to_string(),
+            binary_extension: match driver_model {
+                Some(DriverModel::UMDF { .. }) => "dll",
+                Some(DriverModel::KMDF { .. } | DriverModel::WDM) | None => "sys",
+            },
+            driver_model,
+        })
+    }
+
+    fn output_file(&self, extension: &str) -> PathBuf {
+        self.output_directory
+            .join(format!("{}.{extension}", self.name))
+    }
+
+    /// Path of the file with `extension` in the driver package (ex. the `.inf`)
+    pub fn package_file(&self, extension: &str) -> PathBuf {
+        self.package_directory
+            .join(format!("{}.{extension}", self.name))
+    }
+}
+
+/// Builds all selected drivers and lays out a signed driver package for each of
+/// them. Returns the driver packages that were created.
+pub fn run(args: &BuildArgs) -> anyhow::Result<Vec<DriverPackage>> {
+    let target_architecture = args.target_architecture()?;
+    wdk_build::cargo_make::configure_wdk_tools_path()
+        .context("failed to add WDK tools to the path")?;
+
+    let cargo_metadata = MetadataCommand::new()
+        .exec()
+        .context("failed to execute cargo metadata")?;
+
+    let artifacts = cargo_build(args)?;
+    let driver_packages = find_driver_packages(&cargo_metadata, &artifacts)?;
+    if driver_packages.is_empty() {
+        println!(
+            "No packages with a {WDK_METADATA_KEY} metadata section were built. Skipping driver \
+             packaging."
+        );
+        return Ok(driver_packages);
+    }
+
+    for driver_package in &driver_packages {
+        println!("Packaging driver: {}", driver_package.name);
+        package_driver(args, driver_package, target_architecture)?;
+        println!(
+            "Driver package created at {}",
+            driver_package.package_directory.display()
+        );
+    }
+    Ok(driver_packages)
+}
+
+/// Executes `cargo build`, and returns the artifacts it generated
+fn cargo_build(args: &BuildArgs) -> anyhow::Result<Vec<Artifact>> {
+    cargo_artifacts("build", &[], args)
+}
+
+/// Builds the test binaries of all selected packages via `cargo test --no-run`,
+/// and returns the paths of the test executables
+pub fn build_tests(args: &BuildArgs) -> anyhow::Result<Vec<PathBuf>> {
+    Ok(cargo_artifacts("test", &["--no-run"], args)?
+        .into_iter()
+        .filter(|artifact| artifact.profile.test)
+        .filter_map(|artifact| artifact.executable)
+        .map(Utf8PathBuf::into_std_path_buf)
+        .collect())
+}
+
+/// Executes `cargo <subcommand>` with `subcommand_args` and the arguments
+/// forwarded from `args`, and returns the artifacts it generated
+fn cargo_artifacts(
+    subcommand: &str,
+    subcommand_args: &[&str],
+    args: &BuildArgs,
+) -> anyhow::Result<Vec<Artifact>> {
+    // Cargo sets CARGO to the path of the cargo binary that invoked this
+    // subcommand
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let mut child = Command::new(cargo)
+        .arg(subcommand)
+        .args(subcommand_args)
+        .arg("--message-format=json-render-diagnostics")
+        .args(args.cargo_build_args())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to execute cargo {subcommand}"))?;
+
+    let stdout = child
+        .stdout
+        .take()
+        .expect("stdout of cargo should be piped");
+    let mut artifacts = Vec::new();
+    for message in Message::parse_stream(BufReader::new(stdout)) {
+        if let Message::CompilerArtifact(artifact) =
+            message.with_context(|| format!("failed to read cargo {subcommand} output"))?
+        {
+            artifacts.push(artifact);
+        }
+    }
+
+    let status = child
+        .wait()
+        .with_context(|| format!("failed to wait for cargo {subcommand}"))?;
+    if !status.success() {
+        bail!("cargo {subcommand} failed with {status}");
+    }
+    Ok(artifacts)
+}
+
+/// Finds the `cdylib` artifacts of workspace members that are marked as
+/// drivers via a `wdk` metadata section. The `wdk` metadata of each driver is
+/// resolved from its own dependency graph, so that drivers with different
+/// driver models can be built from the same workspace.
+fn find_driver_packages(
+    cargo_metadata: &Metadata,
+    artifacts: &[Artifact],
+) -> anyhow::Result<Vec<DriverPackage>> {
+    let mut driver_packages = Vec::new();
+    for artifact in artifacts {
+        if !artifact.target.kind.iter().any(|kind| kind == "cdylib")
+            || !cargo_metadata
+                .workspace_members
+                .contains(&artifact.package_id)
+        {
+            continue;
+        }
+        let Some(package) = cargo_metadata
+            .packages
+            .iter()
+            .find(|package| package.id == artifact.package_id)
+        else {
+            continue;
+        };
+        if package.metadata.get(WDK_METADATA_KEY).is_none() {
+            continue;
+        }
+
+        let driver_model =
+            WDKMetadata::try_from_cargo_metadata_for_package(cargo_metadata, &package.id)
+                .with_context(|| format!("failed to resolve the wdk metadata of {}", package.name))?
+                .driver_model;
+        driver_packages.extend(DriverPackage::new(package, artifact, driver_model));
+    }
+    Ok(driver_packages)
+}
+
+/// Executes the driver packaging steps of `rust-driver-makefile.toml` for
+/// `driver_package`
+fn package_driver(
+    args: &BuildArgs,
+    driver_package: &DriverPackage,
+    target_architecture: CPUArchitecture,
+) -> anyhow::Result<()> {
+    std::fs::create_dir_all(&driver_package.package_directory).with_context(|| {
+        format!(
+            "failed to create driver package directory at {}",
+            driver_package.package_directory.display()
+        )
+    })?;
+
+    // Rename the driver binary and stamp the INF in the output directory
+    rename_driver_binary(driver_package)?;
+    render_inx(driver_package, target_architecture)?;
+    run_command(Command::new("stampinf").args(stampinf_args(driver_package, target_architecture)))?;
+    run_command(
+        Command::new("infverif")
+            .args(["/v", "/w"])
+            .args(
+                std::env::var(WDK_BUILD_ADDITIONAL_INFVERIF_FLAGS_ENV_VAR)
+                    .unwrap_or_default()
+                    .split_whitespace(),
+            )
+            .arg(driver_package.output_file("inf")),
+    )?;
+
+    // Lay out the driver package
+    for extension in [driver_package.binary_extension, "pdb", "inf"] {
+        copy_file(
+            &driver_package.output_file(extension),
+            &driver_package.package_file(extension),
+        )?;
+    }
+    let map_file = driver_package
+        .output_directory
+        .join("deps")
+        .join(format!("{}.map", driver_package.name));
+    if map_file.exists() {
+        copy_file(&map_file, &driver_package.package_file("map"))?;
+    }
+
+    run_command(
+        Command::new("inf2cat")
+            .arg(format!(
+                "/driver:{}",
+                driver_package.package_directory.display()
+            ))
+            .arg(format!("/os:{}", target_architecture.as_inf2cat_os_str()))
+            .arg("/uselocaltime"),
+    )?;
+
+    // Sign the driver package with the test certificate
+    let certificate = driver_package
+        .output_directory
+        .join(format!("{}.cer", args.signing_options.cert_name));
+    ensure_test_certificate(args, &certificate)?;
+    copy_file(
+        &certificate,
+        &driver_package
+            .package_directory
+            .join(format!("{}.cer", args.signing_options.cert_name)),
+    )?;
+    for extension in [driver_package.binary_extension, "cat"] {
+        let file_to_sign = driver_package.package_file(extension);
+        run_command(
+            Command::new("signtool")
+                .args([
+                    "sign",
+                    "/v",
+                    "/s",
+                    &args.signing_options.cert_store,
+                    "/n",
+                    &args.signing_options.cert_name,
+                ])
+                .args(["/t", TIMESTAMP_SERVER_URL, "/fd", "SHA256"])
+                .arg(&file_to_sign),
+        )?;
+        if args.signing_options.verify_signature {
+            run_command(
+                Command::new("signtool")
+                    .args(["verify", "/v", "/pa"])
+                    .arg(&file_to_sign),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies the `.dll` generated by `cargo build` for `driver_package` to the
+/// binary extension of its driver model in the output directory. User-mode
+/// drivers keep the `.dll`, so there is nothing to copy for them.
+fn rename_driver_binary(driver_package: &DriverPackage) -> anyhow::Result<()> {
+    if driver_package.binary_extension == "dll" {
+        return Ok(());
+    }
+    copy_file(
+        &driver_package.output_file("dll"),
+        &driver_package.output_file(driver_package.binary_extension),
+    )
+}
+
+/// Renders the `.inx` template of `driver_package` into its INF in the output
+/// directory, resolving its variables from the package's version, the target
+/// architecture and the driver model
+fn render_inx(
+    driver_package: &DriverPackage,
+    target_architecture: CPUArchitecture,
+) -> anyhow::Result<()> {
+    let mut variables = InfTemplateVariables::new(
+        &driver_package.name,
+        &driver_package.version,
+        target_architecture,
+    )?;
+    if let Some(driver_model) = driver_package.driver_model {
+        variables = variables.with_driver_config(&driver_model.into());
+    }
+
+    let inx_file = driver_package
+        .source_directory
+        .join(format!("{}.inx", driver_package.name));
+    variables
+        .render_file(&inx_file, &driver_package.output_file("inf"))
+        .with_context(|| format!("failed to render {}", inx_file.display()))?;
+    Ok(())
+}
+
+/// Arguments passed to `stampinf` to stamp the INF of `driver_package` in the
+/// output directory. The KMDF or UMDF version is taken from the `driver-model`
+/// in the `wdk` metadata of the driver.
+fn stampinf_args(
+    driver_package: &DriverPackage,
+    target_architecture: CPUArchitecture,
+) -> Vec<OsString> {
+    let mut stampinf_args: Vec<OsString> = vec![
+        "-f".into(),
+        driver_package.output_file("inf").into(),
+        "-d".into(),
+        "*".into(),
+        "-a".into(),
+        target_architecture.as_stampinf_str().into(),
+        "-c".into(),
+        format!("{}.cat", driver_package.name).into(),
+        "-v".into(),
+        "*".into(),
+    ];
+    match driver_package.driver_model {
+        Some(DriverModel::KMDF {
+            kmdf_version_major,
+            kmdf_version_minor,
+        }) => {
+            stampinf_args.push("-k".into());
+            stampinf_args.push(format!("{kmdf_version_major}.{kmdf_version_minor}").into());
+        }
+        Some(DriverModel::UMDF {
+            umdf_version_major,
+            umdf_version_minor,
+        }) => {
+            stampinf_args.push("-u".into());
+            stampinf_args.push(format!("{umdf_version_major}.{umdf_version_minor}.0").into());
+        }
+        Some(DriverModel::WDM) | None => {}
+    }
+    stampinf_args
+}
+
+/// Exports the test certificate from the certificate store to `certificate`,
+/// generating it first if it does not exist yet
+fn ensure_test_certificate(args: &BuildArgs, certificate: &Path) -> anyhow::Result<()> {
+    let certificate_exported = Command::new("certmgr.exe")
+        .args([
+            "-put",
+            "-s",
+            &args.signing_options.cert_store,
+            "-c",
+            "-n",
+            &args.signing_options.cert_name,
+        ])
+        .arg(certificate)
+        .stdout(Stdio::null())
+        .status()
+        .context("failed to execute certmgr.exe")?
+        .success();
+    if certificate_exported {
+        return Ok(());
+    }
+
+    println!(
+        "{} not found in {}. Generating new certificate.",
+        args.signing_options.cert_name, args.signing_options.cert_store
+    );
+    run_command(
+        Command::new("makecert")
+            .args(["-r", "-pe", "-a", "SHA256", "-eku", "1.3.6.1.5.5.7.3.3"])
+            .args(["-ss", &args.signing_options.cert_store])
+            .args(["-n", &format!("CN={}", args.signing_options.cert_name)])
+            .arg(certificate),
+    )
+}
+
+/// Copies `source` to `destination`, with the paths of both in the error
+pub fn copy_file(source: &Path, destination: &Path) -> anyhow::Result<()> {
+    std::fs::copy(source, destination).with_context(|| {
+        format!(
+            "failed to copy {} to {}",
+            source.display(),
+            destination.display()
+        )
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Debug, Parser)]
+    struct TestCli {
+        #[command(flatten)]
+        build_args: BuildArgs,
+    }
+
+    fn parse_build_args(args: &[&str]) -> BuildArgs {
+        TestCli::parse_from(std::iter::once("cargo-wdk").chain(args.iter().copied())).build_args
+    }
+
+    #[test]
+    fn cargo_build_args_are_forwarded() {
+        let build_args = parse_build_args(&[
+            "-p",
+            "sample-kmdf-driver",
+            "--features",
+            "foo",
+            "--features",
+            "bar",
+            "--release",
+            "--target",
+            "aarch64-pc-windows-msvc",
+            "--locked",
+            "--cert-name",
+            "MyTestCert",
+        ]);
+
+        assert_eq!(
+            build_args.cargo_build_args(),
+            [
+                "--package",
+                "sample-kmdf-driver",
+                "--features",
+                "foo,bar",
+                "--release",
+                "--target",
+                "aarch64-pc-windows-msvc",
+                "--locked",
+            ]
+        );
+        assert_eq!(build_args.signing_options.cert_name, "MyTestCert");
+        assert_eq!(build_args.signing_options.cert_store, "WDRTestCertStore");
+    }
+
+    #[test]
+    fn target_architecture() {
+        assert_eq!(
+            parse_build_args(&["--target", "aarch64-pc-windows-msvc"])
+                .target_architecture()
+                .unwrap(),
+            CPUArchitecture::ARM64
+        );
+        assert!(parse_build_args(&["--target", "x86_64-unknown-linux-gnu"])
+            .target_architecture()
+            .is_err());
+    }
+
+    #[test]
+    fn output_directory_follows_profile_and_target() {
+        let target_directory = Path::new("target");
+
+        assert_eq!(
+            parse_build_args(&[])
+                .compilation_options
+                .output_directory(target_directory),
+            Path::new("target").join("debug")
+        );
+        assert_eq!(
+            parse_build_args(&["--release", "--target", "aarch64-pc-windows-msvc"])
+                .compilation_options
+                .output_directory(target_directory),
+            Path::new("target")
+                .join("aarch64-pc-windows-msvc")
+                .join("release")
+        );
+        assert_eq!(
+            parse_build_args(&["--profile", "dev"])
+                .compilation_options
+                .output_directory(target_directory),
+            Path::new("target").join("debug")
+        );
+        assert_eq!(
+            parse_build_args(&["--profile", "driver-ci"])
+                .compilation_options
+                .output_directory(target_directory),
+            Path::new("target").join("driver-ci")
+        );
+    }
+
+    fn driver_package(
+        name: &str,
+        output_directory: &Path,
+        driver_model: Option<DriverModel>,
+    ) -> DriverPackage {
+        DriverPackage {
+            name: name.to_string(),
+            version: "0.1.0".to_string(),
+            source_directory: PathBuf::from(name),
+            output_directory: output_directory.to_path_buf(),
+            package_directory: output_directory.join(format!("{name}_package")),
+            binary_extension: match driver_model {
+                Some(DriverModel::UMDF { .. }) => "dll",
+                Some(DriverModel::KMDF { .. } | DriverModel::WDM) | None => "sys",
+            },
+            driver_model,
+        }
+    }
+
+    #[test]
+    fn stampinf_args_use_driver_model_version() {
+        let kmdf_args = stampinf_args(
+            &driver_package(
+                "sample_kmdf_driver",
+                Path::new("target"),
+                Some(DriverModel::KMDF {
+                    kmdf_version_major: 1,
+                    kmdf_version_minor: 33,
+                }),
+            ),
+            CPUArchitecture::AMD64,
+        );
+        assert_eq!(
+            kmdf_args,
+            [
+                "-f",
+                Path::new("target")
+                    .join("sample_kmdf_driver.inf")
+                    .to_str()
+                    .unwrap(),
+                "-d",
+                "*",
+                "-a",
+                "amd64",
+                "-c",
+                "sample_kmdf_driver.cat",
+                "-v",
+                "*",
+                "-k",
+                "1.33",
+            ]
+        );
+
+        let umdf_args = stampinf_args(
+            &driver_package(
+                "sample_umdf_driver",
+                Path::new("target"),
+                Some(DriverModel::UMDF {
+                    umdf_version_major: 2,
+                    umdf_version_minor: 33,
+                }),
+            ),
+            CPUArchitecture::ARM64,
+        );
+        assert_eq!(umdf_args[5], "arm64");
+        assert_eq!(umdf_args[10..], ["-u", "2.33.0"]);
+
+        let wdm_args = stampinf_args(
+            &driver_package(
+                "sample_wdm_driver",
+                Path::new("target"),
+                Some(DriverModel::WDM),
+            ),
+            CPUArchitecture::AMD64,
+        );
+        assert_eq!(wdm_args.len(), 10);
+    }
+
+    #[test]
+    fn umdf_driver_binary_is_not_renamed() {
+        let output_directory =
+            std::env::temp_dir().join(format!("cargo-wdk-umdf-rename-{}", std::process::id()));
+        std::fs::create_dir_all(&output_directory).unwrap();
+        let driver_package = driver_package(
+            "sample_umdf_driver",
+            &output_directory,
+            Some(DriverModel::UMDF {
+                umdf_version_major: 2,
+                umdf_version_minor: 33,
+            }),
+        );
+        std::fs::write(driver_package.output_file("dll"), b"driver binary").unwrap();
+
+        rename_driver_binary(&driver_package).unwrap();
+
+        assert_eq!(
+            std::fs::read(driver_package.output_file("dll")).unwrap(),
+            b"driver binary"
+        );
+        std::fs::remove_dir_all(&output_directory).unwrap();
+    }
+
+    #[test]
+    fn kmdf_driver_binary_is_renamed_to_sys() {
+        let output_directory =
+            std::env::temp_dir().join(format!("cargo-wdk-kmdf-rename-{}", std::process::id()));
+        std::fs::create_dir_all(&output_directory).unwrap();
+        let driver_package = driver_package(
+            "sample_kmdf_driver",
+            &output_directory,
+            Some(DriverModel::KMDF {
+                kmdf_version_major: 1,
+                kmdf_version_minor: 33,
+            }),
+        );
+        std::fs::write(driver_package.output_file("dll"), b"driver binary").unwrap();
+
+        rename_driver_binary(&driver_package).unwrap();
+
+        assert_eq!(
+            std::fs::read(driver_package.output_file("sys")).unwrap(),
+            b"driver binary"
+        );
+        std::fs::remove_dir_all(&output_directory).unwrap();
+    }
+}
@@ -0,0 +1,494 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Implementation of `cargo wdk deploy`, which builds drivers and installs
+//! their driver packages on a test machine.
+//!
+//! The test machine is configured via a `.wdk-deploy.toml` file in the root of
+//! the workspace. All keys are optional, and can be overridden via the command
+//! line:
+//!
+//! ```toml
+//! # Host name of the test machine
+//! target-machine = "driver-test-vm"
+//! # How the driver package is copied to the test machine: "smb" (via the
+//! # administrative share of the drive containing `remote-directory`) or "winrm"
+//! transport = "smb"
+//! # Directory on the test machine that driver packages are copied to
+//! remote-directory = 'C:\DriverTest'
+//! # Tool used to install the driver on the test machine: "pnputil" or "devcon"
+//! install-tool = "devcon"
+//! # Hardware ID of the device to install the driver for. Required by devcon.
+//! hardware-id = 'Root\SAMPLE_KMDF_HW_ID'
+//! # Reboot the test machine after installing the driver
+//! reboot = false
+//! ```
+//!
+//! The driver is installed via PowerShell remoting, so it must be enabled on
+//! the test machine (ex. via `Enable-PSRemoting`), regardless of the transport
+//! used to copy the driver package.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{bail, Context};
+use cargo_metadata::MetadataCommand;
+use serde::Deserialize;
+
+use crate::{
+    build::{self, BuildArgs, DriverPackage},
+    run_command,
+};
+
+/// Name of the file, in the root of the workspace, that configures the test
+/// machine
+const DEPLOY_CONFIG_FILE_NAME: &str = ".wdk-deploy.toml";
+
+/// Default directory on the test machine that driver packages are copied to
+const DEFAULT_REMOTE_DIRECTORY: &str = r"C:\DriverTest";
+
+/// Arguments of `cargo wdk deploy`
+#[derive(Debug, clap::Args)]
+pub struct DeployArgs {
+    #[command(flatten)]
+    pub build_args: BuildArgs,
+
+    #[command(flatten)]
+    pub deploy_options: DeployOptions,
+}
+
+#[derive(Debug, clap::Args)]
+#[command(next_help_heading = "Deployment Options")]
+pub struct DeployOptions {
+    #[arg(
+        long,
+        visible_alias = "vm",
+        value_name = "HOST",
+        help = "Host name of the test machine to deploy to [default: target-machine in \
+                .wdk-deploy.toml]"
+    )]
+    target_machine: Option<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "How the driver package is copied to the test machine [default: transport in \
+                .wdk-deploy.toml, or smb]"
+    )]
+    transport: Option<Transport>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Tool used to install the driver on the test machine [default: install-tool in \
+                .wdk-deploy.toml, or pnputil]"
+    )]
+    install_tool: Option<InstallTool>,
+
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Reboot the test machine after installing the driver, or not with \
+                --reboot=false [default: reboot in .wdk-deploy.toml, or false]"
+    )]
+    reboot: Option<bool>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Path to the deployment configuration [default: .wdk-deploy.toml in the workspace \
+                root]"
+    )]
+    config: Option<PathBuf>,
+}
+
+/// Contents of `.wdk-deploy.toml`
+#[derive(Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct DeployConfig {
+    target_machine: Option<String>,
+    #[serde(default)]
+    transport: Transport,
+    remote_directory: Option<String>,
+    #[serde(default)]
+    install_tool: InstallTool,
+    hardware_id: Option<String>,
+    #[serde(default)]
+    reboot: bool,
+}
+
+/// How the driver package is copied to the test machine
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+enum Transport {
+    /// Copy via the administrative share (ex. `\\<host>\C$`) of the test
+    /// machine
+    #[default]
+    Smb,
+    /// Copy via a PowerShell remoting session to the test machine
+    Winrm,
+}
+
+/// Tool used to install the driver on the test machine
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+enum InstallTool {
+    /// Add the driver package to the driver store and install it on matching
+    /// devices via `pnputil /add-driver /install`
+    #[default]
+    Pnputil,
+    /// Create a root-enumerated device for `hardware-id` and install the
+    /// driver on it via `devcon install`
+    Devcon,
+}
+
+impl DeployConfig {
+    /// Reads the deployment configuration from `path`. If `path` is not
+    /// provided, `.wdk-deploy.toml` in the workspace root is used if it exists.
+    fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let path = if let Some(path) = path {
+            path.to_path_buf()
+        } else {
+            let workspace_config = MetadataCommand::new()
+                .no_deps()
+                .exec()
+                .context("failed to execute cargo metadata")?
+                .workspace_root
+                .join(DEPLOY_CONFIG_FILE_NAME)
+                .into_std_path_buf();
+            if !workspace_config.exists() {
+                return Ok(Self::default());
+            }
+            workspace_config
+        };
+
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    /// Applies the overrides passed via the command line
+    fn apply_overrides(&mut self, deploy_options: &DeployOptions) {
+        if let Some(target_machine) = &deploy_options.target_machine {
+            self.target_machine = Some(target_machine.clone());
+        }
+        if let Some(transport) = deploy_options.transport {
+            self.transport = transport;
+        }
+        if let Some(install_tool) = deploy_options.install_tool {
+            self.install_tool = install_tool;
+        }
+        if let Some(reboot) = deploy_options.reboot {
+            self.reboot = reboot;
+        }
+    }
+}
+
+/// Builds all selected drivers and installs their driver packages on the test
+/// machine
+pub fn run(args: &DeployArgs) -> anyhow::Result<()> {
+    let deployment = Deployment::new(&args.deploy_options)?;
+    for driver_package in build::run(&args.build_args)? {
+        deployment.install(&driver_package)?;
+    }
+    if deployment.config.reboot {
+        deployment.reboot()?;
+    }
+    Ok(())
+}
+
+/// A test machine that driver packages (and other files) are deployed to, as
+/// configured by `.wdk-deploy.toml` and the command line
+pub struct Deployment {
+    config: DeployConfig,
+    /// Host name of the test machine
+    pub target_machine: String,
+    /// Directory on the test machine that files are copied to
+    pub remote_directory: String,
+}
+
+impl Deployment {
+    /// Loads the deployment configuration and applies the overrides passed via
+    /// the command line
+    pub fn new(deploy_options: &DeployOptions) -> anyhow::Result<Self> {
+        let mut config = DeployConfig::load(deploy_options.config.as_deref())?;
+        config.apply_overrides(deploy_options);
+        let Some(target_machine) = config.target_machine.clone() else {
+            bail!(
+                "no test machine to deploy to. Specify one via --target-machine or the \
+                 target-machine key of {DEPLOY_CONFIG_FILE_NAME}"
+            );
+        };
+        if config.install_tool == InstallTool::Devcon && config.hardware_id.is_none() {
+            bail!("devcon requires a hardware-id to be specified in {DEPLOY_CONFIG_FILE_NAME}");
+        }
+        let remote_directory = config
+            .remote_directory
+            .clone()
+            .unwrap_or_else(|| DEFAULT_REMOTE_DIRECTORY.to_string());
+
+        Ok(Self {
+            config,
+            target_machine,
+            remote_directory,
+        })
+    }
+
+    /// Copies `local_directory` into the remote directory of the test machine
+    /// via the configured transport. Returns the path of the copy on the test
+    /// machine.
+    pub fn copy_directory(&self, local_directory: &Path) -> anyhow::Result<String> {
+        let Some(directory_name) = local_directory.file_name() else {
+            bail!("{} is not a directory", local_directory.display());
+        };
+        let remote_path = format!(
+            r"{}\{}",
+            self.remote_directory,
+            directory_name.to_string_lossy()
+        );
+
+        match self.config.transport {
+            Transport::Smb => copy_via_smb(
+                local_directory,
+                &administrative_share_path(&self.target_machine, &remote_path)?,
+            )?,
+            Transport::Winrm => run_powershell(&copy_via_winrm_script(
+                &self.target_machine,
+                local_directory,
+                &self.remote_directory,
+            ))?,
+        }
+        Ok(remote_path)
+    }
+
+    /// Copies `driver_package` to the test machine and installs it via the
+    /// configured install tool
+    pub fn install(&self, driver_package: &DriverPackage) -> anyhow::Result<()> {
+        println!(
+            "Deploying {} to {}:{}",
+            driver_package.name, self.target_machine, self.remote_directory
+        );
+        let remote_package_directory = self.copy_directory(&driver_package.package_directory)?;
+        run_powershell(&install_script(
+            &self.target_machine,
+            &driver_package.name,
+            &remote_package_directory,
+            self.config.install_tool,
+            self.config.hardware_id.as_deref(),
+        ))
+    }
+
+    /// Reboots the test machine
+    pub fn reboot(&self) -> anyhow::Result<()> {
+        println!("Rebooting {}", self.target_machine);
+        run_powershell(&format!(
+            "Restart-Computer -ComputerName {} -Force",
+            powershell_quote(&self.target_machine)
+        ))
+    }
+}
+
+/// Converts a local path on the test machine (ex. `C:\DriverTest`) to the path
+/// of its administrative share (ex. `\\<host>\C$\DriverTest`)
+fn administrative_share_path(target_machine: &str, remote_path: &str) -> anyhow::Result<PathBuf> {
+    let Some((drive, path)) = remote_path.split_once(':') else {
+        bail!("remote-directory {remote_path} must be an absolute path (ex. C:\\DriverTest)");
+    };
+    Ok(PathBuf::from(format!(r"\\{target_machine}\{drive}${path}")))
+}
+
+/// Recursively copies the driver package directory to `destination` (ie. an
+/// administrative share on the test machine)
+fn copy_via_smb(package_directory: &Path, destination: &Path) -> anyhow::Result<()> {
+    std::fs::create_dir_all(destination)
+        .with_context(|| format!("failed to create {}", destination.display()))?;
+    for entry in std::fs::read_dir(package_directory)
+        .with_context(|| format!("failed to read {}", package_directory.display()))?
+    {
+        let entry = entry?;
+        let destination_path = destination.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_via_smb(&entry.path(), &destination_path)?;
+        } else {
+            std::fs::copy(entry.path(), &destination_path).with_context(|| {
+                format!(
+                    "failed to copy {} to {}",
+                    entry.path().display(),
+                    destination_path.display()
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// PowerShell script that copies the driver package directory into
+/// `remote_directory` via a PowerShell remoting session
+fn copy_via_winrm_script(
+    target_machine: &str,
+    package_directory: &Path,
+    remote_directory: &str,
+) -> String {
+    format!(
+        "$session = New-PSSession -ComputerName {target_machine}; try {{ Invoke-Command -Session \
+         $session -ScriptBlock {{ New-Item -ItemType Directory -Force -Path {remote_directory} | \
+         Out-Null }}; Copy-Item -ToSession $session -Recurse -Force -Path {package_directory} \
+         -Destination {remote_directory} }} finally {{ Remove-PSSession $session }}",
+        target_machine = powershell_quote(target_machine),
+        remote_directory = powershell_quote(remote_directory),
+        package_directory = powershell_quote(&package_directory.to_string_lossy()),
+    )
+}
+
+/// PowerShell script that installs the driver package on the test machine
+fn install_script(
+    target_machine: &str,
+    driver_name: &str,
+    remote_package_directory: &str,
+    install_tool: InstallTool,
+    hardware_id: Option<&str>,
+) -> String {
+    let remote_inf = powershell_quote(&format!(r"{remote_package_directory}\{driver_name}.inf"));
+    let install_command = match install_tool {
+        InstallTool::Pnputil => format!("pnputil.exe /add-driver {remote_inf} /install"),
+        InstallTool::Devcon => format!(
+            "devcon.exe install {remote_inf} {}",
+            powershell_quote(hardware_id.unwrap_or_default())
+        ),
+    };
+    format!(
+        "Invoke-Command -ComputerName {} -ScriptBlock {{ {install_command}; if ($LASTEXITCODE -ne \
+         0) {{ throw \"driver installation failed with exit code $LASTEXITCODE\" }} }}",
+        powershell_quote(target_machine)
+    )
+}
+
+/// Quotes `string` as a PowerShell single-quoted string literal
+pub fn powershell_quote(string: &str) -> String {
+    format!("'{}'", string.replace('\'', "''"))
+}
+
+/// Executes `script` via `powershell.exe`
+pub fn run_powershell(script: &str) -> anyhow::Result<()> {
+    run_command(
+        Command::new("powershell.exe")
+            .args(["-NoProfile", "-NonInteractive", "-Command"])
+            .arg(script),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Debug, Parser)]
+    struct TestCli {
+        #[command(flatten)]
+        deploy_options: DeployOptions,
+    }
+
+    fn parse_deploy_options(args: &[&str]) -> DeployOptions {
+        TestCli::parse_from(std::iter::once("cargo-wdk").chain(args.iter().copied()))
+            .deploy_options
+    }
+
+    #[test]
+    fn deploy_config_is_parsed() {
+        let deploy_config: DeployConfig = toml::from_str(
+            r#"
+            target-machine = "driver-test-vm"
+            transport = "winrm"
+            remote-directory = 'D:\Drivers'
+            install-tool = "devcon"
+            hardware-id = 'Root\SAMPLE_KMDF_HW_ID'
+            reboot = true
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            deploy_config,
+            DeployConfig {
+                target_machine: Some("driver-test-vm".to_string()),
+                transport: Transport::Winrm,
+                remote_directory: Some(r"D:\Drivers".to_string()),
+                install_tool: InstallTool::Devcon,
+                hardware_id: Some(r"Root\SAMPLE_KMDF_HW_ID".to_string()),
+                reboot: true,
+            }
+        );
+        assert_eq!(
+            toml::from_str::<DeployConfig>("").unwrap(),
+            DeployConfig::default()
+        );
+        assert!(toml::from_str::<DeployConfig>("unknown-key = 1").is_err());
+    }
+
+    #[test]
+    fn command_line_overrides_reboot() {
+        for (args, expected_reboot) in [
+            (&[][..], true),
+            (&["--reboot"][..], true),
+            (&["--reboot=true"][..], true),
+            (&["--reboot=false"][..], false),
+        ] {
+            let mut deploy_config = DeployConfig {
+                reboot: true,
+                ..DeployConfig::default()
+            };
+            deploy_config.apply_overrides(&parse_deploy_options(args));
+            assert_eq!(deploy_config.reboot, expected_reboot, "{args:?}");
+        }
+
+        let mut deploy_config = DeployConfig::default();
+        deploy_config.apply_overrides(&parse_deploy_options(&["--reboot"]));
+        assert!(deploy_config.reboot);
+    }
+
+    #[test]
+    fn administrative_share_path_of_remote_directory() {
+        assert_eq!(
+            administrative_share_path("driver-test-vm", r"C:\DriverTest\sample_package").unwrap(),
+            PathBuf::from(r"\\driver-test-vm\C$\DriverTest\sample_package")
+        );
+        assert!(administrative_share_path("driver-test-vm", r"DriverTest").is_err());
+    }
+
+    #[test]
+    fn install_script_uses_install_tool() {
+        let pnputil_script = install_script(
+            "driver-test-vm",
+            "sample_kmdf_driver",
+            r"C:\DriverTest\sample_kmdf_driver_package",
+            InstallTool::Pnputil,
+            None,
+        );
+        assert!(pnputil_script.starts_with("Invoke-Command -ComputerName 'driver-test-vm'"));
+        assert!(pnputil_script.contains(
+            r"pnputil.exe /add-driver 'C:\DriverTest\sample_kmdf_driver_package\sample_kmdf_driver.inf' /install"
+        ));
+
+        let devcon_script = install_script(
+            "driver-test-vm",
+            "sample_kmdf_driver",
+            r"C:\DriverTest\sample_kmdf_driver_package",
+            InstallTool::Devcon,
+            Some(r"Root\SAMPLE_KMDF_HW_ID"),
+        );
+        assert!(devcon_script.contains(
+            r"devcon.exe install 'C:\DriverTest\sample_kmdf_driver_package\sample_kmdf_driver.inf' 'Root\SAMPLE_KMDF_HW_ID'"
+        ));
+    }
+
+    #[test]
+    fn powershell_strings_are_quoted() {
+        assert_eq!(powershell_quote("it's"), "'it''s'");
+    }
+}
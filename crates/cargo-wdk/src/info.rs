@@ -0,0 +1,268 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Implementation of `cargo wdk info`, which prints the WDK configuration that
+//! `wdk-build` resolves for the current workspace: the WDK it detects, the
+//! driver model from the `wdk` metadata, the target architecture, the output
+//! directory and the enabled features of `wdk-sys`.
+//!
+//! Values that cannot be resolved (ex. because no WDK is installed) are printed
+//! with the reason they could not be resolved, instead of failing the command,
+//! so that its output can be attached to bug reports about environment issues.
+//!
+//! ```text
+//! cargo wdk info --target aarch64-pc-windows-msvc
+//! ```
+
+use std::path::PathBuf;
+
+use anyhow::Context;
+use cargo_metadata::{CargoOpt, Metadata, MetadataCommand};
+use wdk_build::{
+    metadata::{DriverModel, WDKMetadata},
+    Config,
+    DriverConfig,
+};
+
+use crate::build::CompilationOptions;
+
+/// Name of the package whose enabled features are reported
+const WDK_SYS_PACKAGE_NAME: &str = "wdk-sys";
+
+/// Arguments of `cargo wdk info`
+#[derive(Debug, clap::Args)]
+pub struct InfoArgs {
+    #[command(flatten)]
+    #[command(next_help_heading = "Feature Selection")]
+    features: clap_cargo::Features,
+
+    #[command(flatten)]
+    compilation_options: CompilationOptions,
+}
+
+/// Prints the WDK configuration resolved for the current workspace
+pub fn run(args: &InfoArgs) -> anyhow::Result<()> {
+    let mut metadata_command = MetadataCommand::new();
+    if args.features.all_features {
+        metadata_command.features(CargoOpt::AllFeatures);
+    }
+    if args.features.no_default_features {
+        metadata_command.features(CargoOpt::NoDefaultFeatures);
+    }
+    if !args.features.features.is_empty() {
+        metadata_command.features(CargoOpt::SomeFeatures(args.features.features.clone()));
+    }
+    let cargo_metadata = metadata_command
+        .exec()
+        .context("failed to execute cargo metadata")?;
+
+    print!("{}", format_report(&report(args, &cargo_metadata)));
+    Ok(())
+}
+
+/// Resolves the entries of the report printed by `cargo wdk info`, as pairs of
+/// labels and values
+fn report(args: &InfoArgs, cargo_metadata: &Metadata) -> Vec<(&'static str, String)> {
+    let mut report = vec![("Workspace root", cargo_metadata.workspace_root.to_string())];
+
+    let wdk_metadata = WDKMetadata::try_from_cargo_metadata(cargo_metadata);
+    let (driver_model, pinned_wdk_version) =
+        wdk_metadata.as_ref().map_or((None, None), |wdk_metadata| {
+            (wdk_metadata.driver_model, wdk_metadata.wdk_version.clone())
+        });
+    report.push((
+        "Driver model",
+        match &wdk_metadata {
+            Ok(_) => describe_driver_model(driver_model),
+            Err(error) => format!("failed to resolve wdk metadata: {error}"),
+        },
+    ));
+
+    let target_architecture = args.compilation_options.target_architecture();
+    report.push((
+        "Target architecture",
+        match &target_architecture {
+            Ok(target_architecture) => target_architecture.as_windows_str().to_string(),
+            Err(error) => format!("{error:#}"),
+        },
+    ));
+    report.push((
+        "Output directory",
+        args.compilation_options
+            .output_directory(cargo_metadata.target_directory.as_std_path())
+            .display()
+            .to_string(),
+    ));
+
+    match wdk_build::detect_wdk_content_root() {
+        Ok(wdk_content_root) => {
+            report.push(("WDK content root", wdk_content_root.display().to_string()));
+            if let Ok(cpu_architecture) = target_architecture {
+                let config = Config {
+                    wdk_content_root,
+                    driver_config: driver_model.map_or_else(DriverConfig::WDM, DriverConfig::from),
+                    cpu_architecture,
+                    ndis_config: None,
+                    netadapter_config: None,
+                    subsystems: Vec::new(),
+                    link_libraries: Vec::new(),
+                    wdk_version: pinned_wdk_version,
+                };
+                report.extend(config_report(&config));
+            }
+        }
+        Err(error) => report.push(("WDK content root", format!("not found: {error}"))),
+    }
+
+    report.push((
+        "wdk-sys features",
+        enabled_features(cargo_metadata, WDK_SYS_PACKAGE_NAME).map_or_else(
+            || format!("{WDK_SYS_PACKAGE_NAME} is not a dependency of the workspace"),
+            |features| {
+                if features.is_empty() {
+                    "none".to_string()
+                } else {
+                    features.join(", ")
+                }
+            },
+        ),
+    ));
+    report
+}
+
+/// Resolves the entries of the report that `wdk-build` computes from `config`:
+/// the WDK version, include paths and library paths
+fn config_report(config: &Config) -> Vec<(&'static str, String)> {
+    let wdk_version = match config.resolve_wdk_version() {
+        Ok(wdk_version) if config.wdk_version.is_some() => format!("{wdk_version} (pinned)"),
+        Ok(wdk_version) => format!("{wdk_version} (latest installed)"),
+        Err(error) => error.to_string(),
+    };
+    vec![
+        ("WDK version", wdk_version),
+        (
+            "Include paths",
+            config
+                .get_include_paths()
+                .map_or_else(|error| error.to_string(), |paths| join_paths(&paths)),
+        ),
+        (
+            "Library paths",
+            config
+                .get_library_paths()
+                .map_or_else(|error| error.to_string(), |paths| join_paths(&paths)),
+        ),
+    ]
+}
+
+/// Describes the driver model resolved from the `wdk` metadata, including its
+/// KMDF or UMDF version
+fn describe_driver_model(driver_model: Option<DriverModel>) -> String {
+    match driver_model {
+        Some(DriverModel::WDM) => "WDM".to_string(),
+        Some(DriverModel::KMDF {
+            kmdf_version_major,
+            kmdf_version_minor,
+        }) => format!("KMDF {kmdf_version_major}.{kmdf_version_minor}"),
+        Some(DriverModel::UMDF {
+            umdf_version_major,
+            umdf_version_minor,
+        }) => format!("UMDF {umdf_version_major}.{umdf_version_minor}"),
+        None => "not specified in wdk metadata (WDM is used)".to_string(),
+    }
+}
+
+/// Returns the sorted features enabled for `package_name` in the resolved
+/// dependency graph, or `None` if it is not part of the graph
+fn enabled_features(cargo_metadata: &Metadata, package_name: &str) -> Option<Vec<String>> {
+    let package_id = &cargo_metadata
+        .packages
+        .iter()
+        .find(|package| package.name == package_name)?
+        .id;
+    let mut features = cargo_metadata
+        .resolve
+        .as_ref()?
+        .nodes
+        .iter()
+        .find(|node| &node.id == package_id)?
+        .features
+        .clone();
+    features.sort();
+    Some(features)
+}
+
+/// Joins `paths` with line breaks, so that [`format_report`] prints each of
+/// them on its own line
+fn join_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats the entries of the report as `label: value` lines, with the values
+/// aligned. Values spanning multiple lines are indented to the same column.
+fn format_report(report: &[(&str, String)]) -> String {
+    let label_width = report
+        .iter()
+        .map(|(label, _)| label.len() + 1)
+        .max()
+        .unwrap_or_default();
+
+    report
+        .iter()
+        .flat_map(|(label, value)| {
+            value.lines().enumerate().map(move |(line_index, line)| {
+                let label = if line_index == 0 {
+                    format!("{label}:")
+                } else {
+                    String::new()
+                };
+                format!("{label:label_width$} {line}\n")
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn driver_model_includes_framework_version() {
+        assert_eq!(
+            describe_driver_model(Some(DriverModel::KMDF {
+                kmdf_version_major: 1,
+                kmdf_version_minor: 33,
+            })),
+            "KMDF 1.33"
+        );
+        assert_eq!(
+            describe_driver_model(Some(DriverModel::UMDF {
+                umdf_version_major: 2,
+                umdf_version_minor: 31,
+            })),
+            "UMDF 2.31"
+        );
+        assert_eq!(describe_driver_model(Some(DriverModel::WDM)), "WDM");
+        assert_eq!(
+            describe_driver_model(None),
+            "not specified in wdk metadata (WDM is used)"
+        );
+    }
+
+    #[test]
+    fn report_values_are_aligned() {
+        let report = [
+            ("Driver model", "KMDF 1.33".to_string()),
+            ("Include paths", "C:\\a\nC:\\b".to_string()),
+        ];
+
+        assert_eq!(
+            format_report(&report),
+            "Driver model:  KMDF 1.33\nInclude paths: C:\\a\n               C:\\b\n"
+        );
+    }
+}
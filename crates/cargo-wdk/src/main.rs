@@ -0,0 +1,108 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! `cargo-wdk` is a Cargo subcommand to build and package drivers built with
+//! [`windows-drivers-rs`](https://github.com/microsoft/windows-drivers-rs),
+//! without requiring [`cargo-make`](https://github.com/sagiegurari/cargo-make).
+//!
+//! `cargo wdk build` compiles every driver in the workspace (ie. every package
+//! with a `wdk` metadata section in its manifest) and runs the same packaging
+//! steps as `rust-driver-makefile.toml`: the driver binary is renamed to
+//! `.sys`, the INF is stamped and verified, a catalog file is generated, and
+//! both the driver binary and the catalog file are signed with a test
+//! certificate. The resulting driver package is placed in
+//! `<output directory>/<driver name>_package`.
+//!
+//! `cargo wdk package --hlk` additionally lays out a Windows Hardware Lab Kit
+//! submission for each driver package, with a checklist of the steps that
+//! remain before it can be submitted. See the `package` module for its layout.
+//!
+//! `cargo wdk deploy` additionally copies the driver packages to a test machine
+//! and installs them. See the `deploy` module for how to configure the test
+//! machine.
+//!
+//! `cargo wdk test` additionally runs the test binaries of the drivers on the
+//! test machine (ex. a Hyper-V VM), and copies back any crash dumps written
+//! while they ran.
+//!
+//! `cargo wdk info` prints the WDK configuration resolved for the workspace
+//! (ex. the detected WDK and the driver model), which is useful to include in
+//! bug reports about environment issues.
+//!
+//! ```text
+//! cargo install --path crates/cargo-wdk
+//! cargo wdk build --release
+//! cargo wdk package --release --hlk
+//! cargo wdk deploy --target-machine driver-test-vm
+//! cargo wdk test --vm driver-test-vm
+//! cargo wdk info
+//! ```
+
+mod build;
+mod deploy;
+mod info;
+mod package;
+mod test;
+
+use std::process::Command;
+
+use anyhow::{bail, Context};
+use clap::{Parser, Subcommand};
+
+/// Cargo invokes subcommands with the name of the subcommand as the first
+/// argument (ie. `cargo wdk build` executes `cargo-wdk wdk build`)
+#[derive(Debug, Parser)]
+#[command(name = "cargo", bin_name = "cargo")]
+enum CargoCli {
+    Wdk(WdkArgs),
+}
+
+/// Build and package drivers built with windows-drivers-rs
+#[derive(Debug, clap::Args)]
+#[command(version, about)]
+struct WdkArgs {
+    #[command(subcommand)]
+    command: WdkCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum WdkCommand {
+    /// Build all drivers in the workspace and lay out signed driver packages
+    Build(build::BuildArgs),
+    /// Build all drivers in the workspace and install them on a test machine
+    Deploy(deploy::DeployArgs),
+    /// Print the WDK configuration resolved for the workspace, for diagnosing
+    /// environment issues
+    Info(info::InfoArgs),
+    /// Build all drivers in the workspace and prepare their driver packages
+    /// for distribution (ex. Windows Hardware Lab Kit submission)
+    Package(package::PackageArgs),
+    /// Install all drivers in the workspace on a test machine and run their
+    /// tests on it
+    Test(test::TestArgs),
+}
+
+fn main() -> anyhow::Result<()> {
+    let CargoCli::Wdk(wdk_args) = CargoCli::parse();
+
+    match wdk_args.command {
+        WdkCommand::Build(build_args) => build::run(&build_args).map(|_| ()),
+        WdkCommand::Deploy(deploy_args) => deploy::run(&deploy_args),
+        WdkCommand::Info(info_args) => info::run(&info_args),
+        WdkCommand::Package(package_args) => package::run(&package_args),
+        WdkCommand::Test(test_args) => test::run(&test_args),
+    }
+}
+
+/// Executes `command` and returns an error if it fails to launch or exits with
+/// a non-zero exit code
+fn run_command(command: &mut Command) -> anyhow::Result<()> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let status = command
+        .status()
+        .with_context(|| format!("failed to execute {program}"))?;
+    if !status.success() {
+        bail!("{program} failed with {status}");
+    }
+    Ok(())
+}
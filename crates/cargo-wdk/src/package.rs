@@ -0,0 +1,422 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Implementation of `cargo wdk package`, which builds drivers and prepares
+//! their driver packages for distribution.
+//!
+//! With `--hlk`, a Windows Hardware Lab Kit (HLK) submission is laid out for
+//! each driver package in `<output directory>/<driver name>_hlk`:
+//!
+//! ```text
+//! <driver name>_hlk
+//! ├── driver            Driver folder to add to the HLK package in HLK Studio
+//! │   ├── <driver name>.sys
+//! │   ├── <driver name>.inf
+//! │   └── <driver name>.cat
+//! ├── symbols           Symbols to add to the HLK package with the driver folder
+//! │   ├── <driver name>.pdb
+//! │   └── <driver name>.map
+//! ├── submission.toml   Metadata of the submission
+//! └── checklist.md      Steps that remain before the submission can be uploaded
+//! ```
+//!
+//! Submissions to the Hardware Dev Center must be signed with an EV
+//! certificate. If `--ev-cert-name` is not provided, the files in the `driver`
+//! folder keep the test signature of the driver package as a placeholder, and
+//! the checklist marks EV signing as pending.
+
+use std::{fmt, path::Path, process::Command};
+
+use anyhow::{bail, Context};
+use serde::Serialize;
+use wdk_build::{symbols::verify_pdb_matches_binary, CPUArchitecture};
+
+use crate::{
+    build::{self, copy_file, BuildArgs, DriverPackage, TIMESTAMP_SERVER_URL},
+    run_command,
+};
+
+/// Name of the folder of an HLK submission that holds the driver files
+const DRIVER_FOLDER_NAME: &str = "driver";
+
+/// Name of the folder of an HLK submission that holds the symbols
+const SYMBOLS_FOLDER_NAME: &str = "symbols";
+
+/// Name of the metadata file of an HLK submission
+const SUBMISSION_METADATA_FILE_NAME: &str = "submission.toml";
+
+/// Name of the checklist file of an HLK submission
+const CHECKLIST_FILE_NAME: &str = "checklist.md";
+
+/// Arguments of `cargo wdk package`
+#[derive(Debug, clap::Args)]
+pub struct PackageArgs {
+    #[command(flatten)]
+    build_args: BuildArgs,
+
+    #[command(flatten)]
+    hlk_options: HlkOptions,
+}
+
+#[derive(Debug, clap::Args)]
+#[command(next_help_heading = "HLK Options")]
+struct HlkOptions {
+    #[arg(
+        long,
+        help = "Lay out a Windows Hardware Lab Kit submission for each driver package"
+    )]
+    hlk: bool,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        requires = "hlk",
+        help = "Name of the EV certificate, in the personal certificate store, used to sign the \
+                HLK submission. If not provided, the submission keeps the test signature as a \
+                placeholder."
+    )]
+    ev_cert_name: Option<String>,
+}
+
+/// How the driver files of an HLK submission are signed
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum Signing {
+    /// Signed with the test certificate of the driver package, as a
+    /// placeholder for an EV signature
+    TestCertificatePlaceholder,
+    /// Signed with an EV certificate
+    EvCertificate,
+}
+
+/// Metadata of an HLK submission, written to `submission.toml`
+#[derive(Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+struct SubmissionMetadata {
+    driver: String,
+    version: String,
+    architecture: String,
+    inf2cat_os: String,
+    signing: Signing,
+    pdb_signature: String,
+    symbol_server_key: String,
+    /// Paths of the files in the submission, relative to its root
+    files: Vec<String>,
+}
+
+/// Steps of an HLK submission, written to `checklist.md`
+#[derive(Debug, PartialEq, Eq)]
+struct Checklist {
+    driver: String,
+    items: Vec<ChecklistItem>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct ChecklistItem {
+    done: bool,
+    description: String,
+}
+
+impl Checklist {
+    /// Builds the checklist of the HLK submission described by `metadata`.
+    /// The steps that `cargo wdk package --hlk` performs are checked, and the
+    /// steps that must be performed in HLK Studio and the Hardware Dev Center
+    /// are not.
+    fn new(metadata: &SubmissionMetadata, binary_file_name: &str) -> Self {
+        let driver = &metadata.driver;
+        let ev_signed = metadata.signing == Signing::EvCertificate;
+        let items = [
+            (
+                true,
+                format!(
+                    "`{DRIVER_FOLDER_NAME}` contains `{binary_file_name}`, `{driver}.inf` and \
+                     `{driver}.cat`, and the INF passes `infverif`"
+                ),
+            ),
+            (
+                true,
+                format!(
+                    "`{SYMBOLS_FOLDER_NAME}/{driver}.pdb` matches `{binary_file_name}` ({})",
+                    metadata.pdb_signature
+                ),
+            ),
+            (
+                ev_signed,
+                if ev_signed {
+                    format!(
+                        "`{binary_file_name}` and `{driver}.cat` are signed with an EV certificate"
+                    )
+                } else {
+                    format!(
+                        "Sign `{binary_file_name}` and `{driver}.cat` with an EV certificate \
+                         (they are test-signed as a placeholder)"
+                    )
+                },
+            ),
+            (
+                false,
+                format!(
+                    "Run the HLK tests of the device in HLK Studio, and add the \
+                     `{DRIVER_FOLDER_NAME}` folder to the HLK package with \
+                     `{SYMBOLS_FOLDER_NAME}` as its symbols ({} on {})",
+                    metadata.architecture, metadata.inf2cat_os
+                ),
+            ),
+            (
+                false,
+                "Sign the HLK package (`.hlkx`) with the EV certificate, and submit it via the \
+                 Hardware Dev Center"
+                    .to_string(),
+            ),
+        ];
+
+        Self {
+            driver: driver.clone(),
+            items: items
+                .into_iter()
+                .map(|(done, description)| ChecklistItem { done, description })
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for Checklist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# HLK submission checklist for {}", self.driver)?;
+        writeln!(f)?;
+        for item in &self.items {
+            let check_mark = if item.done { 'x' } else { ' ' };
+            writeln!(f, "- [{check_mark}] {}", item.description)?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds all selected drivers, and lays out an HLK submission for each of
+/// their driver packages if `--hlk` is passed
+pub fn run(args: &PackageArgs) -> anyhow::Result<()> {
+    let target_architecture = args.build_args.target_architecture()?;
+    let driver_packages = build::run(&args.build_args)?;
+    if !args.hlk_options.hlk {
+        return Ok(());
+    }
+
+    for driver_package in &driver_packages {
+        println!("Preparing HLK submission: {}", driver_package.name);
+        let checklist = prepare_hlk_submission(
+            driver_package,
+            target_architecture,
+            args.hlk_options.ev_cert_name.as_deref(),
+        )?;
+        print!("{checklist}");
+    }
+    Ok(())
+}
+
+/// Lays out the HLK submission of `driver_package`, next to it, and returns its
+/// checklist
+fn prepare_hlk_submission(
+    driver_package: &DriverPackage,
+    target_architecture: CPUArchitecture,
+    ev_cert_name: Option<&str>,
+) -> anyhow::Result<Checklist> {
+    let binary_extension = driver_package.binary_extension;
+    let missing_files = [binary_extension, "inf", "cat", "pdb"]
+        .into_iter()
+        .map(|extension| driver_package.package_file(extension))
+        .filter(|file| !file.exists())
+        .map(|file| file.display().to_string())
+        .collect::<Vec<_>>();
+    if !missing_files.is_empty() {
+        bail!(
+            "driver package of {} is missing files required for an HLK submission: {}",
+            driver_package.name,
+            missing_files.join(", ")
+        );
+    }
+
+    // Start from an empty directory, so that files of a previous submission are
+    // never mistaken for files of this one
+    let hlk_directory = driver_package
+        .package_directory
+        .with_file_name(format!("{}_hlk", driver_package.name));
+    if hlk_directory.exists() {
+        std::fs::remove_dir_all(&hlk_directory)
+            .with_context(|| format!("failed to remove {}", hlk_directory.display()))?;
+    }
+    let driver_directory = hlk_directory.join(DRIVER_FOLDER_NAME);
+    let symbols_directory = hlk_directory.join(SYMBOLS_FOLDER_NAME);
+    for directory in [&driver_directory, &symbols_directory] {
+        std::fs::create_dir_all(directory)
+            .with_context(|| format!("failed to create {}", directory.display()))?;
+    }
+
+    let mut files = Vec::new();
+    let mut copy_to_submission = |extension: &str, folder_name: &str| -> anyhow::Result<()> {
+        let source = driver_package.package_file(extension);
+        let file_name = source
+            .file_name()
+            .expect("driver package files should always have a file name")
+            .to_string_lossy()
+            .into_owned();
+        copy_file(&source, &hlk_directory.join(folder_name).join(&file_name))?;
+        files.push(format!("{folder_name}/{file_name}"));
+        Ok(())
+    };
+    for extension in [binary_extension, "inf", "cat"] {
+        copy_to_submission(extension, DRIVER_FOLDER_NAME)?;
+    }
+    copy_to_submission("pdb", SYMBOLS_FOLDER_NAME)?;
+    if driver_package.package_file("map").exists() {
+        copy_to_submission("map", SYMBOLS_FOLDER_NAME)?;
+    }
+
+    let binary_file_name = format!("{}.{binary_extension}", driver_package.name);
+    let pdb_signature = verify_pdb_matches_binary(
+        &driver_directory.join(&binary_file_name),
+        &symbols_directory.join(format!("{}.pdb", driver_package.name)),
+    )?;
+
+    let signing = if let Some(ev_cert_name) = ev_cert_name {
+        for file_name in [
+            binary_file_name.clone(),
+            format!("{}.cat", driver_package.name),
+        ] {
+            sign_with_ev_certificate(&driver_directory.join(file_name), ev_cert_name)?;
+        }
+        Signing::EvCertificate
+    } else {
+        Signing::TestCertificatePlaceholder
+    };
+
+    let metadata = SubmissionMetadata {
+        driver: driver_package.name.clone(),
+        version: driver_package.version.clone(),
+        architecture: target_architecture.as_windows_str().to_string(),
+        inf2cat_os: target_architecture.as_inf2cat_os_str().to_string(),
+        signing,
+        pdb_signature: pdb_signature.to_string(),
+        symbol_server_key: pdb_signature.symbol_server_key(),
+        files,
+    };
+    let checklist = Checklist::new(&metadata, &binary_file_name);
+    write_file(
+        &hlk_directory.join(SUBMISSION_METADATA_FILE_NAME),
+        &toml::to_string_pretty(&metadata).context("failed to serialize submission metadata")?,
+    )?;
+    write_file(
+        &hlk_directory.join(CHECKLIST_FILE_NAME),
+        &checklist.to_string(),
+    )?;
+    println!("HLK submission created at {}", hlk_directory.display());
+    Ok(checklist)
+}
+
+/// Replaces the test signature of `file` with a signature from the EV
+/// certificate named `ev_cert_name` in the personal certificate store
+fn sign_with_ev_certificate(file: &Path, ev_cert_name: &str) -> anyhow::Result<()> {
+    run_command(
+        Command::new("signtool")
+            .args(["sign", "/v", "/n", ev_cert_name])
+            .args(["/t", TIMESTAMP_SERVER_URL, "/fd", "SHA256"])
+            .arg(file),
+    )
+}
+
+fn write_file(path: &Path, contents: &str) -> anyhow::Result<()> {
+    std::fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Debug, Parser)]
+    struct TestCli {
+        #[command(flatten)]
+        package_args: PackageArgs,
+    }
+
+    fn submission_metadata(signing: Signing) -> SubmissionMetadata {
+        SubmissionMetadata {
+            driver: "sample_kmdf_driver".to_string(),
+            version: "0.1.0".to_string(),
+            architecture: "x64".to_string(),
+            inf2cat_os: "10_x64".to_string(),
+            signing,
+            pdb_signature: "{0F6A1C1B-3E2D-4F9A-8B7C-6D5E4F3A2B1C} (age 1)".to_string(),
+            symbol_server_key: "0F6A1C1B3E2D4F9A8B7C6D5E4F3A2B1C1".to_string(),
+            files: vec![
+                "driver/sample_kmdf_driver.sys".to_string(),
+                "driver/sample_kmdf_driver.inf".to_string(),
+                "driver/sample_kmdf_driver.cat".to_string(),
+                "symbols/sample_kmdf_driver.pdb".to_string(),
+            ],
+        }
+    }
+
+    #[test]
+    fn ev_cert_name_requires_hlk() {
+        let package_args =
+            TestCli::parse_from(["cargo-wdk", "--hlk", "--ev-cert-name", "Contoso EV"])
+                .package_args;
+        assert!(package_args.hlk_options.hlk);
+        assert_eq!(
+            package_args.hlk_options.ev_cert_name.as_deref(),
+            Some("Contoso EV")
+        );
+
+        assert!(TestCli::try_parse_from(["cargo-wdk", "--ev-cert-name", "Contoso EV"]).is_err());
+    }
+
+    #[test]
+    fn submission_metadata_is_serialized() {
+        let metadata =
+            toml::to_string_pretty(&submission_metadata(Signing::TestCertificatePlaceholder))
+                .unwrap();
+
+        assert!(metadata.contains(r#"inf2cat-os = "10_x64""#));
+        assert!(metadata.contains(r#"signing = "test-certificate-placeholder""#));
+        assert!(metadata.contains(r#"symbol-server-key = "0F6A1C1B3E2D4F9A8B7C6D5E4F3A2B1C1""#));
+        assert!(metadata.contains(r#""symbols/sample_kmdf_driver.pdb","#));
+    }
+
+    #[test]
+    fn checklist_marks_placeholder_signature_as_pending() {
+        let checklist = Checklist::new(
+            &submission_metadata(Signing::TestCertificatePlaceholder),
+            "sample_kmdf_driver.sys",
+        );
+
+        assert_eq!(
+            checklist
+                .items
+                .iter()
+                .map(|item| item.done)
+                .collect::<Vec<_>>(),
+            [true, true, false, false, false]
+        );
+        let checklist = checklist.to_string();
+        assert!(checklist.starts_with("# HLK submission checklist for sample_kmdf_driver\n\n"));
+        assert!(checklist.contains(
+            "- [ ] Sign `sample_kmdf_driver.sys` and `sample_kmdf_driver.cat` with an EV \
+             certificate (they are test-signed as a placeholder)\n"
+        ));
+    }
+
+    #[test]
+    fn checklist_marks_ev_signature_as_done() {
+        let checklist = Checklist::new(
+            &submission_metadata(Signing::EvCertificate),
+            "sample_kmdf_driver.sys",
+        );
+
+        assert!(checklist.items[2].done);
+        assert!(checklist.to_string().contains(
+            "- [x] `sample_kmdf_driver.sys` and `sample_kmdf_driver.cat` are signed with an EV \
+             certificate\n"
+        ));
+    }
+}
@@ -0,0 +1,307 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Implementation of `cargo wdk test`, which runs the tests of drivers on a
+//! test machine (ex. a Hyper-V VM).
+//!
+//! The drivers are built and installed on the test machine the same way as
+//! `cargo wdk deploy`, so the test machine is configured via
+//! `.wdk-deploy.toml`. The test binaries (ie. the output of `cargo test
+//! --no-run`) are then copied to the test machine and executed via PowerShell
+//! remoting. Their libtest output is forwarded as is, so it can be consumed the
+//! same way as the output of `cargo test`.
+//!
+//! If a test binary fails, the test machine may have bugchecked while the
+//! driver was under test. In that case, `cargo wdk test` waits for the test
+//! machine to reboot, and copies back any crash dumps written during the test
+//! run (ie. `C:\Windows\MEMORY.DMP` and `C:\Windows\Minidump\*.dmp`).
+//!
+//! ```text
+//! cargo wdk test --vm driver-test-vm -- --test-threads 1
+//! ```
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{bail, Context};
+use cargo_metadata::MetadataCommand;
+
+use crate::{
+    build,
+    deploy::{powershell_quote, run_powershell, DeployArgs, Deployment},
+};
+
+/// Name of the directory, in the target directory, that test binaries are
+/// staged in before they are copied to the test machine
+const TEST_STAGING_DIRECTORY_NAME: &str = "wdk-test";
+
+/// Name of the directory, in the target directory, that crash dumps are copied
+/// back to by default
+const DEFAULT_CRASH_DUMP_DIRECTORY_NAME: &str = "wdk-test-crash-dumps";
+
+/// How long to wait for the test machine to come back online after a test
+/// binary fails
+const REBOOT_TIMEOUT: Duration = Duration::from_mins(10);
+
+/// Arguments of `cargo wdk test`
+#[derive(Debug, clap::Args)]
+pub struct TestArgs {
+    #[command(flatten)]
+    deploy_args: DeployArgs,
+
+    #[command(flatten)]
+    test_options: TestOptions,
+}
+
+#[derive(Debug, clap::Args)]
+#[command(next_help_heading = "Test Options")]
+struct TestOptions {
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Directory that crash dumps are copied back to [default: \
+                <target-dir>/wdk-test-crash-dumps]"
+    )]
+    crash_dump_directory: Option<PathBuf>,
+
+    #[arg(long, help = "Do not copy crash dumps back from the test machine")]
+    no_crash_dumps: bool,
+
+    #[arg(
+        last = true,
+        value_name = "ARGS",
+        help = "Arguments passed to the test binaries (ex. --test-threads 1)"
+    )]
+    test_binary_args: Vec<String>,
+}
+
+/// Builds and installs all selected drivers on the test machine, then runs
+/// their test binaries on it
+pub fn run(args: &TestArgs) -> anyhow::Result<()> {
+    let deployment = Deployment::new(&args.deploy_args.deploy_options)?;
+    let target_directory = MetadataCommand::new()
+        .no_deps()
+        .exec()
+        .context("failed to execute cargo metadata")?
+        .target_directory
+        .into_std_path_buf();
+
+    for driver_package in build::run(&args.deploy_args.build_args)? {
+        deployment.install(&driver_package)?;
+    }
+
+    let test_executables = build::build_tests(&args.deploy_args.build_args)?;
+    if test_executables.is_empty() {
+        println!("No test binaries were built. Skipping test execution.");
+        return Ok(());
+    }
+    let staging_directory = target_directory.join(TEST_STAGING_DIRECTORY_NAME);
+    stage_test_executables(&test_executables, &staging_directory)?;
+    let remote_test_directory = deployment.copy_directory(&staging_directory)?;
+
+    let test_run_start = SystemTime::now();
+    let mut failed_test_binaries = Vec::new();
+    for test_executable in &test_executables {
+        let file_name = test_executable
+            .file_name()
+            .expect("test executables reported by cargo should have a file name")
+            .to_string_lossy();
+        println!("     Running {file_name} on {}", deployment.target_machine);
+        if let Err(error) = run_powershell(&run_test_script(
+            &deployment.target_machine,
+            &format!(r"{remote_test_directory}\{file_name}"),
+            &args.test_options.test_binary_args,
+        )) {
+            eprintln!("{file_name} failed: {error:#}");
+            failed_test_binaries.push(file_name.into_owned());
+        }
+    }
+
+    if failed_test_binaries.is_empty() {
+        return Ok(());
+    }
+    if !args.test_options.no_crash_dumps {
+        let crash_dump_directory = args
+            .test_options
+            .crash_dump_directory
+            .clone()
+            .unwrap_or_else(|| target_directory.join(DEFAULT_CRASH_DUMP_DIRECTORY_NAME));
+        collect_crash_dumps(&deployment, test_run_start, &crash_dump_directory)?;
+    }
+    bail!(
+        "{} test binaries failed on {}: {}",
+        failed_test_binaries.len(),
+        deployment.target_machine,
+        failed_test_binaries.join(", ")
+    );
+}
+
+/// Copies `test_executables` into a clean `staging_directory`, so that they can
+/// be copied to the test machine at once
+fn stage_test_executables(
+    test_executables: &[PathBuf],
+    staging_directory: &Path,
+) -> anyhow::Result<()> {
+    if staging_directory.exists() {
+        std::fs::remove_dir_all(staging_directory)
+            .with_context(|| format!("failed to remove {}", staging_directory.display()))?;
+    }
+    std::fs::create_dir_all(staging_directory)
+        .with_context(|| format!("failed to create {}", staging_directory.display()))?;
+
+    for test_executable in test_executables {
+        let Some(file_name) = test_executable.file_name() else {
+            bail!("{} is not a file", test_executable.display());
+        };
+        let destination = staging_directory.join(file_name);
+        std::fs::copy(test_executable, &destination).with_context(|| {
+            format!(
+                "failed to copy {} to {}",
+                test_executable.display(),
+                destination.display()
+            )
+        })?;
+    }
+    Ok(())
+}
+
+/// Waits for the test machine to be reachable via PowerShell remoting, and
+/// copies the crash dumps written after `test_run_start` to
+/// `crash_dump_directory`
+fn collect_crash_dumps(
+    deployment: &Deployment,
+    test_run_start: SystemTime,
+    crash_dump_directory: &Path,
+) -> anyhow::Result<()> {
+    println!(
+        "Collecting crash dumps from {} into {}",
+        deployment.target_machine,
+        crash_dump_directory.display()
+    );
+    std::fs::create_dir_all(crash_dump_directory)
+        .with_context(|| format!("failed to create {}", crash_dump_directory.display()))?;
+    run_powershell(&wait_for_test_machine_script(
+        &deployment.target_machine,
+        REBOOT_TIMEOUT,
+    ))?;
+    run_powershell(&collect_crash_dumps_script(
+        &deployment.target_machine,
+        test_run_start,
+        crash_dump_directory,
+    ))
+}
+
+/// PowerShell script that executes `remote_test_executable` on the test
+/// machine, and fails if it exits with a non-zero exit code
+fn run_test_script(
+    target_machine: &str,
+    remote_test_executable: &str,
+    test_binary_args: &[String],
+) -> String {
+    let mut test_command = format!("& {}", powershell_quote(remote_test_executable));
+    for arg in test_binary_args {
+        test_command.push(' ');
+        test_command.push_str(&powershell_quote(arg));
+    }
+    format!(
+        "Invoke-Command -ComputerName {} -ScriptBlock {{ {test_command}; if ($LASTEXITCODE -ne 0) \
+         {{ throw \"test binary failed with exit code $LASTEXITCODE\" }} }}",
+        powershell_quote(target_machine),
+    )
+}
+
+/// PowerShell script that waits up to `timeout` for the test machine to accept
+/// PowerShell remoting connections (ex. after rebooting from a bugcheck)
+fn wait_for_test_machine_script(target_machine: &str, timeout: Duration) -> String {
+    format!(
+        "$deadline = (Get-Date).AddSeconds({}); while (-not (Test-WSMan -ComputerName {} \
+         -ErrorAction SilentlyContinue)) {{ if ((Get-Date) -gt $deadline) {{ throw 'timed out \
+         waiting for the test machine' }}; Start-Sleep -Seconds 5 }}",
+        timeout.as_secs(),
+        powershell_quote(target_machine),
+    )
+}
+
+/// PowerShell script that copies the crash dumps on the test machine that were
+/// written after `test_run_start` into `crash_dump_directory`
+fn collect_crash_dumps_script(
+    target_machine: &str,
+    test_run_start: SystemTime,
+    crash_dump_directory: &Path,
+) -> String {
+    let test_run_start = test_run_start
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    format!(
+        "$session = New-PSSession -ComputerName {target_machine}; try {{ $start = \
+         [DateTimeOffset]::FromUnixTimeSeconds({test_run_start}).UtcDateTime; $dumps = \
+         Invoke-Command -Session $session -ScriptBlock {{ param($start) Get-Item -Path \
+         \"$env:SystemRoot\\MEMORY.DMP\", \"$env:SystemRoot\\Minidump\\*.dmp\" -ErrorAction \
+         SilentlyContinue | Where-Object {{ $_.LastWriteTimeUtc -gt $start }} | ForEach-Object {{ \
+         $_.FullName }} }} -ArgumentList $start; if (-not $dumps) {{ Write-Host 'No crash dumps \
+         were written during the test run' }}; foreach ($dump in $dumps) {{ Write-Host \"Copying \
+         $dump\"; Copy-Item -FromSession $session -Path $dump -Destination {crash_dump_directory} \
+         }} }} finally {{ Remove-PSSession $session }}",
+        target_machine = powershell_quote(target_machine),
+        crash_dump_directory = powershell_quote(&crash_dump_directory.to_string_lossy()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Debug, Parser)]
+    struct TestCli {
+        #[command(flatten)]
+        test_args: TestArgs,
+    }
+
+    #[test]
+    fn vm_and_test_binary_args_are_parsed() {
+        let test_args = TestCli::parse_from([
+            "cargo-wdk",
+            "--vm",
+            "driver-test-vm",
+            "--release",
+            "--",
+            "--test-threads",
+            "1",
+        ])
+        .test_args;
+
+        assert_eq!(
+            test_args.test_options.test_binary_args,
+            ["--test-threads", "1"]
+        );
+    }
+
+    #[test]
+    fn run_test_script_forwards_test_binary_args() {
+        let script = run_test_script(
+            "driver-test-vm",
+            r"C:\DriverTest\wdk-test\sample_kmdf_driver-0123456789abcdef.exe",
+            &["--test-threads".to_string(), "1".to_string()],
+        );
+        assert!(script.starts_with("Invoke-Command -ComputerName 'driver-test-vm'"));
+        assert!(script.contains(
+            r"& 'C:\DriverTest\wdk-test\sample_kmdf_driver-0123456789abcdef.exe' '--test-threads' '1';"
+        ));
+    }
+
+    #[test]
+    fn collect_crash_dumps_script_filters_by_test_run_start() {
+        let script = collect_crash_dumps_script(
+            "driver-test-vm",
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            Path::new(r"C:\target\wdk-test-crash-dumps"),
+        );
+        assert!(script.contains("[DateTimeOffset]::FromUnixTimeSeconds(1700000000)"));
+        assert!(script.contains(r"-Destination 'C:\target\wdk-test-crash-dumps'"));
+    }
+}
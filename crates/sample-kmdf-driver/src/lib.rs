@@ -139,7 +139,7 @@
     );
 
     // It is much better to use the println macro that has an implementation in
-    // wdk::print.rs to call DbgPrint. The println! implementation in
+    // wdk::print.rs to call DbgPrintEx. The println! implementation in
     // wdk::print.rs has the same features as the one in std (ex. format args
     // support).
     println!("KMDF Driver Entry Complete! Driver Registry Parameter Key: {registry_path}");
@@ -16,8 +16,15 @@
 
 #![no_std]
 
+mod lookaside;
+mod statistics;
+
 use core::alloc::{GlobalAlloc, Layout};
 
+pub use lookaside::LookasideAllocator;
+pub use statistics::{set_alloc_failure_handler, AllocFailureHandler};
+#[cfg(feature = "statistics")]
+pub use statistics::{dump_statistics, statistics, AllocatorStatistics};
 use wdk_sys::{
     ntddk::{ExAllocatePool2, ExFreePool},
     POOL_FLAG_NON_PAGED,
@@ -28,11 +35,21 @@
 /// Allocator implementation to use with `#[global_allocator]` to allow use of
 /// [`core::alloc`].
 ///
+/// Failed allocations can be observed via [`set_alloc_failure_handler`].
+/// When the `statistics` feature is enabled, allocations are also counted
+/// under [`WDKAllocator::POOL_TAG`], and can be inspected via `statistics` or
+/// `dump_statistics`.
+///
 /// # Safety
 /// This allocator is only safe to use for allocations happening at `IRQL` <=
 /// `DISPATCH_LEVEL`
 pub struct WDKAllocator;
 
+impl WDKAllocator {
+    /// Pool tag of the allocations made by [`WDKAllocator`]
+    pub const POOL_TAG: ULONG = RUST_TAG;
+}
+
 // The value of memory tags are stored in little-endian order, so it is
 // convenient to reverse the order for readability in tooling (ie. Windbg)
 const RUST_TAG: ULONG = u32::from_ne_bytes(*b"rust");
@@ -50,16 +67,23 @@ unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
                 ExAllocatePool2(POOL_FLAG_NON_PAGED, layout.size() as SIZE_T, RUST_TAG)
             };
         if ptr.is_null() {
+            #[cfg(feature = "statistics")]
+            statistics::record_failed_allocation(RUST_TAG);
+            statistics::notify_alloc_failure(layout);
             return core::ptr::null_mut();
         }
+        #[cfg(feature = "statistics")]
+        statistics::record_allocation(RUST_TAG, layout.size());
         ptr.cast()
     }
 
-    unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
         // SAFETY: `ExFreePool` is safe to call from any `IRQL` <= `DISPATCH_LEVEL`
         // since its freeing memory allocated from `POOL_FLAG_NON_PAGED` in `alloc`
         unsafe {
             ExFreePool(ptr.cast());
         }
+        #[cfg(feature = "statistics")]
+        statistics::record_deallocation(RUST_TAG, layout.size());
     }
 }
@@ -0,0 +1,370 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Allocator for fixed-size objects backed by a lookaside list.
+//!
+//! A lookaside list caches freed blocks of a single size, so that allocations
+//! in hot paths (ex. per-request contexts in an I/O path) are usually served
+//! from the cache instead of the general pool allocator.
+//!
+//! ```rust, no_run
+//! use wdk_alloc::LookasideAllocator;
+//!
+//! struct RequestContext {
+//!     bytes_transferred: usize,
+//! }
+//!
+//! let allocator =
+//!     LookasideAllocator::<RequestContext>::try_new(u32::from_ne_bytes(*b"rctx"))?;
+//! let context = allocator
+//!     .allocate(RequestContext {
+//!         bytes_transferred: 0,
+//!     })
+//!     .map_err(|_| wdk_sys::STATUS_INSUFFICIENT_RESOURCES)?;
+//!
+//! // SAFETY: `context` was allocated by `allocator` and is not used after it is freed
+//! unsafe { allocator.free(context) };
+//! # Ok::<(), wdk_sys::NTSTATUS>(())
+//! ```
+
+use core::{
+    alloc::Layout,
+    marker::PhantomData,
+    mem::{align_of, size_of},
+    ptr::NonNull,
+    sync::atomic::{AtomicU16, AtomicU32, Ordering},
+};
+
+use wdk_sys::{
+    ntddk::{
+        ExAllocatePool2,
+        ExDeleteLookasideListEx,
+        ExFreePool,
+        ExInitializeLookasideListEx,
+        ExQueryDepthSList,
+        ExpInterlockedPopEntrySList,
+        ExpInterlockedPushEntrySList,
+    },
+    _POOL_TYPE::NonPagedPoolNx,
+    GENERAL_LOOKASIDE_POOL,
+    LOOKASIDE_LIST_EX,
+    MEMORY_ALLOCATION_ALIGNMENT,
+    NTSTATUS,
+    POOL_FLAG_NON_PAGED,
+    PVOID,
+    SIZE_T,
+    SLIST_ENTRY,
+    STATUS_INSUFFICIENT_RESOURCES,
+    ULONG,
+};
+
+/// Allocator for objects of type `T`, backed by a lookaside list of
+/// non-paged pool.
+///
+/// Freed objects are cached by the lookaside list (up to a depth that the
+/// system adjusts based on usage) and reused by later allocations, which makes
+/// [`LookasideAllocator::allocate`] faster than
+/// [`WDKAllocator`](crate::WDKAllocator) for objects that are allocated and
+/// freed frequently.
+///
+/// Failed allocations are reported to the handler registered via
+/// [`set_alloc_failure_handler`](crate::set_alloc_failure_handler). When the
+/// `statistics` feature is enabled, allocations are also counted under the
+/// pool tag of the allocator.
+///
+/// # Safety
+/// This allocator is only safe to use for allocations happening at `IRQL` <=
+/// `DISPATCH_LEVEL`
+pub struct LookasideAllocator<T> {
+    lookaside: NonNull<LOOKASIDE_LIST_EX>,
+    #[cfg(feature = "statistics")]
+    tag: ULONG,
+    _marker: PhantomData<T>,
+}
+
+impl<T> LookasideAllocator<T> {
+    /// Size of each block in the lookaside list. Blocks are linked into the
+    /// lookaside list while they are cached, so they must be able to hold a
+    /// [`SLIST_ENTRY`].
+    const BLOCK_SIZE: usize = if size_of::<T>() > size_of::<SLIST_ENTRY>() {
+        size_of::<T>()
+    } else {
+        size_of::<SLIST_ENTRY>()
+    };
+
+    /// Try to construct a [`LookasideAllocator`] whose blocks are allocated
+    /// with the pool tag `tag`
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INSUFFICIENT_RESOURCES` if the
+    /// lookaside list cannot be allocated, or any error returned by
+    /// `ExInitializeLookasideListEx`.
+    ///
+    /// # Panics
+    ///
+    /// This function will fail to compile if the alignment of `T` is larger
+    /// than `MEMORY_ALLOCATION_ALIGNMENT`, which is the alignment of blocks in
+    /// the lookaside list.
+    pub fn try_new(tag: ULONG) -> Result<Self, NTSTATUS> {
+        const {
+            assert!(
+                align_of::<T>() <= MEMORY_ALLOCATION_ALIGNMENT as usize,
+                "LookasideAllocator does not support types aligned to more than \
+                 MEMORY_ALLOCATION_ALIGNMENT"
+            );
+        };
+
+        let lookaside =
+            // SAFETY: `ExAllocatePool2` is safe to call from any `IRQL` <= `DISPATCH_LEVEL` since its allocating from `POOL_FLAG_NON_PAGED`
+            unsafe {
+                ExAllocatePool2(
+                    POOL_FLAG_NON_PAGED,
+                    size_of::<LOOKASIDE_LIST_EX>() as SIZE_T,
+                    tag,
+                )
+            };
+        let Some(lookaside) = NonNull::new(lookaside.cast::<LOOKASIDE_LIST_EX>()) else {
+            return Err(STATUS_INSUFFICIENT_RESOURCES);
+        };
+
+        let nt_status;
+        // SAFETY: `lookaside` points to non-paged memory large enough for a
+        // `LOOKASIDE_LIST_EX`, which is not moved or freed until it is deleted in
+        // `drop`. Null allocate and free functions select the system's default
+        // pool functions.
+        unsafe {
+            nt_status = ExInitializeLookasideListEx(
+                lookaside.as_ptr(),
+                None,
+                None,
+                NonPagedPoolNx,
+                0,
+                Self::BLOCK_SIZE as SIZE_T,
+                tag,
+                0,
+            );
+        }
+        if !wdk_sys::NT_SUCCESS(nt_status) {
+            // SAFETY: `lookaside` was allocated by `ExAllocatePool2` above, and was not
+            // initialized, so nothing else references it
+            unsafe {
+                ExFreePool(lookaside.as_ptr().cast());
+            }
+            return Err(nt_status);
+        }
+
+        Ok(Self {
+            lookaside,
+            #[cfg(feature = "statistics")]
+            tag,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Allocate a block from the lookaside list and move `value` into it
+    ///
+    /// # Errors
+    ///
+    /// This function will return `value` if a block cannot be allocated
+    pub fn allocate(&self, value: T) -> Result<NonNull<T>, T> {
+        // SAFETY: `lookaside` is initialized in `try_new` and not deleted until `drop`
+        let block = unsafe { allocate_from_lookaside_list_ex(self.lookaside.as_ptr()) };
+        let Some(block) = NonNull::new(block.cast::<T>()) else {
+            #[cfg(feature = "statistics")]
+            crate::statistics::record_failed_allocation(self.tag);
+            crate::statistics::notify_alloc_failure(Layout::new::<T>());
+            return Err(value);
+        };
+        #[cfg(feature = "statistics")]
+        crate::statistics::record_allocation(self.tag, Self::BLOCK_SIZE);
+
+        // SAFETY: `block` is a newly allocated block of `BLOCK_SIZE` bytes, which is
+        // at least the size of `T`, and is aligned to `MEMORY_ALLOCATION_ALIGNMENT`,
+        // which is checked in `try_new` to be at least the alignment of `T`
+        unsafe {
+            block.as_ptr().write(value);
+        }
+        Ok(block)
+    }
+
+    /// Drop the value in `block` and return `block` to the lookaside list
+    ///
+    /// # Safety
+    ///
+    /// `block` must have been returned by [`LookasideAllocator::allocate`] of
+    /// this allocator, and must not have been freed already. `block` must not
+    /// be used after it is freed.
+    pub unsafe fn free(&self, block: NonNull<T>) {
+        // SAFETY: The caller guarantees that `block` holds a value allocated by
+        // `allocate` that has not been freed, so it is valid to drop
+        unsafe {
+            block.as_ptr().drop_in_place();
+        }
+
+        // SAFETY: The caller guarantees that `block` was allocated from `lookaside` and
+        // is not used after it is freed
+        unsafe {
+            free_to_lookaside_list_ex(self.lookaside.as_ptr(), block.as_ptr().cast());
+        }
+        #[cfg(feature = "statistics")]
+        crate::statistics::record_deallocation(self.tag, Self::BLOCK_SIZE);
+    }
+}
+
+impl<T> Drop for LookasideAllocator<T> {
+    fn drop(&mut self) {
+        // SAFETY: `lookaside` is initialized in `try_new`, and is not used after it is
+        // deleted here. Blocks cached by the lookaside list are freed by
+        // `ExDeleteLookasideListEx`.
+        unsafe {
+            ExDeleteLookasideListEx(self.lookaside.as_ptr());
+        }
+
+        // SAFETY: `lookaside` was allocated by `ExAllocatePool2` in `try_new`, and was
+        // deleted above, so nothing else references it
+        unsafe {
+            ExFreePool(self.lookaside.as_ptr().cast());
+        }
+    }
+}
+
+// SAFETY: Lookaside lists can be used from any thread, and values of `T` can
+// be sent to the thread that frees them
+unsafe impl<T: Send> Send for LookasideAllocator<T> {}
+
+// SAFETY: Lookaside lists synchronize concurrent allocations and frees via
+// interlocked singly linked lists
+unsafe impl<T: Send> Sync for LookasideAllocator<T> {}
+
+/// Port of the `ExAllocateFromLookasideListEx` function from `wdm.h`, which is
+/// `FORCEINLINE` and therefore not exported by `ntoskrnl`
+///
+/// # Safety
+///
+/// `lookaside` must point to an initialized `LOOKASIDE_LIST_EX` that has not
+/// been deleted
+unsafe fn allocate_from_lookaside_list_ex(lookaside: *mut LOOKASIDE_LIST_EX) -> PVOID {
+    // SAFETY: The caller guarantees that `lookaside` points to an initialized
+    // `LOOKASIDE_LIST_EX`
+    let lookaside_pool = unsafe { &raw mut (*lookaside).L };
+
+    // SAFETY: `lookaside_pool` points to the `GENERAL_LOOKASIDE_POOL` of an
+    // initialized `LOOKASIDE_LIST_EX`
+    let total_allocates = unsafe { &raw mut (*lookaside_pool).TotalAllocates };
+    // SAFETY: `total_allocates` is a counter of an initialized lookaside list
+    unsafe { increment_counter(total_allocates) };
+
+    // SAFETY: `lookaside_pool` points to the `GENERAL_LOOKASIDE_POOL` of an
+    // initialized `LOOKASIDE_LIST_EX`
+    let list_head = unsafe { &raw mut (*lookaside_pool).__bindgen_anon_1.ListHead };
+    // SAFETY: `list_head` is the list head of an initialized lookaside list, which
+    // is only accessed via interlocked functions
+    let entry = unsafe { ExpInterlockedPopEntrySList(list_head) };
+    if !entry.is_null() {
+        return entry.cast();
+    }
+
+    // SAFETY: `lookaside_pool` points to the `GENERAL_LOOKASIDE_POOL` of an
+    // initialized `LOOKASIDE_LIST_EX`
+    let allocate_misses = unsafe { &raw mut (*lookaside_pool).__bindgen_anon_2.AllocateMisses };
+    // SAFETY: `allocate_misses` is a counter of an initialized lookaside list
+    unsafe { increment_counter(allocate_misses) };
+
+    // SAFETY: `lookaside_pool` points to the `GENERAL_LOOKASIDE_POOL` of an
+    // initialized `LOOKASIDE_LIST_EX`, whose allocate function, pool type, size
+    // and tag are set by `ExInitializeLookasideListEx` and never modified
+    // afterwards
+    let GENERAL_LOOKASIDE_POOL {
+        __bindgen_anon_4: allocate,
+        Type: pool_type,
+        Size: size,
+        Tag: tag,
+        ..
+    } = unsafe { *lookaside_pool };
+    // SAFETY: `ExInitializeLookasideListEx` sets `AllocateEx` for
+    // `LOOKASIDE_LIST_EX`
+    let allocate = unsafe { allocate.AllocateEx }.expect(
+        "ExInitializeLookasideListEx should always set the allocate function of the lookaside list",
+    );
+
+    // SAFETY: `allocate` is the allocate function of the lookaside list, which is
+    // called with the parameters of the lookaside list
+    unsafe { allocate(pool_type, SIZE_T::from(size), tag, lookaside) }
+}
+
+/// Port of the `ExFreeToLookasideListEx` function from `wdm.h`, which is
+/// `FORCEINLINE` and therefore not exported by `ntoskrnl`
+///
+/// # Safety
+///
+/// `lookaside` must point to an initialized `LOOKASIDE_LIST_EX` that has not
+/// been deleted, and `entry` must have been allocated from it and not used
+/// after it is freed
+unsafe fn free_to_lookaside_list_ex(lookaside: *mut LOOKASIDE_LIST_EX, entry: PVOID) {
+    // SAFETY: The caller guarantees that `lookaside` points to an initialized
+    // `LOOKASIDE_LIST_EX`
+    let lookaside_pool = unsafe { &raw mut (*lookaside).L };
+
+    // SAFETY: `lookaside_pool` points to the `GENERAL_LOOKASIDE_POOL` of an
+    // initialized `LOOKASIDE_LIST_EX`
+    let total_frees = unsafe { &raw mut (*lookaside_pool).TotalFrees };
+    // SAFETY: `total_frees` is a counter of an initialized lookaside list
+    unsafe { increment_counter(total_frees) };
+
+    // SAFETY: `lookaside_pool` points to the `GENERAL_LOOKASIDE_POOL` of an
+    // initialized `LOOKASIDE_LIST_EX`
+    let list_head = unsafe { &raw mut (*lookaside_pool).__bindgen_anon_1.ListHead };
+    // SAFETY: `list_head` is the list head of an initialized lookaside list, which
+    // is only accessed via interlocked functions
+    let cached_count = unsafe { ExQueryDepthSList(list_head) };
+
+    // SAFETY: `lookaside_pool` points to the `GENERAL_LOOKASIDE_POOL` of an
+    // initialized `LOOKASIDE_LIST_EX`
+    let depth = unsafe { &raw mut (*lookaside_pool).Depth };
+    // SAFETY: `depth` is valid for the lifetime of the lookaside list, and is
+    // adjusted periodically by the system, so it is only read atomically
+    let depth = unsafe { AtomicU16::from_ptr(depth) }.load(Ordering::Relaxed);
+
+    if cached_count < depth {
+        // SAFETY: The caller guarantees that `entry` was allocated from the lookaside
+        // list, so it is large enough and sufficiently aligned to hold a `SLIST_ENTRY`,
+        // and is not used after it is freed
+        unsafe {
+            ExpInterlockedPushEntrySList(list_head, entry.cast());
+        }
+        return;
+    }
+
+    // SAFETY: `lookaside_pool` points to the `GENERAL_LOOKASIDE_POOL` of an
+    // initialized `LOOKASIDE_LIST_EX`
+    let free_misses = unsafe { &raw mut (*lookaside_pool).__bindgen_anon_3.FreeMisses };
+    // SAFETY: `free_misses` is a counter of an initialized lookaside list
+    unsafe { increment_counter(free_misses) };
+
+    // SAFETY: `lookaside_pool` points to the `GENERAL_LOOKASIDE_POOL` of an
+    // initialized `LOOKASIDE_LIST_EX`, whose free function is set by
+    // `ExInitializeLookasideListEx` and never modified afterwards
+    let free = unsafe { (*lookaside_pool).__bindgen_anon_5 };
+    // SAFETY: `ExInitializeLookasideListEx` sets `FreeEx` for `LOOKASIDE_LIST_EX`
+    let free = unsafe { free.FreeEx }.expect(
+        "ExInitializeLookasideListEx should always set the free function of the lookaside list",
+    );
+
+    // SAFETY: `free` is the free function of the lookaside list, and the caller
+    // guarantees that `entry` was allocated from it
+    unsafe { free(entry, lookaside) }
+}
+
+/// Atomically increment a counter of a lookaside list. The counters are only
+/// used as hints by the system to tune the depth of the lookaside list, but
+/// are updated atomically so that concurrent updates are not lost.
+///
+/// # Safety
+///
+/// `counter` must point to a counter of an initialized lookaside list
+unsafe fn increment_counter(counter: *mut ULONG) {
+    // SAFETY: The caller guarantees that `counter` points to a counter of an
+    // initialized lookaside list, which is only accessed atomically
+    unsafe { AtomicU32::from_ptr(counter) }.fetch_add(1, Ordering::Relaxed);
+}
@@ -0,0 +1,258 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Observability of the allocations made by the allocators of this crate.
+//!
+//! A handler can be registered via [`set_alloc_failure_handler`] to be
+//! notified of failed allocations before the failure is reported to the
+//! caller.
+//!
+//! When the `statistics` feature is enabled,
+//! [`WDKAllocator`](crate::WDKAllocator) and
+//! [`LookasideAllocator`](crate::LookasideAllocator) also keep counters of
+//! their allocations, keyed by the pool tag of the allocations. The counters of
+//! a tag can be queried via `statistics`, and the counters of every tag can
+//! be printed to the kernel debugger via `dump_statistics` (ex. from
+//! `EvtDriverUnload` to detect leaked pool). The counters are updated with
+//! atomic operations on every allocation, so the feature is disabled by
+//! default.
+//!
+//! ```rust, no_run
+//! use core::alloc::Layout;
+//!
+//! fn on_alloc_failure(layout: Layout) {
+//!     #[cfg(feature = "statistics")]
+//!     wdk_alloc::dump_statistics();
+//! }
+//!
+//! wdk_alloc::set_alloc_failure_handler(Some(on_alloc_failure));
+//! ```
+
+#[cfg(feature = "statistics")]
+use core::sync::atomic::{AtomicU32, AtomicUsize};
+use core::{
+    alloc::Layout,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+#[cfg(feature = "statistics")]
+use wdk_sys::{ntddk::DbgPrint, SIZE_T, ULONG};
+
+/// Handler called with the [`Layout`] of an allocation that could not be
+/// satisfied, before the failure is reported to the caller of the allocator
+pub type AllocFailureHandler = fn(Layout);
+
+/// The registered [`AllocFailureHandler`], stored as a type-erased pointer
+/// since function pointers cannot be stored in atomics directly. Null if no
+/// handler is registered.
+static ALLOC_FAILURE_HANDLER: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Maximum number of pool tags that counters are kept for. Allocations made
+/// with further pool tags are not counted.
+#[cfg(feature = "statistics")]
+const MAX_TRACKED_TAGS: usize = 16;
+
+/// Counters of every tracked pool tag. Slots are claimed in order by the
+/// first allocation made with a pool tag, and are never released.
+#[cfg(feature = "statistics")]
+static TAG_COUNTERS: [TagCounters; MAX_TRACKED_TAGS] =
+    [const { TagCounters::new() }; MAX_TRACKED_TAGS];
+
+/// Counters of the allocations made with a single pool tag
+#[cfg(feature = "statistics")]
+struct TagCounters {
+    /// Pool tag the counters are for, or 0 if the slot is not claimed yet
+    tag: AtomicU32,
+    allocation_count: AtomicUsize,
+    deallocation_count: AtomicUsize,
+    failed_allocation_count: AtomicUsize,
+    bytes_allocated: AtomicUsize,
+    peak_bytes_allocated: AtomicUsize,
+}
+
+#[cfg(feature = "statistics")]
+impl TagCounters {
+    const fn new() -> Self {
+        Self {
+            tag: AtomicU32::new(0),
+            allocation_count: AtomicUsize::new(0),
+            deallocation_count: AtomicUsize::new(0),
+            failed_allocation_count: AtomicUsize::new(0),
+            bytes_allocated: AtomicUsize::new(0),
+            peak_bytes_allocated: AtomicUsize::new(0),
+        }
+    }
+
+    /// Find the counters of `tag`, or claim a free slot for them. Returns
+    /// [`None`] if every slot is claimed by other pool tags.
+    fn get_or_claim(tag: ULONG) -> Option<&'static Self> {
+        if tag == 0 {
+            return None;
+        }
+
+        TAG_COUNTERS.iter().find(|counters| {
+            match counters
+                .tag
+                .compare_exchange(0, tag, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => true,
+                Err(claimed_tag) => claimed_tag == tag,
+            }
+        })
+    }
+
+    /// Find the counters of `tag`, without claiming a slot for them
+    fn get(tag: ULONG) -> Option<&'static Self> {
+        if tag == 0 {
+            return None;
+        }
+
+        TAG_COUNTERS
+            .iter()
+            .find(|counters| counters.tag.load(Ordering::Acquire) == tag)
+    }
+
+    fn snapshot(&self) -> AllocatorStatistics {
+        AllocatorStatistics {
+            tag: self.tag.load(Ordering::Acquire),
+            allocation_count: self.allocation_count.load(Ordering::Relaxed),
+            deallocation_count: self.deallocation_count.load(Ordering::Relaxed),
+            failed_allocation_count: self.failed_allocation_count.load(Ordering::Relaxed),
+            bytes_allocated: self.bytes_allocated.load(Ordering::Relaxed),
+            peak_bytes_allocated: self.peak_bytes_allocated.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Snapshot of the counters kept for the allocations made with a pool tag
+///
+/// The counters are updated independently of each other, so a snapshot taken
+/// while other threads are allocating may be slightly inconsistent.
+#[cfg(feature = "statistics")]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct AllocatorStatistics {
+    /// Pool tag of the allocations these statistics are for
+    pub tag: u32,
+    /// Number of successful allocations
+    pub allocation_count: usize,
+    /// Number of deallocations
+    pub deallocation_count: usize,
+    /// Number of allocations that could not be satisfied
+    pub failed_allocation_count: usize,
+    /// Number of bytes currently allocated
+    pub bytes_allocated: usize,
+    /// Largest number of bytes that were allocated at the same time
+    pub peak_bytes_allocated: usize,
+}
+
+#[cfg(feature = "statistics")]
+impl AllocatorStatistics {
+    /// Number of allocations that have not been deallocated
+    #[must_use]
+    pub const fn outstanding_allocation_count(&self) -> usize {
+        self.allocation_count
+            .saturating_sub(self.deallocation_count)
+    }
+}
+
+/// Register `handler` to be called whenever an allocator of this crate fails
+/// to satisfy an allocation, or unregister the current handler if `handler` is
+/// [`None`].
+///
+/// The handler is called at the IRQL of the failed allocation, which can be up
+/// to `DISPATCH_LEVEL`, so it must not allocate or access paged memory.
+pub fn set_alloc_failure_handler(handler: Option<AllocFailureHandler>) {
+    ALLOC_FAILURE_HANDLER.store(
+        handler.map_or(core::ptr::null_mut(), |handler| handler as *mut ()),
+        Ordering::Release,
+    );
+}
+
+/// Get a snapshot of the counters kept for the allocations made with the pool
+/// tag `tag` (ex. [`WDKAllocator::POOL_TAG`](crate::WDKAllocator::POOL_TAG))
+///
+/// Returns [`None`] if no allocation was made with `tag` yet, or if `tag` is
+/// not tracked because counters are already kept for the maximum number of
+/// pool tags.
+#[cfg(feature = "statistics")]
+#[must_use]
+pub fn statistics(tag: ULONG) -> Option<AllocatorStatistics> {
+    TagCounters::get(tag).map(TagCounters::snapshot)
+}
+
+/// Print the counters kept for every tracked pool tag to the kernel debugger
+/// via `DbgPrint`
+#[cfg(feature = "statistics")]
+pub fn dump_statistics() {
+    for counters in &TAG_COUNTERS {
+        let statistics = counters.snapshot();
+        if statistics.tag == 0 {
+            break;
+        }
+        let [tag_0, tag_1, tag_2, tag_3] = statistics.tag.to_ne_bytes();
+
+        // SAFETY: The format string is null-terminated, and each conversion
+        // specification matches the type of its corresponding argument. `%c`
+        // arguments are promoted to int as required for C variadic arguments.
+        unsafe {
+            DbgPrint(
+                c"Allocation statistics for pool tag '%c%c%c%c': %Iu allocations, %Iu deallocations, %Iu failed allocations, %Iu bytes allocated (peak %Iu bytes)\n".as_ptr(),
+                i32::from(tag_0),
+                i32::from(tag_1),
+                i32::from(tag_2),
+                i32::from(tag_3),
+                statistics.allocation_count as SIZE_T,
+                statistics.deallocation_count as SIZE_T,
+                statistics.failed_allocation_count as SIZE_T,
+                statistics.bytes_allocated as SIZE_T,
+                statistics.peak_bytes_allocated as SIZE_T,
+            );
+        }
+    }
+}
+
+/// Record a successful allocation of `size` bytes with the pool tag `tag`
+#[cfg(feature = "statistics")]
+pub fn record_allocation(tag: ULONG, size: usize) {
+    let Some(counters) = TagCounters::get_or_claim(tag) else {
+        return;
+    };
+    counters.allocation_count.fetch_add(1, Ordering::Relaxed);
+    let bytes_allocated = counters.bytes_allocated.fetch_add(size, Ordering::Relaxed) + size;
+    counters
+        .peak_bytes_allocated
+        .fetch_max(bytes_allocated, Ordering::Relaxed);
+}
+
+/// Record a deallocation of `size` bytes with the pool tag `tag`
+#[cfg(feature = "statistics")]
+pub fn record_deallocation(tag: ULONG, size: usize) {
+    let Some(counters) = TagCounters::get(tag) else {
+        return;
+    };
+    counters.deallocation_count.fetch_add(1, Ordering::Relaxed);
+    counters.bytes_allocated.fetch_sub(size, Ordering::Relaxed);
+}
+
+/// Record a failed allocation with the pool tag `tag`
+#[cfg(feature = "statistics")]
+pub fn record_failed_allocation(tag: ULONG) {
+    let Some(counters) = TagCounters::get_or_claim(tag) else {
+        return;
+    };
+    counters
+        .failed_allocation_count
+        .fetch_add(1, Ordering::Relaxed);
+}
+
+/// Call the registered [`AllocFailureHandler`] with the `layout` of a failed
+/// allocation, if there is one
+pub fn notify_alloc_failure(layout: Layout) {
+    let handler = ALLOC_FAILURE_HANDLER.load(Ordering::Acquire);
+    if !handler.is_null() {
+        // SAFETY: Non-null values of `ALLOC_FAILURE_HANDLER` are only ever stored by
+        // `set_alloc_failure_handler`, which casts them from an `AllocFailureHandler`
+        let handler = unsafe { core::mem::transmute::<*mut (), AllocFailureHandler>(handler) };
+        handler(layout);
+    }
+}
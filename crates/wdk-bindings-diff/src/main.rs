@@ -0,0 +1,346 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! `wdk-bindings-diff` summarizes the differences between two versions of a
+//! bindings file generated by [`wdk-sys`](https://docs.rs/wdk-sys) (ex.
+//! `types.rs` generated against two different WDK versions).
+//!
+//! Instead of diffing the files line by line, both files are parsed with
+//! [`syn`] and compared item by item, so that reordering of items and changes
+//! to doc comments do not show up as differences. The output is a list of
+//! added, removed and changed items, ex. `WdfDeviceCreate signature changed`.
+//!
+//! ```text
+//! cargo run -p wdk-bindings-diff -- old/types.rs new/types.rs
+//! ```
+
+use std::{collections::BTreeMap, fmt, path::PathBuf};
+
+use anyhow::Context;
+use clap::Parser;
+use quote::ToTokens;
+use syn::{
+    visit_mut::{self, VisitMut},
+    Attribute,
+    Field,
+    ForeignItem,
+    ForeignItemFn,
+    ForeignItemStatic,
+    Item,
+    ItemConst,
+    ItemEnum,
+    ItemFn,
+    ItemStatic,
+    ItemStruct,
+    ItemType,
+    ItemUnion,
+    Variant,
+};
+
+/// Summarize the item-level differences between two bindings files generated
+/// by wdk-sys
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    /// Path to the old version of the bindings file
+    old: PathBuf,
+    /// Path to the new version of the bindings file
+    new: PathBuf,
+}
+
+/// The kind of a binding item, which determines how a change to it is
+/// described
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum BindingKind {
+    Function,
+    Type,
+    TypeAlias,
+    Constant,
+    Static,
+}
+
+/// Map of binding items, keyed by their kind and their (module-qualified)
+/// name. The value is the normalized token representation of the item.
+type Bindings = BTreeMap<(BindingKind, String), String>;
+
+/// A single item-level difference between two versions of a bindings file
+#[derive(Debug, PartialEq, Eq)]
+enum BindingDiff {
+    Added(BindingKind, String),
+    Removed(BindingKind, String),
+    Changed(BindingKind, String),
+}
+
+/// [`VisitMut`] implementation that removes doc comments from all items,
+/// fields and variants, since changes to them are not semantic changes
+struct DocCommentStripper;
+
+impl fmt::Display for BindingKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Function => "function",
+            Self::Type => "type",
+            Self::TypeAlias => "type alias",
+            Self::Constant => "constant",
+            Self::Static => "static",
+        })
+    }
+}
+
+impl fmt::Display for BindingDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Added(kind, name) => write!(f, "added {kind} {name}"),
+            Self::Removed(kind, name) => write!(f, "removed {kind} {name}"),
+            Self::Changed(kind, name) => {
+                let change_description = match kind {
+                    BindingKind::Function => "signature changed",
+                    BindingKind::Type | BindingKind::TypeAlias => "definition changed",
+                    BindingKind::Constant | BindingKind::Static => "value changed",
+                };
+                write!(f, "{name} {change_description}")
+            }
+        }
+    }
+}
+
+impl VisitMut for DocCommentStripper {
+    fn visit_item_struct_mut(&mut self, item: &mut ItemStruct) {
+        strip_doc_comments(&mut item.attrs);
+        visit_mut::visit_item_struct_mut(self, item);
+    }
+
+    fn visit_item_union_mut(&mut self, item: &mut ItemUnion) {
+        strip_doc_comments(&mut item.attrs);
+        visit_mut::visit_item_union_mut(self, item);
+    }
+
+    fn visit_item_enum_mut(&mut self, item: &mut ItemEnum) {
+        strip_doc_comments(&mut item.attrs);
+        visit_mut::visit_item_enum_mut(self, item);
+    }
+
+    fn visit_item_type_mut(&mut self, item: &mut ItemType) {
+        strip_doc_comments(&mut item.attrs);
+        visit_mut::visit_item_type_mut(self, item);
+    }
+
+    fn visit_item_const_mut(&mut self, item: &mut ItemConst) {
+        strip_doc_comments(&mut item.attrs);
+        visit_mut::visit_item_const_mut(self, item);
+    }
+
+    fn visit_item_static_mut(&mut self, item: &mut ItemStatic) {
+        strip_doc_comments(&mut item.attrs);
+        visit_mut::visit_item_static_mut(self, item);
+    }
+
+    fn visit_item_fn_mut(&mut self, item: &mut ItemFn) {
+        strip_doc_comments(&mut item.attrs);
+        visit_mut::visit_item_fn_mut(self, item);
+    }
+
+    fn visit_foreign_item_fn_mut(&mut self, item: &mut ForeignItemFn) {
+        strip_doc_comments(&mut item.attrs);
+        visit_mut::visit_foreign_item_fn_mut(self, item);
+    }
+
+    fn visit_foreign_item_static_mut(&mut self, item: &mut ForeignItemStatic) {
+        strip_doc_comments(&mut item.attrs);
+        visit_mut::visit_foreign_item_static_mut(self, item);
+    }
+
+    fn visit_field_mut(&mut self, field: &mut Field) {
+        strip_doc_comments(&mut field.attrs);
+        visit_mut::visit_field_mut(self, field);
+    }
+
+    fn visit_variant_mut(&mut self, variant: &mut Variant) {
+        strip_doc_comments(&mut variant.attrs);
+        visit_mut::visit_variant_mut(self, variant);
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    let old_bindings = parse_bindings_file(&args.old)?;
+    let new_bindings = parse_bindings_file(&args.new)?;
+    let binding_diffs = diff_bindings(&old_bindings, &new_bindings);
+
+    for binding_diff in &binding_diffs {
+        println!("{binding_diff}");
+    }
+
+    let (mut added_count, mut removed_count, mut changed_count) = (0, 0, 0);
+    for binding_diff in &binding_diffs {
+        match binding_diff {
+            BindingDiff::Added(..) => added_count += 1,
+            BindingDiff::Removed(..) => removed_count += 1,
+            BindingDiff::Changed(..) => changed_count += 1,
+        }
+    }
+    println!("{added_count} added, {removed_count} removed, {changed_count} changed");
+
+    Ok(())
+}
+
+fn parse_bindings_file(path: &PathBuf) -> anyhow::Result<Bindings> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read bindings file at {}", path.display()))?;
+    parse_bindings(&contents)
+        .with_context(|| format!("failed to parse bindings file at {}", path.display()))
+}
+
+fn parse_bindings(contents: &str) -> syn::Result<Bindings> {
+    let mut file = syn::parse_file(contents)?;
+    DocCommentStripper.visit_file_mut(&mut file);
+
+    let mut bindings = Bindings::new();
+    collect_bindings(&file.items, "", &mut bindings);
+    Ok(bindings)
+}
+
+/// Collect the bindings in `items` into `bindings`. Items in modules (ex. the
+/// modules bindgen generates for C enums) are prefixed with `module_prefix`.
+fn collect_bindings(items: &[Item], module_prefix: &str, bindings: &mut Bindings) {
+    for item in items {
+        let (kind, name) = match item {
+            Item::Fn(item_fn) => (BindingKind::Function, &item_fn.sig.ident),
+            Item::Struct(ItemStruct { ident, .. })
+            | Item::Union(ItemUnion { ident, .. })
+            | Item::Enum(ItemEnum { ident, .. }) => (BindingKind::Type, ident),
+            Item::Type(item_type) => (BindingKind::TypeAlias, &item_type.ident),
+            Item::Const(item_const) => (BindingKind::Constant, &item_const.ident),
+            Item::Static(item_static) => (BindingKind::Static, &item_static.ident),
+            Item::Mod(item_mod) => {
+                if let Some((_, module_items)) = &item_mod.content {
+                    collect_bindings(
+                        module_items,
+                        &format!("{module_prefix}{}::", item_mod.ident),
+                        bindings,
+                    );
+                }
+                continue;
+            }
+            Item::ForeignMod(item_foreign_mod) => {
+                for foreign_item in &item_foreign_mod.items {
+                    let (kind, name, tokens) = match foreign_item {
+                        ForeignItem::Fn(foreign_item_fn) => (
+                            BindingKind::Function,
+                            &foreign_item_fn.sig.ident,
+                            foreign_item_fn.sig.to_token_stream(),
+                        ),
+                        ForeignItem::Static(foreign_item_static) => (
+                            BindingKind::Static,
+                            &foreign_item_static.ident,
+                            foreign_item_static.to_token_stream(),
+                        ),
+                        _ => continue,
+                    };
+                    bindings.insert((kind, format!("{module_prefix}{name}")), tokens.to_string());
+                }
+                continue;
+            }
+            // Impl blocks (ex. bitfield accessors and `Default` impls) are derived from the
+            // types they are implemented for, so changes to them are covered by changes to
+            // those types
+            _ => continue,
+        };
+        bindings.insert(
+            (kind, format!("{module_prefix}{name}")),
+            item.to_token_stream().to_string(),
+        );
+    }
+}
+
+fn diff_bindings(old_bindings: &Bindings, new_bindings: &Bindings) -> Vec<BindingDiff> {
+    let removed_and_changed = old_bindings
+        .iter()
+        .filter_map(
+            |((kind, name), old_tokens)| match new_bindings.get(&(*kind, name.clone())) {
+                None => Some(BindingDiff::Removed(*kind, name.clone())),
+                Some(new_tokens) if new_tokens != old_tokens => {
+                    Some(BindingDiff::Changed(*kind, name.clone()))
+                }
+                Some(_) => None,
+            },
+        );
+    let added = new_bindings
+        .keys()
+        .filter(|key| !old_bindings.contains_key(*key))
+        .map(|(kind, name)| BindingDiff::Added(*kind, name.clone()));
+
+    added.chain(removed_and_changed).collect()
+}
+
+fn strip_doc_comments(attributes: &mut Vec<Attribute>) {
+    attributes.retain(|attribute| !attribute.path().is_ident("doc"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(old: &str, new: &str) -> Vec<String> {
+        diff_bindings(&parse_bindings(old).unwrap(), &parse_bindings(new).unwrap())
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn reordering_and_doc_comments_are_ignored() {
+        let old = r#"
+            #[doc = "old doc"]
+            pub type ULONG = u32;
+            pub struct _FOO {
+                #[doc = "old field doc"]
+                pub Bar: ULONG,
+            }
+        "#;
+        let new = r#"
+            pub struct _FOO {
+                #[doc = "new field doc"]
+                pub Bar: ULONG,
+            }
+            #[doc = "new doc"]
+            pub type ULONG = u32;
+        "#;
+
+        assert!(diff(old, new).is_empty());
+    }
+
+    #[test]
+    fn added_removed_and_changed_items() {
+        let old = r#"
+            pub const REMOVED: u32 = 1;
+            pub const CHANGED: u32 = 1;
+            extern "C" {
+                pub fn WdfDeviceCreate(DeviceInit: *mut PWDFDEVICE_INIT) -> NTSTATUS;
+            }
+        "#;
+        let new = r#"
+            pub const CHANGED: u32 = 2;
+            pub mod _WDFFUNCENUM {
+                pub const WdfAddedTableIndex: u32 = 0;
+            }
+            extern "C" {
+                pub fn WdfDeviceCreate(
+                    DeviceInit: *mut PWDFDEVICE_INIT,
+                    DeviceAttributes: PWDF_OBJECT_ATTRIBUTES,
+                ) -> NTSTATUS;
+            }
+        "#;
+
+        assert_eq!(
+            diff(old, new),
+            vec![
+                "added constant _WDFFUNCENUM::WdfAddedTableIndex",
+                "WdfDeviceCreate signature changed",
+                "CHANGED value changed",
+                "removed constant REMOVED",
+            ]
+        );
+    }
+}
@@ -0,0 +1,358 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! This module provides functions to run the WDK's `ApiValidator.exe` against
+//! a driver package.
+//!
+//! `ApiValidator` detects calls to APIs that are not part of the Universal
+//! DDIs for the target architecture, which is required for drivers submitted
+//! to the Windows Hardware Compatibility Program. See the [ApiValidator Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/develop/validating-windows-drivers#apivalidator)
+//! for more details.
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! use wdk_build::{api_validator::ApiValidator, CPUArchitecture};
+//!
+//! let api_validator = ApiValidator::new(
+//!     Path::new(r"C:\Program Files (x86)\Windows Kits\10"),
+//!     "10.0.22621.0",
+//!     CPUArchitecture::AMD64,
+//! );
+//! let report = api_validator.validate(
+//!     Path::new(r"target\debug\sample_kmdf_driver_package"),
+//!     Path::new(r"target\debug\sample_kmdf_driver.apivalidator.xml"),
+//! )?;
+//! report.ensure_compliant()?;
+//! # Ok::<(), wdk_build::api_validator::ApiValidatorError>(())
+//! ```
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+};
+
+use thiserror::Error;
+
+use crate::CPUArchitecture;
+
+/// Name of the XML element `ApiValidator` reports each violation with
+const VIOLATION_ELEMENT_NAME: &str = "Violation";
+/// Name of the XML element `ApiValidator` reports each validated binary with
+const BINARY_ELEMENT_NAME: &str = "Binary";
+
+/// Errors that could result from running `ApiValidator`
+#[derive(Debug, Error)]
+pub enum ApiValidatorError {
+    /// Error returned when `ApiValidator.exe` cannot be executed, or its report
+    /// cannot be read
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+
+    /// Error returned when `ApiValidator.exe` exits with a failure without
+    /// reporting any violations
+    #[error("ApiValidator failed with {exit_status}")]
+    ApiValidatorFailed {
+        /// Exit status of `ApiValidator.exe`
+        exit_status: ExitStatus,
+    },
+
+    /// Error returned when the report generated by `ApiValidator.exe` is not
+    /// valid XML
+    #[error("cannot parse ApiValidator report")]
+    ReportParseError(#[from] roxmltree::Error),
+
+    /// Error returned when the report generated by `ApiValidator.exe` is
+    /// missing a required attribute
+    #[error("{element} element in ApiValidator report is missing the {attribute} attribute")]
+    MissingAttribute {
+        /// Name of the element that is missing the attribute
+        element: &'static str,
+        /// Name of the missing attribute
+        attribute: &'static str,
+    },
+
+    /// Error returned when the driver package calls APIs that are not
+    /// supported for the target architecture
+    #[error(
+        "driver package calls {} API(s) that are not supported:\n{}",
+        violations.len(),
+        violations.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n")
+    )]
+    ViolationsFound {
+        /// The unsupported API calls
+        violations: Vec<ApiViolation>,
+    },
+}
+
+/// Runs `ApiValidator.exe` from a WDK installation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiValidator {
+    api_extractor_directory: PathBuf,
+    supported_api_xml_file: PathBuf,
+}
+
+/// Call to an API that is not supported for the target architecture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiViolation {
+    /// Name of the binary in the driver package that calls the API (ex.
+    /// `sample_kmdf_driver.sys`)
+    pub binary: String,
+    /// Name of the module that exports the API (ex. `ntoskrnl.exe`)
+    pub module: String,
+    /// Name of the API
+    pub api: String,
+}
+
+/// Results of running `ApiValidator` against a driver package
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApiValidationReport {
+    /// Names of the binaries in the driver package that were validated
+    pub binaries: Vec<String>,
+    /// Calls to APIs that are not supported for the target architecture
+    pub violations: Vec<ApiViolation>,
+}
+
+impl ApiValidator {
+    /// Creates an [`ApiValidator`] that runs `ApiValidator.exe` from the WDK in
+    /// `wdk_content_root` with version `wdk_version` (ex. `10.0.22621.0`), and
+    /// validates against the Universal DDIs of `target_architecture`
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the CPU architecture of the host cannot be
+    /// determined from `std::env::consts::ARCH`
+    #[must_use]
+    pub fn new(
+        wdk_content_root: &Path,
+        wdk_version: &str,
+        target_architecture: CPUArchitecture,
+    ) -> Self {
+        let host_architecture = CPUArchitecture::try_from_cargo_str(std::env::consts::ARCH)
+            .expect("The rust standard library should always set std::env::consts::ARCH");
+
+        Self {
+            api_extractor_directory: wdk_content_root
+                .join(format!("bin/{wdk_version}"))
+                .join(host_architecture.as_windows_str()),
+            supported_api_xml_file: wdk_content_root
+                .join(format!("build/{wdk_version}/universalDDIs"))
+                .join(target_architecture.as_windows_str())
+                .join("UniversalDDIs.xml"),
+        }
+    }
+
+    /// Runs `ApiValidator.exe` against the binaries in
+    /// `driver_package_directory`, writing its XML report to `report_file`,
+    /// and returns the parsed report.
+    ///
+    /// Violations are returned as part of the [`ApiValidationReport`] instead
+    /// of as an error, so that callers can decide how to handle them. Use
+    /// [`ApiValidationReport::ensure_compliant`] to fail on violations.
+    ///
+    /// # Errors
+    ///
+    /// This function returns:
+    /// - [`ApiValidatorError::IoError`] if `ApiValidator.exe` cannot be
+    ///   executed, or its report cannot be read
+    /// - [`ApiValidatorError::ApiValidatorFailed`] if `ApiValidator.exe` fails
+    ///   without reporting any violations
+    /// - [`ApiValidatorError::ReportParseError`] or
+    ///   [`ApiValidatorError::MissingAttribute`] if the report cannot be parsed
+    pub fn validate(
+        &self,
+        driver_package_directory: &Path,
+        report_file: &Path,
+    ) -> Result<ApiValidationReport, ApiValidatorError> {
+        // Remove the report of a previous run, so that it is never mistaken for the
+        // report of this run
+        if report_file.exists() {
+            std::fs::remove_file(report_file)?;
+        }
+
+        let exit_status = Command::new(
+            self.api_extractor_directory
+                .join("ApiValidation/ApiValidator.exe"),
+        )
+        .arg(format!(
+            "-DriverPackagePath:{}",
+            driver_package_directory.display()
+        ))
+        .arg(format!(
+            "-SupportedApiXmlFiles:{}",
+            self.supported_api_xml_file.display()
+        ))
+        .arg(format!(
+            "-ApiExtractorExePath:{}",
+            self.api_extractor_directory.display()
+        ))
+        .arg(format!("-OutputXmlFile:{}", report_file.display()))
+        .status()?;
+
+        // ApiValidator exits with a failure when violations are found, so the report is
+        // parsed before the exit status is checked
+        let report = if report_file.exists() {
+            ApiValidationReport::parse(&std::fs::read_to_string(report_file)?)?
+        } else {
+            ApiValidationReport::default()
+        };
+        if !exit_status.success() && report.violations.is_empty() {
+            return Err(ApiValidatorError::ApiValidatorFailed { exit_status });
+        }
+        Ok(report)
+    }
+}
+
+impl ApiValidationReport {
+    /// Parses an XML report generated by `ApiValidator.exe`
+    ///
+    /// # Errors
+    ///
+    /// This function returns a [`ApiValidatorError::ReportParseError`] if
+    /// `report` is not valid XML, and a
+    /// [`ApiValidatorError::MissingAttribute`] if a `Binary` or `Violation`
+    /// element is missing one of its attributes.
+    pub fn parse(report: &str) -> Result<Self, ApiValidatorError> {
+        let document = roxmltree::Document::parse(report)?;
+
+        let mut api_validation_report = Self::default();
+        for binary_node in document
+            .descendants()
+            .filter(|node| node.has_tag_name(BINARY_ELEMENT_NAME))
+        {
+            let binary = required_attribute(binary_node, BINARY_ELEMENT_NAME, "Name")?;
+            for violation_node in binary_node
+                .descendants()
+                .filter(|node| node.has_tag_name(VIOLATION_ELEMENT_NAME))
+            {
+                api_validation_report.violations.push(ApiViolation {
+                    binary: binary.to_string(),
+                    module: required_attribute(violation_node, VIOLATION_ELEMENT_NAME, "Module")?
+                        .to_string(),
+                    api: required_attribute(violation_node, VIOLATION_ELEMENT_NAME, "Api")?
+                        .to_string(),
+                });
+            }
+            api_validation_report.binaries.push(binary.to_string());
+        }
+        Ok(api_validation_report)
+    }
+
+    /// Returns `true` if no unsupported API calls were found
+    #[must_use]
+    pub const fn is_compliant(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Returns an error if any unsupported API calls were found
+    ///
+    /// # Errors
+    ///
+    /// This function returns a [`ApiValidatorError::ViolationsFound`]
+    /// containing all violations in the report, if there are any
+    pub fn ensure_compliant(self) -> Result<(), ApiValidatorError> {
+        if self.is_compliant() {
+            Ok(())
+        } else {
+            Err(ApiValidatorError::ViolationsFound {
+                violations: self.violations,
+            })
+        }
+    }
+}
+
+impl fmt::Display for ApiViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} calls {}!{}, which is not supported",
+            self.binary, self.module, self.api
+        )
+    }
+}
+
+fn required_attribute<'a>(
+    node: roxmltree::Node<'a, '_>,
+    element: &'static str,
+    attribute: &'static str,
+) -> Result<&'a str, ApiValidatorError> {
+    node.attribute(attribute)
+        .ok_or(ApiValidatorError::MissingAttribute { element, attribute })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_compliant_report() {
+        let report = ApiValidationReport::parse(
+            r#"<ApiValidation><Binary Name="sample_kmdf_driver.sys" /></ApiValidation>"#,
+        )
+        .unwrap();
+
+        assert_eq!(report.binaries, ["sample_kmdf_driver.sys"]);
+        assert!(report.is_compliant());
+        assert!(report.ensure_compliant().is_ok());
+    }
+
+    #[test]
+    fn parse_report_with_violations() {
+        let report = ApiValidationReport::parse(
+            r#"<ApiValidation>
+                <Binary Name="sample_kmdf_driver.sys">
+                    <Violation Module="ntoskrnl.exe" Api="ExAllocatePool" />
+                    <Violation Module="hal.dll" Api="HalGetBusData" />
+                </Binary>
+                <Binary Name="sample_helper.dll" />
+            </ApiValidation>"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            report.binaries,
+            ["sample_kmdf_driver.sys", "sample_helper.dll"]
+        );
+        assert_eq!(
+            report.violations,
+            [
+                ApiViolation {
+                    binary: "sample_kmdf_driver.sys".to_string(),
+                    module: "ntoskrnl.exe".to_string(),
+                    api: "ExAllocatePool".to_string(),
+                },
+                ApiViolation {
+                    binary: "sample_kmdf_driver.sys".to_string(),
+                    module: "hal.dll".to_string(),
+                    api: "HalGetBusData".to_string(),
+                },
+            ]
+        );
+        assert!(matches!(
+            report.ensure_compliant(),
+            Err(ApiValidatorError::ViolationsFound { violations }) if violations.len() == 2
+        ));
+    }
+
+    #[test]
+    fn parse_report_with_missing_attribute() {
+        assert!(matches!(
+            ApiValidationReport::parse(
+                r#"<ApiValidation><Binary Name="sample_kmdf_driver.sys"><Violation Api="ExAllocatePool" /></Binary></ApiValidation>"#,
+            ),
+            Err(ApiValidatorError::MissingAttribute {
+                element: "Violation",
+                attribute: "Module",
+            })
+        ));
+    }
+
+    #[test]
+    fn parse_invalid_report() {
+        assert!(matches!(
+            ApiValidationReport::parse("<ApiValidation>"),
+            Err(ApiValidatorError::ReportParseError(_))
+        ));
+    }
+}
@@ -1,15 +1,79 @@
 // Copyright (c) Microsoft Corporation
 // License: MIT OR Apache-2.0
 
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
 use bindgen::Builder;
+use cargo_metadata::MetadataCommand;
+
+use crate::{
+    metadata::BindgenMetadata,
+    utils::LIBCLANG_PATH_ENV_VAR,
+    CPUArchitecture,
+    Config,
+    ConfigError,
+};
+
+/// Default name of the file in `OUT_DIR` that [`generate_custom_bindings`]
+/// writes the bindings to
+const DEFAULT_CUSTOM_BINDINGS_FILE_NAME: &str = "bindings.rs";
 
-use crate::{CPUArchitecture, Config, ConfigError, DriverConfig};
+/// Oldest `libclang` version (major, minor) that bindings to the WDK are known
+/// to generate correctly with
+pub const MINIMUM_LIBCLANG_VERSION: (u32, u32) = (17, 0);
+
+/// Major version of `libclang` that fails to generate bindings for ARM64, see <https://github.com/rust-lang/rust-bindgen/issues/2842>
+const ARM64_INCOMPATIBLE_LIBCLANG_MAJOR_VERSION: u32 = 18;
 
 /// An extension trait that provides a way to create a [`bindgen::Builder`]
 /// configured for generating bindings to the wdk
+///
+/// This is the same configuration that `wdk-sys` uses to generate its
+/// bindings, and is a stable API that other `-sys` crates (ex. bindings to a
+/// vendor SDK that includes WDK headers) can build on. The returned builder:
+/// - adds `--include-directory` arguments for every path returned by
+///   [`Config::get_include_paths`], and disables `bindgen`'s detection of
+///   system include paths
+/// - adds `--define-macro` arguments for every definition returned by
+///   [`Config::get_preprocessor_definitions`], which depend on the
+///   [`CPUArchitecture`], [`DriverConfig`] and `NetAdapterCx` configuration
+///   of the [`Config`]. The NDIS definitions of
+///   [`Config::get_ndis_preprocessor_definitions`] are not added, since they
+///   only apply to bindings for the NDIS headers.
+/// - enables the Microsoft extensions of `clang` (`-fms-extensions`), and
+///   silences the `clang` warnings that the WDK headers are known to trigger
+/// - generates `core`-only bindings, with `Default` implementations and
+///   [`CStr`](core::ffi::CStr) string constants
+/// - generates enums as modules of constants
+///   ([`EnumVariation::ModuleConsts`](bindgen::EnumVariation::ModuleConsts))
+/// - marks `NTSTATUS` and `HRESULT` as `#[must_use]`
+/// - blocklists deprecated APIs (ex. `ExAllocatePoolWithTag`) and types that
+///   `bindgen` cannot generate correctly
+/// - emits `cargo::rerun-if-changed` for every included header
+///
+/// Additional configuration (ex. allowlists) can be applied to the returned
+/// builder:
+///
+/// ```rust, no_run
+/// use wdk_build::{BuilderExt, Config};
+///
+/// let config = Config::from_env_auto()?;
+/// wdk_build::check_libclang(&config)?;
+/// let bindings = bindgen::Builder::wdk_default(vec!["vendor-sdk-input.h"], &config)?
+///     .allowlist_file("(?i).*vendor_sdk.*")
+///     .generate()?;
+/// bindings.write_to_file("bindings.rs")?;
+/// # Ok::<(), wdk_build::ConfigError>(())
+/// ```
+///
+/// [`CPUArchitecture`]: crate::CPUArchitecture
+/// [`DriverConfig`]: crate::DriverConfig
 pub trait BuilderExt {
     /// Returns a `bindgen::Builder` with the default configuration for
-    /// generation of bindings to the WDK
+    /// generation of bindings to the WDK, for the headers in `c_header_files`
     ///
     /// # Errors
     ///
@@ -33,107 +97,274 @@ fn wdk_default(c_header_files: Vec<&str>, config: &Config) -> Result<Self, Confi
             builder = builder.header(c_header);
         }
 
-        builder = builder
-            .use_core() // Can't use std for kernel code
-            .derive_default(true) // allows for default initializing structs
-            // CStr types are safer and easier to work with when interacting with string constants
-            // from C
-            .generate_cstr(true)
-            // Building in eWDK can pollute system search path when clang-sys tries to detect
-            // c_search_paths
-            .detect_include_paths(false)
-            .clang_args(config.get_include_paths()?.iter().map(|include_path| {
-                format!(
-                    "--include-directory={}",
-                    include_path
-                        .to_str()
-                        .expect("Non Unicode paths are not supported")
-                )
-            }))
-            .clang_args(
-                match config.cpu_architecture {
-                    // Definitions sourced from `Program Files\Windows
-                    // Kits\10\build\10.0.22621.0\WindowsDriver.x64.props`
-                    CPUArchitecture::AMD64 => {
-                        vec!["_WIN64", "_AMD64_", "AMD64"]
-                    }
-                    // Definitions sourced from `Program Files\Windows
-                    // Kits\10\build\10.0.22621.0\WindowsDriver.arm64.props`
-                    CPUArchitecture::ARM64 => {
-                        vec!["_ARM64_", "ARM64", "_USE_DECLSPECS_FOR_SAL=1", "STD_CALL"]
-                    }
-                }
-                .iter()
-                .map(|preprocessor_definition| format!("--define-macro={preprocessor_definition}")),
-            )
-            .clang_args(
-                match config.driver_config {
-                    // FIXME: Add support for KMDF_MINIMUM_VERSION_REQUIRED and
-                    // UMDF_MINIMUM_VERSION_REQUIRED
-                    DriverConfig::WDM() => {
-                        vec![]
-                    }
-                    DriverConfig::KMDF(kmdf_config) => {
-                        vec![
-                            format!("KMDF_VERSION_MAJOR={}", kmdf_config.kmdf_version_major),
-                            format!("KMDF_VERSION_MINOR={}", kmdf_config.kmdf_version_minor),
-                        ]
-                    }
-                    DriverConfig::UMDF(umdf_config) => {
-                        let mut umdf_definitions = vec![
-                            format!("UMDF_VERSION_MAJOR={}", umdf_config.umdf_version_major),
-                            format!("UMDF_VERSION_MINOR={}", umdf_config.umdf_version_minor),
-                        ];
-
-                        if umdf_config.umdf_version_major >= 2 {
-                            umdf_definitions.push("UMDF_USING_NTSTATUS".to_string());
-                            umdf_definitions.push("_UNICODE".to_string());
-                            umdf_definitions.push("UNICODE".to_string());
-                        }
-
-                        umdf_definitions
-                    }
-                }
-                .iter()
-                .map(|preprocessor_definition| format!("--define-macro={preprocessor_definition}")),
-            )
-            // Windows SDK & DDK have non-portable paths (ex. #include "DriverSpecs.h" but the file
-            // is actually driverspecs.h)
-            .clang_arg("--warn-=no-nonportable-include-path")
-            // Windows SDK & DDK use pshpack and poppack headers to change packing
-            .clang_arg("--warn-=no-pragma-pack")
-            .clang_arg("--warn-=no-ignored-attributes")
-            .clang_arg("--warn-=no-ignored-pragma-intrinsic")
-            .clang_arg("--warn-=no-visibility")
-            .clang_arg("--warn-=no-microsoft-anon-tag")
-            .clang_arg("--warn-=no-microsoft-enum-forward-reference")
-            // Don't warn for deprecated declarations. deprecated items are already blocklisted
-            // below and if there are any non-blocklisted function definitions, it will throw a
-            // -WDeprecated warning
-            .clang_arg("--warn-=no-deprecated-declarations")
-            // Windows SDK & DDK contain unnecessary token pasting (ex. &##_variable: `&` and
-            // `_variable` are separate tokens already, and don't need `##` to concatenate them)
-            .clang_arg("--warn-=no-invalid-token-paste")
-            .clang_arg("-fms-extensions")
-            .blocklist_item("ExAllocatePoolWithTag") // Deprecated
-            .blocklist_item("ExAllocatePoolWithQuotaTag") // Deprecated
-            .blocklist_item("ExAllocatePoolWithTagPriority") // Deprecated
-            // FIXME: Types containing 32-bit pointers (via __ptr32) are not generated properly and cause bindgen layout tests to fail: https://github.com/rust-lang/rust-bindgen/issues/2636
-            .blocklist_item(".*EXTENDED_CREATE_INFORMATION_32")
-            // FIXME: bitfield generated with non-1byte alignment in _MCG_CAP
-            .blocklist_item(".*MCG_CAP(?:__bindgen.*)?")
-            .blocklist_item(".*WHEA_XPF_MCA_SECTION")
-            .blocklist_item(".*WHEA_ARM_BUS_ERROR(?:__bindgen.*)?")
-            .blocklist_item(".*WHEA_ARM_PROCESSOR_ERROR")
-            .blocklist_item(".*WHEA_ARM_CACHE_ERROR")
-            .must_use_type("NTSTATUS")
-            .must_use_type("HRESULT")
-            // Defaults enums to generate as a set of constants contained in a module (default value
-            // is EnumVariation::Consts which generates enums as global constants)
-            .default_enum_style(bindgen::EnumVariation::ModuleConsts)
-            .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
-            .formatter(bindgen::Formatter::Prettyplease);
+        builder =
+            builder
+                .use_core() // Can't use std for kernel code
+                .derive_default(true) // allows for default initializing structs
+                // CStr types are safer and easier to work with when interacting with string
+                // constants from C
+                .generate_cstr(true)
+                // Building in eWDK can pollute system search path when clang-sys tries to detect
+                // c_search_paths
+                .detect_include_paths(false)
+                .clang_args(config.get_include_paths()?.iter().map(|include_path| {
+                    format!(
+                        "--include-directory={}",
+                        include_path
+                            .to_str()
+                            .expect("Non Unicode paths are not supported")
+                    )
+                }))
+                .clang_args(config.get_preprocessor_definitions().iter().map(
+                    |preprocessor_definition| format!("--define-macro={preprocessor_definition}"),
+                ))
+                // Windows SDK & DDK have non-portable paths (ex. #include "DriverSpecs.h" but the
+                // file is actually driverspecs.h)
+                .clang_arg("--warn-=no-nonportable-include-path")
+                // Windows SDK & DDK use pshpack and poppack headers to change packing
+                .clang_arg("--warn-=no-pragma-pack")
+                .clang_arg("--warn-=no-ignored-attributes")
+                .clang_arg("--warn-=no-ignored-pragma-intrinsic")
+                .clang_arg("--warn-=no-visibility")
+                .clang_arg("--warn-=no-microsoft-anon-tag")
+                .clang_arg("--warn-=no-microsoft-enum-forward-reference")
+                // Don't warn for deprecated declarations. deprecated items are already blocklisted
+                // below and if there are any non-blocklisted function definitions, it will throw a
+                // -WDeprecated warning
+                .clang_arg("--warn-=no-deprecated-declarations")
+                // Windows SDK & DDK contain unnecessary token pasting (ex. &##_variable: `&` and
+                // `_variable` are separate tokens already, and don't need `##` to concatenate them)
+                .clang_arg("--warn-=no-invalid-token-paste")
+                .clang_arg("-fms-extensions")
+                .blocklist_item("ExAllocatePoolWithTag") // Deprecated
+                .blocklist_item("ExAllocatePoolWithQuotaTag") // Deprecated
+                .blocklist_item("ExAllocatePoolWithTagPriority") // Deprecated
+                // FIXME: Types containing 32-bit pointers (via __ptr32) are not generated properly and cause bindgen layout tests to fail: https://github.com/rust-lang/rust-bindgen/issues/2636
+                .blocklist_item(".*EXTENDED_CREATE_INFORMATION_32")
+                // FIXME: bitfield generated with non-1byte alignment in _MCG_CAP
+                .blocklist_item(".*MCG_CAP(?:__bindgen.*)?")
+                .blocklist_item(".*WHEA_XPF_MCA_SECTION")
+                .blocklist_item(".*WHEA_ARM_BUS_ERROR(?:__bindgen.*)?")
+                .blocklist_item(".*WHEA_ARM_PROCESSOR_ERROR")
+                .blocklist_item(".*WHEA_ARM_CACHE_ERROR")
+                .must_use_type("NTSTATUS")
+                .must_use_type("HRESULT")
+                // Defaults enums to generate as a set of constants contained in a module (default
+                // value is EnumVariation::Consts which generates enums as global
+                // constants)
+                .default_enum_style(bindgen::EnumVariation::ModuleConsts)
+                .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
+                .formatter(bindgen::Formatter::Prettyplease);
 
         Ok(builder)
     }
 }
+
+/// Checks that `bindgen` can load a `libclang` whose version supports
+/// generating bindings to the WDK for `config`.
+///
+/// This should be called by build scripts before generating any bindings,
+/// since `bindgen` panics with an opaque message if it fails to load
+/// `libclang`, and older `libclang` versions fail to parse the WDK headers.
+///
+/// `libclang` is located by `clang-sys`, which searches the directory in the
+/// `LIBCLANG_PATH` environment variable, followed by the directories in
+/// `PATH`.
+///
+/// # Errors
+///
+/// This function will return [`ConfigError::LibclangNotFound`] if `libclang`
+/// cannot be loaded, or [`ConfigError::UnsupportedLibclangVersion`] if its
+/// version is older than [`MINIMUM_LIBCLANG_VERSION`], cannot be determined,
+/// or is known to be incompatible with the CPU architecture of `config`.
+pub fn check_libclang(config: &Config) -> Result<(), ConfigError> {
+    println!("cargo::rerun-if-env-changed={LIBCLANG_PATH_ENV_VAR}");
+
+    if !clang_sys::is_loaded() {
+        clang_sys::load().map_err(|reason| ConfigError::LibclangNotFound { reason })?;
+    }
+    let path = clang_sys::get_library()
+        .map(|library| library.path().display().to_string())
+        .unwrap_or_default();
+
+    let clang_version = bindgen::clang_version();
+    let Some(version) = clang_version.parsed else {
+        return Err(ConfigError::UnsupportedLibclangVersion {
+            path,
+            version: clang_version.full,
+            reason: "its version could not be determined".to_string(),
+        });
+    };
+
+    check_libclang_version(version, config.cpu_architecture).map_err(|reason| {
+        ConfigError::UnsupportedLibclangVersion {
+            path,
+            version: clang_version.full,
+            reason,
+        }
+    })
+}
+
+/// Checks that `libclang` with `version` can generate bindings for
+/// `cpu_architecture`, returning the reason it cannot otherwise
+fn check_libclang_version(
+    version: (u32, u32),
+    cpu_architecture: CPUArchitecture,
+) -> Result<(), String> {
+    if version < MINIMUM_LIBCLANG_VERSION {
+        let (minimum_major, minimum_minor) = MINIMUM_LIBCLANG_VERSION;
+        return Err(format!(
+            "libclang {minimum_major}.{minimum_minor} or newer is required"
+        ));
+    }
+
+    if cpu_architecture == CPUArchitecture::ARM64
+        && version.0 == ARM64_INCOMPATIBLE_LIBCLANG_MAJOR_VERSION
+    {
+        return Err(format!(
+            "libclang {ARM64_INCOMPATIBLE_LIBCLANG_MAJOR_VERSION}.x fails to generate bindings for \
+             ARM64 (see https://github.com/rust-lang/rust-bindgen/issues/2842)"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Generates bindings to the custom C headers specified in the
+/// `[package.metadata.wdk.bindgen]` table of the package being built.
+///
+/// The bindings are generated with the same [`BuilderExt::wdk_default`]
+/// configuration as the WDK bindings in `wdk-sys`. This must be called from a
+/// Cargo build script.
+///
+/// The bindings are written to `OUT_DIR`, and can be included in the package
+/// with:
+///
+/// ```rust, ignore
+/// include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+/// ```
+///
+/// Returns the path of the generated bindings, or [`None`] if the package has
+/// no `bindgen` table.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - the `bindgen` table of the package is invalid
+/// - `cargo metadata` fails to run
+/// - `libclang` cannot be loaded, or its version is not supported (see
+///   [`check_libclang`])
+/// - the WDK include paths cannot be resolved
+/// - `bindgen` fails to generate the bindings
+/// - the bindings cannot be written to `OUT_DIR`
+///
+/// # Panics
+///
+/// This function will panic if it is not called from a Cargo build script
+/// (i.e. if `CARGO_MANIFEST_DIR`, `CARGO_PKG_NAME` or `OUT_DIR` are not set),
+/// or if any of the header paths are not valid UTF-8.
+pub fn generate_custom_bindings(config: &Config) -> Result<Option<PathBuf>, ConfigError> {
+    let manifest_dir = PathBuf::from(
+        env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR should be set by Cargo"),
+    );
+    let package_name = env::var("CARGO_PKG_NAME").expect("CARGO_PKG_NAME should be set by Cargo");
+    let out_dir = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR should be set by Cargo"));
+
+    let cargo_metadata = MetadataCommand::new()
+        .manifest_path(manifest_dir.join("Cargo.toml"))
+        .no_deps()
+        .exec()?;
+    let Some(package) = cargo_metadata
+        .packages
+        .iter()
+        .find(|package| package.name == package_name)
+    else {
+        return Ok(None);
+    };
+    let Some(bindgen_metadata) = BindgenMetadata::try_from_package(package)? else {
+        return Ok(None);
+    };
+
+    let headers = bindgen_metadata
+        .headers
+        .iter()
+        .map(|header| {
+            let header = manifest_dir.join(header);
+            println!("cargo::rerun-if-changed={}", header.display());
+            header
+                .to_str()
+                .expect("Non Unicode paths are not supported")
+                .to_string()
+        })
+        .collect::<Vec<_>>();
+
+    check_libclang(config)?;
+    let mut builder = Builder::wdk_default(headers.iter().map(String::as_str).collect(), config)?
+        .allowlist_recursively(bindgen_metadata.allowlist_recursively.unwrap_or(true));
+
+    if bindgen_metadata.allowlist_files.is_empty() && bindgen_metadata.allowlist_items.is_empty() {
+        // Only generate the items declared in the custom headers themselves, instead of
+        // the entire WDK that they include
+        for header in &bindgen_metadata.headers {
+            builder = builder.allowlist_file(custom_header_file_regex(header));
+        }
+    }
+    for allowlist_file in &bindgen_metadata.allowlist_files {
+        builder = builder.allowlist_file(allowlist_file);
+    }
+    for allowlist_item in &bindgen_metadata.allowlist_items {
+        builder = builder.allowlist_item(allowlist_item);
+    }
+    for blocklist_item in &bindgen_metadata.blocklist_items {
+        builder = builder.blocklist_item(blocklist_item);
+    }
+
+    let output_path = out_dir.join(
+        bindgen_metadata
+            .output
+            .as_deref()
+            .unwrap_or(DEFAULT_CUSTOM_BINDINGS_FILE_NAME),
+    );
+    builder.generate()?.write_to_file(&output_path)?;
+
+    Ok(Some(output_path))
+}
+
+/// Returns a regex that matches the path of `header`, regardless of the path
+/// separators `clang` reports it with
+fn custom_header_file_regex(header: &Path) -> String {
+    let file_name = header
+        .file_name()
+        .expect("header paths should end in a file name")
+        .to_str()
+        .expect("Non Unicode paths are not supported");
+    let escaped_file_name = file_name
+        .chars()
+        .fold(String::new(), |mut regex, character| {
+            if r"\.+*?()|[]{}^$".contains(character) {
+                regex.push('\\');
+            }
+            regex.push(character);
+            regex
+        });
+    format!(r"(?i).*[\\/]{escaped_file_name}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn libclang_version_older_than_minimum_is_rejected() {
+        assert!(check_libclang_version((16, 0), CPUArchitecture::AMD64).is_err());
+        assert!(check_libclang_version((17, 0), CPUArchitecture::AMD64).is_ok());
+        assert!(check_libclang_version((19, 1), CPUArchitecture::AMD64).is_ok());
+    }
+
+    #[test]
+    fn libclang_18_is_rejected_for_arm64_only() {
+        assert!(check_libclang_version((18, 1), CPUArchitecture::AMD64).is_ok());
+        assert!(check_libclang_version((18, 1), CPUArchitecture::ARM64).is_err());
+        assert!(check_libclang_version((17, 0), CPUArchitecture::ARM64).is_ok());
+    }
+}
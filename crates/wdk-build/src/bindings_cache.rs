@@ -0,0 +1,511 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Cache of generated bindings, shared between builds of the same bindings.
+//!
+//! Running bindgen over the WDK headers takes several minutes, but its output
+//! only depends on the WDK version, the `libclang` version, the [`Config`] and
+//! the inputs of the build script. [`BindingsCache`] stores the generated
+//! bindings in a per-user cache directory, keyed by all of these, so that
+//! clean builds and CI jobs can reuse the output of a previous build instead
+//! of re-running bindgen. Since the key does not depend on any paths of the
+//! build machine, a cache directory can be shared between machines (ex.
+//! restored by a CI job).
+//!
+//! Within a single output directory, [`BindingsStamps`] records a hash of the
+//! inputs of each module of bindings when it is generated, so that build
+//! scripts can skip regenerating the modules whose inputs did not change.
+
+use std::{
+    env,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{utils, Config, ConfigError, ExportError};
+
+/// Environment variable that overrides the root directory of the bindings
+/// cache. Setting it to an empty value disables the cache.
+pub const BINDINGS_CACHE_DIR_ENV_VAR: &str = "WDK_BUILD_BINDINGS_CACHE_DIR";
+
+/// Environment variable that forces bindings to be regenerated, even if they
+/// are present in the bindings cache. The regenerated bindings replace the
+/// cached ones.
+pub const REGENERATE_BINDINGS_ENV_VAR: &str = "WDK_BUILD_REGENERATE_BINDINGS";
+
+/// Name of the directory in `%LOCALAPPDATA%` that is used as the default root
+/// of the bindings cache
+const DEFAULT_CACHE_DIRECTORY_NAME: &str = "wdk-build";
+
+/// Name of the file that marks a cache entry as completely written. It is
+/// written last, so entries without it are never restored.
+const COMPLETION_MARKER_FILE_NAME: &str = ".complete";
+
+/// A cache entry of bindings generated by a build script, keyed by the WDK
+/// version, the `libclang` version and a hash of the [`Config`], the enabled
+/// Cargo features, the versions of `wdk-build` and `bindgen`, the build script
+/// and its input files.
+#[derive(Debug)]
+pub struct BindingsCache {
+    entry_directory: PathBuf,
+    regenerate: bool,
+}
+
+impl BindingsCache {
+    /// Returns the cache entry for bindings generated by the current build
+    /// script for `config`, or [`None`] if the bindings cache is disabled or
+    /// no cache directory can be determined. `input_files` are the files
+    /// (ex. C header files) that the build script generates bindings from,
+    /// besides the WDK headers.
+    ///
+    /// This must be called from a Cargo build script.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the WDK version cannot be
+    /// determined, or if the build script or any of `input_files` cannot be
+    /// read.
+    pub fn new<P: AsRef<Path>>(
+        config: &Config,
+        input_files: impl IntoIterator<Item = P>,
+    ) -> Result<Option<Self>, ConfigError> {
+        println!("cargo::rerun-if-env-changed={BINDINGS_CACHE_DIR_ENV_VAR}");
+        println!("cargo::rerun-if-env-changed={REGENERATE_BINDINGS_ENV_VAR}");
+
+        let Some(cache_root) = cache_root() else {
+            return Ok(None);
+        };
+
+        // The version of libclang can only be determined when it is loaded
+        // successfully, in which case bindgen will fail later on anyways
+        let Some((clang_major_version, clang_minor_version)) = bindgen::clang_version().parsed
+        else {
+            return Ok(None);
+        };
+
+        let wdk_version = utils::get_windows_sdk_version(
+            &config.wdk_content_root.join("Include"),
+            config.wdk_version.as_deref(),
+        )?;
+
+        let mut hasher = build_inputs_hasher(config)?;
+        for input_file in input_files {
+            hasher.write(&fs::read(input_file)?);
+        }
+
+        Ok(Some(Self {
+            entry_directory: cache_root
+                .join(wdk_version)
+                .join(format!("clang-{clang_major_version}.{clang_minor_version}"))
+                .join(format!("{:016x}", hasher.finish())),
+            regenerate: env::var_os(REGENERATE_BINDINGS_ENV_VAR)
+                .is_some_and(|value| !value.is_empty()),
+        }))
+    }
+
+    /// Returns the directory of this cache entry
+    #[must_use]
+    pub fn entry_directory(&self) -> &Path {
+        &self.entry_directory
+    }
+
+    /// Copies the cached bindings into each of `destination_directories`.
+    /// Returns `false` without copying anything if the bindings are not
+    /// completely cached yet, or if they must be regenerated because
+    /// [`REGENERATE_BINDINGS_ENV_VAR`] is set.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the cached bindings fail to be
+    /// copied.
+    pub fn restore<P: AsRef<Path>>(
+        &self,
+        destination_directories: &[P],
+    ) -> Result<bool, ConfigError> {
+        if self.regenerate
+            || !self
+                .entry_directory
+                .join(COMPLETION_MARKER_FILE_NAME)
+                .is_file()
+        {
+            return Ok(false);
+        }
+
+        for destination_directory in destination_directories {
+            copy_bindings(&self.entry_directory, destination_directory.as_ref())?;
+        }
+        Ok(true)
+    }
+
+    /// Stores the bindings (all `.rs` files) in `source_directory` in this
+    /// cache entry, replacing any bindings that are already cached.
+    ///
+    /// The bindings are first copied to a temporary directory, which is
+    /// marked as complete and then moved into place. Bindings that are
+    /// already cached are moved aside before they are deleted, so that
+    /// concurrent builds never observe a partially written or partially
+    /// deleted cache entry.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the bindings fail to be copied
+    /// into the cache.
+    pub fn store(&self, source_directory: &Path) -> Result<(), ConfigError> {
+        let temporary_directory = self.sibling_directory("tmp");
+        if temporary_directory.exists() {
+            fs::remove_dir_all(&temporary_directory)?;
+        }
+        fs::create_dir_all(&temporary_directory)?;
+        copy_bindings(source_directory, &temporary_directory)?;
+        fs::write(temporary_directory.join(COMPLETION_MARKER_FILE_NAME), "")?;
+
+        let stale_directory = self.sibling_directory("stale");
+        // Another build may have moved the cached bindings aside concurrently
+        let has_stale_directory = self.entry_directory.exists()
+            && fs::rename(&self.entry_directory, &stale_directory).is_ok();
+
+        if fs::rename(&temporary_directory, &self.entry_directory).is_err() {
+            // Another build stored the same bindings concurrently
+            fs::remove_dir_all(&temporary_directory)?;
+        }
+        if has_stale_directory {
+            fs::remove_dir_all(&stale_directory)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the path of a directory next to this cache entry that is only
+    /// used by the current process, ex. `<entry>.tmp-<pid>`
+    fn sibling_directory(&self, purpose: &str) -> PathBuf {
+        let mut sibling_directory = self.entry_directory.clone().into_os_string();
+        sibling_directory.push(format!(".{purpose}-{}", std::process::id()));
+        PathBuf::from(sibling_directory)
+    }
+}
+
+/// Freshness stamps of the modules of bindings that a build script generates
+/// into an output directory.
+///
+/// The stamp of a module is a hash of the WDK version, the `libclang` version,
+/// the [`Config`], the enabled Cargo features, the versions of `wdk-build` and
+/// `bindgen`, the build script and the input files of that module. A module only needs to be regenerated if its stamp
+/// differs from the one stored when it was last generated.
+#[derive(Debug)]
+pub struct BindingsStamps {
+    directory: PathBuf,
+    build_inputs_hasher: Fnv1aHasher,
+    regenerate: bool,
+}
+
+impl BindingsStamps {
+    /// Returns the stamps of bindings generated by the current build script
+    /// for `config`, which are stored in `directory` (ex. a subdirectory of
+    /// `OUT_DIR`).
+    ///
+    /// This must be called from a Cargo build script.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the WDK version cannot be
+    /// determined, or if the build script cannot be read.
+    pub fn new(config: &Config, directory: impl Into<PathBuf>) -> Result<Self, ConfigError> {
+        println!("cargo::rerun-if-env-changed={REGENERATE_BINDINGS_ENV_VAR}");
+
+        let mut build_inputs_hasher = build_inputs_hasher(config)?;
+        build_inputs_hasher.write(bindgen::clang_version().full.as_bytes());
+        build_inputs_hasher.write(
+            utils::get_windows_sdk_version(
+                &config.wdk_content_root.join("Include"),
+                config.wdk_version.as_deref(),
+            )?
+            .as_bytes(),
+        );
+
+        Ok(Self {
+            directory: directory.into(),
+            build_inputs_hasher,
+            regenerate: env::var_os(REGENERATE_BINDINGS_ENV_VAR)
+                .is_some_and(|value| !value.is_empty()),
+        })
+    }
+
+    /// Returns the stamp of a module of bindings generated from
+    /// `input_files`, besides the WDK headers
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if any of `input_files` cannot be
+    /// read.
+    pub fn stamp<P: AsRef<Path>>(
+        &self,
+        input_files: impl IntoIterator<Item = P>,
+    ) -> Result<u64, ConfigError> {
+        let mut hasher = self.build_inputs_hasher.clone();
+        for input_file in input_files {
+            hasher.write(&fs::read(input_file)?);
+        }
+        Ok(hasher.finish())
+    }
+
+    /// Returns `true` if the module named `module_name` was last generated
+    /// with `stamp`, unless bindings must be regenerated because
+    /// [`REGENERATE_BINDINGS_ENV_VAR`] is set
+    #[must_use]
+    pub fn is_fresh(&self, module_name: &str, stamp: u64) -> bool {
+        !self.regenerate
+            && fs::read_to_string(self.stamp_path(module_name))
+                .is_ok_and(|stored_stamp| stored_stamp == format!("{stamp:016x}"))
+    }
+
+    /// Records that the module named `module_name` was generated with `stamp`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the stamp fails to be written.
+    pub fn store(&self, module_name: &str, stamp: u64) -> Result<(), ConfigError> {
+        fs::create_dir_all(&self.directory)?;
+        Ok(fs::write(
+            self.stamp_path(module_name),
+            format!("{stamp:016x}"),
+        )?)
+    }
+
+    /// Returns the path of the stamp of the module named `module_name`
+    fn stamp_path(&self, module_name: &str) -> PathBuf {
+        self.directory.join(format!("{module_name}.stamp"))
+    }
+}
+
+/// Returns a hasher over the inputs that all bindings generated by the current
+/// build script for `config` depend on
+fn build_inputs_hasher(config: &Config) -> Result<Fnv1aHasher, ConfigError> {
+    let mut hasher = Fnv1aHasher::new();
+    // The location of the WDK differs between machines, and the WDK version is part
+    // of the key already
+    let mut config = serde_json::to_value(config).map_err(ExportError::from)?;
+    if let Some(config) = config.as_object_mut() {
+        config.remove("wdk_content_root");
+    }
+    hasher.write(config.to_string().as_bytes());
+    hasher.write(env::var("TARGET").unwrap_or_default().as_bytes());
+    hasher.write(enabled_cargo_features().join(",").as_bytes());
+    hasher.write(env!("CARGO_PKG_VERSION").as_bytes());
+    hasher.write(bindgen_version_header().as_bytes());
+    // The bindgen configuration is part of the build script, which can only change
+    // without a version bump of its package if it is built from source
+    hasher.write(env::var("CARGO_PKG_NAME").unwrap_or_default().as_bytes());
+    hasher.write(env::var("CARGO_PKG_VERSION").unwrap_or_default().as_bytes());
+    if let Ok(manifest_directory) = env::var("CARGO_MANIFEST_DIR") {
+        let build_script_path = Path::new(&manifest_directory).join("build.rs");
+        if build_script_path.is_file() {
+            hasher.write(&fs::read(build_script_path)?);
+        }
+    }
+    Ok(hasher)
+}
+
+/// Returns the header comment of the bindings generated by the version of
+/// `bindgen` that `wdk-build` is built with (ex. `/* automatically generated by
+/// rust-bindgen 0.69.5 */`), which contains its version. `bindgen` does not
+/// expose its version otherwise.
+fn bindgen_version_header() -> String {
+    bindgen::Builder::default()
+        .header_contents("bindgen-version.h", "")
+        .generate()
+        .map(|bindings| bindings.to_string())
+        .unwrap_or_default()
+}
+
+/// Returns the root directory of the bindings cache, or [`None`] if the cache
+/// is disabled
+fn cache_root() -> Option<PathBuf> {
+    if let Some(cache_root) = env::var_os(BINDINGS_CACHE_DIR_ENV_VAR) {
+        return (!cache_root.is_empty()).then(|| PathBuf::from(cache_root));
+    }
+
+    env::var_os("LOCALAPPDATA").map(|local_app_data| {
+        PathBuf::from(local_app_data)
+            .join(DEFAULT_CACHE_DIRECTORY_NAME)
+            .join("bindings-cache")
+    })
+}
+
+/// Returns the sorted names of the Cargo features enabled for the crate whose
+/// build script is running
+fn enabled_cargo_features() -> Vec<String> {
+    let mut enabled_cargo_features = env::vars()
+        .filter_map(|(key, _)| key.strip_prefix("CARGO_FEATURE_").map(str::to_string))
+        .collect::<Vec<_>>();
+    enabled_cargo_features.sort();
+    enabled_cargo_features
+}
+
+/// Copies all `.rs` files in `source_directory` to `destination_directory`
+fn copy_bindings(source_directory: &Path, destination_directory: &Path) -> Result<(), ConfigError> {
+    fs::create_dir_all(destination_directory)?;
+    for entry in fs::read_dir(source_directory)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|extension| extension == "rs") {
+            fs::copy(
+                &path,
+                destination_directory.join(
+                    path.file_name()
+                        .expect("path returned by read_dir should have a file name"),
+                ),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// 64-bit FNV-1a hasher. This is used instead of
+/// [`std::collections::hash_map::DefaultHasher`], since the cache key must be
+/// stable across Rust releases.
+#[derive(Clone, Debug)]
+struct Fnv1aHasher(u64);
+
+impl Fnv1aHasher {
+    const OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01B3;
+
+    const fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+        // Separate consecutive writes, so that moving bytes between them changes
+        // the hash
+        self.0 ^= 0xFF;
+        self.0 = self.0.wrapping_mul(Self::PRIME);
+    }
+
+    const fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv1a_hash_is_stable() {
+        let mut hasher = Fnv1aHasher::new();
+        assert_eq!(hasher.finish(), Fnv1aHasher::OFFSET_BASIS);
+
+        hasher.write(b"wdk");
+        let mut other_hasher = Fnv1aHasher::new();
+        other_hasher.write(b"wdk");
+        assert_eq!(hasher.finish(), other_hasher.finish());
+    }
+
+    #[test]
+    fn fnv1a_hash_separates_writes() {
+        let mut hasher = Fnv1aHasher::new();
+        hasher.write(b"wd");
+        hasher.write(b"k");
+
+        let mut other_hasher = Fnv1aHasher::new();
+        other_hasher.write(b"w");
+        other_hasher.write(b"dk");
+
+        assert_ne!(hasher.finish(), other_hasher.finish());
+    }
+
+    #[test]
+    fn store_and_restore_bindings() {
+        let test_directory = env::temp_dir().join(format!(
+            "wdk-build-bindings-cache-test-{}",
+            std::process::id()
+        ));
+        let source_directory = test_directory.join("source");
+        let destination_directory = test_directory.join("destination");
+        fs::create_dir_all(&source_directory).unwrap();
+        fs::write(source_directory.join("types.rs"), "pub type ULONG = u32;").unwrap();
+        fs::write(source_directory.join("output.txt"), "not bindings").unwrap();
+
+        let bindings_cache = BindingsCache {
+            entry_directory: test_directory.join("cache").join("entry"),
+            regenerate: false,
+        };
+        assert!(!bindings_cache.restore(&[&destination_directory]).unwrap());
+
+        // Entries that are not marked as complete (ex. because another build is
+        // still storing them) are never restored
+        fs::create_dir_all(bindings_cache.entry_directory()).unwrap();
+        assert!(!bindings_cache.restore(&[&destination_directory]).unwrap());
+
+        bindings_cache.store(&source_directory).unwrap();
+        assert!(bindings_cache.restore(&[&destination_directory]).unwrap());
+        assert_eq!(
+            fs::read_to_string(destination_directory.join("types.rs")).unwrap(),
+            "pub type ULONG = u32;"
+        );
+        assert!(!destination_directory.join("output.txt").exists());
+
+        // Storing again replaces the cached bindings without leaving stale entries
+        // behind
+        fs::write(source_directory.join("types.rs"), "pub type ULONG = u64;").unwrap();
+        bindings_cache.store(&source_directory).unwrap();
+        assert!(bindings_cache.restore(&[&destination_directory]).unwrap());
+        assert_eq!(
+            fs::read_to_string(destination_directory.join("types.rs")).unwrap(),
+            "pub type ULONG = u64;"
+        );
+        assert_eq!(
+            fs::read_dir(test_directory.join("cache")).unwrap().count(),
+            1
+        );
+
+        let regenerating_bindings_cache = BindingsCache {
+            regenerate: true,
+            ..bindings_cache
+        };
+        assert!(
+            !regenerating_bindings_cache
+                .restore(&[&destination_directory])
+                .unwrap()
+        );
+
+        fs::remove_dir_all(&test_directory).unwrap();
+    }
+
+    #[test]
+    fn bindings_stamps_are_fresh_until_inputs_change() {
+        let test_directory = env::temp_dir().join(format!(
+            "wdk-build-bindings-stamps-test-{}",
+            std::process::id()
+        ));
+        let input_file = test_directory.join("acpi-input.h");
+        fs::create_dir_all(&test_directory).unwrap();
+        fs::write(&input_file, "#include \"acpiioct.h\"").unwrap();
+
+        let bindings_stamps = BindingsStamps {
+            directory: test_directory.join("stamps"),
+            build_inputs_hasher: Fnv1aHasher::new(),
+            regenerate: false,
+        };
+        let stamp = bindings_stamps.stamp([&input_file]).unwrap();
+        assert!(!bindings_stamps.is_fresh("acpi.rs", stamp));
+
+        bindings_stamps.store("acpi.rs", stamp).unwrap();
+        assert!(bindings_stamps.is_fresh("acpi.rs", stamp));
+        assert!(!bindings_stamps.is_fresh("cng.rs", stamp));
+
+        fs::write(&input_file, "#include \"acpitabl.h\"").unwrap();
+        let changed_stamp = bindings_stamps.stamp([&input_file]).unwrap();
+        assert!(!bindings_stamps.is_fresh("acpi.rs", changed_stamp));
+
+        let regenerating_bindings_stamps = BindingsStamps {
+            regenerate: true,
+            ..bindings_stamps
+        };
+        assert!(!regenerating_bindings_stamps.is_fresh("acpi.rs", stamp));
+
+        fs::remove_dir_all(&test_directory).unwrap();
+    }
+}
@@ -8,15 +8,30 @@
 //! provide a CLI very close to cargo's own, but only exposes the arguments
 //! supported by `rust-driver-makefile.toml`.
 
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
 
 use cargo_metadata::MetadataCommand;
 use clap::{Args, Parser};
 
 use crate::{
-    utils::{detect_wdk_content_root, get_latest_windows_sdk_version, PathExt},
+    api_validator::{ApiValidationReport, ApiValidator},
+    inf_template::InfTemplateVariables,
+    lints,
+    metadata::WDKMetadata,
+    symbols::{verify_pdb_matches_binary, SymStore},
+    utils::{
+        detect_libclang_directory,
+        detect_wdk_content_root,
+        get_windows_sdk_version,
+        PathExt,
+        LIBCLANG_PATH_ENV_VAR,
+    },
     CPUArchitecture,
     ConfigError,
+    WDK_VERSION_ENV_VAR,
 };
 
 const PATH_ENV_VAR: &str = "Path";
@@ -34,6 +49,41 @@
 const CARGO_MAKE_WORKSPACE_WORKING_DIRECTORY_ENV_VAR: &str =
     "CARGO_MAKE_WORKSPACE_WORKING_DIRECTORY";
 const WDK_BUILD_OUTPUT_DIRECTORY_ENV_VAR: &str = "WDK_BUILD_OUTPUT_DIRECTORY";
+/// The name of the environment variable containing the architecture passed to
+/// `stampinf -a`
+const WDK_BUILD_STAMPINF_ARCHITECTURE_ENV_VAR: &str = "WDK_BUILD_STAMPINF_ARCHITECTURE";
+/// The name of the environment variable containing the OS targets passed to
+/// `inf2cat /os:`
+const WDK_BUILD_INF2CAT_OS_ENV_VAR: &str = "WDK_BUILD_INF2CAT_OS";
+/// The name of the environment variable containing the Windows name of the
+/// target architecture (ex. `x64`), used to locate architecture-specific WDK
+/// files
+const WDK_BUILD_TARGET_ARCHITECTURE_ENV_VAR: &str = "WDK_BUILD_TARGET_ARCHITECTURE";
+/// The name of the environment variable containing additional flags passed to
+/// `infverif`
+const WDK_BUILD_ADDITIONAL_INFVERIF_FLAGS_ENV_VAR: &str = "WDK_BUILD_ADDITIONAL_INFVERIF_FLAGS";
+const CARGO_MAKE_WORKING_DIRECTORY_ENV_VAR: &str = "CARGO_MAKE_WORKING_DIRECTORY";
+const CARGO_MAKE_CRATE_VERSION_ENV_VAR: &str = "CARGO_MAKE_CRATE_VERSION";
+/// The name of the environment variable containing the symbol store (ex. a
+/// symbol share) that [`collect_symbols`] publishes symbols to
+const WDK_BUILD_SYMBOL_STORE_ENV_VAR: &str = "WDK_BUILD_SYMBOL_STORE";
+/// The name of the environment variable containing the absolute path of the
+/// manifest passed via `--manifest-path`, which is used to locate the target
+/// directory of its workspace
+const WDK_BUILD_MANIFEST_PATH_ENV_VAR: &str = "WDK_BUILD_MANIFEST_PATH";
+/// The name of the environment variable containing the `;`-delimited target
+/// triples of a build for multiple targets
+const WDK_BUILD_TARGET_TRIPLES_ENV_VAR: &str = "WDK_BUILD_TARGET_TRIPLES";
+/// The name of the environment variable containing the `;`-delimited build
+/// output directories of a build for multiple targets, in the same order as
+/// [`WDK_BUILD_TARGET_TRIPLES_ENV_VAR`]
+const WDK_BUILD_OUTPUT_DIRECTORIES_ENV_VAR: &str = "WDK_BUILD_OUTPUT_DIRECTORIES";
+/// The name of the environment variable containing the single target that a
+/// build for multiple targets is currently being packaged for
+const WDK_BUILD_SELECTED_TARGET_ENV_VAR: &str = "WDK_BUILD_SELECTED_TARGET";
+
+/// A check run by [`run_static_analysis`], returning whether the check passed
+type StaticAnalysisCheck = fn() -> Result<bool, ConfigError>;
 
 /// `clap` uses an exit code of 2 for usage errors: <https://github.com/clap-rs/clap/blob/14fd853fb9c5b94e371170bbd0ca2bf28ef3abff/clap_builder/src/util/mod.rs#L30C18-L30C28>
 const CLAP_USAGE_EXIT_CODE: i32 = 2;
@@ -97,9 +147,13 @@ struct CompilationOptions {
     )]
     jobs: Option<String>,
 
-    // TODO: support building multiple targets at once
-    #[arg(long, value_name = "TRIPLE", help = "Build for a target triple")]
-    target: Option<String>,
+    #[arg(
+        long = "target",
+        value_name = "TRIPLE",
+        help = "Build for the target triple. Can be specified multiple times to build for \
+                multiple targets"
+    )]
+    targets: Vec<String>,
 
     #[allow(clippy::option_option)] // This is how clap_derive expects "optional value for optional argument" args
     #[arg(
@@ -109,6 +163,13 @@ struct CompilationOptions {
         help = "Timing output formats (unstable) (comma separated): html, json"
     )]
     timings: Option<Option<String>>,
+
+    #[arg(
+        long,
+        value_name = "DIRECTORY",
+        help = "Directory for all generated artifacts"
+    )]
+    target_dir: Option<PathBuf>,
 }
 
 #[derive(Args, Debug)]
@@ -122,6 +183,9 @@ struct ManifestOptions {
 
     #[arg(long, help = "Run without accessing the network")]
     offline: bool,
+
+    #[arg(long, value_name = "PATH", help = "Path to Cargo.toml")]
+    manifest_path: Option<PathBuf>,
 }
 
 impl ParseCargoArg for BaseOptions {
@@ -312,15 +376,7 @@ fn parse_cargo_arg(&self) {
             );
         }
 
-        if let Some(target) = &self.target {
-            println!("CARGO_MAKE_CRATE_TARGET_TRIPLE={target}");
-            append_to_space_delimited_env_var(
-                CARGO_MAKE_CARGO_BUILD_TEST_FLAGS_ENV_VAR,
-                format!("--target {target}").as_str(),
-            );
-        }
-
-        configure_wdf_build_output_dir(&self.target, &cargo_make_cargo_profile);
+        self.configure_targets(&cargo_make_cargo_profile);
 
         if let Some(timings_option) = &self.timings {
             timings_option.as_ref().map_or_else(
@@ -341,6 +397,51 @@ fn parse_cargo_arg(&self) {
     }
 }
 
+impl CompilationOptions {
+    /// Forwards the `--target` and `--target-dir` flags, and configures the
+    /// build output directory and target architecture of the packaging tasks
+    fn configure_targets(&self, cargo_make_cargo_profile: &str) {
+        let targets = selected_targets(&self.targets);
+        for target in &targets {
+            append_to_space_delimited_env_var(
+                CARGO_MAKE_CARGO_BUILD_TEST_FLAGS_ENV_VAR,
+                format!("--target {target}").as_str(),
+            );
+        }
+
+        let target_dir = self
+            .target_dir
+            .as_deref()
+            .map(absolute_from_working_directory);
+        if let Some(target_dir) = &target_dir {
+            append_to_space_delimited_env_var(
+                CARGO_MAKE_CARGO_BUILD_TEST_FLAGS_ENV_VAR,
+                format!("--target-dir {}", target_dir.display()).as_str(),
+            );
+        }
+
+        let cargo_target_directory = cargo_target_directory(target_dir.as_deref());
+        if targets.len() > 1 {
+            configure_multi_target_build(
+                &cargo_target_directory,
+                &targets,
+                cargo_make_cargo_profile,
+            );
+        } else {
+            let target = targets.first().map(String::as_str);
+            if let Some(target) = target {
+                println!("CARGO_MAKE_CRATE_TARGET_TRIPLE={target}");
+            }
+            configure_wdf_build_output_dir(
+                &cargo_target_directory,
+                target,
+                cargo_make_cargo_profile,
+            );
+            configure_target_architecture(target);
+        }
+    }
+}
+
 impl ParseCargoArg for ManifestOptions {
     fn parse_cargo_arg(&self) {
         if self.frozen {
@@ -363,6 +464,15 @@ fn parse_cargo_arg(&self) {
                 "--offline",
             );
         }
+
+        if let Some(manifest_path) = &self.manifest_path {
+            let manifest_path = absolute_from_working_directory(manifest_path);
+            append_to_space_delimited_env_var(
+                CARGO_MAKE_CARGO_BUILD_TEST_FLAGS_ENV_VAR,
+                format!("--manifest-path {}", manifest_path.display()).as_str(),
+            );
+            std::env::set_var(WDK_BUILD_MANIFEST_PATH_ENV_VAR, manifest_path);
+        }
     }
 }
 
@@ -410,19 +520,34 @@ pub fn validate_and_forward_args() {
     command_line_interface.base.parse_cargo_arg();
     command_line_interface.workspace.parse_cargo_arg();
     command_line_interface.features.parse_cargo_arg();
-    command_line_interface.compilation_options.parse_cargo_arg();
+    // The manifest options are parsed before the compilation options, since the
+    // build output directory depends on the workspace of `--manifest-path`
     command_line_interface.manifest_options.parse_cargo_arg();
+    command_line_interface.compilation_options.parse_cargo_arg();
 
     forward_env_var_to_cargo_make(CARGO_MAKE_CARGO_BUILD_TEST_FLAGS_ENV_VAR);
     forward_env_var_to_cargo_make(WDK_BUILD_OUTPUT_DIRECTORY_ENV_VAR);
+    forward_env_var_to_cargo_make(WDK_BUILD_TARGET_TRIPLES_ENV_VAR);
+    forward_env_var_to_cargo_make(WDK_BUILD_OUTPUT_DIRECTORIES_ENV_VAR);
+    forward_env_var_to_cargo_make(WDK_BUILD_STAMPINF_ARCHITECTURE_ENV_VAR);
+    forward_env_var_to_cargo_make(WDK_BUILD_INF2CAT_OS_ENV_VAR);
+    forward_env_var_to_cargo_make(WDK_BUILD_TARGET_ARCHITECTURE_ENV_VAR);
 }
 
-/// Prepends the path variable with the necessary paths to access WDK tools
+/// Prepends the path variable with the necessary paths to access WDK tools, and
+/// forwards it to `cargo-make`.
+///
+/// If `LIBCLANG_PATH` is not already set, it is also set to the LLVM bundled
+/// with the MSVC build tools (ex. in an eWDK), if one is found. The WDK version
+/// pinned in the `wdk` metadata, if any, is forwarded via
+/// [`WDK_VERSION_ENV_VAR`].
 ///
 /// # Errors
 ///
 /// This function returns a [`ConfigError::WDKContentRootDetectionError`] if the
-/// WDK content root directory could not be found.
+/// WDK content root directory could not be found, and a
+/// [`ConfigError::WDKVersionNotFound`] if the WDK version pinned via the
+/// `wdk-version` key of the `wdk` metadata is not installed.
 ///
 /// # Panics
 ///
@@ -430,10 +555,55 @@ pub fn validate_and_forward_args() {
 /// `std::env::consts::ARCH` or if the PATH variable contains non-UTF8
 /// characters.
 pub fn setup_path() -> Result<(), ConfigError> {
-    let Some(wdk_content_root) = detect_wdk_content_root() else {
-        return Err(ConfigError::WDKContentRootDetectionError);
-    };
-    let version = get_latest_windows_sdk_version(&wdk_content_root.join("Lib"))?;
+    let libclang_path_was_set = std::env::var_os(LIBCLANG_PATH_ENV_VAR).is_some();
+
+    configure_wdk_tools_path()?;
+
+    forward_env_var_to_cargo_make(PATH_ENV_VAR);
+    forward_env_var_to_cargo_make(WDK_VERSION_ENV_VAR);
+    if !libclang_path_was_set {
+        forward_env_var_to_cargo_make(LIBCLANG_PATH_ENV_VAR);
+    }
+    Ok(())
+}
+
+/// Prepends the path variable of the current process with the necessary paths
+/// to access WDK tools.
+///
+/// If `LIBCLANG_PATH` is not already set, it is also set to the LLVM bundled
+/// with the MSVC build tools (ex. in an eWDK), if one is found. If a WDK
+/// version is pinned via the `wdk-version` key of the `wdk` metadata,
+/// [`WDK_VERSION_ENV_VAR`] is set to it, so that the bindings of `wdk-sys` are
+/// generated for the same WDK.
+///
+/// Unlike [`setup_path`], nothing is forwarded to `cargo-make`, so this can be
+/// used by tools that execute the driver packaging steps themselves (ex.
+/// `cargo-wdk`).
+///
+/// # Errors
+///
+/// This function returns a [`ConfigError::WDKContentRootDetectionError`] if the
+/// WDK content root directory could not be found, and a
+/// [`ConfigError::WDKVersionNotFound`] if the WDK version pinned via the
+/// `wdk-version` key of the `wdk` metadata is not installed.
+///
+/// # Panics
+///
+/// This function will panic if the CPU architecture cannot be determined from
+/// `std::env::consts::ARCH` or if the PATH variable contains non-UTF8
+/// characters.
+pub fn configure_wdk_tools_path() -> Result<(), ConfigError> {
+    let wdk_content_root = detect_wdk_content_root()?;
+    let wdk_metadata = WDKMetadata::try_from_cargo_metadata(&MetadataCommand::new().exec()?)?;
+    let version = get_windows_sdk_version(
+        &wdk_content_root.join("Lib"),
+        wdk_metadata.wdk_version.as_deref(),
+    )?;
+    // Build scripts of dependencies (ex. wdk-sys) cannot resolve the `wdk` metadata of the
+    // driver, so the pinned version is passed to them via the environment
+    if let Some(wdk_version) = &wdk_metadata.wdk_version {
+        std::env::set_var(WDK_VERSION_ENV_VAR, wdk_version);
+    }
     let host_arch = CPUArchitecture::try_from_cargo_str(std::env::consts::ARCH)
         .expect("The rust standard library should always set std::env::consts::ARCH");
 
@@ -488,7 +658,11 @@ pub fn setup_path() -> Result<(), ConfigError> {
             .expect("arch_specific_wdk_tool_root should only contain valid UTF8"),
     );
 
-    forward_env_var_to_cargo_make(PATH_ENV_VAR);
+    if std::env::var_os(LIBCLANG_PATH_ENV_VAR).is_none() {
+        if let Some(libclang_directory) = detect_libclang_directory() {
+            std::env::set_var(LIBCLANG_PATH_ENV_VAR, libclang_directory);
+        }
+    }
     Ok(())
 }
 
@@ -553,6 +727,335 @@ pub fn copy_to_driver_package_folder<P: AsRef<Path>>(path_to_copy: P) -> Result<
     Ok(())
 }
 
+/// Renders the `.inx` template of the current package into its INF in the WDK
+/// build output directory
+///
+/// The variables referred to by the template are resolved from the package's
+/// version, the target architecture, and the driver model in the `wdk`
+/// metadata of the package. See [`inf_template`](crate::inf_template) for the
+/// supported variables. The INF is only written if its contents change.
+///
+/// # Errors
+///
+/// This function returns:
+/// - [`ConfigError::CargoMetadataError`] if there is an error executing or
+///   parsing `cargo_metadata`
+/// - [`ConfigError::WDKMetadataError`] if the `wdk` metadata of the package
+///   cannot be resolved
+/// - [`ConfigError::InfTemplateError`] if the template cannot be rendered
+/// - [`ConfigError::IoError`] if the WDK build output directory cannot be
+///   created
+///
+/// # Panics
+///
+/// This function will panic if the environment variables set by cargo-make and
+/// the `wdk-build-init` task are not set
+pub fn render_inx_to_output() -> Result<(), ConfigError> {
+    let package_name = get_current_package_name();
+    let cargo_make_working_directory = std::env::var(CARGO_MAKE_WORKING_DIRECTORY_ENV_VAR)
+        .unwrap_or_else(|_| {
+            panic!("{CARGO_MAKE_WORKING_DIRECTORY_ENV_VAR} should be set by cargo-make.")
+        });
+    let version = std::env::var(CARGO_MAKE_CRATE_VERSION_ENV_VAR).unwrap_or_else(|_| {
+        panic!("{CARGO_MAKE_CRATE_VERSION_ENV_VAR} should be set by cargo-make")
+    });
+
+    let mut variables =
+        InfTemplateVariables::new(&package_name, &version, get_wdk_build_target_architecture())?;
+    let wdk_metadata = WDKMetadata::try_from_cargo_metadata(&MetadataCommand::new().exec()?)?;
+    if let Some(driver_model) = wdk_metadata.driver_model {
+        variables = variables.with_driver_config(&driver_model.into());
+    }
+
+    let output_directory = get_wdk_build_output_directory();
+    std::fs::create_dir_all(&output_directory)?;
+    variables.render_file(
+        &Path::new(&cargo_make_working_directory).join(format!("{package_name}.inx")),
+        &output_directory.join(format!("{package_name}.inf")),
+    )?;
+    Ok(())
+}
+
+/// Runs the static analysis checks for the current driver package and prints a
+/// summary of their results. The checks are:
+/// - `clippy`, with all warnings and a set of driver-specific lints denied
+/// - `infverif`, against the stamped INF file of the driver
+/// - `ApiValidator`, against the driver package, to detect calls to APIs that
+///   are not part of the Universal DDIs
+///
+/// All checks are run, even if one of them fails, so that all issues are
+/// reported at once.
+///
+/// # Errors
+///
+/// This function returns a [`ConfigError::StaticAnalysisFailed`] if any of the
+/// checks fail or cannot be run.
+///
+/// # Panics
+///
+/// This function will panic if the environment variables set by cargo-make and
+/// the `wdk-build-init` task are not set
+pub fn run_static_analysis() -> Result<(), ConfigError> {
+    let checks: [(&str, StaticAnalysisCheck); 3] = [
+        ("clippy", run_clippy),
+        ("infverif", run_infverif),
+        ("ApiValidator", check_api_validator),
+    ];
+
+    let mut failed_checks = Vec::new();
+    for (check_name, check) in checks {
+        println!("Running {check_name}...");
+        match check() {
+            Ok(true) => {}
+            Ok(false) => {
+                eprintln!("{check_name} failed");
+                failed_checks.push(check_name.to_string());
+            }
+            Err(error) => {
+                eprintln!("{check_name} could not be run: {error}");
+                failed_checks.push(check_name.to_string());
+            }
+        }
+    }
+
+    println!("Static analysis summary:");
+    for (check_name, _) in checks {
+        let result = if failed_checks
+            .iter()
+            .any(|failed_check| failed_check == check_name)
+        {
+            "FAILED"
+        } else {
+            "passed"
+        };
+        println!("    {check_name}: {result}");
+    }
+
+    if failed_checks.is_empty() {
+        Ok(())
+    } else {
+        Err(ConfigError::StaticAnalysisFailed { failed_checks })
+    }
+}
+
+/// Runs `clippy` on the current package with the driver-specific lints
+/// configured by [`lints`]
+///
+/// This denies floating point arithmetic, stack frames larger than
+/// [`lints::KERNEL_STACK_SIZE_THRESHOLD`] bytes, and calls to the CRT functions
+/// in [`lints::BANNED_CRT_FUNCTIONS`], in addition to all warnings. If the
+/// package has its own `clippy.toml`, it is used instead of the generated one,
+/// so `stack-size-threshold` and `disallowed-methods` should be set in it.
+///
+/// # Errors
+///
+/// This function returns:
+/// - [`ConfigError::IoError`] if the `clippy` configuration cannot be written,
+///   or `clippy` cannot be executed
+/// - [`ConfigError::StaticAnalysisFailed`] if `clippy` reports any errors
+///
+/// # Panics
+///
+/// This function will panic if the `CARGO_MAKE_WORKING_DIRECTORY` or
+/// `WDK_BUILD_OUTPUT_DIRECTORY` environment variables are not set
+pub fn run_driver_lints() -> Result<(), ConfigError> {
+    if run_clippy()? {
+        Ok(())
+    } else {
+        Err(ConfigError::StaticAnalysisFailed {
+            failed_checks: vec!["clippy".to_string()],
+        })
+    }
+}
+
+fn run_clippy() -> Result<bool, ConfigError> {
+    let working_directory = PathBuf::from(
+        std::env::var(CARGO_MAKE_WORKING_DIRECTORY_ENV_VAR).unwrap_or_else(|_| {
+            panic!("{CARGO_MAKE_WORKING_DIRECTORY_ENV_VAR} should be set by cargo-make")
+        }),
+    );
+
+    let mut command = Command::new("cargo");
+    if let Ok(toolchain) = std::env::var(CARGO_MAKE_RUST_DEFAULT_TOOLCHAIN_ENV_VAR) {
+        command.arg(format!("+{toolchain}"));
+    }
+    command.arg("clippy").args(
+        std::env::var(CARGO_MAKE_CARGO_BUILD_TEST_FLAGS_ENV_VAR)
+            .unwrap_or_default()
+            .split_whitespace(),
+    );
+    command.arg("--");
+    for lint in lints::DRIVER_CLIPPY_LINTS {
+        command.args(["-D", lint]);
+    }
+
+    if let Some(clippy_config_path) = lints::find_clippy_config(&working_directory) {
+        println!(
+            "Using {} instead of the driver clippy configuration",
+            clippy_config_path.display()
+        );
+    } else {
+        let clippy_conf_dir = get_wdk_build_output_directory().join("clippy");
+        lints::write_clippy_config(&clippy_conf_dir)?;
+        command.env(lints::CLIPPY_CONF_DIR_ENV_VAR, clippy_conf_dir);
+    }
+
+    Ok(command.current_dir(working_directory).status()?.success())
+}
+
+fn run_infverif() -> Result<bool, ConfigError> {
+    Ok(Command::new("infverif")
+        .args(["/v", "/w"])
+        .args(
+            std::env::var(WDK_BUILD_ADDITIONAL_INFVERIF_FLAGS_ENV_VAR)
+                .unwrap_or_default()
+                .split_whitespace(),
+        )
+        .arg(get_wdk_build_output_directory().join(format!("{}.inf", get_current_package_name())))
+        .status()?
+        .success())
+}
+
+/// Runs `ApiValidator` against the driver package of the current package
+///
+/// This detects calls to APIs that are not part of the Universal DDIs for the
+/// target architecture. The XML report of `ApiValidator` is written to
+/// `<package name>.apivalidator.xml` in the WDK build output directory.
+///
+/// # Errors
+///
+/// This function returns:
+/// - [`ConfigError::WDKContentRootDetectionError`] if the WDK content root
+///   directory could not be found
+/// - [`ConfigError::ApiValidatorError`] if `ApiValidator` fails to run, or if
+///   the driver package calls unsupported APIs
+///
+/// # Panics
+///
+/// This function will panic if the environment variables set by cargo-make and
+/// the `wdk-build-init` task are not set
+pub fn run_api_validator() -> Result<(), ConfigError> {
+    Ok(validate_driver_package_apis()?.ensure_compliant()?)
+}
+
+fn check_api_validator() -> Result<bool, ConfigError> {
+    let report = validate_driver_package_apis()?;
+    for violation in &report.violations {
+        eprintln!("{violation}");
+    }
+    Ok(report.is_compliant())
+}
+
+fn validate_driver_package_apis() -> Result<ApiValidationReport, ConfigError> {
+    let wdk_content_root = detect_wdk_content_root()?;
+    let wdk_metadata = WDKMetadata::try_from_cargo_metadata(&MetadataCommand::new().exec()?)?;
+    let version = get_windows_sdk_version(
+        &wdk_content_root.join("Lib"),
+        wdk_metadata.wdk_version.as_deref(),
+    )?;
+    let target_arch = get_wdk_build_target_architecture();
+
+    let package_name = get_current_package_name();
+    let wdk_build_output_directory = get_wdk_build_output_directory();
+    Ok(
+        ApiValidator::new(&wdk_content_root, &version, target_arch).validate(
+            &wdk_build_output_directory.join(format!("{package_name}_package")),
+            &wdk_build_output_directory.join(format!("{package_name}.apivalidator.xml")),
+        )?,
+    )
+}
+
+/// Returns the target architecture of the current cargo-make flow, as
+/// configured by the `wdk-build-init` task
+fn get_wdk_build_target_architecture() -> CPUArchitecture {
+    let target_arch = std::env::var(WDK_BUILD_TARGET_ARCHITECTURE_ENV_VAR).unwrap_or_else(|_| {
+        panic!("{WDK_BUILD_TARGET_ARCHITECTURE_ENV_VAR} should be set by the wdk-build-init task")
+    });
+    match target_arch.as_str() {
+        "x64" => CPUArchitecture::AMD64,
+        "ARM64" => CPUArchitecture::ARM64,
+        _ => panic!("{WDK_BUILD_TARGET_ARCHITECTURE_ENV_VAR} should be set to x64 or ARM64"),
+    }
+}
+
+/// Collects the symbols of the current driver into the `<package
+/// name>_symbols` folder of the WDK build output directory, and publishes them
+/// to a symbol store if one is configured
+///
+/// The `.pdb` is verified to match the `.sys` before anything is collected,
+/// so that stale symbols are never archived. The `.sys`, `.pdb` and, if the
+/// driver is linked with `/MAP`, the `.map` of the driver are collected. If
+/// the `WDK_BUILD_SYMBOL_STORE` environment variable is set, the `.sys` and
+/// `.pdb` are then added to the symbol store it points to via `symstore.exe`,
+/// with the package name and version as the product and version of the
+/// transaction.
+///
+/// # Errors
+///
+/// This function returns:
+/// - [`ConfigError::SymbolsError`] if the `.pdb` does not match the `.sys`, or
+///   `symstore.exe` fails to publish them
+/// - [`ConfigError::IoError`] if the symbols cannot be copied
+/// - [`ConfigError::WDKContentRootDetectionError`] if a symbol store is
+///   configured, and the WDK content root directory could not be found
+///
+/// # Panics
+///
+/// This function will panic if the environment variables set by cargo-make and
+/// the `wdk-build-init` task are not set
+pub fn collect_symbols() -> Result<(), ConfigError> {
+    let package_name = get_current_package_name();
+    let wdk_build_output_directory = get_wdk_build_output_directory();
+    let sys_file = wdk_build_output_directory.join(format!("{package_name}.sys"));
+    let pdb_file = wdk_build_output_directory.join(format!("{package_name}.pdb"));
+    let map_file = wdk_build_output_directory.join(format!("deps/{package_name}.map"));
+
+    let signature = verify_pdb_matches_binary(&sys_file, &pdb_file)?;
+    println!(
+        "{} matches {} with signature {signature}",
+        pdb_file.display(),
+        sys_file.display()
+    );
+
+    let symbols_folder_path = wdk_build_output_directory.join(format!("{package_name}_symbols"));
+    if !symbols_folder_path.exists() {
+        std::fs::create_dir(&symbols_folder_path)?;
+    }
+    let mut symbol_files = vec![&sys_file, &pdb_file];
+    // The `.map` is only generated if the driver is linked with `/MAP`
+    if map_file.exists() {
+        symbol_files.push(&map_file);
+    }
+    for file in symbol_files {
+        std::fs::copy(
+            file,
+            symbols_folder_path.join(
+                file.file_name()
+                    .expect("symbol files should always end with a valid file name"),
+            ),
+        )?;
+    }
+
+    let Some(symbol_store) = std::env::var_os(WDK_BUILD_SYMBOL_STORE_ENV_VAR)
+        .filter(|symbol_store| !symbol_store.is_empty())
+    else {
+        return Ok(());
+    };
+    let version = std::env::var(CARGO_MAKE_CRATE_VERSION_ENV_VAR).unwrap_or_else(|_| {
+        panic!("{CARGO_MAKE_CRATE_VERSION_ENV_VAR} should be set by cargo-make")
+    });
+    println!(
+        "Publishing symbols to {}...",
+        Path::new(&symbol_store).display()
+    );
+    Ok(SymStore::new(&detect_wdk_content_root()?).add(
+        &[&sys_file, &pdb_file],
+        Path::new(&symbol_store),
+        &package_name,
+        &version,
+    )?)
+}
+
 /// Symlinks `rust-driver-toolchain.toml` to the `target` folder where it can be
 /// extended from a `Makefile.toml`. This is necessary so that paths in the
 /// `rust-driver-toolchain.toml` can to be relative to
@@ -624,45 +1127,174 @@ pub fn load_rust_driver_makefile() -> Result<(), ConfigError> {
     Ok(())
 }
 
-fn configure_wdf_build_output_dir(target_arg: &Option<String>, cargo_make_cargo_profile: &str) {
-    let cargo_make_crate_custom_triple_target_directory = std::env::var(
-        CARGO_MAKE_CRATE_CUSTOM_TRIPLE_TARGET_DIRECTORY_ENV_VAR,
-    )
-    .unwrap_or_else(|_| {
-        panic!(
-            "{CARGO_MAKE_CRATE_CUSTOM_TRIPLE_TARGET_DIRECTORY_ENV_VAR} should be set by \
-             cargo-make."
-        )
-    });
+fn configure_wdf_build_output_dir(
+    cargo_target_directory: &Path,
+    target_arg: Option<&str>,
+    cargo_make_cargo_profile: &str,
+) {
+    std::env::set_var(
+        WDK_BUILD_OUTPUT_DIRECTORY_ENV_VAR,
+        wdf_build_output_dir(cargo_target_directory, target_arg, cargo_make_cargo_profile),
+    );
+}
 
-    let wdk_build_output_directory = {
-        let mut output_dir = cargo_make_crate_custom_triple_target_directory;
+/// Returns the directory that cargo places the artifacts of a build for
+/// `target_arg` with the `cargo_make_cargo_profile` profile in
+fn wdf_build_output_dir(
+    cargo_target_directory: &Path,
+    target_arg: Option<&str>,
+    cargo_make_cargo_profile: &str,
+) -> PathBuf {
+    let mut output_dir = cargo_target_directory.to_path_buf();
+
+    // Providing the "--target" flag causes the build output to go into a subdirectory: https://doc.rust-lang.org/cargo/guide/build-cache.html#build-cache
+    if let Some(target) = target_arg {
+        output_dir.push(target);
+    }
 
-        // Providing the "--target" flag causes the build output to go into a subdirectory: https://doc.rust-lang.org/cargo/guide/build-cache.html#build-cache
-        if let Some(target) = target_arg {
-            output_dir += "/";
-            output_dir += target;
-        }
+    if cargo_make_cargo_profile == "dev" {
+        // Cargo puts "dev" profile builds in the "debug" target folder: https://doc.rust-lang.org/cargo/guide/build-cache.html#build-cache.
+        // This also supports cargo-make profile of "development" since cargo-make maps
+        // CARGO_MAKE_PROFILE value of "development" to CARGO_MAKE_CARGO_PROFILE of
+        // "dev".
+        output_dir.push("debug");
+    } else {
+        output_dir.push(cargo_make_cargo_profile);
+    }
 
-        if cargo_make_cargo_profile == "dev" {
-            // Cargo puts "dev" profile builds in the "debug" target folder: https://doc.rust-lang.org/cargo/guide/build-cache.html#build-cache.
-            // This also supports cargo-make profile of "development" since cargo-make maps
-            // CARGO_MAKE_PROFILE value of "development" to CARGO_MAKE_CARGO_PROFILE of
-            // "dev".
-            output_dir += "/debug";
-        } else {
-            output_dir += "/";
-            output_dir += cargo_make_cargo_profile;
-        }
+    output_dir
+}
 
-        output_dir
-    };
+/// Sets the env vars that `rust-driver-makefile.toml` iterates over to package
+/// the driver once per target: the `;`-delimited target triples, and the
+/// build output directory of each of them. Unsupported targets are rejected.
+fn configure_multi_target_build(
+    cargo_target_directory: &Path,
+    targets: &[String],
+    cargo_make_cargo_profile: &str,
+) {
+    for target in targets {
+        target_architecture(Some(target));
+    }
+
+    std::env::set_var(WDK_BUILD_TARGET_TRIPLES_ENV_VAR, targets.join(";"));
     std::env::set_var(
-        WDK_BUILD_OUTPUT_DIRECTORY_ENV_VAR,
-        wdk_build_output_directory,
+        WDK_BUILD_OUTPUT_DIRECTORIES_ENV_VAR,
+        targets
+            .iter()
+            .map(|target| {
+                wdf_build_output_dir(
+                    cargo_target_directory,
+                    Some(target),
+                    cargo_make_cargo_profile,
+                )
+                .display()
+                .to_string()
+            })
+            .collect::<Vec<_>>()
+            .join(";"),
     );
 }
 
+/// Returns the targets to build for. When packaging a build for multiple
+/// targets, `rust-driver-makefile.toml` reinvokes cargo-make once per target
+/// with `WDK_BUILD_SELECTED_TARGET` set, in which case only that target is
+/// built and packaged.
+fn selected_targets(targets: &[String]) -> Vec<String> {
+    match std::env::var(WDK_BUILD_SELECTED_TARGET_ENV_VAR) {
+        Ok(selected_target) if targets.contains(&selected_target) => vec![selected_target],
+        _ => targets.to_vec(),
+    }
+}
+
+/// Returns the directory that cargo places build artifacts in. This is the
+/// `--target-dir` if one is provided, the target directory of the workspace of
+/// `--manifest-path` if one is provided, and the target directory computed by
+/// cargo-make otherwise.
+fn cargo_target_directory(target_dir_arg: Option<&Path>) -> PathBuf {
+    if let Some(target_dir) = target_dir_arg {
+        return target_dir.to_path_buf();
+    }
+
+    if let Some(manifest_path) = std::env::var_os(WDK_BUILD_MANIFEST_PATH_ENV_VAR) {
+        let cargo_metadata = MetadataCommand::new()
+            .manifest_path(&manifest_path)
+            .no_deps()
+            .exec()
+            .unwrap_or_else(|error| {
+                eprintln!(
+                    "Failed to read the manifest at {}: {error}",
+                    Path::new(&manifest_path).display()
+                );
+                std::process::exit(CLAP_USAGE_EXIT_CODE);
+            });
+        return cargo_metadata.target_directory.into_std_path_buf();
+    }
+
+    std::env::var(CARGO_MAKE_CRATE_CUSTOM_TRIPLE_TARGET_DIRECTORY_ENV_VAR)
+        .unwrap_or_else(|_| {
+            panic!(
+                "{CARGO_MAKE_CRATE_CUSTOM_TRIPLE_TARGET_DIRECTORY_ENV_VAR} should be set by \
+                 cargo-make."
+            )
+        })
+        .into()
+}
+
+/// Resolves `path` relative to the directory that cargo-make was invoked from,
+/// which is the directory that cargo resolves relative path arguments from
+fn absolute_from_working_directory(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+
+    let cargo_make_working_directory = std::env::var(CARGO_MAKE_WORKING_DIRECTORY_ENV_VAR)
+        .unwrap_or_else(|_| {
+            panic!("{CARGO_MAKE_WORKING_DIRECTORY_ENV_VAR} should be set by cargo-make.")
+        });
+    Path::new(&cargo_make_working_directory).join(path)
+}
+
+/// Sets the architecture-specific arguments of the driver packaging tools based
+/// on the `--target` triple, or the host architecture if no target is provided
+fn configure_target_architecture(target_arg: Option<&str>) {
+    let target_arch = target_architecture(target_arg);
+
+    std::env::set_var(
+        WDK_BUILD_STAMPINF_ARCHITECTURE_ENV_VAR,
+        target_arch.as_stampinf_str(),
+    );
+    std::env::set_var(
+        WDK_BUILD_INF2CAT_OS_ENV_VAR,
+        target_arch.as_inf2cat_os_str(),
+    );
+    std::env::set_var(
+        WDK_BUILD_TARGET_ARCHITECTURE_ENV_VAR,
+        target_arch.as_windows_str(),
+    );
+}
+
+/// Returns the architecture of the `--target` triple, or the host architecture
+/// if no target is provided. Exits with a usage error if the target is not
+/// supported.
+fn target_architecture(target_arg: Option<&str>) -> CPUArchitecture {
+    target_arg.map_or_else(
+        || {
+            CPUArchitecture::try_from_cargo_str(std::env::consts::ARCH)
+                .expect("The rust standard library should always set std::env::consts::ARCH")
+        },
+        |target| {
+            CPUArchitecture::try_from_target_triple(target).unwrap_or_else(|| {
+                eprintln!(
+                    "Target triple {target} is not supported. Supported targets are \
+                     x86_64-pc-windows-msvc and aarch64-pc-windows-msvc."
+                );
+                std::process::exit(CLAP_USAGE_EXIT_CODE);
+            })
+        },
+    )
+}
+
 fn append_to_space_delimited_env_var<S, T>(env_var_name: S, string_to_append: T)
 where
     S: AsRef<str>,
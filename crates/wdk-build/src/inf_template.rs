@@ -0,0 +1,416 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! This module provides functions to render the `.inx` template of a driver
+//! into its INF file.
+//!
+//! An `.inx` file can refer to variables as `${NAME}`, which are replaced
+//! with values resolved from the configuration of the driver when the INF is
+//! generated, so that the INF does not need to be kept in sync with the
+//! crate's configuration by hand:
+//!
+//! ```ini
+//! [SampleKMDFDriver.NT.Wdf]
+//! KmdfService = SampleKMDFDriver, SampleKMDFDriver_wdfsect
+//!
+//! [SampleKMDFDriver_wdfsect]
+//! KmdfLibraryVersion = ${KMDF_VERSION}
+//! ```
+//!
+//! The following variables are defined:
+//!
+//! | Variable         | Value                                                        |
+//! | ---------------- | ------------------------------------------------------------ |
+//! | `DRIVER_NAME`    | File name of the driver binary (ex. `sample_kmdf_driver`)    |
+//! | `DRIVER_VERSION` | Package version as an INF version (ex. `1.2.3.0` for `1.2.3`)|
+//! | `ARCH`           | Target architecture as expected by `stampinf` (ex. `amd64`)  |
+//! | `KMDF_VERSION`   | KMDF version of KMDF drivers (ex. `1.33`)                    |
+//! | `UMDF_VERSION`   | UMDF version of UMDF drivers (ex. `2.33.0`)                  |
+//!
+//! Referring to a variable that is not defined (ex. `KMDF_VERSION` in a UMDF
+//! driver) is an error, so that a typo never ends up in the INF. `$${` is
+//! rendered as a literal `${`, and other uses of `$` (ex. the `$ARCH$`
+//! placeholder that `stampinf` replaces) are left as is.
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! use wdk_build::{
+//!     inf_template::InfTemplateVariables,
+//!     CPUArchitecture,
+//!     DriverConfig,
+//!     KMDFConfig,
+//! };
+//!
+//! let variables =
+//!     InfTemplateVariables::new("sample_kmdf_driver", "0.1.0", CPUArchitecture::AMD64)?
+//!         .with_driver_config(&DriverConfig::KMDF(KMDFConfig {
+//!             kmdf_version_major: 1,
+//!             kmdf_version_minor: 33,
+//!         }));
+//! variables.render_file(
+//!     Path::new("sample_kmdf_driver.inx"),
+//!     Path::new(r"target\debug\sample_kmdf_driver.inf"),
+//! )?;
+//! # Ok::<(), wdk_build::inf_template::InfTemplateError>(())
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use cargo_metadata::semver::Version;
+use thiserror::Error;
+
+use crate::{CPUArchitecture, DriverConfig};
+
+/// Start of a variable reference in an `.inx` file
+const VARIABLE_START: &str = "${";
+/// End of a variable reference in an `.inx` file
+const VARIABLE_END: char = '}';
+/// Escaped form of [`VARIABLE_START`], which is rendered as a literal `${`
+const ESCAPED_VARIABLE_START: &str = "$${";
+
+/// Errors that could result from rendering an `.inx` template
+#[derive(Debug, Error)]
+pub enum InfTemplateError {
+    /// Error returned when the template cannot be read, or the INF cannot be
+    /// written
+    #[error("failed to access {}", path.display())]
+    IoError {
+        /// Path of the file that could not be accessed
+        path: PathBuf,
+        /// Underlying error returned by the file operation
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// Error returned when the package version cannot be represented as an
+    /// INF version, whose components must each fit in 16 bits
+    #[error("package version {version} cannot be represented as an INF driver version")]
+    InvalidDriverVersion {
+        /// Package version that could not be converted
+        version: String,
+    },
+
+    /// Error returned when the template refers to a variable that is not
+    /// defined
+    #[error(
+        "line {line} of the INF template refers to undefined variable ${{{name}}}. Defined \
+         variables: {defined_variables:?}"
+    )]
+    UndefinedVariable {
+        /// Line of the template that refers to the variable, starting at 1
+        line: usize,
+        /// Name of the variable
+        name: String,
+        /// Names of the variables that are defined
+        defined_variables: Vec<String>,
+    },
+
+    /// Error returned when a `${` in the template is not closed by a `}` on
+    /// the same line
+    #[error("line {line} of the INF template has an unterminated variable reference")]
+    UnterminatedVariable {
+        /// Line of the template with the unterminated variable reference,
+        /// starting at 1
+        line: usize,
+    },
+}
+
+/// Variables that can be referred to from an `.inx` template, and their
+/// values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfTemplateVariables {
+    /// Values of the variables, by name. This is ordered so that error
+    /// messages are deterministic.
+    variables: BTreeMap<&'static str, String>,
+}
+
+impl InfTemplateVariables {
+    /// Creates the variables of a driver named `driver_name`, whose package
+    /// version is `driver_version`, for `target_architecture`. The variables
+    /// that depend on the driver model are added via
+    /// [`InfTemplateVariables::with_driver_config`].
+    ///
+    /// # Errors
+    ///
+    /// This function returns [`InfTemplateError::InvalidDriverVersion`] if
+    /// `driver_version` is not a valid semantic version, or any of its major,
+    /// minor or patch components does not fit in 16 bits
+    pub fn new(
+        driver_name: &str,
+        driver_version: &str,
+        target_architecture: CPUArchitecture,
+    ) -> Result<Self, InfTemplateError> {
+        let variables = BTreeMap::from([
+            ("DRIVER_NAME", driver_name.to_string()),
+            ("DRIVER_VERSION", inf_driver_version(driver_version)?),
+            ("ARCH", target_architecture.as_stampinf_str().to_string()),
+        ]);
+        Ok(Self { variables })
+    }
+
+    /// Adds the variables that depend on the driver model of `driver_config`
+    /// (ie. `KMDF_VERSION` for KMDF drivers and `UMDF_VERSION` for UMDF
+    /// drivers)
+    #[must_use]
+    pub fn with_driver_config(mut self, driver_config: &DriverConfig) -> Self {
+        match driver_config {
+            DriverConfig::WDM() => {}
+            DriverConfig::KMDF(kmdf_config) => {
+                self.variables.insert(
+                    "KMDF_VERSION",
+                    format!(
+                        "{}.{}",
+                        kmdf_config.kmdf_version_major, kmdf_config.kmdf_version_minor
+                    ),
+                );
+            }
+            DriverConfig::UMDF(umdf_config) => {
+                self.variables.insert(
+                    "UMDF_VERSION",
+                    format!(
+                        "{}.{}.0",
+                        umdf_config.umdf_version_major, umdf_config.umdf_version_minor
+                    ),
+                );
+            }
+        }
+        self
+    }
+
+    /// Returns the value of the variable called `name`, if it is defined
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.variables.get(name).map(String::as_str)
+    }
+
+    /// Renders `template` by replacing every `${NAME}` with the value of the
+    /// variable called `NAME`
+    ///
+    /// # Errors
+    ///
+    /// This function returns:
+    /// - [`InfTemplateError::UndefinedVariable`] if `template` refers to a
+    ///   variable that is not defined
+    /// - [`InfTemplateError::UnterminatedVariable`] if a `${` in `template` is
+    ///   not closed on the same line
+    pub fn render(&self, template: &str) -> Result<String, InfTemplateError> {
+        let mut rendered = String::with_capacity(template.len());
+        for (line_index, line) in template.split_inclusive('\n').enumerate() {
+            let mut remaining = line;
+            while let Some(dollar_index) = remaining.find('$') {
+                rendered.push_str(&remaining[..dollar_index]);
+                remaining = &remaining[dollar_index..];
+
+                if let Some(after_escape) = remaining.strip_prefix(ESCAPED_VARIABLE_START) {
+                    rendered.push_str(VARIABLE_START);
+                    remaining = after_escape;
+                } else if let Some(after_start) = remaining.strip_prefix(VARIABLE_START) {
+                    let (name, after_end) = after_start.split_once(VARIABLE_END).ok_or(
+                        InfTemplateError::UnterminatedVariable {
+                            line: line_index + 1,
+                        },
+                    )?;
+                    let value =
+                        self.get(name)
+                            .ok_or_else(|| InfTemplateError::UndefinedVariable {
+                                line: line_index + 1,
+                                name: name.to_string(),
+                                defined_variables: self
+                                    .variables
+                                    .keys()
+                                    .map(ToString::to_string)
+                                    .collect(),
+                            })?;
+                    rendered.push_str(value);
+                    remaining = after_end;
+                } else {
+                    rendered.push('$');
+                    remaining = &remaining[1..];
+                }
+            }
+            rendered.push_str(remaining);
+        }
+        Ok(rendered)
+    }
+
+    /// Renders the template at `template_path` into `output_path`. The output
+    /// is only written if its contents change, so that its modification time
+    /// (and therefore the incremental state of the steps that consume it) is
+    /// preserved across builds with the same configuration. Returns whether
+    /// the output was written.
+    ///
+    /// # Errors
+    ///
+    /// This function returns:
+    /// - [`InfTemplateError::IoError`] if the template cannot be read, or the
+    ///   output cannot be written
+    /// - any error returned by [`InfTemplateVariables::render`]
+    pub fn render_file(
+        &self,
+        template_path: &Path,
+        output_path: &Path,
+    ) -> Result<bool, InfTemplateError> {
+        let template =
+            std::fs::read_to_string(template_path).map_err(|source| InfTemplateError::IoError {
+                path: template_path.to_path_buf(),
+                source,
+            })?;
+        let rendered = self.render(&template)?;
+
+        if std::fs::read_to_string(output_path).is_ok_and(|existing| existing == rendered) {
+            return Ok(false);
+        }
+        std::fs::write(output_path, rendered).map_err(|source| InfTemplateError::IoError {
+            path: output_path.to_path_buf(),
+            source,
+        })?;
+        Ok(true)
+    }
+}
+
+/// Converts a package version (ex. `1.2.3-beta.1`) to the four-part version
+/// of the `DriverVer` directive of an INF (ex. `1.2.3.0`). Pre-release and
+/// build metadata are not representable in an INF version, so they are
+/// dropped.
+fn inf_driver_version(package_version: &str) -> Result<String, InfTemplateError> {
+    let invalid_driver_version = || InfTemplateError::InvalidDriverVersion {
+        version: package_version.to_string(),
+    };
+
+    let version = Version::parse(package_version).map_err(|_| invalid_driver_version())?;
+    let components = [version.major, version.minor, version.patch]
+        .map(|component| u16::try_from(component).map_err(|_| invalid_driver_version()));
+    let [major, minor, patch] = components;
+    Ok(format!("{}.{}.{}.0", major?, minor?, patch?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KMDFConfig, UMDFConfig};
+
+    fn kmdf_variables() -> InfTemplateVariables {
+        InfTemplateVariables::new("sample_kmdf_driver", "1.2.3", CPUArchitecture::ARM64)
+            .unwrap()
+            .with_driver_config(&DriverConfig::KMDF(KMDFConfig {
+                kmdf_version_major: 1,
+                kmdf_version_minor: 33,
+            }))
+    }
+
+    #[test]
+    fn render_replaces_variables() {
+        let template = concat!(
+            "[Version]\r\n",
+            "DriverVer = 01/01/2024,${DRIVER_VERSION}\r\n",
+            "\r\n",
+            "[Files]\r\n",
+            "${DRIVER_NAME}.sys ; ${ARCH}\r\n",
+            "KmdfLibraryVersion = ${KMDF_VERSION}\r\n",
+        );
+
+        assert_eq!(
+            kmdf_variables().render(template).unwrap(),
+            concat!(
+                "[Version]\r\n",
+                "DriverVer = 01/01/2024,1.2.3.0\r\n",
+                "\r\n",
+                "[Files]\r\n",
+                "sample_kmdf_driver.sys ; arm64\r\n",
+                "KmdfLibraryVersion = 1.33\r\n",
+            )
+        );
+    }
+
+    #[test]
+    fn render_leaves_other_dollar_signs() {
+        let template = concat!(
+            "[Manufacturer]\n",
+            "%ManufacturerName%=Standard,NT$ARCH$.10.0...16299\n",
+            "Escaped = $${DRIVER_NAME} $\n",
+        );
+
+        assert_eq!(
+            kmdf_variables().render(template).unwrap(),
+            concat!(
+                "[Manufacturer]\n",
+                "%ManufacturerName%=Standard,NT$ARCH$.10.0...16299\n",
+                "Escaped = ${DRIVER_NAME} $\n",
+            )
+        );
+    }
+
+    #[test]
+    fn render_rejects_undefined_variable() {
+        let umdf_variables =
+            InfTemplateVariables::new("sample_umdf_driver", "0.1.0", CPUArchitecture::AMD64)
+                .unwrap()
+                .with_driver_config(&DriverConfig::UMDF(UMDFConfig {
+                    umdf_version_major: 2,
+                    umdf_version_minor: 33,
+                }));
+        assert_eq!(umdf_variables.get("UMDF_VERSION"), Some("2.33.0"));
+
+        let error = umdf_variables
+            .render("[Version]\n\nKmdfLibraryVersion = ${KMDF_VERSION}\n")
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            InfTemplateError::UndefinedVariable { line: 3, ref name, .. } if name == "KMDF_VERSION"
+        ));
+    }
+
+    #[test]
+    fn render_rejects_unterminated_variable() {
+        let error = kmdf_variables()
+            .render("[Version]\nDriverVer = ${DRIVER_VERSION\n}")
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            InfTemplateError::UnterminatedVariable { line: 2 }
+        ));
+    }
+
+    #[test]
+    fn driver_version_drops_pre_release() {
+        assert_eq!(
+            inf_driver_version("0.2.0-beta.1+build.5").unwrap(),
+            "0.2.0.0"
+        );
+        assert!(matches!(
+            inf_driver_version("1.70000.0"),
+            Err(InfTemplateError::InvalidDriverVersion { .. })
+        ));
+        assert!(matches!(
+            inf_driver_version("1.2"),
+            Err(InfTemplateError::InvalidDriverVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn render_file_only_writes_changed_output() {
+        let directory = std::env::temp_dir().join(format!(
+            "wdk-build-inf-template-test-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&directory).unwrap();
+        let template_path = directory.join("sample_kmdf_driver.inx");
+        let output_path = directory.join("sample_kmdf_driver.inf");
+        std::fs::write(&template_path, "KmdfLibraryVersion = ${KMDF_VERSION}\n").unwrap();
+        let _ = std::fs::remove_file(&output_path);
+
+        let variables = kmdf_variables();
+        assert!(variables.render_file(&template_path, &output_path).unwrap());
+        assert!(!variables.render_file(&template_path, &output_path).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(&output_path).unwrap(),
+            "KmdfLibraryVersion = 1.33\n"
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
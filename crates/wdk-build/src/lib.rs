@@ -15,15 +15,38 @@
 mod bindgen;
 mod utils;
 
+pub mod api_validator;
+pub mod bindings_cache;
 pub mod cargo_make;
-
-use std::{env, path::PathBuf};
-
-pub use bindgen::BuilderExt;
+pub mod inf_template;
+pub mod lints;
+pub mod metadata;
+pub mod struct_initializers;
+pub mod symbols;
+pub mod typed_constants;
+pub mod verifier;
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+pub use bindgen::{check_libclang, generate_custom_bindings, BuilderExt, MINIMUM_LIBCLANG_VERSION};
+pub use bindings_cache::{BindingsCache, BindingsStamps};
+use cargo_metadata::MetadataCommand;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+pub use utils::detect_wdk_content_root;
 use utils::PathExt;
 
+/// Environment variable that pins the WDK version that build scripts which
+/// cannot resolve the `wdk` metadata of the driver being built (ex. the one of
+/// `wdk-sys`) build against. [`cargo_make::setup_path`] and
+/// [`cargo_make::configure_wdk_tools_path`] set it to the `wdk-version` of
+/// the `wdk` metadata, so that bindings, linking and packaging all use the
+/// same WDK. See [`Config::wdk_version_from_env`].
+pub const WDK_VERSION_ENV_VAR: &str = "WDK_BUILD_WDK_VERSION";
+
 /// Configuration parameters for a build dependent on the WDK
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Config {
@@ -34,6 +57,30 @@ pub struct Config {
     pub driver_config: DriverConfig,
     /// CPU architecture to target
     pub cpu_architecture: CPUArchitecture,
+    /// NDIS configuration of driver. This is only set for drivers that use
+    /// NDIS (ex. network miniport, protocol and filter drivers), which also
+    /// link against the libraries of [`Subsystem::Ndis`].
+    pub ndis_config: Option<NDISConfig>,
+    /// `NetAdapterCx` configuration of driver. This is only set for KMDF network
+    /// adapter drivers that use the Network Adapter WDF Class Extension
+    /// (`NetAdapterCx`), which also link against the libraries of
+    /// [`Subsystem::NetAdapter`].
+    #[serde(default)]
+    pub netadapter_config: Option<NetAdapterConfig>,
+    /// Subsystems whose APIs the driver uses. The import libraries of each
+    /// subsystem are linked in addition to those of the driver model.
+    #[serde(default)]
+    pub subsystems: Vec<Subsystem>,
+    /// Additional libraries that the package being built links against,
+    /// which are emitted as-is via `cargo::rustc-link-lib`. This is resolved
+    /// from the `link-libraries` key of the `[package.metadata.wdk]` table of
+    /// the package being built by [`Config::from_wdk_metadata`] and
+    /// [`Config::from_env_auto`].
+    #[serde(default)]
+    pub link_libraries: Vec<String>,
+    /// Version of the WDK to build against (ex. `10.0.26100.0`). If not set,
+    /// the latest WDK installed in [`Config::wdk_content_root`] is used.
+    pub wdk_version: Option<String>,
 }
 
 /// The driver type with its associated configuration parameters
@@ -48,7 +95,7 @@ pub enum DriverConfig {
 }
 
 /// Driver model type
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DriverType {
     /// Windows Driver Model
     WDM,
@@ -85,6 +132,92 @@ pub struct UMDFConfig {
     pub umdf_version_minor: u8,
 }
 
+/// The configuration parameters for drivers that use NDIS
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NDISConfig {
+    /// Major NDIS Version
+    pub ndis_version_major: u8,
+    /// Minor NDIS Version (ex. 30 for NDIS 6.30)
+    pub ndis_version_minor: u8,
+    /// Whether the driver is an NDIS miniport driver. Protocol and filter
+    /// drivers are not miniport drivers.
+    pub miniport_driver: bool,
+}
+
+/// The configuration parameters for drivers that use the Network Adapter WDF
+/// Class Extension (`NetAdapterCx`)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NetAdapterConfig {
+    /// Major `NetAdapterCx` Version
+    pub netadapter_version_major: u8,
+    /// Minor `NetAdapterCx` Version
+    pub netadapter_version_minor: u8,
+}
+
+/// Subsystems of the WDK whose APIs require linking against additional import
+/// libraries
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Subsystem {
+    /// CNG (Cryptography API: Next Generation) APIs from `bcrypt.h`
+    Cng,
+    /// Filter Manager APIs (ex. of file system minifilter drivers)
+    FilterManager,
+    /// General-purpose I/O controller extension (`GpioClx`) APIs
+    Gpio,
+    /// HID class driver and HID parsing APIs
+    Hid,
+    /// USB APIs
+    Usb,
+    /// Simple Peripheral Bus controller extension (`SpbCx`) APIs
+    Spb,
+    /// Storport miniport APIs, and the SCSI and disk IOCTLs of the storage
+    /// stack
+    Storport,
+    /// Secure device object creation (ex. `IoCreateDeviceSecure`) and SDDL
+    /// string APIs from `wdmsec.h`
+    Wdmsec,
+    /// NDIS APIs. This is linked for every driver with a
+    /// [`Config::ndis_config`].
+    Ndis,
+    /// Network Adapter WDF Class Extension (`NetAdapterCx`) APIs. This is
+    /// linked for every driver with a [`Config::netadapter_config`].
+    NetAdapter,
+}
+
+impl Subsystem {
+    /// Returns the import libraries that a driver with `driver_config` must
+    /// link against to use this subsystem
+    #[must_use]
+    pub const fn link_libraries(self, driver_config: &DriverConfig) -> &'static [&'static str] {
+        match (self, driver_config) {
+            // Kernel-mode CNG is provided by cng.sys (and ksecdd.sys on older versions of
+            // Windows), whose exports are forwarded via cng.lib
+            (Self::Cng, DriverConfig::WDM() | DriverConfig::KMDF(_)) => &["cng"],
+            (Self::Cng, DriverConfig::UMDF(_)) => &["bcrypt"],
+            (Self::FilterManager, _) => &["FltMgr"],
+            // GpioClx is a KMDF class extension, whose client registration functions are
+            // provided by its static stub library
+            (Self::Gpio, DriverConfig::WDM() | DriverConfig::KMDF(_)) => &["msgpioclxstub"],
+            (Self::Hid, DriverConfig::WDM() | DriverConfig::KMDF(_)) => &["hidclass", "hidparse"],
+            (Self::Hid, DriverConfig::UMDF(_)) => &["hid"],
+            (Self::Usb, DriverConfig::WDM() | DriverConfig::KMDF(_)) => &["usbd", "usbdex"],
+            (Self::Usb, DriverConfig::UMDF(_)) => &["winusb"],
+            // SpbCx is a KMDF class extension, so its functions are bound via the stubs
+            // library
+            (Self::Spb, DriverConfig::WDM() | DriverConfig::KMDF(_)) => &["SpbCxStubs"],
+            (Self::Storport, _) => &["storport"],
+            (Self::Wdmsec, DriverConfig::WDM() | DriverConfig::KMDF(_)) => &["wdmsec"],
+            // GpioClx and wdmsec.h are only available to kernel-mode drivers, and user-mode
+            // SPB peripheral drivers only send IOCTLs to the controller
+            (Self::Gpio | Self::Spb | Self::Wdmsec, DriverConfig::UMDF(_)) => &[],
+            (Self::Ndis, _) => &["ndis"],
+            // NetAdapterCx functions are bound via the stubs library, like those of the other
+            // KMDF class extensions
+            (Self::NetAdapter, _) => &["NetAdapterCxStub"],
+        }
+    }
+}
+
 /// Errors that could result from configuring a build via [`wdk-build`]
 #[derive(Debug, Error)]
 pub enum ConfigError {
@@ -93,7 +226,10 @@ pub enum ConfigError {
     IoError(#[from] std::io::Error),
 
     /// Error returned when an expected directory does not exist
-    #[error("cannot find directory: {directory}")]
+    #[error(
+        "cannot find directory: {directory}\nhelp: the WDK installation may be incomplete. Repair \
+         it via the WDK installer, or ensure that the configured KMDF/UMDF version is installed."
+    )]
     DirectoryNotFound {
         /// Path of directory that was not found
         directory: String,
@@ -112,17 +248,46 @@ pub enum ConfigError {
     #[error(transparent)]
     ExportError(#[from] ExportError),
 
-    /// Error returned when a [`Config`] fails to be serialized
+    /// Error returned when the WDK content root cannot be detected
     #[error(
-        "WDKContentRoot should be able to be detected. Ensure that the WDK is installed, or that \
-         the environment setup scripts in the eWDK have been run."
+        "WDKContentRoot could not be detected. Searched locations:\n{}\nhelp: install the WDK, \
+         run the environment setup scripts of the eWDK (ex. LaunchBuildEnv.cmd), or set {} to a \
+         WDK content root (ex. C:\\Program Files (x86)\\Windows Kits\\10) or to the root of a \
+         mounted eWDK.",
+        bulleted_list(searched_locations),
+        utils::WDK_CONTENT_ROOT_ENV_VAR
     )]
-    WDKContentRootDetectionError,
+    WDKContentRootDetectionError {
+        /// Environment variables and registry keys that were consulted, and
+        /// why each of them was rejected
+        searched_locations: Vec<String>,
+    },
 
     /// Error returned when `cargo_metadata` execution or parsing fails
     #[error(transparent)]
     CargoMetadataError(#[from] cargo_metadata::Error),
 
+    /// Error returned when the WDK version pinned via [`Config::wdk_version`]
+    /// is not installed
+    #[error(
+        "WDK version {version} is not installed in {directory}. Installed versions: \
+         {installed_versions:?}\nhelp: install WDK version {version}, or change the `wdk-version` \
+         key of the `wdk` metadata to one of the installed versions."
+    )]
+    WDKVersionNotFound {
+        /// WDK version that was pinned
+        version: String,
+        /// Directory that was searched for the pinned version
+        directory: String,
+        /// WDK versions that are installed in `directory`
+        installed_versions: Vec<String>,
+    },
+
+    /// Error returned when the `wdk` metadata of the packages being built
+    /// cannot be resolved
+    #[error(transparent)]
+    WDKMetadataError(#[from] metadata::WDKMetadataError),
+
     /// Error returned when multiple versions of the wdk-build package are
     /// detected
     #[error(
@@ -133,19 +298,89 @@ pub enum ConfigError {
         /// package ids of the wdk-build crates detected
         package_ids: Vec<cargo_metadata::PackageId>,
     },
+
+    /// Error returned when `bindgen` fails to generate bindings, ex. for the
+    /// custom headers of [`generate_custom_bindings`]
+    #[error(transparent)]
+    BindgenError(#[from] ::bindgen::BindgenError),
+
+    /// Error returned when `libclang`, which `bindgen` requires to generate
+    /// bindings, cannot be loaded
+    #[error(
+        "cannot load libclang, which is required to generate bindings: {reason}\nhelp: install \
+         LLVM (ex. `winget install -i LLVM.LLVM --version 17.0.6 --force`) and add its bin \
+         directory to PATH, or set LIBCLANG_PATH to the directory that contains libclang.dll."
+    )]
+    LibclangNotFound {
+        /// Reason reported by `clang-sys`, including the directories that were
+        /// searched
+        reason: String,
+    },
+
+    /// Error returned when the version of the `libclang` loaded by `bindgen`
+    /// cannot generate bindings to the WDK
+    #[error(
+        "libclang at {path} is not supported: {reason}. Version: {version}\nhelp: install LLVM \
+         17.0.6 (ex. `winget install -i LLVM.LLVM --version 17.0.6 --force`), and set \
+         LIBCLANG_PATH to the directory that contains its libclang.dll."
+    )]
+    UnsupportedLibclangVersion {
+        /// Path of the `libclang` that was loaded
+        path: String,
+        /// Full version string reported by `libclang`
+        version: String,
+        /// Reason the version is not supported
+        reason: String,
+    },
+
+    /// Error returned when `ApiValidator` fails to run, or finds calls to
+    /// unsupported APIs
+    #[error(transparent)]
+    ApiValidatorError(#[from] api_validator::ApiValidatorError),
+
+    /// Error returned when the `.inx` template of a driver cannot be rendered
+    /// into its INF
+    #[error(transparent)]
+    InfTemplateError(#[from] inf_template::InfTemplateError),
+
+    /// Error returned when the symbols of a driver cannot be verified or
+    /// published
+    #[error(transparent)]
+    SymbolsError(#[from] symbols::SymbolsError),
+
+    /// Error returned when any of the checks run by
+    /// [`cargo_make::run_static_analysis`] fail
+    #[error("static analysis checks failed: {}", failed_checks.join(", "))]
+    StaticAnalysisFailed {
+        /// Names of the checks that failed
+        failed_checks: Vec<String>,
+    },
 }
 
 /// Errors that could result from parsing a configuration from a [`wdk-build`]
 /// build environment
 #[derive(Debug, Error)]
 pub enum ConfigFromEnvError {
-    /// Error returned when an expected environment variable is not found
-    #[error(transparent)]
-    EnvError(#[from] std::env::VarError),
+    /// Error returned when the environment variable that a [`Config`] is
+    /// expected to be exported to cannot be read
+    #[error("cannot read WDK config exported via {env_var_name}")]
+    EnvError {
+        /// Name of the environment variable that could not be read
+        env_var_name: String,
+        /// Underlying error returned when reading the environment variable
+        #[source]
+        source: std::env::VarError,
+    },
 
     /// Error returned when [`serde_json`] fails to deserialize the [`Config`]
-    #[error(transparent)]
-    DeserializeError(#[from] serde_json::Error),
+    #[error("invalid WDK config exported via {env_var_name}")]
+    DeserializeError {
+        /// Name of the environment variable containing the invalid config
+        env_var_name: String,
+        /// Underlying error returned when deserializing the config
+        #[source]
+        source: serde_json::Error,
+    },
 
     /// Error returned when the config from one WDK dependency does not match
     /// the config from another
@@ -168,8 +403,17 @@ pub enum ConfigFromEnvError {
 
     /// Error returned when no WDK configs exported from dependencies could be
     /// found
-    #[error("no WDK configs exported from dependencies could be found")]
+    #[error(
+        "no WDK configs exported from dependencies could be found\nhelp: add a dependency on \
+         wdk-sys (or another crate that exports a WDK config via its `links` value), or call \
+         `Config::from_env_auto` only from crates that depend on one."
+    )]
     ConfigNotFound,
+
+    /// Error returned when the `wdk` metadata of the package being built is
+    /// invalid
+    #[error(transparent)]
+    WDKMetadataError(#[from] metadata::WDKMetadataError),
 }
 
 /// Errors that could result from exporting a [`wdk-build`] build configuration
@@ -188,16 +432,28 @@ pub enum ExportError {
     SerializeError(#[from] serde_json::Error),
 }
 
+/// Formats `items` as an indented bulleted list, one item per line
+fn bulleted_list(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|item| format!("  - {item}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 impl Default for Config {
     #[must_use]
     fn default() -> Self {
         Self {
-            wdk_content_root: utils::detect_wdk_content_root().expect(
-                "WDKContentRoot should be able to be detected. Ensure that the WDK is installed, \
-                 or that the environment setup scripts in the eWDK have been run.",
-            ),
+            wdk_content_root: utils::detect_wdk_content_root()
+                .unwrap_or_else(|error| panic!("{error}")),
             driver_config: DriverConfig::WDM(),
             cpu_architecture: utils::detect_cpu_architecture_in_build_script(),
+            ndis_config: None,
+            netadapter_config: None,
+            subsystems: Vec::new(),
+            link_libraries: Vec::new(),
+            wdk_version: None,
         }
     }
 }
@@ -211,6 +467,31 @@ pub fn new() -> Self {
         Self::default()
     }
 
+    /// Creates a [`Config`] whose driver configuration is resolved from the
+    /// `[workspace.metadata.wdk]` and `[package.metadata.wdk]` tables of the
+    /// dependency graph of the current package. See [`metadata`] for the
+    /// precedence order. If no driver model is specified,
+    /// [`DriverConfig::WDM`] is used. If no WDK version is pinned, the latest
+    /// installed WDK is used. [`Config::link_libraries`] is set to the
+    /// `link-libraries` of the current package.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `cargo metadata` fails to execute
+    /// or if the `wdk` metadata cannot be resolved.
+    pub fn from_wdk_metadata() -> Result<Self, ConfigError> {
+        let wdk_metadata =
+            metadata::WDKMetadata::try_from_cargo_metadata(&MetadataCommand::new().exec()?)?;
+        Ok(Self {
+            driver_config: wdk_metadata
+                .driver_model
+                .map_or_else(DriverConfig::WDM, DriverConfig::from),
+            wdk_version: wdk_metadata.wdk_version,
+            link_libraries: wdk_metadata.link_libraries,
+            ..Self::default()
+        })
+    }
+
     /// Creates a [`Config`] from a config exported from a dependency. The
     /// dependency must have exported a [`Config`] via
     /// [`Config::export_config`], and the dependency must have set a `links`
@@ -226,13 +507,16 @@ pub fn new() -> Self {
     pub fn from_env<S: AsRef<str> + std::fmt::Display>(
         links_value: S,
     ) -> Result<Self, ConfigFromEnvError> {
-        Ok(serde_json::from_str::<Self>(
-            std::env::var(format!(
-                "DEP_{links_value}_{}",
-                Self::CARGO_CONFIG_KEY.to_ascii_uppercase()
-            ))?
-            .as_str(),
-        )?)
+        let env_var_name = format!(
+            "DEP_{links_value}_{}",
+            Self::CARGO_CONFIG_KEY.to_ascii_uppercase()
+        );
+        let serialized_config =
+            std::env::var(&env_var_name).map_err(|source| ConfigFromEnvError::EnvError {
+                env_var_name: env_var_name.clone(),
+                source,
+            })?;
+        Self::deserialize_exported_config(&env_var_name, &serialized_config)
     }
 
     /// Creates a [`Config`] from a config exported from [`wdk`](https://docs.rs/wdk/latest/wdk/) or
@@ -246,7 +530,20 @@ pub fn from_env<S: AsRef<str> + std::fmt::Display>(
     ///       be found
     ///     * there is a config mismatch between [`wdk`](https://docs.rs/wdk/latest/wdk/)
     ///       and [`wdk_sys`](https://docs.rs/wdk-sys/latest/wdk_sys/)
+    ///     * the `wdk` metadata of the package being built is invalid
     pub fn from_env_auto() -> Result<Self, ConfigFromEnvError> {
+        // The libraries of the dependency that exported the config are already linked by
+        // its own build, so only those of the package being built are linked by it
+        Ok(Self {
+            link_libraries: current_package_link_libraries()?,
+            ..Self::from_exported_configs()?
+        })
+    }
+
+    /// Creates a [`Config`] from the configs exported from [`wdk`](https://docs.rs/wdk/latest/wdk/)
+    /// and/or [`wdk_sys`](https://docs.rs/wdk-sys/latest/wdk_sys/), checking
+    /// that they match if both are exported
+    fn from_exported_configs() -> Result<Self, ConfigFromEnvError> {
         let wdk_sys_crate_dep_key =
             format!("DEP_WDK_{}", Self::CARGO_CONFIG_KEY.to_ascii_uppercase());
         let wdk_crate_dep_key = format!(
@@ -261,9 +558,14 @@ pub fn from_env_auto() -> Result<Self, ConfigFromEnvError> {
             wdk_sys_crate_config_serialized.clone(),
             wdk_crate_config_serialized.clone(),
         ) {
-            let wdk_sys_crate_config =
-                serde_json::from_str::<Self>(&wdk_sys_crate_config_serialized)?;
-            let wdk_crate_config = serde_json::from_str::<Self>(&wdk_crate_config_serialized)?;
+            let wdk_sys_crate_config = Self::deserialize_exported_config(
+                &wdk_sys_crate_dep_key,
+                &wdk_sys_crate_config_serialized,
+            )?;
+            let wdk_crate_config = Self::deserialize_exported_config(
+                &wdk_crate_dep_key,
+                &wdk_crate_config_serialized,
+            )?;
 
             if wdk_sys_crate_config == wdk_crate_config {
                 Ok(wdk_sys_crate_config)
@@ -276,16 +578,57 @@ pub fn from_env_auto() -> Result<Self, ConfigFromEnvError> {
                 })
             }
         } else if let Ok(wdk_sys_crate_config_serialized) = wdk_sys_crate_config_serialized {
-            Ok(serde_json::from_str::<Self>(
+            Self::deserialize_exported_config(
+                &wdk_sys_crate_dep_key,
                 &wdk_sys_crate_config_serialized,
-            )?)
+            )
         } else if let Ok(wdk_crate_config_serialized) = wdk_crate_config_serialized {
-            Ok(serde_json::from_str::<Self>(&wdk_crate_config_serialized)?)
+            Self::deserialize_exported_config(&wdk_crate_dep_key, &wdk_crate_config_serialized)
         } else {
             Err(ConfigFromEnvError::ConfigNotFound)
         }
     }
 
+    /// Deserializes a [`Config`] that was exported via
+    /// [`Config::export_config`], attributing any error to `env_var_name`
+    fn deserialize_exported_config(
+        env_var_name: &str,
+        serialized_config: &str,
+    ) -> Result<Self, ConfigFromEnvError> {
+        serde_json::from_str::<Self>(serialized_config).map_err(|source| {
+            ConfigFromEnvError::DeserializeError {
+                env_var_name: env_var_name.to_string(),
+                source,
+            }
+        })
+    }
+
+    /// Returns the WDK version pinned via [`WDK_VERSION_ENV_VAR`], or [`None`]
+    /// if it is not set, in which case the latest installed WDK is used. This
+    /// also instructs Cargo to rerun the build script when the pin changes.
+    #[must_use]
+    pub fn wdk_version_from_env() -> Option<String> {
+        println!("cargo::rerun-if-env-changed={WDK_VERSION_ENV_VAR}");
+        env::var(WDK_VERSION_ENV_VAR)
+            .ok()
+            .filter(|wdk_version| !wdk_version.is_empty())
+    }
+
+    /// Returns the version of the WDK that is built against: the pinned
+    /// [`Config::wdk_version`] if it is set, or the latest WDK installed in
+    /// [`Config::wdk_content_root`] otherwise
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the pinned version is not
+    /// installed, or if no WDK version is installed.
+    pub fn resolve_wdk_version(&self) -> Result<String, ConfigError> {
+        utils::get_windows_sdk_version(
+            self.wdk_content_root.join("Include").as_path(),
+            self.wdk_version.as_deref(),
+        )
+    }
+
     /// Returns header include paths required to build and link based off of the
     /// configuration of `Config`
     ///
@@ -301,10 +644,18 @@ pub fn get_include_paths(&self) -> Result<Vec<PathBuf>, ConfigError> {
         // Add windows sdk include paths
         // Based off of logic from WindowsDriver.KernelMode.props &
         // WindowsDriver.UserMode.props in NI(22H2) WDK
-        let sdk_version = utils::get_latest_windows_sdk_version(include_directory.as_path())?;
+        let sdk_version = utils::get_windows_sdk_version(
+            include_directory.as_path(),
+            self.wdk_version.as_deref(),
+        )?;
         let windows_sdk_include_path = include_directory.join(sdk_version);
 
-        let crt_include_path = windows_sdk_include_path.join("km/crt");
+        // Kernel-mode drivers use the kernel-mode CRT headers, while UMDF drivers use
+        // the Universal CRT
+        let crt_include_path = windows_sdk_include_path.join(match self.driver_config {
+            DriverConfig::WDM() | DriverConfig::KMDF(_) => "km/crt",
+            DriverConfig::UMDF(_) => "ucrt",
+        });
         if !crt_include_path.is_dir() {
             return Err(ConfigError::DirectoryNotFound {
                 directory: crt_include_path.to_string_lossy().into(),
@@ -380,9 +731,152 @@ pub fn get_include_paths(&self) -> Result<Vec<PathBuf>, ConfigError> {
             }
         }
 
+        include_paths.extend(self.get_subsystem_include_paths(&windows_sdk_include_path)?);
+
+        Ok(include_paths)
+    }
+
+    /// Returns the header include paths of the subsystems whose headers are
+    /// versioned separately from the WDK, based off of the configuration of
+    /// `Config`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if any of the required paths do not
+    /// exist.
+    fn get_subsystem_include_paths(
+        &self,
+        windows_sdk_include_path: &Path,
+    ) -> Result<Vec<PathBuf>, ConfigError> {
+        let mut include_paths = vec![];
+
+        // NetAdapterCx is a KMDF class extension, whose headers are versioned
+        // separately from KMDF. The headers shared by the NetCx class extensions
+        // (ex. `net/ring.h`) are not versioned with NetAdapterCx.
+        if let (DriverConfig::KMDF(_), Some(netadapter_config)) =
+            (&self.driver_config, self.netadapter_config)
+        {
+            let netcx_include_path = windows_sdk_include_path.join("km/netcx");
+            for netadapter_include_path in [
+                netcx_include_path.join(format!(
+                    "kmdf/adapter/{}.{}",
+                    netadapter_config.netadapter_version_major,
+                    netadapter_config.netadapter_version_minor
+                )),
+                netcx_include_path.join("shared/1.0"),
+            ] {
+                if !netadapter_include_path.is_dir() {
+                    return Err(ConfigError::DirectoryNotFound {
+                        directory: netadapter_include_path.to_string_lossy().into(),
+                    });
+                }
+                include_paths.push(
+                    netadapter_include_path
+                        .canonicalize()?
+                        .strip_extended_length_path_prefix()?,
+                );
+            }
+        }
+
         Ok(include_paths)
     }
 
+    /// Returns the preprocessor definitions required to generate bindings to,
+    /// or build against, the WDK headers based off of the configuration of
+    /// `Config`.
+    ///
+    /// This includes the definitions for the [`CPUArchitecture`], the WDF
+    /// version of the [`DriverConfig`] and the `NetAdapterCx` version of the
+    /// [`NetAdapterConfig`]. The NDIS definitions are returned separately by
+    /// [`Config::get_ndis_preprocessor_definitions`], since they only apply to
+    /// the NDIS headers. Definitions with a value are in the `NAME=VALUE`
+    /// form.
+    #[must_use]
+    pub fn get_preprocessor_definitions(&self) -> Vec<String> {
+        let mut preprocessor_definitions = match self.cpu_architecture {
+            // Definitions sourced from `Program Files\Windows
+            // Kits\10\build\10.0.22621.0\WindowsDriver.x64.props`
+            CPUArchitecture::AMD64 => vec!["_WIN64", "_AMD64_", "AMD64"],
+            // Definitions sourced from `Program Files\Windows
+            // Kits\10\build\10.0.22621.0\WindowsDriver.arm64.props`
+            CPUArchitecture::ARM64 => {
+                vec!["_ARM64_", "ARM64", "_USE_DECLSPECS_FOR_SAL=1", "STD_CALL"]
+            }
+        }
+        .into_iter()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+
+        match self.driver_config {
+            // FIXME: Add support for KMDF_MINIMUM_VERSION_REQUIRED and
+            // UMDF_MINIMUM_VERSION_REQUIRED
+            DriverConfig::WDM() => {}
+            DriverConfig::KMDF(kmdf_config) => {
+                preprocessor_definitions.extend([
+                    format!("KMDF_VERSION_MAJOR={}", kmdf_config.kmdf_version_major),
+                    format!("KMDF_VERSION_MINOR={}", kmdf_config.kmdf_version_minor),
+                ]);
+            }
+            DriverConfig::UMDF(umdf_config) => {
+                preprocessor_definitions.extend([
+                    format!("UMDF_VERSION_MAJOR={}", umdf_config.umdf_version_major),
+                    format!("UMDF_VERSION_MINOR={}", umdf_config.umdf_version_minor),
+                ]);
+
+                if umdf_config.umdf_version_major >= 2 {
+                    preprocessor_definitions.extend([
+                        "UMDF_USING_NTSTATUS".to_string(),
+                        "_UNICODE".to_string(),
+                        "UNICODE".to_string(),
+                    ]);
+                }
+            }
+        }
+
+        if let Some(netadapter_config) = self.netadapter_config {
+            preprocessor_definitions.extend([
+                format!(
+                    "NETADAPTER_VERSION_MAJOR={}",
+                    netadapter_config.netadapter_version_major
+                ),
+                format!(
+                    "NETADAPTER_VERSION_MINOR={}",
+                    netadapter_config.netadapter_version_minor
+                ),
+            ]);
+        }
+        preprocessor_definitions
+    }
+
+    /// Returns the NDIS preprocessor definitions required to generate bindings
+    /// to, or build against, `ndis.h` based off of the configuration of
+    /// `Config`. `ndis.h` derives the `NDIS_SUPPORT_NDIS6xx` definitions for
+    /// the configured NDIS version and all prior versions from these.
+    ///
+    /// These are not part of [`Config::get_preprocessor_definitions`], and
+    /// should only be applied when generating bindings for the NDIS subsystem
+    /// (ex. in addition to the definitions applied by
+    /// [`BuilderExt::wdk_default`]).
+    #[must_use]
+    pub fn get_ndis_preprocessor_definitions(&self) -> Vec<String> {
+        let Some(ndis_config) = self.ndis_config else {
+            return vec![];
+        };
+
+        let ndis_version = format!(
+            "NDIS{}{}",
+            ndis_config.ndis_version_major, ndis_config.ndis_version_minor
+        );
+        if ndis_config.miniport_driver {
+            vec![
+                "NDIS_MINIPORT_DRIVER=1".to_string(),
+                format!("{ndis_version}_MINIPORT=1"),
+            ]
+        } else {
+            vec![format!("{ndis_version}=1")]
+        }
+    }
+
     /// Returns library include paths required to build and link based off of
     /// the configuration of `Config`
     ///
@@ -398,7 +892,10 @@ pub fn get_library_paths(&self) -> Result<Vec<PathBuf>, ConfigError> {
         // Add windows sdk library paths
         // Based off of logic from WindowsDriver.KernelMode.props &
         // WindowsDriver.UserMode.props in NI(22H2) WDK
-        let sdk_version = utils::get_latest_windows_sdk_version(library_directory.as_path())?;
+        let sdk_version = utils::get_windows_sdk_version(
+            library_directory.as_path(),
+            self.wdk_version.as_deref(),
+        )?;
         let windows_sdk_library_path =
             library_directory
                 .join(sdk_version)
@@ -441,6 +938,24 @@ pub fn get_library_paths(&self) -> Result<Vec<PathBuf>, ConfigError> {
                         .canonicalize()?
                         .strip_extended_length_path_prefix()?,
                 );
+
+                if let Some(netadapter_config) = self.netadapter_config {
+                    let netadapter_library_path = windows_sdk_library_path.join(format!(
+                        "netcx/kmdf/adapter/{}.{}",
+                        netadapter_config.netadapter_version_major,
+                        netadapter_config.netadapter_version_minor
+                    ));
+                    if !netadapter_library_path.is_dir() {
+                        return Err(ConfigError::DirectoryNotFound {
+                            directory: netadapter_library_path.to_string_lossy().into(),
+                        });
+                    }
+                    library_paths.push(
+                        netadapter_library_path
+                            .canonicalize()?
+                            .strip_extended_length_path_prefix()?,
+                    );
+                }
             }
             DriverConfig::UMDF(umdf_config) => {
                 let umdf_library_path = library_directory.join(format!(
@@ -473,6 +988,9 @@ pub fn get_library_paths(&self) -> Result<Vec<PathBuf>, ConfigError> {
     /// directives, and WDK-specific configuration definitions. This must be
     /// called from a Cargo build script of the library.
     ///
+    /// Besides the libraries of the driver model and of
+    /// [`Config::subsystems`], this links [`Config::link_libraries`].
+    ///
     /// # Errors
     ///
     /// This function will return an error if any of the required paths do not
@@ -482,6 +1000,9 @@ pub fn get_library_paths(&self) -> Result<Vec<PathBuf>, ConfigError> {
     ///
     /// Panics if the invoked from outside a Cargo build environment
     pub fn configure_library_build(&self) -> Result<(), ConfigError> {
+        forward_types_rs_path();
+        self.emit_cfg_settings();
+
         let library_paths = self.get_library_paths()?;
 
         // Emit linker search paths
@@ -534,9 +1055,31 @@ pub fn configure_library_build(&self) -> Result<(), ConfigError> {
             }
         }
 
+        for subsystem in self.linked_subsystems() {
+            for library in subsystem.link_libraries(&self.driver_config) {
+                println!("cargo::rustc-link-lib={library}");
+            }
+        }
+
+        for library in &self.link_libraries {
+            println!("cargo::rustc-link-lib={library}");
+        }
+
         Ok(())
     }
 
+    /// Returns the subsystems whose libraries are linked by
+    /// [`Config::configure_library_build`], i.e. [`Config::subsystems`] and
+    /// the subsystems implied by [`Config::ndis_config`] and
+    /// [`Config::netadapter_config`]
+    fn linked_subsystems(&self) -> impl Iterator<Item = Subsystem> + '_ {
+        self.ndis_config
+            .map(|_| Subsystem::Ndis)
+            .into_iter()
+            .chain(self.netadapter_config.map(|_| Subsystem::NetAdapter))
+            .chain(self.subsystems.iter().copied())
+    }
+
     /// Configures a Cargo build of a binary that depends on the WDK. This
     /// emits specially formatted prints to Cargo based on this [`Config`].
     ///
@@ -607,6 +1150,11 @@ pub fn configure_binary_build(&self) -> Result<(), ConfigError> {
     /// Serializes this [`Config`] and exports it via the Cargo
     /// `DEP_<CARGO_MANIFEST_LINKS>_WDK_CONFIG` environment variable.
     ///
+    /// The whole [`Config`] is exported as a single JSON value, so list-valued
+    /// and nested fields (ex. [`Config::subsystems`] and
+    /// [`Config::ndis_config`]) are carried to dependent crates without any
+    /// additional encoding.
+    ///
     /// # Errors
     ///
     /// This function will return an error if the crate does not have a `links`
@@ -624,8 +1172,60 @@ pub fn export_config(&self) -> Result<(), ExportError> {
             Self::CARGO_CONFIG_KEY,
             serde_json::to_string(self)?
         );
+        forward_types_rs_path();
+        self.emit_cfg_settings();
         Ok(())
     }
+
+    /// Emits the `driver_type` cfg of the configured [`DriverConfig`] (ex.
+    /// `driver_type = "umdf"`) for the crate being built, so that it can
+    /// conditionally compile code for a driver model via
+    /// `#[cfg(driver_type = "umdf")]`.
+    ///
+    /// This is called by [`Config::configure_library_build`],
+    /// [`Config::configure_binary_build`] and [`Config::export_config`].
+    pub fn emit_cfg_settings(&self) {
+        println!(
+            "cargo::rustc-check-cfg=cfg(driver_type, values({}))",
+            DriverType::ALL
+                .iter()
+                .map(|driver_type| format!("\"{}\"", driver_type.as_cfg_value()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        println!(
+            "cargo::rustc-cfg=driver_type=\"{}\"",
+            self.driver_config.driver_type().as_cfg_value()
+        );
+    }
+}
+
+impl DriverConfig {
+    /// Returns the [`DriverType`] of this configuration
+    #[must_use]
+    pub const fn driver_type(&self) -> DriverType {
+        match self {
+            Self::WDM() => DriverType::WDM,
+            Self::KMDF(_) => DriverType::KMDF,
+            Self::UMDF(_) => DriverType::UMDF,
+        }
+    }
+}
+
+impl DriverType {
+    /// All driver types, in the order of the values of the `driver_type` cfg
+    pub const ALL: [Self; 3] = [Self::WDM, Self::KMDF, Self::UMDF];
+
+    /// Returns the value of the `driver_type` cfg emitted for this driver type
+    /// by [`Config::emit_cfg_settings`] (ex. `"kmdf"`)
+    #[must_use]
+    pub const fn as_cfg_value(self) -> &'static str {
+        match self {
+            Self::WDM => "wdm",
+            Self::KMDF => "kmdf",
+            Self::UMDF => "umdf",
+        }
+    }
 }
 
 impl Default for KMDFConfig {
@@ -666,6 +1266,49 @@ pub fn new() -> Self {
     }
 }
 
+impl Default for NDISConfig {
+    #[must_use]
+    fn default() -> Self {
+        // NDIS 6.50 is the NDIS version of Windows 10, version 1507, which is the oldest
+        // Windows version that the WDK can build drivers for. Drivers are not treated as
+        // miniport drivers by default, since `NDIS_MINIPORT_DRIVER` exposes the miniport
+        // APIs that protocol and filter drivers must not use.
+        Self {
+            ndis_version_major: 6,
+            ndis_version_minor: 50,
+            miniport_driver: false,
+        }
+    }
+}
+
+impl NDISConfig {
+    /// Creates a new [`NDISConfig`] with default values
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Default for NetAdapterConfig {
+    #[must_use]
+    fn default() -> Self {
+        // NetAdapterCx 2.0 is the first version that is supported outside of preview
+        // releases of Windows (Windows 10, version 2004)
+        Self {
+            netadapter_version_major: 2,
+            netadapter_version_minor: 0,
+        }
+    }
+}
+
+impl NetAdapterConfig {
+    /// Creates a new [`NetAdapterConfig`] with default values
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
 impl CPUArchitecture {
     /// Converts [`CPUArchitecture`] to the string corresponding to what the
     /// architecture is typically referred to in Windows
@@ -690,6 +1333,26 @@ pub const fn to_windows_str(&self) -> &str {
         self.as_windows_str()
     }
 
+    /// Converts [`CPUArchitecture`] to the architecture string expected by
+    /// `stampinf -a`
+    #[must_use]
+    pub const fn as_stampinf_str(&self) -> &str {
+        match self {
+            Self::AMD64 => "amd64",
+            Self::ARM64 => "arm64",
+        }
+    }
+
+    /// Converts [`CPUArchitecture`] to the comma-separated OS targets expected
+    /// by `inf2cat /os:`
+    #[must_use]
+    pub const fn as_inf2cat_os_str(&self) -> &str {
+        match self {
+            Self::AMD64 => "10_NI_X64,10_VB_X64",
+            Self::ARM64 => "10_NI_ARM64,10_VB_ARM64",
+        }
+    }
+
     /// Converts from a cargo-provided [`std::str`] to a [`CPUArchitecture`].
     ///
     /// #
@@ -703,6 +1366,57 @@ pub fn try_from_cargo_str<S: AsRef<str>>(cargo_str: S) -> Option<Self> {
             _ => None,
         }
     }
+
+    /// Converts from a Windows MSVC target triple (ex.
+    /// `aarch64-pc-windows-msvc`) to a [`CPUArchitecture`]. Returns [`None`]
+    /// for triples that are not Windows MSVC targets or whose architecture is
+    /// not supported.
+    #[must_use]
+    pub fn try_from_target_triple<S: AsRef<str>>(target_triple: S) -> Option<Self> {
+        let target_triple = target_triple.as_ref();
+        if !target_triple.ends_with("-windows-msvc") {
+            return None;
+        }
+        target_triple
+            .split('-')
+            .next()
+            .and_then(Self::try_from_cargo_str)
+    }
+}
+
+/// Forwards the path of the `types.rs` file generated by `wdk-sys`, if it was
+/// exported by a direct dependency on `wdk-sys` or `wdk`, to the crate being
+/// built via the `WDK_SYS_TYPES_RS_PATH` environment variable. This lets
+/// `call_unsafe_wdf_function_binding` skip spawning a nested `cargo check` to
+/// locate `wdk-sys`'s `OUT_DIR`. If the crate has a `links` value, the path is
+/// also re-exported to its dependents.
+fn forward_types_rs_path() {
+    let Some(types_rs_path) = ["DEP_WDK_TYPES_RS_PATH", "DEP_WDK-SYS_TYPES_RS_PATH"]
+        .into_iter()
+        .find_map(|key| env::var(key).ok())
+    else {
+        return;
+    };
+
+    println!("cargo::rustc-env=WDK_SYS_TYPES_RS_PATH={types_rs_path}");
+    if env::var("CARGO_MANIFEST_LINKS").is_ok() {
+        println!("cargo::metadata=types_rs_path={types_rs_path}");
+    }
+}
+
+/// Returns the libraries listed in the `link-libraries` key of the
+/// `[package.metadata.wdk]` table of the package whose build script is running.
+/// Only its manifest is read, so this does not spawn `cargo metadata`.
+fn current_package_link_libraries() -> Result<Vec<String>, metadata::WDKMetadataError> {
+    let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") else {
+        return Ok(Vec::new());
+    };
+
+    Ok(
+        metadata::parse_from_manifest(&Path::new(&manifest_dir).join("Cargo.toml"))?
+            .map(|wdk_metadata| wdk_metadata.link_libraries)
+            .unwrap_or_default(),
+    )
 }
 
 #[cfg(test)]
@@ -866,6 +1580,241 @@ fn umdf_config() {
         assert_eq!(config.cpu_architecture, CPUArchitecture::ARM64);
     }
 
+    #[test]
+    fn driver_type_cfg_values() {
+        assert_eq!(DriverConfig::WDM().driver_type(), DriverType::WDM);
+        assert_eq!(
+            DriverConfig::KMDF(KMDFConfig::new()).driver_type(),
+            DriverType::KMDF
+        );
+        assert_eq!(
+            DriverConfig::UMDF(UMDFConfig::new()).driver_type(),
+            DriverType::UMDF
+        );
+
+        assert_eq!(
+            DriverType::ALL.map(DriverType::as_cfg_value),
+            ["wdm", "kmdf", "umdf"]
+        );
+    }
+
+    #[test]
+    fn preprocessor_definitions() {
+        let config = with_env(&[("CARGO_CFG_TARGET_ARCH", "x86_64")], || Config {
+            driver_config: DriverConfig::KMDF(KMDFConfig::new()),
+            ..Config::default()
+        });
+        assert_eq!(
+            config.get_preprocessor_definitions(),
+            vec![
+                "_WIN64".to_string(),
+                "_AMD64_".to_string(),
+                "AMD64".to_string(),
+                "KMDF_VERSION_MAJOR=1".to_string(),
+                "KMDF_VERSION_MINOR=33".to_string(),
+            ]
+        );
+
+        let config = Config {
+            driver_config: DriverConfig::UMDF(UMDFConfig::new()),
+            ndis_config: Some(NDISConfig::new()),
+            ..config
+        };
+        assert_eq!(
+            config.get_preprocessor_definitions(),
+            vec![
+                "_WIN64".to_string(),
+                "_AMD64_".to_string(),
+                "AMD64".to_string(),
+                "UMDF_VERSION_MAJOR=2".to_string(),
+                "UMDF_VERSION_MINOR=33".to_string(),
+                "UMDF_USING_NTSTATUS".to_string(),
+                "_UNICODE".to_string(),
+                "UNICODE".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn ndis_preprocessor_definitions() {
+        let config = with_env(&[("CARGO_CFG_TARGET_ARCH", "x86_64")], || Config {
+            driver_config: DriverConfig::WDM(),
+            ..Config::default()
+        });
+        assert!(config.get_ndis_preprocessor_definitions().is_empty());
+
+        let config = Config {
+            ndis_config: Some(NDISConfig::new()),
+            ..config
+        };
+        assert_eq!(
+            config.get_ndis_preprocessor_definitions(),
+            vec!["NDIS650=1".to_string()]
+        );
+
+        let config = Config {
+            ndis_config: Some(NDISConfig {
+                ndis_version_major: 6,
+                ndis_version_minor: 82,
+                miniport_driver: true,
+            }),
+            ..config
+        };
+        assert_eq!(
+            config.get_ndis_preprocessor_definitions(),
+            vec![
+                "NDIS_MINIPORT_DRIVER=1".to_string(),
+                "NDIS682_MINIPORT=1".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn netadapter_preprocessor_definitions() {
+        let config = with_env(&[("CARGO_CFG_TARGET_ARCH", "x86_64")], || Config {
+            driver_config: DriverConfig::KMDF(KMDFConfig::new()),
+            netadapter_config: Some(NetAdapterConfig {
+                netadapter_version_major: 2,
+                netadapter_version_minor: 4,
+            }),
+            ..Config::default()
+        });
+        assert_eq!(
+            config.get_preprocessor_definitions(),
+            vec![
+                "_WIN64".to_string(),
+                "_AMD64_".to_string(),
+                "AMD64".to_string(),
+                "KMDF_VERSION_MAJOR=1".to_string(),
+                "KMDF_VERSION_MINOR=33".to_string(),
+                "NETADAPTER_VERSION_MAJOR=2".to_string(),
+                "NETADAPTER_VERSION_MINOR=4".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn config_from_env() {
+        let config = Config {
+            wdk_content_root: PathBuf::from("C:\\Program Files (x86)\\Windows Kits\\10"),
+            driver_config: DriverConfig::KMDF(KMDFConfig::new()),
+            cpu_architecture: CPUArchitecture::AMD64,
+            ndis_config: Some(NDISConfig {
+                ndis_version_major: 6,
+                ndis_version_minor: 82,
+                miniport_driver: true,
+            }),
+            netadapter_config: Some(NetAdapterConfig::new()),
+            subsystems: vec![Subsystem::Hid, Subsystem::Usb],
+            link_libraries: vec!["setupapi".to_string(), "static=mylib".to_string()],
+            wdk_version: Some("10.0.26100.0".to_string()),
+        };
+        let serialized_config = serde_json::to_string(&config).unwrap();
+
+        let config_from_env = with_env(&[("DEP_TEST_WDK_CONFIG", &serialized_config)], || {
+            Config::from_env("TEST")
+        });
+        assert_eq!(config_from_env.unwrap(), config);
+
+        let missing_config_error = Config::from_env("MISSING").unwrap_err();
+        assert!(matches!(
+            missing_config_error,
+            ConfigFromEnvError::EnvError { env_var_name, .. } if env_var_name == "DEP_MISSING_WDK_CONFIG"
+        ));
+
+        let invalid_config_error = with_env(&[("DEP_INVALID_WDK_CONFIG", "{}")], || {
+            Config::from_env("INVALID")
+        })
+        .unwrap_err();
+        assert!(matches!(
+            invalid_config_error,
+            ConfigFromEnvError::DeserializeError { env_var_name, .. } if env_var_name == "DEP_INVALID_WDK_CONFIG"
+        ));
+    }
+
+    #[test]
+    fn subsystem_link_libraries() {
+        let kmdf_config = DriverConfig::KMDF(KMDFConfig::new());
+        let umdf_config = DriverConfig::UMDF(UMDFConfig::new());
+
+        assert_eq!(
+            Subsystem::Hid.link_libraries(&kmdf_config),
+            ["hidclass", "hidparse"]
+        );
+        assert_eq!(Subsystem::Hid.link_libraries(&umdf_config), ["hid"]);
+        assert_eq!(Subsystem::Usb.link_libraries(&umdf_config), ["winusb"]);
+        assert!(Subsystem::Spb.link_libraries(&umdf_config).is_empty());
+        assert_eq!(
+            Subsystem::Gpio.link_libraries(&kmdf_config),
+            ["msgpioclxstub"]
+        );
+        assert!(Subsystem::Gpio.link_libraries(&umdf_config).is_empty());
+        assert_eq!(Subsystem::Wdmsec.link_libraries(&kmdf_config), ["wdmsec"]);
+        assert!(Subsystem::Wdmsec.link_libraries(&umdf_config).is_empty());
+        assert_eq!(Subsystem::Cng.link_libraries(&kmdf_config), ["cng"]);
+        assert_eq!(Subsystem::Cng.link_libraries(&umdf_config), ["bcrypt"]);
+        assert_eq!(
+            Subsystem::FilterManager.link_libraries(&kmdf_config),
+            ["FltMgr"]
+        );
+        assert_eq!(
+            Subsystem::Storport.link_libraries(&kmdf_config),
+            ["storport"]
+        );
+        assert_eq!(Subsystem::Ndis.link_libraries(&kmdf_config), ["ndis"]);
+        assert_eq!(
+            Subsystem::NetAdapter.link_libraries(&kmdf_config),
+            ["NetAdapterCxStub"]
+        );
+    }
+
+    #[test]
+    fn linked_subsystems() {
+        let config = with_env(&[("CARGO_CFG_TARGET_ARCH", "x86_64")], || Config {
+            driver_config: DriverConfig::KMDF(KMDFConfig::new()),
+            subsystems: vec![Subsystem::Hid],
+            ..Config::default()
+        });
+        assert_eq!(
+            config.linked_subsystems().collect::<Vec<_>>(),
+            [Subsystem::Hid]
+        );
+
+        let config = Config {
+            ndis_config: Some(NDISConfig::new()),
+            netadapter_config: Some(NetAdapterConfig::new()),
+            ..config
+        };
+        assert_eq!(
+            config.linked_subsystems().collect::<Vec<_>>(),
+            [Subsystem::Ndis, Subsystem::NetAdapter, Subsystem::Hid]
+        );
+    }
+
+    #[test]
+    fn config_exported_without_link_libraries() {
+        let config = Config {
+            wdk_content_root: PathBuf::from("C:\\Program Files (x86)\\Windows Kits\\10"),
+            driver_config: DriverConfig::WDM(),
+            cpu_architecture: CPUArchitecture::AMD64,
+            ndis_config: None,
+            netadapter_config: None,
+            subsystems: Vec::new(),
+            link_libraries: Vec::new(),
+            wdk_version: None,
+        };
+        let mut serialized_config = serde_json::to_value(&config).unwrap();
+        let serialized_config_table = serialized_config.as_object_mut().unwrap();
+        serialized_config_table.remove("netadapter_config");
+        serialized_config_table.remove("subsystems");
+        serialized_config_table.remove("link_libraries");
+
+        assert_eq!(
+            serde_json::from_value::<Config>(serialized_config).unwrap(),
+            config
+        );
+    }
+
     #[test]
     fn test_try_from_cargo_str() {
         assert_eq!(
@@ -878,4 +1827,43 @@ fn test_try_from_cargo_str() {
         );
         assert_eq!(CPUArchitecture::try_from_cargo_str("arm"), None);
     }
+
+    #[test]
+    fn test_try_from_target_triple() {
+        assert_eq!(
+            CPUArchitecture::try_from_target_triple("x86_64-pc-windows-msvc"),
+            Some(CPUArchitecture::AMD64)
+        );
+        assert_eq!(
+            CPUArchitecture::try_from_target_triple("aarch64-pc-windows-msvc"),
+            Some(CPUArchitecture::ARM64)
+        );
+        assert_eq!(
+            CPUArchitecture::try_from_target_triple("aarch64-unknown-linux-gnu"),
+            None
+        );
+        assert_eq!(
+            CPUArchitecture::try_from_target_triple("i686-pc-windows-msvc"),
+            None
+        );
+    }
+
+    #[test]
+    fn wdk_content_root_detection_error_reports_searched_locations() {
+        let error = ConfigError::WDKContentRootDetectionError {
+            searched_locations: vec![
+                "environment variable WDK_CONTENT_ROOT: not set".to_string(),
+                r"environment variable WDKContentRoot: D:\missing is not a valid directory"
+                    .to_string(),
+            ],
+        };
+        let report = error.to_string();
+
+        assert!(report.starts_with(
+            "WDKContentRoot could not be detected. Searched locations:\n  - environment variable \
+             WDK_CONTENT_ROOT: not set\n  - environment variable WDKContentRoot: D:\\missing"
+        ));
+        assert!(report.contains("\nhelp: install the WDK"));
+        assert!(report.contains("set WDK_CONTENT_ROOT to a WDK content root"));
+    }
 }
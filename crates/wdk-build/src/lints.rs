@@ -0,0 +1,187 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! This module provides the driver-specific `clippy` configuration used by the
+//! `driver-lints` and `static-analysis` tasks of `rust-driver-makefile.toml`.
+//!
+//! The default `clippy` configuration does not know about the constraints of
+//! kernel-mode code. The configuration in this module denies:
+//! - floating point arithmetic, which is not allowed in most kernel code paths
+//!   without saving the floating point state first
+//! - stack frames larger than [`KERNEL_STACK_SIZE_THRESHOLD`] bytes, since the
+//!   kernel stack is small and cannot grow
+//! - calls to the CRT string functions in [`BANNED_CRT_FUNCTIONS`], which do
+//!   not check the size of their destination buffers
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! use wdk_build::lints;
+//!
+//! let clippy_conf_dir = Path::new(r"target\debug\clippy");
+//! lints::write_clippy_config(clippy_conf_dir)?;
+//!
+//! let mut command = std::process::Command::new("cargo");
+//! command
+//!     .arg("clippy")
+//!     .env(lints::CLIPPY_CONF_DIR_ENV_VAR, clippy_conf_dir)
+//!     .arg("--");
+//! for lint in lints::DRIVER_CLIPPY_LINTS {
+//!     command.args(["-D", lint]);
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+/// The name of the environment variable that `clippy` uses to locate its
+/// `clippy.toml` configuration file
+pub const CLIPPY_CONF_DIR_ENV_VAR: &str = "CLIPPY_CONF_DIR";
+
+/// Names of the files that `clippy` reads its configuration from
+pub const CLIPPY_CONFIG_FILE_NAMES: [&str; 2] = ["clippy.toml", ".clippy.toml"];
+
+/// Size of the largest stack frame, in bytes, that is allowed by
+/// [`DRIVER_CLIPPY_LINTS`].
+///
+/// Kernel stacks are 12KB on x86 and 24KB on x64, and are shared by every
+/// function in the call chain, so a single frame should never come close to the
+/// smaller of the two.
+pub const KERNEL_STACK_SIZE_THRESHOLD: u64 = 12 * 1024;
+
+/// Lints that are denied in addition to all warnings when running `clippy`
+/// with the configuration generated by [`clippy_config`]
+pub const DRIVER_CLIPPY_LINTS: [&str; 7] = [
+    "warnings",
+    "unsafe_op_in_unsafe_fn",
+    "clippy::float_arithmetic",
+    "clippy::large_stack_frames",
+    "clippy::disallowed_methods",
+    "clippy::undocumented_unsafe_blocks",
+    "clippy::multiple_unsafe_ops_per_block",
+];
+
+/// A function whose use is denied by [`DRIVER_CLIPPY_LINTS`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BannedFunction {
+    /// Path of the function (ex. `wdk_sys::ntddk::strcpy`)
+    pub path: &'static str,
+    /// Explanation shown by `clippy` when the function is called
+    pub reason: &'static str,
+}
+
+/// CRT functions exported by `wdk-sys` that are banned in driver code. These
+/// functions do not check the size of their destination buffers, and have
+/// safe replacements in `ntstrsafe.h` or in Rust itself.
+pub const BANNED_CRT_FUNCTIONS: [BannedFunction; 10] = [
+    BannedFunction {
+        path: "wdk_sys::ntddk::strcpy",
+        reason: "does not check the size of the destination buffer, use RtlStringCbCopyA instead",
+    },
+    BannedFunction {
+        path: "wdk_sys::ntddk::strncpy",
+        reason: "does not guarantee a null-terminated result, use RtlStringCbCopyNA instead",
+    },
+    BannedFunction {
+        path: "wdk_sys::ntddk::strcat",
+        reason: "does not check the size of the destination buffer, use RtlStringCbCatA instead",
+    },
+    BannedFunction {
+        path: "wdk_sys::ntddk::strncat",
+        reason: "does not check the size of the destination buffer, use RtlStringCbCatNA instead",
+    },
+    BannedFunction {
+        path: "wdk_sys::ntddk::wcscpy",
+        reason: "does not check the size of the destination buffer, use RtlStringCbCopyW instead",
+    },
+    BannedFunction {
+        path: "wdk_sys::ntddk::wcsncpy",
+        reason: "does not guarantee a null-terminated result, use RtlStringCbCopyNW instead",
+    },
+    BannedFunction {
+        path: "wdk_sys::ntddk::wcscat",
+        reason: "does not check the size of the destination buffer, use RtlStringCbCatW instead",
+    },
+    BannedFunction {
+        path: "wdk_sys::ntddk::wcsncat",
+        reason: "does not check the size of the destination buffer, use RtlStringCbCatNW instead",
+    },
+    BannedFunction {
+        path: "wdk_sys::ntddk::strtok",
+        reason: "uses global state that is shared between threads, use str::split instead",
+    },
+    BannedFunction {
+        path: "wdk_sys::ntddk::wcstok",
+        reason: "uses global state that is shared between threads, use slice::split instead",
+    },
+];
+
+/// Returns the contents of a `clippy.toml` that configures the
+/// [`DRIVER_CLIPPY_LINTS`] for kernel-mode code
+#[must_use]
+pub fn clippy_config() -> String {
+    let mut config = format!("stack-size-threshold = {KERNEL_STACK_SIZE_THRESHOLD}\n");
+    config.push_str("disallowed-methods = [\n");
+    for BannedFunction { path, reason } in BANNED_CRT_FUNCTIONS {
+        writeln!(config, "    {{ path = {path:?}, reason = {reason:?} }},")
+            .expect("writing to a String should never fail");
+    }
+    config.push_str("]\n");
+    config
+}
+
+/// Writes the `clippy.toml` returned by [`clippy_config`] to `directory`, and
+/// returns the path of the written file.
+///
+/// `directory` is created if it does not exist. `clippy` uses it when
+/// [`CLIPPY_CONF_DIR_ENV_VAR`] is set to `directory`.
+///
+/// # Errors
+///
+/// This function returns a [`std::io::Error`] if `directory` cannot be created
+/// or the file cannot be written
+pub fn write_clippy_config(directory: &Path) -> Result<PathBuf, std::io::Error> {
+    std::fs::create_dir_all(directory)?;
+    let clippy_config_path = directory.join(CLIPPY_CONFIG_FILE_NAMES[0]);
+    std::fs::write(&clippy_config_path, clippy_config())?;
+    Ok(clippy_config_path)
+}
+
+/// Returns the path of the `clippy` configuration file in `directory`, if
+/// there is one
+#[must_use]
+pub fn find_clippy_config(directory: &Path) -> Option<PathBuf> {
+    CLIPPY_CONFIG_FILE_NAMES
+        .iter()
+        .map(|file_name| directory.join(file_name))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clippy_config_contains_stack_size_threshold() {
+        assert!(clippy_config()
+            .lines()
+            .any(|line| line == "stack-size-threshold = 12288"));
+    }
+
+    #[test]
+    fn clippy_config_contains_banned_crt_functions() {
+        let config = clippy_config();
+
+        assert!(config.contains(
+            r#"{ path = "wdk_sys::ntddk::strcpy", reason = "does not check the size of the destination buffer, use RtlStringCbCopyA instead" },"#
+        ));
+        assert_eq!(
+            config.matches("{ path = ").count(),
+            BANNED_CRT_FUNCTIONS.len()
+        );
+        assert!(config.ends_with("]\n"));
+    }
+}
@@ -0,0 +1,1410 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Parsing of WDK configuration from the `wdk` metadata tables of Cargo
+//! manifests.
+//!
+//! Packages can specify their WDK configuration in `[package.metadata.wdk]`,
+//! and workspaces can specify a configuration shared by all of their members
+//! in `[workspace.metadata.wdk]`:
+//!
+//! ```toml
+//! [workspace.metadata.wdk.driver-model]
+//! driver-type = "KMDF"
+//! kmdf-version-major = 1
+//! kmdf-version-minor = 33
+//! ```
+//!
+//! The WDK version used to build can also be pinned, instead of using the
+//! latest installed WDK:
+//!
+//! ```toml
+//! [workspace.metadata.wdk]
+//! wdk-version = "10.0.26100.0"
+//! ```
+//!
+//! The bindings of `wdk-sys` are generated for the pinned WDK when building via
+//! `cargo make` or `cargo wdk`, which pass it to the build script of `wdk-sys`
+//! via [`WDK_VERSION_ENV_VAR`](crate::WDK_VERSION_ENV_VAR).
+//!
+//! # Precedence
+//!
+//! 1. Keys in the `[package.metadata.wdk]` table of a workspace member replace
+//!    the same keys in `[workspace.metadata.wdk]`. Keys are replaced as a
+//!    whole, so a member that specifies a `driver-model` must specify the
+//!    entire `driver-model` table.
+//! 2. Keys in `[workspace.metadata.wdk]` apply to every workspace member that
+//!    does not override them.
+//! 3. Packages outside of the workspace (ex. dependencies from crates.io) only
+//!    use their own `[package.metadata.wdk]` table.
+//!
+//! All packages in a dependency graph must resolve to the same driver model
+//! and WDK version, since they are linked into the same driver binary. Only the
+//! dependency graph of the package being built is considered, so independent
+//! drivers in the same workspace can use different driver models.
+//!
+//! # Custom Bindings
+//!
+//! Packages can generate bindings to their own C headers (ex. register
+//! definitions of their hardware) with the same `bindgen` configuration as the
+//! WDK bindings in `wdk-sys`, by specifying a `[package.metadata.wdk.bindgen]`
+//! table and calling
+//! [`generate_custom_bindings`](crate::generate_custom_bindings) from their
+//! build script:
+//!
+//! ```toml
+//! [package.metadata.wdk.bindgen]
+//! headers = ["include/registers.h"]
+//! allowlist-files = [".*registers\\.h"]
+//! ```
+//!
+//! The `bindgen` table only applies to the package that specifies it, and is
+//! not inherited from `[workspace.metadata.wdk]`.
+//!
+//! # Link Libraries
+//!
+//! Packages can link against additional libraries (ex. import libraries of
+//! WDK components that `wdk-sys` does not know about) by listing them in the
+//! `link-libraries` key. The libraries of the package being built are added
+//! to [`Config::link_libraries`](crate::Config::link_libraries), and each of
+//! them is emitted as a `cargo::rustc-link-lib` directive by
+//! [`Config::configure_library_build`](crate::Config::configure_library_build)
+//! and [`Config::configure_binary_build`](crate::Config::configure_binary_build):
+//!
+//! ```toml
+//! [package.metadata.wdk]
+//! link-libraries = ["ksecdd", "static=mylib"]
+//! ```
+//!
+//! Like the `bindgen` table, `link-libraries` only applies to the package that
+//! specifies it.
+//!
+//! # Packaging and Deployment
+//!
+//! The `inf`, `signing` and `deploy` tables configure how tools like
+//! `cargo-wdk` package a driver and install it on a test machine:
+//!
+//! ```toml
+//! [package.metadata.wdk.inf]
+//! template = "driver.inx"
+//!
+//! [package.metadata.wdk.signing]
+//! cert-store = "WDRTestCertStore"
+//! cert-name = "WDRLocalTestCert"
+//! timestamp-server = "http://timestamp.digicert.com"
+//!
+//! [package.metadata.wdk.deploy]
+//! target-machine = "driver-test-vm"
+//! install-tool = "devcon"
+//! hardware-id = 'Root\SAMPLE_KMDF_HW_ID'
+//! ```
+//!
+//! These tables are not resolved across packages either. Tools read them from
+//! a single manifest via [`parse_from_manifest`].
+//!
+//! # Validation
+//!
+//! Besides its structure, the values of the `wdk` metadata are validated when
+//! it is parsed (ex. `kmdf-version-major` must be `1`), and errors name the
+//! offending key (ex. `driver-model.kmdf-version-major`).
+
+use std::{
+    collections::HashSet,
+    env,
+    path::{Path, PathBuf},
+};
+
+use cargo_metadata::{DependencyKind, Metadata, Package, PackageId};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{DriverConfig, KMDFConfig, UMDFConfig};
+
+/// Name of the key in `package.metadata` and `workspace.metadata` that
+/// contains the WDK configuration
+const WDK_METADATA_KEY: &str = "wdk";
+/// Name of the key in the `wdk` metadata table that contains the
+/// configuration of custom bindings
+const BINDGEN_METADATA_KEY: &str = "bindgen";
+/// Name of the key in the `wdk` metadata table that contains the additional
+/// libraries of a package
+const LINK_LIBRARIES_METADATA_KEY: &str = "link-libraries";
+/// Keys of the `wdk` metadata table that only apply to the package that
+/// specifies them, and are not inherited from `[workspace.metadata.wdk]`
+const PACKAGE_ONLY_METADATA_KEYS: [&str; 5] = [
+    BINDGEN_METADATA_KEY,
+    LINK_LIBRARIES_METADATA_KEY,
+    "inf",
+    "signing",
+    "deploy",
+];
+
+/// WDK configuration specified in the `wdk` metadata table of a Cargo
+/// manifest
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct WDKMetadata {
+    /// Driver model of the driver, and its associated configuration
+    pub driver_model: Option<DriverModel>,
+    /// Version of the WDK to build against (ex. `10.0.26100.0`). If not set,
+    /// the latest installed WDK is used.
+    pub wdk_version: Option<String>,
+    /// Bindings to custom C headers of the package. This is never resolved
+    /// across packages, see [`BindgenMetadata::try_from_package`].
+    pub bindgen: Option<BindgenMetadata>,
+    /// Additional libraries that the package links against. This is never
+    /// resolved across packages: [`WDKMetadata::try_from_cargo_metadata`] only
+    /// takes them from the current package, see
+    /// [`WDKMetadata::link_libraries_of_package`].
+    #[serde(default)]
+    pub link_libraries: Vec<String>,
+    /// INF of the driver package. This is never resolved across packages.
+    pub inf: Option<InfMetadata>,
+    /// Signing of the driver package. This is never resolved across
+    /// packages.
+    pub signing: Option<SigningMetadata>,
+    /// Deployment of the driver package to a test machine. This is never
+    /// resolved across packages.
+    pub deploy: Option<DeployMetadata>,
+}
+
+/// Bindings to custom C headers specified in the `bindgen` table of the `wdk`
+/// metadata
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct BindgenMetadata {
+    /// Paths of the C headers to generate bindings for, relative to the
+    /// directory of the package's manifest
+    pub headers: Vec<PathBuf>,
+    /// Regexes of the paths of the headers whose items are generated. If
+    /// neither this nor [`BindgenMetadata::allowlist_items`] is set, only the
+    /// items of [`BindgenMetadata::headers`] are generated.
+    #[serde(default)]
+    pub allowlist_files: Vec<String>,
+    /// Regexes of the names of the items that are generated
+    #[serde(default)]
+    pub allowlist_items: Vec<String>,
+    /// Regexes of the names of the items that are not generated
+    #[serde(default)]
+    pub blocklist_items: Vec<String>,
+    /// Whether items that allowlisted items depend on are also generated.
+    /// Defaults to `true`. This can be set to `false` to reuse the WDK types
+    /// from `wdk-sys` instead of generating them again.
+    pub allowlist_recursively: Option<bool>,
+    /// Name of the file in `OUT_DIR` that the bindings are written to.
+    /// Defaults to `bindings.rs`.
+    pub output: Option<String>,
+}
+
+/// INF of the driver package specified in the `inf` table of the `wdk`
+/// metadata
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct InfMetadata {
+    /// Path of the `.inx` template that the INF is rendered from, relative to
+    /// the directory of the package's manifest. Defaults to `<crate name>.inx`.
+    pub template: Option<PathBuf>,
+    /// Paths of additional files copied into the driver package (ex.
+    /// co-installers referenced by the INF), relative to the directory of the
+    /// package's manifest
+    #[serde(default)]
+    pub additional_files: Vec<PathBuf>,
+}
+
+/// Signing of the driver package specified in the `signing` table of the `wdk`
+/// metadata
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SigningMetadata {
+    /// Certificate store containing the certificate used to sign the driver
+    /// package
+    pub cert_store: Option<String>,
+    /// Name of the certificate used to sign the driver package
+    pub cert_name: Option<String>,
+    /// URL of the timestamp server used when signing
+    pub timestamp_server: Option<String>,
+    /// Whether the signatures of the driver package are verified after
+    /// signing
+    pub verify_signature: Option<bool>,
+}
+
+/// Deployment of the driver package specified in the `deploy` table of the
+/// `wdk` metadata
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DeployMetadata {
+    /// Host name of the test machine
+    pub target_machine: Option<String>,
+    /// How the driver package is copied to the test machine
+    pub transport: Option<DeployTransport>,
+    /// Directory on the test machine that the driver package is copied to
+    pub remote_directory: Option<String>,
+    /// Tool used to install the driver on the test machine
+    pub install_tool: Option<InstallTool>,
+    /// Hardware ID of the device to install the driver for. Required when
+    /// [`DeployMetadata::install_tool`] is [`InstallTool::Devcon`].
+    pub hardware_id: Option<String>,
+    /// Whether the test machine is rebooted after installing the driver
+    pub reboot: Option<bool>,
+}
+
+/// How the driver package is copied to the test machine
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeployTransport {
+    /// Copy via the administrative share of the test machine
+    Smb,
+    /// Copy via a PowerShell remoting session to the test machine
+    Winrm,
+}
+
+/// Tool used to install the driver on the test machine
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum InstallTool {
+    /// `pnputil /add-driver /install`
+    Pnputil,
+    /// `devcon install`
+    Devcon,
+}
+
+/// Driver model specified in the `driver-model` table of the `wdk` metadata
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(
+    tag = "driver-type",
+    rename_all_fields = "kebab-case",
+    deny_unknown_fields
+)]
+pub enum DriverModel {
+    /// Windows Driver Model
+    WDM,
+    /// Kernel Mode Driver Framework
+    KMDF {
+        /// Major KMDF Version
+        kmdf_version_major: u8,
+        /// Minor KMDF Version
+        kmdf_version_minor: u8,
+    },
+    /// User Mode Driver Framework
+    UMDF {
+        /// Major UMDF Version
+        umdf_version_major: u8,
+        /// Minor UMDF Version
+        umdf_version_minor: u8,
+    },
+}
+
+/// Errors that could result from resolving [`WDKMetadata`] from Cargo
+/// manifests
+#[derive(Debug, Error)]
+pub enum WDKMetadataError {
+    /// Error returned when the `wdk` metadata table of a manifest fails to be
+    /// deserialized
+    #[error("invalid wdk metadata in {source_description}")]
+    InvalidMetadata {
+        /// Description of the manifest table that failed to deserialize
+        source_description: String,
+        /// Underlying error returned when deserializing the metadata
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// Error returned when the `wdk` metadata table of a manifest deserializes,
+    /// but one of its values is invalid
+    #[error("invalid wdk metadata in {source_description}: {key} {reason}")]
+    InvalidValue {
+        /// Description of the manifest table that contains the invalid value
+        source_description: String,
+        /// Path of the invalid key in the `wdk` metadata table (ex.
+        /// `driver-model.kmdf-version-major`)
+        key: String,
+        /// Requirement that the value does not satisfy (ex. `must be 1`)
+        reason: String,
+    },
+
+    /// Error returned when a manifest cannot be read
+    #[error("failed to read {}", path.display())]
+    IoError {
+        /// Path of the manifest that could not be read
+        path: PathBuf,
+        /// Underlying error returned by the file operation
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// Error returned when a manifest is not valid TOML
+    #[error("failed to parse {}", path.display())]
+    InvalidManifest {
+        /// Path of the manifest
+        path: PathBuf,
+        /// Underlying error returned when parsing the manifest
+        #[source]
+        source: toml::de::Error,
+    },
+
+    /// Error returned when two packages in the same dependency graph specify
+    /// different driver models
+    #[error(
+        "conflicting driver models in wdk metadata: {package_1} specifies {driver_model_1:?}, but \
+         {package_2} specifies {driver_model_2:?}"
+    )]
+    ConflictingDriverModels {
+        /// Package that specified the first driver model
+        package_1: PackageId,
+        /// Driver model specified by the first package
+        driver_model_1: DriverModel,
+        /// Package that specified the second driver model
+        package_2: PackageId,
+        /// Driver model specified by the second package
+        driver_model_2: DriverModel,
+    },
+
+    /// Error returned when two packages in the same dependency graph pin
+    /// different WDK versions
+    #[error(
+        "conflicting WDK versions in wdk metadata: {package_1} pins {wdk_version_1}, but \
+         {package_2} pins {wdk_version_2}"
+    )]
+    ConflictingWDKVersions {
+        /// Package that pinned the first WDK version
+        package_1: PackageId,
+        /// WDK version pinned by the first package
+        wdk_version_1: String,
+        /// Package that pinned the second WDK version
+        package_2: PackageId,
+        /// WDK version pinned by the second package
+        wdk_version_2: String,
+    },
+}
+
+impl WDKMetadata {
+    /// Resolves the [`WDKMetadata`] of the dependency graph of the current
+    /// package, following the precedence order documented in the
+    /// [module documentation](self). The current package is the package whose
+    /// build script is running, or otherwise the root package of
+    /// `cargo_metadata`. If there is no current package (ex. `cargo metadata`
+    /// was run in a virtual workspace), every package in the resolved
+    /// dependency graph is considered. `link-libraries` are taken from the
+    /// current package only.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if:
+    ///     * any `wdk` metadata table fails to deserialize
+    ///     * packages in the dependency graph specify conflicting driver models
+    ///       or WDK versions
+    pub fn try_from_cargo_metadata(cargo_metadata: &Metadata) -> Result<Self, WDKMetadataError> {
+        Self::try_from_dependency_graph(cargo_metadata, current_package_id(cargo_metadata))
+    }
+
+    /// Resolves the [`WDKMetadata`] of the dependency graph of the package
+    /// with `package_id`, like [`WDKMetadata::try_from_cargo_metadata`] does
+    /// for the current package. This resolves the metadata of each driver of
+    /// a workspace independently (ex. a KMDF and a UMDF driver in the same
+    /// virtual workspace).
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if:
+    ///     * any `wdk` metadata table fails to deserialize
+    ///     * packages in the dependency graph specify conflicting driver models
+    ///       or WDK versions
+    pub fn try_from_cargo_metadata_for_package(
+        cargo_metadata: &Metadata,
+        package_id: &PackageId,
+    ) -> Result<Self, WDKMetadataError> {
+        Self::try_from_dependency_graph(cargo_metadata, Some(package_id))
+    }
+
+    /// Resolves the [`WDKMetadata`] of the dependency graph rooted at
+    /// `root_package_id`, or of every package if there is no root. The
+    /// `link-libraries` are taken from the root package only.
+    fn try_from_dependency_graph(
+        cargo_metadata: &Metadata,
+        root_package_id: Option<&PackageId>,
+    ) -> Result<Self, WDKMetadataError> {
+        let workspace_metadata = cargo_metadata.workspace_metadata.get(WDK_METADATA_KEY);
+
+        let mut wdk_metadata = resolve(
+            workspace_metadata,
+            packages_in_dependency_graph(cargo_metadata, root_package_id)
+                .into_iter()
+                .map(|package| {
+                    (
+                        &package.id,
+                        cargo_metadata.workspace_members.contains(&package.id),
+                        package.metadata.get(WDK_METADATA_KEY),
+                    )
+                }),
+        )?;
+        if let Some(root_package) = root_package_id.and_then(|root_package_id| {
+            cargo_metadata
+                .packages
+                .iter()
+                .find(|package| &package.id == root_package_id)
+        }) {
+            wdk_metadata.link_libraries = Self::link_libraries_of_package(root_package)?;
+        }
+        Ok(wdk_metadata)
+    }
+
+    /// Parses the libraries listed in the `link-libraries` key of the
+    /// `[package.metadata.wdk]` table of `package`. Returns an empty list if
+    /// the package does not specify any.
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`WDKMetadataError::InvalidMetadata`] if the
+    /// `link-libraries` key fails to deserialize
+    pub fn link_libraries_of_package(package: &Package) -> Result<Vec<String>, WDKMetadataError> {
+        package
+            .metadata
+            .get(WDK_METADATA_KEY)
+            .and_then(|wdk_metadata| wdk_metadata.get(LINK_LIBRARIES_METADATA_KEY))
+            .map_or_else(
+                || Ok(Vec::new()),
+                |link_libraries| {
+                    let source_description =
+                        format!("link-libraries of [package.metadata.wdk] of {}", package.id);
+                    let link_libraries: Vec<String> =
+                        serde_json::from_value(link_libraries.clone()).map_err(|source| {
+                            WDKMetadataError::InvalidMetadata {
+                                source_description: source_description.clone(),
+                                source,
+                            }
+                        })?;
+                    validate_link_libraries(&link_libraries)
+                        .map_err(|invalid_value| invalid_value.into_error(source_description))?;
+                    Ok(link_libraries)
+                },
+            )
+    }
+
+    /// Validates the values of the metadata that its structure does not
+    /// constrain, ex. that `kmdf-version-major` is `1`
+    fn validate(&self) -> Result<(), InvalidValue> {
+        if let Some(driver_model) = self.driver_model {
+            driver_model
+                .validate()
+                .map_err(|invalid_value| invalid_value.in_table("driver-model"))?;
+        }
+        if let Some(wdk_version) = &self.wdk_version {
+            validate_wdk_version(wdk_version)?;
+        }
+        if let Some(bindgen) = &self.bindgen {
+            bindgen
+                .validate()
+                .map_err(|invalid_value| invalid_value.in_table(BINDGEN_METADATA_KEY))?;
+        }
+        validate_link_libraries(&self.link_libraries)?;
+        if let Some(inf) = &self.inf {
+            inf.validate()
+                .map_err(|invalid_value| invalid_value.in_table("inf"))?;
+        }
+        if let Some(signing) = &self.signing {
+            signing
+                .validate()
+                .map_err(|invalid_value| invalid_value.in_table("signing"))?;
+        }
+        if let Some(deploy) = &self.deploy {
+            deploy
+                .validate()
+                .map_err(|invalid_value| invalid_value.in_table("deploy"))?;
+        }
+        Ok(())
+    }
+}
+
+impl BindgenMetadata {
+    /// Parses the [`BindgenMetadata`] from the `[package.metadata.wdk.bindgen]`
+    /// table of `package`, if it has one
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`WDKMetadataError::InvalidMetadata`] if the
+    /// `bindgen` table fails to deserialize
+    pub fn try_from_package(package: &Package) -> Result<Option<Self>, WDKMetadataError> {
+        package
+            .metadata
+            .get(WDK_METADATA_KEY)
+            .and_then(|wdk_metadata| wdk_metadata.get(BINDGEN_METADATA_KEY))
+            .map(|bindgen_metadata| {
+                let source_description =
+                    format!("[package.metadata.wdk.bindgen] of {}", package.id);
+                let bindgen_metadata: Self = serde_json::from_value(bindgen_metadata.clone())
+                    .map_err(|source| WDKMetadataError::InvalidMetadata {
+                        source_description: source_description.clone(),
+                        source,
+                    })?;
+                bindgen_metadata
+                    .validate()
+                    .map_err(|invalid_value| invalid_value.into_error(source_description))?;
+                Ok(bindgen_metadata)
+            })
+            .transpose()
+    }
+
+    fn validate(&self) -> Result<(), InvalidValue> {
+        if self.headers.is_empty() {
+            return Err(InvalidValue::new(
+                "headers",
+                "must list at least one header",
+            ));
+        }
+        if let Some(output) = &self.output {
+            if Path::new(output).file_name() != Some(output.as_ref()) {
+                return Err(InvalidValue::new(
+                    "output",
+                    "must be a file name, not a path",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl InfMetadata {
+    fn validate(&self) -> Result<(), InvalidValue> {
+        if let Some(template) = &self.template {
+            if template
+                .extension()
+                .is_none_or(|extension| extension != "inx")
+            {
+                return Err(InvalidValue::new(
+                    "template",
+                    "must be the path of an .inx file",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SigningMetadata {
+    fn validate(&self) -> Result<(), InvalidValue> {
+        validate_not_empty("cert-store", self.cert_store.as_deref())?;
+        validate_not_empty("cert-name", self.cert_name.as_deref())?;
+        if let Some(timestamp_server) = &self.timestamp_server {
+            if !(timestamp_server.starts_with("http://")
+                || timestamp_server.starts_with("https://"))
+            {
+                return Err(InvalidValue::new(
+                    "timestamp-server",
+                    "must be an http or https URL",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DeployMetadata {
+    fn validate(&self) -> Result<(), InvalidValue> {
+        validate_not_empty("target-machine", self.target_machine.as_deref())?;
+        validate_not_empty("remote-directory", self.remote_directory.as_deref())?;
+        validate_not_empty("hardware-id", self.hardware_id.as_deref())?;
+        if self.install_tool == Some(InstallTool::Devcon) && self.hardware_id.is_none() {
+            return Err(InvalidValue::new(
+                "hardware-id",
+                "must be set when install-tool is \"devcon\"",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl DriverModel {
+    fn validate(self) -> Result<(), InvalidValue> {
+        match self {
+            Self::KMDF {
+                kmdf_version_major, ..
+            } if kmdf_version_major != 1 => {
+                Err(InvalidValue::new("kmdf-version-major", "must be 1"))
+            }
+            Self::UMDF {
+                umdf_version_major, ..
+            } if umdf_version_major != 2 => {
+                Err(InvalidValue::new("umdf-version-major", "must be 2"))
+            }
+            Self::WDM | Self::KMDF { .. } | Self::UMDF { .. } => Ok(()),
+        }
+    }
+}
+
+impl From<DriverModel> for DriverConfig {
+    fn from(driver_model: DriverModel) -> Self {
+        match driver_model {
+            DriverModel::WDM => Self::WDM(),
+            DriverModel::KMDF {
+                kmdf_version_major,
+                kmdf_version_minor,
+            } => Self::KMDF(KMDFConfig {
+                kmdf_version_major,
+                kmdf_version_minor,
+            }),
+            DriverModel::UMDF {
+                umdf_version_major,
+                umdf_version_minor,
+            } => Self::UMDF(UMDFConfig {
+                umdf_version_major,
+                umdf_version_minor,
+            }),
+        }
+    }
+}
+
+/// Parses the `wdk` metadata of the manifest at `manifest_path`
+///
+/// Unlike [`WDKMetadata::try_from_cargo_metadata`], the metadata is not
+/// resolved across the dependency graph. This is meant for tools that need the
+/// configuration of a single package, including the tables that are never
+/// resolved (ex. `inf`, `signing` and `deploy`).
+///
+/// If the manifest has a `[package.metadata.wdk]` table, it is returned with
+/// the keys of `[workspace.metadata.wdk]` of the same manifest that it does not
+/// override. Otherwise, `[workspace.metadata.wdk]` is returned. Returns `None`
+/// if the manifest has neither.
+///
+/// # Errors
+///
+/// This function will return an error if:
+///     * the manifest cannot be read, or is not valid TOML
+///     * the `wdk` metadata fails to deserialize, or any of its values is
+///       invalid
+pub fn parse_from_manifest(manifest_path: &Path) -> Result<Option<WDKMetadata>, WDKMetadataError> {
+    let contents =
+        std::fs::read_to_string(manifest_path).map_err(|source| WDKMetadataError::IoError {
+            path: manifest_path.to_path_buf(),
+            source,
+        })?;
+    let manifest: toml::Table =
+        toml::from_str(&contents).map_err(|source| WDKMetadataError::InvalidManifest {
+            path: manifest_path.to_path_buf(),
+            source,
+        })?;
+
+    let wdk_metadata_table = |table_name: &str| {
+        let source_description =
+            format!("[{table_name}.metadata.wdk] of {}", manifest_path.display());
+        manifest
+            .get(table_name)
+            .and_then(|table| table.get("metadata"))
+            .and_then(|metadata| metadata.get(WDK_METADATA_KEY))
+            .map(|wdk_metadata| {
+                serde_json::to_value(wdk_metadata).map_err(|source| {
+                    WDKMetadataError::InvalidMetadata {
+                        source_description: source_description.clone(),
+                        source,
+                    }
+                })
+            })
+            .transpose()
+            .map(|wdk_metadata| wdk_metadata.map(|wdk_metadata| (wdk_metadata, source_description)))
+    };
+
+    let workspace_metadata = wdk_metadata_table("workspace")?;
+    let (metadata, source_description) = match (workspace_metadata, wdk_metadata_table("package")?)
+    {
+        (workspace_metadata, Some((package_metadata, source_description))) => (
+            merge(
+                workspace_metadata
+                    .map(|(workspace_metadata, _)| workspace_metadata)
+                    .as_ref(),
+                &package_metadata,
+            ),
+            source_description,
+        ),
+        (Some(workspace_metadata), None) => workspace_metadata,
+        (None, None) => return Ok(None),
+    };
+    parse(metadata, source_description).map(Some)
+}
+
+/// Returns the id of the package whose build script is running, or otherwise
+/// the root of the resolved dependency graph of `cargo_metadata`
+fn current_package_id(cargo_metadata: &Metadata) -> Option<&PackageId> {
+    // Outside of a build script, the root is the package in the directory that
+    // `cargo metadata` was run from
+    if let (Ok(manifest_dir), Ok(package_name)) =
+        (env::var("CARGO_MANIFEST_DIR"), env::var("CARGO_PKG_NAME"))
+    {
+        let manifest_path = Path::new(&manifest_dir).join("Cargo.toml");
+        if let Some(package) = cargo_metadata.packages.iter().find(|package| {
+            package.name == package_name && package.manifest_path.as_std_path() == manifest_path
+        }) {
+            return Some(&package.id);
+        }
+    }
+
+    cargo_metadata.resolve.as_ref()?.root.as_ref()
+}
+
+/// Returns the packages reachable from `root` in the resolved dependency graph
+/// of `cargo_metadata`, including `root` itself. Dev-dependencies are not
+/// followed, since they are not linked into the driver. If there is no `root`,
+/// every package in the resolved dependency graph is returned.
+fn packages_in_dependency_graph<'a>(
+    cargo_metadata: &'a Metadata,
+    root: Option<&'a PackageId>,
+) -> Vec<&'a Package> {
+    let Some(resolve) = cargo_metadata.resolve.as_ref() else {
+        return cargo_metadata.packages.iter().collect();
+    };
+
+    let reachable_package_ids: HashSet<&PackageId> = match root {
+        Some(root) => {
+            let mut reachable_package_ids = HashSet::from([root]);
+            let mut unvisited_package_ids = vec![root];
+            while let Some(package_id) = unvisited_package_ids.pop() {
+                let Some(node) = resolve.nodes.iter().find(|node| &node.id == package_id) else {
+                    continue;
+                };
+                for dependency in &node.deps {
+                    // `dep_kinds` is empty on Cargo versions older than 1.41
+                    let is_linked = dependency.dep_kinds.is_empty()
+                        || dependency
+                            .dep_kinds
+                            .iter()
+                            .any(|dep_kind| dep_kind.kind != DependencyKind::Development);
+                    if is_linked && reachable_package_ids.insert(&dependency.pkg) {
+                        unvisited_package_ids.push(&dependency.pkg);
+                    }
+                }
+            }
+            reachable_package_ids
+        }
+        None => resolve.nodes.iter().map(|node| &node.id).collect(),
+    };
+
+    cargo_metadata
+        .packages
+        .iter()
+        .filter(|package| reachable_package_ids.contains(&package.id))
+        .collect()
+}
+
+/// Resolves [`WDKMetadata`] from the `wdk` metadata table of the workspace,
+/// and the package id, workspace membership and `wdk` metadata table of every
+/// package in the dependency graph
+fn resolve<'a>(
+    workspace_metadata: Option<&serde_json::Value>,
+    packages: impl IntoIterator<Item = (&'a PackageId, bool, Option<&'a serde_json::Value>)>,
+) -> Result<WDKMetadata, WDKMetadataError> {
+    let workspace_wdk_metadata = workspace_metadata
+        .map(|workspace_metadata| {
+            parse(
+                workspace_metadata.clone(),
+                "[workspace.metadata.wdk]".to_string(),
+            )
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    let mut resolved_driver_model: Option<(&PackageId, DriverModel)> = None;
+    let mut resolved_wdk_version: Option<(&PackageId, String)> = None;
+    for (package_id, is_workspace_member, package_metadata) in packages {
+        let package_wdk_metadata = match (package_metadata, is_workspace_member) {
+            (Some(package_metadata), true) => parse(
+                merge(workspace_metadata, package_metadata),
+                format!("[package.metadata.wdk] of {package_id}"),
+            )?,
+            (Some(package_metadata), false) => parse(
+                package_metadata.clone(),
+                format!("[package.metadata.wdk] of {package_id}"),
+            )?,
+            (None, true) => workspace_wdk_metadata.clone(),
+            (None, false) => continue,
+        };
+
+        if let Some(driver_model) = package_wdk_metadata.driver_model {
+            match resolved_driver_model {
+                Some((resolved_package_id, resolved_driver_model))
+                    if resolved_driver_model != driver_model =>
+                {
+                    return Err(WDKMetadataError::ConflictingDriverModels {
+                        package_1: resolved_package_id.clone(),
+                        driver_model_1: resolved_driver_model,
+                        package_2: package_id.clone(),
+                        driver_model_2: driver_model,
+                    });
+                }
+                Some(_) => {}
+                None => resolved_driver_model = Some((package_id, driver_model)),
+            }
+        }
+
+        if let Some(wdk_version) = package_wdk_metadata.wdk_version {
+            match resolved_wdk_version {
+                Some((resolved_package_id, resolved_wdk_version))
+                    if resolved_wdk_version != wdk_version =>
+                {
+                    return Err(WDKMetadataError::ConflictingWDKVersions {
+                        package_1: resolved_package_id.clone(),
+                        wdk_version_1: resolved_wdk_version,
+                        package_2: package_id.clone(),
+                        wdk_version_2: wdk_version,
+                    });
+                }
+                Some(_) => {}
+                None => resolved_wdk_version = Some((package_id, wdk_version)),
+            }
+        }
+    }
+
+    Ok(WDKMetadata {
+        driver_model: resolved_driver_model
+            .map(|(_, driver_model)| driver_model)
+            .or(workspace_wdk_metadata.driver_model),
+        wdk_version: resolved_wdk_version
+            .map(|(_, wdk_version)| wdk_version)
+            .or(workspace_wdk_metadata.wdk_version),
+        bindgen: None,
+        link_libraries: Vec::new(),
+        inf: None,
+        signing: None,
+        deploy: None,
+    })
+}
+
+/// Merges the `wdk` metadata table of a workspace member into the `wdk`
+/// metadata table of its workspace. Keys in `package_metadata` replace the
+/// same keys in `workspace_metadata`, and the keys of `workspace_metadata`
+/// that only apply to the workspace itself are dropped.
+fn merge(
+    workspace_metadata: Option<&serde_json::Value>,
+    package_metadata: &serde_json::Value,
+) -> serde_json::Value {
+    match (workspace_metadata, package_metadata) {
+        (
+            Some(serde_json::Value::Object(workspace_table)),
+            serde_json::Value::Object(package_table),
+        ) => {
+            let mut merged_table = workspace_table.clone();
+            merged_table.retain(|key, _| !PACKAGE_ONLY_METADATA_KEYS.contains(&key.as_str()));
+            merged_table.extend(
+                package_table
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone())),
+            );
+            serde_json::Value::Object(merged_table)
+        }
+        _ => package_metadata.clone(),
+    }
+}
+
+/// Deserializes and validates the `wdk` metadata table described by
+/// `source_description`
+fn parse(
+    metadata: serde_json::Value,
+    source_description: String,
+) -> Result<WDKMetadata, WDKMetadataError> {
+    let wdk_metadata: WDKMetadata =
+        serde_json::from_value(metadata).map_err(|source| WDKMetadataError::InvalidMetadata {
+            source_description: source_description.clone(),
+            source,
+        })?;
+    wdk_metadata
+        .validate()
+        .map_err(|invalid_value| invalid_value.into_error(source_description))?;
+    Ok(wdk_metadata)
+}
+
+/// Value of the `wdk` metadata that deserializes, but fails validation
+#[derive(Debug)]
+struct InvalidValue {
+    /// Path of the invalid key, relative to the table being validated
+    key: String,
+    /// Requirement that the value does not satisfy
+    reason: String,
+}
+
+impl InvalidValue {
+    fn new(key: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            reason: reason.into(),
+        }
+    }
+
+    /// Prefixes the key with the name of the table that contains it
+    fn in_table(self, table: &str) -> Self {
+        Self {
+            key: format!("{table}.{}", self.key),
+            ..self
+        }
+    }
+
+    fn into_error(self, source_description: String) -> WDKMetadataError {
+        WDKMetadataError::InvalidValue {
+            source_description,
+            key: self.key,
+            reason: self.reason,
+        }
+    }
+}
+
+/// Validates that `wdk_version` has the form of the versioned directories of a
+/// WDK installation (ex. `10.0.26100.0`)
+fn validate_wdk_version(wdk_version: &str) -> Result<(), InvalidValue> {
+    let components = wdk_version.split('.').collect::<Vec<_>>();
+    if components.len() == 4
+        && components[0] == "10"
+        && components
+            .iter()
+            .all(|component| !component.is_empty() && component.chars().all(|c| c.is_ascii_digit()))
+    {
+        Ok(())
+    } else {
+        Err(InvalidValue::new(
+            "wdk-version",
+            "must be a version of the form 10.0.<build>.0 (ex. 10.0.26100.0)",
+        ))
+    }
+}
+
+/// Validates that every entry of `link_libraries` names a library, optionally
+/// prefixed with its kind (ex. `static=mylib`)
+fn validate_link_libraries(link_libraries: &[String]) -> Result<(), InvalidValue> {
+    for (index, link_library) in link_libraries.iter().enumerate() {
+        let (kind, name) = link_library
+            .split_once('=')
+            .map_or((None, link_library.as_str()), |(kind, name)| {
+                (Some(kind), name)
+            });
+        if name.is_empty() || kind.is_some_and(str::is_empty) {
+            return Err(InvalidValue::new(
+                format!("{LINK_LIBRARIES_METADATA_KEY}[{index}]"),
+                "must be a library name, optionally prefixed with its kind (ex. static=mylib)",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Validates that the value of `key` is not empty if it is set
+fn validate_not_empty(key: &str, value: Option<&str>) -> Result<(), InvalidValue> {
+    if value.is_some_and(str::is_empty) {
+        return Err(InvalidValue::new(key, "must not be empty"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn package_id(name: &str) -> PackageId {
+        PackageId {
+            repr: format!("{name} 0.1.0 (path+file:///{name})"),
+        }
+    }
+
+    /// Builds the `cargo metadata` of a workspace whose `members` are
+    /// `(name, wdk metadata, dependencies)`, with the dependency graph rooted at
+    /// `root`
+    fn workspace_cargo_metadata(
+        members: &[(&str, serde_json::Value, &[&str])],
+        root: &str,
+    ) -> Metadata {
+        let packages = members
+            .iter()
+            .map(|(name, wdk_metadata, _)| {
+                json!({
+                    "name": name,
+                    "version": "0.1.0",
+                    "id": package_id(name).repr,
+                    "dependencies": [],
+                    "targets": [],
+                    "features": {},
+                    "manifest_path": format!("/workspace/{name}/Cargo.toml"),
+                    "metadata": { "wdk": wdk_metadata },
+                })
+            })
+            .collect::<Vec<_>>();
+        let nodes = members
+            .iter()
+            .map(|(name, _, dependencies)| {
+                json!({
+                    "id": package_id(name).repr,
+                    "dependencies": dependencies
+                        .iter()
+                        .map(|dependency| package_id(dependency).repr)
+                        .collect::<Vec<_>>(),
+                    "deps": dependencies
+                        .iter()
+                        .map(|dependency| json!({
+                            "name": dependency,
+                            "pkg": package_id(dependency).repr,
+                            "dep_kinds": [{ "kind": null, "target": null }],
+                        }))
+                        .collect::<Vec<_>>(),
+                    "features": [],
+                })
+            })
+            .collect::<Vec<_>>();
+
+        serde_json::from_value(json!({
+            "packages": packages,
+            "workspace_members": members
+                .iter()
+                .map(|(name, ..)| package_id(name).repr)
+                .collect::<Vec<_>>(),
+            "workspace_default_members": [],
+            "resolve": {
+                "nodes": nodes,
+                "root": package_id(root).repr,
+            },
+            "workspace_root": "/workspace",
+            "target_directory": "/workspace/target",
+            "version": 1,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn package_metadata_overrides_workspace_metadata() {
+        let workspace_metadata = json!({
+            "driver-model": {
+                "driver-type": "KMDF",
+                "kmdf-version-major": 1,
+                "kmdf-version-minor": 33,
+            },
+        });
+        let package_metadata = json!({
+            "driver-model": {
+                "driver-type": "UMDF",
+                "umdf-version-major": 2,
+                "umdf-version-minor": 31,
+            },
+        });
+        let driver = package_id("driver");
+
+        let wdk_metadata = resolve(
+            Some(&workspace_metadata),
+            [(&driver, true, Some(&package_metadata))],
+        )
+        .unwrap();
+
+        assert_eq!(
+            wdk_metadata.driver_model,
+            Some(DriverModel::UMDF {
+                umdf_version_major: 2,
+                umdf_version_minor: 31,
+            })
+        );
+    }
+
+    #[test]
+    fn workspace_metadata_applies_to_members_only() {
+        let workspace_metadata = json!({
+            "driver-model": {
+                "driver-type": "KMDF",
+                "kmdf-version-major": 1,
+                "kmdf-version-minor": 33,
+            },
+        });
+        let dependency_metadata = json!({
+            "driver-model": {
+                "driver-type": "WDM",
+            },
+        });
+        let driver = package_id("driver");
+        let dependency = package_id("dependency");
+
+        let error = resolve(
+            Some(&workspace_metadata),
+            [
+                (&driver, true, None),
+                (&dependency, false, Some(&dependency_metadata)),
+            ],
+        )
+        .unwrap_err();
+
+        assert!(matches!(
+            error,
+            WDKMetadataError::ConflictingDriverModels {
+                driver_model_1: DriverModel::KMDF { .. },
+                driver_model_2: DriverModel::WDM,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn independent_drivers_with_different_driver_models() {
+        let kmdf_driver_metadata = json!({
+            "driver-model": {
+                "driver-type": "KMDF",
+                "kmdf-version-major": 1,
+                "kmdf-version-minor": 33,
+            },
+            "link-libraries": ["ksecdd"],
+        });
+        let umdf_driver_metadata = json!({
+            "driver-model": {
+                "driver-type": "UMDF",
+                "umdf-version-major": 2,
+                "umdf-version-minor": 31,
+            },
+        });
+        let members: &[(&str, serde_json::Value, &[&str])] = &[
+            ("kmdf-driver", kmdf_driver_metadata, &["common"]),
+            ("umdf-driver", umdf_driver_metadata, &["common"]),
+            ("common", json!({}), &[]),
+        ];
+
+        let kmdf_wdk_metadata =
+            WDKMetadata::try_from_cargo_metadata(&workspace_cargo_metadata(members, "kmdf-driver"))
+                .unwrap();
+        assert_eq!(
+            kmdf_wdk_metadata.driver_model,
+            Some(DriverModel::KMDF {
+                kmdf_version_major: 1,
+                kmdf_version_minor: 33,
+            })
+        );
+        assert_eq!(kmdf_wdk_metadata.link_libraries, ["ksecdd"]);
+
+        let umdf_wdk_metadata =
+            WDKMetadata::try_from_cargo_metadata(&workspace_cargo_metadata(members, "umdf-driver"))
+                .unwrap();
+        assert_eq!(
+            umdf_wdk_metadata.driver_model,
+            Some(DriverModel::UMDF {
+                umdf_version_major: 2,
+                umdf_version_minor: 31,
+            })
+        );
+        assert!(umdf_wdk_metadata.link_libraries.is_empty());
+
+        // Each driver resolves its own metadata, regardless of the root package
+        let umdf_wdk_metadata = WDKMetadata::try_from_cargo_metadata_for_package(
+            &workspace_cargo_metadata(members, "kmdf-driver"),
+            &package_id("umdf-driver"),
+        )
+        .unwrap();
+        assert_eq!(
+            umdf_wdk_metadata.driver_model,
+            Some(DriverModel::UMDF {
+                umdf_version_major: 2,
+                umdf_version_minor: 31,
+            })
+        );
+    }
+
+    #[test]
+    fn conflicting_wdk_versions() {
+        let workspace_metadata = json!({
+            "wdk-version": "10.0.22621.0",
+        });
+        let dependency_metadata = json!({
+            "wdk-version": "10.0.26100.0",
+        });
+        let driver = package_id("driver");
+        let dependency = package_id("dependency");
+
+        let wdk_metadata = resolve(Some(&workspace_metadata), [(&driver, true, None)]).unwrap();
+        assert_eq!(wdk_metadata.wdk_version.as_deref(), Some("10.0.22621.0"));
+
+        let error = resolve(
+            Some(&workspace_metadata),
+            [
+                (&driver, true, None),
+                (&dependency, false, Some(&dependency_metadata)),
+            ],
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            WDKMetadataError::ConflictingWDKVersions {
+                wdk_version_1,
+                wdk_version_2,
+                ..
+            } if wdk_version_1 == "10.0.22621.0" && wdk_version_2 == "10.0.26100.0"
+        ));
+    }
+
+    #[test]
+    fn bindgen_metadata_is_not_resolved() {
+        let package_metadata = json!({
+            "wdk-version": "10.0.26100.0",
+            "bindgen": {
+                "headers": ["include/registers.h"],
+            },
+        });
+        let driver = package_id("driver");
+
+        let wdk_metadata = resolve(None, [(&driver, true, Some(&package_metadata))]).unwrap();
+
+        assert_eq!(wdk_metadata.wdk_version.as_deref(), Some("10.0.26100.0"));
+        assert_eq!(wdk_metadata.bindgen, None);
+    }
+
+    #[test]
+    fn link_libraries_are_not_resolved() {
+        let package_metadata = json!({
+            "link-libraries": ["ksecdd"],
+        });
+        let driver = package_id("driver");
+
+        let wdk_metadata = resolve(None, [(&driver, true, Some(&package_metadata))]).unwrap();
+
+        assert!(wdk_metadata.link_libraries.is_empty());
+    }
+
+    #[test]
+    fn parse_bindgen_metadata() {
+        let bindgen_metadata: BindgenMetadata = serde_json::from_value(json!({
+            "headers": ["include/registers.h"],
+            "allowlist-items": ["REG_.*"],
+            "allowlist-recursively": false,
+        }))
+        .unwrap();
+
+        assert_eq!(
+            bindgen_metadata,
+            BindgenMetadata {
+                headers: vec![PathBuf::from("include/registers.h")],
+                allowlist_items: vec!["REG_.*".to_string()],
+                allowlist_recursively: Some(false),
+                ..BindgenMetadata::default()
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_metadata() {
+        let package_metadata = json!({
+            "driver-model": {
+                "driver-type": "KMDF",
+                "kmdf-version-major": 1,
+            },
+        });
+        let driver = package_id("driver");
+
+        let error = resolve(None, [(&driver, true, Some(&package_metadata))]).unwrap_err();
+
+        assert!(matches!(error, WDKMetadataError::InvalidMetadata { .. }));
+    }
+
+    #[test]
+    fn invalid_framework_version() {
+        let package_metadata = json!({
+            "driver-model": {
+                "driver-type": "KMDF",
+                "kmdf-version-major": 2,
+                "kmdf-version-minor": 33,
+            },
+        });
+        let driver = package_id("driver");
+
+        let error = resolve(None, [(&driver, true, Some(&package_metadata))]).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "invalid wdk metadata in [package.metadata.wdk] of driver 0.1.0 \
+             (path+file:///driver): driver-model.kmdf-version-major must be 1"
+        );
+    }
+
+    #[test]
+    fn invalid_values() {
+        for (metadata, expected_key) in [
+            (json!({ "wdk-version": "10.0.26100" }), "wdk-version"),
+            (
+                json!({ "link-libraries": ["ksecdd", "static="] }),
+                "link-libraries[1]",
+            ),
+            (json!({ "bindgen": { "headers": [] } }), "bindgen.headers"),
+            (
+                json!({ "bindgen": { "headers": ["registers.h"], "output": "out/bindings.rs" } }),
+                "bindgen.output",
+            ),
+            (
+                json!({ "inf": { "template": "driver.inf" } }),
+                "inf.template",
+            ),
+            (
+                json!({ "signing": { "timestamp-server": "timestamp.digicert.com" } }),
+                "signing.timestamp-server",
+            ),
+            (
+                json!({ "deploy": { "install-tool": "devcon" } }),
+                "deploy.hardware-id",
+            ),
+        ] {
+            let error = parse(metadata, "test".to_string()).unwrap_err();
+
+            assert!(
+                matches!(&error, WDKMetadataError::InvalidValue { key, .. } if key == expected_key),
+                "unexpected error: {error:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_packaging_metadata() {
+        let wdk_metadata = parse(
+            json!({
+                "inf": { "template": "driver.inx" },
+                "signing": { "cert-name": "WDRLocalTestCert", "verify-signature": true },
+                "deploy": {
+                    "target-machine": "driver-test-vm",
+                    "transport": "winrm",
+                    "install-tool": "devcon",
+                    "hardware-id": "Root\\SAMPLE_KMDF_HW_ID",
+                },
+            }),
+            "test".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            wdk_metadata.inf.unwrap().template,
+            Some(PathBuf::from("driver.inx"))
+        );
+        assert_eq!(wdk_metadata.signing.unwrap().verify_signature, Some(true));
+        let deploy = wdk_metadata.deploy.unwrap();
+        assert_eq!(deploy.transport, Some(DeployTransport::Winrm));
+        assert_eq!(deploy.install_tool, Some(InstallTool::Devcon));
+    }
+
+    #[test]
+    fn parse_from_manifest_merges_workspace_metadata() {
+        let directory =
+            std::env::temp_dir().join(format!("wdk-build-metadata-test-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let manifest_path = directory.join("Cargo.toml");
+        std::fs::write(
+            &manifest_path,
+            r#"
+            [package]
+            name = "driver"
+            version = "0.1.0"
+
+            [package.metadata.wdk.driver-model]
+            driver-type = "WDM"
+
+            [workspace.metadata.wdk]
+            wdk-version = "10.0.26100.0"
+
+            [workspace.metadata.wdk.deploy]
+            target-machine = "driver-test-vm"
+            "#,
+        )
+        .unwrap();
+
+        let wdk_metadata = parse_from_manifest(&manifest_path).unwrap().unwrap();
+
+        assert_eq!(wdk_metadata.driver_model, Some(DriverModel::WDM));
+        assert_eq!(wdk_metadata.wdk_version.as_deref(), Some("10.0.26100.0"));
+        assert_eq!(wdk_metadata.deploy, None);
+
+        std::fs::write(&manifest_path, "[package]\nname = \"driver\"\n").unwrap();
+        assert_eq!(parse_from_manifest(&manifest_path).unwrap(), None);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Generation of initializers for the structs in generated bindings that
+//! carry their own size.
+//!
+//! WDF requires the `Size` field of its configuration structs (ex.
+//! `WDF_DRIVER_CONFIG`) to be set to the size of the struct, which the
+//! `WDF_*_INIT` macros take care of in C. Forgetting to set it is only caught
+//! at runtime, when WDF rejects the struct. This module generates, for every
+//! struct whose first field is `Size`, a `SIZE` associated constant and a
+//! `const fn with_size()` constructor that zero-initializes the struct and sets
+//! its `Size` field.
+//!
+//! ```
+//! use wdk_build::struct_initializers;
+//!
+//! let bindings = "pub struct _WDF_TIMER_CONFIG { pub Size : ULONG , pub Period : ULONG , }";
+//! let struct_initializers = struct_initializers::generate(bindings);
+//! assert!(struct_initializers.contains("impl _WDF_TIMER_CONFIG {"));
+//! assert!(struct_initializers.contains("pub const fn with_size() -> Self"));
+//! ```
+
+use std::fmt::Write;
+
+/// Generates the initializers for every struct in `bindings` whose first field
+/// is named `Size`.
+///
+/// `bindings` is expected to be the unformatted output of `bindgen`, and the
+/// generated code must be included in a module where the structs and the type
+/// of their `Size` field are in scope.
+///
+/// # Panics
+///
+/// Panics if writing to the generated [`String`] fails, which cannot happen
+#[must_use]
+pub fn generate(bindings: &str) -> String {
+    let mut generated = String::new();
+    for (struct_name, size_type) in find_sized_structs(bindings) {
+        let type_alias = struct_name.strip_prefix('_').unwrap_or(struct_name);
+        write!(
+            generated,
+            r"
+impl {struct_name} {{
+    /// Value of the `Size` field of [`{type_alias}`]
+    #[allow(clippy::cast_possible_truncation)]
+    pub const SIZE: {size_type} = ::core::mem::size_of::<Self>() as {size_type};
+
+    /// Creates a zero-initialized [`{type_alias}`] with its `Size` field set
+    /// to [`Self::SIZE`]
+    #[must_use]
+    #[inline]
+    pub const fn with_size() -> Self {{
+        // SAFETY: `{type_alias}` is a C struct, for which the all-zero bit pattern is a
+        // valid value
+        let mut value: Self = unsafe {{ ::core::mem::zeroed() }};
+        value.Size = Self::SIZE;
+        value
+    }}
+}}
+"
+        )
+        .expect("writing to a String should not fail");
+    }
+    generated
+}
+
+/// Returns the names of the structs in `bindings` whose first field is named
+/// `Size`, along with the type of that field
+fn find_sized_structs(bindings: &str) -> Vec<(&str, &str)> {
+    bindings
+        .split("pub struct ")
+        .skip(1)
+        .filter_map(|item| {
+            let (struct_name, body) = item.split_once('{')?;
+            let struct_name = struct_name.trim();
+            if struct_name.is_empty()
+                || !struct_name
+                    .chars()
+                    .all(|character| character.is_ascii_alphanumeric() || character == '_')
+            {
+                return None;
+            }
+            let first_field = body.trim_start().strip_prefix("pub Size")?;
+            let size_type = first_field.trim_start().strip_prefix(':')?;
+            let size_type = size_type[..size_type.find(',')?].trim();
+            Some((struct_name, size_type))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BINDINGS: &str =
+        "# [repr (C)] # [derive (Debug , Default , Copy , Clone)] pub struct _WDF_DRIVER_CONFIG { \
+         pub Size : ULONG , pub EvtDriverDeviceAdd : PFN_WDF_DRIVER_DEVICE_ADD , } pub type \
+         WDF_DRIVER_CONFIG = _WDF_DRIVER_CONFIG ; # [repr (C)] pub struct _WDF_REQUEST_PARAMETERS \
+         { pub Size : USHORT , pub MinorFunction : UCHAR , } # [repr (C)] pub struct \
+         _WDF_OBJECT_CONTEXT { pub ContextSize : usize , } pub struct _WDF_TUPLE (pub u32) ;";
+
+    #[test]
+    fn sized_structs_are_found() {
+        assert_eq!(
+            find_sized_structs(BINDINGS),
+            vec![
+                ("_WDF_DRIVER_CONFIG", "ULONG"),
+                ("_WDF_REQUEST_PARAMETERS", "USHORT"),
+            ]
+        );
+    }
+
+    #[test]
+    fn initializers_use_size_type() {
+        let generated = generate(BINDINGS);
+
+        assert!(generated.contains("impl _WDF_DRIVER_CONFIG {"));
+        assert!(generated.contains("/// Value of the `Size` field of [`WDF_DRIVER_CONFIG`]"));
+        assert!(generated
+            .contains("pub const SIZE: USHORT = ::core::mem::size_of::<Self>() as USHORT;"));
+        assert!(!generated.contains("_WDF_OBJECT_CONTEXT"));
+        assert!(!generated.contains("_WDF_TUPLE"));
+    }
+}
@@ -0,0 +1,668 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! This module provides functions to verify and publish the symbols (PDBs) of
+//! a driver.
+//!
+//! A debugger only loads a PDB for a driver binary if the GUID and age
+//! recorded in the `CodeView` debug record of the binary match the ones in the
+//! PDB, so a PDB from a different build of the same driver is useless for
+//! crash dump triage. [`verify_pdb_matches_binary`] checks this before the
+//! symbols are archived, and [`SymStore`] publishes them to a symbol store
+//! (ex. a symbol share) via the WDK's `symstore.exe`, so that debuggers can
+//! find them via the symbol path. See the [SymStore Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/debugger/symstore)
+//! for more details.
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! use wdk_build::symbols::{verify_pdb_matches_binary, SymStore};
+//!
+//! let binary = Path::new(r"target\debug\sample_kmdf_driver.sys");
+//! let pdb = Path::new(r"target\debug\sample_kmdf_driver.pdb");
+//! let signature = verify_pdb_matches_binary(binary, pdb)?;
+//! println!("symbol server key: {}", signature.symbol_server_key());
+//!
+//! SymStore::new(Path::new(r"C:\Program Files (x86)\Windows Kits\10")).add(
+//!     &[binary, pdb],
+//!     Path::new(r"\\symbols\drivers"),
+//!     "sample_kmdf_driver",
+//!     "0.1.0",
+//! )?;
+//! # Ok::<(), wdk_build::symbols::SymbolsError>(())
+//! ```
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+};
+
+use thiserror::Error;
+
+use crate::CPUArchitecture;
+
+/// Signature at the start of every PE file (`IMAGE_DOS_SIGNATURE`)
+const DOS_SIGNATURE: &[u8] = b"MZ";
+/// Offset of `e_lfanew` in the DOS header, which holds the offset of the NT
+/// headers
+const DOS_HEADER_NT_HEADERS_OFFSET: usize = 0x3C;
+/// Signature at the start of the NT headers (`IMAGE_NT_SIGNATURE`)
+const NT_SIGNATURE: &[u8] = b"PE\0\0";
+/// Size of `IMAGE_FILE_HEADER`
+const FILE_HEADER_SIZE: usize = 20;
+/// `Magic` of a 32-bit `IMAGE_OPTIONAL_HEADER`
+const PE32_MAGIC: u16 = 0x10B;
+/// `Magic` of a 64-bit `IMAGE_OPTIONAL_HEADER`
+const PE32_PLUS_MAGIC: u16 = 0x20B;
+/// Index of the debug directory in the data directories of the optional header
+/// (`IMAGE_DIRECTORY_ENTRY_DEBUG`)
+const DEBUG_DIRECTORY_INDEX: usize = 6;
+/// Size of `IMAGE_SECTION_HEADER`
+const SECTION_HEADER_SIZE: usize = 40;
+/// Size of `IMAGE_DEBUG_DIRECTORY`
+const DEBUG_DIRECTORY_ENTRY_SIZE: usize = 28;
+/// `Type` of a debug directory entry that points to a `CodeView` record
+/// (`IMAGE_DEBUG_TYPE_CODEVIEW`)
+const DEBUG_TYPE_CODEVIEW: u32 = 2;
+/// Signature of a `CodeView` record that refers to a PDB 7.0 file
+const CODEVIEW_PDB70_SIGNATURE: &[u8] = b"RSDS";
+
+/// Magic at the start of every PDB file, which is an MSF 7.0 container
+const MSF_MAGIC: &[u8] = b"Microsoft C/C++ MSF 7.00\r\n\x1aDS\0\0\0";
+/// Index of the PDB info stream, which holds the GUID of the PDB
+const PDB_INFO_STREAM_INDEX: usize = 1;
+/// Index of the DBI stream, which holds the age that binaries refer to
+const DBI_STREAM_INDEX: usize = 3;
+/// Size of a stream that does not exist in an MSF container
+const MSF_NIL_STREAM_SIZE: u32 = u32::MAX;
+
+/// Errors that could result from verifying or publishing symbols
+#[derive(Debug, Error)]
+pub enum SymbolsError {
+    /// Error returned when a binary or PDB cannot be read, or `symstore.exe`
+    /// cannot be executed
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+
+    /// Error returned when a binary is not a valid PE file
+    #[error("{} is not a valid PE file: {reason}", path.display())]
+    InvalidBinary {
+        /// Path of the binary
+        path: PathBuf,
+        /// Description of the problem
+        reason: &'static str,
+    },
+
+    /// Error returned when a binary has no `CodeView` debug record that refers
+    /// to a PDB, ex. because it was linked without `/DEBUG`
+    #[error("{} does not refer to a PDB", path.display())]
+    MissingCodeViewRecord {
+        /// Path of the binary
+        path: PathBuf,
+    },
+
+    /// Error returned when a PDB is not a valid MSF 7.0 file
+    #[error("{} is not a valid PDB file: {reason}", path.display())]
+    InvalidPdb {
+        /// Path of the PDB
+        path: PathBuf,
+        /// Description of the problem
+        reason: &'static str,
+    },
+
+    /// Error returned when a PDB was not generated by the same build as a
+    /// binary
+    #[error(
+        "{} does not match {}: the binary refers to {binary_signature}, but the PDB is \
+         {pdb_signature}",
+        pdb.display(),
+        binary.display()
+    )]
+    SignatureMismatch {
+        /// Path of the binary
+        binary: PathBuf,
+        /// Path of the PDB
+        pdb: PathBuf,
+        /// Signature of the PDB that the binary refers to
+        binary_signature: PdbSignature,
+        /// Signature of the PDB
+        pdb_signature: PdbSignature,
+    },
+
+    /// Error returned when `symstore.exe` exits with a failure
+    #[error("symstore failed with {exit_status}")]
+    SymStoreFailed {
+        /// Exit status of `symstore.exe`
+        exit_status: ExitStatus,
+    },
+}
+
+/// Identity of a PDB, which a binary records to refer to the PDB generated by
+/// the same build
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PdbSignature {
+    /// GUID of the PDB, in its on-disk (little-endian `GUID` struct) layout
+    pub guid: [u8; 16],
+    /// Number of times the PDB has been written
+    pub age: u32,
+}
+
+/// `CodeView` debug record of a binary, which refers to the PDB generated by
+/// the same build
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeViewRecord {
+    /// Signature of the PDB
+    pub signature: PdbSignature,
+    /// Path of the PDB when the binary was linked
+    pub pdb_path: String,
+}
+
+/// Runs `symstore.exe` from a WDK installation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymStore {
+    symstore_path: PathBuf,
+}
+
+impl PdbSignature {
+    /// Returns the key of the PDB in a symbol store: the GUID as uppercase hex
+    /// digits without separators, followed by the age in hex (ex.
+    /// `0F6A1C1B3E2D4F9A8B7C6D5E4F3A2B1C1`)
+    #[must_use]
+    pub fn symbol_server_key(&self) -> String {
+        let [data1, data2, data3, data4] = self.guid_fields();
+        format!(
+            "{data1:08X}{data2:04X}{data3:04X}{data4:016X}{:X}",
+            self.age
+        )
+    }
+
+    fn guid_fields(&self) -> [u64; 4] {
+        let guid = &self.guid;
+        [
+            u64::from(u32::from_le_bytes([guid[0], guid[1], guid[2], guid[3]])),
+            u64::from(u16::from_le_bytes([guid[4], guid[5]])),
+            u64::from(u16::from_le_bytes([guid[6], guid[7]])),
+            u64::from_be_bytes([
+                guid[8], guid[9], guid[10], guid[11], guid[12], guid[13], guid[14], guid[15],
+            ]),
+        ]
+    }
+}
+
+impl fmt::Display for PdbSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [data1, data2, data3, data4] = self.guid_fields();
+        write!(
+            f,
+            "{{{data1:08X}-{data2:04X}-{data3:04X}-{:04X}-{:012X}}} (age {})",
+            data4 >> 48,
+            data4 & 0xFFFF_FFFF_FFFF,
+            self.age
+        )
+    }
+}
+
+impl CodeViewRecord {
+    /// Reads the `CodeView` debug record of the PE binary at `binary_path` (ex.
+    /// a `.sys` or `.dll` file)
+    ///
+    /// # Errors
+    ///
+    /// This function returns:
+    /// - [`SymbolsError::IoError`] if the binary cannot be read
+    /// - [`SymbolsError::InvalidBinary`] if the binary is not a valid PE file
+    /// - [`SymbolsError::MissingCodeViewRecord`] if the binary does not refer
+    ///   to a PDB
+    pub fn read(binary_path: &Path) -> Result<Self, SymbolsError> {
+        match parse_codeview_record(&std::fs::read(binary_path)?) {
+            Ok(Some(codeview_record)) => Ok(codeview_record),
+            Ok(None) => Err(SymbolsError::MissingCodeViewRecord {
+                path: binary_path.to_path_buf(),
+            }),
+            Err(reason) => Err(SymbolsError::InvalidBinary {
+                path: binary_path.to_path_buf(),
+                reason,
+            }),
+        }
+    }
+}
+
+impl SymStore {
+    /// Creates a [`SymStore`] that runs `symstore.exe` from the Debugging
+    /// Tools for Windows in `wdk_content_root`, for the architecture of the
+    /// host
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the CPU architecture of the host cannot be
+    /// determined from `std::env::consts::ARCH`
+    #[must_use]
+    pub fn new(wdk_content_root: &Path) -> Self {
+        let host_architecture = CPUArchitecture::try_from_cargo_str(std::env::consts::ARCH)
+            .expect("The rust standard library should always set std::env::consts::ARCH");
+
+        Self {
+            symstore_path: wdk_content_root
+                .join("Debuggers")
+                .join(host_architecture.as_windows_str())
+                .join("symstore.exe"),
+        }
+    }
+
+    /// Adds `files` (ex. the `.sys` and `.pdb` of a driver) to the symbol
+    /// store at `symbol_store` (ex. a symbol share), as a transaction for
+    /// `product` at `version`
+    ///
+    /// # Errors
+    ///
+    /// This function returns:
+    /// - [`SymbolsError::IoError`] if `symstore.exe` cannot be executed
+    /// - [`SymbolsError::SymStoreFailed`] if `symstore.exe` fails to add any of
+    ///   the files
+    pub fn add(
+        &self,
+        files: &[&Path],
+        symbol_store: &Path,
+        product: &str,
+        version: &str,
+    ) -> Result<(), SymbolsError> {
+        for file in files {
+            let exit_status = Command::new(&self.symstore_path)
+                .arg("add")
+                .arg("/f")
+                .arg(file)
+                .arg("/s")
+                .arg(symbol_store)
+                .args(["/t", product, "/v", version])
+                .status()?;
+            if !exit_status.success() {
+                return Err(SymbolsError::SymStoreFailed { exit_status });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads the signature of the PDB at `pdb_path`
+///
+/// The age is read from the DBI stream, since that is the age that the
+/// linker records in binaries. The age of the PDB info stream is only used if
+/// the PDB has no DBI stream.
+///
+/// # Errors
+///
+/// This function returns:
+/// - [`SymbolsError::IoError`] if the PDB cannot be read
+/// - [`SymbolsError::InvalidPdb`] if the PDB is not a valid MSF 7.0 file
+pub fn read_pdb_signature(pdb_path: &Path) -> Result<PdbSignature, SymbolsError> {
+    parse_pdb_signature(&std::fs::read(pdb_path)?).map_err(|reason| SymbolsError::InvalidPdb {
+        path: pdb_path.to_path_buf(),
+        reason,
+    })
+}
+
+/// Verifies that the PDB at `pdb_path` was generated by the same build as the
+/// binary at `binary_path`, and returns its signature
+///
+/// # Errors
+///
+/// This function returns:
+/// - [`SymbolsError::SignatureMismatch`] if the binary refers to a different
+///   PDB
+/// - any of the errors of [`CodeViewRecord::read`] and [`read_pdb_signature`]
+///   if the binary or PDB cannot be read
+pub fn verify_pdb_matches_binary(
+    binary_path: &Path,
+    pdb_path: &Path,
+) -> Result<PdbSignature, SymbolsError> {
+    let binary_signature = CodeViewRecord::read(binary_path)?.signature;
+    let pdb_signature = read_pdb_signature(pdb_path)?;
+    if binary_signature != pdb_signature {
+        return Err(SymbolsError::SignatureMismatch {
+            binary: binary_path.to_path_buf(),
+            pdb: pdb_path.to_path_buf(),
+            binary_signature,
+            pdb_signature,
+        });
+    }
+    Ok(pdb_signature)
+}
+
+/// Parses the PDB 7.0 `CodeView` record out of the debug directory of a PE
+/// file, or returns [`None`] if it has none
+fn parse_codeview_record(binary: &[u8]) -> Result<Option<CodeViewRecord>, &'static str> {
+    if !binary.starts_with(DOS_SIGNATURE) {
+        return Err("missing DOS signature");
+    }
+    let nt_headers_offset = read_u32(binary, DOS_HEADER_NT_HEADERS_OFFSET)? as usize;
+    if binary.get(nt_headers_offset..nt_headers_offset + NT_SIGNATURE.len()) != Some(NT_SIGNATURE) {
+        return Err("missing NT signature");
+    }
+
+    let file_header_offset = nt_headers_offset + NT_SIGNATURE.len();
+    let number_of_sections = usize::from(read_u16(binary, file_header_offset + 2)?);
+    let optional_header_size = usize::from(read_u16(binary, file_header_offset + 16)?);
+    let optional_header_offset = file_header_offset + FILE_HEADER_SIZE;
+    let (number_of_data_directories_offset, data_directories_offset) =
+        match read_u16(binary, optional_header_offset)? {
+            PE32_MAGIC => (92, 96),
+            PE32_PLUS_MAGIC => (108, 112),
+            _ => return Err("unknown optional header magic"),
+        };
+
+    let number_of_data_directories = read_u32(
+        binary,
+        optional_header_offset + number_of_data_directories_offset,
+    )? as usize;
+    if number_of_data_directories <= DEBUG_DIRECTORY_INDEX {
+        return Ok(None);
+    }
+    let debug_directory_offset =
+        optional_header_offset + data_directories_offset + DEBUG_DIRECTORY_INDEX * 8;
+    let debug_directory_rva = read_u32(binary, debug_directory_offset)?;
+    let debug_directory_size = read_u32(binary, debug_directory_offset + 4)? as usize;
+    if debug_directory_rva == 0 || debug_directory_size == 0 {
+        return Ok(None);
+    }
+
+    let section_headers_offset = optional_header_offset + optional_header_size;
+    let debug_directory_file_offset = (0..number_of_sections)
+        .map(|index| section_headers_offset + index * SECTION_HEADER_SIZE)
+        .find_map(|section_header_offset| {
+            let virtual_size = read_u32(binary, section_header_offset + 8).ok()?;
+            let virtual_address = read_u32(binary, section_header_offset + 12).ok()?;
+            let pointer_to_raw_data = read_u32(binary, section_header_offset + 20).ok()?;
+            (virtual_address..virtual_address.saturating_add(virtual_size))
+                .contains(&debug_directory_rva)
+                .then(|| {
+                    (debug_directory_rva - virtual_address) as usize + pointer_to_raw_data as usize
+                })
+        })
+        .ok_or("debug directory is not in any section")?;
+
+    for entry_offset in (0..debug_directory_size / DEBUG_DIRECTORY_ENTRY_SIZE)
+        .map(|index| debug_directory_file_offset + index * DEBUG_DIRECTORY_ENTRY_SIZE)
+    {
+        if read_u32(binary, entry_offset + 12)? != DEBUG_TYPE_CODEVIEW {
+            continue;
+        }
+        let size_of_data = read_u32(binary, entry_offset + 16)? as usize;
+        let pointer_to_raw_data = read_u32(binary, entry_offset + 24)? as usize;
+        let record = binary
+            .get(pointer_to_raw_data..pointer_to_raw_data + size_of_data)
+            .ok_or("CodeView record is out of bounds")?;
+        if !record.starts_with(CODEVIEW_PDB70_SIGNATURE) {
+            continue;
+        }
+
+        let pdb_path = record.get(24..).ok_or("CodeView record is truncated")?;
+        let pdb_path_length = pdb_path
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(pdb_path.len());
+        return Ok(Some(CodeViewRecord {
+            signature: PdbSignature {
+                guid: read_guid(record, 4)?,
+                age: read_u32(record, 20)?,
+            },
+            pdb_path: String::from_utf8_lossy(&pdb_path[..pdb_path_length]).into_owned(),
+        }));
+    }
+    Ok(None)
+}
+
+/// Parses the signature of a PDB out of its PDB info and DBI streams
+fn parse_pdb_signature(pdb: &[u8]) -> Result<PdbSignature, &'static str> {
+    if !pdb.starts_with(MSF_MAGIC) {
+        return Err("missing MSF 7.0 magic");
+    }
+    let block_size = read_u32(pdb, 32)? as usize;
+    if block_size == 0 {
+        return Err("block size is zero");
+    }
+    let directory_size = read_u32(pdb, 44)? as usize;
+    let block_map_offset = read_u32(pdb, 52)? as usize * block_size;
+
+    // The stream directory is stored in the blocks listed in the block map
+    let directory_blocks = (0..directory_size.div_ceil(block_size))
+        .map(|index| read_u32(pdb, block_map_offset + index * 4))
+        .collect::<Result<Vec<_>, _>>()?;
+    let directory = read_blocks(pdb, block_size, &directory_blocks, directory_size)?;
+
+    // The directory holds the number of streams, followed by the size of each
+    // stream, followed by the blocks of each stream
+    let number_of_streams = read_u32(&directory, 0)? as usize;
+    let mut stream_blocks_offset = 4 + number_of_streams * 4;
+    let mut streams = Vec::with_capacity(number_of_streams.min(DBI_STREAM_INDEX + 1));
+    for stream_index in 0..number_of_streams.min(DBI_STREAM_INDEX + 1) {
+        let stream_size = match read_u32(&directory, 4 + stream_index * 4)? {
+            MSF_NIL_STREAM_SIZE => 0,
+            stream_size => stream_size as usize,
+        };
+        let stream_blocks = (0..stream_size.div_ceil(block_size))
+            .map(|index| read_u32(&directory, stream_blocks_offset + index * 4))
+            .collect::<Result<Vec<_>, _>>()?;
+        stream_blocks_offset += stream_blocks.len() * 4;
+        streams.push(read_blocks(pdb, block_size, &stream_blocks, stream_size)?);
+    }
+
+    // The PDB info stream holds a version, a timestamp, an age and the GUID
+    let pdb_info_stream = streams
+        .get(PDB_INFO_STREAM_INDEX)
+        .ok_or("missing PDB info stream")?;
+    let guid = read_guid(pdb_info_stream, 12)?;
+    // The DBI stream holds a signature, a version and the age
+    let age = match streams.get(DBI_STREAM_INDEX) {
+        Some(dbi_stream) if !dbi_stream.is_empty() => read_u32(dbi_stream, 8)?,
+        _ => read_u32(pdb_info_stream, 8)?,
+    };
+    Ok(PdbSignature { guid, age })
+}
+
+/// Reads the first `size` bytes of the concatenation of `blocks`
+fn read_blocks(
+    file: &[u8],
+    block_size: usize,
+    blocks: &[u32],
+    size: usize,
+) -> Result<Vec<u8>, &'static str> {
+    let mut data = Vec::with_capacity(blocks.len() * block_size);
+    for &block in blocks {
+        let block_offset = block as usize * block_size;
+        data.extend_from_slice(
+            file.get(block_offset..block_offset + block_size)
+                .ok_or("block is out of bounds")?,
+        );
+    }
+    data.truncate(size);
+    Ok(data)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, &'static str> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .ok_or("unexpected end of file")
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, &'static str> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or("unexpected end of file")
+}
+
+fn read_guid(data: &[u8], offset: usize) -> Result<[u8; 16], &'static str> {
+    data.get(offset..offset + 16)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("unexpected end of file")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUID: [u8; 16] = [
+        0x1B, 0x1C, 0x6A, 0x0F, 0x2D, 0x3E, 0x9A, 0x4F, 0x8B, 0x7C, 0x6D, 0x5E, 0x4F, 0x3A, 0x2B,
+        0x1C,
+    ];
+
+    fn write_u16<T: TryInto<u16>>(data: &mut [u8], offset: usize, value: T)
+    where
+        T::Error: fmt::Debug,
+    {
+        data[offset..offset + 2].copy_from_slice(&value.try_into().unwrap().to_le_bytes());
+    }
+
+    fn write_u32<T: TryInto<u32>>(data: &mut [u8], offset: usize, value: T)
+    where
+        T::Error: fmt::Debug,
+    {
+        data[offset..offset + 4].copy_from_slice(&value.try_into().unwrap().to_le_bytes());
+    }
+
+    /// Builds a minimal PE32+ file with one section holding a debug directory
+    /// with a `CodeView` record
+    fn pe_with_codeview_record(guid: [u8; 16], age: u32, pdb_path: &str) -> Vec<u8> {
+        const NT_HEADERS_OFFSET: usize = 0x80;
+        const OPTIONAL_HEADER_OFFSET: usize = NT_HEADERS_OFFSET + 4 + FILE_HEADER_SIZE;
+        const OPTIONAL_HEADER_SIZE: usize = 112 + 16 * 8;
+        const SECTION_HEADER_OFFSET: usize = OPTIONAL_HEADER_OFFSET + OPTIONAL_HEADER_SIZE;
+        const SECTION_FILE_OFFSET: usize = 0x200;
+        const SECTION_RVA: u32 = 0x1000;
+        const CODEVIEW_RECORD_FILE_OFFSET: usize = SECTION_FILE_OFFSET + DEBUG_DIRECTORY_ENTRY_SIZE;
+
+        let mut binary = vec![0; 0x400];
+        binary[..2].copy_from_slice(DOS_SIGNATURE);
+        write_u32(&mut binary, DOS_HEADER_NT_HEADERS_OFFSET, NT_HEADERS_OFFSET);
+        binary[NT_HEADERS_OFFSET..NT_HEADERS_OFFSET + 4].copy_from_slice(NT_SIGNATURE);
+        write_u16(&mut binary, NT_HEADERS_OFFSET + 4 + 2, 1);
+        write_u16(
+            &mut binary,
+            NT_HEADERS_OFFSET + 4 + 16,
+            OPTIONAL_HEADER_SIZE,
+        );
+
+        write_u16(&mut binary, OPTIONAL_HEADER_OFFSET, PE32_PLUS_MAGIC);
+        write_u32(&mut binary, OPTIONAL_HEADER_OFFSET + 108, 16);
+        let debug_directory_offset = OPTIONAL_HEADER_OFFSET + 112 + DEBUG_DIRECTORY_INDEX * 8;
+        write_u32(&mut binary, debug_directory_offset, SECTION_RVA);
+        write_u32(
+            &mut binary,
+            debug_directory_offset + 4,
+            DEBUG_DIRECTORY_ENTRY_SIZE,
+        );
+
+        write_u32(&mut binary, SECTION_HEADER_OFFSET + 8, 0x200);
+        write_u32(&mut binary, SECTION_HEADER_OFFSET + 12, SECTION_RVA);
+        write_u32(&mut binary, SECTION_HEADER_OFFSET + 16, 0x200);
+        write_u32(&mut binary, SECTION_HEADER_OFFSET + 20, SECTION_FILE_OFFSET);
+
+        let codeview_record_size = 24 + pdb_path.len() + 1;
+        write_u32(&mut binary, SECTION_FILE_OFFSET + 12, DEBUG_TYPE_CODEVIEW);
+        write_u32(&mut binary, SECTION_FILE_OFFSET + 16, codeview_record_size);
+        write_u32(
+            &mut binary,
+            SECTION_FILE_OFFSET + 24,
+            CODEVIEW_RECORD_FILE_OFFSET,
+        );
+
+        let record = &mut binary[CODEVIEW_RECORD_FILE_OFFSET..];
+        record[..4].copy_from_slice(CODEVIEW_PDB70_SIGNATURE);
+        record[4..20].copy_from_slice(&guid);
+        write_u32(record, 20, age);
+        record[24..24 + pdb_path.len()].copy_from_slice(pdb_path.as_bytes());
+        binary
+    }
+
+    /// Builds a minimal PDB with a PDB info stream and a DBI stream, with one
+    /// block per stream
+    fn pdb_with_signature(guid: [u8; 16], info_age: u32, dbi_age: u32) -> Vec<u8> {
+        const BLOCK_SIZE: usize = 0x200;
+        // Block 0 is the superblock, block 1 is the block map, block 2 is the stream
+        // directory, and blocks 3 to 6 hold streams 0 to 3
+        let mut pdb = vec![0; BLOCK_SIZE * 7];
+        pdb[..MSF_MAGIC.len()].copy_from_slice(MSF_MAGIC);
+        write_u32(&mut pdb, 32, BLOCK_SIZE);
+        write_u32(&mut pdb, 40, 7);
+        write_u32(&mut pdb, 52, 1);
+        write_u32(&mut pdb, BLOCK_SIZE, 2);
+
+        let directory = &mut pdb[2 * BLOCK_SIZE..3 * BLOCK_SIZE];
+        let stream_sizes = [MSF_NIL_STREAM_SIZE, 28, 0, 64];
+        write_u32(directory, 0, stream_sizes.len());
+        for (index, stream_size) in stream_sizes.iter().enumerate() {
+            write_u32(directory, 4 + index * 4, *stream_size);
+        }
+        // Streams 1 and 3 are the only ones with data, in blocks 4 and 6
+        write_u32(directory, 20, 4);
+        write_u32(directory, 24, 6);
+        let directory_size = 28;
+        write_u32(&mut pdb, 44, directory_size);
+
+        let pdb_info_stream = &mut pdb[4 * BLOCK_SIZE..5 * BLOCK_SIZE];
+        write_u32(pdb_info_stream, 8, info_age);
+        pdb_info_stream[12..28].copy_from_slice(&guid);
+        write_u32(&mut pdb[6 * BLOCK_SIZE..7 * BLOCK_SIZE], 8, dbi_age);
+        pdb
+    }
+
+    #[test]
+    fn parse_codeview_record_from_pe() {
+        let codeview_record = parse_codeview_record(&pe_with_codeview_record(
+            GUID,
+            3,
+            r"C:\driver\target\debug\deps\sample_kmdf_driver.pdb",
+        ))
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            codeview_record,
+            CodeViewRecord {
+                signature: PdbSignature { guid: GUID, age: 3 },
+                pdb_path: r"C:\driver\target\debug\deps\sample_kmdf_driver.pdb".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_invalid_pe() {
+        assert_eq!(
+            parse_codeview_record(b"not a PE file"),
+            Err("missing DOS signature")
+        );
+    }
+
+    #[test]
+    fn parse_pdb_signature_uses_dbi_age() {
+        assert_eq!(
+            parse_pdb_signature(&pdb_with_signature(GUID, 5, 3)),
+            Ok(PdbSignature { guid: GUID, age: 3 })
+        );
+    }
+
+    #[test]
+    fn parse_invalid_pdb() {
+        assert_eq!(
+            parse_pdb_signature(b"not a PDB file"),
+            Err("missing MSF 7.0 magic")
+        );
+    }
+
+    #[test]
+    fn format_signature() {
+        let signature = PdbSignature {
+            guid: GUID,
+            age: 0x1A,
+        };
+
+        assert_eq!(
+            signature.symbol_server_key(),
+            "0F6A1C1B3E2D4F9A8B7C6D5E4F3A2B1C1A"
+        );
+        assert_eq!(
+            signature.to_string(),
+            "{0F6A1C1B-3E2D-4F9A-8B7C-6D5E4F3A2B1C} (age 26)"
+        );
+    }
+}
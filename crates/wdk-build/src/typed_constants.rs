@@ -0,0 +1,391 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Generation of typed wrappers for families of constants in generated
+//! bindings.
+//!
+//! `bindgen` emits `#define`d constants (ex. `POOL_FLAG_*`) as untyped
+//! integers, and C enums as modules of integer constants, so nothing prevents
+//! passing a constant of one family where another one is expected. This module
+//! generates a `#[repr(transparent)]` newtype per [`ConstantFamily`], with an
+//! associated constant for every member of the family found in the generated
+//! bindings, and [`From`] conversions to and from the raw type. The raw
+//! constants are left untouched, so the typed wrappers can be adopted
+//! incrementally.
+//!
+//! ```
+//! use wdk_build::typed_constants::{self, ConstantFamily, ConstantFamilyKind, ConstantMembers};
+//!
+//! const POOL_FLAGS: ConstantFamily = ConstantFamily {
+//!     type_name: "PoolFlags",
+//!     doc: "Flags of pool allocations",
+//!     raw_type: "POOL_FLAGS",
+//!     kind: ConstantFamilyKind::Bitflags,
+//!     module: None,
+//!     members: ConstantMembers::Prefix("POOL_FLAG_"),
+//! };
+//!
+//! let bindings = "pub const POOL_FLAG_NON_PAGED: u32 = 64;";
+//! let typed_constants = typed_constants::generate(bindings, &[POOL_FLAGS]);
+//! assert!(typed_constants.contains("pub const NON_PAGED: Self"));
+//! ```
+
+use std::fmt::Write;
+
+/// The kind of wrapper generated for a [`ConstantFamily`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstantFamilyKind {
+    /// Members are mutually exclusive values (ex. the values of a C enum)
+    Enum,
+    /// Members are flags that can be combined (ex. `POOL_FLAG_*`). The
+    /// generated wrapper implements the bitwise operators.
+    Bitflags,
+}
+
+/// The constants that are members of a [`ConstantFamily`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstantMembers {
+    /// All constants whose name starts with the prefix. The prefix is
+    /// stripped from the names of the generated associated constants, which
+    /// are converted to `SCREAMING_SNAKE_CASE` (ex. `WdfIoQueueDispatch` turns
+    /// `WdfIoQueueDispatchParallel` into `PARALLEL`).
+    Prefix(&'static str),
+    /// The listed constants, which keep their names. Constants that are not
+    /// found in the bindings (ex. because they are architecture-specific) are
+    /// skipped.
+    Names(&'static [&'static str]),
+}
+
+/// A family of related constants to generate a typed wrapper for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConstantFamily {
+    /// Name of the generated wrapper type (ex. `PoolFlags`)
+    pub type_name: &'static str,
+    /// Documentation of the generated wrapper type
+    pub doc: &'static str,
+    /// Path of the raw type that the wrapper wraps, relative to the crate
+    /// root (ex. `POOL_FLAGS`)
+    pub raw_type: &'static str,
+    /// Kind of wrapper to generate
+    pub kind: ConstantFamilyKind,
+    /// Path of the module that contains the constants, relative to the crate
+    /// root (ex. `_WDF_IO_QUEUE_DISPATCH_TYPE` for C enums generated as
+    /// modules of constants), or [`None`] if they are at the crate root
+    pub module: Option<&'static str>,
+    /// Constants that are members of the family
+    pub members: ConstantMembers,
+}
+
+/// Generates the typed wrappers for `families`, with the members that are
+/// found in `bindings`.
+///
+/// `bindings` is the source of the generated bindings that contain the raw
+/// constants. The returned source refers to the raw types and constants via
+/// `crate::` paths, so it must be included in the crate that the bindings are
+/// included in.
+///
+/// # Panics
+///
+/// This function will panic if writing to the returned [`String`] fails,
+/// which never happens.
+#[must_use]
+pub fn generate(bindings: &str, families: &[ConstantFamily]) -> String {
+    let mut typed_constants = String::new();
+    for family in families {
+        generate_family(&mut typed_constants, bindings, family)
+            .expect("writing to a String should never fail");
+    }
+    typed_constants
+}
+
+fn generate_family(
+    output: &mut String,
+    bindings: &str,
+    family: &ConstantFamily,
+) -> std::fmt::Result {
+    let ConstantFamily {
+        type_name,
+        doc,
+        raw_type,
+        kind,
+        module,
+        members,
+    } = *family;
+    let module_path = module.map_or_else(String::new, |module| format!("{module}::"));
+
+    writeln!(output, "#[doc = {doc:?}]")?;
+    writeln!(output, "#[repr(transparent)]")?;
+    writeln!(
+        output,
+        "#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]"
+    )?;
+    writeln!(output, "pub struct {type_name}(pub crate::{raw_type});")?;
+    writeln!(output)?;
+
+    writeln!(output, "impl {type_name} {{")?;
+    for (raw_name, member_name) in find_members(bindings, module, members) {
+        writeln!(
+            output,
+            "    #[doc = \"`{raw_name}`\"]\n    pub const {member_name}: Self = \
+             Self(crate::{module_path}{raw_name} as crate::{raw_type});"
+        )?;
+    }
+    writeln!(output, "    /// Get the raw value")?;
+    writeln!(output, "    #[must_use]")?;
+    writeln!(
+        output,
+        "    pub const fn bits(self) -> crate::{raw_type} {{ self.0 }}"
+    )?;
+    if kind == ConstantFamilyKind::Bitflags {
+        writeln!(output, "    /// Construct a value without any flag set")?;
+        writeln!(output, "    #[must_use]")?;
+        writeln!(output, "    pub const fn empty() -> Self {{ Self(0) }}")?;
+        writeln!(
+            output,
+            "    /// Returns `true` if all flags of `other` are set"
+        )?;
+        writeln!(output, "    #[must_use]")?;
+        writeln!(
+            output,
+            "    pub const fn contains(self, other: Self) -> bool {{ self.0 & other.0 == other.0 \
+             }}"
+        )?;
+    }
+    writeln!(output, "}}")?;
+    writeln!(output)?;
+
+    writeln!(
+        output,
+        "impl From<crate::{raw_type}> for {type_name} {{\n    fn from(raw: crate::{raw_type}) -> \
+         Self {{ Self(raw) }}\n}}"
+    )?;
+    writeln!(output)?;
+    writeln!(
+        output,
+        "impl From<{type_name}> for crate::{raw_type} {{\n    fn from(typed: {type_name}) -> Self \
+         {{ typed.0 }}\n}}"
+    )?;
+    writeln!(output)?;
+
+    if kind == ConstantFamilyKind::Bitflags {
+        for (operator_trait, operator_fn, operator) in [
+            ("BitOr", "bitor", "|"),
+            ("BitAnd", "bitand", "&"),
+            ("BitXor", "bitxor", "^"),
+        ] {
+            writeln!(
+                output,
+                "impl core::ops::{operator_trait} for {type_name} {{\n    type Output = Self;\n    \
+                 fn {operator_fn}(self, rhs: Self) -> Self {{ Self(self.0 {operator} rhs.0) }}\n}}"
+            )?;
+            writeln!(output)?;
+            writeln!(
+                output,
+                "impl core::ops::{operator_trait}Assign for {type_name} {{\n    fn \
+                 {operator_fn}_assign(&mut self, rhs: Self) {{ self.0 = self.0 {operator} rhs.0; \
+                 }}\n}}"
+            )?;
+            writeln!(output)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the raw names of the members of a family that are found in
+/// `bindings`, along with the names of their associated constants
+fn find_members(
+    bindings: &str,
+    module: Option<&str>,
+    members: ConstantMembers,
+) -> Vec<(String, String)> {
+    let scope = module.map_or(Some(bindings), |module| module_body(bindings, module));
+    let constant_names = scope.map_or_else(Vec::new, constant_names);
+
+    let mut found_members = Vec::new();
+    for constant_name in constant_names {
+        let member_name = match members {
+            ConstantMembers::Prefix(prefix) => constant_name
+                .strip_prefix(prefix)
+                .filter(|suffix| !suffix.is_empty())
+                .map(to_screaming_snake_case),
+            ConstantMembers::Names(names) => names
+                .contains(&constant_name)
+                .then(|| constant_name.to_string()),
+        };
+        let Some(mut member_name) = member_name else {
+            continue;
+        };
+        if member_name.starts_with(|character: char| character.is_ascii_digit()) {
+            member_name.insert(0, '_');
+        }
+        if !found_members
+            .iter()
+            .any(|(raw_name, _)| raw_name == constant_name)
+        {
+            found_members.push((constant_name.to_string(), member_name));
+        }
+    }
+    found_members
+}
+
+/// Returns the body of the `pub mod {module}` in `bindings`, if there is one
+fn module_body<'a>(bindings: &'a str, module: &str) -> Option<&'a str> {
+    let module_declaration = format!("pub mod {module} ");
+    let body_start = bindings.find(&module_declaration)? + module_declaration.len();
+    let body = &bindings[body_start..];
+    // Modules of enum constants generated by bindgen do not contain nested braces
+    Some(&body[..body.find('}')?])
+}
+
+/// Returns the names of the `pub const` items in `source`
+fn constant_names(source: &str) -> Vec<&str> {
+    source
+        .split("pub const ")
+        .skip(1)
+        .filter_map(|item| {
+            let name_length = item
+                .find(|character: char| !(character.is_ascii_alphanumeric() || character == '_'))?;
+            Some(&item[..name_length])
+        })
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Converts a `CamelCase` or `SCREAMING_SNAKE_CASE` name to
+/// `SCREAMING_SNAKE_CASE`
+fn to_screaming_snake_case(name: &str) -> String {
+    let mut screaming_snake_case = String::with_capacity(name.len());
+    let mut previous: Option<char> = None;
+    for character in name.chars() {
+        if character.is_ascii_uppercase()
+            && previous.is_some_and(|previous| previous.is_ascii_lowercase())
+        {
+            screaming_snake_case.push('_');
+        }
+        screaming_snake_case.push(character.to_ascii_uppercase());
+        previous = Some(character);
+    }
+    screaming_snake_case
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BINDINGS: &str = "pub const POOL_FLAG_UNINITIALIZED: u32 = 2;
+pub const POOL_FLAG_NON_PAGED: u32 = 64;
+pub const PASSIVE_LEVEL: u32 = 0;
+pub const DISPATCH_LEVEL: u32 = 2;
+pub mod _WDF_IO_QUEUE_DISPATCH_TYPE {
+    pub type Type = ::core::ffi::c_int;
+    pub const WdfIoQueueDispatchSequential: Type = 1;
+    pub const WdfIoQueueDispatchParallel: Type = 2;
+}
+pub const WdfIoQueueDispatchOutsideOfModule: u32 = 0;
+";
+
+    #[test]
+    fn prefix_members() {
+        assert_eq!(
+            find_members(BINDINGS, None, ConstantMembers::Prefix("POOL_FLAG_")),
+            vec![
+                (
+                    "POOL_FLAG_UNINITIALIZED".to_string(),
+                    "UNINITIALIZED".to_string()
+                ),
+                ("POOL_FLAG_NON_PAGED".to_string(), "NON_PAGED".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn named_members() {
+        assert_eq!(
+            find_members(
+                BINDINGS,
+                None,
+                ConstantMembers::Names(&["PASSIVE_LEVEL", "DISPATCH_LEVEL", "HIGH_LEVEL"])
+            ),
+            vec![
+                ("PASSIVE_LEVEL".to_string(), "PASSIVE_LEVEL".to_string()),
+                ("DISPATCH_LEVEL".to_string(), "DISPATCH_LEVEL".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn module_members() {
+        assert_eq!(
+            find_members(
+                BINDINGS,
+                Some("_WDF_IO_QUEUE_DISPATCH_TYPE"),
+                ConstantMembers::Prefix("WdfIoQueueDispatch")
+            ),
+            vec![
+                (
+                    "WdfIoQueueDispatchSequential".to_string(),
+                    "SEQUENTIAL".to_string()
+                ),
+                (
+                    "WdfIoQueueDispatchParallel".to_string(),
+                    "PARALLEL".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn generate_bitflags() {
+        let typed_constants = generate(
+            BINDINGS,
+            &[ConstantFamily {
+                type_name: "PoolFlags",
+                doc: "Flags of pool allocations",
+                raw_type: "POOL_FLAGS",
+                kind: ConstantFamilyKind::Bitflags,
+                module: None,
+                members: ConstantMembers::Prefix("POOL_FLAG_"),
+            }],
+        );
+
+        assert!(typed_constants.contains("pub struct PoolFlags(pub crate::POOL_FLAGS);"));
+        assert!(typed_constants.contains(
+            "pub const NON_PAGED: Self = Self(crate::POOL_FLAG_NON_PAGED as crate::POOL_FLAGS);"
+        ));
+        assert!(typed_constants.contains("impl core::ops::BitOr for PoolFlags"));
+        assert!(typed_constants.contains("impl From<PoolFlags> for crate::POOL_FLAGS"));
+    }
+
+    #[test]
+    fn generate_enum() {
+        let typed_constants = generate(
+            BINDINGS,
+            &[ConstantFamily {
+                type_name: "WdfIoQueueDispatchType",
+                doc: "Dispatch type of a WDF I/O queue",
+                raw_type: "WDF_IO_QUEUE_DISPATCH_TYPE",
+                kind: ConstantFamilyKind::Enum,
+                module: Some("_WDF_IO_QUEUE_DISPATCH_TYPE"),
+                members: ConstantMembers::Prefix("WdfIoQueueDispatch"),
+            }],
+        );
+
+        assert!(typed_constants.contains(
+            "pub const PARALLEL: Self = \
+             Self(crate::_WDF_IO_QUEUE_DISPATCH_TYPE::WdfIoQueueDispatchParallel as \
+             crate::WDF_IO_QUEUE_DISPATCH_TYPE);"
+        ));
+        assert!(!typed_constants.contains("OUTSIDE_OF_MODULE"));
+        assert!(!typed_constants.contains("core::ops::BitOr"));
+    }
+
+    #[test]
+    fn screaming_snake_case() {
+        assert_eq!(to_screaming_snake_case("Parallel"), "PARALLEL");
+        assert_eq!(
+            to_screaming_snake_case("InheritFromParent"),
+            "INHERIT_FROM_PARENT"
+        );
+        assert_eq!(to_screaming_snake_case("NON_PAGED"), "NON_PAGED");
+    }
+}
@@ -66,20 +66,77 @@ fn strip_extended_length_path_prefix(&self) -> Result<PathBuf, Self::Error> {
     }
 }
 
+/// Environment variable that can be set to explicitly specify the WDK content
+/// root. This can either point directly at a WDK content root (ex.
+/// `C:\Program Files (x86)\Windows Kits\10`), or at the root of a mounted
+/// eWDK (ex. `D:\`).
+pub const WDK_CONTENT_ROOT_ENV_VAR: &str = "WDK_CONTENT_ROOT";
+
+/// The name of the environment variable that `clang-sys` uses to locate
+/// `libclang.dll`
+pub const LIBCLANG_PATH_ENV_VAR: &str = "LIBCLANG_PATH";
+
+/// Path of the WDK content root, relative to the root of a mounted eWDK
+const EWDK_WDK_CONTENT_ROOT_RELATIVE_PATH: &str = r"Program Files\Windows Kits\10";
+
+/// Path of the `VCINSTALLDIR` of the build tools bundled in an eWDK, relative
+/// to the root of a mounted eWDK
+const EWDK_VC_INSTALL_DIR_RELATIVE_PATH: &str =
+    r"Program Files\Microsoft Visual Studio\2022\BuildTools\VC";
+
 /// Detect `WDKContentRoot` Directory. Logic is based off of Toolset.props in
 /// NI(22H2) WDK
-pub fn detect_wdk_content_root() -> Option<PathBuf> {
+///
+/// # Errors
+///
+/// This function returns a [`ConfigError::WDKContentRootDetectionError`]
+/// listing the environment variables and registry keys that were consulted,
+/// and why each of them was rejected, if the WDK content root cannot be found.
+pub fn detect_wdk_content_root() -> Result<PathBuf, ConfigError> {
+    let mut searched_locations = Vec::new();
+
+    // If WDK_CONTENT_ROOT is explicitly set (ex. in CI using a mounted eWDK without
+    // running SetupBuildEnv.cmd), use it
+    if let Ok(wdk_content_root) = env::var(WDK_CONTENT_ROOT_ENV_VAR) {
+        let path = Path::new(wdk_content_root.as_str());
+        let ewdk_wdk_content_root = path.join(EWDK_WDK_CONTENT_ROOT_RELATIVE_PATH);
+        if ewdk_wdk_content_root.is_dir() {
+            return Ok(ewdk_wdk_content_root);
+        }
+        if path.is_dir() {
+            return Ok(path.to_path_buf());
+        }
+        reject_wdk_content_root_location(
+            &mut searched_locations,
+            format!(
+                "environment variable {WDK_CONTENT_ROOT_ENV_VAR}: {} does not exist or is not a \
+                 valid directory",
+                path.display()
+            ),
+        );
+    } else {
+        searched_locations.push(format!(
+            "environment variable {WDK_CONTENT_ROOT_ENV_VAR}: not set"
+        ));
+    }
+
     // If WDKContentRoot is present in environment(ex. running in an eWDK prompt),
     // use it
     if let Ok(wdk_content_root) = env::var("WDKContentRoot") {
         let path = Path::new(wdk_content_root.as_str());
         if path.is_dir() {
-            return Some(path.to_path_buf());
+            return Ok(path.to_path_buf());
         }
-        eprintln!(
-            "WDKContentRoot was detected to be {}, but does not exist or is not a valid directory.",
-            path.display()
+        reject_wdk_content_root_location(
+            &mut searched_locations,
+            format!(
+                "environment variable WDKContentRoot: {} does not exist or is not a valid \
+                 directory",
+                path.display()
+            ),
         );
+    } else {
+        searched_locations.push("environment variable WDKContentRoot: not set".to_string());
     }
 
     // If MicrosoftKitRoot environment variable is set, use it to set WDKContentRoot
@@ -87,65 +144,109 @@ pub fn detect_wdk_content_root() -> Option<PathBuf> {
         let path = Path::new(microsoft_kit_root.as_str());
 
         if !path.is_absolute() {
-            eprintln!(
-                "MicrosoftKitRoot({}) was found in environment, but is not an absolute path.",
-                path.display()
+            reject_wdk_content_root_location(
+                &mut searched_locations,
+                format!(
+                    "environment variable MicrosoftKitRoot: {} is not an absolute path",
+                    path.display()
+                ),
             );
         } else if !path.is_dir() {
-            eprintln!(
-                "MicrosoftKitRoot({}) was found in environment, but does not exist or is not a \
-                 valid directory.",
-                path.display()
+            reject_wdk_content_root_location(
+                &mut searched_locations,
+                format!(
+                    "environment variable MicrosoftKitRoot: {} does not exist or is not a valid \
+                     directory",
+                    path.display()
+                ),
             );
         } else {
             let wdk_kit_version =
                 env::var("WDKKitVersion").map_or("10.0".to_string(), |version| version);
             let path = path.join("Windows Kits").join(wdk_kit_version);
             if path.is_dir() {
-                return Some(path);
+                return Ok(path);
             }
-            eprintln!(
-                "WDKContentRoot was detected to be {}, but does not exist or is not a valid \
-                 directory.",
-                path.display()
+            reject_wdk_content_root_location(
+                &mut searched_locations,
+                format!(
+                    "environment variables MicrosoftKitRoot and WDKKitVersion: {} does not exist \
+                     or is not a valid directory",
+                    path.display()
+                ),
             );
         }
+    } else {
+        searched_locations.push("environment variable MicrosoftKitRoot: not set".to_string());
     }
 
-    // Check WDKContentRoot environment variable
-    if let Ok(wdk_content_root) = env::var("WDKContentRoot") {
-        let path = Path::new(wdk_content_root.as_str());
-        if path.is_dir() {
-            return Some(path.to_path_buf());
+    // Check HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Windows Kits\Installed
+    // Roots@KitsRoot10 and
+    // HKEY_LOCAL_MACHINE\SOFTWARE\Wow6432Node\Microsoft\Windows Kits\Installed
+    // Roots@KitsRoot10 registry keys
+    for (sub_key, sub_key_name) in [
+        (
+            s!(r"SOFTWARE\Microsoft\Windows Kits\Installed Roots"),
+            r"SOFTWARE\Microsoft\Windows Kits\Installed Roots",
+        ),
+        (
+            s!(r"SOFTWARE\Wow6432Node\Microsoft\Windows Kits\Installed Roots"),
+            r"SOFTWARE\Wow6432Node\Microsoft\Windows Kits\Installed Roots",
+        ),
+    ] {
+        if let Some(path) =
+            read_registry_key_string_value(HKEY_LOCAL_MACHINE, sub_key, s!(r"KitsRoot10"))
+        {
+            return Ok(Path::new(path.as_str()).to_path_buf());
         }
-        eprintln!(
-            "WDKContentRoot({}) was found in environment, but does not exist or is not a valid \
-             directory.",
-            path.display()
-        );
+        searched_locations.push(format!(
+            r"registry value HKEY_LOCAL_MACHINE\{sub_key_name}@KitsRoot10: not found"
+        ));
     }
 
-    // Check HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Windows Kits\Installed
-    // Roots@KitsRoot10 registry key
-    if let Some(path) = read_registry_key_string_value(
-        HKEY_LOCAL_MACHINE,
-        s!(r"SOFTWARE\Microsoft\Windows Kits\Installed Roots"),
-        s!(r"KitsRoot10"),
-    ) {
-        return Some(Path::new(path.as_str()).to_path_buf());
-    }
+    Err(ConfigError::WDKContentRootDetectionError { searched_locations })
+}
 
-    // Check HKEY_LOCAL_MACHINE\SOFTWARE\Wow6432Node\Microsoft\Windows
-    // Kits\Installed Roots@KitsRoot10 registry key
-    if let Some(path) = read_registry_key_string_value(
-        HKEY_LOCAL_MACHINE,
-        s!(r"SOFTWARE\Wow6432Node\Microsoft\Windows Kits\Installed Roots"),
-        s!(r"KitsRoot10"),
-    ) {
-        return Some(Path::new(path.as_str()).to_path_buf());
-    }
+/// Records a location that was set, but rejected as the WDK content root. This
+/// is also reported immediately, since it is likely a misconfiguration even if
+/// the WDK content root is found elsewhere.
+fn reject_wdk_content_root_location(searched_locations: &mut Vec<String>, location: String) {
+    eprintln!("{location}");
+    searched_locations.push(location);
+}
 
-    None
+/// Detect the directory containing `libclang.dll` bundled with the MSVC build
+/// tools, so that `bindgen` can be used in environments where LLVM is not
+/// installed separately (ex. an eWDK). The directory is searched for in:
+///
+/// 1. `VCINSTALLDIR`, which is set in an eWDK prompt and in Visual Studio
+///    developer prompts
+/// 2. the build tools bundled in the eWDK mounted at `WDK_CONTENT_ROOT`
+///
+/// # Panics
+///
+/// Panics if the CPU architecture of the host cannot be determined from
+/// `std::env::consts::ARCH`
+#[must_use]
+pub fn detect_libclang_directory() -> Option<PathBuf> {
+    let host_arch = CPUArchitecture::try_from_cargo_str(env::consts::ARCH)
+        .expect("The rust standard library should always set std::env::consts::ARCH");
+    let llvm_bin_relative_path = Path::new("Tools")
+        .join("Llvm")
+        .join(host_arch.as_windows_str())
+        .join("bin");
+
+    env::var("VCINSTALLDIR")
+        .ok()
+        .map(PathBuf::from)
+        .into_iter()
+        .chain(
+            env::var(WDK_CONTENT_ROOT_ENV_VAR)
+                .ok()
+                .map(|ewdk_root| Path::new(&ewdk_root).join(EWDK_VC_INSTALL_DIR_RELATIVE_PATH)),
+        )
+        .map(|vc_install_dir| vc_install_dir.join(&llvm_bin_relative_path))
+        .find(|llvm_bin_directory| llvm_bin_directory.join("libclang.dll").is_file())
 }
 
 /// Read a string value from a registry key
@@ -245,30 +346,64 @@ fn read_registry_key_string_value(
 /// Searches a directory and determines the latest windows SDK version in that
 /// directory
 pub fn get_latest_windows_sdk_version(path_to_search: &Path) -> Result<String, ConfigError> {
-    Ok(path_to_search
-        .read_dir()?
-        .filter_map(std::result::Result::ok)
-        .map(|valid_directory_entry| valid_directory_entry.path())
-        .filter(|path| {
-            path.is_dir()
-                && path.file_name().is_some_and(|directory_name| {
-                    directory_name
-                        .to_str()
-                        .is_some_and(|directory_name| directory_name.starts_with("10."))
-                })
-        })
-        .max() // Get the latest SDK folder in case there are multiple installed
-        .ok_or(ConfigError::DirectoryNotFound {
+    get_installed_windows_sdk_versions(path_to_search)?
+        .pop() // Get the latest SDK folder in case there are multiple installed
+        .ok_or_else(|| ConfigError::DirectoryNotFound {
             directory: format!(
                 "Windows SDK Directory in {}",
                 path_to_search.to_string_lossy()
             ),
-        })?
-        .file_name()
-        .expect("path should never terminate in ..")
-        .to_str()
-        .expect("directory name should always be valid Unicode")
-        .to_string())
+        })
+}
+
+/// Searches a directory and determines the windows SDK version to use in that
+/// directory. If `pinned_version` is set, it must be installed in
+/// `path_to_search`. Otherwise, the latest installed version is used.
+///
+/// # Errors
+///
+/// This function returns a [`ConfigError::WDKVersionNotFound`] listing the
+/// installed versions if `pinned_version` is not installed in
+/// `path_to_search`, or a [`ConfigError::DirectoryNotFound`] if no version is
+/// installed.
+pub fn get_windows_sdk_version(
+    path_to_search: &Path,
+    pinned_version: Option<&str>,
+) -> Result<String, ConfigError> {
+    let Some(pinned_version) = pinned_version else {
+        return get_latest_windows_sdk_version(path_to_search);
+    };
+
+    let installed_versions = get_installed_windows_sdk_versions(path_to_search)?;
+    if installed_versions
+        .iter()
+        .any(|installed_version| installed_version == pinned_version)
+    {
+        return Ok(pinned_version.to_string());
+    }
+    Err(ConfigError::WDKVersionNotFound {
+        version: pinned_version.to_string(),
+        directory: path_to_search.to_string_lossy().to_string(),
+        installed_versions,
+    })
+}
+
+/// Returns the sorted list of windows SDK versions installed in a directory
+fn get_installed_windows_sdk_versions(path_to_search: &Path) -> Result<Vec<String>, ConfigError> {
+    let mut installed_versions = path_to_search
+        .read_dir()?
+        .filter_map(std::result::Result::ok)
+        .map(|valid_directory_entry| valid_directory_entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| {
+            path.file_name()
+                .and_then(|directory_name| directory_name.to_str())
+                .filter(|directory_name| directory_name.starts_with("10."))
+                .map(ToString::to_string)
+        })
+        .collect::<Vec<_>>();
+    installed_versions.sort();
+    Ok(installed_versions)
 }
 
 /// Detect architecture based on cargo TARGET variable.
@@ -0,0 +1,540 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! This module provides functions to enable and disable Driver Verifier and
+//! the KMDF Verifier for a driver.
+//!
+//! This allows test pipelines to toggle verification without maintaining their
+//! own scripts. See the [Driver Verifier Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/devtest/driver-verifier)
+//! and the [KMDF Verifier Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/wdf/using-kmdf-verifier)
+//! for more details.
+//!
+//! The settings are written directly to the same registry values that
+//! `verifier.exe` and `WdfVerifier.exe` use, so they take effect after the next
+//! reboot of the machine. Modifying these settings requires administrator
+//! privileges.
+//!
+//! ```no_run
+//! use wdk_build::verifier::{self, KmdfVerifierSettings, VerifierFlags};
+//!
+//! verifier::enable("sample_kmdf_driver", VerifierFlags::STANDARD)?;
+//! verifier::enable_kmdf_verifier("sample_kmdf_driver", KmdfVerifierSettings::default())?;
+//! # Ok::<(), wdk_build::verifier::VerifierError>(())
+//! ```
+
+use std::{
+    ffi::CString,
+    ops::{BitOr, BitOrAssign},
+};
+
+use thiserror::Error;
+use windows::{
+    core::PCSTR,
+    Win32::{
+        Foundation::ERROR_FILE_NOT_FOUND,
+        System::Registry::{
+            RegCloseKey,
+            RegCreateKeyExA,
+            RegDeleteValueA,
+            RegGetValueA,
+            RegSetValueExA,
+            HKEY,
+            HKEY_LOCAL_MACHINE,
+            KEY_READ,
+            KEY_WRITE,
+            REG_DWORD,
+            REG_OPTION_NON_VOLATILE,
+            REG_SZ,
+            RRF_RT_REG_SZ,
+        },
+    },
+};
+
+/// Registry key containing the Driver Verifier settings
+const MEMORY_MANAGEMENT_KEY: &str =
+    r"SYSTEM\CurrentControlSet\Control\Session Manager\Memory Management";
+/// Registry value containing the space-delimited list of drivers verified by
+/// Driver Verifier
+const VERIFY_DRIVERS_VALUE: &str = "VerifyDrivers";
+/// Registry value containing the [`VerifierFlags`] used by Driver Verifier
+const VERIFY_DRIVER_LEVEL_VALUE: &str = "VerifyDriverLevel";
+
+/// Registry value that enables the KMDF Verifier for a driver
+const KMDF_VERIFIER_ON_VALUE: &str = "VerifierOn";
+/// Registry value that enables verbose KMDF logging for a driver
+const KMDF_VERBOSE_ON_VALUE: &str = "VerboseOn";
+/// Registry value that makes KMDF break into the debugger when a driver
+/// error is detected
+const KMDF_DBG_BREAK_ON_ERROR_VALUE: &str = "DbgBreakOnError";
+
+/// Errors that could result from configuring Driver Verifier or the KMDF
+/// Verifier
+#[derive(Debug, Error)]
+pub enum VerifierError {
+    /// Error returned when a registry operation fails (ex. when not running
+    /// with administrator privileges)
+    #[error("failed to {operation} registry key HKLM\\{key}: {source}")]
+    RegistryError {
+        /// Description of the registry operation that failed
+        operation: &'static str,
+        /// Path of the registry key, relative to `HKEY_LOCAL_MACHINE`
+        key: String,
+        /// Error returned by the registry API
+        source: windows::core::Error,
+    },
+}
+
+/// Driver Verifier options, as passed to `verifier.exe /flags`. Options can be
+/// combined via `|`.
+///
+/// The full documentation of each option is available in the [Driver Verifier Options Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/devtest/driver-verifier-options-and-rule-classes)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VerifierFlags(u32);
+
+/// Settings of the KMDF Verifier for a driver
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KmdfVerifierSettings {
+    /// Log verbose information to the KMDF log (`VerboseOn`)
+    pub verbose: bool,
+    /// Break into the debugger when KMDF detects a driver error
+    /// (`DbgBreakOnError`)
+    pub break_on_error: bool,
+}
+
+/// Open handle to a registry key, that is closed when dropped
+struct RegistryKey {
+    handle: HKEY,
+    path: String,
+}
+
+impl VerifierFlags {
+    /// Code integrity checks
+    pub const CODE_INTEGRITY_CHECKS: Self = Self(0x0200_0000);
+    /// DDI compliance checking
+    pub const DDI_COMPLIANCE_CHECKING: Self = Self(0x0002_0000);
+    /// DDI compliance checking (additional)
+    pub const DDI_COMPLIANCE_CHECKING_ADDITIONAL: Self = Self(0x0008_0000);
+    /// Deadlock detection
+    pub const DEADLOCK_DETECTION: Self = Self(0x0000_0020);
+    /// DMA checking
+    pub const DMA_CHECKING: Self = Self(0x0000_0080);
+    /// Force IRQL checking
+    pub const FORCE_IRQL_CHECKING: Self = Self(0x0000_0002);
+    /// Force pending I/O requests
+    pub const FORCE_PENDING_IO_REQUESTS: Self = Self(0x0000_0200);
+    /// Invariant MDL checking for driver
+    pub const INVARIANT_MDL_CHECKING_FOR_DRIVER: Self = Self(0x0000_4000);
+    /// Invariant MDL checking for stack
+    pub const INVARIANT_MDL_CHECKING_FOR_STACK: Self = Self(0x0000_2000);
+    /// I/O verification
+    pub const IO_VERIFICATION: Self = Self(0x0000_0010);
+    /// IRP logging
+    pub const IRP_LOGGING: Self = Self(0x0000_0400);
+    /// Kernel synchronization delay fuzzing
+    pub const KERNEL_SYNCHRONIZATION_DELAY_FUZZING: Self = Self(0x0080_0000);
+    /// Low resources simulation
+    pub const LOW_RESOURCES_SIMULATION: Self = Self(0x0000_0004);
+    /// Miscellaneous checks
+    pub const MISCELLANEOUS_CHECKS: Self = Self(0x0000_0800);
+    /// NDIS/WIFI verification
+    pub const NDIS_WIFI_VERIFICATION: Self = Self(0x0020_0000);
+    /// Pool tracking
+    pub const POOL_TRACKING: Self = Self(0x0000_0008);
+    /// Port/miniport interface checking
+    pub const PORT_MINIPORT_INTERFACE_CHECKING: Self = Self(0x0001_0000);
+    /// Power framework delay fuzzing
+    pub const POWER_FRAMEWORK_DELAY_FUZZING: Self = Self(0x0000_8000);
+    /// Security checks
+    pub const SECURITY_CHECKS: Self = Self(0x0000_0100);
+    /// Special Pool
+    pub const SPECIAL_POOL: Self = Self(0x0000_0001);
+    /// The standard options, as enabled by `verifier.exe /standard`
+    pub const STANDARD: Self = Self(
+        Self::SPECIAL_POOL.0
+            | Self::FORCE_IRQL_CHECKING.0
+            | Self::POOL_TRACKING.0
+            | Self::IO_VERIFICATION.0
+            | Self::DEADLOCK_DETECTION.0
+            | Self::DMA_CHECKING.0
+            | Self::SECURITY_CHECKS.0
+            | Self::MISCELLANEOUS_CHECKS.0
+            | Self::DDI_COMPLIANCE_CHECKING.0,
+    );
+    /// Systematic low resources simulation
+    pub const SYSTEMATIC_LOW_RESOURCES_SIMULATION: Self = Self(0x0004_0000);
+    /// VM switch verification
+    pub const VM_SWITCH_VERIFICATION: Self = Self(0x0100_0000);
+
+    /// Construct [`VerifierFlags`] from the raw value passed to
+    /// `verifier.exe /flags`
+    #[must_use]
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// Get the raw value of the [`VerifierFlags`], as passed to `verifier.exe
+    /// /flags`
+    #[must_use]
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Returns `true` if all options enabled in `other` are also enabled in
+    /// `self`
+    #[must_use]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for VerifierFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for VerifierFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl Default for KmdfVerifierSettings {
+    fn default() -> Self {
+        Self {
+            verbose: true,
+            break_on_error: true,
+        }
+    }
+}
+
+impl RegistryKey {
+    /// Opens the registry key at `path` (relative to `HKEY_LOCAL_MACHINE`),
+    /// creating it if it does not exist
+    fn create(path: String) -> Result<Self, VerifierError> {
+        let sub_key = to_cstring(&path);
+        let mut handle = HKEY::default();
+        // SAFETY: `sub_key` is a valid null-terminated string that outlives the call.
+        // `&mut handle` is coerced to a &raw mut, so the address passed as the argument
+        // is always valid.
+        unsafe {
+            RegCreateKeyExA(
+                HKEY_LOCAL_MACHINE,
+                PCSTR(sub_key.as_ptr().cast()),
+                0,
+                None,
+                REG_OPTION_NON_VOLATILE,
+                KEY_READ | KEY_WRITE,
+                None,
+                &mut handle,
+                None,
+            )
+        }
+        .ok()
+        .map_err(|source| VerifierError::RegistryError {
+            operation: "open",
+            key: path.clone(),
+            source,
+        })?;
+        Ok(Self { handle, path })
+    }
+
+    /// Reads the `REG_SZ` value `name`, or returns `None` if it does not exist
+    fn get_string(&self, name: &str) -> Result<Option<String>, VerifierError> {
+        let value_name = to_cstring(name);
+        let mut len = 0;
+        // SAFETY: `handle` is a valid key opened with the `KEY_QUERY_VALUE` access
+        // right (included in `KEY_READ`). `value_name` is a valid
+        // null-terminated string that outlives the call. `&mut len` is coerced
+        // to a &raw mut, so the address passed as the argument is always valid.
+        let result = unsafe {
+            RegGetValueA(
+                self.handle,
+                None,
+                PCSTR(value_name.as_ptr().cast()),
+                RRF_RT_REG_SZ,
+                None,
+                None,
+                Some(&mut len),
+            )
+        };
+        if result == ERROR_FILE_NOT_FOUND {
+            return Ok(None);
+        }
+        result.ok().map_err(|source| self.error("read", source))?;
+
+        let mut buffer = vec![0u8; len as usize];
+        // SAFETY: `handle` is a valid key opened with the `KEY_QUERY_VALUE` access
+        // right (included in `KEY_READ`). `value_name` is a valid
+        // null-terminated string that outlives the call. `buffer` is valid for
+        // writes of `len` bytes. `&mut len` is coerced to a &raw mut, so the
+        // address passed as the argument is always valid.
+        unsafe {
+            RegGetValueA(
+                self.handle,
+                None,
+                PCSTR(value_name.as_ptr().cast()),
+                RRF_RT_REG_SZ,
+                None,
+                Some(buffer.as_mut_ptr().cast()),
+                Some(&mut len),
+            )
+        }
+        .ok()
+        .map_err(|source| self.error("read", source))?;
+
+        buffer.truncate(len as usize);
+        Ok(Some(
+            String::from_utf8_lossy(&buffer)
+                .trim_end_matches('\0')
+                .to_string(),
+        ))
+    }
+
+    /// Writes `value` to the `REG_SZ` value `name`
+    fn set_string(&self, name: &str, value: &str) -> Result<(), VerifierError> {
+        let value_name = to_cstring(name);
+        let value = to_cstring(value);
+        // SAFETY: `handle` is a valid key opened with the `KEY_SET_VALUE` access right
+        // (included in `KEY_WRITE`). `value_name` is a valid null-terminated string
+        // that outlives the call.
+        unsafe {
+            RegSetValueExA(
+                self.handle,
+                PCSTR(value_name.as_ptr().cast()),
+                0,
+                REG_SZ,
+                Some(value.as_bytes_with_nul()),
+            )
+        }
+        .ok()
+        .map_err(|source| self.error("write", source))
+    }
+
+    /// Writes `value` to the `REG_DWORD` value `name`
+    fn set_u32(&self, name: &str, value: u32) -> Result<(), VerifierError> {
+        let value_name = to_cstring(name);
+        // SAFETY: `handle` is a valid key opened with the `KEY_SET_VALUE` access right
+        // (included in `KEY_WRITE`). `value_name` is a valid null-terminated string
+        // that outlives the call.
+        unsafe {
+            RegSetValueExA(
+                self.handle,
+                PCSTR(value_name.as_ptr().cast()),
+                0,
+                REG_DWORD,
+                Some(&value.to_le_bytes()),
+            )
+        }
+        .ok()
+        .map_err(|source| self.error("write", source))
+    }
+
+    /// Deletes the value `name`, if it exists
+    fn delete_value(&self, name: &str) -> Result<(), VerifierError> {
+        let value_name = to_cstring(name);
+        // SAFETY: `handle` is a valid key opened with the `KEY_SET_VALUE` access right
+        // (included in `KEY_WRITE`). `value_name` is a valid null-terminated string
+        // that outlives the call.
+        let result = unsafe { RegDeleteValueA(self.handle, PCSTR(value_name.as_ptr().cast())) };
+        if result == ERROR_FILE_NOT_FOUND {
+            return Ok(());
+        }
+        result.ok().map_err(|source| self.error("write", source))
+    }
+
+    fn error(&self, operation: &'static str, source: windows::core::Error) -> VerifierError {
+        VerifierError::RegistryError {
+            operation,
+            key: self.path.clone(),
+            source,
+        }
+    }
+}
+
+impl Drop for RegistryKey {
+    fn drop(&mut self) {
+        // SAFETY: `handle` is a valid key that was opened by `RegCreateKeyExA`, and is
+        // not used after this point
+        unsafe { RegCloseKey(self.handle) }
+            .ok()
+            .expect("registry key handle should be successfully closed");
+    }
+}
+
+/// Enables Driver Verifier for the driver named `driver_name` (ex.
+/// `sample_kmdf_driver`), with the options in `flags`.
+///
+/// Other drivers that Driver Verifier is already enabled for stay enabled, but
+/// use the new `flags`, since the options apply to all verified drivers.
+///
+/// # Errors
+///
+/// This function returns a [`VerifierError::RegistryError`] if the Driver
+/// Verifier settings could not be read or written (ex. when not running with
+/// administrator privileges).
+pub fn enable(driver_name: &str, flags: VerifierFlags) -> Result<(), VerifierError> {
+    let memory_management_key = RegistryKey::create(MEMORY_MANAGEMENT_KEY.to_string())?;
+    let verify_drivers = memory_management_key
+        .get_string(VERIFY_DRIVERS_VALUE)?
+        .unwrap_or_default();
+
+    memory_management_key.set_string(
+        VERIFY_DRIVERS_VALUE,
+        &add_to_driver_list(&verify_drivers, &driver_image_name(driver_name)),
+    )?;
+    memory_management_key.set_u32(VERIFY_DRIVER_LEVEL_VALUE, flags.bits())
+}
+
+/// Disables Driver Verifier for the driver named `driver_name` (ex.
+/// `sample_kmdf_driver`). If no other drivers are verified, Driver Verifier is
+/// disabled entirely.
+///
+/// # Errors
+///
+/// This function returns a [`VerifierError::RegistryError`] if the Driver
+/// Verifier settings could not be read or written (ex. when not running with
+/// administrator privileges).
+pub fn disable(driver_name: &str) -> Result<(), VerifierError> {
+    let memory_management_key = RegistryKey::create(MEMORY_MANAGEMENT_KEY.to_string())?;
+    let Some(verify_drivers) = memory_management_key.get_string(VERIFY_DRIVERS_VALUE)? else {
+        return Ok(());
+    };
+
+    let verify_drivers = remove_from_driver_list(&verify_drivers, &driver_image_name(driver_name));
+    if verify_drivers.is_empty() {
+        memory_management_key.delete_value(VERIFY_DRIVERS_VALUE)?;
+        memory_management_key.delete_value(VERIFY_DRIVER_LEVEL_VALUE)
+    } else {
+        memory_management_key.set_string(VERIFY_DRIVERS_VALUE, &verify_drivers)
+    }
+}
+
+/// Enables the KMDF Verifier for the driver whose service is named
+/// `driver_name` (ex. `sample_kmdf_driver`)
+///
+/// # Errors
+///
+/// This function returns a [`VerifierError::RegistryError`] if the KMDF
+/// Verifier settings could not be written (ex. when not running with
+/// administrator privileges).
+pub fn enable_kmdf_verifier(
+    driver_name: &str,
+    settings: KmdfVerifierSettings,
+) -> Result<(), VerifierError> {
+    let wdf_parameters_key = RegistryKey::create(wdf_parameters_key_path(driver_name))?;
+    wdf_parameters_key.set_u32(KMDF_VERIFIER_ON_VALUE, 1)?;
+    wdf_parameters_key.set_u32(KMDF_VERBOSE_ON_VALUE, u32::from(settings.verbose))?;
+    wdf_parameters_key.set_u32(
+        KMDF_DBG_BREAK_ON_ERROR_VALUE,
+        u32::from(settings.break_on_error),
+    )
+}
+
+/// Disables the KMDF Verifier for the driver whose service is named
+/// `driver_name` (ex. `sample_kmdf_driver`)
+///
+/// # Errors
+///
+/// This function returns a [`VerifierError::RegistryError`] if the KMDF
+/// Verifier settings could not be written (ex. when not running with
+/// administrator privileges).
+pub fn disable_kmdf_verifier(driver_name: &str) -> Result<(), VerifierError> {
+    let wdf_parameters_key = RegistryKey::create(wdf_parameters_key_path(driver_name))?;
+    wdf_parameters_key.delete_value(KMDF_VERIFIER_ON_VALUE)?;
+    wdf_parameters_key.delete_value(KMDF_VERBOSE_ON_VALUE)?;
+    wdf_parameters_key.delete_value(KMDF_DBG_BREAK_ON_ERROR_VALUE)
+}
+
+fn driver_image_name(driver_name: &str) -> String {
+    if driver_name.to_ascii_lowercase().ends_with(".sys") {
+        driver_name.to_string()
+    } else {
+        format!("{driver_name}.sys")
+    }
+}
+
+fn wdf_parameters_key_path(driver_name: &str) -> String {
+    let service_name = driver_name.strip_suffix(".sys").unwrap_or(driver_name);
+    format!(r"SYSTEM\CurrentControlSet\Services\{service_name}\Parameters\Wdf")
+}
+
+/// Adds `image_name` to the space-delimited `driver_list`, if it is not already
+/// in it
+fn add_to_driver_list(driver_list: &str, image_name: &str) -> String {
+    let mut drivers = driver_list.split_whitespace().collect::<Vec<_>>();
+    if !drivers
+        .iter()
+        .any(|driver| driver.eq_ignore_ascii_case(image_name))
+    {
+        drivers.push(image_name);
+    }
+    drivers.join(" ")
+}
+
+/// Removes `image_name` from the space-delimited `driver_list`
+fn remove_from_driver_list(driver_list: &str, image_name: &str) -> String {
+    driver_list
+        .split_whitespace()
+        .filter(|driver| !driver.eq_ignore_ascii_case(image_name))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn to_cstring(string: &str) -> CString {
+    CString::new(string).expect("registry key and value names should not contain interior nulls")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_flags() {
+        assert_eq!(VerifierFlags::STANDARD.bits(), 0x209BB);
+        assert!(VerifierFlags::STANDARD.contains(VerifierFlags::SPECIAL_POOL));
+        assert!(!VerifierFlags::STANDARD.contains(VerifierFlags::LOW_RESOURCES_SIMULATION));
+
+        let mut flags = VerifierFlags::SPECIAL_POOL;
+        flags |= VerifierFlags::IO_VERIFICATION;
+        assert_eq!(
+            flags,
+            VerifierFlags::SPECIAL_POOL | VerifierFlags::from_bits(0x10)
+        );
+    }
+
+    #[test]
+    fn driver_list() {
+        assert_eq!(add_to_driver_list("", "foo.sys"), "foo.sys");
+        assert_eq!(add_to_driver_list("bar.sys", "foo.sys"), "bar.sys foo.sys");
+        assert_eq!(
+            add_to_driver_list("bar.sys FOO.SYS", "foo.sys"),
+            "bar.sys FOO.SYS"
+        );
+
+        assert_eq!(
+            remove_from_driver_list("bar.sys FOO.SYS", "foo.sys"),
+            "bar.sys"
+        );
+        assert_eq!(remove_from_driver_list("foo.sys", "foo.sys"), "");
+        assert_eq!(remove_from_driver_list("bar.sys", "foo.sys"), "bar.sys");
+    }
+
+    #[test]
+    fn driver_names() {
+        assert_eq!(
+            driver_image_name("sample_kmdf_driver"),
+            "sample_kmdf_driver.sys"
+        );
+        assert_eq!(
+            driver_image_name("sample_kmdf_driver.sys"),
+            "sample_kmdf_driver.sys"
+        );
+        assert_eq!(
+            wdf_parameters_key_path("sample_kmdf_driver.sys"),
+            r"SYSTEM\CurrentControlSet\Services\sample_kmdf_driver\Parameters\Wdf"
+        );
+    }
+}
@@ -0,0 +1,390 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Implementation of the `driver_entry` attribute macro
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{meta, parse::Parser, parse2, Error, ItemFn, LitBool, Result};
+
+/// Struct storing the arguments parsed from the `driver_entry` attribute, ex.
+/// `#[driver_entry(panic_handler = true)]`
+#[derive(Debug, PartialEq)]
+struct Arguments {
+    /// Whether to generate a `#[panic_handler]` for the driver. Disabled by
+    /// default, since it requires the `runtime` feature of `wdk` and conflicts
+    /// with panic handlers provided by other crates (ex. `wdk-panic`).
+    panic_handler: bool,
+}
+
+impl Arguments {
+    fn parse(attribute_tokens: TokenStream2) -> Result<Self> {
+        let mut arguments = Self {
+            panic_handler: false,
+        };
+
+        meta::parser(|meta| {
+            if meta.path.is_ident("panic_handler") {
+                arguments.panic_handler = meta.value()?.parse::<LitBool>()?.value;
+                return Ok(());
+            }
+            Err(meta.error("unsupported driver_entry argument"))
+        })
+        .parse2(attribute_tokens)?;
+
+        Ok(arguments)
+    }
+}
+
+pub fn driver_entry_impl(
+    attribute_tokens: TokenStream2,
+    item_tokens: TokenStream2,
+) -> TokenStream2 {
+    match generate_driver_entry(attribute_tokens, item_tokens) {
+        Ok(output_tokens) => output_tokens,
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+fn generate_driver_entry(
+    attribute_tokens: TokenStream2,
+    item_tokens: TokenStream2,
+) -> Result<TokenStream2> {
+    let arguments = Arguments::parse(attribute_tokens)?;
+    let user_driver_entry = parse2::<ItemFn>(item_tokens)?;
+    validate_signature(&user_driver_entry)?;
+
+    let user_driver_entry_ident = &user_driver_entry.sig.ident;
+    let panic_handler = arguments.panic_handler.then(|| {
+        quote! {
+            #[cfg(not(any(test, driver_type = "umdf")))]
+            #[panic_handler]
+            fn __wdk_panic_handler(info: &::core::panic::PanicInfo) -> ! {
+                ::wdk::__private::panic_handler(info)
+            }
+        }
+    });
+
+    Ok(quote! {
+        #user_driver_entry
+
+        #[doc(hidden)]
+        #[export_name = "DriverEntry"]
+        pub unsafe extern "system" fn __wdk_driver_entry(
+            driver_object: ::wdk::__private::wdk_sys::PDRIVER_OBJECT,
+            registry_path: ::wdk::__private::wdk_sys::PCUNICODE_STRING,
+        ) -> ::wdk::__private::wdk_sys::NTSTATUS {
+            // SAFETY: `DriverEntry` is only called by the I/O manager, which passes a
+            // valid driver object and registry path
+            unsafe {
+                ::wdk::__private::driver_entry(
+                    driver_object,
+                    registry_path,
+                    #user_driver_entry_ident,
+                )
+            }
+        }
+
+        #panic_handler
+    })
+}
+
+/// Validate the parts of the annotated function's signature that would
+/// otherwise result in confusing errors in the generated code. The argument
+/// and return types are validated by the type checker.
+fn validate_signature(user_driver_entry: &ItemFn) -> Result<()> {
+    let signature = &user_driver_entry.sig;
+
+    if let Some(asyncness) = &signature.asyncness {
+        return Err(Error::new_spanned(
+            asyncness,
+            "driver_entry function cannot be async",
+        ));
+    }
+
+    if let Some(abi) = &signature.abi {
+        return Err(Error::new_spanned(
+            abi,
+            "driver_entry function should not specify an ABI, since the `extern \"system\"` \
+             DriverEntry function is generated by this macro",
+        ));
+    }
+
+    if !signature.generics.params.is_empty() || signature.generics.where_clause.is_some() {
+        return Err(Error::new_spanned(
+            &signature.generics,
+            "driver_entry function cannot be generic",
+        ));
+    }
+
+    if let Some(variadic) = &signature.variadic {
+        return Err(Error::new_spanned(
+            variadic,
+            "driver_entry function cannot be variadic",
+        ));
+    }
+
+    if signature.inputs.len() != 2 {
+        return Err(Error::new_spanned(
+            &signature.inputs,
+            "driver_entry function must take exactly two arguments: `&mut wdk::DriverObject` and \
+             `wdk::string::NtUnicodeStr<'_>`",
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use quote::quote;
+
+    use super::*;
+
+    mod arguments {
+        use super::*;
+
+        #[test]
+        fn no_arguments() {
+            let attribute_tokens = quote! {};
+            let expected = Arguments {
+                panic_handler: false,
+            };
+
+            pretty_assert_eq!(Arguments::parse(attribute_tokens).unwrap(), expected);
+        }
+
+        #[test]
+        fn panic_handler_enabled() {
+            let attribute_tokens = quote! { panic_handler = true };
+            let expected = Arguments {
+                panic_handler: true,
+            };
+
+            pretty_assert_eq!(Arguments::parse(attribute_tokens).unwrap(), expected);
+        }
+
+        #[test]
+        fn unsupported_argument() {
+            let attribute_tokens = quote! { unload = false };
+            let expected = "unsupported driver_entry argument";
+
+            pretty_assert_eq!(
+                Arguments::parse(attribute_tokens).unwrap_err().to_string(),
+                expected
+            );
+        }
+
+        #[test]
+        fn non_bool_panic_handler() {
+            let attribute_tokens = quote! { panic_handler = "false" };
+            let expected = "expected boolean literal";
+
+            pretty_assert_eq!(
+                Arguments::parse(attribute_tokens).unwrap_err().to_string(),
+                expected
+            );
+        }
+    }
+
+    mod validate_signature {
+        use super::*;
+
+        #[test]
+        fn valid_signature() {
+            let user_driver_entry = parse2::<ItemFn>(quote! {
+                fn driver_entry(
+                    driver_object: &mut wdk::DriverObject,
+                    registry_path: wdk::string::NtUnicodeStr<'_>,
+                ) -> wdk::Result<wdk::wdf::Driver> {
+                    todo!()
+                }
+            })
+            .unwrap();
+
+            assert!(validate_signature(&user_driver_entry).is_ok());
+        }
+
+        #[test]
+        fn async_fn() {
+            let user_driver_entry = parse2::<ItemFn>(quote! {
+                async fn driver_entry(
+                    driver_object: &mut wdk::DriverObject,
+                    registry_path: wdk::string::NtUnicodeStr<'_>,
+                ) -> wdk::Result<wdk::wdf::Driver> {
+                    todo!()
+                }
+            })
+            .unwrap();
+            let expected = "driver_entry function cannot be async";
+
+            pretty_assert_eq!(
+                validate_signature(&user_driver_entry)
+                    .unwrap_err()
+                    .to_string(),
+                expected
+            );
+        }
+
+        #[test]
+        fn explicit_abi() {
+            let user_driver_entry = parse2::<ItemFn>(quote! {
+                extern "system" fn driver_entry(
+                    driver_object: &mut wdk::DriverObject,
+                    registry_path: wdk::string::NtUnicodeStr<'_>,
+                ) -> wdk::Result<wdk::wdf::Driver> {
+                    todo!()
+                }
+            })
+            .unwrap();
+            let expected = "driver_entry function should not specify an ABI, since the `extern \
+                            \"system\"` DriverEntry function is generated by this macro";
+
+            pretty_assert_eq!(
+                validate_signature(&user_driver_entry)
+                    .unwrap_err()
+                    .to_string(),
+                expected
+            );
+        }
+
+        #[test]
+        fn generic_fn() {
+            let user_driver_entry = parse2::<ItemFn>(quote! {
+                fn driver_entry<T>(
+                    driver_object: &mut wdk::DriverObject,
+                    registry_path: wdk::string::NtUnicodeStr<'_>,
+                ) -> wdk::Result<wdk::wdf::Driver> {
+                    todo!()
+                }
+            })
+            .unwrap();
+            let expected = "driver_entry function cannot be generic";
+
+            pretty_assert_eq!(
+                validate_signature(&user_driver_entry)
+                    .unwrap_err()
+                    .to_string(),
+                expected
+            );
+        }
+
+        #[test]
+        fn wrong_number_of_arguments() {
+            let user_driver_entry = parse2::<ItemFn>(quote! {
+                fn driver_entry(
+                    driver_object: &mut wdk::DriverObject,
+                ) -> wdk::Result<wdk::wdf::Driver> {
+                    todo!()
+                }
+            })
+            .unwrap();
+            let expected = "driver_entry function must take exactly two arguments: `&mut \
+                            wdk::DriverObject` and `wdk::string::NtUnicodeStr<'_>`";
+
+            pretty_assert_eq!(
+                validate_signature(&user_driver_entry)
+                    .unwrap_err()
+                    .to_string(),
+                expected
+            );
+        }
+    }
+
+    mod generate_driver_entry {
+        use super::*;
+
+        #[test]
+        fn default_arguments() {
+            let attribute_tokens = quote! {};
+            let item_tokens = quote! {
+                fn driver_entry(
+                    driver_object: &mut wdk::DriverObject,
+                    registry_path: wdk::string::NtUnicodeStr<'_>,
+                ) -> wdk::Result<wdk::wdf::Driver> {
+                    todo!()
+                }
+            };
+            let expected = quote! {
+                fn driver_entry(
+                    driver_object: &mut wdk::DriverObject,
+                    registry_path: wdk::string::NtUnicodeStr<'_>,
+                ) -> wdk::Result<wdk::wdf::Driver> {
+                    todo!()
+                }
+
+                #[doc(hidden)]
+                #[export_name = "DriverEntry"]
+                pub unsafe extern "system" fn __wdk_driver_entry(
+                    driver_object: ::wdk::__private::wdk_sys::PDRIVER_OBJECT,
+                    registry_path: ::wdk::__private::wdk_sys::PCUNICODE_STRING,
+                ) -> ::wdk::__private::wdk_sys::NTSTATUS {
+                    unsafe {
+                        ::wdk::__private::driver_entry(
+                            driver_object,
+                            registry_path,
+                            driver_entry,
+                        )
+                    }
+                }
+            };
+
+            pretty_assert_eq!(
+                generate_driver_entry(attribute_tokens, item_tokens)
+                    .unwrap()
+                    .to_string(),
+                expected.to_string()
+            );
+        }
+
+        #[test]
+        fn panic_handler_enabled() {
+            let attribute_tokens = quote! { panic_handler = true };
+            let item_tokens = quote! {
+                fn entry(
+                    driver_object: &mut wdk::DriverObject,
+                    registry_path: wdk::string::NtUnicodeStr<'_>,
+                ) -> wdk::Result<wdk::wdf::Driver> {
+                    todo!()
+                }
+            };
+            let expected = quote! {
+                fn entry(
+                    driver_object: &mut wdk::DriverObject,
+                    registry_path: wdk::string::NtUnicodeStr<'_>,
+                ) -> wdk::Result<wdk::wdf::Driver> {
+                    todo!()
+                }
+
+                #[doc(hidden)]
+                #[export_name = "DriverEntry"]
+                pub unsafe extern "system" fn __wdk_driver_entry(
+                    driver_object: ::wdk::__private::wdk_sys::PDRIVER_OBJECT,
+                    registry_path: ::wdk::__private::wdk_sys::PCUNICODE_STRING,
+                ) -> ::wdk::__private::wdk_sys::NTSTATUS {
+                    unsafe {
+                        ::wdk::__private::driver_entry(
+                            driver_object,
+                            registry_path,
+                            entry,
+                        )
+                    }
+                }
+
+                #[cfg(not(any(test, driver_type = "umdf")))]
+                #[panic_handler]
+                fn __wdk_panic_handler(info: &::core::panic::PanicInfo) -> ! {
+                    ::wdk::__private::panic_handler(info)
+                }
+            };
+
+            pretty_assert_eq!(
+                generate_driver_entry(attribute_tokens, item_tokens)
+                    .unwrap()
+                    .to_string(),
+                expected.to_string()
+            );
+        }
+    }
+}
@@ -11,7 +11,6 @@
 };
 
 use cargo_metadata::{Message, MetadataCommand, PackageId};
-use itertools::Itertools;
 use proc_macro::TokenStream;
 use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::{format_ident, quote};
@@ -32,6 +31,7 @@
     Ident,
     Item,
     ItemType,
+    ItemUse,
     Path,
     PathArguments,
     PathSegment,
@@ -46,6 +46,25 @@
     TypePtr,
 };
 
+mod driver_entry;
+mod wdf_function_table_bindings;
+
+/// Environment variable that, when set at compile time, contains the path to
+/// the `types.rs` file generated by `wdk-sys`. This lets the macros skip the
+/// `cargo check` invocation in [`find_wdk_sys_out_dir`].
+const TYPES_RS_PATH_ENV_VAR: &str = "WDK_SYS_TYPES_RS_PATH";
+
+/// Handle outputs of WDF functions that are annotated `_Out_opt_`, as pairs of
+/// function and parameter names, which can be omitted with `..` to pass
+/// `WDF_NO_HANDLE`. Other handle outputs (ex. the `Device` parameter of
+/// `WdfDeviceCreate`) are written to unconditionally by WDF, so they have no
+/// default.
+const OPTIONAL_HANDLE_OUTPUT_PARAMETERS: [(&str, &str); 3] = [
+    ("WdfDriverCreate", "Driver"),
+    ("WdfIoQueueCreate", "Queue"),
+    ("WdfWmiInstanceCreate", "Instance"),
+];
+
 /// A procedural macro that allows WDF functions to be called by name.
 ///
 /// This function parses the name of the WDF function, finds it function pointer
@@ -57,6 +76,14 @@
 /// documentation. This macro does not perform any validation of the arguments
 /// passed to it., beyond type validation.
 ///
+/// The macro expands to a call to an inline `unsafe fn` named after the WDF
+/// function, so it must be invoked from an `unsafe` block or `unsafe fn`.
+/// Otherwise, compilation fails with an error that names the WDF function:
+///
+/// ```text
+/// error[E0133]: call to unsafe function `WdfTimerCreate` is unsafe and requires unsafe function or block
+/// ```
+///
 /// # Examples
 ///
 /// ```rust, no_run
@@ -85,28 +112,221 @@
 ///     }
 /// }
 /// ```
+///
+/// # Default trailing arguments
+///
+/// If the last argument passed to the macro is `..`, any remaining arguments
+/// of the WDF function are filled in with their canonical WDF default. This is
+/// only supported for the parameters that WDF defines a default for, which is
+/// a null pointer: `PWDF_OBJECT_ATTRIBUTES` (`WDF_NO_OBJECT_ATTRIBUTES`),
+/// `WDFCONTEXT` (`WDF_NO_CONTEXT`), `PWDF_REQUEST_SEND_OPTIONS`
+/// (`WDF_NO_SEND_OPTIONS`) and optional handle outputs (`WDF_NO_HANDLE`).
+/// Omitting any other parameter is a compilation error. For example, the
+/// following calls `WdfDriverCreate` with `WDF_NO_HANDLE` as its `Driver`
+/// argument:
+///
+/// ```rust, no_run
+/// # use wdk_sys::*;
+/// #
+/// # #[export_name = "DriverEntry"]
+/// # pub extern "system" fn driver_entry(
+/// #     driver: &mut DRIVER_OBJECT,
+/// #     registry_path: PCUNICODE_STRING,
+/// # ) -> NTSTATUS {
+/// #     let mut driver_config = WDF_DRIVER_CONFIG {
+/// #         Size: core::mem::size_of::<WDF_DRIVER_CONFIG>() as ULONG,
+/// #         ..WDF_DRIVER_CONFIG::default()
+/// #     };
+/// unsafe {
+///     wdk_macros::call_unsafe_wdf_function_binding!(
+///         WdfDriverCreate,
+///         driver as PDRIVER_OBJECT,
+///         registry_path,
+///         WDF_NO_OBJECT_ATTRIBUTES,
+///         &mut driver_config,
+///         ..
+///     )
+/// }
+/// # }
+/// ```
+///
+/// # Unused return values
+///
+/// The generated code is annotated with `#[must_use]` if the WDF function
+/// returns a value. Since WDF functions that return an `NTSTATUS` report their
+/// failures through it, discarding it produces an `unused_must_use` warning
+/// explaining that it should be checked (ex. with `nt_success`).
+///
+/// If the `strict` feature is enabled, discarding the `NTSTATUS` is a
+/// compilation error instead, which cannot be silenced by lint attributes or
+/// `let _ = ...`. Statuses that are intentionally ignored must be bound with
+/// their type spelled out:
+///
+/// ```rust, no_run
+/// # use wdk_sys::*;
+/// #
+/// # fn f(device: WDFDEVICE, symbolic_link_name: PCUNICODE_STRING) {
+/// let _: NTSTATUS = unsafe {
+///     wdk_macros::call_unsafe_wdf_function_binding!(
+///         WdfDeviceCreateSymbolicLink,
+///         device,
+///         symbolic_link_name,
+///     )
+/// };
+/// # }
+/// ```
+///
+/// # Locating `wdk-sys` types
+///
+/// The signatures of the WDF functions are read from the `types.rs` file
+/// generated by `wdk-sys`. If the `WDK_SYS_TYPES_RS_PATH` environment variable
+/// is set at compile time, it is used as the path to that file. Otherwise, the
+/// macro runs `cargo check` on `wdk-sys` in a separate target directory to
+/// locate it, which is slow and can fail in offline or vendored builds.
+///
+/// `WDK_SYS_TYPES_RS_PATH` is set automatically for `wdk-sys`, `wdk`, and any
+/// crate that directly depends on either of them and whose build script calls
+/// `wdk_build::Config::configure_library_build`,
+/// `wdk_build::Config::configure_binary_build` or
+/// `wdk_build::Config::export_config`.
+///
+/// # Renamed `wdk-sys` dependencies
+///
+/// The generated code refers to the `wdk-sys` crate as `wdk_sys`. If
+/// `wdk-sys` is renamed in `Cargo.toml`, or only reachable through a
+/// re-export in another crate, its path can be passed via a leading `crate =
+/// <path>` argument:
+///
+/// ```rust, no_run
+/// # extern crate wdk_sys as renamed_wdk_sys;
+/// unsafe {
+///     wdk_macros::call_unsafe_wdf_function_binding!(
+///         crate = renamed_wdk_sys,
+///         WdfVerifierDbgBreakPoint,
+///     )
+/// }
+/// ```
+///
+/// # Supplied driver globals
+///
+/// The WDF function is called with `wdk_sys::WdfDriverGlobals` by default.
+/// Code that receives a different `PWDF_DRIVER_GLOBALS` (ex. a companion
+/// library or class extension client) can pass it via a `globals = <expr>`
+/// argument, after any `crate = <path>` argument:
+///
+/// ```rust, no_run
+/// # use wdk_sys::*;
+/// #
+/// # fn f(driver_globals: PWDF_DRIVER_GLOBALS) {
+/// unsafe {
+///     wdk_macros::call_unsafe_wdf_function_binding!(
+///         globals = driver_globals,
+///         WdfVerifierDbgBreakPoint,
+///     )
+/// }
+/// # }
+/// ```
 #[allow(clippy::unnecessary_safety_doc)]
 #[proc_macro]
 pub fn call_unsafe_wdf_function_binding(input_tokens: TokenStream) -> TokenStream {
     call_unsafe_wdf_function_binding_impl(TokenStream2::from(input_tokens)).into()
 }
 
-/// A trait to provide additional functionality to the `String` type
-trait StringExt {
-    /// Convert a string to `snake_case`
-    fn to_snake_case(&self) -> String;
+/// An attribute macro that generates the `DriverEntry` function of a driver,
+/// and forwards to the annotated function.
+///
+/// This macro should be used via its re-export in the `wdk` crate
+/// (`wdk::driver_entry`), since the generated code depends on it. See the
+/// documentation there for the required signature of the annotated function.
+#[proc_macro_attribute]
+pub fn driver_entry(attribute_tokens: TokenStream, item_tokens: TokenStream) -> TokenStream {
+    driver_entry::driver_entry_impl(
+        TokenStream2::from(attribute_tokens),
+        TokenStream2::from(item_tokens),
+    )
+    .into()
+}
+
+/// A procedural macro that generates a module of typed wrapper functions for
+/// WDF functions in the WDF function table.
+///
+/// Each invocation of [`call_unsafe_wdf_function_binding!`] expands to its own
+/// inline function, which bloats code on hot paths that call the same WDF
+/// function in many places. This macro instead generates each wrapper once,
+/// as an `unsafe fn` with the same name and parameters as the WDF function in
+/// C (minus the `DriverGlobals` parameter), so call sites can invoke them
+/// directly and IDEs can discover their signatures.
+///
+/// If no list of WDF functions is supplied, a wrapper is generated for every
+/// function in the WDF function table.
+///
+/// # Safety
+/// Function arguments passed to the generated wrappers must abide by any rules
+/// outlined in the WDF documentation. The wrappers do not perform any
+/// validation of their arguments, beyond type validation.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use wdk_sys::*;
+///
+/// wdk_macros::wdf_function_table_bindings!(mod wdf {
+///     WdfDriverCreate,
+/// });
+///
+/// #[export_name = "DriverEntry"]
+/// pub extern "system" fn driver_entry(
+///     driver: &mut DRIVER_OBJECT,
+///     registry_path: PCUNICODE_STRING,
+/// ) -> NTSTATUS {
+///     let mut driver_config = WDF_DRIVER_CONFIG {
+///         Size: core::mem::size_of::<WDF_DRIVER_CONFIG>() as ULONG,
+///         ..WDF_DRIVER_CONFIG::default()
+///     };
+///     let driver_handle_output = WDF_NO_HANDLE as *mut WDFDRIVER;
+///
+///     unsafe {
+///         wdf::WdfDriverCreate(
+///             driver as PDRIVER_OBJECT,
+///             registry_path,
+///             WDF_NO_OBJECT_ATTRIBUTES,
+///             &mut driver_config,
+///             driver_handle_output,
+///         )
+///     }
+/// }
+/// ```
+///
+/// Like [`call_unsafe_wdf_function_binding!`], the path to a renamed `wdk-sys`
+/// dependency can be passed via a leading `crate = <path>` argument, and the
+/// `wdk-sys` types are located in the same way.
+#[allow(clippy::unnecessary_safety_doc)]
+#[proc_macro]
+pub fn wdf_function_table_bindings(input_tokens: TokenStream) -> TokenStream {
+    wdf_function_table_bindings::wdf_function_table_bindings_impl(TokenStream2::from(input_tokens))
+        .into()
 }
 
 /// Struct storing the input tokens directly parsed from calls to
 /// `call_unsafe_wdf_function_binding` macro
 #[derive(Debug, PartialEq)]
 struct Inputs {
+    /// Path to the `wdk-sys` crate, if overridden via a leading `crate = path`
+    /// argument. The generated code refers to `wdk_sys` otherwise.
+    wdk_sys_crate_path: Option<Path>,
+    /// Expression evaluating to the `PWDF_DRIVER_GLOBALS` to call the WDF
+    /// function with, if overridden via a `globals = expr` argument. The
+    /// generated code uses `wdk_sys::WdfDriverGlobals` otherwise.
+    driver_globals: Option<Expr>,
     /// The name of the WDF function to call. This matches the name of the
     /// function in C/C++.
     wdf_function_identifier: Ident,
     /// The arguments to pass to the WDF function. These should match the
     /// function signature of the WDF function.
     wdf_function_arguments: Punctuated<Expr, Token![,]>,
+    /// Whether the arguments ended with `..`, in which case the remaining
+    /// arguments of the WDF function are filled in with their WDF defaults
+    default_trailing_arguments: bool,
 }
 
 /// Struct storing all the AST fragments derived from `Inputs`. This represents
@@ -114,6 +334,8 @@ struct Inputs {
 /// final generated code that.
 #[derive(Debug, PartialEq)]
 struct DerivedASTFragments {
+    wdk_sys_crate_path: Option<Path>,
+    driver_globals: Option<Expr>,
     function_pointer_type: Ident,
     function_table_index: Ident,
     parameters: Punctuated<BareFnArg, Token![,]>,
@@ -126,66 +348,92 @@ struct DerivedASTFragments {
 /// Struct storing the AST fragments that form distinct sections of the final
 /// generated code. These sections are derived from `DerivedASTFragments`.
 struct IntermediateOutputASTFragments {
+    wdk_sys_crate_alias: Option<ItemUse>,
     must_use_attribute: Option<Attribute>,
     inline_wdf_fn_signature: Signature,
     inline_wdf_fn_body_statments: Vec<Stmt>,
+    strict_nt_status_items: Vec<Item>,
+    argument_bindings: Vec<Stmt>,
     inline_wdf_fn_invocation: ExprCall,
 }
 
-impl StringExt for String {
-    fn to_snake_case(&self) -> String {
-        // There will be, at max, 2 characters unhandled by the 3-char windows. It is
-        // only less than 2 when the string has length less than 2
-        const MAX_PADDING_NEEDED: usize = 2;
-
-        let mut snake_case_string = Self::with_capacity(self.len());
-
-        for (current_char, next_char, next_next_char) in self
-            .chars()
-            .map(Some)
-            .chain([None; MAX_PADDING_NEEDED])
-            .tuple_windows()
-            .filter_map(|(c1, c2, c3)| Some((c1?, c2, c3)))
-        {
-            // Handle camelCase or PascalCase word boundary (e.g. lC in camelCase)
-            if current_char.is_lowercase() && next_char.is_some_and(|c| c.is_ascii_uppercase()) {
-                snake_case_string.push(current_char);
-                snake_case_string.push('_');
-            }
-            // Handle UPPERCASE acronym word boundary (e.g. ISt in ASCIIString)
-            else if current_char.is_uppercase()
-                && next_char.is_some_and(|c| c.is_ascii_uppercase())
-                && next_next_char.is_some_and(|c| c.is_ascii_lowercase())
-            {
-                snake_case_string.push(current_char.to_ascii_lowercase());
-                snake_case_string.push('_');
-            } else {
-                snake_case_string.push(current_char.to_ascii_lowercase());
-            }
-        }
-
-        snake_case_string
-    }
-}
-
 impl Parse for Inputs {
     fn parse(input: ParseStream) -> Result<Self> {
+        let wdk_sys_crate_path = if input.peek(Token![crate]) && input.peek2(Token![=]) {
+            input.parse::<Token![crate]>()?;
+            input.parse::<Token![=]>()?;
+            let wdk_sys_crate_path = Path::parse_mod_style(input)?;
+            input.parse::<Token![,]>()?;
+            Some(wdk_sys_crate_path)
+        } else {
+            None
+        };
+
+        // `globals` cannot be the name of a WDF function, so it is unambiguous when
+        // followed by `=`
+        let driver_globals = if input.peek(Ident) && input.peek2(Token![=]) {
+            let globals_keyword = input.parse::<Ident>()?;
+            if globals_keyword != "globals" {
+                return Err(Error::new(
+                    globals_keyword.span(),
+                    "expected `globals = <expr>` or a WDF function name",
+                ));
+            }
+            input.parse::<Token![=]>()?;
+            let driver_globals = input.parse::<Expr>()?;
+            input.parse::<Token![,]>()?;
+            Some(driver_globals)
+        } else {
+            None
+        };
+
         let c_wdf_function_identifier = input.parse::<Ident>()?;
 
         // Support WDF apis with no arguments
         if input.is_empty() {
             return Ok(Self {
+                wdk_sys_crate_path,
+                driver_globals,
                 wdf_function_identifier: c_wdf_function_identifier,
                 wdf_function_arguments: Punctuated::new(),
+                default_trailing_arguments: false,
             });
         }
 
         input.parse::<Token![,]>()?;
-        let wdf_function_arguments = input.parse_terminated(Expr::parse, Token![,])?;
+        let mut wdf_function_arguments = Punctuated::new();
+        let mut default_trailing_arguments = false;
+        while !input.is_empty() {
+            // `..` must be checked before parsing an `Expr`, since it would otherwise be
+            // parsed as a `RangeFull` expression
+            if input.peek(Token![..]) {
+                let dot_dot_token = input.parse::<Token![..]>()?;
+                if !input.is_empty() {
+                    input.parse::<Token![,]>()?;
+                }
+                if !input.is_empty() {
+                    return Err(Error::new(
+                        dot_dot_token.spans[0],
+                        "`..` must be the last argument",
+                    ));
+                }
+                default_trailing_arguments = true;
+                break;
+            }
+
+            wdf_function_arguments.push_value(input.parse::<Expr>()?);
+            if input.is_empty() {
+                break;
+            }
+            wdf_function_arguments.push_punct(input.parse::<Token![,]>()?);
+        }
 
         Ok(Self {
+            wdk_sys_crate_path,
+            driver_globals,
             wdf_function_identifier: c_wdf_function_identifier,
             wdf_function_arguments,
+            default_trailing_arguments,
         })
     }
 }
@@ -204,31 +452,44 @@ fn generate_derived_ast_fragments(self) -> Result<DerivedASTFragments> {
         );
         let (parameters, return_type) =
             generate_parameters_and_return_type(&function_pointer_type)?;
-        let parameter_identifiers = parameters
-            .iter()
-            .cloned()
-            .map(|bare_fn_arg| {
-                if let Some((identifier, _)) = bare_fn_arg.name {
-                    return Ok(identifier);
-                }
-                Err(Error::new(
-                    function_pointer_type.span(),
-                    format!("Expected fn parameter to have a name: {bare_fn_arg:#?}"),
-                ))
-            })
-            .collect::<Result<_>>()?;
-        let inline_wdf_fn_name = format_ident!(
-            "{c_function_name_snake_case}_impl",
-            c_function_name_snake_case = self.wdf_function_identifier.to_string().to_snake_case()
-        );
+        let parameter_identifiers =
+            compute_parameter_identifiers(&parameters, function_pointer_type.span())?;
+        // The inline function is named after the WDF function, so that diagnostics
+        // about it (ex. calling it outside of an unsafe block) refer to the WDF
+        // function
+        let inline_wdf_fn_name = format_ident!("{}", self.wdf_function_identifier);
+
+        let mut arguments = self.wdf_function_arguments;
+        if self.default_trailing_arguments {
+            if arguments.len() > parameters.len() {
+                return Err(Error::new(
+                    self.wdf_function_identifier.span(),
+                    format!(
+                        "{} takes {} arguments, but {} arguments were supplied before `..`",
+                        self.wdf_function_identifier,
+                        parameters.len(),
+                        arguments.len()
+                    ),
+                ));
+            }
+            for bare_fn_arg in parameters.iter().skip(arguments.len()) {
+                arguments.push(generate_default_argument(
+                    &self.wdf_function_identifier,
+                    bare_fn_arg,
+                    self.wdf_function_identifier.span(),
+                )?);
+            }
+        }
 
         Ok(DerivedASTFragments {
+            wdk_sys_crate_path: self.wdk_sys_crate_path,
+            driver_globals: self.driver_globals,
             function_pointer_type,
             function_table_index,
             parameters,
             parameter_identifiers,
             return_type,
-            arguments: self.wdf_function_arguments,
+            arguments,
             inline_wdf_fn_name,
         })
     }
@@ -237,6 +498,8 @@ fn generate_derived_ast_fragments(self) -> Result<DerivedASTFragments> {
 impl DerivedASTFragments {
     fn generate_intermediate_output_ast_fragments(self) -> IntermediateOutputASTFragments {
         let Self {
+            wdk_sys_crate_path,
+            driver_globals,
             function_pointer_type,
             function_table_index,
             parameters,
@@ -246,52 +509,117 @@ fn generate_intermediate_output_ast_fragments(self) -> IntermediateOutputASTFrag
             inline_wdf_fn_name,
         } = self;
 
+        // The generated code refers to `wdk-sys` as `wdk_sys`, so an overridden crate
+        // path is brought into scope under that name
+        let wdk_sys_crate_alias = wdk_sys_crate_path.map(|wdk_sys_crate_path| {
+            parse_quote! {
+                use #wdk_sys_crate_path as wdk_sys;
+            }
+        });
         let must_use_attribute = generate_must_use_attribute(&return_type);
 
-        let inline_wdf_fn_signature = parse_quote! {
-            unsafe fn #inline_wdf_fn_name(#parameters) #return_type
+        // A supplied globals pointer cannot be captured by the inline function, so it
+        // is passed to it as an extra leading `DriverGlobals` parameter, the name of the
+        // parameter that the WDF function signatures omit
+        let (inline_wdf_fn_signature, inline_wdf_fn_driver_globals) = if driver_globals.is_some() {
+            (
+                parse_quote! {
+                    unsafe fn #inline_wdf_fn_name(
+                        DriverGlobals: wdk_sys::PWDF_DRIVER_GLOBALS,
+                        #parameters
+                    ) #return_type
+                },
+                parse_quote! { DriverGlobals },
+            )
+        } else {
+            (
+                parse_quote! {
+                    unsafe fn #inline_wdf_fn_name(#parameters) #return_type
+                },
+                parse_quote! { wdk_sys::WdfDriverGlobals },
+            )
         };
 
-        let inline_wdf_fn_body_statments = parse_quote! {
-            // Get handle to WDF function from the function table
-            let wdf_function: wdk_sys::#function_pointer_type = Some(
-                // SAFETY: This `transmute` from a no-argument function pointer to a function pointer with the correct
-                //         arguments for the WDF function is safe befause WDF maintains the strict mapping between the
-                //         function table index and the correct function pointer type.
-                unsafe {
-                    core::mem::transmute(
-                        // FIXME: investigate why _WDFFUNCENUM does not have a generated type alias without the underscore prefix
-                        wdk_sys::WDF_FUNCTION_TABLE[wdk_sys::_WDFFUNCENUM::#function_table_index as usize],
-                    )
-                }
-            );
+        let inline_wdf_fn_body_statments = generate_wdf_function_body_statements(
+            &function_pointer_type,
+            &function_table_index,
+            &inline_wdf_fn_driver_globals,
+            &parameter_identifiers,
+        );
 
-            // Call the WDF function with the supplied args. This mirrors what happens in the inlined WDF function in
-            // the various wdf headers(ex. wdfdriver.h)
-            if let Some(wdf_function) = wdf_function {
-                // SAFETY: The WDF function pointer is always valid because its an entry in
-                // `wdk_sys::WDF_FUNCTION_TABLE` indexed by `table_index` and guarded by the type-safety of
-                // `pointer_type`. The passed arguments are also guaranteed to be of a compatible type due to
-                // `pointer_type`.
-                unsafe {
-                    (wdf_function)(
-                        wdk_sys::WdfDriverGlobals,
-                        #parameter_identifiers
-                    )
-                }
-            } else {
-                unreachable!("Option should never be None");
-            }
-        };
+        // The globals pointer is bound to a variable annotated with its type, so that a
+        // mismatched type is reported at the supplied expression
+        let (driver_globals_binding, driver_globals_argument): (Option<Stmt>, Option<Ident>) =
+            driver_globals.map_or((None, None), |driver_globals| {
+                let driver_globals_binding_identifier = format_ident!("__driver_globals");
+                (
+                    Some(parse_quote! {
+                        let #driver_globals_binding_identifier: wdk_sys::PWDF_DRIVER_GLOBALS = #driver_globals;
+                    }),
+                    Some(driver_globals_binding_identifier),
+                )
+            });
 
-        let inline_wdf_fn_invocation = parse_quote! {
-            #inline_wdf_fn_name(#arguments)
+        // `Option` is not a repetition iterator for `quote`, so the optional globals
+        // argument is iterated explicitly
+        let driver_globals_arguments = driver_globals_argument.iter();
+
+        // Bind each argument to a variable annotated with the type of its corresponding
+        // parameter, so that type mismatches are reported at the argument expression
+        // instead of inside the generated function. If the number of arguments does not
+        // match, the arguments are passed through as-is so that the argument count
+        // mismatch is reported instead.
+        let (argument_bindings, inline_wdf_fn_invocation) = if arguments.len() == parameters.len() {
+            let (argument_bindings, argument_binding_identifiers): (Vec<Stmt>, Vec<Ident>) =
+                arguments
+                    .iter()
+                    .zip(parameters.iter())
+                    .enumerate()
+                    .map(|(index, (argument, parameter))| {
+                        let argument_binding_identifier = format_ident!("__arg{index}");
+                        let parameter_type = &parameter.ty;
+                        (
+                            parse_quote! {
+                                let #argument_binding_identifier: #parameter_type = #argument;
+                            },
+                            argument_binding_identifier,
+                        )
+                    })
+                    .unzip();
+            (
+                driver_globals_binding
+                    .into_iter()
+                    .chain(argument_bindings)
+                    .collect(),
+                parse_quote! {
+                    #inline_wdf_fn_name(#(#driver_globals_arguments,)* #(#argument_binding_identifiers),*)
+                },
+            )
+        } else {
+            (
+                driver_globals_binding.into_iter().collect(),
+                parse_quote! {
+                    #inline_wdf_fn_name(#(#driver_globals_arguments,)* #arguments)
+                },
+            )
         };
 
+        // With the `strict` feature, the returned `NTSTATUS` is passed through a
+        // function whose return type can only be inferred from how it is used, so
+        // discarding it is a compilation error instead of an `unused_must_use` warning
+        let (strict_nt_status_items, inline_wdf_fn_invocation) = apply_strict_nt_status_check(
+            cfg!(feature = "strict"),
+            &return_type,
+            inline_wdf_fn_invocation,
+        );
+
         IntermediateOutputASTFragments {
+            wdk_sys_crate_alias,
             must_use_attribute,
             inline_wdf_fn_signature,
             inline_wdf_fn_body_statments,
+            strict_nt_status_items,
+            argument_bindings,
             inline_wdf_fn_invocation,
         }
     }
@@ -300,9 +628,12 @@ unsafe fn #inline_wdf_fn_name(#parameters) #return_type
 impl IntermediateOutputASTFragments {
     fn assemble_final_output(self) -> TokenStream2 {
         let Self {
+            wdk_sys_crate_alias,
             must_use_attribute,
             inline_wdf_fn_signature,
             inline_wdf_fn_body_statments,
+            strict_nt_status_items,
+            argument_bindings,
             inline_wdf_fn_invocation,
         } = self;
 
@@ -311,12 +642,19 @@ fn assemble_final_output(self) -> TokenStream2 {
 
         quote! {
             {
+                #wdk_sys_crate_alias
+
                 #conditional_must_use_attribute
                 #[inline(always)]
+                #[allow(non_snake_case)]
                 #inline_wdf_fn_signature {
                     #(#inline_wdf_fn_body_statments)*
                 }
 
+                #(#strict_nt_status_items)*
+
+                #(#argument_bindings)*
+
                 #inline_wdf_fn_invocation
             }
         }
@@ -339,6 +677,49 @@ fn call_unsafe_wdf_function_binding_impl(input_tokens: TokenStream2) -> TokenStr
         .assemble_final_output()
 }
 
+/// Generate the statements that look up the WDF function at
+/// `function_table_index` in the WDF function table, and call it with
+/// `driver_globals` followed by `parameter_identifiers`. This mirrors the
+/// inlined WDF functions in the various WDF headers (ex. `wdfdriver.h`).
+fn generate_wdf_function_body_statements(
+    function_pointer_type: &Ident,
+    function_table_index: &Ident,
+    driver_globals: &Expr,
+    parameter_identifiers: &Punctuated<Ident, Token![,]>,
+) -> Vec<Stmt> {
+    parse_quote! {
+        // Get handle to WDF function from the function table
+        let wdf_function: wdk_sys::#function_pointer_type = Some(
+            // SAFETY: This `transmute` from a no-argument function pointer to a function pointer with the correct
+            //         arguments for the WDF function is safe befause WDF maintains the strict mapping between the
+            //         function table index and the correct function pointer type.
+            unsafe {
+                core::mem::transmute(
+                    // FIXME: investigate why _WDFFUNCENUM does not have a generated type alias without the underscore prefix
+                    wdk_sys::WDF_FUNCTION_TABLE[wdk_sys::_WDFFUNCENUM::#function_table_index as usize],
+                )
+            }
+        );
+
+        // Call the WDF function with the supplied args. This mirrors what happens in the inlined WDF function in
+        // the various wdf headers(ex. wdfdriver.h)
+        if let Some(wdf_function) = wdf_function {
+            // SAFETY: The WDF function pointer is always valid because its an entry in
+            // `wdk_sys::WDF_FUNCTION_TABLE` indexed by `table_index` and guarded by the type-safety of
+            // `pointer_type`. The passed arguments are also guaranteed to be of a compatible type due to
+            // `pointer_type`.
+            unsafe {
+                (wdf_function)(
+                    #driver_globals,
+                    #parameter_identifiers
+                )
+            }
+        } else {
+            unreachable!("Option should never be None");
+        }
+    }
+}
+
 /// Generate the function parameters and return type corresponding to the
 /// function signature of the `function_pointer_type` type alias in the AST for
 /// types.rs
@@ -360,23 +741,77 @@ fn call_unsafe_wdf_function_binding_impl(input_tokens: TokenStream2) -> TokenStr
 fn generate_parameters_and_return_type(
     function_pointer_type: &Ident,
 ) -> Result<(Punctuated<BareFnArg, Token![,]>, ReturnType)> {
-    let types_rs_ast = get_type_rs_ast()?;
-    let type_alias_definition = find_type_alias_definition(&types_rs_ast, function_pointer_type)?;
+    generate_parameters_and_return_type_from_ast(&get_type_rs_ast()?, function_pointer_type)
+}
+
+/// Same as [`generate_parameters_and_return_type`], but looks up
+/// `function_pointer_type` in an already parsed `types_rs_ast`. This avoids
+/// re-parsing the `wdk-sys` types when generating many WDF functions at once.
+fn generate_parameters_and_return_type_from_ast(
+    types_rs_ast: &File,
+    function_pointer_type: &Ident,
+) -> Result<(Punctuated<BareFnArg, Token![,]>, ReturnType)> {
+    let type_alias_definition = find_type_alias_definition(types_rs_ast, function_pointer_type)?;
     let fn_pointer_definition =
         extract_fn_pointer_definition(type_alias_definition, function_pointer_type.span())?;
     parse_fn_pointer_definition(fn_pointer_definition, function_pointer_type.span())
 }
 
-/// Finds the `types.rs` file generated by `wdk-sys` and parses it into an AST
+/// Collect the names of the parameters in `parameters`, so that they can be
+/// forwarded to the WDF function pointer
+fn compute_parameter_identifiers(
+    parameters: &Punctuated<BareFnArg, Token![,]>,
+    error_span: Span,
+) -> Result<Punctuated<Ident, Token![,]>> {
+    parameters
+        .iter()
+        .cloned()
+        .map(|bare_fn_arg| {
+            if let Some((identifier, _)) = bare_fn_arg.name {
+                return Ok(identifier);
+            }
+            Err(Error::new(
+                error_span,
+                format!("Expected fn parameter to have a name: {bare_fn_arg:#?}"),
+            ))
+        })
+        .collect()
+}
+
+/// Finds the `types.rs`, `wdf_types.rs` and (if present) `wdf_usb_types.rs`
+/// files generated by `wdk-sys` and parses them into a single AST. The path in
+/// [`TYPES_RS_PATH_ENV_VAR`] is used if it is set, and the `OUT_DIR` of
+/// `wdk-sys` is discovered via [`find_wdk_sys_out_dir`] otherwise.
 fn get_type_rs_ast() -> Result<File> {
-    let types_rs_path = find_wdk_sys_out_dir()?.join("types.rs");
-    let types_rs_contents = match std::fs::read_to_string(&types_rs_path) {
+    let types_rs_path = match std::env::var_os(TYPES_RS_PATH_ENV_VAR) {
+        Some(types_rs_path) => PathBuf::from(types_rs_path),
+        None => find_wdk_sys_out_dir()?.join("types.rs"),
+    };
+    let mut types_rs_ast = parse_types_rs_file(&types_rs_path)?;
+    // WDF types are generated separately from the rest of the WDK types
+    types_rs_ast
+        .items
+        .extend(parse_types_rs_file(&types_rs_path.with_file_name("wdf_types.rs"))?.items);
+    // WDF USB types are only generated when the `usb` feature of wdk-sys is enabled
+    let wdf_usb_types_rs_path = types_rs_path.with_file_name("wdf_usb_types.rs");
+    if wdf_usb_types_rs_path.exists() {
+        types_rs_ast
+            .items
+            .extend(parse_types_rs_file(&wdf_usb_types_rs_path)?.items);
+    }
+    Ok(types_rs_ast)
+}
+
+/// Reads the type definition file generated by `wdk-sys` at `types_rs_path` and
+/// parses it into an AST
+fn parse_types_rs_file(types_rs_path: &std::path::Path) -> Result<File> {
+    let types_rs_contents = match std::fs::read_to_string(types_rs_path) {
         Ok(contents) => contents,
         Err(err) => {
             return Err(Error::new(
                 Span::call_site(),
                 format!(
-                    "Failed to read wdk-sys types.rs at {}: {}",
+                    "Failed to read wdk-sys types at {}: {}",
                     types_rs_path.display(),
                     err
                 ),
@@ -389,7 +824,7 @@ fn get_type_rs_ast() -> Result<File> {
         Err(err) => Err(Error::new(
             Span::call_site(),
             format!(
-                "Failed to parse wdk-sys types.rs into AST at {}: {}",
+                "Failed to parse wdk-sys types into AST at {}: {}",
                 types_rs_path.display(),
                 err
             ),
@@ -844,8 +1279,7 @@ fn compute_fn_parameters(
                     }
                 };
 
-            parameter_type_path_segments
-                .insert(0, syn::PathSegment::from(format_ident!("wdk_sys")));
+            prepend_wdk_sys_path_segment(parameter_type_path_segments);
             Ok(bare_fn_arg)
         })
         .collect::<Result<_>>()?;
@@ -894,13 +1328,7 @@ fn compute_return_type(bare_fn_type: &syn::TypeBareFn, error_span: Span) -> Resu
                             ));
                         };
                         let mut segments = segments.clone();
-                        segments.insert(
-                            0,
-                            PathSegment {
-                                ident: format_ident!("wdk_sys"),
-                                arguments: PathArguments::None,
-                            },
-                        );
+                        prepend_wdk_sys_path_segment(&mut segments);
                         segments
                     },
                 },
@@ -910,105 +1338,170 @@ fn compute_return_type(bare_fn_type: &syn::TypeBareFn, error_span: Span) -> Resu
     Ok(return_type)
 }
 
-/// Generate the `#[must_use]` attribute if the return type is not `()`
+/// Prepend `wdk_sys` to the [`PathSegment`]s of a type, unless the type is a
+/// primitive type (ex. `usize`), which is not defined in `wdk_sys`
+fn prepend_wdk_sys_path_segment(segments: &mut Punctuated<PathSegment, syn::token::PathSep>) {
+    const PRIMITIVE_TYPES: [&str; 14] = [
+        "bool", "f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32",
+        "u64", "usize",
+    ];
+
+    let is_primitive_type = segments.len() == 1
+        && PRIMITIVE_TYPES
+            .iter()
+            .any(|primitive_type| segments[0].ident == primitive_type);
+    if !is_primitive_type {
+        segments.insert(
+            0,
+            PathSegment {
+                ident: format_ident!("wdk_sys"),
+                arguments: PathArguments::None,
+            },
+        );
+    }
+}
+
+/// Generate the canonical WDF default argument for a parameter omitted via
+/// `..`. Only the parameters that WDF documents a `WDF_NO_*` default for have
+/// one, which is a null pointer:
+///
+/// * `WDF_NO_OBJECT_ATTRIBUTES` for `PWDF_OBJECT_ATTRIBUTES` parameters
+/// * `WDF_NO_CONTEXT` for `WDFCONTEXT` parameters
+/// * `WDF_NO_SEND_OPTIONS` for `PWDF_REQUEST_SEND_OPTIONS` parameters
+/// * `WDF_NO_HANDLE` for the optional (`_Out_opt_`) handle outputs listed in
+///   [`OPTIONAL_HANDLE_OUTPUT_PARAMETERS`]
+///
+/// # Examples
+///
+/// Passing the [`BareFnArg`] representation of
+/// `DriverAttributes: wdk_sys::PWDF_OBJECT_ATTRIBUTES` would return the
+/// [`Expr`] representation of `core::ptr::null_mut()`
+fn generate_default_argument(
+    wdf_function_identifier: &Ident,
+    bare_fn_arg: &BareFnArg,
+    error_span: Span,
+) -> Result<Expr> {
+    const WDF_NO_PARAMETER_TYPES: [&str; 3] = [
+        "PWDF_OBJECT_ATTRIBUTES",
+        "WDFCONTEXT",
+        "PWDF_REQUEST_SEND_OPTIONS",
+    ];
+
+    let has_wdf_default = match &bare_fn_arg.ty {
+        Type::Path(TypePath { path, .. }) => path.segments.last().is_some_and(|segment| {
+            WDF_NO_PARAMETER_TYPES
+                .iter()
+                .any(|parameter_type| segment.ident == parameter_type)
+        }),
+        Type::Ptr(TypePtr {
+            mutability: Some(_),
+            ..
+        }) => bare_fn_arg.name.as_ref().is_some_and(|(identifier, _)| {
+            OPTIONAL_HANDLE_OUTPUT_PARAMETERS
+                .iter()
+                .any(|(function, parameter)| {
+                    wdf_function_identifier == function && identifier == parameter
+                })
+        }),
+        _ => false,
+    };
+    if has_wdf_default {
+        return Ok(parse_quote! { core::ptr::null_mut() });
+    }
+
+    Err(Error::new(
+        error_span,
+        format!(
+            "{} cannot be omitted with `..` since WDF does not define a `WDF_NO_*` default for it",
+            bare_fn_arg.name.as_ref().map_or_else(
+                || "parameter".to_string(),
+                |(identifier, _)| format!("parameter `{identifier}`")
+            ),
+        ),
+    ))
+}
+
+/// Generate the `#[must_use]` attribute if the return type is not `()`. WDF
+/// functions that return `NTSTATUS` report their failures through it, so their
+/// attribute explains how the returned value should be handled.
 fn generate_must_use_attribute(return_type: &ReturnType) -> Option<Attribute> {
-    if matches!(return_type, ReturnType::Type(..)) {
+    if returns_nt_status(return_type) {
+        Some(parse_quote! {
+            #[must_use = "WDF reports failures through the returned NTSTATUS, which should be checked (ex. with `nt_success`)"]
+        })
+    } else if matches!(return_type, ReturnType::Type(..)) {
         Some(parse_quote! { #[must_use] })
     } else {
         None
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use pretty_assertions::assert_eq as pretty_assert_eq;
-    use quote::ToTokens;
-
-    use super::*;
-
-    mod to_snake_case {
-        use super::*;
-
-        #[test]
-        fn camel_case() {
-            let input = "camelCaseString".to_string();
-            let expected = "camel_case_string";
-
-            pretty_assert_eq!(input.to_snake_case(), expected);
-        }
-
-        #[test]
-        fn short_camel_case() {
-            let input = "aB".to_string();
-            let expected = "a_b";
-
-            pretty_assert_eq!(input.to_snake_case(), expected);
-        }
-
-        #[test]
-        fn pascal_case() {
-            let input = "PascalCaseString".to_string();
-            let expected = "pascal_case_string";
-
-            pretty_assert_eq!(input.to_snake_case(), expected);
-        }
-
-        #[test]
-        fn pascal_case_with_leading_acronym() {
-            let input = "ASCIIEncodedString".to_string();
-            let expected = "ascii_encoded_string";
-
-            pretty_assert_eq!(input.to_snake_case(), expected);
-        }
-
-        #[test]
-        fn pascal_case_with_trailing_acronym() {
-            let input = "IsASCII".to_string();
-            let expected = "is_ascii";
-
-            pretty_assert_eq!(input.to_snake_case(), expected);
-        }
-
-        #[test]
-        fn screaming_snake_case() {
-            let input = "PFN_WDF_DRIVER_DEVICE_ADD".to_string();
-            let expected = "pfn_wdf_driver_device_add";
-
-            pretty_assert_eq!(input.to_snake_case(), expected);
-        }
+/// Returns whether `return_type` is `NTSTATUS`, as written in the function
+/// pointer types of `types.rs` (ex. `-> wdk_sys::NTSTATUS`)
+fn returns_nt_status(return_type: &ReturnType) -> bool {
+    match return_type {
+        ReturnType::Type(_, return_type) => matches!(
+            return_type.as_ref(),
+            Type::Path(TypePath { qself: None, path })
+                if path.segments.last().is_some_and(|segment| segment.ident == "NTSTATUS")
+        ),
+        ReturnType::Default => false,
+    }
+}
 
-        #[test]
-        fn screaming_snake_case_with_leading_acronym() {
-            let input = "ASCII_STRING".to_string();
-            let expected = "ascii_string";
+/// Route `inline_wdf_fn_invocation` through the items generated by
+/// [`generate_strict_nt_status_items`] if `strict` is set and the WDF function
+/// returns `NTSTATUS`. Returns the items to emit along with the (possibly
+/// wrapped) invocation.
+fn apply_strict_nt_status_check(
+    strict: bool,
+    return_type: &ReturnType,
+    inline_wdf_fn_invocation: ExprCall,
+) -> (Vec<Item>, ExprCall) {
+    if strict && returns_nt_status(return_type) {
+        (
+            generate_strict_nt_status_items(),
+            parse_quote! {
+                nt_status_must_be_used(#inline_wdf_fn_invocation)
+            },
+        )
+    } else {
+        (Vec::new(), inline_wdf_fn_invocation)
+    }
+}
 
-            pretty_assert_eq!(input.to_snake_case(), expected);
+/// Generate the items used by the `strict` feature to turn a discarded
+/// `NTSTATUS` into a compilation error. `nt_status_must_be_used` returns its
+/// argument as a type that must be inferred from its usage, which is
+/// impossible if the result is discarded, while `let _: NTSTATUS = ...` remains
+/// available to explicitly ignore the status.
+fn generate_strict_nt_status_items() -> Vec<Item> {
+    let strict_nt_status_items: File = parse_quote! {
+        trait NtStatus {
+            fn from_nt_status(nt_status: wdk_sys::NTSTATUS) -> Self;
         }
 
-        #[test]
-        fn screaming_snake_case_with_leading_underscore() {
-            let input = "_WDF_DRIVER_INIT_FLAGS".to_string();
-            let expected = "_wdf_driver_init_flags";
-
-            pretty_assert_eq!(input.to_snake_case(), expected);
+        impl NtStatus for wdk_sys::NTSTATUS {
+            #[inline(always)]
+            fn from_nt_status(nt_status: wdk_sys::NTSTATUS) -> Self {
+                nt_status
+            }
         }
 
-        #[test]
-        fn snake_case() {
-            let input = "snake_case_string".to_string();
-            let expected = "snake_case_string";
-
-            pretty_assert_eq!(input.to_snake_case(), expected);
+        #[inline(always)]
+        fn nt_status_must_be_used<T: NtStatus>(nt_status: wdk_sys::NTSTATUS) -> T {
+            T::from_nt_status(nt_status)
         }
+    };
+    strict_nt_status_items.items
+}
 
-        #[test]
-        fn snake_case_with_leading_underscore() {
-            let input = "_snake_case_with_leading_underscore".to_string();
-            let expected = "_snake_case_with_leading_underscore";
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use quote::ToTokens;
 
-            pretty_assert_eq!(input.to_snake_case(), expected);
-        }
-    }
+    use super::*;
 
     mod inputs {
         use super::*;
@@ -1020,6 +1513,8 @@ mod parse {
             fn valid_input() {
                 let input_tokens = quote! { WdfDriverCreate, driver, registry_path, WDF_NO_OBJECT_ATTRIBUTES, &mut driver_config, driver_handle_output };
                 let expected = Inputs {
+                    wdk_sys_crate_path: None,
+                    driver_globals: None,
                     wdf_function_identifier: format_ident!("WdfDriverCreate"),
                     wdf_function_arguments: parse_quote! {
                         driver,
@@ -1028,6 +1523,7 @@ fn valid_input() {
                         &mut driver_config,
                         driver_handle_output
                     },
+                    default_trailing_arguments: false,
                 };
 
                 pretty_assert_eq!(parse2::<Inputs>(input_tokens).unwrap(), expected);
@@ -1037,6 +1533,8 @@ fn valid_input() {
             fn valid_input_with_trailing_comma() {
                 let input_tokens = quote! { WdfDriverCreate, driver, registry_path, WDF_NO_OBJECT_ATTRIBUTES, &mut driver_config, driver_handle_output, };
                 let expected = Inputs {
+                    wdk_sys_crate_path: None,
+                    driver_globals: None,
                     wdf_function_identifier: format_ident!("WdfDriverCreate"),
                     wdf_function_arguments: parse_quote! {
                         driver,
@@ -1045,6 +1543,7 @@ fn valid_input_with_trailing_comma() {
                         &mut driver_config,
                         driver_handle_output,
                     },
+                    default_trailing_arguments: false,
                 };
 
                 pretty_assert_eq!(parse2::<Inputs>(input_tokens).unwrap(), expected);
@@ -1054,8 +1553,11 @@ fn valid_input_with_trailing_comma() {
             fn wdf_function_with_no_arguments() {
                 let input_tokens = quote! { WdfVerifierDbgBreakPoint };
                 let expected = Inputs {
+                    wdk_sys_crate_path: None,
+                    driver_globals: None,
                     wdf_function_identifier: format_ident!("WdfVerifierDbgBreakPoint"),
                     wdf_function_arguments: Punctuated::new(),
+                    default_trailing_arguments: false,
                 };
 
                 pretty_assert_eq!(parse2::<Inputs>(input_tokens).unwrap(), expected);
@@ -1065,31 +1567,137 @@ fn wdf_function_with_no_arguments() {
             fn wdf_function_with_no_arguments_and_trailing_comma() {
                 let input_tokens = quote! { WdfVerifierDbgBreakPoint, };
                 let expected = Inputs {
+                    wdk_sys_crate_path: None,
+                    driver_globals: None,
                     wdf_function_identifier: format_ident!("WdfVerifierDbgBreakPoint"),
                     wdf_function_arguments: Punctuated::new(),
+                    default_trailing_arguments: false,
                 };
 
                 pretty_assert_eq!(parse2::<Inputs>(input_tokens).unwrap(), expected);
             }
 
             #[test]
-            fn invalid_ident() {
-                let input_tokens = quote! { 123InvalidIdent, driver, registry_path, WDF_NO_OBJECT_ATTRIBUTES, &mut driver_config, driver_handle_output, };
-                let expected = Error::new(Span::call_site(), "expected identifier");
+            fn valid_input_with_default_trailing_arguments() {
+                let input_tokens = quote! { WdfDriverCreate, driver, registry_path, .. };
+                let expected = Inputs {
+                    wdk_sys_crate_path: None,
+                    driver_globals: None,
+                    wdf_function_identifier: format_ident!("WdfDriverCreate"),
+                    wdf_function_arguments: parse_quote! {
+                        driver,
+                        registry_path,
+                    },
+                    default_trailing_arguments: true,
+                };
+
+                pretty_assert_eq!(parse2::<Inputs>(input_tokens).unwrap(), expected);
+            }
+
+            #[test]
+            fn default_trailing_arguments_not_last() {
+                let input_tokens = quote! { WdfDriverCreate, driver, .., registry_path };
+                let expected = Error::new(Span::call_site(), "`..` must be the last argument");
 
                 pretty_assert_eq!(
                     parse2::<Inputs>(input_tokens).unwrap_err().to_string(),
                     expected.to_string()
                 );
             }
-        }
-
-        mod generate_derived_ast_fragments {
-            use super::*;
+
+            #[test]
+            fn valid_input_with_wdk_sys_crate_path() {
+                let input_tokens = quote! { crate = ::my_driver::wdk_sys, WdfDriverCreate, driver, registry_path, .. };
+                let expected = Inputs {
+                    wdk_sys_crate_path: Some(parse_quote! { ::my_driver::wdk_sys }),
+                    driver_globals: None,
+                    wdf_function_identifier: format_ident!("WdfDriverCreate"),
+                    wdf_function_arguments: parse_quote! {
+                        driver,
+                        registry_path,
+                    },
+                    default_trailing_arguments: true,
+                };
+
+                pretty_assert_eq!(parse2::<Inputs>(input_tokens).unwrap(), expected);
+            }
+
+            #[test]
+            fn wdk_sys_crate_path_without_wdf_function() {
+                let input_tokens = quote! { crate = renamed_wdk_sys };
+                let expected = Error::new(Span::call_site(), "expected `,`");
+
+                pretty_assert_eq!(
+                    parse2::<Inputs>(input_tokens).unwrap_err().to_string(),
+                    expected.to_string()
+                );
+            }
+
+            #[test]
+            fn valid_input_with_driver_globals() {
+                let input_tokens = quote! { globals = spb_driver_globals, WdfDriverCreate, driver, registry_path, .. };
+                let expected = Inputs {
+                    wdk_sys_crate_path: None,
+                    driver_globals: Some(parse_quote! { spb_driver_globals }),
+                    wdf_function_identifier: format_ident!("WdfDriverCreate"),
+                    wdf_function_arguments: parse_quote! {
+                        driver,
+                        registry_path,
+                    },
+                    default_trailing_arguments: true,
+                };
+
+                pretty_assert_eq!(parse2::<Inputs>(input_tokens).unwrap(), expected);
+            }
+
+            #[test]
+            fn valid_input_with_wdk_sys_crate_path_and_driver_globals() {
+                let input_tokens = quote! { crate = renamed_wdk_sys, globals = *globals_ptr, WdfVerifierDbgBreakPoint };
+                let expected = Inputs {
+                    wdk_sys_crate_path: Some(parse_quote! { renamed_wdk_sys }),
+                    driver_globals: Some(parse_quote! { *globals_ptr }),
+                    wdf_function_identifier: format_ident!("WdfVerifierDbgBreakPoint"),
+                    wdf_function_arguments: Punctuated::new(),
+                    default_trailing_arguments: false,
+                };
+
+                pretty_assert_eq!(parse2::<Inputs>(input_tokens).unwrap(), expected);
+            }
+
+            #[test]
+            fn unknown_named_argument() {
+                let input_tokens = quote! { global = spb_driver_globals, WdfVerifierDbgBreakPoint };
+                let expected = Error::new(
+                    Span::call_site(),
+                    "expected `globals = <expr>` or a WDF function name",
+                );
+
+                pretty_assert_eq!(
+                    parse2::<Inputs>(input_tokens).unwrap_err().to_string(),
+                    expected.to_string()
+                );
+            }
+
+            #[test]
+            fn invalid_ident() {
+                let input_tokens = quote! { 123InvalidIdent, driver, registry_path, WDF_NO_OBJECT_ATTRIBUTES, &mut driver_config, driver_handle_output, };
+                let expected = Error::new(Span::call_site(), "expected identifier");
+
+                pretty_assert_eq!(
+                    parse2::<Inputs>(input_tokens).unwrap_err().to_string(),
+                    expected.to_string()
+                );
+            }
+        }
+
+        mod generate_derived_ast_fragments {
+            use super::*;
 
             #[test]
             fn valid_input() {
                 let inputs = Inputs {
+                    wdk_sys_crate_path: None,
+                    driver_globals: None,
                     wdf_function_identifier: format_ident!("WdfDriverCreate"),
                     wdf_function_arguments: parse_quote! {
                         driver,
@@ -1098,8 +1706,11 @@ fn valid_input() {
                         &mut driver_config,
                         driver_handle_output,
                     },
+                    default_trailing_arguments: false,
                 };
                 let expected = DerivedASTFragments {
+                    wdk_sys_crate_path: None,
+                    driver_globals: None,
                     function_pointer_type: format_ident!("PFN_WDFDRIVERCREATE"),
                     function_table_index: format_ident!("WdfDriverCreateTableIndex"),
                     parameters: parse_quote! {
@@ -1124,7 +1735,7 @@ fn valid_input() {
                         &mut driver_config,
                         driver_handle_output,
                     },
-                    inline_wdf_fn_name: format_ident!("wdf_driver_create_impl"),
+                    inline_wdf_fn_name: format_ident!("WdfDriverCreate"),
                 };
 
                 pretty_assert_eq!(inputs.generate_derived_ast_fragments().unwrap(), expected);
@@ -1133,21 +1744,212 @@ fn valid_input() {
             #[test]
             fn valid_input_with_no_arguments() {
                 let inputs = Inputs {
+                    wdk_sys_crate_path: None,
+                    driver_globals: None,
+                    wdf_function_identifier: format_ident!("WdfVerifierDbgBreakPoint"),
+                    wdf_function_arguments: Punctuated::new(),
+                    default_trailing_arguments: false,
+                };
+                let expected = DerivedASTFragments {
+                    wdk_sys_crate_path: None,
+                    driver_globals: None,
+                    function_pointer_type: format_ident!("PFN_WDFVERIFIERDBGBREAKPOINT"),
+                    function_table_index: format_ident!("WdfVerifierDbgBreakPointTableIndex"),
+                    parameters: Punctuated::new(),
+                    parameter_identifiers: Punctuated::new(),
+                    return_type: ReturnType::Default,
+                    arguments: Punctuated::new(),
+                    inline_wdf_fn_name: format_ident!("WdfVerifierDbgBreakPoint"),
+                };
+
+                pretty_assert_eq!(inputs.generate_derived_ast_fragments().unwrap(), expected);
+            }
+
+            #[test]
+            fn valid_input_with_wdk_sys_crate_path() {
+                let inputs = Inputs {
+                    wdk_sys_crate_path: Some(parse_quote! { renamed_wdk_sys }),
+                    driver_globals: None,
                     wdf_function_identifier: format_ident!("WdfVerifierDbgBreakPoint"),
                     wdf_function_arguments: Punctuated::new(),
+                    default_trailing_arguments: false,
                 };
                 let expected = DerivedASTFragments {
+                    wdk_sys_crate_path: Some(parse_quote! { renamed_wdk_sys }),
+                    driver_globals: None,
                     function_pointer_type: format_ident!("PFN_WDFVERIFIERDBGBREAKPOINT"),
                     function_table_index: format_ident!("WdfVerifierDbgBreakPointTableIndex"),
                     parameters: Punctuated::new(),
                     parameter_identifiers: Punctuated::new(),
                     return_type: ReturnType::Default,
                     arguments: Punctuated::new(),
-                    inline_wdf_fn_name: format_ident!("wdf_verifier_dbg_break_point_impl"),
+                    inline_wdf_fn_name: format_ident!("WdfVerifierDbgBreakPoint"),
+                };
+
+                pretty_assert_eq!(inputs.generate_derived_ast_fragments().unwrap(), expected);
+            }
+
+            #[test]
+            fn valid_input_with_default_trailing_arguments() {
+                let inputs = Inputs {
+                    wdk_sys_crate_path: None,
+                    driver_globals: None,
+                    wdf_function_identifier: format_ident!("WdfDriverCreate"),
+                    wdf_function_arguments: parse_quote! {
+                        driver,
+                        registry_path,
+                        WDF_NO_OBJECT_ATTRIBUTES,
+                        &mut driver_config,
+                    },
+                    default_trailing_arguments: true,
+                };
+                let expected = DerivedASTFragments {
+                    wdk_sys_crate_path: None,
+                    driver_globals: None,
+                    function_pointer_type: format_ident!("PFN_WDFDRIVERCREATE"),
+                    function_table_index: format_ident!("WdfDriverCreateTableIndex"),
+                    parameters: parse_quote! {
+                        DriverObject: wdk_sys::PDRIVER_OBJECT,
+                        RegistryPath: wdk_sys::PCUNICODE_STRING,
+                        DriverAttributes: wdk_sys::PWDF_OBJECT_ATTRIBUTES,
+                        DriverConfig: wdk_sys::PWDF_DRIVER_CONFIG,
+                        Driver: *mut wdk_sys::WDFDRIVER
+                    },
+                    parameter_identifiers: parse_quote! {
+                        DriverObject,
+                        RegistryPath,
+                        DriverAttributes,
+                        DriverConfig,
+                        Driver
+                    },
+                    return_type: parse_quote! { -> wdk_sys::NTSTATUS },
+                    arguments: parse_quote! {
+                        driver,
+                        registry_path,
+                        WDF_NO_OBJECT_ATTRIBUTES,
+                        &mut driver_config,
+                        core::ptr::null_mut()
+                    },
+                    inline_wdf_fn_name: format_ident!("WdfDriverCreate"),
                 };
 
                 pretty_assert_eq!(inputs.generate_derived_ast_fragments().unwrap(), expected);
             }
+
+            #[test]
+            fn default_trailing_arguments_for_non_pointer_parameter() {
+                let inputs = Inputs {
+                    wdk_sys_crate_path: None,
+                    driver_globals: None,
+                    wdf_function_identifier: format_ident!("WdfRequestComplete"),
+                    wdf_function_arguments: parse_quote! {
+                        request,
+                    },
+                    default_trailing_arguments: true,
+                };
+                let expected = Error::new(
+                    Span::call_site(),
+                    "parameter `Status` cannot be omitted with `..` since WDF does not define a \
+                     `WDF_NO_*` default for it",
+                );
+
+                pretty_assert_eq!(
+                    inputs
+                        .generate_derived_ast_fragments()
+                        .unwrap_err()
+                        .to_string(),
+                    expected.to_string()
+                );
+            }
+
+            #[test]
+            fn default_trailing_arguments_for_pointer_parameter_without_wdf_default() {
+                let inputs = Inputs {
+                    wdk_sys_crate_path: None,
+                    driver_globals: None,
+                    wdf_function_identifier: format_ident!("WdfDriverCreate"),
+                    wdf_function_arguments: parse_quote! {
+                        driver,
+                        registry_path,
+                        WDF_NO_OBJECT_ATTRIBUTES,
+                    },
+                    default_trailing_arguments: true,
+                };
+                let expected = Error::new(
+                    Span::call_site(),
+                    "parameter `DriverConfig` cannot be omitted with `..` since WDF does not \
+                     define a `WDF_NO_*` default for it",
+                );
+
+                pretty_assert_eq!(
+                    inputs
+                        .generate_derived_ast_fragments()
+                        .unwrap_err()
+                        .to_string(),
+                    expected.to_string()
+                );
+            }
+
+            #[test]
+            fn default_trailing_arguments_for_required_handle_output() {
+                let inputs = Inputs {
+                    wdk_sys_crate_path: None,
+                    driver_globals: None,
+                    wdf_function_identifier: format_ident!("WdfDeviceCreate"),
+                    wdf_function_arguments: parse_quote! {
+                        &mut device_init,
+                        WDF_NO_OBJECT_ATTRIBUTES,
+                    },
+                    default_trailing_arguments: true,
+                };
+                let expected = Error::new(
+                    Span::call_site(),
+                    "parameter `Device` cannot be omitted with `..` since WDF does not define a \
+                     `WDF_NO_*` default for it",
+                );
+
+                pretty_assert_eq!(
+                    inputs
+                        .generate_derived_ast_fragments()
+                        .unwrap_err()
+                        .to_string(),
+                    expected.to_string()
+                );
+            }
+        }
+    }
+
+    mod generate_default_argument {
+        use super::*;
+
+        #[test]
+        fn optional_handle_output() {
+            let bare_fn_arg: BareFnArg = parse_quote! { Driver: *mut wdk_sys::WDFDRIVER };
+            let expected: Expr = parse_quote! { core::ptr::null_mut() };
+
+            pretty_assert_eq!(
+                generate_default_argument(
+                    &format_ident!("WdfDriverCreate"),
+                    &bare_fn_arg,
+                    Span::call_site()
+                )
+                .unwrap(),
+                expected
+            );
+        }
+
+        #[test]
+        fn required_handle_output() {
+            let bare_fn_arg: BareFnArg = parse_quote! { Timer: *mut wdk_sys::WDFTIMER };
+
+            assert!(
+                generate_default_argument(
+                    &format_ident!("WdfTimerCreate"),
+                    &bare_fn_arg,
+                    Span::call_site()
+                )
+                .is_err()
+            );
         }
     }
 
@@ -1383,6 +2185,27 @@ fn valid_input() {
             );
         }
 
+        #[test]
+        fn valid_input_with_primitive_argument() {
+            // WdfDmaTransactionSetMaximumLength has the following generated signature:
+            let bare_fn_type = parse_quote! {
+                unsafe extern "C" fn(
+                    DriverGlobals: PWDF_DRIVER_GLOBALS,
+                    DmaTransaction: WDFDMATRANSACTION,
+                    MaximumLength: usize,
+                )
+            };
+            let expected = parse_quote! {
+                DmaTransaction: wdk_sys::WDFDMATRANSACTION,
+                MaximumLength: usize
+            };
+
+            pretty_assert_eq!(
+                compute_fn_parameters(&bare_fn_type, Span::call_site()).unwrap(),
+                expected
+            );
+        }
+
         #[test]
         fn valid_input_with_no_arguments() {
             // WdfVerifierDbgBreakPoint has the following generated signature:
@@ -1425,6 +2248,26 @@ fn ntstatus() {
             );
         }
 
+        #[test]
+        fn primitive() {
+            // WdfDmaEnablerGetMaximumLength has the following generated signature:
+            let bare_fn_type = parse_quote! {
+                unsafe extern "C" fn(
+                    DriverGlobals: PWDF_DRIVER_GLOBALS,
+                    DmaEnabler: WDFDMAENABLER,
+                ) -> usize
+            };
+            let expected = ReturnType::Type(
+                Token![->](Span::call_site()),
+                Box::new(Type::Path(parse_quote! { usize })),
+            );
+
+            pretty_assert_eq!(
+                compute_return_type(&bare_fn_type, Span::call_site()).unwrap(),
+                expected
+            );
+        }
+
         #[test]
         fn unit() {
             // WdfSpinLockAcquire has the following generated signature:
@@ -1457,6 +2300,23 @@ fn unit_return_type() {
         #[test]
         fn ntstatus_return_type() {
             let return_type: ReturnType = parse_quote! { -> NTSTATUS };
+            let expected_tokens = quote! {
+                #[must_use = "WDF reports failures through the returned NTSTATUS, which should be checked (ex. with `nt_success`)"]
+            };
+            let generated_must_use_attribute_tokens = generate_must_use_attribute(&return_type);
+
+            pretty_assert_eq!(
+                generated_must_use_attribute_tokens
+                    .unwrap()
+                    .into_token_stream()
+                    .to_string(),
+                expected_tokens.to_string(),
+            );
+        }
+
+        #[test]
+        fn non_ntstatus_return_type() {
+            let return_type: ReturnType = parse_quote! { -> wdk_sys::BOOLEAN };
             let expected_tokens = quote! { #[must_use] };
             let generated_must_use_attribute_tokens = generate_must_use_attribute(&return_type);
 
@@ -1469,4 +2329,131 @@ fn ntstatus_return_type() {
             );
         }
     }
+
+    mod returns_nt_status {
+        use super::*;
+
+        #[test]
+        fn unit_return_type() {
+            assert!(!returns_nt_status(&ReturnType::Default));
+        }
+
+        #[test]
+        fn ntstatus_return_type() {
+            assert!(returns_nt_status(&parse_quote! { -> NTSTATUS }));
+            assert!(returns_nt_status(&parse_quote! { -> wdk_sys::NTSTATUS }));
+        }
+
+        #[test]
+        fn non_ntstatus_return_type() {
+            assert!(!returns_nt_status(&parse_quote! { -> wdk_sys::BOOLEAN }));
+            assert!(!returns_nt_status(
+                &parse_quote! { -> *mut wdk_sys::NTSTATUS }
+            ));
+        }
+    }
+
+    mod apply_strict_nt_status_check {
+        use super::*;
+
+        #[test]
+        fn ntstatus_return_type() {
+            let return_type: ReturnType = parse_quote! { -> wdk_sys::NTSTATUS };
+            let inline_wdf_fn_invocation: ExprCall = parse_quote! {
+                WdfDeviceCreate(__arg0, __arg1, __arg2)
+            };
+            let expected_invocation_tokens = quote! {
+                nt_status_must_be_used(WdfDeviceCreate(__arg0, __arg1, __arg2))
+            };
+
+            let (strict_nt_status_items, generated_invocation) =
+                apply_strict_nt_status_check(true, &return_type, inline_wdf_fn_invocation);
+
+            pretty_assert_eq!(strict_nt_status_items.len(), 3);
+            pretty_assert_eq!(
+                strict_nt_status_items
+                    .iter()
+                    .map(|item| match item {
+                        Item::Trait(item_trait) => item_trait.ident.to_string(),
+                        Item::Impl(_) => "impl".to_string(),
+                        Item::Fn(item_fn) => item_fn.sig.ident.to_string(),
+                        _ => panic!("unexpected strict NTSTATUS item: {item:#?}"),
+                    })
+                    .collect::<Vec<_>>(),
+                ["NtStatus", "impl", "nt_status_must_be_used"],
+            );
+            pretty_assert_eq!(
+                generated_invocation.into_token_stream().to_string(),
+                expected_invocation_tokens.to_string(),
+            );
+        }
+
+        #[test]
+        fn ntstatus_return_type_without_strict() {
+            let return_type: ReturnType = parse_quote! { -> wdk_sys::NTSTATUS };
+            let inline_wdf_fn_invocation: ExprCall = parse_quote! {
+                WdfDeviceCreate(__arg0, __arg1, __arg2)
+            };
+            let expected_invocation_tokens = inline_wdf_fn_invocation.to_token_stream();
+
+            let (strict_nt_status_items, generated_invocation) =
+                apply_strict_nt_status_check(false, &return_type, inline_wdf_fn_invocation);
+
+            assert!(strict_nt_status_items.is_empty());
+            pretty_assert_eq!(
+                generated_invocation.into_token_stream().to_string(),
+                expected_invocation_tokens.to_string(),
+            );
+        }
+
+        #[test]
+        fn non_ntstatus_return_type() {
+            let return_type: ReturnType = parse_quote! { -> wdk_sys::BOOLEAN };
+            let inline_wdf_fn_invocation: ExprCall = parse_quote! {
+                WdfRequestSend(__arg0, __arg1, __arg2)
+            };
+
+            let (strict_nt_status_items, _) =
+                apply_strict_nt_status_check(true, &return_type, inline_wdf_fn_invocation);
+
+            assert!(strict_nt_status_items.is_empty());
+        }
+
+        #[test]
+        fn assembled_output_is_valid_expression() {
+            let inputs: Inputs = parse2(quote! {
+                WdfDeviceCreate, &mut device_init, WDF_NO_OBJECT_ATTRIBUTES, &mut device
+            })
+            .unwrap();
+            let mut intermediate_output_ast_fragments = inputs
+                .generate_derived_ast_fragments()
+                .unwrap()
+                .generate_intermediate_output_ast_fragments();
+            let (strict_nt_status_items, inline_wdf_fn_invocation) = apply_strict_nt_status_check(
+                true,
+                &parse_quote! { -> wdk_sys::NTSTATUS },
+                intermediate_output_ast_fragments.inline_wdf_fn_invocation,
+            );
+            intermediate_output_ast_fragments.strict_nt_status_items = strict_nt_status_items;
+            intermediate_output_ast_fragments.inline_wdf_fn_invocation = inline_wdf_fn_invocation;
+
+            let Expr::Block(expanded) =
+                parse2::<Expr>(intermediate_output_ast_fragments.assemble_final_output()).unwrap()
+            else {
+                panic!("expected the expanded macro to be a block expression");
+            };
+            let Some(Stmt::Expr(Expr::Call(invocation), None)) = expanded.block.stmts.last() else {
+                panic!("expected the expanded macro to end with the WDF function invocation");
+            };
+
+            pretty_assert_eq!(
+                invocation.func.to_token_stream().to_string(),
+                quote! { nt_status_must_be_used }.to_string(),
+            );
+            assert!(expanded.block.stmts.iter().any(|stmt| matches!(
+                stmt,
+                Stmt::Item(Item::Fn(item_fn)) if item_fn.sig.ident == "nt_status_must_be_used"
+            )));
+        }
+    }
 }
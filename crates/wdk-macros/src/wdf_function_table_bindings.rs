@@ -0,0 +1,397 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Implementation of the `wdf_function_table_bindings` macro
+
+use std::collections::BTreeSet;
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    braced,
+    parse::{Parse, ParseStream},
+    parse2,
+    parse_quote,
+    punctuated::Punctuated,
+    Error,
+    File,
+    Ident,
+    Item,
+    ItemFn,
+    Path,
+    Result,
+    Token,
+    Visibility,
+};
+
+use crate::{
+    compute_parameter_identifiers,
+    find_type_alias_definition,
+    generate_must_use_attribute,
+    generate_parameters_and_return_type_from_ast,
+    generate_wdf_function_body_statements,
+    get_type_rs_ast,
+};
+
+/// Name of the module in `types.rs` containing the WDF function table indices
+const WDF_FUNCTION_TABLE_INDEX_MODULE_NAME: &str = "_WDFFUNCENUM";
+
+/// Suffix of the constants in [`WDF_FUNCTION_TABLE_INDEX_MODULE_NAME`] that
+/// name the index of a WDF function in the WDF function table
+const WDF_FUNCTION_TABLE_INDEX_SUFFIX: &str = "TableIndex";
+
+/// Struct storing the input tokens directly parsed from calls to the
+/// `wdf_function_table_bindings` macro, ex. `pub mod wdf { WdfDriverCreate }`
+#[derive(Debug, PartialEq)]
+struct Inputs {
+    /// Path to the `wdk-sys` crate, if overridden via a leading `crate = path`
+    /// argument. The generated code refers to `wdk_sys` otherwise.
+    wdk_sys_crate_path: Option<Path>,
+    /// Visibility of the generated module
+    module_visibility: Visibility,
+    /// Name of the generated module
+    module_identifier: Ident,
+    /// The names of the WDF functions to generate wrappers for. `None` if no
+    /// list was supplied, in which case wrappers are generated for every
+    /// function in the WDF function table.
+    wdf_function_identifiers: Option<Punctuated<Ident, Token![,]>>,
+}
+
+impl Parse for Inputs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let wdk_sys_crate_path = if input.peek(Token![crate]) && input.peek2(Token![=]) {
+            input.parse::<Token![crate]>()?;
+            input.parse::<Token![=]>()?;
+            let wdk_sys_crate_path = Path::parse_mod_style(input)?;
+            input.parse::<Token![,]>()?;
+            Some(wdk_sys_crate_path)
+        } else {
+            None
+        };
+
+        let module_visibility = input.parse::<Visibility>()?;
+        input.parse::<Token![mod]>()?;
+        let module_identifier = input.parse::<Ident>()?;
+
+        let wdf_function_identifiers = if input.is_empty() {
+            None
+        } else {
+            let content;
+            braced!(content in input);
+            Some(content.parse_terminated(Ident::parse, Token![,])?)
+        };
+
+        if !input.is_empty() {
+            return Err(input.error("unexpected tokens after the list of WDF functions"));
+        }
+
+        Ok(Self {
+            wdk_sys_crate_path,
+            module_visibility,
+            module_identifier,
+            wdf_function_identifiers,
+        })
+    }
+}
+
+impl Inputs {
+    /// Resolve the list of WDF functions to generate wrappers for. Explicitly
+    /// listed functions must be unique, and must exist in `types_rs_ast`.
+    /// Otherwise, every function in the WDF function table with a
+    /// corresponding function pointer type is used.
+    fn resolve_wdf_function_identifiers(&self, types_rs_ast: &File) -> Result<Vec<Ident>> {
+        let Some(wdf_function_identifiers) = &self.wdf_function_identifiers else {
+            return find_all_wdf_function_identifiers(types_rs_ast, self.module_identifier.span());
+        };
+
+        let mut seen_wdf_function_names = BTreeSet::new();
+        for wdf_function_identifier in wdf_function_identifiers {
+            if !seen_wdf_function_names.insert(wdf_function_identifier.to_string()) {
+                return Err(Error::new(
+                    wdf_function_identifier.span(),
+                    format!("{wdf_function_identifier} is listed more than once"),
+                ));
+            }
+        }
+        Ok(wdf_function_identifiers.iter().cloned().collect())
+    }
+}
+
+pub fn wdf_function_table_bindings_impl(input_tokens: TokenStream2) -> TokenStream2 {
+    match generate_wdf_function_table_bindings(input_tokens) {
+        Ok(output_tokens) => output_tokens,
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+fn generate_wdf_function_table_bindings(input_tokens: TokenStream2) -> Result<TokenStream2> {
+    let inputs = parse2::<Inputs>(input_tokens)?;
+    let types_rs_ast = get_type_rs_ast()?;
+
+    let wrapper_fns = inputs
+        .resolve_wdf_function_identifiers(&types_rs_ast)?
+        .iter()
+        .map(|wdf_function_identifier| generate_wrapper_fn(&types_rs_ast, wdf_function_identifier))
+        .collect::<Result<Vec<_>>>()?;
+
+    let Inputs {
+        wdk_sys_crate_path,
+        module_visibility,
+        module_identifier,
+        ..
+    } = inputs;
+    // The generated code refers to `wdk-sys` as `wdk_sys`, so an overridden crate
+    // path is brought into scope under that name
+    let wdk_sys_crate_alias = wdk_sys_crate_path.map(|wdk_sys_crate_path| {
+        quote! {
+            #[allow(unused_imports)]
+            use #wdk_sys_crate_path as wdk_sys;
+        }
+    });
+
+    Ok(quote! {
+        /// Typed wrappers for WDF functions, generated by
+        /// `wdf_function_table_bindings!`
+        #[allow(non_snake_case, clippy::too_many_arguments)]
+        #module_visibility mod #module_identifier {
+            #wdk_sys_crate_alias
+
+            #(#wrapper_fns)*
+        }
+    })
+}
+
+/// Generate the wrapper function for a single WDF function. The wrapper has
+/// the same name and parameters as the WDF function in C, minus the
+/// `DriverGlobals` parameter.
+///
+/// # Examples
+///
+/// Passing the `WdfDriverCreate` [`Ident`] as `wdf_function_identifier` would
+/// return the [`ItemFn`] representation of
+///
+/// ```rust, compile_fail
+/// /// Calls `WdfDriverCreate` via the WDF function table
+/// ///
+/// /// # Safety
+/// ///
+/// /// Function arguments must abide by any rules outlined in the WDF
+/// /// documentation for `WdfDriverCreate`, and the WDF function table must be
+/// /// initialized.
+/// #[must_use = "WDF reports failures through the returned NTSTATUS, which should be checked (ex. with `nt_success`)"]
+/// #[inline]
+/// pub unsafe fn WdfDriverCreate(
+///     DriverObject: wdk_sys::PDRIVER_OBJECT,
+///     RegistryPath: wdk_sys::PCUNICODE_STRING,
+///     DriverAttributes: wdk_sys::PWDF_OBJECT_ATTRIBUTES,
+///     DriverConfig: wdk_sys::PWDF_DRIVER_CONFIG,
+///     Driver: *mut wdk_sys::WDFDRIVER,
+/// ) -> wdk_sys::NTSTATUS {
+///     // ...
+/// }
+/// ```
+fn generate_wrapper_fn(types_rs_ast: &File, wdf_function_identifier: &Ident) -> Result<ItemFn> {
+    let function_pointer_type = format_ident!(
+        "PFN_{uppercase_c_function_name}",
+        uppercase_c_function_name = wdf_function_identifier.to_string().to_uppercase(),
+        span = wdf_function_identifier.span()
+    );
+    let function_table_index = format_ident!(
+        "{wdf_function_identifier}{table_index_suffix}",
+        wdf_function_identifier = wdf_function_identifier,
+        table_index_suffix = WDF_FUNCTION_TABLE_INDEX_SUFFIX,
+        span = wdf_function_identifier.span()
+    );
+    let (parameters, return_type) =
+        generate_parameters_and_return_type_from_ast(types_rs_ast, &function_pointer_type)?;
+    let parameter_identifiers =
+        compute_parameter_identifiers(&parameters, function_pointer_type.span())?;
+    let must_use_attribute = generate_must_use_attribute(&return_type);
+    let body_statements = generate_wdf_function_body_statements(
+        &function_pointer_type,
+        &function_table_index,
+        &parse_quote! { wdk_sys::WdfDriverGlobals },
+        &parameter_identifiers,
+    );
+
+    let summary_doc = format!(" Calls `{wdf_function_identifier}` via the WDF function table");
+    let safety_doc = format!(
+        " documentation for `{wdf_function_identifier}`, and the WDF function table must be \
+         initialized."
+    );
+
+    Ok(parse_quote! {
+        #[doc = #summary_doc]
+        ///
+        /// # Safety
+        ///
+        /// Function arguments must abide by any rules outlined in the WDF
+        #[doc = #safety_doc]
+        #must_use_attribute
+        #[inline]
+        pub unsafe fn #wdf_function_identifier(#parameters) #return_type {
+            #(#body_statements)*
+        }
+    })
+}
+
+/// Find the names of every WDF function in the WDF function table that has a
+/// corresponding `PFN_*` function pointer type in `types_rs_ast`. The names
+/// are derived from the `*TableIndex` constants in `_WDFFUNCENUM`.
+fn find_all_wdf_function_identifiers(types_rs_ast: &File, error_span: Span) -> Result<Vec<Ident>> {
+    let wdf_function_table_index_items = types_rs_ast
+        .items
+        .iter()
+        .find_map(|item| {
+            if let Item::Mod(item_mod) = item {
+                if item_mod.ident == WDF_FUNCTION_TABLE_INDEX_MODULE_NAME {
+                    return item_mod.content.as_ref().map(|(_, items)| items);
+                }
+            }
+            None
+        })
+        .ok_or_else(|| {
+            Error::new(
+                error_span,
+                format!(
+                    "Failed to find {WDF_FUNCTION_TABLE_INDEX_MODULE_NAME} module in wdk-sys types"
+                ),
+            )
+        })?;
+
+    Ok(wdf_function_table_index_items
+        .iter()
+        .filter_map(|item| {
+            let Item::Const(item_const) = item else {
+                return None;
+            };
+            let wdf_function_name = item_const
+                .ident
+                .to_string()
+                .strip_suffix(WDF_FUNCTION_TABLE_INDEX_SUFFIX)?
+                .to_string();
+            let wdf_function_identifier = Ident::new(&wdf_function_name, error_span);
+            // Not every entry in the function table has a function pointer type (ex.
+            // `WdfFunctionTableNumEntries`), so those entries are skipped
+            find_type_alias_definition(
+                types_rs_ast,
+                &format_ident!("PFN_{}", wdf_function_name.to_uppercase()),
+            )
+            .is_ok()
+            .then_some(wdf_function_identifier)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use quote::{format_ident, quote};
+
+    use super::*;
+
+    mod inputs {
+        use super::*;
+
+        #[test]
+        fn all_wdf_functions() {
+            let input_tokens = quote! { pub mod wdf };
+            let expected = Inputs {
+                wdk_sys_crate_path: None,
+                module_visibility: parse_quote! { pub },
+                module_identifier: format_ident!("wdf"),
+                wdf_function_identifiers: None,
+            };
+
+            pretty_assert_eq!(parse2::<Inputs>(input_tokens).unwrap(), expected);
+        }
+
+        #[test]
+        fn listed_wdf_functions() {
+            let input_tokens = quote! { mod wdf { WdfDriverCreate, WdfSpinLockAcquire, } };
+            let expected = Inputs {
+                wdk_sys_crate_path: None,
+                module_visibility: Visibility::Inherited,
+                module_identifier: format_ident!("wdf"),
+                wdf_function_identifiers: Some(
+                    parse_quote! { WdfDriverCreate, WdfSpinLockAcquire, },
+                ),
+            };
+
+            pretty_assert_eq!(parse2::<Inputs>(input_tokens).unwrap(), expected);
+        }
+
+        #[test]
+        fn crate_path_override() {
+            let input_tokens =
+                quote! { crate = renamed_wdk_sys, pub(crate) mod wdf { WdfDriverCreate } };
+            let expected = Inputs {
+                wdk_sys_crate_path: Some(parse_quote! { renamed_wdk_sys }),
+                module_visibility: parse_quote! { pub(crate) },
+                module_identifier: format_ident!("wdf"),
+                wdf_function_identifiers: Some(parse_quote! { WdfDriverCreate }),
+            };
+
+            pretty_assert_eq!(parse2::<Inputs>(input_tokens).unwrap(), expected);
+        }
+
+        #[test]
+        fn trailing_tokens() {
+            let input_tokens = quote! { mod wdf { WdfDriverCreate } WdfSpinLockAcquire };
+            let expected = Error::new(
+                Span::call_site(),
+                "unexpected tokens after the list of WDF functions",
+            );
+
+            pretty_assert_eq!(
+                parse2::<Inputs>(input_tokens).unwrap_err().to_string(),
+                expected.to_string()
+            );
+        }
+
+        #[test]
+        fn duplicate_wdf_function() {
+            let inputs =
+                parse2::<Inputs>(quote! { mod wdf { WdfDriverCreate, WdfDriverCreate } }).unwrap();
+            let expected = Error::new(
+                Span::call_site(),
+                "WdfDriverCreate is listed more than once",
+            );
+
+            pretty_assert_eq!(
+                inputs
+                    .resolve_wdf_function_identifiers(&parse_quote! {})
+                    .unwrap_err()
+                    .to_string(),
+                expected.to_string()
+            );
+        }
+    }
+
+    mod find_all_wdf_function_identifiers {
+        use super::*;
+
+        #[test]
+        fn skips_entries_without_function_pointer_type() {
+            // This is just a snippet of a generated types.rs file
+            let types_rs_ast = parse_quote! {
+                pub type PWDF_DRIVER_GLOBALS = *mut _WDF_DRIVER_GLOBALS;
+                pub mod _WDFFUNCENUM {
+                    pub type Type = ::core::ffi::c_int;
+                    pub const WdfChildListCreateTableIndex: Type = 0;
+                    pub const WdfGetTriageInfoTableIndex: Type = 1;
+                    pub const WdfFunctionTableNumEntries: Type = 2;
+                }
+                pub type PFN_WDFGETTRIAGEINFO = ::core::option::Option<
+                    unsafe extern "C" fn(DriverGlobals: PWDF_DRIVER_GLOBALS) -> PVOID,
+                >;
+            };
+            let expected = vec![format_ident!("WdfGetTriageInfo")];
+
+            pretty_assert_eq!(
+                find_all_wdf_function_identifiers(&types_rs_ast, Span::call_site()).unwrap(),
+                expected
+            );
+        }
+    }
+}
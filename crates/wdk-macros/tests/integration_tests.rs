@@ -248,6 +248,7 @@ fn create_symlink_if_nonexistent(link: &Path, target: &Path) {
     wdf_verifier_dbg_break_point
 );
 
+#[cfg(not(feature = "strict"))]
 generate_trybuild_tests!(
     wdf_api_that_does_not_exist,
     wdf_device_create_unused_return_type,
@@ -255,3 +256,8 @@ fn create_symlink_if_nonexistent(link: &Path, target: &Path) {
     wdf_driver_create_wrong_arg_order,
     wdf_timer_create_missing_unsafe
 );
+
+// The `strict` feature changes how discarded NTSTATUS return values are
+// reported, so these tests only run when it is enabled
+#[cfg(feature = "strict")]
+generate_trybuild_tests!(wdf_device_create_unused_return_type_strict);
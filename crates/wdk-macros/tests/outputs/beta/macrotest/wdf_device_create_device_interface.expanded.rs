@@ -10,9 +10,10 @@
 fn create_device_interface(wdf_device: WDFDEVICE) -> NTSTATUS {
     unsafe {
         {
-            #[must_use]
+            #[must_use = "WDF reports failures through the returned NTSTATUS, which should be checked (ex. with `nt_success`)"]
             #[inline(always)]
-            unsafe fn wdf_device_create_device_interface_impl(
+            #[allow(non_snake_case)]
+            unsafe fn WdfDeviceCreateDeviceInterface(
                 Device: wdk_sys::WDFDEVICE,
                 InterfaceClassGUID: *const wdk_sys::GUID,
                 ReferenceString: wdk_sys::PCUNICODE_STRING,
@@ -43,11 +44,10 @@ unsafe fn wdf_device_create_device_interface_impl(
                     };
                 }
             }
-            wdf_device_create_device_interface_impl(
-                wdf_device,
-                &GUID_DEVINTERFACE_COMPORT,
-                core::ptr::null(),
-            )
+            let __arg0: wdk_sys::WDFDEVICE = wdf_device;
+            let __arg1: *const wdk_sys::GUID = &GUID_DEVINTERFACE_COMPORT;
+            let __arg2: wdk_sys::PCUNICODE_STRING = core::ptr::null();
+            WdfDeviceCreateDeviceInterface(__arg0, __arg1, __arg2)
         }
     }
 }
@@ -5,7 +5,8 @@ fn acquire_lock(wdf_spin_lock: WDFSPINLOCK) {
     unsafe {
         {
             #[inline(always)]
-            unsafe fn wdf_spin_lock_acquire_impl(SpinLock: wdk_sys::WDFSPINLOCK) {
+            #[allow(non_snake_case)]
+            unsafe fn WdfSpinLockAcquire(SpinLock: wdk_sys::WDFSPINLOCK) {
                 let wdf_function: wdk_sys::PFN_WDFSPINLOCKACQUIRE = Some(unsafe {
                     core::mem::transmute(
                         wdk_sys::WDF_FUNCTION_TABLE[wdk_sys::_WDFFUNCENUM::WdfSpinLockAcquireTableIndex
@@ -25,7 +26,8 @@ unsafe fn wdf_spin_lock_acquire_impl(SpinLock: wdk_sys::WDFSPINLOCK) {
                     };
                 }
             }
-            wdf_spin_lock_acquire_impl(wdf_spin_lock)
+            let __arg0: wdk_sys::WDFSPINLOCK = wdf_spin_lock;
+            WdfSpinLockAcquire(__arg0)
         };
     }
 }
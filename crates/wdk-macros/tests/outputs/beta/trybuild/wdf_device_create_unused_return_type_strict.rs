@@ -0,0 +1 @@
+../../../inputs/trybuild/wdf_device_create_unused_return_type_strict.rs
\ No newline at end of file
@@ -13,9 +13,10 @@ pub extern "system" fn driver_entry(
     let driver_handle_output = WDF_NO_HANDLE as *mut WDFDRIVER;
     unsafe {
         {
-            #[must_use]
+            #[must_use = "WDF reports failures through the returned NTSTATUS, which should be checked (ex. with `nt_success`)"]
             #[inline(always)]
-            unsafe fn wdf_driver_create_impl(
+            #[allow(non_snake_case)]
+            unsafe fn WdfDriverCreate(
                 DriverObject: wdk_sys::PDRIVER_OBJECT,
                 RegistryPath: wdk_sys::PCUNICODE_STRING,
                 DriverAttributes: wdk_sys::PWDF_OBJECT_ATTRIBUTES,
@@ -50,13 +51,12 @@ unsafe fn wdf_driver_create_impl(
                     };
                 }
             }
-            wdf_driver_create_impl(
-                driver as PDRIVER_OBJECT,
-                registry_path,
-                WDF_NO_OBJECT_ATTRIBUTES,
-                &mut driver_config,
-                driver_handle_output,
-            )
+            let __arg0: wdk_sys::PDRIVER_OBJECT = driver as PDRIVER_OBJECT;
+            let __arg1: wdk_sys::PCUNICODE_STRING = registry_path;
+            let __arg2: wdk_sys::PWDF_OBJECT_ATTRIBUTES = WDF_NO_OBJECT_ATTRIBUTES;
+            let __arg3: wdk_sys::PWDF_DRIVER_CONFIG = &mut driver_config;
+            let __arg4: *mut wdk_sys::WDFDRIVER = driver_handle_output;
+            WdfDriverCreate(__arg0, __arg1, __arg2, __arg3, __arg4)
         }
     }
 }
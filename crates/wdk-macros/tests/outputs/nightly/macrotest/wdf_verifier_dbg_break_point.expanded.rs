@@ -6,7 +6,8 @@ fn foo() {
     unsafe {
         {
             #[inline(always)]
-            unsafe fn wdf_verifier_dbg_break_point_impl() {
+            #[allow(non_snake_case)]
+            unsafe fn WdfVerifierDbgBreakPoint() {
                 let wdf_function: wdk_sys::PFN_WDFVERIFIERDBGBREAKPOINT = Some(unsafe {
                     core::mem::transmute(
                         wdk_sys::WDF_FUNCTION_TABLE[wdk_sys::_WDFFUNCENUM::WdfVerifierDbgBreakPointTableIndex
@@ -26,7 +27,7 @@ unsafe fn wdf_verifier_dbg_break_point_impl() {
                     };
                 }
             }
-            wdf_verifier_dbg_break_point_impl()
+            WdfVerifierDbgBreakPoint()
         }
     }
 }
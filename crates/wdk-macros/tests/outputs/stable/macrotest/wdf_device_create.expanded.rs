@@ -8,9 +8,10 @@ extern "C" fn evt_driver_device_add(
     let mut device_handle_output: WDFDEVICE = WDF_NO_HANDLE.cast();
     unsafe {
         {
-            #[must_use]
+            #[must_use = "WDF reports failures through the returned NTSTATUS, which should be checked (ex. with `nt_success`)"]
             #[inline(always)]
-            unsafe fn wdf_device_create_impl(
+            #[allow(non_snake_case)]
+            unsafe fn WdfDeviceCreate(
                 DeviceInit: *mut wdk_sys::PWDFDEVICE_INIT,
                 DeviceAttributes: wdk_sys::PWDF_OBJECT_ATTRIBUTES,
                 Device: *mut wdk_sys::WDFDEVICE,
@@ -41,11 +42,10 @@ unsafe fn wdf_device_create_impl(
                     };
                 }
             }
-            wdf_device_create_impl(
-                &mut device_init,
-                WDF_NO_OBJECT_ATTRIBUTES,
-                &mut device_handle_output,
-            )
+            let __arg0: *mut wdk_sys::PWDFDEVICE_INIT = &mut device_init;
+            let __arg1: wdk_sys::PWDF_OBJECT_ATTRIBUTES = WDF_NO_OBJECT_ATTRIBUTES;
+            let __arg2: *mut wdk_sys::WDFDEVICE = &mut device_handle_output;
+            WdfDeviceCreate(__arg0, __arg1, __arg2)
         }
     }
 }
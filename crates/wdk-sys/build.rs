@@ -4,80 +4,790 @@
 //! Build script for the `wdk-sys` crate.
 
 use std::{
+    any::Any,
     env,
     path::{Path, PathBuf},
     sync::Arc,
     thread::{self, JoinHandle},
 };
 
-use bindgen::CodegenConfig;
+use anyhow::{anyhow, bail, Context};
+use bindgen::{Bindings, CodegenConfig};
 use tracing_subscriber::{filter::LevelFilter, EnvFilter};
-use wdk_build::{BuilderExt, Config, ConfigError, DriverConfig, KMDFConfig};
-
-// FIXME: feature gate the WDF version
-// FIXME: check that the features are exclusive
-// const KMDF_VERSIONS: &'static [&'static str] = &[
-//     "1.9", "1.11", "1.13", "1.15", "1.17", "1.19", "1.21", "1.23", "1.25",
-// "1.27", "1.31", "1.33", ];
-// const UMDF_VERSIONS: &'static [&'static str] = &[
-//     "2.0", "2.15", "2.17", "2.19", "2.21", "2.23", "2.25", "2.27", "2.31",
-// "2.33", ];
-
-fn generate_constants(out_path: &Path, config: &Config) -> Result<(), ConfigError> {
-    Ok(
-        bindgen::Builder::wdk_default(vec!["src/ntddk-input.h", "src/wdf-input.h"], config)?
-            .with_codegen_config(CodegenConfig::VARS)
-            .generate()
-            .expect("Bindings should succeed to generate")
-            .write_to_file(out_path.join("constants.rs"))?,
-    )
+use wdk_build::{
+    check_libclang,
+    struct_initializers,
+    typed_constants::{self, ConstantFamily, ConstantFamilyKind, ConstantMembers},
+    BindingsCache,
+    BindingsStamps,
+    BuilderExt,
+    Config,
+    ConfigError,
+    DriverConfig,
+    DriverType,
+    KMDFConfig,
+    NDISConfig,
+    NetAdapterConfig,
+    Subsystem,
+    UMDFConfig,
+};
+
+/// KMDF versions that can be selected via `kmdf-<major>-<minor>` Cargo features
+const KMDF_VERSIONS: [(u8, u8); 12] = [
+    (1, 9),
+    (1, 11),
+    (1, 13),
+    (1, 15),
+    (1, 17),
+    (1, 19),
+    (1, 21),
+    (1, 23),
+    (1, 25),
+    (1, 27),
+    (1, 31),
+    (1, 33),
+];
+
+/// UMDF versions that can be selected via `umdf-<major>-<minor>` Cargo features
+const UMDF_VERSIONS: [(u8, u8); 10] = [
+    (2, 0),
+    (2, 15),
+    (2, 17),
+    (2, 19),
+    (2, 21),
+    (2, 23),
+    (2, 25),
+    (2, 27),
+    (2, 31),
+    (2, 33),
+];
+
+/// Families of kernel-mode constants that typed wrappers are generated for in
+/// `typed_constants.rs`. These are not generated for UMDF drivers, since their
+/// raw types are not defined by the user-mode headers.
+const KERNEL_MODE_TYPED_CONSTANT_FAMILIES: [ConstantFamily; 2] = [
+    ConstantFamily {
+        type_name: "PoolFlags",
+        doc: "Flags of pool allocations made via `ExAllocatePool2` and `ExAllocatePool3` \
+              (`POOL_FLAG_*`)",
+        raw_type: "POOL_FLAGS",
+        kind: ConstantFamilyKind::Bitflags,
+        module: None,
+        members: ConstantMembers::Prefix("POOL_FLAG_"),
+    },
+    ConstantFamily {
+        type_name: "Irql",
+        doc: "Interrupt request level (`*_LEVEL`)",
+        raw_type: "KIRQL",
+        kind: ConstantFamilyKind::Enum,
+        module: None,
+        members: ConstantMembers::Names(&[
+            "PASSIVE_LEVEL",
+            "LOW_LEVEL",
+            "APC_LEVEL",
+            "DISPATCH_LEVEL",
+            "CMCI_LEVEL",
+            "CLOCK_LEVEL",
+            "IPI_LEVEL",
+            "DRS_LEVEL",
+            "POWER_LEVEL",
+            "PROFILE_LEVEL",
+            "HIGH_LEVEL",
+        ]),
+    },
+];
+
+/// Families of WDF constants that typed wrappers are generated for in
+/// `typed_constants.rs`
+const WDF_TYPED_CONSTANT_FAMILIES: [ConstantFamily; 2] = [
+    ConstantFamily {
+        type_name: "WdfIoQueueDispatchType",
+        doc: "Dispatch type of a WDF I/O queue (`WdfIoQueueDispatch*`)",
+        raw_type: "WDF_IO_QUEUE_DISPATCH_TYPE",
+        kind: ConstantFamilyKind::Enum,
+        module: Some("_WDF_IO_QUEUE_DISPATCH_TYPE"),
+        members: ConstantMembers::Prefix("WdfIoQueueDispatch"),
+    },
+    ConstantFamily {
+        type_name: "WdfTriState",
+        doc: "WDF tri-state value (`WdfFalse`, `WdfTrue` or `WdfUseDefault`)",
+        raw_type: "WDF_TRI_STATE",
+        kind: ConstantFamilyKind::Enum,
+        module: Some("_WDF_TRI_STATE"),
+        members: ConstantMembers::Prefix("Wdf"),
+    },
+];
+
+/// Returns the header that the bindings to the non-WDF APIs of the configured
+/// driver model are generated from. UMDF drivers are built against the Win32
+/// headers instead of the kernel-mode headers.
+const fn driver_model_input_header(config: &Config) -> &'static str {
+    match config.driver_config {
+        DriverConfig::WDM() | DriverConfig::KMDF(_) => "src/ntddk-input.h",
+        DriverConfig::UMDF(_) => "src/umdf-input.h",
+    }
 }
 
-fn generate_types(out_path: &Path, config: &Config) -> Result<(), ConfigError> {
-    Ok(
-        bindgen::Builder::wdk_default(vec!["src/ntddk-input.h", "src/wdf-input.h"], config)?
-            .with_codegen_config(CodegenConfig::TYPES)
-            .generate()
-            .expect("Bindings should succeed to generate")
-            .write_to_file(out_path.join("types.rs"))?,
-    )
+/// Returns the headers that the constants and types are generated from. The WDF
+/// headers are not included for WDM drivers, which do not use WDF.
+fn constants_and_types_input_headers(config: &Config) -> Vec<&'static str> {
+    let mut input_headers = vec![driver_model_input_header(config)];
+    if config.driver_config.driver_type() != DriverType::WDM {
+        input_headers.push("src/wdf-input.h");
+    }
+    input_headers
 }
 
-fn generate_ntddk(out_path: &Path, config: &Config) -> Result<(), ConfigError> {
-    Ok(
-        bindgen::Builder::wdk_default(vec!["src/ntddk-input.h"], config)?
-            .with_codegen_config((CodegenConfig::TYPES | CodegenConfig::VARS).complement())
-            .generate()
-            .expect("Bindings should succeed to generate")
-            .write_to_file(out_path.join("ntddk.rs"))?,
-    )
+fn generate_constants(input_headers: Vec<&str>, config: &Config) -> Result<Bindings, ConfigError> {
+    Ok(bindgen::Builder::wdk_default(input_headers, config)?
+        .with_codegen_config(CodegenConfig::VARS)
+        .generate()?)
+}
+
+fn generate_types(input_headers: Vec<&str>, config: &Config) -> Result<Bindings, ConfigError> {
+    Ok(bindgen::Builder::wdk_default(input_headers, config)?
+        .with_codegen_config(CodegenConfig::TYPES)
+        // WDF types are generated separately in wdf_types.rs, so that they are only
+        // exposed via the wdf module
+        .blocklist_file("(?i).*wdf.*")
+        .generate()?)
 }
 
-fn generate_wdf(out_path: &Path, config: &Config) -> Result<(), ConfigError> {
+fn generate_wdf_types(input_headers: Vec<&str>, config: &Config) -> Result<Bindings, ConfigError> {
+    let mut builder = bindgen::Builder::wdk_default(input_headers, config)?
+        .with_codegen_config(CodegenConfig::TYPES)
+        // Only generate for files that are prefixed with (case-insensitive) wdf, and do
+        // not pull in the types they depend on, since those are already in types.rs
+        .allowlist_file("(?i).*wdf.*")
+        .allowlist_recursively(false);
+    if is_feature_enabled("usb") {
+        // wdfrequest.h forward declares this struct, which is defined in wdfusb.h. Its
+        // definition is generated in wdf_usb_types.rs instead of an opaque type.
+        builder = builder.blocklist_type("_WDF_USB_REQUEST_COMPLETION_PARAMS");
+    }
+
+    Ok(builder.generate()?)
+}
+
+fn generate_wdf_usb_types(
+    input_headers: Vec<&str>,
+    config: &Config,
+) -> Result<Bindings, ConfigError> {
+    Ok(bindgen::Builder::wdk_default(input_headers, config)?
+        .with_codegen_config(CodegenConfig::TYPES)
+        // Only generate for wdfusb.h, which is not included by wdf.h. The types it
+        // depends on are already in types.rs, wdf_types.rs and usb.rs.
+        .allowlist_file("(?i).*[\\\\/]wdfusb\\.h")
+        .allowlist_recursively(false)
+        .generate()?)
+}
+
+fn generate_ntddk(input_headers: Vec<&str>, config: &Config) -> Result<Bindings, ConfigError> {
+    Ok(bindgen::Builder::wdk_default(input_headers, config)?
+        .with_codegen_config((CodegenConfig::TYPES | CodegenConfig::VARS).complement())
+        .generate()?)
+}
+
+fn generate_umdf(input_headers: Vec<&str>, config: &Config) -> Result<Bindings, ConfigError> {
+    Ok(bindgen::Builder::wdk_default(input_headers, config)?
+        .with_codegen_config((CodegenConfig::TYPES | CodegenConfig::VARS).complement())
+        .generate()?)
+}
+
+fn generate_wdf(input_headers: Vec<&str>, config: &Config) -> Result<Bindings, ConfigError> {
     // As of NI WDK, this may generate an empty file due to no non-type and non-var
     // items in the wdf headers(i.e. functions are all inlined). This step is
     // intentionally left here in case older WDKs have non-inlined functions or new
     // WDKs may introduce non-inlined functions.
-    Ok(
-        bindgen::Builder::wdk_default(vec!["src/wdf-input.h"], config)?
-            .with_codegen_config((CodegenConfig::TYPES | CodegenConfig::VARS).complement())
-            .allowlist_file("(?i).*wdf.*") // Only generate for files that are prefixed with (case-insensitive) wdf (ie.
-            // /some/path/WdfSomeHeader.h), to prevent duplication of code in ntddk.rs
-            .generate()
-            .expect("Bindings should succeed to generate")
-            .write_to_file(out_path.join("wdf.rs"))?,
-    )
+    Ok(bindgen::Builder::wdk_default(input_headers, config)?
+        .with_codegen_config((CodegenConfig::TYPES | CodegenConfig::VARS).complement())
+        .allowlist_file("(?i).*wdf.*") // Only generate for files that are prefixed with (case-insensitive) wdf (ie.
+        // /some/path/WdfSomeHeader.h), to prevent duplication of code in ntddk.rs
+        .generate()?)
+}
+
+fn generate_acpi(input_headers: Vec<&str>, config: &Config) -> Result<Bindings, ConfigError> {
+    Ok(bindgen::Builder::wdk_default(input_headers, config)?
+        // Only generate for the ACPI headers, to prevent duplication of code in types.rs and
+        // ntddk.rs. Their structs are packed via `#pragma pack`, which clang (and therefore
+        // bindgen) honors when computing their layouts.
+        .allowlist_file("(?i).*[\\\\/]acpi(?:ioct|tabl)\\.h")
+        .generate()?)
+}
+
+fn generate_cng(input_headers: Vec<&str>, config: &Config) -> Result<Bindings, ConfigError> {
+    Ok(bindgen::Builder::wdk_default(input_headers, config)?
+        // Only generate for bcrypt.h, to prevent duplication of code in types.rs and
+        // ntddk.rs
+        .allowlist_file("(?i).*[\\\\/]bcrypt\\.h")
+        .generate()?)
+}
+
+fn generate_ndis(input_headers: Vec<&str>, config: &Config) -> Result<Bindings, ConfigError> {
+    Ok(bindgen::Builder::wdk_default(input_headers, config)?
+        // The NDIS version definitions only apply to the NDIS headers, so they are not
+        // part of the default configuration
+        .clang_args(
+            config
+                .get_ndis_preprocessor_definitions()
+                .iter()
+                .map(|preprocessor_definition| format!("--define-macro={preprocessor_definition}")),
+        )
+        .allowlist_file("(?i).*ndis.*") // Only generate for files that contain (case-insensitive) ndis (ie.
+        // /some/path/ndis/SomeHeader.h), to prevent duplication of code in types.rs and
+        // ntddk.rs
+        .generate()?)
+}
+
+fn generate_storage(input_headers: Vec<&str>, config: &Config) -> Result<Bindings, ConfigError> {
+    Ok(bindgen::Builder::wdk_default(input_headers, config)?
+        // Only generate for the storage headers, to prevent duplication of code in types.rs
+        // and ntddk.rs
+        .allowlist_file("(?i).*(?:storport|ntddscsi|ntdddisk|srb)\\.h")
+        .generate()?)
+}
+
+fn generate_filesystem(input_headers: Vec<&str>, config: &Config) -> Result<Bindings, ConfigError> {
+    Ok(bindgen::Builder::wdk_default(input_headers, config)?
+        // Only generate for the Filter Manager headers (ie. fltKernel.h and
+        // fltUserStructures.h), to prevent duplication of code in types.rs and ntddk.rs
+        .allowlist_file("(?i).*flt.*")
+        .generate()?)
+}
+
+fn generate_gpio(input_headers: Vec<&str>, config: &Config) -> Result<Bindings, ConfigError> {
+    Ok(bindgen::Builder::wdk_default(input_headers, config)?
+        // Only generate for the GPIO headers, to prevent duplication of code in types.rs and
+        // ntddk.rs. The types they depend on are not pulled in, since GpioClx callbacks take
+        // WDF handles that must be the same types as the ones in wdf_types.rs.
+        .allowlist_file("(?i).*[\\\\/]gpio(?:clx)?\\.h")
+        .allowlist_recursively(false)
+        .generate()?)
+}
+
+fn generate_hid(input_headers: Vec<&str>, config: &Config) -> Result<Bindings, ConfigError> {
+    Ok(bindgen::Builder::wdk_default(input_headers, config)?
+        // Only generate for the HID headers, to prevent duplication of code in types.rs and
+        // ntddk.rs
+        .allowlist_file("(?i).*(?:hid|vhf)[^\\\\/]*\\.h")
+        .generate()?)
+}
+
+fn generate_netadaptercx(
+    input_headers: Vec<&str>,
+    config: &Config,
+) -> Result<Bindings, ConfigError> {
+    Ok(bindgen::Builder::wdk_default(input_headers, config)?
+        // Only generate for the NetCx headers, to prevent duplication of code in types.rs and
+        // ntddk.rs. The types they depend on are not pulled in, since they are already in
+        // types.rs and wdf_types.rs, and duplicating them would make them incompatible with
+        // the WDF types used by the rest of the crate.
+        .allowlist_file("(?i).*[\\\\/]netcx[\\\\/].*")
+        .allowlist_recursively(false)
+        .generate()?)
+}
+
+fn generate_parallel_ports(
+    input_headers: Vec<&str>,
+    config: &Config,
+) -> Result<Bindings, ConfigError> {
+    Ok(bindgen::Builder::wdk_default(input_headers, config)?
+        // Only generate for the parallel port headers, to prevent duplication of code in
+        // types.rs and ntddk.rs
+        .allowlist_file("(?i).*(?:parallel|ntddpar)\\.h")
+        .generate()?)
+}
+
+fn generate_pep(input_headers: Vec<&str>, config: &Config) -> Result<Bindings, ConfigError> {
+    Ok(bindgen::Builder::wdk_default(input_headers, config)?
+        // Only generate for the PEP headers, to prevent duplication of code in types.rs and
+        // ntddk.rs
+        .allowlist_file("(?i).*[\\\\/]pep(?:fx|events)\\.h")
+        .generate()?)
+}
+
+fn generate_spb(input_headers: Vec<&str>, config: &Config) -> Result<Bindings, ConfigError> {
+    Ok(bindgen::Builder::wdk_default(input_headers, config)?
+        // Only generate for the SPB headers, to prevent duplication of code in types.rs and
+        // ntddk.rs. The types they depend on are not pulled in, since SpbCx callbacks take
+        // WDF handles that must be the same types as the ones in wdf_types.rs.
+        .allowlist_file("(?i).*spb.*")
+        .allowlist_recursively(false)
+        .generate()?)
+}
+
+fn generate_usb(input_headers: Vec<&str>, config: &Config) -> Result<Bindings, ConfigError> {
+    Ok(bindgen::Builder::wdk_default(input_headers, config)?
+        // Only generate for headers prefixed with (case-insensitive) usb, to prevent
+        // duplication of code in types.rs and ntddk.rs (WDF's wdfusb.h is generated
+        // separately in wdf_usb_types.rs). The types they depend on are not pulled in,
+        // since they are already in types.rs, and duplicating them would make them
+        // ambiguous in wdf_usb_types.rs.
+        .allowlist_file("(?i).*[\\\\/]usb[^\\\\/]*\\.h")
+        .allowlist_recursively(false)
+        .generate()?)
+}
+
+fn generate_wdmsec(input_headers: Vec<&str>, config: &Config) -> Result<Bindings, ConfigError> {
+    Ok(bindgen::Builder::wdk_default(input_headers, config)?
+        // Only generate for wdmsec.h, to prevent duplication of code in types.rs and
+        // ntddk.rs
+        .allowlist_file("(?i).*[\\\\/]wdmsec\\.h")
+        .generate()?)
+}
+
+type GenerateFn = fn(Vec<&str>, &Config) -> Result<Bindings, ConfigError>;
+
+/// A module of bindings, which is generated by a single run of bindgen
+#[derive(Clone, Copy)]
+struct BindingsModule {
+    /// Name of the file in each output directory that the module is written to
+    file_name: &'static str,
+    /// Returns the headers in `src` that the module is generated from
+    input_headers: fn(&Config) -> Vec<&'static str>,
+    generate: GenerateFn,
+}
+
+impl BindingsModule {
+    /// Returns the stamp of the inputs of this module, which are its input
+    /// headers and the headers in `src` that they include
+    fn stamp(&self, config: &Config, bindings_stamps: &BindingsStamps) -> anyhow::Result<u64> {
+        let mut input_files = (self.input_headers)(config)
+            .into_iter()
+            .map(PathBuf::from)
+            .collect::<Vec<_>>();
+        let mut index = 0;
+        while let Some(input_file) = input_files.get(index) {
+            for line in std::fs::read_to_string(input_file)
+                .with_context(|| format!("failed to read {}", input_file.display()))?
+                .lines()
+            {
+                let Some(included_header) = line
+                    .trim()
+                    .strip_prefix("#include \"")
+                    .and_then(|line| line.strip_suffix('"'))
+                else {
+                    continue;
+                };
+                let included_header = Path::new("src").join(included_header);
+                if included_header.exists() && !input_files.contains(&included_header) {
+                    input_files.push(included_header);
+                }
+            }
+            index += 1;
+        }
+        Ok(bindings_stamps.stamp(input_files)?)
+    }
+}
+
+const BINDINGS_MODULES: [BindingsModule; 2] = [
+    BindingsModule {
+        file_name: "constants.rs",
+        input_headers: constants_and_types_input_headers,
+        generate: generate_constants,
+    },
+    BindingsModule {
+        file_name: "types.rs",
+        input_headers: constants_and_types_input_headers,
+        generate: generate_types,
+    },
+];
+
+/// Modules of WDF bindings, which are not generated for WDM drivers
+const WDF_BINDINGS_MODULES: [BindingsModule; 2] = [
+    BindingsModule {
+        file_name: "wdf_types.rs",
+        input_headers: |_| vec!["src/wdf-input.h"],
+        generate: generate_wdf_types,
+    },
+    BindingsModule {
+        file_name: "wdf.rs",
+        input_headers: |_| vec!["src/wdf-input.h"],
+        generate: generate_wdf,
+    },
+];
+
+/// Returns the module of bindings to the non-WDF functions of the configured
+/// driver model: `ntddk.rs` for kernel-mode drivers, and `umdf.rs` for UMDF
+/// drivers
+const fn driver_model_bindings_module(config: &Config) -> BindingsModule {
+    match config.driver_config {
+        DriverConfig::WDM() | DriverConfig::KMDF(_) => BindingsModule {
+            file_name: "ntddk.rs",
+            input_headers: |_| vec!["src/ntddk-input.h"],
+            generate: generate_ntddk,
+        },
+        DriverConfig::UMDF(_) => BindingsModule {
+            file_name: "umdf.rs",
+            input_headers: |_| vec!["src/umdf-input.h"],
+            generate: generate_umdf,
+        },
+    }
 }
 
-type GenerateFn = fn(&Path, &Config) -> Result<(), ConfigError>;
+/// Modules of bindings that are only generated when their corresponding Cargo
+/// feature is enabled
+const OPTIONAL_BINDINGS_MODULES: [(&str, BindingsModule); 11] = [
+    (
+        "acpi",
+        BindingsModule {
+            file_name: "acpi.rs",
+            input_headers: |_| vec!["src/acpi-input.h"],
+            generate: generate_acpi,
+        },
+    ),
+    (
+        "cng",
+        BindingsModule {
+            file_name: "cng.rs",
+            input_headers: |_| vec!["src/cng-input.h"],
+            generate: generate_cng,
+        },
+    ),
+    (
+        "filesystem",
+        BindingsModule {
+            file_name: "filesystem.rs",
+            input_headers: |_| vec!["src/filesystem-input.h"],
+            generate: generate_filesystem,
+        },
+    ),
+    (
+        "hid",
+        BindingsModule {
+            file_name: "hid.rs",
+            input_headers: |_| vec!["src/hid-input.h"],
+            generate: generate_hid,
+        },
+    ),
+    (
+        "ndis",
+        BindingsModule {
+            file_name: "ndis.rs",
+            input_headers: |_| vec!["src/ndis-input.h"],
+            generate: generate_ndis,
+        },
+    ),
+    (
+        "parallel-ports",
+        BindingsModule {
+            file_name: "parallel_ports.rs",
+            input_headers: |_| vec!["src/parallel-ports-input.h"],
+            generate: generate_parallel_ports,
+        },
+    ),
+    (
+        "pep",
+        BindingsModule {
+            file_name: "pep.rs",
+            input_headers: |_| vec!["src/pep-input.h"],
+            generate: generate_pep,
+        },
+    ),
+    (
+        "spb",
+        BindingsModule {
+            file_name: "spb.rs",
+            input_headers: |_| vec!["src/spb-input.h"],
+            generate: generate_spb,
+        },
+    ),
+    (
+        "storage",
+        BindingsModule {
+            file_name: "storage.rs",
+            input_headers: |_| vec!["src/storage-input.h"],
+            generate: generate_storage,
+        },
+    ),
+    (
+        "usb",
+        BindingsModule {
+            file_name: "usb.rs",
+            input_headers: |_| vec!["src/usb-input.h"],
+            generate: generate_usb,
+        },
+    ),
+    (
+        "wdmsec",
+        BindingsModule {
+            file_name: "wdmsec.rs",
+            input_headers: |_| vec!["src/wdmsec-input.h"],
+            generate: generate_wdmsec,
+        },
+    ),
+];
 
-const GENERATE_FUNCTIONS: [GenerateFn; 4] = [
-    generate_constants,
-    generate_types,
-    generate_ntddk,
-    generate_wdf,
+/// Modules of WDF bindings that are only generated when their corresponding
+/// Cargo feature is enabled, and the driver is not a WDM driver
+const OPTIONAL_WDF_BINDINGS_MODULES: [(&str, BindingsModule); 3] = [
+    (
+        "gpio",
+        BindingsModule {
+            file_name: "gpio.rs",
+            input_headers: |_| vec!["src/gpio-input.h"],
+            generate: generate_gpio,
+        },
+    ),
+    (
+        "netadaptercx",
+        BindingsModule {
+            file_name: "netadaptercx.rs",
+            input_headers: |_| vec!["src/netadaptercx-input.h"],
+            generate: generate_netadaptercx,
+        },
+    ),
+    (
+        "usb",
+        BindingsModule {
+            file_name: "wdf_usb_types.rs",
+            input_headers: |_| vec!["src/wdf-usb-input.h"],
+            generate: generate_wdf_usb_types,
+        },
+    ),
 ];
 
+/// Subsystems whose import libraries are linked when their corresponding Cargo
+/// feature is enabled
+const SUBSYSTEM_FEATURES: [(&str, Subsystem); 8] = [
+    ("cng", Subsystem::Cng),
+    ("filesystem", Subsystem::FilterManager),
+    ("gpio", Subsystem::Gpio),
+    ("hid", Subsystem::Hid),
+    ("spb", Subsystem::Spb),
+    ("storage", Subsystem::Storport),
+    ("usb", Subsystem::Usb),
+    ("wdmsec", Subsystem::Wdmsec),
+];
+
+/// Returns `true` if the Cargo feature named `feature` is enabled for this
+/// build
+fn is_feature_enabled(feature: &str) -> bool {
+    env::var_os(format!(
+        "CARGO_FEATURE_{}",
+        feature.to_ascii_uppercase().replace('-', "_")
+    ))
+    .is_some()
+}
+
+/// Determines the [`DriverConfig`] from the enabled `wdm`, `kmdf-*` and
+/// `umdf-*` Cargo features. KMDF with the default [`KMDFConfig`] is used if no
+/// driver model feature is enabled.
+fn driver_config_from_features() -> anyhow::Result<DriverConfig> {
+    let wdm_configs = is_feature_enabled("wdm").then(DriverConfig::WDM);
+    let kmdf_configs = KMDF_VERSIONS
+        .iter()
+        .filter(|(major, minor)| is_feature_enabled(&format!("kmdf-{major}-{minor}")))
+        .map(|&(kmdf_version_major, kmdf_version_minor)| {
+            DriverConfig::KMDF(KMDFConfig {
+                kmdf_version_major,
+                kmdf_version_minor,
+            })
+        });
+    let umdf_configs = UMDF_VERSIONS
+        .iter()
+        .filter(|(major, minor)| is_feature_enabled(&format!("umdf-{major}-{minor}")))
+        .map(|&(umdf_version_major, umdf_version_minor)| {
+            DriverConfig::UMDF(UMDFConfig {
+                umdf_version_major,
+                umdf_version_minor,
+            })
+        });
+    let mut selected_driver_configs = wdm_configs
+        .into_iter()
+        .chain(kmdf_configs)
+        .chain(umdf_configs)
+        .collect::<Vec<_>>();
+
+    match selected_driver_configs.len() {
+        0 => Ok(DriverConfig::KMDF(KMDFConfig::new())),
+        1 => Ok(selected_driver_configs.remove(0)),
+        _ => bail!(
+            "only one of the wdm, KMDF version or UMDF version features can be enabled for \
+             wdk-sys, but found: {selected_driver_configs:?}"
+        ),
+    }
+}
+
+/// Exports the selected WDF version to the `wdk-sys` crate via environment
+/// variables, so that the version constants and function table symbol match
+/// the generated bindings
+fn export_wdf_version(driver_config: &DriverConfig) {
+    let (major, minor) = match driver_config {
+        DriverConfig::WDM() => return,
+        DriverConfig::KMDF(kmdf_config) => (
+            kmdf_config.kmdf_version_major,
+            kmdf_config.kmdf_version_minor,
+        ),
+        DriverConfig::UMDF(umdf_config) => (
+            umdf_config.umdf_version_major,
+            umdf_config.umdf_version_minor,
+        ),
+    };
+
+    println!("cargo::rustc-env=WDK_SYS_WDF_MAJOR_VERSION={major}");
+    println!("cargo::rustc-env=WDK_SYS_WDF_MINOR_VERSION={minor}");
+    println!(
+        "cargo::rustc-env=WDK_SYS_WDF_FUNCTION_TABLE_SYMBOL=WdfFunctions_{major:02}{minor:03}"
+    );
+}
+
+/// Generates the typed wrappers of [`KERNEL_MODE_TYPED_CONSTANT_FAMILIES`] for
+/// kernel-mode drivers and of [`WDF_TYPED_CONSTANT_FAMILIES`] for WDF drivers
+/// from the bindings previously generated in `out_path`, and writes them to
+/// `typed_constants.rs`
+fn generate_typed_constants(out_path: &Path, config: &Config) -> Result<(), ConfigError> {
+    let is_wdm = config.driver_config.driver_type() == DriverType::WDM;
+
+    let mut bindings = String::new();
+    for file_name in ["constants.rs", "types.rs"]
+        .into_iter()
+        .chain((!is_wdm).then_some("wdf_types.rs"))
+    {
+        bindings.push_str(&std::fs::read_to_string(out_path.join(file_name))?);
+    }
+
+    let mut families = Vec::new();
+    if !matches!(config.driver_config, DriverConfig::UMDF(_)) {
+        families.extend(KERNEL_MODE_TYPED_CONSTANT_FAMILIES);
+    }
+    if !is_wdm {
+        families.extend(WDF_TYPED_CONSTANT_FAMILIES);
+    }
+
+    Ok(std::fs::write(
+        out_path.join("typed_constants.rs"),
+        typed_constants::generate(&bindings, &families),
+    )?)
+}
+
+/// Generates the `Size` initializers of the WDF structs in the bindings
+/// previously generated in `out_path`, and writes them next to the bindings
+/// they were generated from
+fn generate_struct_initializers(out_path: &Path) -> Result<(), ConfigError> {
+    for (bindings_file_name, struct_initializers_file_name) in [
+        ("wdf_types.rs", "wdf_struct_initializers.rs"),
+        ("wdf_usb_types.rs", "wdf_usb_struct_initializers.rs"),
+    ] {
+        let bindings_path = out_path.join(bindings_file_name);
+        // wdf_usb_types.rs is only generated if the usb feature is enabled
+        if !bindings_path.exists() {
+            continue;
+        }
+        std::fs::write(
+            out_path.join(struct_initializers_file_name),
+            struct_initializers::generate(&std::fs::read_to_string(bindings_path)?),
+        )?;
+    }
+    Ok(())
+}
+
+/// Returns the C header files in `src` that bindings are generated from
+fn input_header_files() -> std::io::Result<Vec<PathBuf>> {
+    let mut input_header_files = Vec::new();
+    for entry in std::fs::read_dir("src")? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|extension| extension == "h") {
+            input_header_files.push(path);
+        }
+    }
+    Ok(input_header_files)
+}
+
+/// Returns the modules of bindings that are generated for `config` and the
+/// enabled Cargo features
+fn bindings_modules(config: &Config) -> Vec<BindingsModule> {
+    let is_wdm = config.driver_config.driver_type() == DriverType::WDM;
+    let wdf_bindings_modules = WDF_BINDINGS_MODULES.into_iter().filter(|_| !is_wdm);
+    let enabled_optional_bindings_modules = OPTIONAL_BINDINGS_MODULES
+        .iter()
+        .chain(OPTIONAL_WDF_BINDINGS_MODULES.iter().filter(|_| !is_wdm))
+        .filter(|(feature, _)| is_feature_enabled(feature))
+        .map(|(_, bindings_module)| *bindings_module);
+    BINDINGS_MODULES
+        .into_iter()
+        .chain([driver_model_bindings_module(config)])
+        .chain(wdf_bindings_modules)
+        .chain(enabled_optional_bindings_modules)
+        .collect()
+}
+
+/// Runs bindgen in parallel for each module of bindings whose inputs changed
+/// since it was last generated, or that is missing from any of `out_paths`,
+/// writing the module to each of `out_paths`
+fn generate_bindings(
+    out_paths: &[PathBuf],
+    config: &Config,
+    bindings_stamps: &BindingsStamps,
+) -> anyhow::Result<()> {
+    let mut handles = Vec::new();
+    let out_paths_arc = Arc::new(out_paths.to_vec());
+    let config_arc = Arc::new(config.clone());
+
+    for bindings_module in bindings_modules(config) {
+        let stamp = bindings_module.stamp(config, bindings_stamps)?;
+        if bindings_stamps.is_fresh(bindings_module.file_name, stamp)
+            && out_paths
+                .iter()
+                .all(|out_path| out_path.join(bindings_module.file_name).exists())
+        {
+            continue;
+        }
+
+        let input_headers = (bindings_module.input_headers)(config);
+        let temp_out_paths = out_paths_arc.clone();
+        let temp_config = config_arc.clone();
+        let temp_input_headers = input_headers.clone();
+        let handle: JoinHandle<Result<(), ConfigError>> = thread::spawn(move || {
+            let bindings = (bindings_module.generate)(temp_input_headers, &temp_config)?;
+            for out_path in temp_out_paths.iter() {
+                bindings.write_to_file(out_path.join(bindings_module.file_name))?;
+            }
+            Ok(())
+        });
+        handles.push((bindings_module, input_headers, stamp, handle));
+    }
+
+    for (bindings_module, input_headers, stamp, handle) in handles {
+        let context = || {
+            format!(
+                "failed to generate {} from {}",
+                bindings_module.file_name,
+                input_headers.join(", ")
+            )
+        };
+        match handle.join() {
+            Ok(result) => result.with_context(context)?,
+            Err(panic) => {
+                return Err(anyhow!("bindgen panicked: {}", panic_message(&*panic)))
+                    .with_context(context);
+            }
+        }
+        bindings_stamps.store(bindings_module.file_name, stamp)?;
+    }
+    Ok(())
+}
+
+/// Records that every module of bindings for `config` is up to date with its
+/// inputs, after the bindings were restored from the bindings cache
+fn stamp_bindings(config: &Config, bindings_stamps: &BindingsStamps) -> anyhow::Result<()> {
+    for bindings_module in bindings_modules(config) {
+        let stamp = bindings_module.stamp(config, bindings_stamps)?;
+        bindings_stamps.store(bindings_module.file_name, stamp)?;
+    }
+    Ok(())
+}
+
+/// Returns the message of a panic raised via `panic!`, whose payload is either
+/// a `&str` or a `String`
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic payload")
+}
+
 fn main() -> anyhow::Result<()> {
     let tracing_filter = EnvFilter::default()
         // Show errors and warnings by default
@@ -126,9 +836,29 @@ fn main() -> anyhow::Result<()> {
         .with_env_filter(tracing_filter)
         .init();
 
+    let driver_config = driver_config_from_features()?;
+    // NetAdapterCx and GpioClx are KMDF class extensions
+    for feature in ["gpio", "netadaptercx"] {
+        if is_feature_enabled(feature) && driver_config.driver_type() != DriverType::KMDF {
+            bail!(
+                "the {feature} feature of wdk-sys is only supported for KMDF drivers, but found: \
+                 {driver_config:?}"
+            );
+        }
+    }
+
     let config = Config {
-        // FIXME: this should be based off of Cargo feature version
-        driver_config: DriverConfig::KMDF(KMDFConfig::new()),
+        driver_config,
+        ndis_config: is_feature_enabled("ndis").then(NDISConfig::new),
+        netadapter_config: is_feature_enabled("netadaptercx").then(NetAdapterConfig::new),
+        subsystems: SUBSYSTEM_FEATURES
+            .iter()
+            .filter(|(feature, _)| is_feature_enabled(feature))
+            .map(|&(_, subsystem)| subsystem)
+            .collect(),
+        // The `wdk-version` pinned by the driver is passed via an env var, since the `wdk`
+        // metadata of the driver cannot be resolved from the build script of a dependency
+        wdk_version: Config::wdk_version_from_env(),
         ..Config::default()
     };
 
@@ -146,27 +876,63 @@ fn main() -> anyhow::Result<()> {
         ),
     ];
 
-    let mut handles = Vec::<JoinHandle<Result<(), ConfigError>>>::new();
-    let config_arc = Arc::new(config);
-
-    for out_path in out_paths {
-        let path_arc = Arc::new(out_path);
-        for generate_function in GENERATE_FUNCTIONS {
-            let temp_path = path_arc.clone();
-            let temp_config = config_arc.clone();
-            let handle: JoinHandle<Result<(), ConfigError>> = thread::spawn(move || {
-                generate_function(&temp_path, &temp_config)?;
-                Ok(())
-            });
-            handles.push(handle);
+    export_wdf_version(&config.driver_config);
+
+    // Fail early with an actionable error if libclang is missing or too old,
+    // instead of panicking partway through binding generation
+    check_libclang(&config)?;
+
+    // Reuse the bindings of a previous build with the same configuration if they
+    // are cached, since running bindgen over the WDK headers takes several minutes
+    let bindings_cache = BindingsCache::new(&config, input_header_files()?)?;
+    let restored_from_cache = match &bindings_cache {
+        Some(bindings_cache) => bindings_cache.restore(&out_paths)?,
+        None => false,
+    };
+
+    // Only regenerate the modules of bindings whose inputs changed since the
+    // previous build, since a change to a single input header otherwise reruns
+    // bindgen for every module
+    let bindings_stamps = BindingsStamps::new(&config, out_paths[1].join("bindings-stamps"))?;
+    if restored_from_cache {
+        stamp_bindings(&config, &bindings_stamps)?;
+    } else {
+        generate_bindings(&out_paths, &config, &bindings_stamps)?;
+        if let Some(bindings_cache) = &bindings_cache {
+            bindings_cache.store(&out_paths[1])?;
         }
     }
 
-    for handle in handles {
-        if let Err(e) = handle.join().unwrap() {
-            return Err(e.into());
-        }
+    // The typed constants and struct initializers are derived from the bindings, so
+    // they are regenerated even if the bindings were restored from the cache
+    for out_path in &out_paths {
+        generate_typed_constants(out_path, &config).with_context(|| {
+            format!(
+                "failed to generate typed constants in {}",
+                out_path.display()
+            )
+        })?;
+        generate_struct_initializers(out_path).with_context(|| {
+            format!(
+                "failed to generate struct initializers in {}",
+                out_path.display()
+            )
+        })?;
     }
 
-    Ok(config_arc.export_config()?)
+    // Export the location of the generated types so that
+    // `call_unsafe_wdf_function_binding` does not need to spawn a nested `cargo
+    // check` to find it, both in this crate and in crates that forward it via
+    // `wdk_build`
+    let types_rs_path = PathBuf::from(
+        env::var("OUT_DIR").expect("OUT_DIR should be exist in Cargo build environment"),
+    )
+    .join("types.rs");
+    println!(
+        "cargo::rustc-env=WDK_SYS_TYPES_RS_PATH={}",
+        types_rs_path.display()
+    );
+    println!("cargo::metadata=types_rs_path={}", types_rs_path.display());
+
+    Ok(config.export_config()?)
 }
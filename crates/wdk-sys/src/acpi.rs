@@ -0,0 +1,55 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Direct FFI bindings to ACPI APIs from the Windows Driver Kit (WDK)
+//!
+//! This includes the input and output buffers of the `IOCTL_ACPI_*` requests
+//! (ex. [`ACPI_EVAL_INPUT_BUFFER`] and [`ACPI_EVAL_OUTPUT_BUFFER`]) from
+//! `acpiioct.h`, and the ACPI table definitions from `acpitabl.h`. The
+//! `IOCTL_ACPI_*` control codes are defined via the `CTL_CODE` function-like
+//! macro, which `bindgen` cannot evaluate, so they are defined in this module
+//! instead.
+
+use crate::{FILE_DEVICE_ACPI, FILE_READ_ACCESS, FILE_WRITE_ACCESS, METHOD_BUFFERED};
+
+#[allow(missing_docs)]
+#[allow(clippy::unreadable_literal)]
+mod bindings {
+    // allow wildcards for types module since underlying c code relies on all
+    // type definitions being in scope
+    #[allow(clippy::wildcard_imports)]
+    use crate::types::*;
+
+    include!(concat!(env!("OUT_DIR"), "/acpi.rs"));
+}
+pub use bindings::*;
+
+/// Evaluates an ACPI control method synchronously. The input buffer is an
+/// [`ACPI_EVAL_INPUT_BUFFER`] (or one of its variants with arguments), and
+/// the output buffer is an [`ACPI_EVAL_OUTPUT_BUFFER`].
+pub const IOCTL_ACPI_EVAL_METHOD: u32 = acpi_ctl_code(1);
+/// Evaluates an ACPI control method asynchronously. The buffers are the same
+/// as those of [`IOCTL_ACPI_EVAL_METHOD`].
+pub const IOCTL_ACPI_ASYNC_EVAL_METHOD: u32 = acpi_ctl_code(0);
+/// Acquires the ACPI global lock
+pub const IOCTL_ACPI_ACQUIRE_GLOBAL_LOCK: u32 = acpi_ctl_code(4);
+/// Releases the ACPI global lock
+pub const IOCTL_ACPI_RELEASE_GLOBAL_LOCK: u32 = acpi_ctl_code(5);
+/// Evaluates an ACPI control method, identified by its full path in the ACPI
+/// namespace, synchronously. The input buffer is an
+/// `ACPI_EVAL_INPUT_BUFFER_EX` (or one of its variants with arguments).
+pub const IOCTL_ACPI_EVAL_METHOD_EX: u32 = acpi_ctl_code(6);
+/// Evaluates an ACPI control method, identified by its full path in the ACPI
+/// namespace, asynchronously. The buffers are the same as those of
+/// [`IOCTL_ACPI_EVAL_METHOD_EX`].
+pub const IOCTL_ACPI_ASYNC_EVAL_METHOD_EX: u32 = acpi_ctl_code(7);
+
+/// Equivalent to `CTL_CODE(FILE_DEVICE_ACPI, function, METHOD_BUFFERED,
+/// FILE_READ_ACCESS | FILE_WRITE_ACCESS)`, which the `IOCTL_ACPI_*` control
+/// codes in this module are defined with
+const fn acpi_ctl_code(function: u32) -> u32 {
+    (FILE_DEVICE_ACPI << 16)
+        | ((FILE_READ_ACCESS | FILE_WRITE_ACCESS) << 14)
+        | (function << 2)
+        | METHOD_BUFFERED
+}
@@ -0,0 +1,6 @@
+/* Copyright (c) Microsoft Corporation
+   License: MIT OR Apache-2.0 */
+
+#include "ntifs.h"
+#include "ntddk.h"
+#include "bcrypt.h"
@@ -0,0 +1,21 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Direct FFI bindings to CNG (Cryptography API: Next Generation) APIs from
+//! `bcrypt.h` in the Windows Driver Kit (WDK)
+//!
+//! These include random number generation (ex. `BCryptGenRandom`), hashing
+//! and HMAC (ex. `BCryptCreateHash`), and symmetric encryption primitives that
+//! are callable from kernel-mode.
+
+#[allow(missing_docs)]
+#[allow(clippy::unreadable_literal)]
+mod bindings {
+    // allow wildcards for types module since underlying c code relies on all
+    // type definitions being in scope
+    #[allow(clippy::wildcard_imports)]
+    use crate::types::*;
+
+    include!(concat!(env!("OUT_DIR"), "/cng.rs"));
+}
+pub use bindings::*;
@@ -3,7 +3,7 @@
 
 #![allow(missing_docs)]
 
-use crate::types::{NTSTATUS, POOL_FLAGS, PVOID, PWDF_OBJECT_ATTRIBUTES};
+use crate::types::NTSTATUS;
 
 #[allow(non_upper_case_globals)]
 #[rustversion::attr(
@@ -18,6 +18,9 @@
 mod bindings {
     // allow wildcards for types module since underlying c code relies on all
     // type definitions being in scope
+    #[cfg(not(driver_type = "wdm"))]
+    #[allow(clippy::wildcard_imports)]
+    use crate::types::wdf_types::*;
     #[allow(clippy::wildcard_imports)]
     use crate::types::*;
 
@@ -25,29 +28,49 @@ mod bindings {
 }
 pub use bindings::*;
 
-pub const WDF_NO_OBJECT_ATTRIBUTES: PWDF_OBJECT_ATTRIBUTES = core::ptr::null_mut();
-pub const WDF_NO_EVENT_CALLBACK: PVOID = core::ptr::null_mut();
-pub const WDF_NO_HANDLE: PVOID = core::ptr::null_mut();
-pub const WDF_NO_CONTEXT: PVOID = core::ptr::null_mut();
-pub const WDF_NO_SEND_OPTIONS: PVOID = core::ptr::null_mut();
+// WDM drivers do not use WDF, so the WDF placeholder values are not available to
+// them.
+#[cfg(not(driver_type = "wdm"))]
+pub use wdf_placeholders::*;
+
+#[cfg(not(driver_type = "wdm"))]
+mod wdf_placeholders {
+    use crate::types::{wdf_types::PWDF_OBJECT_ATTRIBUTES, PVOID};
+
+    pub const WDF_NO_OBJECT_ATTRIBUTES: PWDF_OBJECT_ATTRIBUTES = core::ptr::null_mut();
+    pub const WDF_NO_EVENT_CALLBACK: PVOID = core::ptr::null_mut();
+    pub const WDF_NO_HANDLE: PVOID = core::ptr::null_mut();
+    pub const WDF_NO_CONTEXT: PVOID = core::ptr::null_mut();
+    pub const WDF_NO_SEND_OPTIONS: PVOID = core::ptr::null_mut();
+}
 
 // Macros with MSVC C Integer Constant Suffixes are not supported by bindgen, so they must be manually ported or imported from elsewhere: https://github.com/rust-lang/rust-bindgen/issues/2600
-pub const POOL_FLAG_REQUIRED_START: POOL_FLAGS = 0x0000_0000_0000_0001;
-pub const POOL_FLAG_USE_QUOTA: POOL_FLAGS = 0x0000_0000_0000_0001; // Charge quota
-pub const POOL_FLAG_UNINITIALIZED: POOL_FLAGS = 0x0000_0000_0000_0002; // Don't zero-initialize allocation
-pub const POOL_FLAG_SESSION: POOL_FLAGS = 0x0000_0000_0000_0004; // Use session specific pool
-pub const POOL_FLAG_CACHE_ALIGNED: POOL_FLAGS = 0x0000_0000_0000_0008; // Cache aligned allocation
-pub const POOL_FLAG_RESERVED1: POOL_FLAGS = 0x0000_0000_0000_0010; // Reserved for system use
-pub const POOL_FLAG_RAISE_ON_FAILURE: POOL_FLAGS = 0x0000_0000_0000_0020; // Raise exception on failure
-pub const POOL_FLAG_NON_PAGED: POOL_FLAGS = 0x0000_0000_0000_0040; // Non paged pool NX
-pub const POOL_FLAG_NON_PAGED_EXECUTE: POOL_FLAGS = 0x0000_0000_0000_0080; // Non paged pool executable
-pub const POOL_FLAG_PAGED: POOL_FLAGS = 0x0000_0000_0000_0100; // Paged pool
-pub const POOL_FLAG_RESERVED2: POOL_FLAGS = 0x0000_0000_0000_0200; // Reserved for system use
-pub const POOL_FLAG_RESERVED3: POOL_FLAGS = 0x0000_0000_0000_0400; // Reserved for system use
-pub const POOL_FLAG_REQUIRED_END: POOL_FLAGS = 0x0000_0000_8000_0000;
-pub const POOL_FLAG_OPTIONAL_START: POOL_FLAGS = 0x0000_0001_0000_0000;
-pub const POOL_FLAG_SPECIAL_POOL: POOL_FLAGS = 0x0000_0001_0000_0000; // Make special pool allocation
-pub const POOL_FLAG_OPTIONAL_END: POOL_FLAGS = 0x8000_0000_0000_0000;
+// These are only defined by the kernel-mode headers, so they are not available
+// to UMDF drivers.
+#[cfg(not(driver_type = "umdf"))]
+pub use pool_flags::*;
+
+#[cfg(not(driver_type = "umdf"))]
+mod pool_flags {
+    use crate::types::POOL_FLAGS;
+
+    pub const POOL_FLAG_REQUIRED_START: POOL_FLAGS = 0x0000_0000_0000_0001;
+    pub const POOL_FLAG_USE_QUOTA: POOL_FLAGS = 0x0000_0000_0000_0001; // Charge quota
+    pub const POOL_FLAG_UNINITIALIZED: POOL_FLAGS = 0x0000_0000_0000_0002; // Don't zero-initialize allocation
+    pub const POOL_FLAG_SESSION: POOL_FLAGS = 0x0000_0000_0000_0004; // Use session specific pool
+    pub const POOL_FLAG_CACHE_ALIGNED: POOL_FLAGS = 0x0000_0000_0000_0008; // Cache aligned allocation
+    pub const POOL_FLAG_RESERVED1: POOL_FLAGS = 0x0000_0000_0000_0010; // Reserved for system use
+    pub const POOL_FLAG_RAISE_ON_FAILURE: POOL_FLAGS = 0x0000_0000_0000_0020; // Raise exception on failure
+    pub const POOL_FLAG_NON_PAGED: POOL_FLAGS = 0x0000_0000_0000_0040; // Non paged pool NX
+    pub const POOL_FLAG_NON_PAGED_EXECUTE: POOL_FLAGS = 0x0000_0000_0000_0080; // Non paged pool executable
+    pub const POOL_FLAG_PAGED: POOL_FLAGS = 0x0000_0000_0000_0100; // Paged pool
+    pub const POOL_FLAG_RESERVED2: POOL_FLAGS = 0x0000_0000_0000_0200; // Reserved for system use
+    pub const POOL_FLAG_RESERVED3: POOL_FLAGS = 0x0000_0000_0000_0400; // Reserved for system use
+    pub const POOL_FLAG_REQUIRED_END: POOL_FLAGS = 0x0000_0000_8000_0000;
+    pub const POOL_FLAG_OPTIONAL_START: POOL_FLAGS = 0x0000_0001_0000_0000;
+    pub const POOL_FLAG_SPECIAL_POOL: POOL_FLAGS = 0x0000_0001_0000_0000; // Make special pool allocation
+    pub const POOL_FLAG_OPTIONAL_END: POOL_FLAGS = 0x8000_0000_0000_0000;
+}
 
 // Due to linker issues with windows_sys, these definitions are manually
 // imported definitions from windows_sys::Win32::Foundation:
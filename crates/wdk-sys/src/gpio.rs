@@ -0,0 +1,21 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Direct FFI bindings to the GPIO framework extension (`GpioClx`) APIs from
+//! the Windows Driver Kit (WDK)
+//!
+//! GPIO controller drivers register the `CLIENT_*` callbacks of a
+//! [`GPIO_CLIENT_REGISTRATION_PACKET`] via [`GPIO_CLX_RegisterClient`] in
+//! `DriverEntry`. These bindings are only available to KMDF drivers.
+
+#[allow(missing_docs)]
+#[allow(clippy::unreadable_literal)]
+mod bindings {
+    // allow wildcards for types module since underlying c code relies on all
+    // type definitions being in scope
+    #[allow(clippy::wildcard_imports)]
+    use crate::types::{wdf_types::*, *};
+
+    include!(concat!(env!("OUT_DIR"), "/gpio.rs"));
+}
+pub use bindings::*;
@@ -8,26 +8,81 @@
 mod constants;
 mod types;
 
-pub use crate::{constants::*, types::*};
+// WDF types are also re-exported at the crate root for backwards compatibility
+// WDF USB types are re-exported at the crate root as well, so that they can be
+// used by `call_unsafe_wdf_function_binding`
+// WDM drivers do not use WDF, so none of the WDF items (types, function table
+// and `call_unsafe_wdf_function_binding`) are available to them
+#[cfg(all(feature = "usb", not(driver_type = "wdm")))]
+pub use crate::types::wdf_usb_types::*;
+pub use crate::{
+    constants::*,
+    ntstatus::{nt_error, nt_information, nt_success, nt_warning},
+    typed_constants::*,
+    types::*,
+};
+#[cfg(not(driver_type = "wdm"))]
+pub use crate::{struct_initializers::*, types::wdf_types::*};
 
+#[cfg(feature = "acpi")]
+pub mod acpi;
+#[cfg(feature = "cng")]
+pub mod cng;
+#[cfg(feature = "filesystem")]
+pub mod filesystem;
+#[cfg(all(feature = "gpio", driver_type = "kmdf"))]
+pub mod gpio;
+#[cfg(feature = "hid")]
+pub mod hid;
 pub mod macros;
+#[cfg(all(feature = "wdk-mock", not(driver_type = "wdm")))]
+pub mod mock;
+#[cfg(feature = "ndis")]
+pub mod ndis;
+#[cfg(all(feature = "netadaptercx", driver_type = "kmdf"))]
+pub mod netadaptercx;
+#[cfg(not(driver_type = "umdf"))]
 pub mod ntddk;
+pub mod ntstatus;
+#[cfg(feature = "parallel-ports")]
+pub mod parallel_ports;
+#[cfg(feature = "pep")]
+pub mod pep;
+pub mod prelude;
+#[cfg(feature = "spb")]
+pub mod spb;
+#[cfg(feature = "storage")]
+pub mod storage;
+#[cfg(not(driver_type = "wdm"))]
+pub mod struct_initializers;
+pub mod typed_constants;
+#[cfg(driver_type = "umdf")]
+pub mod umdf;
+#[cfg(feature = "usb")]
+pub mod usb;
+#[cfg(not(driver_type = "wdm"))]
 pub mod wdf;
+#[cfg(feature = "wdmsec")]
+pub mod wdmsec;
 
 #[cfg(feature = "test-stubs")]
 pub mod test_stubs;
 
+#[cfg(all(not(feature = "wdk-mock"), not(driver_type = "wdm")))]
 use lazy_static::lazy_static;
 
 // This is fine because we don't actually have any floating point instruction in
 // our binary, thanks to our target defining soft-floats. fltused symbol is
 // necessary due to LLVM being too eager to set it: it checks the LLVM IR for
-// floating point instructions - even if soft-float is enabled!
+// floating point instructions - even if soft-float is enabled! UMDF drivers
+// link against the CRT, which already defines it.
+#[cfg(not(driver_type = "umdf"))]
 #[allow(missing_docs)]
 #[no_mangle]
 pub static _fltused: () = ();
 
 // FIXME: Is there any way to avoid this stub? See https://github.com/rust-lang/rust/issues/101134
+#[cfg(not(driver_type = "umdf"))]
 #[allow(missing_docs)]
 #[allow(clippy::missing_const_for_fn)] // const extern is not yet supported: https://github.com/rust-lang/rust/issues/64926
 #[no_mangle]
@@ -35,21 +90,33 @@ pub extern "system" fn __CxxFrameHandler3() -> i32 {
     0
 }
 
-// FIXME: dynamically find name of this struct based off of wdk-build settings
+#[cfg(all(feature = "wdk-mock", not(driver_type = "wdm")))]
+pub use mock::WDF_FUNCTION_TABLE;
+
+#[cfg(all(not(feature = "wdk-mock"), not(driver_type = "wdm")))]
+extern "C" {
+    // The name of the WDF function table symbol depends on the selected WDF version
+    // (ex. `WdfFunctions_01033` for KMDF 1.33), so it is declared here instead
+    // of using the generated binding
+    #[link_name = env!("WDK_SYS_WDF_FUNCTION_TABLE_SYMBOL")]
+    static mut WdfFunctions: *const WDFFUNC;
+}
+
 // FIXME: replace lazy_static with std::Lazy once available: https://github.com/rust-lang/rust/issues/109736
+#[cfg(all(not(feature = "wdk-mock"), not(driver_type = "wdm")))]
 lazy_static! {
     #[allow(missing_docs)]
     pub static ref WDF_FUNCTION_TABLE: &'static [WDFFUNC] = {
-        // SAFETY: `WdfFunctions_01033` is generated as a mutable static, but is not supposed to be ever mutated by WDF.
-        let wdf_function_table = unsafe { WdfFunctions_01033 };
+        // SAFETY: `WdfFunctions` is declared as a mutable static, but is not supposed to be ever mutated by WDF.
+        let wdf_function_table = unsafe { WdfFunctions };
 
         // SAFETY: `WdfFunctionCount` is generated as a mutable static, but is not supposed to be ever mutated by WDF.
         let wdf_function_count = unsafe { WdfFunctionCount } as usize;
 
         // SAFETY: This is safe because:
-        //         1. `WdfFunctions_01033` is valid for reads for `WdfFunctionCount` * `core::mem::size_of::<WDFFUNC>()`
+        //         1. `WdfFunctions` is valid for reads for `WdfFunctionCount` * `core::mem::size_of::<WDFFUNC>()`
         //            bytes, and is guaranteed to be aligned and it must be properly aligned.
-        //         2. `WdfFunctions_01033` points to `WdfFunctionCount` consecutive properly initialized values of
+        //         2. `WdfFunctions` points to `WdfFunctionCount` consecutive properly initialized values of
         //            type `WDFFUNC`.
         //         3. WDF does not mutate the memory referenced by the returned slice for for its entire `'static' lifetime.
         //         4. The total size, `WdfFunctionCount` * `core::mem::size_of::<WDFFUNC>()`, of the slice must be no
@@ -61,11 +128,34 @@ pub extern "system" fn __CxxFrameHandler3() -> i32 {
     };
 }
 
-#[allow(missing_docs)]
+/// Returns `true` if `nt_status` is a success or informational status. See
+/// [`nt_success`].
 #[must_use]
 #[allow(non_snake_case)]
 pub const fn NT_SUCCESS(nt_status: NTSTATUS) -> bool {
-    nt_status >= 0
+    nt_success(nt_status)
+}
+
+/// Returns `true` if `nt_status` is an informational status. See
+/// [`nt_information`].
+#[must_use]
+#[allow(non_snake_case)]
+pub const fn NT_INFORMATION(nt_status: NTSTATUS) -> bool {
+    nt_information(nt_status)
+}
+
+/// Returns `true` if `nt_status` is a warning status. See [`nt_warning`].
+#[must_use]
+#[allow(non_snake_case)]
+pub const fn NT_WARNING(nt_status: NTSTATUS) -> bool {
+    nt_warning(nt_status)
+}
+
+/// Returns `true` if `nt_status` is an error status. See [`nt_error`].
+#[must_use]
+#[allow(non_snake_case)]
+pub const fn NT_ERROR(nt_status: NTSTATUS) -> bool {
+    nt_error(nt_status)
 }
 
 #[allow(missing_docs)]
@@ -5,4 +5,9 @@
 //! interacting with WDK apis which are inlined, and so are impossible to
 //! generate with [bindgen](https://docs.rs/bindgen/latest/bindgen/).
 
+// The WDF function table macros are not available to WDM drivers, which do not
+// link against WDF
+#[cfg(driver_type = "wdm")]
+pub use wdk_macros::driver_entry;
+#[cfg(not(driver_type = "wdm"))]
 pub use wdk_macros::*;
@@ -0,0 +1,136 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Mockable WDF function table for unit-testing on the host.
+//!
+//! Code calling WDF functions (ex. via
+//! [`call_unsafe_wdf_function_binding`](crate::call_unsafe_wdf_function_binding)
+//! or the safe wrappers in `wdk`) can be unit-tested without a kernel by
+//! mocking the WDF functions it calls.
+//!
+//! When the `wdk-mock` feature is enabled, [`WDF_FUNCTION_TABLE`] no longer
+//! refers to the function table provided by WDF. Instead, every WDF function
+//! call is dispatched through a function table that is set by the test via
+//! [`set_wdf_function`]. The mocked function table is thread-local, so tests
+//! executing in parallel do not interfere with each other, and functions
+//! mocked by a test are not visible to threads spawned by it. Calling a WDF
+//! function that has not been mocked panics.
+//!
+//! ```rust, no_run
+//! use wdk_sys::{
+//!     call_unsafe_wdf_function_binding,
+//!     mock,
+//!     _WDFFUNCENUM,
+//!     PFN_WDFSPINLOCKACQUIRE,
+//!     PWDF_DRIVER_GLOBALS,
+//!     WDFSPINLOCK,
+//! };
+//!
+//! unsafe extern "C" fn mock_spin_lock_acquire(
+//!     _driver_globals: PWDF_DRIVER_GLOBALS,
+//!     _spin_lock: WDFSPINLOCK,
+//! ) {
+//! }
+//!
+//! let mock_spin_lock_acquire: PFN_WDFSPINLOCKACQUIRE = Some(mock_spin_lock_acquire);
+//! // SAFETY: `mock_spin_lock_acquire` has the signature of `WdfSpinLockAcquire`
+//! unsafe {
+//!     mock::set_wdf_function(
+//!         _WDFFUNCENUM::WdfSpinLockAcquireTableIndex,
+//!         mock_spin_lock_acquire,
+//!     );
+//! }
+//!
+//! // SAFETY: `WdfSpinLockAcquire` is mocked, so the handle is never dereferenced
+//! unsafe {
+//!     call_unsafe_wdf_function_binding!(WdfSpinLockAcquire, core::ptr::null_mut());
+//! }
+//!
+//! mock::reset_wdf_functions();
+//! ```
+
+extern crate alloc;
+extern crate std;
+
+use alloc::{boxed::Box, vec};
+use core::{cell::Cell, ops::Deref};
+
+use crate::{_WDFFUNCENUM, PWDF_DRIVER_GLOBALS, WDFFUNC};
+
+/// Number of entries in the WDF function table
+#[allow(clippy::cast_sign_loss)]
+const WDF_FUNCTION_COUNT: usize = _WDFFUNCENUM::WdfFunctionTableNumEntries as usize;
+
+std::thread_local! {
+    /// Mocked WDF function table of the current thread. Every update leaks a new
+    /// table, so that references to the previous table stay valid.
+    static MOCKED_WDF_FUNCTIONS: Cell<&'static [WDFFUNC]> =
+        Cell::new(Box::leak(vec![None; WDF_FUNCTION_COUNT].into_boxed_slice()));
+}
+
+/// Mocked version of the WDF function table, which dispatches to the functions
+/// set via [`set_wdf_function`] on the current thread
+#[allow(missing_debug_implementations)]
+pub struct MockWdfFunctionTable {
+    _private: (),
+}
+
+impl Deref for MockWdfFunctionTable {
+    type Target = [WDFFUNC];
+
+    fn deref(&self) -> &Self::Target {
+        MOCKED_WDF_FUNCTIONS.with(Cell::get)
+    }
+}
+
+/// Mocked version of the WDF function table. See the [module
+/// documentation](self) for how to mock WDF functions.
+pub static WDF_FUNCTION_TABLE: MockWdfFunctionTable = MockWdfFunctionTable { _private: () };
+
+/// Stubbed version of the `WdfDriverGlobals` symbol, which is passed as the
+/// first argument of every mocked WDF function
+#[no_mangle]
+pub static mut WdfDriverGlobals: PWDF_DRIVER_GLOBALS = core::ptr::null_mut();
+
+/// Mock the WDF function at `function_table_index` (ex.
+/// `_WDFFUNCENUM::WdfSpinLockAcquireTableIndex`) with `function` on the
+/// current thread.
+///
+/// `function` is the generated function pointer type of the WDF function (ex.
+/// [`PFN_WDFSPINLOCKACQUIRE`](crate::PFN_WDFSPINLOCKACQUIRE)).
+///
+/// # Safety
+///
+/// `function` must have the signature of the WDF function at
+/// `function_table_index`, since WDF function calls transmute the function
+/// table entry to that signature.
+///
+/// # Panics
+///
+/// Panics if `function_table_index` is not a valid index of the WDF function
+/// table, or if `F` is not the size of a function pointer.
+pub unsafe fn set_wdf_function<F: Copy>(function_table_index: _WDFFUNCENUM::Type, function: F) {
+    assert_eq!(
+        core::mem::size_of::<F>(),
+        core::mem::size_of::<WDFFUNC>(),
+        "mocked WDF functions must be function pointers"
+    );
+    // SAFETY: `F` is the same size as `WDFFUNC`, and the caller guarantees that it
+    // is a function pointer type that is called with the correct signature
+    let function = unsafe { core::mem::transmute_copy::<F, WDFFUNC>(&function) };
+
+    let function_table_index = usize::try_from(function_table_index)
+        .expect("WDF function table index should not be negative");
+    MOCKED_WDF_FUNCTIONS.with(|mocked_wdf_functions| {
+        let mut updated_wdf_functions = mocked_wdf_functions.get().to_vec();
+        updated_wdf_functions[function_table_index] = function;
+        mocked_wdf_functions.set(Box::leak(updated_wdf_functions.into_boxed_slice()));
+    });
+}
+
+/// Remove all WDF functions mocked on the current thread
+pub fn reset_wdf_functions() {
+    MOCKED_WDF_FUNCTIONS.with(|mocked_wdf_functions| {
+        mocked_wdf_functions.set(Box::leak(vec![None; WDF_FUNCTION_COUNT].into_boxed_slice()));
+    });
+}
@@ -0,0 +1,7 @@
+/* Copyright (c) Microsoft Corporation
+   License: MIT OR Apache-2.0 */
+
+#include "ntifs.h"
+#include "ntddk.h"
+#include "wdf.h"
+#include "netadaptercx.h"
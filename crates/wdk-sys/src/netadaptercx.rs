@@ -0,0 +1,50 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Direct FFI bindings to the Network Adapter WDF Class Extension
+//! (`NetAdapterCx`) APIs from the Windows Driver Kit (WDK)
+//!
+//! Like the WDF functions, the `NetAdapterCx` functions (ex.
+//! `NetAdapterCreate`) are inlined in the `NetCx` headers, and call through the
+//! `NetAdapterCx` function table instead. Their function pointer types (ex.
+//! [`PFN_NETADAPTERCREATE`]) and table indices (ex.
+//! [`_NETFUNCENUM::NetAdapterCreateTableIndex`]) are generated, and the
+//! function pointers are looked up via [`net_function`]. Every function is
+//! called with [`net_driver_globals`] as its first argument.
+//!
+//! These bindings are only available to KMDF drivers.
+
+#[allow(missing_docs)]
+#[allow(clippy::unreadable_literal)]
+mod bindings {
+    // allow wildcards for types module since underlying c code relies on all
+    // type definitions being in scope
+    #[allow(clippy::wildcard_imports)]
+    use crate::types::{wdf_types::*, *};
+
+    include!(concat!(env!("OUT_DIR"), "/netadaptercx.rs"));
+}
+pub use bindings::*;
+
+/// Returns the entry at `table_index` of the `NetAdapterCx` function table.
+/// This mirrors the lookup in the inlined `NetAdapterCx` functions in the
+/// `NetCx` headers (ex. `netadapter.h`).
+///
+/// The returned function pointer must be transmuted to the function pointer
+/// type that corresponds to `table_index` before it is called.
+#[must_use]
+pub fn net_function(table_index: _NETFUNCENUM::Type) -> NETFUNC {
+    // SAFETY: `NetFunctions` is declared as a mutable static, but is not supposed to
+    // be ever mutated by NetAdapterCx after it binds to the driver.
+    unsafe { NetFunctions[table_index as usize] }
+}
+
+/// Returns the driver globals that `NetAdapterCx` assigned to the driver when
+/// binding to it, which are passed as the first argument of every
+/// `NetAdapterCx` function
+#[must_use]
+pub fn net_driver_globals() -> PNET_DRIVER_GLOBALS {
+    // SAFETY: `NetDriverGlobals` is declared as a mutable static, but is not
+    // supposed to be ever mutated by NetAdapterCx after it binds to the driver.
+    unsafe { NetDriverGlobals }
+}
@@ -9,3 +9,34 @@
 use crate::types::*;
 
 include!(concat!(env!("OUT_DIR"), "/ntddk.rs"));
+
+/// Equivalent of the `ExInitializeFastMutex` function in `wdm.h`, which is
+/// defined inline and therefore has no bindings generated for it.
+///
+/// # Safety
+///
+/// `FastMutex` must point to memory that is valid for writes of a
+/// [`FAST_MUTEX`], that is not in use by another thread, and that will not be
+/// moved for as long as the fast mutex is in use.
+#[allow(non_snake_case)]
+pub unsafe fn ExInitializeFastMutex(FastMutex: PFAST_MUTEX) {
+    // SAFETY: The caller guarantees that `FastMutex` is valid for writes.
+    unsafe {
+        FastMutex.write(FAST_MUTEX {
+            // FM_LOCK_BIT is a small positive constant
+            #[allow(clippy::cast_possible_wrap)]
+            Count: crate::FM_LOCK_BIT as LONG,
+            ..FAST_MUTEX::default()
+        });
+    }
+
+    // SAFETY: The caller guarantees that `FastMutex` is valid, and no reference to
+    // the event is created.
+    let event = unsafe { core::ptr::addr_of_mut!((*FastMutex).Event) };
+
+    // SAFETY: The caller guarantees that `FastMutex` will not be moved, and its
+    // event was zero-initialized above.
+    unsafe {
+        KeInitializeEvent(event, _EVENT_TYPE::SynchronizationEvent, 0);
+    }
+}
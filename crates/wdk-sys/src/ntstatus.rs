@@ -0,0 +1,82 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Helpers for classifying [`NTSTATUS`] values, and the most commonly used
+//! `STATUS_*` constants.
+//!
+//! These are ports of the `NT_SUCCESS`, `NT_INFORMATION`, `NT_WARNING` and
+//! `NT_ERROR` macros from `ntdef.h`, which bindgen does not generate. Since
+//! they are `const fn`s, they can also be used to define constants. The
+//! constants in this module are the same as the ones at the crate root, and
+//! are only collected here so that they can be imported without the
+//! thousands of other `STATUS_*` constants.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use wdk_sys::ntstatus::{self, nt_error, nt_success};
+//!
+//! const _: () = assert!(nt_success(ntstatus::STATUS_PENDING));
+//! const _: () = assert!(nt_error(ntstatus::STATUS_INVALID_PARAMETER));
+//! ```
+
+pub use crate::constants::{
+    STATUS_ACCESS_DENIED,
+    STATUS_BUFFER_OVERFLOW,
+    STATUS_BUFFER_TOO_SMALL,
+    STATUS_CANCELLED,
+    STATUS_DELETE_PENDING,
+    STATUS_DEVICE_BUSY,
+    STATUS_DEVICE_NOT_READY,
+    STATUS_DEVICE_REMOVED,
+    STATUS_INSUFFICIENT_RESOURCES,
+    STATUS_INTEGER_OVERFLOW,
+    STATUS_INVALID_BUFFER_SIZE,
+    STATUS_INVALID_DEVICE_REQUEST,
+    STATUS_INVALID_DEVICE_STATE,
+    STATUS_INVALID_PARAMETER,
+    STATUS_MORE_PROCESSING_REQUIRED,
+    STATUS_NOT_FOUND,
+    STATUS_NOT_IMPLEMENTED,
+    STATUS_NOT_SUPPORTED,
+    STATUS_NO_MEMORY,
+    STATUS_NO_MORE_ENTRIES,
+    STATUS_OBJECT_NAME_NOT_FOUND,
+    STATUS_PENDING,
+    STATUS_SUCCESS,
+    STATUS_TIMEOUT,
+    STATUS_UNSUCCESSFUL,
+};
+use crate::NTSTATUS;
+
+/// Severity of a [`NTSTATUS`], stored in its two most significant bits
+#[allow(clippy::cast_sign_loss)]
+const fn severity(nt_status: NTSTATUS) -> u32 {
+    (nt_status as u32) >> 30
+}
+
+/// Returns `true` if `nt_status` is a success or informational status
+/// (`NT_SUCCESS`)
+#[must_use]
+pub const fn nt_success(nt_status: NTSTATUS) -> bool {
+    nt_status >= 0
+}
+
+/// Returns `true` if `nt_status` is an informational status
+/// (`NT_INFORMATION`)
+#[must_use]
+pub const fn nt_information(nt_status: NTSTATUS) -> bool {
+    severity(nt_status) == 1
+}
+
+/// Returns `true` if `nt_status` is a warning status (`NT_WARNING`)
+#[must_use]
+pub const fn nt_warning(nt_status: NTSTATUS) -> bool {
+    severity(nt_status) == 2
+}
+
+/// Returns `true` if `nt_status` is an error status (`NT_ERROR`)
+#[must_use]
+pub const fn nt_error(nt_status: NTSTATUS) -> bool {
+    severity(nt_status) == 3
+}
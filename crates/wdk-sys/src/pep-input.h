@@ -0,0 +1,11 @@
+/* Copyright (c) Microsoft Corporation
+   License: MIT OR Apache-2.0 */
+
+#include "ntifs.h"
+#include "ntddk.h"
+#include "pepfx.h"
+
+// pepevents.h is not shipped by every WDK version
+#if __has_include("pepevents.h")
+#include "pepevents.h"
+#endif
@@ -0,0 +1,24 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Direct FFI bindings to the Power Engine Plugin (PEP) APIs from `pepfx.h`
+//! and `pepevents.h` in the Windows Driver Kit (WDK)
+//!
+//! These include the registration of a PEP with the Windows power management
+//! framework (ex. [`PoFxRegisterPlugin`] and [`PoFxRegisterPluginEx`]), and the
+//! notifications and payloads it exchanges with the framework for device power
+//! management (`PEP_DPM_*`), processor power management (`PEP_PPM_*`) and ACPI
+//! (`PEP_ACPI_*`). They are meant for SoC platform drivers, and are only
+//! available to kernel-mode drivers.
+
+#[allow(missing_docs)]
+#[allow(clippy::unreadable_literal)]
+mod bindings {
+    // allow wildcards for types module since underlying c code relies on all
+    // type definitions being in scope
+    #[allow(clippy::wildcard_imports)]
+    use crate::types::*;
+
+    include!(concat!(env!("OUT_DIR"), "/pep.rs"));
+}
+pub use bindings::*;
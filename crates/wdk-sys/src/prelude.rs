@@ -0,0 +1,27 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Re-exports of the bindings that are available to every driver, regardless of
+//! enabled Cargo features: WDK constants and types (including WDF types, except
+//! for WDM drivers), as well as the `ntddk` function bindings (or the `umdf`
+//! function bindings for UMDF drivers).
+//!
+//! The function bindings generated from the WDF headers are not re-exported
+//! here, since they redeclare functions that are already part of the `ntddk`
+//! bindings (ex. `RtlAssert`).
+//!
+//! Subsystem specific bindings (ex. `wdk_sys::hid`) are not re-exported
+//! here, since they can contain their own definitions of types that are
+//! already defined in the core bindings. They should be imported from their
+//! own modules instead.
+
+#[cfg(not(driver_type = "umdf"))]
+pub use crate::ntddk::*;
+#[cfg(driver_type = "umdf")]
+pub use crate::umdf::*;
+pub use crate::{constants::*, types::*};
+#[cfg(not(driver_type = "wdm"))]
+pub use crate::{
+    types::wdf_types::*,
+    wdf::{WDF_MAJOR_VERSION, WDF_MINOR_VERSION},
+};
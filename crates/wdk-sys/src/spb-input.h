@@ -0,0 +1,8 @@
+/* Copyright (c) Microsoft Corporation
+   License: MIT OR Apache-2.0 */
+
+#include "ntifs.h"
+#include "ntddk.h"
+#include "wdf.h"
+#include "spb.h"
+#include "spbcx.h"
@@ -0,0 +1,52 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Direct FFI bindings to SPB (Simple Peripheral Bus) APIs from the Windows
+//! Driver Kit (WDK)
+//!
+//! Like the WDF functions, the SPB framework extension (`SpbCx`) functions
+//! (ex. `SpbRequestComplete`) are inlined in `spbcx.h`, and call through the
+//! `SpbCx` function table instead. Their function pointer types (ex.
+//! [`PFN_SPBREQUESTCOMPLETE`]) and table indices (ex.
+//! [`_SPBFUNCENUM::SpbRequestCompleteTableIndex`]) are generated, and the
+//! function pointers are looked up via [`spb_function`]. Every function is
+//! called with [`spb_driver_globals`] as its first argument. These functions
+//! are only available to KMDF drivers.
+
+#[allow(missing_docs)]
+#[allow(clippy::unreadable_literal)]
+mod bindings {
+    // allow wildcards for types module since underlying c code relies on all
+    // type definitions being in scope
+    #[cfg(not(driver_type = "wdm"))]
+    #[allow(clippy::wildcard_imports)]
+    use crate::types::wdf_types::*;
+    #[allow(clippy::wildcard_imports)]
+    use crate::types::*;
+
+    include!(concat!(env!("OUT_DIR"), "/spb.rs"));
+}
+pub use bindings::*;
+
+/// Returns the entry at `table_index` of the `SpbCx` function table. This
+/// mirrors the lookup in the inlined `SpbCx` functions in `spbcx.h`.
+///
+/// The returned function pointer must be transmuted to the function pointer
+/// type that corresponds to `table_index` before it is called.
+#[cfg(driver_type = "kmdf")]
+#[must_use]
+pub fn spb_function(table_index: _SPBFUNCENUM::Type) -> SPBFUNC {
+    // SAFETY: `SpbFunctions` is declared as a mutable static, but is not supposed to
+    // be ever mutated by SpbCx after it binds to the driver.
+    unsafe { SpbFunctions[table_index as usize] }
+}
+
+/// Returns the driver globals that `SpbCx` assigned to the driver when binding
+/// to it, which are passed as the first argument of every `SpbCx` function
+#[cfg(driver_type = "kmdf")]
+#[must_use]
+pub fn spb_driver_globals() -> PSPB_DRIVER_GLOBALS {
+    // SAFETY: `SpbDriverGlobals` is declared as a mutable static, but is not
+    // supposed to be ever mutated by SpbCx after it binds to the driver.
+    unsafe { SpbDriverGlobals }
+}
@@ -0,0 +1,11 @@
+/* Copyright (c) Microsoft Corporation
+   License: MIT OR Apache-2.0 */
+
+#include "ntifs.h"
+#include "ntddk.h"
+// storport.h must be included before srb.h, since it replaces the SCSI port
+// definitions from srb.h with their Storport equivalents
+#include "storport.h"
+#include "srb.h"
+#include "ntddscsi.h"
+#include "ntdddisk.h"
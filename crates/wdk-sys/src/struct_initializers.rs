@@ -0,0 +1,96 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Rust equivalents of the `WDF_*_INIT` macros, which set the `Size` field of
+//! WDF structs.
+//!
+//! Every WDF struct whose first field is `Size` gets a `SIZE` associated
+//! constant and a `const fn with_size()` constructor, generated by the build
+//! script via [`wdk_build::struct_initializers`]. Structs whose `WDF_*_INIT`
+//! macro also sets other fields get dedicated constructors (ex.
+//! [`WDF_DRIVER_CONFIG::init`]).
+//!
+//! ```rust, no_run
+//! use wdk_sys::{WDF_OBJECT_ATTRIBUTES, WDF_TIMER_CONFIG};
+//!
+//! let timer_config = WDF_TIMER_CONFIG {
+//!     Period: 1000,
+//!     ..WDF_TIMER_CONFIG::with_size()
+//! };
+//! let attributes = WDF_OBJECT_ATTRIBUTES::init();
+//! ```
+//!
+//! [`wdk_build::struct_initializers`]: https://docs.rs/wdk-build/latest/wdk_build/struct_initializers/index.html
+
+#[allow(clippy::wildcard_imports)]
+use crate::types::wdf_types::*;
+
+mod wdf {
+    #[allow(clippy::wildcard_imports)]
+    use crate::types::{wdf_types::*, *};
+
+    include!(concat!(env!("OUT_DIR"), "/wdf_struct_initializers.rs"));
+}
+
+#[cfg(feature = "usb")]
+mod wdf_usb {
+    #[allow(clippy::wildcard_imports)]
+    use crate::types::{wdf_usb_types::*, *};
+
+    include!(concat!(env!("OUT_DIR"), "/wdf_usb_struct_initializers.rs"));
+}
+
+/// A type that WDF can allocate as the context space of a WDF object.
+///
+/// This is the equivalent of a type declared via `WDF_DECLARE_CONTEXT_TYPE` in
+/// C, for use with [`WDF_OBJECT_ATTRIBUTES::init_context_type`].
+///
+/// # Safety
+///
+/// [`WdfObjectContextType::context_type_info`] must always return a pointer to
+/// the same `'static` [`WDF_OBJECT_CONTEXT_TYPE_INFO`] describing `Self`, and
+/// that type info must not be used for any other type.
+pub unsafe trait WdfObjectContextType {
+    /// Get a pointer to the [`WDF_OBJECT_CONTEXT_TYPE_INFO`] describing this
+    /// context type
+    fn context_type_info() -> PCWDF_OBJECT_CONTEXT_TYPE_INFO;
+}
+
+impl WDF_DRIVER_CONFIG {
+    /// Creates a [`WDF_DRIVER_CONFIG`] for a driver whose devices are added
+    /// via `evt_driver_device_add`. This is equivalent to
+    /// `WDF_DRIVER_CONFIG_INIT` in C.
+    #[must_use]
+    #[inline]
+    pub const fn init(evt_driver_device_add: PFN_WDF_DRIVER_DEVICE_ADD) -> Self {
+        let mut driver_config = Self::with_size();
+        driver_config.EvtDriverDeviceAdd = evt_driver_device_add;
+        driver_config
+    }
+}
+
+impl WDF_OBJECT_ATTRIBUTES {
+    /// Creates [`WDF_OBJECT_ATTRIBUTES`] that inherit their execution level
+    /// and synchronization scope from the parent object. This is equivalent to
+    /// `WDF_OBJECT_ATTRIBUTES_INIT` in C.
+    #[must_use]
+    #[inline]
+    pub const fn init() -> Self {
+        let mut attributes = Self::with_size();
+        attributes.ExecutionLevel = _WDF_EXECUTION_LEVEL::WdfExecutionLevelInheritFromParent;
+        attributes.SynchronizationScope =
+            _WDF_SYNCHRONIZATION_SCOPE::WdfSynchronizationScopeInheritFromParent;
+        attributes
+    }
+
+    /// Creates [`WDF_OBJECT_ATTRIBUTES`] that allocate context space for `T`
+    /// when used to create a WDF object. This is equivalent to
+    /// `WDF_OBJECT_ATTRIBUTES_INIT_CONTEXT_TYPE` in C.
+    #[must_use]
+    #[inline]
+    pub fn init_context_type<T: WdfObjectContextType>() -> Self {
+        let mut attributes = Self::init();
+        attributes.ContextTypeInfo = T::context_type_info();
+        attributes
+    }
+}
@@ -6,7 +6,9 @@
 //! into scope by introducing `wdk-sys` with the `test-stubs` feature in the
 //! `dev-dependencies` of the crate's `Cargo.toml`
 
-use crate::{DRIVER_OBJECT, NTSTATUS, PCUNICODE_STRING, ULONG, WDFFUNC};
+use crate::{DRIVER_OBJECT, NTSTATUS, PCUNICODE_STRING};
+#[cfg(not(driver_type = "wdm"))]
+use crate::{ULONG, WDFFUNC};
 
 /// Stubbed version of `DriverEntry` Symbol so that test targets will compile
 ///
@@ -21,12 +23,14 @@
     0
 }
 
-/// Stubbed version of `WdfFunctions_01033` Symbol so that test targets will
-/// compile
-#[no_mangle]
-pub static mut WdfFunctions_01033: *const WDFFUNC = core::ptr::null();
+/// Stubbed version of the `WdfFunctions_<version>` Symbol (ex.
+/// `WdfFunctions_01033` for KMDF 1.33) so that test targets will compile
+#[cfg(not(driver_type = "wdm"))]
+#[export_name = env!("WDK_SYS_WDF_FUNCTION_TABLE_SYMBOL")]
+pub static mut WDF_FUNCTIONS_STUB: *const WDFFUNC = core::ptr::null();
 
 /// Stubbed version of `WdfFunctionCount` Symbol so that test targets will
 /// compile
+#[cfg(not(driver_type = "wdm"))]
 #[no_mangle]
 pub static mut WdfFunctionCount: ULONG = 0;
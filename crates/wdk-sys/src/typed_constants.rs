@@ -0,0 +1,32 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Typed wrappers for families of constants, ex. [`PoolFlags`] for the
+//! `POOL_FLAG_*` constants.
+//!
+//! The wrappers are generated by the build script from the constants that are
+//! present in the generated bindings, via [`wdk_build::typed_constants`]. Each
+//! wrapper is a `#[repr(transparent)]` newtype over the raw type, and can be
+//! converted to and from it via [`From`], so it can be passed to any API that
+//! takes the raw type. The raw constants remain available at the crate root.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use wdk_sys::{PoolFlags, POOL_FLAGS};
+//!
+//! let pool_flags = PoolFlags::NON_PAGED | PoolFlags::UNINITIALIZED;
+//! assert!(pool_flags.contains(PoolFlags::NON_PAGED));
+//!
+//! let raw_pool_flags: POOL_FLAGS = pool_flags.into();
+//! ```
+//!
+//! [`wdk_build::typed_constants`]: https://docs.rs/wdk-build/latest/wdk_build/typed_constants/index.html
+
+#[allow(clippy::unnecessary_cast)]
+#[allow(clippy::cast_possible_truncation)]
+#[allow(clippy::cast_sign_loss)]
+mod bindings {
+    include!(concat!(env!("OUT_DIR"), "/typed_constants.rs"));
+}
+pub use bindings::*;
@@ -57,5 +57,38 @@
 #[allow(clippy::use_self)]
 mod bindings {
     include!(concat!(env!("OUT_DIR"), "/types.rs"));
+
+    /// Types defined in the WDF headers. These are exposed via [`crate::wdf`],
+    /// and are not generated for WDM drivers.
+    // pub(crate) prevents the module itself from being re-exported by the glob
+    // re-exports of this module
+    #[cfg(not(driver_type = "wdm"))]
+    #[allow(clippy::redundant_pub_crate)]
+    pub(crate) mod wdf_types {
+        // allow wildcards for types module since underlying c code relies on all
+        // type definitions being in scope
+        // Defined in wdfusb.h, but referenced by wdfrequest.h
+        #[cfg(feature = "usb")]
+        use super::wdf_usb_types::_WDF_USB_REQUEST_COMPLETION_PARAMS;
+        #[allow(clippy::wildcard_imports)]
+        use super::*;
+
+        include!(concat!(env!("OUT_DIR"), "/wdf_types.rs"));
+    }
+
+    /// Types defined in WDF's USB header (`wdfusb.h`). These are exposed via
+    /// [`crate::wdf`].
+    #[cfg(all(feature = "usb", not(driver_type = "wdm")))]
+    #[allow(clippy::redundant_pub_crate)]
+    pub(crate) mod wdf_usb_types {
+        // allow wildcards for types module since underlying c code relies on all
+        // type definitions being in scope
+        #[allow(clippy::wildcard_imports)]
+        use super::{wdf_types::*, *};
+        #[allow(clippy::wildcard_imports)]
+        use crate::usb::*;
+
+        include!(concat!(env!("OUT_DIR"), "/wdf_usb_types.rs"));
+    }
 }
 pub use bindings::*;
@@ -0,0 +1,6 @@
+/* Copyright (c) Microsoft Corporation
+   License: MIT OR Apache-2.0 */
+
+// UMDF drivers are user-mode DLLs, so they are built against the Win32 headers
+// instead of ntifs.h and ntddk.h
+#include "windows.h"
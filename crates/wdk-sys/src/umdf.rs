@@ -0,0 +1,15 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Direct FFI bindings to the Win32 APIs available to UMDF drivers, which are
+//! generated instead of [`ntddk`](crate::ntddk) when building a UMDF driver
+//! (ie. with `driver_type = "umdf"`)
+
+#![allow(missing_docs)]
+
+// allow wildcards for types module since underlying c code relies on all
+// type definitions being in scope
+#[allow(clippy::wildcard_imports)]
+use crate::types::*;
+
+include!(concat!(env!("OUT_DIR"), "/umdf.rs"));
@@ -1,6 +1,10 @@
 /* Copyright (c) Microsoft Corporation
    License: MIT OR Apache-2.0 */
 
+#if defined(UMDF_VERSION_MAJOR)
+#include "umdf-input.h"
+#else
 #include "ntifs.h"
 #include "ntddk.h"
+#endif
 #include "wdf.h"
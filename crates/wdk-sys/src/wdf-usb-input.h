@@ -0,0 +1,9 @@
+/* Copyright (c) Microsoft Corporation
+   License: MIT OR Apache-2.0 */
+
+#include "ntifs.h"
+#include "ntddk.h"
+#include "usb.h"
+#include "usbdlib.h"
+#include "wdf.h"
+#include "wdfusb.h"
@@ -17,7 +17,28 @@ mod bindings {
 }
 pub use bindings::*;
 
+pub use crate::types::wdf_types::*;
+#[cfg(feature = "usb")]
+pub use crate::types::wdf_usb_types::*;
+
+/// Major version of the WDF framework (KMDF or UMDF) that these bindings were
+/// generated for. This is selected via the `kmdf-*` and `umdf-*` Cargo
+/// features.
+pub const WDF_MAJOR_VERSION: ULONG = parse_version_component(env!("WDK_SYS_WDF_MAJOR_VERSION"));
+
+/// Minor version of the WDF framework (KMDF or UMDF) that these bindings were
+/// generated for. This is selected via the `kmdf-*` and `umdf-*` Cargo
+/// features.
+pub const WDF_MINOR_VERSION: ULONG = parse_version_component(env!("WDK_SYS_WDF_MINOR_VERSION"));
+
 // FIXME: UMDF >= 2.25 & KMDF >= 1.25 define this in wdffuncenum with
 // _declspec(selectany) so they don't generate symbols
 #[no_mangle]
-static WdfMinimumVersionRequired: ULONG = 33;
+static WdfMinimumVersionRequired: ULONG = WDF_MINOR_VERSION;
+
+const fn parse_version_component(version_component: &str) -> ULONG {
+    match ULONG::from_str_radix(version_component, 10) {
+        Ok(value) => value,
+        Err(_) => panic!("wdk-sys build script should export numeric WDF version components"),
+    }
+}
@@ -0,0 +1,23 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Direct FFI bindings to the device object security APIs from `wdmsec.h` in
+//! the Windows Driver Kit (WDK)
+//!
+//! These include [`IoCreateDeviceSecure`], which creates a device object whose
+//! security descriptor is described by an SDDL string, and the well-known SDDL
+//! strings for device objects (ex. [`SDDL_DEVOBJ_SYS_ALL_ADM_ALL`]), which are
+//! defined in `wdmsec.lib`. Enabling this feature links kernel-mode drivers
+//! against `wdmsec.lib`.
+
+#[allow(missing_docs)]
+#[allow(clippy::unreadable_literal)]
+mod bindings {
+    // allow wildcards for types module since underlying c code relies on all
+    // type definitions being in scope
+    #[allow(clippy::wildcard_imports)]
+    use crate::types::*;
+
+    include!(concat!(env!("OUT_DIR"), "/wdmsec.rs"));
+}
+pub use bindings::*;
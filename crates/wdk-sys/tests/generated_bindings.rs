@@ -0,0 +1,235 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Golden tests for items of the generated bindings that downstream crates
+//! depend on (ex. `wdk-macros` resolves WDF functions via the indices of
+//! `_WDFFUNCENUM` and the signatures of their `PFN_*` types).
+//!
+//! The expected values are taken from the WDK headers, instead of from the
+//! generated bindings, so that a `bindgen` or WDK regression that changes or
+//! removes one of these items fails here, with every WDK version that the
+//! tests run against, rather than in downstream crates. Items whose shape is
+//! only checked by the compiler are asserted by assigning them to a binding of
+//! the expected type.
+
+#[cfg(not(driver_type = "umdf"))]
+mod wdm {
+    use wdk_sys::{
+        DRIVER_OBJECT,
+        IRP_MJ_CREATE,
+        IRP_MJ_DEVICE_CONTROL,
+        IRP_MJ_MAXIMUM_FUNCTION,
+        NTSTATUS,
+        PDEVICE_OBJECT,
+        PDRIVER_DISPATCH,
+        PDRIVER_UNLOAD,
+        PIRP,
+    };
+
+    #[test]
+    fn irp_major_function_codes() {
+        assert_eq!(IRP_MJ_CREATE, 0x00);
+        assert_eq!(IRP_MJ_DEVICE_CONTROL, 0x0E);
+        assert_eq!(IRP_MJ_MAXIMUM_FUNCTION, 0x1B);
+    }
+
+    #[test]
+    fn driver_object_dispatch_routines() {
+        let driver_object = DRIVER_OBJECT::default();
+        let major_function: [PDRIVER_DISPATCH; IRP_MJ_MAXIMUM_FUNCTION as usize + 1] =
+            driver_object.MajorFunction;
+        let driver_unload: PDRIVER_UNLOAD = driver_object.DriverUnload;
+
+        assert!(major_function.iter().all(Option::is_none));
+        assert!(driver_unload.is_none());
+
+        let _: PDRIVER_DISPATCH = None::<unsafe extern "C" fn(PDEVICE_OBJECT, PIRP) -> NTSTATUS>;
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn driver_object_layout() {
+        assert_eq!(core::mem::offset_of!(DRIVER_OBJECT, DeviceObject), 8);
+        assert_eq!(core::mem::offset_of!(DRIVER_OBJECT, DriverExtension), 48);
+        assert_eq!(core::mem::offset_of!(DRIVER_OBJECT, DriverName), 56);
+        assert_eq!(core::mem::offset_of!(DRIVER_OBJECT, DriverUnload), 104);
+        assert_eq!(core::mem::offset_of!(DRIVER_OBJECT, MajorFunction), 112);
+        assert_eq!(core::mem::size_of::<DRIVER_OBJECT>(), 336);
+    }
+}
+
+mod ioctl {
+    use wdk_sys::{
+        FILE_ANY_ACCESS,
+        FILE_DEVICE_UNKNOWN,
+        FILE_READ_ACCESS,
+        FILE_WRITE_ACCESS,
+        METHOD_BUFFERED,
+        METHOD_IN_DIRECT,
+        METHOD_NEITHER,
+        METHOD_OUT_DIRECT,
+    };
+
+    /// Equivalent to the `CTL_CODE` function-like macro, which `bindgen`
+    /// cannot evaluate
+    const fn ctl_code(device_type: u32, function: u32, method: u32, access: u32) -> u32 {
+        (device_type << 16) | (access << 14) | (function << 2) | method
+    }
+
+    #[test]
+    fn ctl_code_components() {
+        assert_eq!(FILE_DEVICE_UNKNOWN, 0x22);
+        assert_eq!(METHOD_BUFFERED, 0);
+        assert_eq!(METHOD_IN_DIRECT, 1);
+        assert_eq!(METHOD_OUT_DIRECT, 2);
+        assert_eq!(METHOD_NEITHER, 3);
+        assert_eq!(FILE_ANY_ACCESS, 0);
+        assert_eq!(FILE_READ_ACCESS, 1);
+        assert_eq!(FILE_WRITE_ACCESS, 2);
+
+        assert_eq!(
+            ctl_code(FILE_DEVICE_UNKNOWN, 0x800, METHOD_BUFFERED, FILE_ANY_ACCESS),
+            0x0022_2000
+        );
+    }
+
+    #[cfg(feature = "acpi")]
+    #[test]
+    fn acpi_ioctls() {
+        use wdk_sys::acpi::{
+            IOCTL_ACPI_ACQUIRE_GLOBAL_LOCK,
+            IOCTL_ACPI_ASYNC_EVAL_METHOD,
+            IOCTL_ACPI_ASYNC_EVAL_METHOD_EX,
+            IOCTL_ACPI_EVAL_METHOD,
+            IOCTL_ACPI_EVAL_METHOD_EX,
+            IOCTL_ACPI_RELEASE_GLOBAL_LOCK,
+        };
+
+        assert_eq!(IOCTL_ACPI_ASYNC_EVAL_METHOD, 0x0032_C000);
+        assert_eq!(IOCTL_ACPI_EVAL_METHOD, 0x0032_C004);
+        assert_eq!(IOCTL_ACPI_ACQUIRE_GLOBAL_LOCK, 0x0032_C010);
+        assert_eq!(IOCTL_ACPI_RELEASE_GLOBAL_LOCK, 0x0032_C014);
+        assert_eq!(IOCTL_ACPI_EVAL_METHOD_EX, 0x0032_C018);
+        assert_eq!(IOCTL_ACPI_ASYNC_EVAL_METHOD_EX, 0x0032_C01C);
+    }
+}
+
+#[cfg(driver_type = "kmdf")]
+mod kmdf {
+    use wdk_sys::{
+        _WDFFUNCENUM,
+        NTSTATUS,
+        PCUNICODE_STRING,
+        PDRIVER_OBJECT,
+        PFN_WDFDEVICECREATE,
+        PFN_WDFDRIVERCREATE,
+        PFN_WDFIOQUEUECREATE,
+        PFN_WDFREQUESTCOMPLETE,
+        PWDFDEVICE_INIT,
+        PWDF_DRIVER_CONFIG,
+        PWDF_DRIVER_GLOBALS,
+        PWDF_IO_QUEUE_CONFIG,
+        PWDF_OBJECT_ATTRIBUTES,
+        WDFDEVICE,
+        WDFDRIVER,
+        WDFQUEUE,
+        WDFREQUEST,
+    };
+
+    /// The WDF function table only ever grows, so the indices of existing
+    /// functions are the same in every KMDF version
+    #[test]
+    fn function_table_indices() {
+        assert_eq!(_WDFFUNCENUM::WdfDeviceCreateTableIndex, 75);
+        assert_eq!(_WDFFUNCENUM::WdfDriverCreateTableIndex, 116);
+        assert_eq!(_WDFFUNCENUM::WdfIoQueueCreateTableIndex, 152);
+        assert_eq!(_WDFFUNCENUM::WdfObjectGetTypedContextWorkerTableIndex, 202);
+        assert_eq!(_WDFFUNCENUM::WdfRequestCompleteTableIndex, 263);
+        assert!(
+            _WDFFUNCENUM::WdfFunctionTableNumEntries > _WDFFUNCENUM::WdfRequestCompleteTableIndex
+        );
+    }
+
+    #[test]
+    fn function_signatures() {
+        let _: PFN_WDFDRIVERCREATE = None::<
+            unsafe extern "C" fn(
+                PWDF_DRIVER_GLOBALS,
+                PDRIVER_OBJECT,
+                PCUNICODE_STRING,
+                PWDF_OBJECT_ATTRIBUTES,
+                PWDF_DRIVER_CONFIG,
+                *mut WDFDRIVER,
+            ) -> NTSTATUS,
+        >;
+        let _: PFN_WDFDEVICECREATE = None::<
+            unsafe extern "C" fn(
+                PWDF_DRIVER_GLOBALS,
+                *mut PWDFDEVICE_INIT,
+                PWDF_OBJECT_ATTRIBUTES,
+                *mut WDFDEVICE,
+            ) -> NTSTATUS,
+        >;
+        let _: PFN_WDFIOQUEUECREATE = None::<
+            unsafe extern "C" fn(
+                PWDF_DRIVER_GLOBALS,
+                WDFDEVICE,
+                PWDF_IO_QUEUE_CONFIG,
+                PWDF_OBJECT_ATTRIBUTES,
+                *mut WDFQUEUE,
+            ) -> NTSTATUS,
+        >;
+        let _: PFN_WDFREQUESTCOMPLETE =
+            None::<unsafe extern "C" fn(PWDF_DRIVER_GLOBALS, WDFREQUEST, NTSTATUS)>;
+    }
+}
+
+#[cfg(all(feature = "netadaptercx", driver_type = "kmdf"))]
+mod netadaptercx {
+    use wdk_sys::{
+        netadaptercx::{NETADAPTER, NETADAPTER_INIT, PFN_NETADAPTERCREATE, PNET_DRIVER_GLOBALS},
+        NTSTATUS,
+        PWDF_OBJECT_ATTRIBUTES,
+    };
+
+    #[test]
+    fn function_signatures() {
+        let _: PFN_NETADAPTERCREATE = None::<
+            unsafe extern "C" fn(
+                PNET_DRIVER_GLOBALS,
+                *mut NETADAPTER_INIT,
+                PWDF_OBJECT_ATTRIBUTES,
+                *mut NETADAPTER,
+            ) -> NTSTATUS,
+        >;
+    }
+}
+
+#[cfg(all(feature = "spb", driver_type = "kmdf"))]
+mod spb {
+    use wdk_sys::{
+        spb::{PFN_SPBREQUESTCOMPLETE, PSPB_DRIVER_GLOBALS, SPBREQUEST},
+        NTSTATUS,
+    };
+
+    #[test]
+    fn function_signatures() {
+        let _: PFN_SPBREQUESTCOMPLETE =
+            None::<unsafe extern "C" fn(PSPB_DRIVER_GLOBALS, SPBREQUEST, NTSTATUS)>;
+    }
+}
+
+#[cfg(all(feature = "gpio", driver_type = "kmdf"))]
+mod gpio {
+    use wdk_sys::{
+        gpio::{PGPIO_CLIENT_READ_PINS, PGPIO_READ_PINS_PARAMETERS},
+        NTSTATUS,
+        PVOID,
+    };
+
+    #[test]
+    fn callback_signatures() {
+        let _: PGPIO_CLIENT_READ_PINS =
+            None::<unsafe extern "C" fn(PVOID, PGPIO_READ_PINS_PARAMETERS) -> NTSTATUS>;
+    }
+}
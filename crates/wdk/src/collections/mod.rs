@@ -0,0 +1,132 @@
+//! Collections that allocate from an explicit kernel pool.
+//!
+//! Unlike the collections in `alloc`, which allocate via the global allocator
+//! and abort when an allocation fails, [`PoolBox`] and [`PoolVec`] allocate via
+//! `ExAllocatePool2` with the [`Pool`] (pool flags and tag) that they were
+//! created with, and return an error when an allocation fails, so that the
+//! driver can fail the current operation gracefully.
+//!
+//! All allocations are aligned to `MEMORY_ALLOCATION_ALIGNMENT`, so these
+//! collections fail to compile for types that are aligned to more than that.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use wdk::collections::{Pool, PoolBox, PoolVec};
+//!
+//! const POOL: Pool = Pool::non_paged(u32::from_le_bytes(*b"Coll"));
+//!
+//! let mut lengths = PoolVec::new(POOL);
+//! lengths.try_push(4_usize)?;
+//! lengths.try_push(2)?;
+//!
+//! let total = PoolBox::try_new(lengths.iter().sum::<usize>(), POOL)?;
+//! assert_eq!(*total, 6);
+//! # Ok::<(), wdk_sys::NTSTATUS>(())
+//! ```
+
+mod pool_box;
+mod pool_vec;
+
+use core::{alloc::Layout, mem::align_of, ptr::NonNull};
+
+pub use pool_box::*;
+pub use pool_vec::*;
+use wdk_sys::{
+    ntddk::{ExAllocatePool2, ExFreePoolWithTag},
+    MEMORY_ALLOCATION_ALIGNMENT,
+    NTSTATUS,
+    PoolFlags,
+    SIZE_T,
+    STATUS_INSUFFICIENT_RESOURCES,
+    ULONG,
+};
+
+/// Kernel pool that a collection allocates from, identified by the flags and
+/// the tag of its allocations.
+///
+/// Non-paged pool can be accessed at any `IRQL`, while paged pool can only be
+/// allocated, accessed and freed at `IRQL` <= `APC_LEVEL`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pool {
+    flags: PoolFlags,
+    tag: ULONG,
+}
+
+impl Pool {
+    /// Construct a [`Pool`] whose allocations are made with `flags` and tagged
+    /// with `tag`
+    #[must_use]
+    pub const fn new(flags: PoolFlags, tag: ULONG) -> Self {
+        Self { flags, tag }
+    }
+
+    /// Construct a [`Pool`] whose allocations are made from non-paged pool
+    /// and tagged with `tag`
+    #[must_use]
+    pub const fn non_paged(tag: ULONG) -> Self {
+        Self::new(PoolFlags::NON_PAGED, tag)
+    }
+
+    /// Construct a [`Pool`] whose allocations are made from paged pool and
+    /// tagged with `tag`
+    #[must_use]
+    pub const fn paged(tag: ULONG) -> Self {
+        Self::new(PoolFlags::PAGED, tag)
+    }
+
+    /// Get the flags of the allocations made from this pool
+    #[must_use]
+    pub const fn flags(&self) -> PoolFlags {
+        self.flags
+    }
+
+    /// Get the tag of the allocations made from this pool
+    #[must_use]
+    pub const fn tag(&self) -> ULONG {
+        self.tag
+    }
+
+    /// Allocate a block of memory that fits `layout`, which must have a
+    /// non-zero size and an alignment of at most `MEMORY_ALLOCATION_ALIGNMENT`
+    fn allocate(self, layout: Layout) -> Result<NonNull<u8>, NTSTATUS> {
+        debug_assert!(layout.size() != 0);
+        debug_assert!(layout.align() <= MEMORY_ALLOCATION_ALIGNMENT as usize);
+
+        let block;
+        // SAFETY: `ExAllocatePool2` can be called at any `IRQL` that is valid for the
+        // pool flags, which callers are responsible for per the documentation of
+        // `Pool`.
+        unsafe {
+            block = ExAllocatePool2(self.flags.into(), layout.size() as SIZE_T, self.tag);
+        }
+        NonNull::new(block.cast()).ok_or(STATUS_INSUFFICIENT_RESOURCES)
+    }
+
+    /// Free a block of memory that was allocated via [`Pool::allocate`]
+    ///
+    /// # Safety
+    ///
+    /// `block` must have been returned by [`Pool::allocate`] of a [`Pool`]
+    /// with the same tag, must not have been freed already, and must not be
+    /// used after it is freed.
+    unsafe fn free(self, block: NonNull<u8>) {
+        // SAFETY: The caller guarantees that `block` is a live allocation with this
+        // pool's tag.
+        unsafe {
+            ExFreePoolWithTag(block.as_ptr().cast(), self.tag);
+        }
+    }
+}
+
+/// Fail to compile if `T` is aligned to more than
+/// `MEMORY_ALLOCATION_ALIGNMENT`, which is the alignment of pool allocations
+const fn assert_pool_alignment<T>() {
+    const {
+        assert!(
+            align_of::<T>() <= MEMORY_ALLOCATION_ALIGNMENT as usize,
+            "pool collections do not support types aligned to more than \
+             MEMORY_ALLOCATION_ALIGNMENT"
+        );
+    };
+}
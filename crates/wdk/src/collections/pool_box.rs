@@ -0,0 +1,129 @@
+use core::{
+    alloc::Layout,
+    fmt,
+    marker::PhantomData,
+    mem::{size_of, ManuallyDrop},
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+};
+
+use wdk_sys::NTSTATUS;
+
+use super::{assert_pool_alignment, Pool};
+
+/// Pointer to a value of type `T` that is allocated from a [`Pool`].
+///
+/// This is the equivalent of `Box<T>`, except that allocation failures are
+/// reported via [`PoolBox::try_new`] instead of aborting. The value is dropped
+/// and its memory is returned to the pool when the [`PoolBox`] is dropped.
+pub struct PoolBox<T> {
+    value: NonNull<T>,
+    pool: Pool,
+    _marker: PhantomData<T>,
+}
+
+impl<T> PoolBox<T> {
+    /// Allocate memory for `value` from `pool`, and move `value` into it
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INSUFFICIENT_RESOURCES` if the memory
+    /// cannot be allocated, in which case `value` is dropped.
+    pub fn try_new(value: T, pool: Pool) -> Result<Self, NTSTATUS> {
+        assert_pool_alignment::<T>();
+
+        let block: NonNull<T> = if size_of::<T>() == 0 {
+            NonNull::dangling()
+        } else {
+            pool.allocate(Layout::new::<T>())?.cast()
+        };
+
+        // SAFETY: `block` is either dangling for a zero-sized `T`, or a newly
+        // allocated block that is large enough and aligned for a `T`.
+        unsafe {
+            block.as_ptr().write(value);
+        }
+        Ok(Self {
+            value: block,
+            pool,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Get the pool that `this` was allocated from.
+    ///
+    /// This is an associated function rather than a method, so that it does not
+    /// shadow methods of `T`.
+    #[must_use]
+    pub const fn pool(this: &Self) -> Pool {
+        this.pool
+    }
+
+    /// Consume `this`, and return the value that it holds after returning its
+    /// memory to the pool
+    #[must_use]
+    pub fn into_inner(this: Self) -> T {
+        let this = ManuallyDrop::new(this);
+
+        // SAFETY: `value` holds an initialized `T`, which is moved out here and not
+        // dropped again, since `this` is never dropped.
+        let value = unsafe { this.value.as_ptr().read() };
+        if size_of::<T>() != 0 {
+            // SAFETY: `value` was allocated from `pool` in `PoolBox::try_new`, and is
+            // not used after it is freed, since `this` is never dropped.
+            unsafe {
+                this.pool.free(this.value.cast());
+            }
+        }
+        value
+    }
+}
+
+impl<T> Deref for PoolBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: `value` holds an initialized `T` for as long as `self` exists.
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<T> DerefMut for PoolBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: `value` holds an initialized `T` for as long as `self` exists, and
+        // the exclusive borrow of `self` prevents other references to it.
+        unsafe { self.value.as_mut() }
+    }
+}
+
+impl<T> Drop for PoolBox<T> {
+    fn drop(&mut self) {
+        // SAFETY: `value` holds an initialized `T`, which is not used after it is
+        // dropped here.
+        unsafe {
+            self.value.as_ptr().drop_in_place();
+        }
+
+        if size_of::<T>() != 0 {
+            // SAFETY: `value` was allocated from `pool` in `PoolBox::try_new`, and is
+            // not used after it is freed.
+            unsafe {
+                self.pool.free(self.value.cast());
+            }
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for PoolBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+// SAFETY: `PoolBox` uniquely owns its value, and pool allocations can be freed
+// from any thread.
+unsafe impl<T: Send> Send for PoolBox<T> {}
+
+// SAFETY: `PoolBox` only hands out shared references to its value via shared
+// references to itself.
+unsafe impl<T: Sync> Sync for PoolBox<T> {}
@@ -0,0 +1,400 @@
+use core::{
+    alloc::Layout,
+    fmt,
+    mem::size_of,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+};
+
+use wdk_sys::{NTSTATUS, STATUS_INTEGER_OVERFLOW};
+
+use super::{assert_pool_alignment, Pool};
+
+/// Minimum capacity that a [`PoolVec`] grows to once it needs to allocate
+const MIN_NON_ZERO_CAPACITY: usize = 4;
+
+/// Contiguous growable array of values of type `T`, allocated from a
+/// [`Pool`].
+///
+/// This is the equivalent of `Vec<T>`, except that every operation that can
+/// allocate is fallible (ex. [`PoolVec::try_push`]) and reports allocation
+/// failures instead of aborting. Pool allocations cannot be resized in place,
+/// so growing the vector moves its elements to a new allocation.
+pub struct PoolVec<T> {
+    elements: NonNull<T>,
+    capacity: usize,
+    length: usize,
+    pool: Pool,
+}
+
+impl<T> PoolVec<T> {
+    /// Construct an empty [`PoolVec`] that allocates from `pool`. This does
+    /// not allocate until elements are added.
+    #[must_use]
+    pub const fn new(pool: Pool) -> Self {
+        assert_pool_alignment::<T>();
+
+        Self {
+            elements: NonNull::dangling(),
+            // Zero-sized elements never need to allocate
+            capacity: if size_of::<T>() == 0 { usize::MAX } else { 0 },
+            length: 0,
+            pool,
+        }
+    }
+
+    /// Construct an empty [`PoolVec`] that allocates from `pool`, with space
+    /// for at least `capacity` elements
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INSUFFICIENT_RESOURCES` if the memory
+    /// cannot be allocated, or `STATUS_INTEGER_OVERFLOW` if the size of
+    /// `capacity` elements overflows.
+    pub fn try_with_capacity(capacity: usize, pool: Pool) -> Result<Self, NTSTATUS> {
+        let mut vec = Self::new(pool);
+        vec.try_reserve(capacity)?;
+        Ok(vec)
+    }
+
+    /// Get the pool that the elements are allocated from
+    #[must_use]
+    pub const fn pool(&self) -> Pool {
+        self.pool
+    }
+
+    /// Get the number of elements
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Check whether there are no elements
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Get the number of elements that fit in the current allocation
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Get the elements as a slice
+    #[must_use]
+    pub const fn as_slice(&self) -> &[T] {
+        // SAFETY: `elements` is aligned and non-null, and its first `length` elements
+        // are initialized.
+        unsafe { core::slice::from_raw_parts(self.elements.as_ptr(), self.length) }
+    }
+
+    /// Get the elements as a mutable slice
+    #[must_use]
+    pub const fn as_mut_slice(&mut self) -> &mut [T] {
+        // SAFETY: `elements` is aligned and non-null, and its first `length` elements
+        // are initialized. The exclusive borrow of `self` prevents other references
+        // to them.
+        unsafe { core::slice::from_raw_parts_mut(self.elements.as_ptr(), self.length) }
+    }
+
+    /// Reserve space for at least `additional` more elements, so that adding
+    /// them does not allocate. Like `Vec::reserve`, this may reserve more
+    /// space than requested to avoid frequent reallocations.
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INSUFFICIENT_RESOURCES` if the memory
+    /// cannot be allocated, or `STATUS_INTEGER_OVERFLOW` if the required
+    /// capacity overflows. The vector is left unchanged on failure.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), NTSTATUS> {
+        let required_capacity = self
+            .length
+            .checked_add(additional)
+            .ok_or(STATUS_INTEGER_OVERFLOW)?;
+        if required_capacity <= self.capacity {
+            return Ok(());
+        }
+
+        let new_capacity = required_capacity
+            .max(self.capacity.saturating_mul(2))
+            .max(MIN_NON_ZERO_CAPACITY);
+        self.grow_to(new_capacity)
+    }
+
+    /// Append `value` to the end of the vector
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INSUFFICIENT_RESOURCES` if the vector
+    /// needs to grow and the memory cannot be allocated, in which case `value`
+    /// is dropped and the vector is left unchanged.
+    pub fn try_push(&mut self, value: T) -> Result<(), NTSTATUS> {
+        self.try_reserve(1)?;
+
+        // SAFETY: Space for at least one more element was reserved above.
+        unsafe {
+            self.end().write(value);
+        }
+        self.length += 1;
+        Ok(())
+    }
+
+    /// Insert `value` at position `index`, shifting all elements after it to
+    /// the right
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INSUFFICIENT_RESOURCES` if the vector
+    /// needs to grow and the memory cannot be allocated, in which case `value`
+    /// is dropped and the vector is left unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the length of the vector.
+    pub fn try_insert(&mut self, index: usize, value: T) -> Result<(), NTSTATUS> {
+        assert!(
+            index <= self.length,
+            "insertion index (is {index}) should be <= len (is {})",
+            self.length
+        );
+        self.try_reserve(1)?;
+
+        // SAFETY: `index` is at most `length`, and space for at least one more element
+        // was reserved above, so shifting the elements from `index` to the end by one
+        // stays within the allocation.
+        let slot = unsafe { self.elements.as_ptr().add(index) };
+        // SAFETY: Space for at least one more element was reserved above, so the slot
+        // after `slot` is still within the allocation.
+        let next_slot = unsafe { slot.add(1) };
+        // SAFETY: The `length - index` elements at `slot` are initialized, and are
+        // shifted by one within the allocation.
+        unsafe {
+            core::ptr::copy(slot, next_slot, self.length - index);
+        }
+        // SAFETY: The element at `slot` was moved to the next slot above, so it can be
+        // overwritten without being dropped.
+        unsafe {
+            slot.write(value);
+        }
+        self.length += 1;
+        Ok(())
+    }
+
+    /// Remove the last element and return it, or [`None`] if the vector is
+    /// empty
+    pub const fn pop(&mut self) -> Option<T> {
+        if self.length == 0 {
+            return None;
+        }
+
+        self.length -= 1;
+        // SAFETY: The element at the previous last index is initialized, and is no
+        // longer considered part of the vector, so it is not dropped again.
+        Some(unsafe { self.end().read() })
+    }
+
+    /// Remove the element at position `index` and return it, shifting all
+    /// elements after it to the left
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(
+            index < self.length,
+            "removal index (is {index}) should be < len (is {})",
+            self.length
+        );
+
+        // SAFETY: `index` is in bounds.
+        let slot = unsafe { self.elements.as_ptr().add(index) };
+        // SAFETY: The element at `slot` is initialized, and is overwritten below
+        // without being dropped.
+        let value = unsafe { slot.read() };
+        // SAFETY: `index` is in bounds, so the slot after `slot` is at most one past
+        // the last element.
+        let next_slot = unsafe { slot.add(1) };
+        // SAFETY: The `length - index - 1` elements after `slot` are initialized, and
+        // are shifted by one within the allocation.
+        unsafe {
+            core::ptr::copy(next_slot, slot, self.length - index - 1);
+        }
+        self.length -= 1;
+        value
+    }
+
+    /// Remove the element at position `index` and return it, replacing it with
+    /// the last element. This does not preserve ordering, but is O(1).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        assert!(
+            index < self.length,
+            "swap_remove index (is {index}) should be < len (is {})",
+            self.length
+        );
+
+        let last_index = self.length - 1;
+        self.as_mut_slice().swap(index, last_index);
+        // The vector is not empty, since `index` is in bounds
+        self.pop().unwrap_or_else(|| unreachable!())
+    }
+
+    /// Shorten the vector to `length` elements, dropping the rest. This has no
+    /// effect if the vector is not longer than `length`.
+    pub fn truncate(&mut self, length: usize) {
+        if length >= self.length {
+            return;
+        }
+
+        let dropped_length = self.length - length;
+        // Shrink the vector before dropping the elements, so that they are not
+        // dropped again if a destructor panics
+        self.length = length;
+        // SAFETY: `length` is less than the previous length, so it is in bounds.
+        let dropped_elements = unsafe { self.elements.as_ptr().add(length) };
+        // SAFETY: The `dropped_length` elements after the new length are initialized,
+        // and are no longer considered part of the vector.
+        unsafe {
+            core::ptr::slice_from_raw_parts_mut(dropped_elements, dropped_length).drop_in_place();
+        }
+    }
+
+    /// Remove and drop all elements. This does not free the allocation.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Get a pointer to the slot after the last element
+    const fn end(&self) -> *mut T {
+        // SAFETY: `length` is at most `capacity`, so the result is at most one past
+        // the end of the allocation.
+        unsafe { self.elements.as_ptr().add(self.length) }
+    }
+
+    /// Move the elements to a new allocation with space for `new_capacity`
+    /// elements, and free the current allocation
+    fn grow_to(&mut self, new_capacity: usize) -> Result<(), NTSTATUS> {
+        let layout = Layout::array::<T>(new_capacity).map_err(|_| STATUS_INTEGER_OVERFLOW)?;
+        let new_elements = self.pool.allocate(layout)?.cast::<T>();
+
+        // SAFETY: The first `length` elements of `elements` are initialized, and
+        // `new_elements` is a new allocation with space for more than `length`
+        // elements, so they do not overlap.
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                self.elements.as_ptr(),
+                new_elements.as_ptr(),
+                self.length,
+            );
+        }
+        if self.capacity != 0 {
+            // SAFETY: `elements` was allocated from `pool` by a previous call to
+            // `grow_to`, and its elements were moved to `new_elements` above.
+            unsafe {
+                self.pool.free(self.elements.cast());
+            }
+        }
+
+        self.elements = new_elements;
+        self.capacity = new_capacity;
+        Ok(())
+    }
+}
+
+impl<T: Clone> PoolVec<T> {
+    /// Append clones of all elements of `other` to the end of the vector
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INSUFFICIENT_RESOURCES` if the vector
+    /// needs to grow and the memory cannot be allocated, or
+    /// `STATUS_INTEGER_OVERFLOW` if the required capacity overflows. The
+    /// vector is left unchanged on failure.
+    pub fn try_extend_from_slice(&mut self, other: &[T]) -> Result<(), NTSTATUS> {
+        self.try_reserve(other.len())?;
+        for element in other {
+            // SAFETY: Space for all elements of `other` was reserved above.
+            unsafe {
+                self.end().write(element.clone());
+            }
+            self.length += 1;
+        }
+        Ok(())
+    }
+
+    /// Clone the vector into a new allocation from the same pool
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INSUFFICIENT_RESOURCES` if the memory
+    /// cannot be allocated.
+    pub fn try_clone(&self) -> Result<Self, NTSTATUS> {
+        let mut clone = Self::try_with_capacity(self.length, self.pool)?;
+        clone.try_extend_from_slice(self)?;
+        Ok(clone)
+    }
+}
+
+impl<T> Deref for PoolVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T> DerefMut for PoolVec<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a PoolVec<T> {
+    type IntoIter = core::slice::Iter<'a, T>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut PoolVec<T> {
+    type IntoIter = core::slice::IterMut<'a, T>;
+    type Item = &'a mut T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T> Drop for PoolVec<T> {
+    fn drop(&mut self) {
+        self.clear();
+
+        if size_of::<T>() != 0 && self.capacity != 0 {
+            // SAFETY: `elements` was allocated from `pool` by `grow_to`, and its
+            // elements were dropped above.
+            unsafe {
+                self.pool.free(self.elements.cast());
+            }
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for PoolVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_slice(), f)
+    }
+}
+
+// SAFETY: `PoolVec` uniquely owns its elements, and pool allocations can be
+// freed from any thread.
+unsafe impl<T: Send> Send for PoolVec<T> {}
+
+// SAFETY: `PoolVec` only hands out shared references to its elements via
+// shared references to itself.
+unsafe impl<T: Sync> Sync for PoolVec<T> {}
@@ -0,0 +1,35 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+use wdk_sys::{DRIVER_OBJECT, PDRIVER_OBJECT};
+
+/// WDM Driver Object.
+///
+/// This is the object that the I/O manager passes to `DriverEntry`. WDF
+/// drivers should not need to access its members directly, and should instead
+/// pass it to [`Driver::try_new`](crate::wdf::Driver::try_new) or
+/// [`DriverBuilder::create`](crate::wdf::DriverBuilder::create).
+#[repr(transparent)]
+pub struct DriverObject(DRIVER_OBJECT);
+
+impl DriverObject {
+    /// Construct a [`DriverObject`] reference from a raw `PDRIVER_OBJECT`
+    ///
+    /// # Safety
+    ///
+    /// `raw` must point to a valid `DRIVER_OBJECT`, and no other references to
+    /// it may exist for the lifetime `'a`.
+    #[must_use]
+    pub unsafe fn from_raw<'a>(raw: PDRIVER_OBJECT) -> &'a mut Self {
+        // SAFETY: `DriverObject` is a `repr(transparent)` wrapper of `DRIVER_OBJECT`,
+        // and the caller guarantees that `raw` is valid and not aliased.
+        unsafe { &mut *raw.cast::<Self>() }
+    }
+
+    /// Get the raw `PDRIVER_OBJECT` of this [`DriverObject`], for use in WDK
+    /// APIs
+    #[must_use]
+    pub const fn as_raw_mut(&mut self) -> PDRIVER_OBJECT {
+        core::ptr::addr_of_mut!(self.0)
+    }
+}
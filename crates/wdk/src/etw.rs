@@ -0,0 +1,575 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Kernel-mode [TraceLogging](https://learn.microsoft.com/en-us/windows/win32/tracelogging/trace-logging-about)
+//! ETW providers.
+//!
+//! `TraceLogging` events are self-describing: the names and types of an event's
+//! fields are encoded into metadata that is written alongside the event, so no
+//! manifest is needed to decode them.
+//!
+//! # Examples
+//!
+//! ```rust, no_run
+//! use wdk::{
+//!     etw::{Level, TraceLoggingProvider},
+//!     trace_event,
+//! };
+//! use wdk_sys::GUID;
+//!
+//! // {3970F9CF-2C0C-4F11-B1CC-E3A1E9958833}
+//! const PROVIDER_GUID: GUID = GUID {
+//!     Data1: 0x3970_F9CF,
+//!     Data2: 0x2C0C,
+//!     Data3: 0x4F11,
+//!     Data4: [0xB1, 0xCC, 0xE3, 0xA1, 0xE9, 0x95, 0x88, 0x33],
+//! };
+//!
+//! let provider = TraceLoggingProvider::register("MyDriver", &PROVIDER_GUID)?;
+//! trace_event!(
+//!     provider,
+//!     "DeviceAdded",
+//!     level = Level::Information,
+//!     u32("DeviceIndex", 0),
+//!     str8("Name", "sample"),
+//! );
+//! # Ok::<(), wdk_sys::NTSTATUS>(())
+//! ```
+
+use core::marker::PhantomData;
+
+use wdk_sys::{
+    _EVENT_DATA_DESCRIPTOR__bindgen_ty_1,
+    _EVENT_DATA_DESCRIPTOR__bindgen_ty_1__bindgen_ty_1,
+    ntddk::{EtwProviderEnabled, EtwRegister, EtwSetInformation, EtwUnregister, EtwWriteTransfer},
+    _EVENT_INFO_CLASS::EventProviderSetTraits,
+    EVENT_DATA_DESCRIPTOR,
+    EVENT_DATA_DESCRIPTOR_TYPE_EVENT_METADATA,
+    EVENT_DATA_DESCRIPTOR_TYPE_NONE,
+    EVENT_DATA_DESCRIPTOR_TYPE_PROVIDER_METADATA,
+    EVENT_DESCRIPTOR,
+    GUID,
+    NTSTATUS,
+    REGHANDLE,
+    STATUS_BUFFER_OVERFLOW,
+    STATUS_INVALID_PARAMETER,
+    UCHAR,
+    ULONG,
+    ULONGLONG,
+};
+
+use crate::nt_success;
+
+/// Channel that marks an event as a `TraceLogging` event
+/// (`WINEVENT_CHANNEL_TRACELOGGING`)
+const TRACELOGGING_CHANNEL: UCHAR = 11;
+
+/// Maximum size of a provider's `TraceLogging` metadata, which contains its
+/// name
+const PROVIDER_METADATA_BUFFER_SIZE: usize = 128;
+
+/// Maximum size of an event's `TraceLogging` metadata, which contains the names
+/// and types of the event and all of its fields
+const EVENT_METADATA_BUFFER_SIZE: usize = 512;
+
+/// Maximum number of fields in a single event
+pub const MAX_EVENT_FIELDS: usize = 32;
+
+/// Flag set on a field's in-type when it is followed by an out-type
+const IN_TYPE_CHAIN_FLAG: u8 = 0x80;
+
+/// ETW event level, which is used by trace sessions to filter events
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum Level {
+    /// Abnormal exit or termination events (`TRACE_LEVEL_CRITICAL`)
+    Critical = 1,
+    /// Severe error events (`TRACE_LEVEL_ERROR`)
+    Error = 2,
+    /// Warning events (`TRACE_LEVEL_WARNING`)
+    Warning = 3,
+    /// Informational events (`TRACE_LEVEL_INFORMATION`)
+    Information = 4,
+    /// Detailed diagnostic events (`TRACE_LEVEL_VERBOSE`)
+    Verbose = 5,
+}
+
+/// `TraceLogging` in-types, which describe how a field's data is encoded
+mod in_type {
+    pub(super) const INT8: u8 = 3;
+    pub(super) const UINT8: u8 = 4;
+    pub(super) const INT16: u8 = 5;
+    pub(super) const UINT16: u8 = 6;
+    pub(super) const INT32: u8 = 7;
+    pub(super) const UINT32: u8 = 8;
+    pub(super) const INT64: u8 = 9;
+    pub(super) const UINT64: u8 = 10;
+    pub(super) const FLOAT: u8 = 11;
+    pub(super) const DOUBLE: u8 = 12;
+    pub(super) const BOOL32: u8 = 13;
+    pub(super) const GUID: u8 = 15;
+    pub(super) const COUNTED_STRING: u8 = 22;
+    pub(super) const COUNTED_ANSI_STRING: u8 = 23;
+    pub(super) const COUNTED_BINARY: u8 = 25;
+}
+
+/// `TraceLogging` out-types, which describe how a field should be formatted
+mod out_type {
+    pub(super) const NONE: u8 = 0;
+    pub(super) const UTF8: u8 = 35;
+}
+
+/// A single named field of a `TraceLogging` event. Fields are usually created
+/// via the [`trace_event`](crate::trace_event) macro.
+#[derive(Clone, Copy, Debug)]
+pub struct Field<'a> {
+    name: &'a str,
+    in_type: u8,
+    out_type: u8,
+    value: FieldValue<'a>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum FieldValue<'a> {
+    /// Fixed-size value, stored inline
+    Scalar { bytes: [u8; 16], len: u8 },
+    /// Variable-size value, encoded as its `u16` size in bytes followed by
+    /// the data
+    Counted {
+        size: u16,
+        data: *const u8,
+        _data: PhantomData<&'a [u8]>,
+    },
+}
+
+macro_rules! scalar_field_constructors {
+    ($($(#[$attribute:meta])* $constructor:ident($type:ty) => $in_type:expr;)*) => {
+        $(
+            $(#[$attribute])*
+            #[must_use]
+            pub fn $constructor(name: &'a str, value: $type) -> Self {
+                Self::scalar(name, $in_type, &value.to_ne_bytes())
+            }
+        )*
+    };
+}
+
+impl<'a> Field<'a> {
+    scalar_field_constructors! {
+        /// Create an `i8` field
+        i8(i8) => in_type::INT8;
+        /// Create a `u8` field
+        u8(u8) => in_type::UINT8;
+        /// Create an `i16` field
+        i16(i16) => in_type::INT16;
+        /// Create a `u16` field
+        u16(u16) => in_type::UINT16;
+        /// Create an `i32` field
+        i32(i32) => in_type::INT32;
+        /// Create a `u32` field
+        u32(u32) => in_type::UINT32;
+        /// Create an `i64` field
+        i64(i64) => in_type::INT64;
+        /// Create a `u64` field
+        u64(u64) => in_type::UINT64;
+        /// Create an `f32` field
+        f32(f32) => in_type::FLOAT;
+        /// Create an `f64` field
+        f64(f64) => in_type::DOUBLE;
+    }
+
+    /// Create a boolean field, encoded as a 32-bit `BOOL`
+    #[must_use]
+    pub fn bool(name: &'a str, value: bool) -> Self {
+        Self::scalar(name, in_type::BOOL32, &i32::from(value).to_ne_bytes())
+    }
+
+    /// Create a [`GUID`] field
+    #[must_use]
+    pub fn guid(name: &'a str, value: &GUID) -> Self {
+        let mut bytes = [0; 16];
+        bytes[..4].copy_from_slice(&value.Data1.to_ne_bytes());
+        bytes[4..6].copy_from_slice(&value.Data2.to_ne_bytes());
+        bytes[6..8].copy_from_slice(&value.Data3.to_ne_bytes());
+        bytes[8..].copy_from_slice(&value.Data4);
+        Self::scalar(name, in_type::GUID, &bytes)
+    }
+
+    /// Create a UTF-8 string field. Strings longer than `u16::MAX` bytes are
+    /// truncated.
+    #[must_use]
+    pub fn str8(name: &'a str, value: &'a str) -> Self {
+        Self::counted(
+            name,
+            in_type::COUNTED_ANSI_STRING,
+            out_type::UTF8,
+            value.as_bytes(),
+        )
+    }
+
+    /// Create a UTF-16 string field. Strings longer than `u16::MAX` bytes are
+    /// truncated.
+    #[must_use]
+    pub fn str16(name: &'a str, value: &'a [u16]) -> Self {
+        // Truncate to a whole number of UTF-16 code units
+        let size = core::mem::size_of_val(value).min(usize::from(u16::MAX)) & !1;
+        Self {
+            name,
+            in_type: in_type::COUNTED_STRING,
+            out_type: out_type::NONE,
+            value: FieldValue::Counted {
+                size: u16::try_from(size).unwrap_or(u16::MAX),
+                data: value.as_ptr().cast(),
+                _data: PhantomData,
+            },
+        }
+    }
+
+    /// Create a binary field. Values longer than `u16::MAX` bytes are
+    /// truncated.
+    #[must_use]
+    pub fn binary(name: &'a str, value: &'a [u8]) -> Self {
+        Self::counted(name, in_type::COUNTED_BINARY, out_type::NONE, value)
+    }
+
+    fn scalar(name: &'a str, in_type: u8, value: &[u8]) -> Self {
+        let mut bytes = [0; 16];
+        bytes[..value.len()].copy_from_slice(value);
+        Self {
+            name,
+            in_type,
+            out_type: out_type::NONE,
+            value: FieldValue::Scalar {
+                bytes,
+                len: u8::try_from(value.len()).expect("scalar fields are at most 16 bytes"),
+            },
+        }
+    }
+
+    fn counted(name: &'a str, in_type: u8, out_type: u8, value: &'a [u8]) -> Self {
+        Self {
+            name,
+            in_type,
+            out_type,
+            value: FieldValue::Counted {
+                size: u16::try_from(value.len()).unwrap_or(u16::MAX),
+                data: value.as_ptr(),
+                _data: PhantomData,
+            },
+        }
+    }
+}
+
+/// Registered `TraceLogging` ETW provider. The provider is unregistered when
+/// dropped.
+pub struct TraceLoggingProvider {
+    reg_handle: REGHANDLE,
+    metadata: MetadataBuffer<PROVIDER_METADATA_BUFFER_SIZE>,
+}
+
+// SAFETY: `REGHANDLE`s can be used to write events from any thread
+unsafe impl Send for TraceLoggingProvider {}
+// SAFETY: `EtwProviderEnabled` and `EtwWriteTransfer` can be called
+// concurrently with the same `REGHANDLE`
+unsafe impl Sync for TraceLoggingProvider {}
+
+impl TraceLoggingProvider {
+    /// Register a `TraceLogging` provider with the given name and [`GUID`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `name` is too long to fit in the
+    /// provider metadata, or if the provider could not be registered. See
+    /// [`EtwRegister`](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-etwregister)
+    /// for possible errors.
+    pub fn register(name: &str, guid: &GUID) -> crate::Result<Self> {
+        // Provider metadata is a u16 size, followed by the provider's null terminated
+        // name
+        let mut metadata = MetadataBuffer::new();
+        metadata.push_bytes(&[0; 2])?;
+        metadata.push_str(name)?;
+        metadata.finish()?;
+
+        let mut reg_handle: REGHANDLE = 0;
+        let nt_status;
+        // SAFETY: `guid` and `reg_handle` are valid for the duration of the call, and
+        // no enable callback is registered.
+        unsafe {
+            nt_status = EtwRegister(
+                guid,
+                None,
+                core::ptr::null_mut(),
+                core::ptr::addr_of_mut!(reg_handle),
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status);
+        }
+
+        let provider = Self {
+            reg_handle,
+            metadata,
+        };
+
+        let nt_status;
+        // SAFETY: `metadata` is a valid provider traits buffer of the given length,
+        // which ETW copies during the call.
+        unsafe {
+            nt_status = EtwSetInformation(
+                provider.reg_handle,
+                EventProviderSetTraits,
+                provider.metadata.buffer.as_ptr().cast_mut().cast(),
+                provider.metadata.len_ulong(),
+            );
+        }
+        // If setting the traits fails, `provider` is unregistered when dropped
+        nt_success(nt_status).then_some(provider).ok_or(nt_status)
+    }
+
+    /// Returns `true` if any trace session is listening for events from this
+    /// provider with the given level and keyword
+    #[must_use]
+    pub fn enabled(&self, level: Level, keyword: u64) -> bool {
+        // SAFETY: `reg_handle` is a registered provider handle
+        unsafe { EtwProviderEnabled(self.reg_handle, level as UCHAR, keyword) != 0 }
+    }
+
+    /// Write a `TraceLogging` event with the given name, level, keyword and
+    /// fields. [`trace_event`](crate::trace_event) should generally be used
+    /// instead, since it avoids building the event when no trace session is
+    /// listening.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the event has more than
+    /// [`MAX_EVENT_FIELDS`] fields, if its metadata is too large, or if the
+    /// event could not be written. See
+    /// [`EtwWriteTransfer`](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-etwwritetransfer)
+    /// for possible errors.
+    pub fn write_event(
+        &self,
+        name: &str,
+        level: Level,
+        keyword: u64,
+        fields: &[Field<'_>],
+    ) -> crate::Result<()> {
+        if fields.len() > MAX_EVENT_FIELDS {
+            return Err(STATUS_INVALID_PARAMETER);
+        }
+
+        // Event metadata is a u16 size, a tag extension byte, and the event's null
+        // terminated name, followed by each field's null terminated name and type
+        let mut metadata = MetadataBuffer::<EVENT_METADATA_BUFFER_SIZE>::new();
+        metadata.push_bytes(&[0; 3])?;
+        metadata.push_str(name)?;
+
+        let mut data_descriptors =
+            [data_descriptor(core::ptr::null(), 0, 0); 2 + 2 * MAX_EVENT_FIELDS];
+        let mut data_descriptor_count = 2;
+        for field in fields {
+            metadata.push_str(field.name)?;
+            if field.out_type == out_type::NONE {
+                metadata.push_bytes(&[field.in_type])?;
+            } else {
+                metadata.push_bytes(&[field.in_type | IN_TYPE_CHAIN_FLAG, field.out_type])?;
+            }
+
+            match &field.value {
+                FieldValue::Scalar { bytes, len } => {
+                    data_descriptors[data_descriptor_count] = data_descriptor(
+                        bytes.as_ptr(),
+                        ULONG::from(*len),
+                        EVENT_DATA_DESCRIPTOR_TYPE_NONE,
+                    );
+                    data_descriptor_count += 1;
+                }
+                FieldValue::Counted { size, data, .. } => {
+                    data_descriptors[data_descriptor_count] = data_descriptor(
+                        core::ptr::from_ref(size).cast(),
+                        ULONG::from(u16::BITS / 8),
+                        EVENT_DATA_DESCRIPTOR_TYPE_NONE,
+                    );
+                    data_descriptors[data_descriptor_count + 1] =
+                        data_descriptor(*data, ULONG::from(*size), EVENT_DATA_DESCRIPTOR_TYPE_NONE);
+                    data_descriptor_count += 2;
+                }
+            }
+        }
+        metadata.finish()?;
+
+        data_descriptors[0] = data_descriptor(
+            self.metadata.buffer.as_ptr(),
+            self.metadata.len_ulong(),
+            EVENT_DATA_DESCRIPTOR_TYPE_PROVIDER_METADATA,
+        );
+        data_descriptors[1] = data_descriptor(
+            metadata.buffer.as_ptr(),
+            metadata.len_ulong(),
+            EVENT_DATA_DESCRIPTOR_TYPE_EVENT_METADATA,
+        );
+
+        // `data_descriptor_count` is bounded by `2 + 2 * MAX_EVENT_FIELDS`
+        #[allow(clippy::cast_possible_truncation)]
+        let data_descriptor_count = data_descriptor_count as ULONG;
+        let event_descriptor = EVENT_DESCRIPTOR {
+            Channel: TRACELOGGING_CHANNEL,
+            Level: level as UCHAR,
+            Keyword: keyword,
+            ..EVENT_DESCRIPTOR::default()
+        };
+
+        let nt_status;
+        // SAFETY: `event_descriptor` and the first `data_descriptor_count` data
+        // descriptors are valid, and all memory they point to outlives the call.
+        unsafe {
+            nt_status = EtwWriteTransfer(
+                self.reg_handle,
+                core::ptr::addr_of!(event_descriptor),
+                core::ptr::null(),
+                core::ptr::null(),
+                data_descriptor_count,
+                data_descriptors.as_mut_ptr(),
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+}
+
+impl Drop for TraceLoggingProvider {
+    fn drop(&mut self) {
+        let nt_status: NTSTATUS;
+        // SAFETY: `reg_handle` is a registered provider handle, which is not used
+        // after this call.
+        unsafe {
+            nt_status = EtwUnregister(self.reg_handle);
+        }
+        debug_assert!(nt_success(nt_status));
+    }
+}
+
+/// Write a `TraceLogging` event to a [`TraceLoggingProvider`], if any trace
+/// session is listening for it.
+///
+/// The event's fields are specified as `type("name", value)`, where `type` is
+/// the name of one of the [`Field`] constructors (ex. `u32`, `str8`). The
+/// keyword defaults to 0 if it is not specified.
+///
+/// Failures to write the event are ignored, since tracing is best-effort. Use
+/// [`TraceLoggingProvider::write_event`] to handle them.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// # use wdk::{etw::{Level, TraceLoggingProvider}, trace_event};
+/// # fn f(provider: &TraceLoggingProvider, status: i32) {
+/// trace_event!(
+///     provider,
+///     "IoctlFailed",
+///     level = Level::Error,
+///     keyword = 0x2,
+///     i32("Status", status),
+///     str8("Reason", "buffer too small"),
+/// );
+/// # }
+/// ```
+#[macro_export]
+macro_rules! trace_event {
+    (
+        $provider:expr,
+        $name:expr,
+        level = $level:expr,
+        keyword = $keyword:expr
+        $(, $field_type:ident($field_name:expr, $field_value:expr))* $(,)?
+    ) => {{
+        let provider: &$crate::etw::TraceLoggingProvider = &$provider;
+        let level: $crate::etw::Level = $level;
+        let keyword: u64 = $keyword;
+        if provider.enabled(level, keyword) {
+            let _ = provider.write_event(
+                $name,
+                level,
+                keyword,
+                &[$($crate::etw::Field::$field_type($field_name, $field_value)),*],
+            );
+        }
+    }};
+
+    (
+        $provider:expr,
+        $name:expr,
+        level = $level:expr
+        $(, $field_type:ident($field_name:expr, $field_value:expr))* $(,)?
+    ) => {
+        $crate::trace_event!(
+            $provider,
+            $name,
+            level = $level,
+            keyword = 0
+            $(, $field_type($field_name, $field_value))*
+        )
+    };
+}
+
+/// Fixed-size buffer that `TraceLogging` metadata is encoded into. The first
+/// two bytes of the metadata hold its total size.
+struct MetadataBuffer<const N: usize> {
+    buffer: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> MetadataBuffer<N> {
+    const fn new() -> Self {
+        Self {
+            buffer: [0; N],
+            len: 0,
+        }
+    }
+
+    fn push_bytes(&mut self, bytes: &[u8]) -> crate::Result<()> {
+        let end = self.len + bytes.len();
+        if end > N {
+            return Err(STATUS_BUFFER_OVERFLOW);
+        }
+        self.buffer[self.len..end].copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+
+    /// Push a null terminated copy of `s`. `s` should not contain null
+    /// characters, since decoders treat them as the end of the string.
+    fn push_str(&mut self, s: &str) -> crate::Result<()> {
+        self.push_bytes(s.as_bytes())?;
+        self.push_bytes(&[0])
+    }
+
+    /// Write the total size of the metadata to its first two bytes
+    fn finish(&mut self) -> crate::Result<()> {
+        let size = u16::try_from(self.len).map_err(|_| STATUS_BUFFER_OVERFLOW)?;
+        self.buffer[..2].copy_from_slice(&size.to_ne_bytes());
+        Ok(())
+    }
+
+    const fn len_ulong(&self) -> ULONG {
+        // `finish` ensures that `len` fits in a u16
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            self.len as ULONG
+        }
+    }
+}
+
+fn data_descriptor(data: *const u8, size: ULONG, descriptor_type: u32) -> EVENT_DATA_DESCRIPTOR {
+    // Descriptor types are all small constants
+    #[allow(clippy::cast_possible_truncation)]
+    let descriptor_type = descriptor_type as UCHAR;
+    EVENT_DATA_DESCRIPTOR {
+        Ptr: data as ULONGLONG,
+        Size: size,
+        __bindgen_anon_1: _EVENT_DATA_DESCRIPTOR__bindgen_ty_1 {
+            __bindgen_anon_1: _EVENT_DATA_DESCRIPTOR__bindgen_ty_1__bindgen_ty_1 {
+                Type: descriptor_type,
+                ..Default::default()
+            },
+        },
+    }
+}
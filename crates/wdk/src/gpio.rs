@@ -0,0 +1,893 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! GPIO controller drivers, implemented on top of the GPIO framework
+//! extension (`GpioClx`).
+//!
+//! `GpioClx` implements the I/O and interrupt interfaces of a GPIO controller,
+//! and calls into the controller driver to access the hardware. A controller
+//! driver implements these calls via [`GpioControllerHandler`], and registers
+//! it in `DriverEntry` via [`GpioController::register_client`]. Each device of
+//! the driver is then set up in `EvtDriverDeviceAdd` via
+//! [`GpioController::configure_device_init`] and
+//! [`GpioController::initialize_device`]:
+//!
+//! ```rust, no_run
+//! use wdk::{
+//!     gpio::{GpioController, GpioControllerHandler, GpioReadPins},
+//!     string::NtUnicodeStr,
+//!     wdf::{Device, DeviceInit, Driver},
+//!     DriverObject,
+//! };
+//! use wdk_sys::{
+//!     gpio::CLIENT_CONTROLLER_BASIC_INFORMATION,
+//!     NTSTATUS,
+//!     WDFCMRESLIST,
+//!     WDF_OBJECT_ATTRIBUTES,
+//! };
+//!
+//! struct Controller;
+//!
+//! impl GpioControllerHandler for Controller {
+//!     fn prepare_controller(
+//!         _device: &Device,
+//!         _resources_raw: WDFCMRESLIST,
+//!         _resources_translated: WDFCMRESLIST,
+//!     ) -> Result<Self, NTSTATUS> {
+//!         Ok(Self)
+//!     }
+//!
+//!     fn query_controller_basic_information(
+//!         &self,
+//!         information: &mut CLIENT_CONTROLLER_BASIC_INFORMATION,
+//!     ) -> Result<(), NTSTATUS> {
+//!         information.TotalPins = 32;
+//!         information.NumberOfPinsPerBank = 32;
+//!         Ok(())
+//!     }
+//!
+//!     fn read_gpio_pins(&self, pins: &mut GpioReadPins<'_>) -> Result<(), NTSTATUS> {
+//!         for index in 0..pins.pins().len() {
+//!             pins.set_pin_value(index, false);
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! #[wdk::driver_entry]
+//! fn driver_entry(
+//!     driver_object: &mut DriverObject,
+//!     registry_path: NtUnicodeStr<'_>,
+//! ) -> wdk::Result<Driver> {
+//!     let driver = Driver::builder()
+//!         .device_add(|driver, device_init| {
+//!             let mut attributes = WDF_OBJECT_ATTRIBUTES::init();
+//!             // SAFETY: `device_init` is the `PWDFDEVICE_INIT` passed to
+//!             // `EvtDriverDeviceAdd`.
+//!             let mut init = unsafe { DeviceInit::from_raw(device_init) };
+//!             GpioController::configure_device_init(driver, &mut init, &mut attributes)?;
+//!             let device = Device::try_new(device_init, Some(&mut attributes))?;
+//!             GpioController::initialize_device(driver, &device)
+//!         })
+//!         .create(driver_object, registry_path)?;
+//!     GpioController::register_client::<Controller>(driver_object, registry_path)?;
+//!     Ok(driver)
+//! }
+//! ```
+
+use core::{cell::UnsafeCell, mem::MaybeUninit};
+
+use wdk_sys::{
+    gpio::{
+        GPIO_CLX_ProcessAddDevicePostDeviceCreate,
+        GPIO_CLX_ProcessAddDevicePreDeviceCreate,
+        GPIO_CLX_RegisterClient,
+        GPIO_CLX_UnregisterClient,
+        BANK_ID,
+        CLIENT_CONTROLLER_BASIC_INFORMATION,
+        GPIO_CLEAR_ACTIVE_INTERRUPTS_PARAMETERS,
+        GPIO_CLIENT_REGISTRATION_PACKET,
+        GPIO_CLIENT_VERSION,
+        GPIO_CONNECT_IO_PINS_PARAMETERS,
+        GPIO_DISABLE_INTERRUPT_PARAMETERS,
+        GPIO_DISCONNECT_IO_PINS_PARAMETERS,
+        GPIO_ENABLE_INTERRUPT_PARAMETERS,
+        GPIO_MASK_INTERRUPT_PARAMETERS,
+        GPIO_QUERY_ACTIVE_INTERRUPTS_PARAMETERS,
+        GPIO_READ_PINS_PARAMETERS,
+        GPIO_WRITE_PINS_PARAMETERS,
+        PCLIENT_CONTROLLER_BASIC_INFORMATION,
+        PGPIO_CLEAR_ACTIVE_INTERRUPTS_PARAMETERS,
+        PGPIO_CONNECT_IO_PINS_PARAMETERS,
+        PGPIO_DISABLE_INTERRUPT_PARAMETERS,
+        PGPIO_DISCONNECT_IO_PINS_PARAMETERS,
+        PGPIO_ENABLE_INTERRUPT_PARAMETERS,
+        PGPIO_MASK_INTERRUPT_PARAMETERS,
+        PGPIO_QUERY_ACTIVE_INTERRUPTS_PARAMETERS,
+        PGPIO_READ_PINS_PARAMETERS,
+        PGPIO_WRITE_PINS_PARAMETERS,
+        PIN_NUMBER,
+    },
+    BOOLEAN,
+    NTSTATUS,
+    PVOID,
+    STATUS_INVALID_DEVICE_STATE,
+    STATUS_NOT_SUPPORTED,
+    STATUS_SUCCESS,
+    ULONG,
+    USHORT,
+    WDFCMRESLIST,
+    WDFDEVICE,
+    WDF_OBJECT_ATTRIBUTES,
+    WDF_POWER_DEVICE_STATE,
+};
+
+use crate::{
+    nt_success,
+    string::NtUnicodeStr,
+    wdf::{Device, DeviceInit, Driver, FromWdfObject},
+    DriverObject,
+};
+
+/// GPIO controller, implemented on top of `GpioClx`.
+///
+/// See the [module documentation](self) for how a GPIO controller driver is
+/// set up.
+pub struct GpioController;
+
+/// Hardware access of a GPIO controller, invoked by `GpioClx`.
+///
+/// The handler of a device is constructed by
+/// [`GpioControllerHandler::prepare_controller`] when the device is started,
+/// and is consumed by [`GpioControllerHandler::release_controller`] when the
+/// device is stopped or removed. It is stored in the device context that
+/// `GpioClx` allocates for the controller driver.
+///
+/// The interrupt callbacks (ex. [`GpioControllerHandler::enable_interrupt`])
+/// can be invoked at `DIRQL` with the interrupt lock of their bank held,
+/// concurrently with the I/O callbacks (ex.
+/// [`GpioControllerHandler::read_gpio_pins`]), which `GpioClx` invokes at
+/// `PASSIVE_LEVEL` or `DISPATCH_LEVEL` depending on whether the controller is
+/// memory-mapped.
+///
+/// The callbacks that are not implemented report `STATUS_NOT_SUPPORTED` to
+/// `GpioClx`, except for the connection and power callbacks, which succeed.
+pub trait GpioControllerHandler: Sized + Send + Sync + 'static {
+    /// Called by `GpioClx` when `device` is started
+    /// (`CLIENT_PrepareController`), to construct the handler from the
+    /// hardware resources of the controller (ex. by mapping its registers)
+    ///
+    /// # Errors
+    ///
+    /// The [`NTSTATUS`] of the failure is reported to `GpioClx`, which fails
+    /// the start of the device.
+    fn prepare_controller(
+        device: &Device,
+        resources_raw: WDFCMRESLIST,
+        resources_translated: WDFCMRESLIST,
+    ) -> Result<Self, NTSTATUS>;
+
+    /// Called by `GpioClx` when `device` is stopped or removed
+    /// (`CLIENT_ReleaseController`), to release the hardware resources
+    /// acquired by [`GpioControllerHandler::prepare_controller`]
+    ///
+    /// # Errors
+    ///
+    /// The [`NTSTATUS`] of the failure is reported to `GpioClx`.
+    fn release_controller(self, _device: &Device) -> Result<(), NTSTATUS> {
+        Ok(())
+    }
+
+    /// Called by `GpioClx` when the controller enters the D0 power state
+    /// (`CLIENT_StartController`). If `restore_context` is `true`, the
+    /// hardware context of the banks that was saved by
+    /// [`GpioControllerHandler::stop_controller`] must be restored.
+    ///
+    /// # Errors
+    ///
+    /// The [`NTSTATUS`] of the failure is reported to `GpioClx`.
+    fn start_controller(
+        &self,
+        _restore_context: bool,
+        _previous_power_state: WDF_POWER_DEVICE_STATE,
+    ) -> Result<(), NTSTATUS> {
+        Ok(())
+    }
+
+    /// Called by `GpioClx` when the controller leaves the D0 power state
+    /// (`CLIENT_StopController`). If `save_context` is `true`, the hardware
+    /// context of the banks must be saved, so that it can be restored by
+    /// [`GpioControllerHandler::start_controller`].
+    ///
+    /// # Errors
+    ///
+    /// The [`NTSTATUS`] of the failure is reported to `GpioClx`.
+    fn stop_controller(
+        &self,
+        _save_context: bool,
+        _target_state: WDF_POWER_DEVICE_STATE,
+    ) -> Result<(), NTSTATUS> {
+        Ok(())
+    }
+
+    /// Called by `GpioClx` to query the attributes of the controller
+    /// (`CLIENT_QueryControllerBasicInformation`), ex. its total number of
+    /// pins and its number of pins per bank. `information` is pre-initialized
+    /// with its `Version` and `Size`.
+    ///
+    /// # Errors
+    ///
+    /// The [`NTSTATUS`] of the failure is reported to `GpioClx`, which fails
+    /// the start of the device.
+    fn query_controller_basic_information(
+        &self,
+        information: &mut CLIENT_CONTROLLER_BASIC_INFORMATION,
+    ) -> Result<(), NTSTATUS>;
+
+    /// Called by `GpioClx` to read the values of a set of pins of a bank that
+    /// are configured as inputs (`CLIENT_ReadGpioPins`)
+    ///
+    /// # Errors
+    ///
+    /// The [`NTSTATUS`] of the failure is reported to `GpioClx`, which fails
+    /// the read request.
+    fn read_gpio_pins(&self, _pins: &mut GpioReadPins<'_>) -> Result<(), NTSTATUS> {
+        Err(STATUS_NOT_SUPPORTED)
+    }
+
+    /// Called by `GpioClx` to write the values of a set of pins of a bank that
+    /// are configured as outputs (`CLIENT_WriteGpioPins`)
+    ///
+    /// # Errors
+    ///
+    /// The [`NTSTATUS`] of the failure is reported to `GpioClx`, which fails
+    /// the write request.
+    fn write_gpio_pins(&self, _pins: &GpioWritePins<'_>) -> Result<(), NTSTATUS> {
+        Err(STATUS_NOT_SUPPORTED)
+    }
+
+    /// Called by `GpioClx` to configure a set of pins of a bank as inputs or
+    /// outputs (`CLIENT_ConnectIoPins`)
+    ///
+    /// # Errors
+    ///
+    /// The [`NTSTATUS`] of the failure is reported to `GpioClx`, which fails
+    /// the connection request.
+    fn connect_io_pins(
+        &self,
+        _parameters: &mut GPIO_CONNECT_IO_PINS_PARAMETERS,
+    ) -> Result<(), NTSTATUS> {
+        Ok(())
+    }
+
+    /// Called by `GpioClx` to release a set of pins of a bank that were
+    /// configured via [`GpioControllerHandler::connect_io_pins`]
+    /// (`CLIENT_DisconnectIoPins`)
+    ///
+    /// # Errors
+    ///
+    /// The [`NTSTATUS`] of the failure is reported to `GpioClx`.
+    fn disconnect_io_pins(
+        &self,
+        _parameters: &mut GPIO_DISCONNECT_IO_PINS_PARAMETERS,
+    ) -> Result<(), NTSTATUS> {
+        Ok(())
+    }
+
+    /// Called by `GpioClx` to configure a pin as an interrupt and enable it
+    /// (`CLIENT_EnableInterrupt`)
+    ///
+    /// # Errors
+    ///
+    /// The [`NTSTATUS`] of the failure is reported to `GpioClx`, which fails
+    /// the connection of the interrupt.
+    fn enable_interrupt(
+        &self,
+        _parameters: &mut GPIO_ENABLE_INTERRUPT_PARAMETERS,
+    ) -> Result<(), NTSTATUS> {
+        Err(STATUS_NOT_SUPPORTED)
+    }
+
+    /// Called by `GpioClx` to disable the interrupt of a pin that was enabled
+    /// via [`GpioControllerHandler::enable_interrupt`]
+    /// (`CLIENT_DisableInterrupt`)
+    ///
+    /// # Errors
+    ///
+    /// The [`NTSTATUS`] of the failure is reported to `GpioClx`.
+    fn disable_interrupt(
+        &self,
+        _parameters: &mut GPIO_DISABLE_INTERRUPT_PARAMETERS,
+    ) -> Result<(), NTSTATUS> {
+        Err(STATUS_NOT_SUPPORTED)
+    }
+
+    /// Called by `GpioClx` to unmask the interrupt of a pin
+    /// (`CLIENT_UnmaskInterrupt`)
+    ///
+    /// # Errors
+    ///
+    /// The [`NTSTATUS`] of the failure is reported to `GpioClx`.
+    fn unmask_interrupt(
+        &self,
+        _parameters: &mut GPIO_ENABLE_INTERRUPT_PARAMETERS,
+    ) -> Result<(), NTSTATUS> {
+        Err(STATUS_NOT_SUPPORTED)
+    }
+
+    /// Called by `GpioClx` to mask the interrupts of a set of pins of a bank
+    /// (`CLIENT_MaskInterrupts`)
+    ///
+    /// # Errors
+    ///
+    /// The [`NTSTATUS`] of the failure is reported to `GpioClx`.
+    fn mask_interrupts(
+        &self,
+        _parameters: &mut GPIO_MASK_INTERRUPT_PARAMETERS,
+    ) -> Result<(), NTSTATUS> {
+        Err(STATUS_NOT_SUPPORTED)
+    }
+
+    /// Called by `GpioClx` to query which pins of a bank have an active
+    /// interrupt (`CLIENT_QueryActiveInterrupts`)
+    ///
+    /// # Errors
+    ///
+    /// The [`NTSTATUS`] of the failure is reported to `GpioClx`.
+    fn query_active_interrupts(
+        &self,
+        _parameters: &mut GPIO_QUERY_ACTIVE_INTERRUPTS_PARAMETERS,
+    ) -> Result<(), NTSTATUS> {
+        Err(STATUS_NOT_SUPPORTED)
+    }
+
+    /// Called by `GpioClx` to clear the active interrupts of a set of pins of
+    /// a bank (`CLIENT_ClearActiveInterrupts`)
+    ///
+    /// # Errors
+    ///
+    /// The [`NTSTATUS`] of the failure is reported to `GpioClx`.
+    fn clear_active_interrupts(
+        &self,
+        _parameters: &mut GPIO_CLEAR_ACTIVE_INTERRUPTS_PARAMETERS,
+    ) -> Result<(), NTSTATUS> {
+        Err(STATUS_NOT_SUPPORTED)
+    }
+}
+
+/// Typed view of the `GPIO_READ_PINS_PARAMETERS` passed to
+/// [`GpioControllerHandler::read_gpio_pins`]. The value of each pin of
+/// [`GpioReadPins::pins`] is reported via [`GpioReadPins::set_pin_value`].
+pub struct GpioReadPins<'a> {
+    parameters: &'a mut GPIO_READ_PINS_PARAMETERS,
+}
+
+/// Typed view of the `GPIO_WRITE_PINS_PARAMETERS` passed to
+/// [`GpioControllerHandler::write_gpio_pins`]. The value to write to each pin
+/// of [`GpioWritePins::pins`] is retrieved via [`GpioWritePins::pin_value`].
+pub struct GpioWritePins<'a> {
+    parameters: &'a GPIO_WRITE_PINS_PARAMETERS,
+}
+
+/// Layout of the device context that `GpioClx` allocates for the controller
+/// driver, which holds the [`GpioControllerHandler`] of the device while it is
+/// prepared. `GpioClx` zero-initializes this memory, which corresponds to an
+/// unprepared controller.
+#[repr(C)]
+struct GpioClientContext<H> {
+    prepared: UnsafeCell<bool>,
+    handler: UnsafeCell<MaybeUninit<H>>,
+}
+
+impl GpioController {
+    /// Register `H` as the [`GpioControllerHandler`] of the devices of the
+    /// driver. This must be called from `DriverEntry`, after the WDF driver
+    /// object is created.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `GpioClx` fails to register the driver. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [GpioClx Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/gpioclx/nf-gpioclx-gpio_clx_registerclient#return-value)
+    pub fn register_client<H: GpioControllerHandler>(
+        driver_object: &mut DriverObject,
+        registry_path: NtUnicodeStr<'_>,
+    ) -> Result<(), NTSTATUS> {
+        // SAFETY: `GPIO_CLIENT_REGISTRATION_PACKET` is a C struct of integers and
+        // optional function pointers, for which all-zero is a valid value. Callbacks
+        // that are left unassigned are not supported by the driver.
+        let mut registration_packet: GPIO_CLIENT_REGISTRATION_PACKET =
+            unsafe { core::mem::zeroed() };
+        // GPIO_CLIENT_VERSION is a small version number, which fits in a USHORT
+        #[allow(clippy::cast_possible_truncation)]
+        let version = GPIO_CLIENT_VERSION as USHORT;
+        registration_packet.Version = version;
+        // The size of GPIO_CLIENT_REGISTRATION_PACKET is known to fit in a USHORT
+        #[allow(clippy::cast_possible_truncation)]
+        let size = core::mem::size_of::<GPIO_CLIENT_REGISTRATION_PACKET>() as USHORT;
+        registration_packet.Size = size;
+        // The size of the context is known to fit in a ULONG
+        #[allow(clippy::cast_possible_truncation)]
+        let controller_context_size = core::mem::size_of::<GpioClientContext<H>>() as ULONG;
+        registration_packet.ControllerContextSize = controller_context_size;
+
+        registration_packet.CLIENT_PrepareController = Some(client_prepare_controller::<H>);
+        registration_packet.CLIENT_ReleaseController = Some(client_release_controller::<H>);
+        registration_packet.CLIENT_StartController = Some(client_start_controller::<H>);
+        registration_packet.CLIENT_StopController = Some(client_stop_controller::<H>);
+        registration_packet.CLIENT_QueryControllerBasicInformation =
+            Some(client_query_controller_basic_information::<H>);
+        registration_packet.CLIENT_ConnectIoPins = Some(client_connect_io_pins::<H>);
+        registration_packet.CLIENT_DisconnectIoPins = Some(client_disconnect_io_pins::<H>);
+        // `CLIENT_ReadGpioPins` and `CLIENT_WriteGpioPins` share their unions with the
+        // mask-based variants, which are only used by controllers that report
+        // `FormatIoRequestsAsMasks`
+        registration_packet.__bindgen_anon_1.CLIENT_ReadGpioPins = Some(client_read_gpio_pins::<H>);
+        registration_packet.__bindgen_anon_2.CLIENT_WriteGpioPins =
+            Some(client_write_gpio_pins::<H>);
+        registration_packet.CLIENT_EnableInterrupt = Some(client_enable_interrupt::<H>);
+        registration_packet.CLIENT_DisableInterrupt = Some(client_disable_interrupt::<H>);
+        registration_packet.CLIENT_UnmaskInterrupt = Some(client_unmask_interrupt::<H>);
+        registration_packet.CLIENT_MaskInterrupts = Some(client_mask_interrupts::<H>);
+        registration_packet.CLIENT_QueryActiveInterrupts =
+            Some(client_query_active_interrupts::<H>);
+        registration_packet.CLIENT_ClearActiveInterrupts =
+            Some(client_clear_active_interrupts::<H>);
+
+        // SAFETY: `DriverObject` guarantees that the driver object is valid, and
+        // `NtUnicodeStr` guarantees that the registry path is valid. `GpioClx` copies
+        // the registration packet.
+        let nt_status = unsafe {
+            GPIO_CLX_RegisterClient(
+                driver_object.as_raw_mut(),
+                &mut registration_packet,
+                registry_path.as_raw(),
+            )
+        };
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Unregister the driver from `GpioClx`. This must be called from the
+    /// driver's unload routine, if it was registered via
+    /// [`GpioController::register_client`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `GpioClx` fails to unregister the driver. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [GpioClx Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/gpioclx/nf-gpioclx-gpio_clx_unregisterclient#return-value)
+    pub fn unregister_client(driver_object: &mut DriverObject) -> Result<(), NTSTATUS> {
+        // SAFETY: `DriverObject` guarantees that the driver object is valid.
+        let nt_status = unsafe { GPIO_CLX_UnregisterClient(driver_object.as_raw_mut()) };
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Configure the device that is being initialized by `device_init` to be
+    /// a GPIO controller. `attributes` are updated with the context that
+    /// `GpioClx` allocates for the device, and must be used to create it. This
+    /// must be called from `EvtDriverDeviceAdd`, before the device is created.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `GpioClx` fails to configure the device. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [GpioClx Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/gpioclx/nf-gpioclx-gpio_clx_processadddevicepredevicecreate#return-value)
+    pub fn configure_device_init(
+        driver: &Driver,
+        device_init: &mut DeviceInit<'_>,
+        attributes: &mut WDF_OBJECT_ATTRIBUTES,
+    ) -> Result<(), NTSTATUS> {
+        // SAFETY: `Driver` guarantees that its handle is valid, and `DeviceInit`
+        // guarantees that its `PWDFDEVICE_INIT` is valid and has not been used to
+        // create a device yet.
+        let nt_status = unsafe {
+            GPIO_CLX_ProcessAddDevicePreDeviceCreate(
+                driver.as_raw(),
+                device_init.as_raw(),
+                attributes,
+            )
+        };
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Finish setting up `device` as a GPIO controller, after it was created
+    /// with the attributes configured by
+    /// [`GpioController::configure_device_init`]. This must be called from
+    /// `EvtDriverDeviceAdd`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `GpioClx` fails to set up the device. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [GpioClx Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/gpioclx/nf-gpioclx-gpio_clx_processadddevicepostdevicecreate#return-value)
+    pub fn initialize_device(driver: &Driver, device: &Device) -> Result<(), NTSTATUS> {
+        // SAFETY: `Driver` and `Device` guarantee that their handles are valid.
+        let nt_status =
+            unsafe { GPIO_CLX_ProcessAddDevicePostDeviceCreate(driver.as_raw(), device.as_raw()) };
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+}
+
+impl GpioReadPins<'_> {
+    /// Get the bank of the pins to read
+    #[must_use]
+    pub const fn bank_id(&self) -> BANK_ID {
+        self.parameters.BankId
+    }
+
+    /// Get the numbers of the pins to read, relative to their bank
+    #[must_use]
+    pub fn pins(&self) -> &[PIN_NUMBER] {
+        // SAFETY: `GpioClx` passes a table of `PinCount` pin numbers, which is valid
+        // for the duration of the callback that `self` is borrowed from.
+        unsafe { pin_number_table(self.parameters.PinNumberTable, self.parameters.PinCount) }
+    }
+
+    /// Report the value of the pin at `index` of [`GpioReadPins::pins`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than the number of pins to read.
+    pub fn set_pin_value(&mut self, index: usize, value: bool) {
+        assert!(index < self.pins().len(), "pin index out of bounds");
+
+        let bit = 1_u8 << (index % 8);
+        // SAFETY: `GpioClx` passes a buffer with a bit for each of the `PinCount`
+        // pins, which is valid for the duration of the callback that `self` is
+        // borrowed from. `index` was checked to be less than `PinCount` above.
+        let byte = unsafe { &mut *self.parameters.Buffer.cast::<u8>().add(index / 8) };
+        if value {
+            *byte |= bit;
+        } else {
+            *byte &= !bit;
+        }
+    }
+
+    /// Get the raw `GPIO_READ_PINS_PARAMETERS`
+    #[must_use]
+    pub const fn parameters(&self) -> &GPIO_READ_PINS_PARAMETERS {
+        self.parameters
+    }
+}
+
+impl GpioWritePins<'_> {
+    /// Get the bank of the pins to write
+    #[must_use]
+    pub const fn bank_id(&self) -> BANK_ID {
+        self.parameters.BankId
+    }
+
+    /// Get the numbers of the pins to write, relative to their bank
+    #[must_use]
+    pub fn pins(&self) -> &[PIN_NUMBER] {
+        // SAFETY: `GpioClx` passes a table of `PinCount` pin numbers, which is valid
+        // for the duration of the callback that `self` is borrowed from.
+        unsafe { pin_number_table(self.parameters.PinNumberTable, self.parameters.PinCount) }
+    }
+
+    /// Get the value to write to the pin at `index` of
+    /// [`GpioWritePins::pins`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than the number of pins to write.
+    #[must_use]
+    pub fn pin_value(&self, index: usize) -> bool {
+        assert!(index < self.pins().len(), "pin index out of bounds");
+
+        // SAFETY: `GpioClx` passes a buffer with a bit for each of the `PinCount`
+        // pins, which is valid for the duration of the callback that `self` is
+        // borrowed from. `index` was checked to be less than `PinCount` above.
+        let byte = unsafe { *self.parameters.Buffer.cast::<u8>().add(index / 8) };
+        byte & (1 << (index % 8)) != 0
+    }
+
+    /// Iterate over the pins to write, along with the value to write to each
+    /// of them
+    pub fn iter(&self) -> impl Iterator<Item = (PIN_NUMBER, bool)> + '_ {
+        self.pins()
+            .iter()
+            .enumerate()
+            .map(|(index, &pin)| (pin, self.pin_value(index)))
+    }
+
+    /// Get the raw `GPIO_WRITE_PINS_PARAMETERS`
+    #[must_use]
+    pub const fn parameters(&self) -> &GPIO_WRITE_PINS_PARAMETERS {
+        self.parameters
+    }
+}
+
+/// Construct a slice of the `pin_count` pin numbers at `pin_number_table`
+///
+/// # Safety
+///
+/// Unless `pin_count` is zero, `pin_number_table` must point to `pin_count`
+/// pin numbers that are valid for the lifetime `'a`.
+unsafe fn pin_number_table<'a>(
+    pin_number_table: *const PIN_NUMBER,
+    pin_count: ULONG,
+) -> &'a [PIN_NUMBER] {
+    if pin_count == 0 || pin_number_table.is_null() {
+        return &[];
+    }
+    // SAFETY: The caller guarantees that `pin_number_table` points to `pin_count`
+    // valid pin numbers.
+    unsafe { core::slice::from_raw_parts(pin_number_table, pin_count as usize) }
+}
+
+/// Get the [`GpioControllerHandler`] stored in the `GpioClx` device context
+/// `context`, or `None` if the controller is not prepared
+///
+/// # Safety
+///
+/// `context` must be the device context that `GpioClx` allocated for a driver
+/// registered via [`GpioController::register_client`], and
+/// [`client_release_controller`] must not run for the lifetime `'a`.
+unsafe fn handler<'a, H: GpioControllerHandler>(context: PVOID) -> Option<&'a H> {
+    // SAFETY: The caller guarantees that `context` is a `GpioClientContext<H>`.
+    let context = unsafe { &*context.cast::<GpioClientContext<H>>() };
+    // SAFETY: `prepared` is only written by `client_prepare_controller` and
+    // `client_release_controller`, which `GpioClx` does not run concurrently with
+    // any other callback of the controller.
+    if !unsafe { *context.prepared.get() } {
+        return None;
+    }
+    // SAFETY: The handler is initialized while the controller is prepared, and the
+    // caller guarantees that it is not released for the lifetime `'a`.
+    Some(unsafe { (*context.handler.get()).assume_init_ref() })
+}
+
+/// Invoke `callback` with the [`GpioControllerHandler`] stored in `context`,
+/// and convert its result into the [`NTSTATUS`] reported to `GpioClx`
+///
+/// # Safety
+///
+/// `context` must be the device context that `GpioClx` passed to one of the
+/// callbacks registered by [`GpioController::register_client`], other
+/// than `CLIENT_PrepareController` and `CLIENT_ReleaseController`.
+unsafe fn dispatch<H: GpioControllerHandler>(
+    context: PVOID,
+    callback: impl FnOnce(&H) -> Result<(), NTSTATUS>,
+) -> NTSTATUS {
+    // SAFETY: The caller guarantees that `context` is the device context of the
+    // controller, and `GpioClx` does not release the controller while another
+    // callback is running.
+    let Some(handler) = (unsafe { handler::<H>(context) }) else {
+        return STATUS_INVALID_DEVICE_STATE;
+    };
+    match callback(handler) {
+        Ok(()) => STATUS_SUCCESS,
+        Err(nt_status) => nt_status,
+    }
+}
+
+/// `CLIENT_PrepareController` trampoline that constructs the
+/// [`GpioControllerHandler`] of the device
+unsafe extern "C" fn client_prepare_controller<H: GpioControllerHandler>(
+    device: WDFDEVICE,
+    context: PVOID,
+    resources_raw: WDFCMRESLIST,
+    resources_translated: WDFCMRESLIST,
+) -> NTSTATUS {
+    // SAFETY: GpioClx passes a valid device handle to its callbacks.
+    let device = unsafe { Device::from_wdf_object(device.cast()) };
+    // SAFETY: GpioClx passes the device context that it allocated with the size
+    // registered by `GpioController::register_client`.
+    let context = unsafe { &*context.cast::<GpioClientContext<H>>() };
+
+    // Release a handler left over from a previous start, should GpioClx prepare
+    // the controller again without releasing it
+    // SAFETY: GpioClx does not run any other callback of the controller while it
+    // is being prepared.
+    if unsafe { *context.prepared.get() } {
+        // SAFETY: The handler is initialized while the controller is prepared, and
+        // is read exactly once before `prepared` is cleared.
+        let handler = unsafe { (*context.handler.get()).assume_init_read() };
+        // SAFETY: GpioClx does not run any other callback of the controller while it
+        // is being prepared.
+        unsafe {
+            *context.prepared.get() = false;
+        }
+        let _ = handler.release_controller(&device);
+    }
+
+    match H::prepare_controller(&device, resources_raw, resources_translated) {
+        Ok(handler) => {
+            // SAFETY: GpioClx does not run any other callback of the controller while
+            // it is being prepared, and the controller is not prepared.
+            unsafe {
+                (*context.handler.get()).write(handler);
+            }
+            // SAFETY: GpioClx does not run any other callback of the controller while
+            // it is being prepared.
+            unsafe {
+                *context.prepared.get() = true;
+            }
+            STATUS_SUCCESS
+        }
+        Err(nt_status) => nt_status,
+    }
+}
+
+/// `CLIENT_ReleaseController` trampoline that consumes the
+/// [`GpioControllerHandler`] of the device
+unsafe extern "C" fn client_release_controller<H: GpioControllerHandler>(
+    device: WDFDEVICE,
+    context: PVOID,
+) -> NTSTATUS {
+    // SAFETY: GpioClx passes a valid device handle to its callbacks.
+    let device = unsafe { Device::from_wdf_object(device.cast()) };
+    // SAFETY: GpioClx passes the device context that it allocated with the size
+    // registered by `GpioController::register_client`.
+    let context = unsafe { &*context.cast::<GpioClientContext<H>>() };
+
+    // SAFETY: GpioClx does not run any other callback of the controller while it
+    // is being released.
+    if !unsafe { *context.prepared.get() } {
+        return STATUS_SUCCESS;
+    }
+    // SAFETY: The handler is initialized while the controller is prepared, and is
+    // read exactly once before `prepared` is cleared.
+    let handler = unsafe { (*context.handler.get()).assume_init_read() };
+    // SAFETY: GpioClx does not run any other callback of the controller while it
+    // is being released.
+    unsafe {
+        *context.prepared.get() = false;
+    }
+
+    match handler.release_controller(&device) {
+        Ok(()) => STATUS_SUCCESS,
+        Err(nt_status) => nt_status,
+    }
+}
+
+/// `CLIENT_StartController` trampoline that forwards to the
+/// [`GpioControllerHandler`] of the device
+unsafe extern "C" fn client_start_controller<H: GpioControllerHandler>(
+    context: PVOID,
+    restore_context: BOOLEAN,
+    previous_power_state: WDF_POWER_DEVICE_STATE,
+) -> NTSTATUS {
+    // SAFETY: GpioClx passes the device context of the controller.
+    unsafe {
+        dispatch::<H>(context, |handler| {
+            handler.start_controller(restore_context != 0, previous_power_state)
+        })
+    }
+}
+
+/// `CLIENT_StopController` trampoline that forwards to the
+/// [`GpioControllerHandler`] of the device
+unsafe extern "C" fn client_stop_controller<H: GpioControllerHandler>(
+    context: PVOID,
+    save_context: BOOLEAN,
+    target_state: WDF_POWER_DEVICE_STATE,
+) -> NTSTATUS {
+    // SAFETY: GpioClx passes the device context of the controller.
+    unsafe {
+        dispatch::<H>(context, |handler| {
+            handler.stop_controller(save_context != 0, target_state)
+        })
+    }
+}
+
+/// `CLIENT_QueryControllerBasicInformation` trampoline that forwards to the
+/// [`GpioControllerHandler`] of the device
+unsafe extern "C" fn client_query_controller_basic_information<H: GpioControllerHandler>(
+    context: PVOID,
+    information: PCLIENT_CONTROLLER_BASIC_INFORMATION,
+) -> NTSTATUS {
+    // SAFETY: GpioClx passes a valid information structure, which is not accessed
+    // elsewhere for the duration of the callback.
+    let Some(information) = (unsafe { information.as_mut() }) else {
+        return STATUS_INVALID_DEVICE_STATE;
+    };
+    // SAFETY: GpioClx passes the device context of the controller.
+    unsafe {
+        dispatch::<H>(context, |handler| {
+            handler.query_controller_basic_information(information)
+        })
+    }
+}
+
+/// `CLIENT_ReadGpioPins` trampoline that forwards to the
+/// [`GpioControllerHandler`] of the device
+unsafe extern "C" fn client_read_gpio_pins<H: GpioControllerHandler>(
+    context: PVOID,
+    parameters: PGPIO_READ_PINS_PARAMETERS,
+) -> NTSTATUS {
+    // SAFETY: GpioClx passes valid parameters, which are not accessed elsewhere
+    // for the duration of the callback.
+    let Some(parameters) = (unsafe { parameters.as_mut() }) else {
+        return STATUS_INVALID_DEVICE_STATE;
+    };
+    let mut pins = GpioReadPins { parameters };
+    // SAFETY: GpioClx passes the device context of the controller.
+    unsafe { dispatch::<H>(context, |handler| handler.read_gpio_pins(&mut pins)) }
+}
+
+/// `CLIENT_WriteGpioPins` trampoline that forwards to the
+/// [`GpioControllerHandler`] of the device
+unsafe extern "C" fn client_write_gpio_pins<H: GpioControllerHandler>(
+    context: PVOID,
+    parameters: PGPIO_WRITE_PINS_PARAMETERS,
+) -> NTSTATUS {
+    // SAFETY: GpioClx passes valid parameters, which are not modified for the
+    // duration of the callback.
+    let Some(parameters) = (unsafe { parameters.as_ref() }) else {
+        return STATUS_INVALID_DEVICE_STATE;
+    };
+    let pins = GpioWritePins { parameters };
+    // SAFETY: GpioClx passes the device context of the controller.
+    unsafe { dispatch::<H>(context, |handler| handler.write_gpio_pins(&pins)) }
+}
+
+/// Define a `GpioClx` callback trampoline that forwards a raw parameters
+/// structure to the [`GpioControllerHandler`] method of the same purpose
+macro_rules! parameters_trampoline {
+    ($(#[$attribute:meta])* $trampoline:ident, $parameters_type:ty, $method:ident) => {
+        $(#[$attribute])*
+        unsafe extern "C" fn $trampoline<H: GpioControllerHandler>(
+            context: PVOID,
+            parameters: $parameters_type,
+        ) -> NTSTATUS {
+            // SAFETY: GpioClx passes valid parameters, which are not accessed elsewhere
+            // for the duration of the callback.
+            let Some(parameters) = (unsafe { parameters.as_mut() }) else {
+                return STATUS_INVALID_DEVICE_STATE;
+            };
+            // SAFETY: GpioClx passes the device context of the controller.
+            unsafe { dispatch::<H>(context, |handler| handler.$method(parameters)) }
+        }
+    };
+}
+
+parameters_trampoline!(
+    /// `CLIENT_ConnectIoPins` trampoline that forwards to the
+    /// [`GpioControllerHandler`] of the device
+    client_connect_io_pins,
+    PGPIO_CONNECT_IO_PINS_PARAMETERS,
+    connect_io_pins
+);
+parameters_trampoline!(
+    /// `CLIENT_DisconnectIoPins` trampoline that forwards to the
+    /// [`GpioControllerHandler`] of the device
+    client_disconnect_io_pins,
+    PGPIO_DISCONNECT_IO_PINS_PARAMETERS,
+    disconnect_io_pins
+);
+parameters_trampoline!(
+    /// `CLIENT_EnableInterrupt` trampoline that forwards to the
+    /// [`GpioControllerHandler`] of the device
+    client_enable_interrupt,
+    PGPIO_ENABLE_INTERRUPT_PARAMETERS,
+    enable_interrupt
+);
+parameters_trampoline!(
+    /// `CLIENT_DisableInterrupt` trampoline that forwards to the
+    /// [`GpioControllerHandler`] of the device
+    client_disable_interrupt,
+    PGPIO_DISABLE_INTERRUPT_PARAMETERS,
+    disable_interrupt
+);
+parameters_trampoline!(
+    /// `CLIENT_UnmaskInterrupt` trampoline that forwards to the
+    /// [`GpioControllerHandler`] of the device
+    client_unmask_interrupt,
+    PGPIO_ENABLE_INTERRUPT_PARAMETERS,
+    unmask_interrupt
+);
+parameters_trampoline!(
+    /// `CLIENT_MaskInterrupts` trampoline that forwards to the
+    /// [`GpioControllerHandler`] of the device
+    client_mask_interrupts,
+    PGPIO_MASK_INTERRUPT_PARAMETERS,
+    mask_interrupts
+);
+parameters_trampoline!(
+    /// `CLIENT_QueryActiveInterrupts` trampoline that forwards to the
+    /// [`GpioControllerHandler`] of the device
+    client_query_active_interrupts,
+    PGPIO_QUERY_ACTIVE_INTERRUPTS_PARAMETERS,
+    query_active_interrupts
+);
+parameters_trampoline!(
+    /// `CLIENT_ClearActiveInterrupts` trampoline that forwards to the
+    /// [`GpioControllerHandler`] of the device
+    client_clear_active_interrupts,
+    PGPIO_CLEAR_ACTIVE_INTERRUPTS_PARAMETERS,
+    clear_active_interrupts
+);
@@ -0,0 +1,194 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Helpers for declaring [`GUID`]s, ex. device interface class GUIDs
+//!
+//! [`Guid`] wraps the raw [`GUID`] struct, and can be constructed in const
+//! contexts from its 128-bit value, or from its registry format:
+//!
+//! ```rust, no_run
+//! use wdk::guid::Guid;
+//!
+//! // {86E0D1E0-8089-11D0-9CE4-08003E301F73}
+//! const GUID_DEVINTERFACE_COMPORT: Guid = Guid::from_u128(0x86E0D1E0_8089_11D0_9CE4_08003E301F73);
+//! const GUID_DEVINTERFACE_COMPORT_PARSED: Guid =
+//!     match Guid::parse("{86E0D1E0-8089-11D0-9CE4-08003E301F73}") {
+//!         Ok(guid) => guid,
+//!         Err(_) => panic!("GUID should be in registry format"),
+//!     };
+//!
+//! assert_eq!(GUID_DEVINTERFACE_COMPORT, GUID_DEVINTERFACE_COMPORT_PARSED);
+//! ```
+
+use core::{fmt, hash, str::FromStr};
+
+use wdk_sys::{GUID, NTSTATUS, STATUS_INVALID_PARAMETER};
+
+/// Length of a GUID in registry format, without braces (ex.
+/// `86E0D1E0-8089-11D0-9CE4-08003E301F73`)
+const GUID_STRING_LEN: usize = 36;
+/// Indices of the hyphens in a GUID in registry format, without braces
+const GUID_STRING_HYPHEN_INDICES: [usize; 4] = [8, 13, 18, 23];
+
+/// Construct a [`GUID`] from its 128-bit value, written in the same order as
+/// its registry format (ex. `0x86E0D1E0_8089_11D0_9CE4_08003E301F73` for
+/// `{86E0D1E0-8089-11D0-9CE4-08003E301F73}`)
+#[must_use]
+pub const fn from_u128(value: u128) -> GUID {
+    Guid::from_u128(value).into_raw()
+}
+
+/// A globally unique identifier, ex. a device interface class GUID.
+///
+/// [`Guid`] has the same layout as the raw [`GUID`] struct, and can be
+/// converted to and from it via [`From`]. Unlike [`GUID`], it can be compared,
+/// hashed, and is displayed in its registry format (ex.
+/// `{86E0D1E0-8089-11D0-9CE4-08003E301F73}`).
+#[derive(Clone, Copy, Default)]
+#[repr(transparent)]
+pub struct Guid(GUID);
+
+impl Guid {
+    /// Construct a [`Guid`] from its 128-bit value, written in the same order
+    /// as its registry format (ex. `0x86E0D1E0_8089_11D0_9CE4_08003E301F73`
+    /// for `{86E0D1E0-8089-11D0-9CE4-08003E301F73}`)
+    #[must_use]
+    pub const fn from_u128(value: u128) -> Self {
+        // Truncating each shifted value to the width of its field extracts the field
+        #[allow(clippy::cast_possible_truncation)]
+        Self(GUID {
+            Data1: (value >> 96) as u32,
+            Data2: (value >> 80) as u16,
+            Data3: (value >> 64) as u16,
+            Data4: (value as u64).to_be_bytes(),
+        })
+    }
+
+    /// Get the 128-bit value of this [`Guid`], in the same order as its
+    /// registry format
+    #[must_use]
+    pub const fn to_u128(&self) -> u128 {
+        ((self.0.Data1 as u128) << 96)
+            | ((self.0.Data2 as u128) << 80)
+            | ((self.0.Data3 as u128) << 64)
+            | (u64::from_be_bytes(self.0.Data4) as u128)
+    }
+
+    /// Parse a [`Guid`] from its registry format, with or without the
+    /// surrounding braces (ex. `{86E0D1E0-8089-11D0-9CE4-08003E301F73}` or
+    /// `86E0D1E0-8089-11D0-9CE4-08003E301F73`). Hexadecimal digits can be
+    /// upper or lower case.
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INVALID_PARAMETER` if `string` is not
+    /// a GUID in registry format.
+    pub const fn parse(string: &str) -> Result<Self, NTSTATUS> {
+        let mut bytes = string.as_bytes();
+        if let [b'{', inner @ .., b'}'] = bytes {
+            bytes = inner;
+        }
+        if bytes.len() != GUID_STRING_LEN {
+            return Err(STATUS_INVALID_PARAMETER);
+        }
+
+        let mut value: u128 = 0;
+        let mut index = 0;
+        while index < GUID_STRING_LEN {
+            let byte = bytes[index];
+            if is_hyphen_index(index) {
+                if byte != b'-' {
+                    return Err(STATUS_INVALID_PARAMETER);
+                }
+            } else {
+                let digit = match byte {
+                    b'0'..=b'9' => byte - b'0',
+                    b'a'..=b'f' => byte - b'a' + 10,
+                    b'A'..=b'F' => byte - b'A' + 10,
+                    _ => return Err(STATUS_INVALID_PARAMETER),
+                };
+                value = (value << 4) | digit as u128;
+            }
+            index += 1;
+        }
+        Ok(Self::from_u128(value))
+    }
+
+    /// Get a reference to the underlying [`GUID`], ex. to pass it to a WDK API
+    #[must_use]
+    pub const fn as_raw(&self) -> &GUID {
+        &self.0
+    }
+
+    /// Convert this [`Guid`] into the underlying [`GUID`]
+    #[must_use]
+    pub const fn into_raw(self) -> GUID {
+        self.0
+    }
+}
+
+const fn is_hyphen_index(index: usize) -> bool {
+    let mut i = 0;
+    while i < GUID_STRING_HYPHEN_INDICES.len() {
+        if GUID_STRING_HYPHEN_INDICES[i] == index {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+impl From<GUID> for Guid {
+    fn from(guid: GUID) -> Self {
+        Self(guid)
+    }
+}
+
+impl From<Guid> for GUID {
+    fn from(guid: Guid) -> Self {
+        guid.0
+    }
+}
+
+impl FromStr for Guid {
+    type Err = NTSTATUS;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        Self::parse(string)
+    }
+}
+
+impl PartialEq for Guid {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_u128() == other.to_u128()
+    }
+}
+
+impl Eq for Guid {}
+
+impl hash::Hash for Guid {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.to_u128().hash(state);
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_u128();
+        write!(
+            f,
+            "{{{:08X}-{:04X}-{:04X}-{:04X}-{:012X}}}",
+            value >> 96,
+            (value >> 80) & 0xFFFF,
+            (value >> 64) & 0xFFFF,
+            (value >> 48) & 0xFFFF,
+            value & 0xFFFF_FFFF_FFFF,
+        )
+    }
+}
+
+impl fmt::Debug for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
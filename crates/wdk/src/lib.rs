@@ -7,13 +7,149 @@
 
 #![no_std]
 
-#[cfg(feature = "alloc")]
-mod print;
-#[cfg(feature = "alloc")]
+mod driver;
+pub use driver::DriverObject;
+pub use guid::Guid;
+#[cfg(not(driver_type = "umdf"))]
 pub use print::_print;
-pub use wdk_sys::{NT_SUCCESS as nt_success, PAGED_CODE as paged_code};
+/// Attribute macro that turns a Rust function into the `DriverEntry` of a
+/// driver.
+///
+/// The annotated function must have the following signature:
+///
+/// ```rust, ignore
+/// fn driver_entry(
+///     driver_object: &mut wdk::DriverObject,
+///     registry_path: wdk::string::NtUnicodeStr<'_>,
+/// ) -> wdk::Result<wdk::wdf::Driver>
+/// ```
+///
+/// WDM drivers (built with the `wdm` feature of `wdk-sys`) do not use WDF, so
+/// the annotated function returns `wdk::Result<()>` instead.
+///
+/// The macro generates an `extern "system"` function exported as
+/// `DriverEntry`, which converts the raw `PDRIVER_OBJECT` and
+/// `PCUNICODE_STRING` passed by the I/O manager into safe wrappers, forwards
+/// them to the annotated function, and converts its result into an
+/// [`NTSTATUS`](wdk_sys::NTSTATUS).
+///
+/// With the `runtime` feature enabled, the macro can also define a
+/// `#[panic_handler]` for the driver that uses [`runtime::panic_handler`], via
+/// `#[wdk::driver_entry(panic_handler = true)]`. This is opt-in, since drivers
+/// that provide their own panic handler (ex. by linking `wdk-panic`) would
+/// otherwise fail to build with a duplicate panic handler. No panic handler is
+/// defined for UMDF drivers, which are user-mode DLLs that use the panic
+/// handler of `std`.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use wdk::{string::NtUnicodeStr, wdf::Driver, DriverObject};
+///
+/// #[wdk::driver_entry]
+/// fn driver_entry(
+///     driver_object: &mut DriverObject,
+///     registry_path: NtUnicodeStr<'_>,
+/// ) -> wdk::Result<Driver> {
+///     Driver::builder()
+///         .device_add(|_driver, _device_init| Ok(()))
+///         .create(driver_object, registry_path)
+/// }
+/// ```
+pub use wdk_macros::driver_entry;
+pub use wdk_sys::{nt_success, PAGED_CODE as paged_code};
+#[cfg(not(driver_type = "umdf"))]
+pub mod collections;
+#[cfg(not(driver_type = "umdf"))]
+pub mod etw;
+#[cfg(all(feature = "gpio", driver_type = "kmdf"))]
+pub mod gpio;
+pub mod guid;
+#[cfg(not(driver_type = "umdf"))]
+pub mod log_buffer;
+#[cfg(not(driver_type = "umdf"))]
+pub mod mdl;
+#[cfg(not(driver_type = "umdf"))]
+pub mod metrics;
+#[cfg(not(driver_type = "umdf"))]
+pub mod print;
+#[cfg(all(feature = "runtime", not(driver_type = "umdf")))]
+pub mod runtime;
+pub mod sddl;
+pub mod string;
+#[cfg(all(feature = "alloc", not(driver_type = "umdf")))]
+pub mod sync;
+pub mod time;
+#[cfg(all(feature = "tracing", not(driver_type = "umdf")))]
+pub mod tracing;
+#[cfg(not(driver_type = "wdm"))]
 pub mod wdf;
 
+/// Result type returned by fallible WDK APIs, where the error is the
+/// [`NTSTATUS`](wdk_sys::NTSTATUS) of the failure
+pub type Result<T> = core::result::Result<T, wdk_sys::NTSTATUS>;
+
+/// Implementation details of macros exported by this crate. Not part of the
+/// public API.
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(all(feature = "runtime", not(driver_type = "umdf")))]
+    use core::panic::PanicInfo;
+
+    pub use wdk_sys;
+    use wdk_sys::{NTSTATUS, PCUNICODE_STRING, PDRIVER_OBJECT, STATUS_SUCCESS};
+
+    pub use crate::string::{encode_utf16, utf16_len};
+    #[cfg(not(driver_type = "wdm"))]
+    use crate::wdf::Driver;
+    use crate::{string::NtUnicodeStr, DriverObject};
+
+    /// Value returned by the function annotated with
+    /// [`driver_entry`](crate::driver_entry) on success. WDM drivers do not
+    /// create a WDF driver object, so they return `()` instead.
+    #[cfg(not(driver_type = "wdm"))]
+    pub type DriverEntryOutput = Driver;
+    /// Value returned by the function annotated with
+    /// [`driver_entry`](crate::driver_entry) on success. WDM drivers do not
+    /// create a WDF driver object, so they return `()` instead.
+    #[cfg(driver_type = "wdm")]
+    pub type DriverEntryOutput = ();
+
+    /// Body of the `DriverEntry` generated by
+    /// [`driver_entry`](crate::driver_entry)
+    ///
+    /// # Safety
+    ///
+    /// `driver_object` and `registry_path` must be the arguments passed to
+    /// `DriverEntry` by the I/O manager.
+    pub unsafe fn driver_entry<F>(
+        driver_object: PDRIVER_OBJECT,
+        registry_path: PCUNICODE_STRING,
+        user_driver_entry: F,
+    ) -> NTSTATUS
+    where
+        F: FnOnce(&mut DriverObject, NtUnicodeStr<'_>) -> crate::Result<DriverEntryOutput>,
+    {
+        // SAFETY: The I/O manager passes a valid driver object, which nothing else
+        // accesses until `DriverEntry` returns.
+        let driver_object = unsafe { DriverObject::from_raw(driver_object) };
+        // SAFETY: The I/O manager passes a valid registry path, which stays valid and
+        // unmodified until `DriverEntry` returns.
+        let registry_path = unsafe { NtUnicodeStr::from_raw(registry_path) };
+
+        match user_driver_entry(driver_object, registry_path) {
+            Ok(_) => STATUS_SUCCESS,
+            Err(nt_status) => nt_status,
+        }
+    }
+
+    /// Panic handler installed by [`driver_entry`](crate::driver_entry)
+    #[cfg(all(feature = "runtime", not(driver_type = "umdf")))]
+    pub fn panic_handler(info: &PanicInfo) -> ! {
+        crate::runtime::panic_handler(info)
+    }
+}
+
 /// Trigger a breakpoint in debugger via architecture-specific inline assembly.
 ///
 /// Implementations derived from details outlined in [MSVC `__debugbreak` intrinsic documentation](https://learn.microsoft.com/en-us/cpp/intrinsics/debugbreak?view=msvc-170#remarks)
@@ -0,0 +1,391 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Always-on diagnostics via a fixed-size in-memory ring buffer of log
+//! messages, which can be drained from user mode.
+//!
+//! Messages printed via [`DbgPrintEx`](wdk_sys::ntddk::DbgPrintEx) are only
+//! visible while a debugger (or a tool like `DbgView`) is attached, so they
+//! are of little use to diagnose issues in the field. A [`LogBuffer`] instead
+//! keeps the most recent messages in memory, so they can be retrieved after
+//! the fact, ex. by a user mode tool that sends a device I/O control request
+//! to the driver.
+//!
+//! Logging to a [`LogBuffer`] never blocks nor allocates, so it can be done at
+//! any IRQL, as long as the [`LogBuffer`] itself is in non-paged memory (ex. a
+//! `static` of the driver). Once the buffer is full, the oldest messages are
+//! overwritten.
+//!
+//! A [`LogBuffer`] can be set as a sink of the [`print!`](crate::print) and
+//! [`println!`](crate::println) macros via
+//! [`set_log_buffer`](crate::print::set_log_buffer), in which case it also
+//! receives the events of the [`tracing`](crate::tracing) adapter:
+//!
+//! ```rust, no_run
+//! use wdk::{
+//!     log_buffer::LogBuffer,
+//!     print,
+//!     wdf::{Device, IoQueue, IoctlRouter},
+//! };
+//! use wdk_sys::_WDF_IO_QUEUE_DISPATCH_TYPE::WdfIoQueueDispatchParallel;
+//!
+//! const IOCTL_DRAIN_LOG: u32 = 0x0022_2000;
+//!
+//! static LOG_BUFFER: LogBuffer<256> = LogBuffer::new();
+//!
+//! fn create_queue(device: &Device) -> wdk::Result<IoQueue> {
+//!     print::set_log_buffer(&LOG_BUFFER)?;
+//!     wdk::println!("logged to the debugger and to LOG_BUFFER");
+//!
+//!     IoctlRouter::new()
+//!         .ioctl_with_request(IOCTL_DRAIN_LOG, |_queue, request| {
+//!             LOG_BUFFER.drain_to_request(request);
+//!         })
+//!         .create_queue(device, WdfIoQueueDispatchParallel)
+//! }
+//! ```
+
+use core::{
+    fmt::{self, Write},
+    sync::atomic::{fence, AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering},
+};
+
+#[cfg(not(driver_type = "wdm"))]
+use wdk_sys::STATUS_SUCCESS;
+
+#[cfg(not(driver_type = "wdm"))]
+use crate::wdf::Request;
+
+/// Number of message bytes stored in each entry of a [`LogBuffer`]. Longer
+/// messages span multiple consecutive entries.
+pub const ENTRY_CAPACITY: usize = 128;
+
+/// Maximum length of the line inserted by [`LogBuffer::drain`] in place of
+/// entries that were overwritten before they were drained
+const LOST_ENTRIES_LINE_CAPACITY: usize = 48;
+
+/// Minimum length of the buffers passed to [`LogBuffer::drain`] for it to be
+/// guaranteed to make progress
+pub const MINIMUM_DRAIN_LENGTH: usize = ENTRY_CAPACITY + LOST_ENTRIES_LINE_CAPACITY;
+
+/// Fixed-size ring buffer of log messages.
+///
+/// Messages are stored in `ENTRIES` entries of [`ENTRY_CAPACITY`] bytes each.
+/// Any number of threads can log concurrently without taking a lock, and
+/// messages are drained in the order they were logged via
+/// [`LogBuffer::drain`] or [`LogBuffer::drain_to_request`].
+///
+/// Messages that span multiple entries can be interleaved with messages
+/// logged concurrently, and entries that are overwritten before they are
+/// drained are replaced by a line stating how many entries were lost.
+pub struct LogBuffer<const ENTRIES: usize> {
+    /// Sequence number of the next entry to be written
+    next_sequence: AtomicU64,
+    /// Sequence number of the next entry to be drained
+    read_sequence: AtomicU64,
+    /// Number of entries overwritten before they were drained, which have not
+    /// been reported by [`LogBuffer::drain`] yet
+    lost_entries: AtomicU64,
+    /// Whether a thread is draining the buffer, in which case other calls to
+    /// [`LogBuffer::drain`] return without draining anything
+    draining: AtomicBool,
+    entries: [LogEntry; ENTRIES],
+}
+
+/// Entry of a [`LogBuffer`], which is protected by a per-entry sequence lock
+struct LogEntry {
+    /// `0` if the entry was never written, `2 * sequence + 1` while the entry
+    /// with `sequence` is being written, and `2 * sequence + 2` once it is
+    /// written
+    state: AtomicU64,
+    len: AtomicUsize,
+    bytes: [AtomicU8; ENTRY_CAPACITY],
+}
+
+/// Type-erased interface of a [`LogBuffer`], used to store it as the sink of
+/// the print macros
+pub(crate) trait LogSink: Sync {
+    /// Log the message formatted from `args`
+    fn log(&self, args: fmt::Arguments);
+}
+
+impl<const ENTRIES: usize> LogBuffer<ENTRIES> {
+    /// Construct an empty [`LogBuffer`]. `ENTRIES` must not be `0`.
+    #[must_use]
+    pub const fn new() -> Self {
+        const {
+            assert!(ENTRIES != 0, "LogBuffer must have at least one entry");
+        };
+
+        Self {
+            next_sequence: AtomicU64::new(0),
+            read_sequence: AtomicU64::new(0),
+            lost_entries: AtomicU64::new(0),
+            draining: AtomicBool::new(false),
+            entries: [const { LogEntry::new() }; ENTRIES],
+        }
+    }
+
+    /// Log the message formatted from `args`. This never blocks, so it can be
+    /// called at any IRQL if the [`LogBuffer`] is in non-paged memory.
+    pub fn log(&self, args: fmt::Arguments) {
+        let mut writer = LogEntryWriter {
+            log_buffer: self,
+            current_entry: None,
+        };
+        // `LogEntryWriter` never fails, so errors can only come from `Debug` or
+        // `Display` implementations, in which case the partial output is still logged
+        let _ = writer.write_fmt(args);
+        writer.finish_entry();
+    }
+
+    /// Copy the oldest messages that were not drained yet to `output`, and
+    /// return the number of bytes copied. Entries are only copied whole, so
+    /// `output` should be at least [`MINIMUM_DRAIN_LENGTH`] bytes long for
+    /// this to make progress.
+    ///
+    /// Entries that are still being written are left in the buffer, to be
+    /// drained by a later call. This never blocks: if another thread is
+    /// already draining the buffer, this returns `0` without copying anything.
+    pub fn drain(&self, output: &mut [u8]) -> usize {
+        if self
+            .draining
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return 0;
+        }
+
+        let mut written = 0;
+        let mut sequence = self.read_sequence.load(Ordering::Relaxed);
+        let mut lost_entries = self.lost_entries.load(Ordering::Relaxed);
+        let next_sequence = self.next_sequence.load(Ordering::Acquire);
+
+        // Entries older than the last `ENTRIES` ones have been overwritten
+        let oldest_sequence = next_sequence.saturating_sub(ENTRIES as u64);
+        if sequence < oldest_sequence {
+            lost_entries += oldest_sequence - sequence;
+            sequence = oldest_sequence;
+        }
+
+        while sequence < next_sequence {
+            let entry = self.entry(sequence);
+            let state = entry.state.load(Ordering::Acquire);
+            if state < written_state(sequence) {
+                // The entry is still being written, so later entries are left in the
+                // buffer to preserve the order of messages
+                break;
+            }
+            if state > written_state(sequence) {
+                lost_entries += 1;
+                sequence += 1;
+                continue;
+            }
+
+            let len = entry.len.load(Ordering::Relaxed);
+            let lost_entries_line = LostEntriesLine::new(lost_entries);
+            let entry_start = written + lost_entries_line.len;
+            if entry_start + len > output.len() {
+                break;
+            }
+            for (output_byte, entry_byte) in output[entry_start..entry_start + len]
+                .iter_mut()
+                .zip(&entry.bytes)
+            {
+                *output_byte = entry_byte.load(Ordering::Relaxed);
+            }
+
+            // Check that the entry was not overwritten while it was copied
+            fence(Ordering::Acquire);
+            if entry.state.load(Ordering::Relaxed) == state {
+                output[written..entry_start].copy_from_slice(lost_entries_line.as_bytes());
+                written = entry_start + len;
+                lost_entries = 0;
+            } else {
+                lost_entries += 1;
+            }
+            sequence += 1;
+        }
+
+        // Report the entries lost after the last copied entry, if there is room
+        let lost_entries_line = LostEntriesLine::new(lost_entries);
+        if written + lost_entries_line.len <= output.len() {
+            output[written..written + lost_entries_line.len]
+                .copy_from_slice(lost_entries_line.as_bytes());
+            written += lost_entries_line.len;
+            lost_entries = 0;
+        }
+
+        self.read_sequence.store(sequence, Ordering::Relaxed);
+        self.lost_entries.store(lost_entries, Ordering::Relaxed);
+        self.draining.store(false, Ordering::Release);
+        written
+    }
+
+    /// Drain the oldest messages to the output buffer of `request` via
+    /// [`LogBuffer::drain`], and complete it with the number of bytes drained.
+    /// This is meant to be used as the handler of a device I/O control code,
+    /// ex. via [`IoctlRouter::ioctl_with_request`](crate::wdf::IoctlRouter::ioctl_with_request).
+    ///
+    /// Requests whose output buffer is shorter than [`MINIMUM_DRAIN_LENGTH`]
+    /// bytes are completed with `STATUS_BUFFER_TOO_SMALL`. Since the buffer
+    /// is drained, messages are only returned to a single requester, and
+    /// requests received while another one is draining the buffer are
+    /// completed with no bytes. This is not available to WDM drivers, which do
+    /// not use WDF.
+    #[cfg(not(driver_type = "wdm"))]
+    pub fn drain_to_request(&self, mut request: Request) {
+        match request.output_buffer(MINIMUM_DRAIN_LENGTH) {
+            Ok(output) => {
+                let information = self.drain(output);
+                request.complete_with_information(STATUS_SUCCESS, information);
+            }
+            Err(nt_status) => request.complete(nt_status),
+        }
+    }
+
+    /// Get the entry that stores `sequence`
+    const fn entry(&self, sequence: u64) -> &LogEntry {
+        // The remainder is less than `ENTRIES`, so it fits in a usize
+        #[allow(clippy::cast_possible_truncation)]
+        &self.entries[(sequence % ENTRIES as u64) as usize]
+    }
+}
+
+impl<const ENTRIES: usize> Default for LogBuffer<ENTRIES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const ENTRIES: usize> fmt::Debug for LogBuffer<ENTRIES> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LogBuffer")
+            .field("next_sequence", &self.next_sequence)
+            .field("read_sequence", &self.read_sequence)
+            .field("lost_entries", &self.lost_entries)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<const ENTRIES: usize> LogSink for LogBuffer<ENTRIES> {
+    fn log(&self, args: fmt::Arguments) {
+        Self::log(self, args);
+    }
+}
+
+impl LogEntry {
+    const fn new() -> Self {
+        Self {
+            state: AtomicU64::new(0),
+            len: AtomicUsize::new(0),
+            bytes: [const { AtomicU8::new(0) }; ENTRY_CAPACITY],
+        }
+    }
+}
+
+/// State of the entry with `sequence` once it is written
+const fn written_state(sequence: u64) -> u64 {
+    2 * sequence + 2
+}
+
+/// [`Write`] implementation that writes a message to consecutive entries of a
+/// [`LogBuffer`], claiming a new entry whenever the current one fills up
+struct LogEntryWriter<'a, const ENTRIES: usize> {
+    log_buffer: &'a LogBuffer<ENTRIES>,
+    /// Entry being written, along with its sequence number and length
+    current_entry: Option<(&'a LogEntry, u64, usize)>,
+}
+
+impl<'a, const ENTRIES: usize> LogEntryWriter<'a, ENTRIES> {
+    fn start_entry(&self) -> (&'a LogEntry, u64, usize) {
+        let sequence = self
+            .log_buffer
+            .next_sequence
+            .fetch_add(1, Ordering::Relaxed);
+        let entry = self.log_buffer.entry(sequence);
+        entry
+            .state
+            .store(written_state(sequence) - 1, Ordering::Relaxed);
+        // Order the state store before the writes to the entry, so that readers
+        // never observe a partially written entry as written
+        fence(Ordering::Release);
+        (entry, sequence, 0)
+    }
+
+    fn finish_entry(&mut self) {
+        if let Some((entry, sequence, len)) = self.current_entry.take() {
+            entry.len.store(len, Ordering::Relaxed);
+            // If a newer entry started overwriting this one, it is left to that
+            // entry's writer to mark it as written
+            let _ = entry.state.compare_exchange(
+                written_state(sequence) - 1,
+                written_state(sequence),
+                Ordering::Release,
+                Ordering::Relaxed,
+            );
+        }
+    }
+}
+
+impl<const ENTRIES: usize> Write for LogEntryWriter<'_, ENTRIES> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut remaining_bytes = s.as_bytes();
+        while !remaining_bytes.is_empty() {
+            let (entry, sequence, len) = self.current_entry.unwrap_or_else(|| self.start_entry());
+
+            let bytes_to_copy = remaining_bytes.len().min(ENTRY_CAPACITY - len);
+            for (entry_byte, byte) in entry.bytes[len..len + bytes_to_copy]
+                .iter()
+                .zip(&remaining_bytes[..bytes_to_copy])
+            {
+                entry_byte.store(*byte, Ordering::Relaxed);
+            }
+            remaining_bytes = &remaining_bytes[bytes_to_copy..];
+
+            self.current_entry = Some((entry, sequence, len + bytes_to_copy));
+            if len + bytes_to_copy == ENTRY_CAPACITY {
+                self.finish_entry();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Line inserted by [`LogBuffer::drain`] in place of entries that were
+/// overwritten before they were drained. Empty if no entries were lost.
+struct LostEntriesLine {
+    buffer: [u8; LOST_ENTRIES_LINE_CAPACITY],
+    len: usize,
+}
+
+impl LostEntriesLine {
+    fn new(lost_entries: u64) -> Self {
+        let mut line = Self {
+            buffer: [0; LOST_ENTRIES_LINE_CAPACITY],
+            len: 0,
+        };
+        if lost_entries != 0 {
+            // The line always fits in the buffer, since a u64 has at most 20 digits
+            let _ = writeln!(line, "[{lost_entries} log entries lost]");
+        }
+        line
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.buffer[..self.len]
+    }
+}
+
+impl Write for LostEntriesLine {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let bytes = s.as_bytes();
+        let end = self.len + bytes.len();
+        if end > LOST_ENTRIES_LINE_CAPACITY {
+            return Err(fmt::Error);
+        }
+        self.buffer[self.len..end].copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+}
@@ -0,0 +1,358 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Memory descriptor lists (MDLs) describing the physical pages of a buffer
+//!
+//! [`Mdl`] owns an MDL allocated via `IoAllocateMdl`. Its pages are locked in
+//! memory via [`Mdl::lock_pages`], after which the buffer can be mapped into
+//! system address space and accessed as a slice, ex. from an arbitrary thread
+//! context or at `IRQL` = `DISPATCH_LEVEL`. When the [`Mdl`] is dropped, its
+//! pages are unmapped and unlocked, and the MDL is freed.
+//!
+//! `MmProbeAndLockPages` reports that the buffer is not accessible by raising
+//! a structured exception, which Rust code cannot catch: the exception is
+//! left unhandled, which bugchecks the system. This is why
+//! [`Mdl::lock_pages`] is `unsafe`, and why the buffer must be validated
+//! before its pages are locked. The user-mode buffers of direct I/O requests
+//! are already probed and locked by the I/O manager, so drivers should access
+//! them via the MDL of the request instead of locking them again.
+//!
+//! ```rust, no_run
+//! use wdk::mdl::{AccessMode, LockOperation, Mdl};
+//!
+//! # fn example() -> wdk::Result<()> {
+//! let mut buffer = [0_u8; 64];
+//! let mut mdl = Mdl::allocate(buffer.as_mut_ptr().cast(), 64)?;
+//! // SAFETY: `buffer` is a valid kernel-mode buffer that outlives `mdl`.
+//! unsafe { mdl.lock_pages(AccessMode::Kernel, LockOperation::Write)? };
+//! // SAFETY: `buffer` is not accessed other than via `mdl` while the slice is
+//! // alive.
+//! let slice = unsafe { mdl.as_mut_slice()? };
+//! slice[0] = 1;
+//! # Ok(())
+//! # }
+//! ```
+
+use core::{ffi::c_void, ptr::NonNull};
+
+use wdk_sys::{
+    ntddk::{
+        IoAllocateMdl,
+        IoFreeMdl,
+        MmMapLockedPagesSpecifyCache,
+        MmProbeAndLockPages,
+        MmUnlockPages,
+    },
+    _LOCK_OPERATION,
+    _MEMORY_CACHING_TYPE,
+    _MM_PAGE_PRIORITY,
+    _MODE,
+    KPROCESSOR_MODE,
+    LOCK_OPERATION,
+    MDL,
+    MDL_MAPPED_TO_SYSTEM_VA,
+    MDL_SOURCE_IS_NONPAGED_POOL,
+    MdlMappingNoExecute,
+    NTSTATUS,
+    PMDL,
+    STATUS_ACCESS_DENIED,
+    STATUS_INSUFFICIENT_RESOURCES,
+    STATUS_INVALID_DEVICE_STATE,
+};
+
+/// Processor mode that the buffer of an [`Mdl`] is probed for by
+/// [`Mdl::lock_pages`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessMode {
+    /// The buffer is accessed by kernel-mode code, so it is not checked to be
+    /// a user-mode address (`KernelMode`)
+    Kernel,
+    /// The buffer was provided by user-mode code, so it is checked to be a
+    /// user-mode address that the process can access (`UserMode`)
+    User,
+}
+
+/// Access that the pages of an [`Mdl`] are locked for by [`Mdl::lock_pages`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockOperation {
+    /// The driver only reads from the buffer (`IoReadAccess`)
+    Read,
+    /// The driver writes to the buffer (`IoWriteAccess`)
+    Write,
+    /// The driver reads from and writes to the buffer (`IoModifyAccess`)
+    Modify,
+}
+
+/// Memory descriptor list describing the pages of a buffer, which are
+/// unlocked when the [`Mdl`] is dropped, before the MDL itself is freed.
+///
+/// See the [module documentation](self) for how an [`Mdl`] is used.
+pub struct Mdl {
+    mdl: NonNull<MDL>,
+    // Access that the pages are locked for, or `None` if they are not locked
+    lock_operation: Option<LockOperation>,
+}
+
+impl AccessMode {
+    // KernelMode and UserMode are zero and one, so they fit in a KPROCESSOR_MODE
+    #[allow(clippy::cast_possible_truncation)]
+    const fn as_raw(self) -> KPROCESSOR_MODE {
+        match self {
+            Self::Kernel => _MODE::KernelMode as KPROCESSOR_MODE,
+            Self::User => _MODE::UserMode as KPROCESSOR_MODE,
+        }
+    }
+}
+
+impl LockOperation {
+    const fn as_raw(self) -> LOCK_OPERATION {
+        match self {
+            Self::Read => _LOCK_OPERATION::IoReadAccess,
+            Self::Write => _LOCK_OPERATION::IoWriteAccess,
+            Self::Modify => _LOCK_OPERATION::IoModifyAccess,
+        }
+    }
+}
+
+impl Mdl {
+    /// Allocate an MDL describing the `length` bytes of the buffer at
+    /// `virtual_address`. The buffer is not accessed until its pages are
+    /// locked via [`Mdl::lock_pages`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INSUFFICIENT_RESOURCES` if the MDL
+    /// could not be allocated, ex. because `length` is too large to be
+    /// described by a single MDL.
+    // The buffer is only accessed by `lock_pages` and the functions that require its
+    // pages to be locked, whose callers are responsible for its validity
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    pub fn allocate(virtual_address: *mut c_void, length: u32) -> Result<Self, NTSTATUS> {
+        // SAFETY: `IoAllocateMdl` only computes the pages of the buffer from its
+        // address, without accessing it, and the MDL is not associated with an
+        // IRP.
+        let mdl = unsafe {
+            IoAllocateMdl(
+                virtual_address,
+                length,
+                u8::from(false),
+                u8::from(false),
+                core::ptr::null_mut(),
+            )
+        };
+
+        NonNull::new(mdl)
+            .map(|mdl| Self {
+                mdl,
+                lock_operation: None,
+            })
+            .ok_or(STATUS_INSUFFICIENT_RESOURCES)
+    }
+
+    /// Probe the buffer of the MDL for `operation` by code running in
+    /// `access_mode`, and lock its pages in memory, so that they can be
+    /// accessed via [`Mdl::as_slice`] and [`Mdl::as_mut_slice`]. The pages
+    /// stay locked until the [`Mdl`] is dropped.
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INVALID_DEVICE_STATE` if the pages
+    /// are already locked.
+    ///
+    /// # Safety
+    ///
+    /// `MmProbeAndLockPages` raises an exception that cannot be handled by
+    /// Rust code if the buffer is not accessible, so the caller must ensure
+    /// that:
+    ///
+    /// * The buffer is valid for `operation` for the duration of this call. For
+    ///   [`AccessMode::User`], this means that the user-mode process must not
+    ///   be able to free or change the protection of the buffer, ex. because it
+    ///   was secured via `MmSecureVirtualMemory`.
+    /// * For [`AccessMode::User`], this is called in the context of the process
+    ///   that owns the buffer.
+    /// * This is called at `IRQL` <= `APC_LEVEL`, or at `IRQL` =
+    ///   `DISPATCH_LEVEL` if the buffer is in nonpaged memory.
+    pub unsafe fn lock_pages(
+        &mut self,
+        access_mode: AccessMode,
+        operation: LockOperation,
+    ) -> Result<(), NTSTATUS> {
+        if self.lock_operation.is_some() {
+            return Err(STATUS_INVALID_DEVICE_STATE);
+        }
+
+        // SAFETY: The MDL is valid and its pages are not locked yet, and the caller
+        // guarantees that probing the buffer succeeds.
+        unsafe {
+            MmProbeAndLockPages(self.mdl.as_ptr(), access_mode.as_raw(), operation.as_raw());
+        }
+        self.lock_operation = Some(operation);
+        Ok(())
+    }
+
+    /// Get the access that the pages of the MDL are locked for, or `None` if
+    /// they are not locked
+    #[must_use]
+    pub const fn lock_operation(&self) -> Option<LockOperation> {
+        self.lock_operation
+    }
+
+    /// Get the length of the buffer described by the MDL, in bytes
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        // SAFETY: The MDL is valid for as long as `self` is alive.
+        let byte_count = unsafe { (*self.mdl.as_ptr()).ByteCount };
+        byte_count as usize
+    }
+
+    /// Returns `true` if the buffer described by the MDL is empty
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the address of the buffer in system address space, mapping its
+    /// locked pages if they are not mapped yet. The mapping is not executable,
+    /// and is released when the pages are unlocked. This is equivalent to
+    /// `MmGetSystemAddressForMdlSafe` with `NormalPagePriority`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return:
+    /// * `STATUS_INVALID_DEVICE_STATE` if the pages are not locked
+    /// * `STATUS_INSUFFICIENT_RESOURCES` if the pages could not be mapped
+    pub fn system_address(&mut self) -> Result<NonNull<u8>, NTSTATUS> {
+        if self.lock_operation.is_none() {
+            return Err(STATUS_INVALID_DEVICE_STATE);
+        }
+
+        // SAFETY: The MDL is valid for as long as `self` is alive, its pages are
+        // locked, and it is not accessed concurrently since `self` is borrowed mutably.
+        unsafe { system_address_for_mdl(self.mdl) }
+    }
+
+    /// Get the buffer described by the MDL as a slice, mapping its locked
+    /// pages into system address space via [`Mdl::system_address`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return the errors of [`Mdl::system_address`].
+    ///
+    /// # Safety
+    ///
+    /// The buffer must not be written to while the returned slice is alive,
+    /// including by user-mode code via its own mapping of a user-mode buffer.
+    /// Buffers that user-mode code can write to concurrently should be copied
+    /// before they are validated, instead of being accessed via a slice.
+    pub unsafe fn as_slice(&mut self) -> Result<&[u8], NTSTATUS> {
+        let system_address = self.system_address()?;
+        // SAFETY: The locked pages are mapped at `system_address` for `self.len()`
+        // bytes until `self` is dropped, which cannot happen while the slice borrows
+        // it, and the caller guarantees that the buffer is not written to while the
+        // slice is alive.
+        Ok(unsafe { core::slice::from_raw_parts(system_address.as_ptr(), self.len()) })
+    }
+
+    /// Get the buffer described by the MDL as a mutable slice, mapping its
+    /// locked pages into system address space via [`Mdl::system_address`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return:
+    /// * `STATUS_ACCESS_DENIED` if the pages are locked for
+    ///   [`LockOperation::Read`]
+    /// * the errors of [`Mdl::system_address`]
+    ///
+    /// # Safety
+    ///
+    /// The buffer must not be accessed other than via the returned slice while
+    /// it is alive, including by user-mode code via its own mapping of a
+    /// user-mode buffer.
+    pub unsafe fn as_mut_slice(&mut self) -> Result<&mut [u8], NTSTATUS> {
+        if self.lock_operation == Some(LockOperation::Read) {
+            return Err(STATUS_ACCESS_DENIED);
+        }
+
+        let system_address = self.system_address()?;
+        // SAFETY: The locked pages are mapped at `system_address` for `self.len()`
+        // bytes until `self` is dropped, which cannot happen while the slice borrows
+        // it, they are locked for write access, and the caller guarantees that the
+        // buffer is not accessed other than via the slice while it is alive.
+        Ok(unsafe { core::slice::from_raw_parts_mut(system_address.as_ptr(), self.len()) })
+    }
+
+    /// Get the raw `PMDL`, ex. to pass it to WDK APIs that take an MDL. The
+    /// MDL is still owned by `self`.
+    #[must_use]
+    pub const fn as_raw(&self) -> PMDL {
+        self.mdl.as_ptr()
+    }
+}
+
+impl Drop for Mdl {
+    fn drop(&mut self) {
+        if self.lock_operation.is_some() {
+            // SAFETY: The pages of the MDL were locked by `lock_pages`, and are unlocked
+            // exactly once here, which also releases their system address space
+            // mapping.
+            unsafe {
+                MmUnlockPages(self.mdl.as_ptr());
+            }
+        }
+
+        // SAFETY: The MDL was allocated by `IoAllocateMdl`, its pages are no longer
+        // locked, and it is freed exactly once here.
+        unsafe {
+            IoFreeMdl(self.mdl.as_ptr());
+        }
+    }
+}
+
+/// Get the address of the buffer described by `mdl` in system address space,
+/// mapping its locked pages if they are not mapped yet. The mapping is not
+/// executable, and is released when the pages are unlocked. This is equivalent
+/// to `MmGetSystemAddressForMdlSafe` with `NormalPagePriority`, and backs
+/// [`Mdl::system_address`] as well as the MDLs that drivers receive from the
+/// framework (ex. the buffers of SPB transfers).
+///
+/// # Errors
+///
+/// This function will return `STATUS_INSUFFICIENT_RESOURCES` if the pages
+/// could not be mapped.
+///
+/// # Safety
+///
+/// `mdl` must be a valid MDL whose pages are locked, which is not accessed
+/// concurrently for the duration of this call.
+pub(crate) unsafe fn system_address_for_mdl(mdl: NonNull<MDL>) -> Result<NonNull<u8>, NTSTATUS> {
+    // SAFETY: The caller guarantees that the MDL is valid.
+    let (mdl_flags, mapped_system_va) = unsafe {
+        let mdl = &*mdl.as_ptr();
+        (mdl.MdlFlags, mdl.MappedSystemVa)
+    };
+    // The MDL flags are a bitmask stored in a CSHORT
+    #[allow(clippy::cast_sign_loss)]
+    let mdl_flags = u32::from(mdl_flags as u16);
+    if mdl_flags & (MDL_MAPPED_TO_SYSTEM_VA | MDL_SOURCE_IS_NONPAGED_POOL) != 0 {
+        return NonNull::new(mapped_system_va.cast()).ok_or(STATUS_INSUFFICIENT_RESOURCES);
+    }
+
+    // NormalPagePriority is non-negative, so it fits in a ULONG
+    #[allow(clippy::cast_sign_loss)]
+    let priority = _MM_PAGE_PRIORITY::NormalPagePriority as u32 | MdlMappingNoExecute;
+    // SAFETY: The caller guarantees that the pages of the MDL are locked, and they
+    // are not mapped yet. Failures are reported by returning null instead of
+    // bugchecking, and the mapping is released by `MmUnlockPages`.
+    let system_address = unsafe {
+        MmMapLockedPagesSpecifyCache(
+            mdl.as_ptr(),
+            AccessMode::Kernel.as_raw(),
+            _MEMORY_CACHING_TYPE::MmCached,
+            core::ptr::null_mut(),
+            u32::from(false),
+            priority,
+        )
+    };
+    NonNull::new(system_address.cast()).ok_or(STATUS_INSUFFICIENT_RESOURCES)
+}
@@ -0,0 +1,308 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Driver-wide counters for performance investigations, which can be written
+//! to ETW or queried from user mode.
+//!
+//! A counter is declared and incremented in a single step via the
+//! [`counter!`] macro. Every call site of the macro owns a separate
+//! [`Counter`], which is registered in a driver-wide list the first time it is
+//! incremented. Counters that are incremented from several places should
+//! instead be declared as a `static` [`Counter`], and incremented via
+//! [`Counter::add`].
+//!
+//! Incrementing a counter never blocks nor allocates, so it can be done at any
+//! IRQL. Each counter keeps [`SLOTS`] values on separate cache lines, and each
+//! processor only increments the value that is assigned to it, so processors
+//! do not contend for the same cache line in the hot path. The value of a
+//! counter is the sum of its slots at the time it is read.
+//!
+//! The registered counters can be written to a
+//! [`TraceLoggingProvider`](crate::etw::TraceLoggingProvider) via
+//! [`write_to_etw`] (ex. periodically from a timer), or returned to user mode
+//! via [`complete_request`]:
+//!
+//! ```rust, no_run
+//! use wdk::{
+//!     metrics::{self, counter},
+//!     wdf::{Device, IoQueue, IoctlRouter},
+//! };
+//! use wdk_sys::_WDF_IO_QUEUE_DISPATCH_TYPE::WdfIoQueueDispatchParallel;
+//!
+//! const IOCTL_READ: u32 = 0x0022_2000;
+//! const IOCTL_QUERY_METRICS: u32 = 0x0022_2004;
+//!
+//! fn create_queue(device: &Device) -> wdk::Result<IoQueue> {
+//!     IoctlRouter::new()
+//!         .ioctl(IOCTL_READ, |_queue, (): ()| {
+//!             counter!("reads");
+//!             Ok(0u32)
+//!         })
+//!         .ioctl_with_request(IOCTL_QUERY_METRICS, |_queue, request| {
+//!             metrics::complete_request(request);
+//!         })
+//!         .create_queue(device, WdfIoQueueDispatchParallel)
+//! }
+//! ```
+
+use core::{
+    fmt::{self, Write},
+    sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering},
+};
+
+#[cfg(not(driver_type = "wdm"))]
+use wdk_sys::{STATUS_BUFFER_OVERFLOW, STATUS_SUCCESS};
+use wdk_sys::{ntddk::KeGetCurrentProcessorNumberEx, ULONG};
+
+#[doc(inline)]
+pub use crate::counter;
+use crate::etw::{Field, Level, TraceLoggingProvider};
+#[cfg(not(driver_type = "wdm"))]
+use crate::wdf::Request;
+
+/// Number of per-processor values kept by each [`Counter`]. Processors whose
+/// index is larger share the values of lower processors.
+pub const SLOTS: usize = 16;
+
+/// Head of the driver-wide list of registered counters
+static COUNTERS: AtomicPtr<Counter> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Driver-wide counter, usually declared via the [`counter!`](crate::counter)
+/// macro.
+///
+/// Counters must be `static`, since they are registered in a driver-wide list
+/// the first time they are incremented, and are never unregistered.
+pub struct Counter {
+    name: &'static str,
+    slots: [Slot; SLOTS],
+    /// Whether the counter was pushed to the list of registered counters
+    registered: AtomicBool,
+    /// Next counter in the list of registered counters
+    next: AtomicPtr<Counter>,
+}
+
+/// Per-processor value of a [`Counter`], aligned to a cache line so that
+/// processors incrementing different slots do not contend
+#[repr(align(64))]
+struct Slot(AtomicU64);
+
+impl Counter {
+    /// Construct a [`Counter`] named `name`, whose value is `0`
+    #[must_use]
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            slots: [const { Slot(AtomicU64::new(0)) }; SLOTS],
+            registered: AtomicBool::new(false),
+            next: AtomicPtr::new(core::ptr::null_mut()),
+        }
+    }
+
+    /// Add `value` to the counter, registering it if this is the first time
+    /// it is incremented. This never blocks, so it can be called at any IRQL.
+    pub fn add(&'static self, value: u64) {
+        if !self.registered.load(Ordering::Relaxed) {
+            self.register();
+        }
+
+        // SAFETY: `KeGetCurrentProcessorNumberEx` can be called at any IRQL, and
+        // accepts a null `ProcNumber`.
+        let processor_index = unsafe { KeGetCurrentProcessorNumberEx(core::ptr::null_mut()) };
+        self.slots[slot_index(processor_index)]
+            .0
+            .fetch_add(value, Ordering::Relaxed);
+    }
+
+    /// Add `1` to the counter
+    pub fn increment(&'static self) {
+        self.add(1);
+    }
+
+    /// Get the name of the counter
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Get the current value of the counter. Since the slots of the counter
+    /// are read one after the other, increments that happen concurrently may
+    /// or may not be included.
+    #[must_use]
+    pub fn value(&self) -> u64 {
+        self.slots.iter().fold(0, |value, slot| {
+            value.wrapping_add(slot.0.load(Ordering::Relaxed))
+        })
+    }
+
+    /// Push the counter to the head of the list of registered counters, unless
+    /// another thread already did
+    fn register(&'static self) {
+        if self.registered.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let counter = core::ptr::from_ref(self).cast_mut();
+        let mut head = COUNTERS.load(Ordering::Relaxed);
+        loop {
+            self.next.store(head, Ordering::Relaxed);
+            match COUNTERS.compare_exchange_weak(
+                head,
+                counter,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(current_head) => head = current_head,
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Counter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Counter")
+            .field("name", &self.name)
+            .field("value", &self.value())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Iterator over the registered counters, returned by [`counters`]
+#[derive(Clone, Debug)]
+pub struct Counters {
+    next: Option<&'static Counter>,
+}
+
+impl Iterator for Counters {
+    type Item = &'static Counter;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let counter = self.next?;
+        // SAFETY: Only `static` counters are pushed to the list, and they are never
+        // removed from it.
+        self.next = unsafe { counter.next.load(Ordering::Acquire).as_ref() };
+        Some(counter)
+    }
+}
+
+/// Get an iterator over the counters that have been incremented at least once,
+/// from the most to the least recently registered
+#[must_use]
+pub fn counters() -> Counters {
+    Counters {
+        // SAFETY: Only `static` counters are pushed to the list, and they are never
+        // removed from it.
+        next: unsafe { COUNTERS.load(Ordering::Acquire).as_ref() },
+    }
+}
+
+/// Write a `Counter` event with the `Name` and `Value` of each registered
+/// counter to `provider`, if any trace session is listening for events with
+/// `level`
+pub fn write_to_etw(provider: &TraceLoggingProvider, level: Level) {
+    if !provider.enabled(level, 0) {
+        return;
+    }
+
+    for counter in counters() {
+        // Tracing is best-effort, so failures to write an event are ignored
+        let _ = provider.write_event(
+            "Counter",
+            level,
+            0,
+            &[
+                Field::str8("Name", counter.name()),
+                Field::u64("Value", counter.value()),
+            ],
+        );
+    }
+}
+
+/// Write a `name=value` line for each registered counter to `output`, and
+/// return the number of bytes written along with whether all counters fit.
+/// Lines are only written whole.
+pub fn write_to_buffer(output: &mut [u8]) -> (usize, bool) {
+    let mut writer = SliceWriter { output, len: 0 };
+    for counter in counters() {
+        let line_start = writer.len;
+        if writeln!(writer, "{}={}", counter.name(), counter.value()).is_err() {
+            return (line_start, false);
+        }
+    }
+    (writer.len, true)
+}
+
+/// Complete `request` with the output of [`write_to_buffer`] written to its
+/// output buffer. The request is completed with `STATUS_BUFFER_OVERFLOW` if the
+/// output buffer is too small to fit every counter, in which case the lines
+/// that fit are still returned.
+#[cfg(not(driver_type = "wdm"))]
+pub fn complete_request(mut request: Request) {
+    match request.output_buffer(0) {
+        Ok(output) => {
+            let (information, complete) = write_to_buffer(output);
+            let nt_status = if complete {
+                STATUS_SUCCESS
+            } else {
+                STATUS_BUFFER_OVERFLOW
+            };
+            request.complete_with_information(nt_status, information);
+        }
+        Err(nt_status) => request.complete(nt_status),
+    }
+}
+
+/// Declare a [`Counter`](crate::metrics::Counter) named `$name` and add
+/// `$value` (`1` by default) to it.
+///
+/// Every call site of the macro declares a separate counter, even if their
+/// names are the same.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// # fn f(buffer: &[u8]) {
+/// wdk::counter!("reads");
+/// wdk::counter!("bytes_read", buffer.len() as u64);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! counter {
+    ($name:expr) => {
+        $crate::counter!($name, 1)
+    };
+
+    ($name:expr, $value:expr) => {{
+        static COUNTER: $crate::metrics::Counter = $crate::metrics::Counter::new($name);
+        COUNTER.add($value);
+    }};
+}
+
+/// Get the slot of a counter that the processor with `processor_index`
+/// increments
+const fn slot_index(processor_index: ULONG) -> usize {
+    // The remainder is less than `SLOTS`, so it fits in a usize
+    #[allow(clippy::cast_possible_truncation)]
+    {
+        (processor_index % SLOTS as ULONG) as usize
+    }
+}
+
+/// [`Write`] implementation over a byte slice, which fails instead of
+/// truncating once the slice is full
+struct SliceWriter<'a> {
+    output: &'a mut [u8],
+    len: usize,
+}
+
+impl Write for SliceWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.output.len() {
+            return Err(fmt::Error);
+        }
+        self.output[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
@@ -1,13 +1,147 @@
 // Copyright (c) Microsoft Corporation
 // License: MIT OR Apache-2.0
 
-extern crate alloc;
+//! Printing to the kernel debugger via [`DbgPrintEx`].
+//!
+//! The [`print!`](crate::print), [`println!`](crate::println) and
+//! [`debug!`](crate::debug) macros format their arguments into a fixed-size
+//! stack buffer, so they do not allocate and can be used at any IRQL that
+//! [`DbgPrintEx`] supports. Messages are printed with the component ID and
+//! level configured via [`set_component_id`] and [`set_level`].
+//!
+//! Since [`DbgPrintEx`] output is only visible while a debugger is attached,
+//! the print macros can additionally log every message to a
+//! [`LogBuffer`](crate::log_buffer::LogBuffer) set via [`set_log_buffer`],
+//! from which it can be drained later.
 
-use alloc::ffi::CString;
+use core::{
+    cell::UnsafeCell,
+    fmt::{self, Write},
+    sync::atomic::{AtomicU32, AtomicU8, Ordering},
+};
 
-use wdk_sys::ntddk::DbgPrint;
+use wdk_sys::{
+    ntddk::DbgPrintEx,
+    _DPFLTR_TYPE::DPFLTR_IHVDRIVER_ID,
+    DPFLTR_ERROR_LEVEL,
+    DPFLTR_INFO_LEVEL,
+    DPFLTR_TRACE_LEVEL,
+    DPFLTR_WARNING_LEVEL,
+    NTSTATUS,
+    STATUS_ALREADY_REGISTERED,
+    ULONG,
+};
 
-/// print to kernel debugger via [`wdk_sys::ntddk::DbgPrint`]
+use crate::log_buffer::{LogBuffer, LogSink};
+
+/// Maximum number of bytes passed to a single [`DbgPrintEx`] call, including
+/// the null terminator. This matches the limit of the debug print buffer, so
+/// longer messages are split across multiple calls.
+const DBG_PRINT_BUFFER_SIZE: usize = 512;
+
+/// Severity level of a debug print, which is used by the kernel to filter
+/// messages based on the component's debug print mask
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum Level {
+    /// `DPFLTR_ERROR_LEVEL`. Messages at this level are always printed.
+    Error = DPFLTR_ERROR_LEVEL,
+    /// `DPFLTR_WARNING_LEVEL`
+    Warning = DPFLTR_WARNING_LEVEL,
+    /// `DPFLTR_TRACE_LEVEL`
+    Trace = DPFLTR_TRACE_LEVEL,
+    /// `DPFLTR_INFO_LEVEL`
+    Info = DPFLTR_INFO_LEVEL,
+}
+
+// DPFLTR_IHVDRIVER_ID is a small positive enum value
+#[allow(clippy::cast_sign_loss)]
+static COMPONENT_ID: AtomicU32 = AtomicU32::new(DPFLTR_IHVDRIVER_ID as ULONG);
+static LEVEL: AtomicU32 = AtomicU32::new(Level::Info as ULONG);
+static LOG_BUFFER: GlobalLogBuffer = GlobalLogBuffer::new();
+
+const LOG_BUFFER_UNSET: u8 = 0;
+const LOG_BUFFER_SETTING: u8 = 1;
+const LOG_BUFFER_SET: u8 = 2;
+
+/// [`LogBuffer`] that receives every message printed by the print macros,
+/// which can be set once
+struct GlobalLogBuffer {
+    state: AtomicU8,
+    log_buffer: UnsafeCell<Option<&'static dyn LogSink>>,
+}
+
+// SAFETY: `log_buffer` is only written once, by the thread that transitions
+// `state` from `LOG_BUFFER_UNSET` to `LOG_BUFFER_SETTING`, and only read after
+// `state` is `LOG_BUFFER_SET`. The referenced `LogSink` is `Sync`.
+unsafe impl Sync for GlobalLogBuffer {}
+
+impl GlobalLogBuffer {
+    const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(LOG_BUFFER_UNSET),
+            log_buffer: UnsafeCell::new(None),
+        }
+    }
+
+    fn get(&self) -> Option<&'static dyn LogSink> {
+        if self.state.load(Ordering::Acquire) != LOG_BUFFER_SET {
+            return None;
+        }
+        // SAFETY: `log_buffer` is never written again once `state` is
+        // `LOG_BUFFER_SET`, and the `Acquire` load synchronizes with its write.
+        unsafe { *self.log_buffer.get() }
+    }
+}
+
+/// Set the component ID (ex. `DPFLTR_IHVDRIVER_ID`) passed to [`DbgPrintEx`]
+/// by the print macros. Defaults to `DPFLTR_IHVDRIVER_ID`.
+pub fn set_component_id(component_id: ULONG) {
+    COMPONENT_ID.store(component_id, Ordering::Relaxed);
+}
+
+/// Set the [`Level`] passed to [`DbgPrintEx`] by the print macros. Defaults
+/// to [`Level::Info`].
+pub fn set_level(level: Level) {
+    LEVEL.store(level as ULONG, Ordering::Relaxed);
+}
+
+/// Set `log_buffer` as a sink of the print macros.
+///
+/// Every message printed via [`DbgPrintEx`] by the print macros and by
+/// [`print_with_level`], including the events of the
+/// [`tracing`](crate::tracing) adapter, is also logged to `log_buffer`.
+///
+/// # Errors
+///
+/// This function will return `STATUS_ALREADY_REGISTERED` if a log buffer was
+/// already set.
+pub fn set_log_buffer<const ENTRIES: usize>(
+    log_buffer: &'static LogBuffer<ENTRIES>,
+) -> Result<(), NTSTATUS> {
+    if LOG_BUFFER
+        .state
+        .compare_exchange(
+            LOG_BUFFER_UNSET,
+            LOG_BUFFER_SETTING,
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        )
+        .is_err()
+    {
+        return Err(STATUS_ALREADY_REGISTERED);
+    }
+
+    // SAFETY: Transitioning `state` to `LOG_BUFFER_SETTING` grants exclusive access
+    // to `log_buffer`, since it is not read until `state` is `LOG_BUFFER_SET`.
+    unsafe {
+        *LOG_BUFFER.log_buffer.get() = Some(log_buffer);
+    }
+    LOG_BUFFER.state.store(LOG_BUFFER_SET, Ordering::Release);
+    Ok(())
+}
+
+/// print to kernel debugger via [`wdk_sys::ntddk::DbgPrintEx`]
 #[macro_export]
 macro_rules! print {
     ($($arg:tt)*) => {
@@ -15,7 +149,7 @@ macro_rules! print {
     };
 }
 
-/// print with newline to debugger via [`wdk_sys::ntddk::DbgPrint`]
+/// print with newline to debugger via [`wdk_sys::ntddk::DbgPrintEx`]
 #[macro_export]
 macro_rules! println {
     () => {
@@ -27,20 +161,123 @@ macro_rules! println {
     };
 }
 
+/// Prints and returns the value of a given expression for quick and dirty
+/// debugging.
+///
+/// This behaves like [`std::dbg!`](https://doc.rust-lang.org/std/macro.dbg.html),
+/// but prints to the kernel debugger via [`wdk_sys::ntddk::DbgPrintEx`].
+#[macro_export]
+macro_rules! debug {
+    () => {
+        $crate::println!("[{}:{}:{}]", file!(), line!(), column!())
+    };
+
+    ($val:expr $(,)?) => {
+        // Use of `match` here is intentional because it affects the lifetimes
+        // of temporaries, same as `std::dbg!`
+        match $val {
+            tmp => {
+                $crate::println!(
+                    "[{}:{}:{}] {} = {:#?}",
+                    file!(),
+                    line!(),
+                    column!(),
+                    stringify!($val),
+                    &tmp
+                );
+                tmp
+            }
+        }
+    };
+
+    ($($val:expr),+ $(,)?) => {
+        ($($crate::debug!($val)),+,)
+    };
+}
+
 /// Internal implementation of print macros. This function is an implementation
 /// detail and should never be called directly, but must be public to be useable
 /// by the print! and println! macro
-///
-/// # Panics
-///
-/// Panics if an internal null byte is passed in
 #[doc(hidden)]
-pub fn _print(args: core::fmt::Arguments) {
-    let formatted_string = CString::new(alloc::format!("{args}"))
-        .expect("CString should be able to be created from a String.");
+pub fn _print(args: fmt::Arguments) {
+    dbg_print(LEVEL.load(Ordering::Relaxed), args);
+}
 
-    // SAFETY: `formatted_string` is a valid null terminated string
-    unsafe {
-        DbgPrint(formatted_string.as_ptr());
+/// Print `args` to the kernel debugger at `level`, ignoring the level
+/// configured via [`set_level`]. The component ID configured via
+/// [`set_component_id`] is still used.
+pub fn print_with_level(level: Level, args: fmt::Arguments) {
+    dbg_print(level as ULONG, args);
+}
+
+fn dbg_print(level: ULONG, args: fmt::Arguments) {
+    if let Some(log_buffer) = LOG_BUFFER.get() {
+        log_buffer.log(args);
+    }
+
+    let mut writer = DbgPrintWriter::new(COMPONENT_ID.load(Ordering::Relaxed), level);
+    // `DbgPrintWriter` never fails, so errors can only come from `Debug` or
+    // `Display` implementations, in which case the partial output is still printed
+    let _ = writer.write_fmt(args);
+    writer.flush();
+}
+
+/// [`Write`] implementation that buffers output on the stack and prints it via
+/// [`DbgPrintEx`] whenever the buffer fills up, and when flushed
+struct DbgPrintWriter {
+    component_id: ULONG,
+    level: ULONG,
+    buffer: [u8; DBG_PRINT_BUFFER_SIZE],
+    len: usize,
+}
+
+impl DbgPrintWriter {
+    const fn new(component_id: ULONG, level: ULONG) -> Self {
+        Self {
+            component_id,
+            level,
+            buffer: [0; DBG_PRINT_BUFFER_SIZE],
+            len: 0,
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.len == 0 {
+            return;
+        }
+        self.buffer[self.len] = 0;
+
+        // SAFETY: The format string and `buffer` are valid null terminated strings,
+        // and the `%s` format specifier matches the single argument passed
+        unsafe {
+            DbgPrintEx(
+                self.component_id,
+                self.level,
+                c"%s".as_ptr(),
+                self.buffer.as_ptr(),
+            );
+        }
+        self.len = 0;
+    }
+}
+
+impl Write for DbgPrintWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut remaining_bytes = s.as_bytes();
+        while !remaining_bytes.is_empty() {
+            // Always leave room for the null terminator
+            let remaining_capacity = DBG_PRINT_BUFFER_SIZE - 1 - self.len;
+            if remaining_capacity == 0 {
+                self.flush();
+                continue;
+            }
+
+            let bytes_to_copy = remaining_bytes.len().min(remaining_capacity);
+            self.buffer[self.len..self.len + bytes_to_copy]
+                .copy_from_slice(&remaining_bytes[..bytes_to_copy]);
+            self.len += bytes_to_copy;
+            remaining_bytes = &remaining_bytes[bytes_to_copy..];
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,317 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Runtime support for `no_std` drivers.
+//!
+//! This module provides a default panic handler, which can be installed via
+//! [`driver_entry`](crate::driver_entry), or manually:
+//!
+//! ```rust, no_run
+//! #[cfg(not(test))]
+//! #[panic_handler]
+//! fn panic(info: &core::panic::PanicInfo) -> ! {
+//!     wdk::runtime::panic_handler(info)
+//! }
+//! ```
+//!
+//! Before breaking into the debugger or bugchecking, the panic handler reports
+//! the panic to a `RustPanic` ETW event on the provider configured via
+//! [`set_panic_telemetry_provider`], and to the callback configured via
+//! [`set_panic_callback`]. This lets production telemetry tell Rust panics
+//! apart from other bugchecks. The callback can be used to capture extra
+//! state, such as a WER live kernel report.
+//!
+//! ```rust, no_run
+//! use wdk::{etw::TraceLoggingProvider, runtime};
+//!
+//! fn enable_panic_telemetry(provider: &'static TraceLoggingProvider) {
+//!     runtime::set_panic_telemetry_provider(provider);
+//!     runtime::set_panic_callback(|_report| {
+//!         // Capture a WER live kernel report, or other driver state, here
+//!     });
+//! }
+//! ```
+
+use core::{
+    fmt::{self, Write},
+    panic::PanicInfo,
+    sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, Ordering},
+};
+
+use wdk_sys::{
+    ntddk::{DbgPrintEx, RtlCaptureStackBackTrace},
+    _DPFLTR_TYPE::DPFLTR_IHVDRIVER_ID,
+    DPFLTR_ERROR_LEVEL,
+    PVOID,
+    ULONG,
+};
+#[cfg(not(debug_assertions))]
+use wdk_sys::{ntddk::KeBugCheckEx, ULONG_PTR};
+
+use crate::{
+    etw::{Level, TraceLoggingProvider},
+    trace_event,
+};
+
+/// Bugcheck code used by [`panic_handler`] if none is configured via
+/// [`set_bugcheck_code`]. This is `"RUST"` encoded as ASCII.
+pub const DEFAULT_BUGCHECK_CODE: ULONG = 0x5255_5354;
+
+/// Maximum length of the panic message logged by [`panic_handler`], including
+/// the null terminator. Longer messages are truncated.
+const PANIC_MESSAGE_BUFFER_SIZE: usize = 512;
+
+// DPFLTR_IHVDRIVER_ID is a small positive enum value
+#[allow(clippy::cast_sign_loss)]
+const DEBUG_PRINT_COMPONENT_ID: ULONG = DPFLTR_IHVDRIVER_ID as ULONG;
+
+/// Maximum number of return addresses captured in a [`PanicReport`]'s
+/// backtrace
+pub const PANIC_BACKTRACE_CAPACITY: usize = 16;
+
+/// Name of the ETW event written by [`panic_handler`] to the provider
+/// configured via [`set_panic_telemetry_provider`]
+pub const PANIC_EVENT_NAME: &str = "RustPanic";
+
+static BUGCHECK_CODE: AtomicU32 = AtomicU32::new(DEFAULT_BUGCHECK_CODE);
+
+static PANIC_TELEMETRY_PROVIDER: AtomicPtr<TraceLoggingProvider> =
+    AtomicPtr::new(core::ptr::null_mut());
+
+static PANIC_CALLBACK: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Set when a panic is being reported, so that a panic while reporting does
+/// not report again
+static REPORTING_PANIC: AtomicBool = AtomicBool::new(false);
+
+/// Details of a panic, which are reported to ETW and to the callback
+/// configured via [`set_panic_callback`] before the system bugchecks
+#[derive(Clone, Copy, Debug)]
+pub struct PanicReport<'a> {
+    /// Panic message, truncated to fit a fixed-size buffer
+    pub message: &'a str,
+    /// Source file that panicked, or an empty string if it is unknown
+    pub file: &'a str,
+    /// Line that panicked, or 0 if it is unknown
+    pub line: u32,
+    /// Column that panicked, or 0 if it is unknown
+    pub column: u32,
+    /// Return addresses of the panicking call stack, innermost first
+    pub backtrace: &'a [usize],
+    /// Bugcheck code that release builds will bugcheck with
+    pub bugcheck_code: ULONG,
+}
+
+/// Callback invoked by [`panic_handler`] with a [`PanicReport`], at the IRQL
+/// that the panic occurred at
+pub type PanicCallback = fn(&PanicReport<'_>);
+
+/// Set the bugcheck code that [`panic_handler`] uses in release builds
+pub fn set_bugcheck_code(bugcheck_code: ULONG) {
+    BUGCHECK_CODE.store(bugcheck_code, Ordering::Relaxed);
+}
+
+/// Get the bugcheck code that [`panic_handler`] uses in release builds
+#[must_use]
+pub fn bugcheck_code() -> ULONG {
+    BUGCHECK_CODE.load(Ordering::Relaxed)
+}
+
+/// Set the ETW provider that [`panic_handler`] writes a [`PANIC_EVENT_NAME`]
+/// event to. The event is written at [`Level::Critical`], and has the fields
+/// of [`PanicReport`].
+pub fn set_panic_telemetry_provider(provider: &'static TraceLoggingProvider) {
+    PANIC_TELEMETRY_PROVIDER.store(core::ptr::from_ref(provider).cast_mut(), Ordering::Release);
+}
+
+/// Set a callback that [`panic_handler`] invokes with a [`PanicReport`] before
+/// bugchecking, ex. to capture a WER live kernel report.
+///
+/// The callback runs at the IRQL that the panic occurred at, so it should only
+/// call functions that are safe at any IRQL. Panics inside the callback are not
+/// reported again.
+pub fn set_panic_callback(callback: PanicCallback) {
+    PANIC_CALLBACK.store(callback as *mut (), Ordering::Release);
+}
+
+/// Default panic handler for drivers.
+///
+/// Logs the panic message to the kernel debugger via
+/// [`DbgPrintEx`](wdk_sys::ntddk::DbgPrintEx), and reports it to the ETW
+/// provider and callback configured via [`set_panic_telemetry_provider`] and
+/// [`set_panic_callback`]. Afterwards, debug builds
+/// break into the debugger and spin, while release builds bugcheck via
+/// [`KeBugCheckEx`](wdk_sys::ntddk::KeBugCheckEx) with the code configured
+/// via [`set_bugcheck_code`]. The bugcheck parameters are the address of the
+/// panic message, the line and column of the panic, and zero.
+pub fn panic_handler(info: &PanicInfo) -> ! {
+    let mut panic_message = PanicMessageBuffer::new();
+    // Truncation is the only possible error, in which case the message is still
+    // logged
+    let _ = write!(panic_message, "{info}");
+
+    // SAFETY: The format string and `panic_message` are valid null terminated
+    // strings, and the `%s` format specifier matches the single argument passed
+    unsafe {
+        DbgPrintEx(
+            DEBUG_PRINT_COMPONENT_ID,
+            DPFLTR_ERROR_LEVEL,
+            c"%s\n".as_ptr(),
+            panic_message.as_ptr(),
+        );
+    }
+
+    if !REPORTING_PANIC.swap(true, Ordering::AcqRel) {
+        report_panic(info);
+    }
+
+    #[cfg(debug_assertions)]
+    {
+        crate::dbg_break();
+        loop {
+            core::hint::spin_loop();
+        }
+    }
+
+    #[cfg(not(debug_assertions))]
+    {
+        let (line, column) = info
+            .location()
+            .map_or((0, 0), |location| (location.line(), location.column()));
+
+        // SAFETY: Bugchecking is always safe, since it does not return. The address of
+        // `panic_message` is only used as an opaque value in the crash dump.
+        unsafe {
+            KeBugCheckEx(
+                bugcheck_code(),
+                panic_message.as_ptr() as ULONG_PTR,
+                ULONG_PTR::from(line),
+                ULONG_PTR::from(column),
+                0,
+            )
+        }
+    }
+}
+
+/// Report a panic to the configured ETW provider and callback
+fn report_panic(info: &PanicInfo) {
+    let mut message = PanicMessageBuffer::new();
+    // Truncation is the only possible error, in which case the message is still
+    // reported
+    let _ = write!(message, "{}", info.message());
+
+    let mut backtrace = [0; PANIC_BACKTRACE_CAPACITY];
+    let backtrace_len = capture_backtrace(&mut backtrace);
+
+    let (file, line, column) = info.location().map_or(("", 0, 0), |location| {
+        (location.file(), location.line(), location.column())
+    });
+    let report = PanicReport {
+        message: message.as_str(),
+        file,
+        line,
+        column,
+        backtrace: &backtrace[..backtrace_len],
+        bugcheck_code: bugcheck_code(),
+    };
+
+    let provider = PANIC_TELEMETRY_PROVIDER.load(Ordering::Acquire);
+    if !provider.is_null() {
+        // SAFETY: `provider` was created from a `&'static TraceLoggingProvider` in
+        // `set_panic_telemetry_provider`
+        let provider = unsafe { &*provider };
+
+        // Return addresses are written as 64-bit values regardless of the target
+        let mut backtrace_bytes = [0; PANIC_BACKTRACE_CAPACITY * 8];
+        for (bytes, address) in backtrace_bytes.chunks_exact_mut(8).zip(report.backtrace) {
+            bytes.copy_from_slice(&(*address as u64).to_ne_bytes());
+        }
+
+        trace_event!(
+            provider,
+            PANIC_EVENT_NAME,
+            level = Level::Critical,
+            str8("Message", report.message),
+            str8("File", report.file),
+            u32("Line", report.line),
+            u32("Column", report.column),
+            binary("Backtrace", &backtrace_bytes[..report.backtrace.len() * 8]),
+            u32("BugcheckCode", report.bugcheck_code),
+        );
+    }
+
+    let callback = PANIC_CALLBACK.load(Ordering::Acquire);
+    if !callback.is_null() {
+        // SAFETY: `callback` was created from a `PanicCallback` in
+        // `set_panic_callback`
+        let callback = unsafe { core::mem::transmute::<*mut (), PanicCallback>(callback) };
+        callback(&report);
+    }
+}
+
+/// Capture the return addresses of the current call stack into `backtrace`,
+/// skipping this function, and return the number of addresses captured
+fn capture_backtrace(backtrace: &mut [usize; PANIC_BACKTRACE_CAPACITY]) -> usize {
+    // PANIC_BACKTRACE_CAPACITY is a small constant
+    #[allow(clippy::cast_possible_truncation)]
+    const FRAMES_TO_CAPTURE: ULONG = PANIC_BACKTRACE_CAPACITY as ULONG;
+
+    let captured_frames;
+    // SAFETY: `backtrace` has room for `PANIC_BACKTRACE_CAPACITY` pointer-sized
+    // return addresses, and no hash is requested
+    unsafe {
+        captured_frames = RtlCaptureStackBackTrace(
+            1,
+            FRAMES_TO_CAPTURE,
+            backtrace.as_mut_ptr().cast::<PVOID>(),
+            core::ptr::null_mut(),
+        );
+    }
+    usize::from(captured_frames).min(PANIC_BACKTRACE_CAPACITY)
+}
+
+/// Fixed-size, null terminated buffer that panic messages are formatted into,
+/// since allocating while panicking is not possible
+struct PanicMessageBuffer {
+    buffer: [u8; PANIC_MESSAGE_BUFFER_SIZE],
+    len: usize,
+}
+
+impl PanicMessageBuffer {
+    const fn new() -> Self {
+        Self {
+            buffer: [0; PANIC_MESSAGE_BUFFER_SIZE],
+            len: 0,
+        }
+    }
+
+    const fn as_ptr(&self) -> *const core::ffi::c_char {
+        self.buffer.as_ptr().cast()
+    }
+
+    /// Get the message as a string, dropping any character that was split by
+    /// truncation
+    fn as_str(&self) -> &str {
+        let bytes = &self.buffer[..self.len];
+        core::str::from_utf8(bytes).unwrap_or_else(|error| {
+            // `valid_up_to` is always a character boundary
+            core::str::from_utf8(&bytes[..error.valid_up_to()]).unwrap_or_default()
+        })
+    }
+}
+
+impl Write for PanicMessageBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        // Always leave room for the null terminator
+        let remaining_capacity = PANIC_MESSAGE_BUFFER_SIZE - 1 - self.len;
+        let bytes_to_copy = s.len().min(remaining_capacity);
+        self.buffer[self.len..self.len + bytes_to_copy]
+            .copy_from_slice(&s.as_bytes()[..bytes_to_copy]);
+        self.len += bytes_to_copy;
+
+        if bytes_to_copy < s.len() {
+            return Err(fmt::Error);
+        }
+        Ok(())
+    }
+}
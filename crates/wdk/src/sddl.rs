@@ -0,0 +1,131 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Typed SDDL strings describing the security descriptors of device objects
+//!
+//! Device object security descriptors are described by a subset of the
+//! Security Descriptor Definition Language (SDDL) that only consists of a
+//! protected DACL (ex. `D:P(A;;GA;;;SY)(A;;GA;;;BA)`). A typo in such a
+//! string can silently grant more access than intended, so APIs of this crate
+//! that apply a security descriptor (ex.
+//! [`ControlDeviceBuilder::allocate`](crate::wdf::ControlDeviceBuilder::allocate))
+//! take an [`Sddl`] instead of a raw string. The well-known strings from
+//! `wdmsec.h` are provided as associated constants, and other strings are
+//! checked to be well-formed when converted, which can happen at compile time:
+//!
+//! ```rust, no_run
+//! use wdk::{nt_unicode_str, sddl::Sddl};
+//!
+//! // Grants all access to the kernel, and read access to administrators
+//! const SDDL_SYS_ALL_ADM_R: Sddl<'static> =
+//!     match Sddl::new(nt_unicode_str!("D:P(A;;GA;;;SY)(A;;GR;;;BA)")) {
+//!         Ok(sddl) => sddl,
+//!         Err(_) => panic!("SDDL string should be well-formed"),
+//!     };
+//! ```
+
+use wdk_sys::{NTSTATUS, STATUS_INVALID_PARAMETER};
+
+use crate::{nt_unicode_str, string::NtUnicodeStr};
+
+/// Prefix of every device object SDDL string, which introduces a protected
+/// DACL
+const PROTECTED_DACL_PREFIX: [u16; 3] = [b'D' as u16, b':' as u16, b'P' as u16];
+/// Number of `;`-delimited fields in an ACE string
+const ACE_FIELD_COUNT: usize = 6;
+
+/// SDDL string describing the security descriptor of a device object
+#[derive(Clone, Copy, Debug)]
+pub struct Sddl<'a>(NtUnicodeStr<'a>);
+
+impl Sddl<'static> {
+    /// Grants no access to anyone, so that only kernel-mode code can open the
+    /// device. This is equivalent to `SDDL_DEVOBJ_KERNEL_ONLY` in `wdmsec.h`.
+    pub const DEVOBJ_KERNEL_ONLY: Self = Self(nt_unicode_str!("D:P"));
+    /// Grants all access to the kernel. This is equivalent to
+    /// `SDDL_DEVOBJ_SYS_ALL` in `wdmsec.h`.
+    pub const DEVOBJ_SYS_ALL: Self = Self(nt_unicode_str!("D:P(A;;GA;;;SY)"));
+    /// Grants all access to the kernel and to administrators. This is
+    /// equivalent to `SDDL_DEVOBJ_SYS_ALL_ADM_ALL` in `wdmsec.h`.
+    pub const DEVOBJ_SYS_ALL_ADM_ALL: Self = Self(nt_unicode_str!("D:P(A;;GA;;;SY)(A;;GA;;;BA)"));
+    /// Grants all access to the kernel, read, write and execute access to
+    /// administrators, and read access to everyone else. This is equivalent to
+    /// `SDDL_DEVOBJ_SYS_ALL_ADM_RWX_WORLD_R` in `wdmsec.h`.
+    pub const DEVOBJ_SYS_ALL_ADM_RWX_WORLD_R: Self = Self(nt_unicode_str!(
+        "D:P(A;;GA;;;SY)(A;;GRGWGX;;;BA)(A;;GR;;;WD)"
+    ));
+    /// Grants all access to the kernel, and read, write and execute access to
+    /// everyone else, including restricted code. This is equivalent to
+    /// `SDDL_DEVOBJ_SYS_ALL_ADM_RWX_WORLD_RWX_RES_RWX` in `wdmsec.h`.
+    pub const DEVOBJ_SYS_ALL_ADM_RWX_WORLD_RWX_RES_RWX: Self = Self(nt_unicode_str!(
+        "D:P(A;;GA;;;SY)(A;;GRGWGX;;;BA)(A;;GRGWGX;;;WD)(A;;GRGWGX;;;RC)"
+    ));
+    /// Grants all access to the kernel, read, write and execute access to
+    /// administrators, read and write access to everyone else, and read access
+    /// to restricted code. This is equivalent to
+    /// `SDDL_DEVOBJ_SYS_ALL_ADM_RWX_WORLD_RW_RES_R` in `wdmsec.h`.
+    pub const DEVOBJ_SYS_ALL_ADM_RWX_WORLD_RW_RES_R: Self = Self(nt_unicode_str!(
+        "D:P(A;;GA;;;SY)(A;;GRGWGX;;;BA)(A;;GRGW;;;WD)(A;;GR;;;RC)"
+    ));
+    /// Grants all access to the kernel, read, write and execute access to
+    /// administrators, and read access to everyone else, including restricted
+    /// code. This is equivalent to `SDDL_DEVOBJ_SYS_ALL_ADM_RWX_WORLD_R_RES_R`
+    /// in `wdmsec.h`.
+    pub const DEVOBJ_SYS_ALL_ADM_RWX_WORLD_R_RES_R: Self = Self(nt_unicode_str!(
+        "D:P(A;;GA;;;SY)(A;;GRGWGX;;;BA)(A;;GR;;;WD)(A;;GR;;;RC)"
+    ));
+    /// Grants all access to the kernel, and read and execute access to
+    /// administrators. This is equivalent to `SDDL_DEVOBJ_SYS_ALL_ADM_RX` in
+    /// `wdmsec.h`.
+    pub const DEVOBJ_SYS_ALL_ADM_RX: Self = Self(nt_unicode_str!("D:P(A;;GA;;;SY)(A;;GRGX;;;BA)"));
+}
+
+impl<'a> Sddl<'a> {
+    /// Construct an [`Sddl`] from `sddl`, after checking that it is a
+    /// well-formed device object SDDL string: a protected DACL (`D:P`)
+    /// followed by any number of parenthesized ACE strings, each consisting of
+    /// six `;`-delimited fields
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INVALID_PARAMETER` if `sddl` is not
+    /// a well-formed device object SDDL string.
+    pub const fn new(sddl: NtUnicodeStr<'a>) -> Result<Self, NTSTATUS> {
+        let utf16 = sddl.as_slice();
+        if utf16.len() < PROTECTED_DACL_PREFIX.len()
+            || utf16[0] != PROTECTED_DACL_PREFIX[0]
+            || utf16[1] != PROTECTED_DACL_PREFIX[1]
+            || utf16[2] != PROTECTED_DACL_PREFIX[2]
+        {
+            return Err(STATUS_INVALID_PARAMETER);
+        }
+
+        let mut index = PROTECTED_DACL_PREFIX.len();
+        // Number of fields of the ACE string being parsed, or `None` between ACE
+        // strings
+        let mut ace_fields: Option<usize> = None;
+        while index < utf16.len() {
+            ace_fields = match (utf16[index], ace_fields) {
+                (0x28 /* ( */, None) => Some(1),
+                (0x3B /* ; */, Some(fields)) => Some(fields + 1),
+                (0x29 /* ) */, Some(ACE_FIELD_COUNT)) => None,
+                (0x28 | 0x29 | 0x3B, _) | (_, None) => return Err(STATUS_INVALID_PARAMETER),
+                // Other characters are only allowed within the fields of an ACE string
+                (_, Some(fields)) => Some(fields),
+            };
+            index += 1;
+        }
+
+        if ace_fields.is_some() {
+            return Err(STATUS_INVALID_PARAMETER);
+        }
+        Ok(Self(sddl))
+    }
+
+    /// Get the SDDL string as an [`NtUnicodeStr`], ex. to pass it to WDK APIs
+    /// that take a `PCUNICODE_STRING`
+    #[must_use]
+    pub const fn as_unicode_str(&self) -> NtUnicodeStr<'a> {
+        self.0
+    }
+}
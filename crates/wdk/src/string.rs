@@ -0,0 +1,333 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Safe abstractions over the counted UTF-16 strings used by WDK APIs
+//!
+//! [`NtUnicodeStr`] is a borrowed string that can be passed to any WDK API
+//! that takes a `PCUNICODE_STRING`. String literals can be converted to an
+//! [`NtUnicodeStr`] at compile time via
+//! [`nt_unicode_str!`](crate::nt_unicode_str), and other strings can be
+//! converted into an owned [`NtUnicodeString`] when the `alloc` feature is
+//! enabled:
+//!
+//! ```rust, no_run
+//! use wdk::{nt_unicode_str, string::NtUnicodeStr};
+//!
+//! const DEVICE_NAME: NtUnicodeStr<'static> = nt_unicode_str!("\\Device\\SampleDevice");
+//!
+//! fn device_name_for(instance: u32) -> wdk::Result<wdk::string::NtUnicodeString> {
+//!     wdk::string::NtUnicodeString::try_from_str(&format!("\\Device\\SampleDevice{instance}"))
+//! }
+//! ```
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::{
+    fmt::{self, Write},
+    marker::PhantomData,
+};
+
+use wdk_sys::{PCUNICODE_STRING, STATUS_INVALID_PARAMETER, UNICODE_STRING, USHORT};
+
+/// Maximum number of UTF-16 code units that fit in a [`UNICODE_STRING`], whose
+/// length is stored in bytes as a `USHORT`
+pub const MAX_LEN: usize = USHORT::MAX as usize / core::mem::size_of::<u16>();
+
+/// Borrowed counted UTF-16 string, equivalent to a `PCUNICODE_STRING`.
+///
+/// Unlike Rust strings, the contents of a [`UNICODE_STRING`] are not required
+/// to be valid UTF-16.
+#[derive(Clone, Copy)]
+pub struct NtUnicodeStr<'a> {
+    raw: UNICODE_STRING,
+    _buffer: PhantomData<&'a [u16]>,
+}
+
+impl<'a> NtUnicodeStr<'a> {
+    /// Construct a [`NtUnicodeStr`] from a raw `PCUNICODE_STRING`
+    ///
+    /// # Safety
+    ///
+    /// `raw` must point to a valid [`UNICODE_STRING`] whose `Buffer` holds at
+    /// least `Length` bytes, and that buffer must not be mutated for the
+    /// lifetime `'a`.
+    #[must_use]
+    pub const unsafe fn from_raw(raw: PCUNICODE_STRING) -> Self {
+        Self {
+            // SAFETY: The caller guarantees that `raw` points to a valid
+            // `UNICODE_STRING`.
+            raw: unsafe { *raw },
+            _buffer: PhantomData,
+        }
+    }
+
+    /// Construct a [`NtUnicodeStr`] from UTF-16 code units
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INVALID_PARAMETER` if `utf16` is
+    /// longer than [`MAX_LEN`] code units.
+    pub const fn from_utf16(utf16: &'a [u16]) -> crate::Result<Self> {
+        if utf16.len() > MAX_LEN {
+            return Err(STATUS_INVALID_PARAMETER);
+        }
+
+        // The length is bounded by `MAX_LEN`, so its length in bytes fits in a USHORT
+        #[allow(clippy::cast_possible_truncation)]
+        let length_in_bytes = core::mem::size_of_val(utf16) as USHORT;
+        Ok(Self {
+            raw: UNICODE_STRING {
+                Length: length_in_bytes,
+                MaximumLength: length_in_bytes,
+                // WDK APIs that take a PCUNICODE_STRING never write through `Buffer`
+                Buffer: utf16.as_ptr().cast_mut(),
+            },
+            _buffer: PhantomData,
+        })
+    }
+
+    /// Get a `PCUNICODE_STRING` pointing to this string, for use in WDK APIs.
+    /// The returned pointer is only valid for as long as `self` is.
+    #[must_use]
+    pub const fn as_raw(&self) -> PCUNICODE_STRING {
+        core::ptr::addr_of!(self.raw)
+    }
+
+    /// Get the UTF-16 code units of this string
+    #[must_use]
+    pub const fn as_slice(&self) -> &'a [u16] {
+        if self.raw.Buffer.is_null() || self.is_empty() {
+            return &[];
+        }
+
+        // SAFETY: `Buffer` is non-null and, per the safety requirements of
+        // `NtUnicodeStr::from_raw`, holds at least `Length` bytes that are not mutated
+        // for the lifetime `'a`.
+        unsafe { core::slice::from_raw_parts(self.raw.Buffer, self.len()) }
+    }
+
+    /// Get the number of UTF-16 code units in this string
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.raw.Length as usize / core::mem::size_of::<u16>()
+    }
+
+    /// Returns `true` if this string contains no UTF-16 code units
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get an iterator over the [`char`]s of this string. Invalid UTF-16 is
+    /// replaced with [`char::REPLACEMENT_CHARACTER`].
+    pub fn chars(&self) -> impl Iterator<Item = char> + 'a {
+        char::decode_utf16(self.as_slice().iter().copied())
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+}
+
+impl fmt::Display for NtUnicodeStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.chars().try_for_each(|c| f.write_char(c))
+    }
+}
+
+impl fmt::Debug for NtUnicodeStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.chars() {
+            for escaped_c in c.escape_debug() {
+                f.write_char(escaped_c)?;
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+impl<'a> TryFrom<&'a [u16]> for NtUnicodeStr<'a> {
+    type Error = wdk_sys::NTSTATUS;
+
+    fn try_from(utf16: &'a [u16]) -> crate::Result<Self> {
+        Self::from_utf16(utf16)
+    }
+}
+
+/// Owned counted UTF-16 string, equivalent to a `UNICODE_STRING` whose buffer
+/// is allocated via the global allocator (ex. `wdk-alloc`).
+///
+/// Use [`NtUnicodeString::as_unicode_str`] to pass it to WDK APIs that take a
+/// `PCUNICODE_STRING`.
+#[cfg(feature = "alloc")]
+#[derive(Clone, PartialEq, Eq)]
+pub struct NtUnicodeString {
+    buffer: Vec<u16>,
+}
+
+#[cfg(feature = "alloc")]
+impl NtUnicodeString {
+    /// Construct a [`NtUnicodeString`] by encoding `string` as UTF-16
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INVALID_PARAMETER` if the UTF-16
+    /// encoding of `string` is longer than [`MAX_LEN`] code units.
+    pub fn try_from_str(string: &str) -> crate::Result<Self> {
+        Self::try_from_utf16(string.encode_utf16().collect())
+    }
+
+    /// Construct a [`NtUnicodeString`] that takes ownership of `utf16`
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INVALID_PARAMETER` if `utf16` is
+    /// longer than [`MAX_LEN`] code units.
+    pub fn try_from_utf16(utf16: Vec<u16>) -> crate::Result<Self> {
+        if utf16.len() > MAX_LEN {
+            return Err(STATUS_INVALID_PARAMETER);
+        }
+        Ok(Self { buffer: utf16 })
+    }
+
+    /// Borrow this string as a [`NtUnicodeStr`], for use in WDK APIs
+    #[must_use]
+    pub fn as_unicode_str(&self) -> NtUnicodeStr<'_> {
+        NtUnicodeStr::from_utf16(&self.buffer).unwrap_or_else(|_| {
+            unreachable!("NtUnicodeString length should always be at most MAX_LEN")
+        })
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl TryFrom<&str> for NtUnicodeString {
+    type Error = wdk_sys::NTSTATUS;
+
+    fn try_from(string: &str) -> crate::Result<Self> {
+        Self::try_from_str(string)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<NtUnicodeStr<'_>> for NtUnicodeString {
+    fn from(unicode_str: NtUnicodeStr<'_>) -> Self {
+        Self {
+            buffer: unicode_str.as_slice().to_vec(),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for NtUnicodeString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.as_unicode_str(), f)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Debug for NtUnicodeString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.as_unicode_str(), f)
+    }
+}
+
+/// Construct a [`NtUnicodeStr<'static>`](NtUnicodeStr) from a string literal.
+/// The string is encoded as UTF-16 at compile time.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use wdk::{nt_unicode_str, string::NtUnicodeStr};
+///
+/// const SYMBOLIC_LINK_NAME: NtUnicodeStr<'static> = nt_unicode_str!("\\DosDevices\\Sample");
+/// ```
+#[macro_export]
+macro_rules! nt_unicode_str {
+    ($string:expr) => {{
+        const UTF16: &[u16] =
+            &$crate::__private::encode_utf16::<{ $crate::__private::utf16_len($string) }>($string);
+        const NT_UNICODE_STR: $crate::string::NtUnicodeStr<'static> =
+            match $crate::string::NtUnicodeStr::from_utf16(UTF16) {
+                Ok(nt_unicode_str) => nt_unicode_str,
+                Err(_) => ::core::panic!("string is too long to be stored in a UNICODE_STRING"),
+            };
+        NT_UNICODE_STR
+    }};
+}
+
+/// Get the number of UTF-16 code units needed to encode `string`
+#[doc(hidden)]
+#[must_use]
+pub const fn utf16_len(string: &str) -> usize {
+    let bytes = string.as_bytes();
+    let mut index = 0;
+    let mut len = 0;
+    while index < bytes.len() {
+        let (code_point, utf8_len) = decode_utf8(bytes, index);
+        len += if code_point >= 0x1_0000 { 2 } else { 1 };
+        index += utf8_len;
+    }
+    len
+}
+
+/// Encode `string` as UTF-16. `N` must be [`utf16_len`] of `string`.
+#[doc(hidden)]
+#[must_use]
+pub const fn encode_utf16<const N: usize>(string: &str) -> [u16; N] {
+    let bytes = string.as_bytes();
+    let mut utf16 = [0; N];
+    let mut index = 0;
+    let mut utf16_index = 0;
+    while index < bytes.len() {
+        let (code_point, utf8_len) = decode_utf8(bytes, index);
+        // Code points are at most 21 bits, so the surrogates and BMP code points
+        // always fit in a u16
+        #[allow(clippy::cast_possible_truncation)]
+        if code_point >= 0x1_0000 {
+            let code_point = code_point - 0x1_0000;
+            utf16[utf16_index] = 0xD800 | (code_point >> 10) as u16;
+            utf16[utf16_index + 1] = 0xDC00 | (code_point & 0x3FF) as u16;
+            utf16_index += 2;
+        } else {
+            utf16[utf16_index] = code_point as u16;
+            utf16_index += 1;
+        }
+        index += utf8_len;
+    }
+    utf16
+}
+
+/// Decode the code point starting at `bytes[index]` of a valid UTF-8 string.
+/// Returns the code point and its length in bytes.
+const fn decode_utf8(bytes: &[u8], index: usize) -> (u32, usize) {
+    let first_byte = bytes[index] as u32;
+    if first_byte < 0x80 {
+        (first_byte, 1)
+    } else if first_byte < 0xE0 {
+        (
+            ((first_byte & 0x1F) << 6) | continuation_bits(bytes[index + 1]),
+            2,
+        )
+    } else if first_byte < 0xF0 {
+        (
+            ((first_byte & 0x0F) << 12)
+                | (continuation_bits(bytes[index + 1]) << 6)
+                | continuation_bits(bytes[index + 2]),
+            3,
+        )
+    } else {
+        (
+            ((first_byte & 0x07) << 18)
+                | (continuation_bits(bytes[index + 1]) << 12)
+                | (continuation_bits(bytes[index + 2]) << 6)
+                | continuation_bits(bytes[index + 3]),
+            4,
+        )
+    }
+}
+
+/// Get the payload bits of a UTF-8 continuation byte
+const fn continuation_bits(byte: u8) -> u32 {
+    (byte & 0x3F) as u32
+}
@@ -0,0 +1,869 @@
+//! Safe wrappers for kernel synchronization primitives.
+//!
+//! WDF spin locks only cover synchronization at `IRQL` <= `DISPATCH_LEVEL`
+//! where the code holding the lock must not block. The primitives in this
+//! module are meant for code that runs at `IRQL` <= `APC_LEVEL`, and that may
+//! block or wait while it holds them:
+//!
+//! * [`KernelEvent`] wraps a `KEVENT`, which threads can wait on until another
+//!   thread signals it.
+//! * [`FastMutex`] wraps a `FAST_MUTEX`, which protects data from concurrent
+//!   access by multiple threads.
+//! * [`Resource`] wraps an `ERESOURCE`, which protects data that is read by
+//!   multiple threads at a time, and written by a single thread at a time.
+//!
+//! The kernel objects wrapped by these types must not move once they are
+//! initialized, so they are always allocated on the heap and returned pinned.
+//! [`Mutex`] and [`RwLock`] are built on [`FastMutex`] and [`Resource`], with
+//! APIs that mirror their `std::sync` counterparts, so they can be moved and
+//! embedded in other types like any other value.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use wdk::sync::{EventType, KernelEvent, Mutex, RwLock};
+//!
+//! let counter = Mutex::new(0_u32);
+//! *counter.lock() += 1;
+//!
+//! let config = RwLock::new([0_u8; 4]);
+//! let first_byte = config.read()[0];
+//! config.write()[0] = first_byte + 1;
+//!
+//! let event = KernelEvent::new(EventType::Notification, false);
+//! assert!(!event.set());
+//! event.wait();
+//! ```
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    marker::{PhantomData, PhantomPinned},
+    ops::{Deref, DerefMut},
+    pin::Pin,
+    time::Duration,
+};
+
+use wdk_sys::{
+    ntddk::{
+        ExAcquireFastMutex,
+        ExAcquireResourceExclusiveLite,
+        ExAcquireResourceSharedLite,
+        ExDeleteResourceLite,
+        ExInitializeFastMutex,
+        ExInitializeResourceLite,
+        ExIsResourceAcquiredExclusiveLite,
+        ExReleaseFastMutex,
+        ExReleaseResourceLite,
+        ExTryToAcquireFastMutex,
+        KeClearEvent,
+        KeEnterCriticalRegion,
+        KeInitializeEvent,
+        KeLeaveCriticalRegion,
+        KeReadStateEvent,
+        KeSetEvent,
+        KeWaitForSingleObject,
+    },
+    _EVENT_TYPE,
+    _KWAIT_REASON,
+    _MODE,
+    ERESOURCE,
+    EVENT_TYPE,
+    FAST_MUTEX,
+    IO_NO_INCREMENT,
+    KEVENT,
+    KPRIORITY,
+    KPROCESSOR_MODE,
+    LARGE_INTEGER,
+    NTSTATUS,
+    STATUS_TIMEOUT,
+};
+
+use crate::{nt_success, time::Timeout};
+
+/// Priority boost of the threads released by [`KernelEvent::set`]
+// IO_NO_INCREMENT is zero, so it fits in a KPRIORITY
+#[allow(clippy::cast_possible_wrap)]
+const EVENT_PRIORITY_INCREMENT: KPRIORITY = IO_NO_INCREMENT as KPRIORITY;
+
+/// Processor mode of the waits in [`KernelEvent`]
+// KernelMode is zero, so it fits in a KPROCESSOR_MODE
+#[allow(clippy::cast_possible_truncation)]
+const KERNEL_MODE: KPROCESSOR_MODE = _MODE::KernelMode as KPROCESSOR_MODE;
+
+/// Type of a [`KernelEvent`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventType {
+    /// The event stays signaled until it is cleared via
+    /// [`KernelEvent::clear`], and releases all waiting threads
+    /// (`NotificationEvent`)
+    Notification,
+    /// The event is cleared automatically when it releases a single waiting
+    /// thread (`SynchronizationEvent`)
+    Synchronization,
+}
+
+/// Kernel Event.
+///
+/// An event is a synchronization object that threads at `IRQL` <=
+/// `APC_LEVEL` can wait on via [`KernelEvent::wait`], until another thread
+/// signals it via [`KernelEvent::set`]. Events can be set at `IRQL` <=
+/// `DISPATCH_LEVEL`, so they are typically used to wait for asynchronous work
+/// (ex. a request sent to an I/O target) to complete.
+pub struct KernelEvent {
+    event: UnsafeCell<KEVENT>,
+    _pinned: PhantomPinned,
+}
+
+/// Fast Mutex protecting a value of type `T`.
+///
+/// The value can only be accessed via the [`FastMutexGuard`] returned by
+/// [`FastMutex::lock`], which releases the mutex when dropped. Acquiring a
+/// fast mutex raises the `IRQL` to `APC_LEVEL`, so the mutex must be acquired
+/// at `IRQL` <= `APC_LEVEL`, and the code holding it must not access pageable
+/// memory that could cause a page fault that has to wait for an APC. Fast
+/// mutexes cannot be acquired recursively.
+pub struct FastMutex<T> {
+    raw_fast_mutex: UnsafeCell<FAST_MUTEX>,
+    data: UnsafeCell<T>,
+    _pinned: PhantomPinned,
+}
+
+/// RAII guard that provides access to the value protected by a [`FastMutex`],
+/// and releases the mutex when dropped
+#[must_use = "the mutex is released as soon as the guard is dropped"]
+pub struct FastMutexGuard<'a, T> {
+    fast_mutex: &'a FastMutex<T>,
+    // The mutex must be released by the thread that acquired it
+    _not_send: PhantomData<*const ()>,
+}
+
+/// Executive Resource protecting a value of type `T`.
+///
+/// A resource is a reader-writer lock, whose value can be accessed by
+/// multiple threads at a time via the [`ResourceSharedGuard`] returned by
+/// [`Resource::read`], or by a single thread via the [`ResourceExclusiveGuard`]
+/// returned by [`Resource::write`]. Resources must be acquired at `IRQL` <=
+/// `APC_LEVEL`. Normal kernel APCs are disabled while a resource is held, as
+/// required by the kernel.
+pub struct Resource<T> {
+    raw_resource: UnsafeCell<ERESOURCE>,
+    data: UnsafeCell<T>,
+    _pinned: PhantomPinned,
+}
+
+/// RAII guard that provides shared access to the value protected by a
+/// [`Resource`], and releases the resource when dropped
+#[must_use = "the resource is released as soon as the guard is dropped"]
+pub struct ResourceSharedGuard<'a, T> {
+    resource: &'a Resource<T>,
+    // The resource must be released by the thread that acquired it
+    _not_send: PhantomData<*const ()>,
+}
+
+/// RAII guard that provides exclusive access to the value protected by a
+/// [`Resource`], and releases the resource when dropped
+#[must_use = "the resource is released as soon as the guard is dropped"]
+pub struct ResourceExclusiveGuard<'a, T> {
+    resource: &'a Resource<T>,
+    // The resource must be released by the thread that acquired it
+    _not_send: PhantomData<*const ()>,
+}
+
+impl EventType {
+    const fn as_raw(self) -> EVENT_TYPE {
+        match self {
+            Self::Notification => _EVENT_TYPE::NotificationEvent,
+            Self::Synchronization => _EVENT_TYPE::SynchronizationEvent,
+        }
+    }
+}
+
+impl KernelEvent {
+    /// Create an event of type `event_type`, which is initially signaled if
+    /// `signaled` is `true`
+    #[must_use]
+    pub fn new(event_type: EventType, signaled: bool) -> Pin<Box<Self>> {
+        let event = Box::pin(Self {
+            event: UnsafeCell::new(KEVENT::default()),
+            _pinned: PhantomPinned,
+        });
+
+        // SAFETY: The event is pinned on the heap, so it is never moved after it is
+        // initialized here.
+        unsafe {
+            KeInitializeEvent(event.event.get(), event_type.as_raw(), u8::from(signaled));
+        }
+        event
+    }
+
+    /// Signal the event, and return whether it was already signaled. This can
+    /// be called at `IRQL` <= `DISPATCH_LEVEL`.
+    #[must_use]
+    pub fn set(&self) -> bool {
+        let previous_state;
+        // SAFETY: `event` is a private member of `KernelEvent`, which was initialized
+        // in `KernelEvent::new`, and is pinned.
+        unsafe {
+            previous_state = KeSetEvent(self.event.get(), EVENT_PRIORITY_INCREMENT, 0);
+        }
+        previous_state != 0
+    }
+
+    /// Reset the event to the non-signaled state. This can be called at
+    /// `IRQL` <= `DISPATCH_LEVEL`.
+    pub fn clear(&self) {
+        // SAFETY: `event` is a private member of `KernelEvent`, which was initialized
+        // in `KernelEvent::new`, and is pinned.
+        unsafe {
+            KeClearEvent(self.event.get());
+        }
+    }
+
+    /// Check whether the event is currently signaled. This can be called at
+    /// `IRQL` <= `DISPATCH_LEVEL`.
+    #[must_use]
+    pub fn is_set(&self) -> bool {
+        let state;
+        // SAFETY: `event` is a private member of `KernelEvent`, which was initialized
+        // in `KernelEvent::new`, and is pinned.
+        unsafe {
+            state = KeReadStateEvent(self.event.get());
+        }
+        state != 0
+    }
+
+    /// Wait until the event is signaled. This must be called at `IRQL` <=
+    /// `APC_LEVEL`.
+    pub fn wait(&self) {
+        let nt_status = self.wait_for(None);
+        debug_assert!(nt_success(nt_status));
+    }
+
+    /// Wait until the event is signaled, or until `timeout` elapses. Returns
+    /// `true` if the event was signaled, or `false` if the wait timed out.
+    ///
+    /// This must be called at `IRQL` <= `APC_LEVEL`, unless `timeout` is zero,
+    /// in which case it can be called at `IRQL` <= `DISPATCH_LEVEL`.
+    #[must_use]
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let mut relative_timeout = Timeout::relative(timeout).as_large_integer();
+        self.wait_for(Some(&mut relative_timeout)) != STATUS_TIMEOUT
+    }
+
+    /// Wait for the event in kernel mode, without alerts
+    fn wait_for(&self, timeout: Option<&mut LARGE_INTEGER>) -> NTSTATUS {
+        let nt_status;
+        // SAFETY: `event` is a private member of `KernelEvent`, which was initialized
+        // in `KernelEvent::new`, and is pinned. `timeout` is either null or a valid
+        // relative timeout.
+        unsafe {
+            nt_status = KeWaitForSingleObject(
+                self.event.get().cast(),
+                _KWAIT_REASON::Executive,
+                KERNEL_MODE,
+                0,
+                timeout.map_or(core::ptr::null_mut(), core::ptr::from_mut),
+            );
+        }
+        nt_status
+    }
+}
+
+// SAFETY: Kernel events are not tied to the thread that created them, and all
+// of their operations are synchronized by the kernel.
+unsafe impl Send for KernelEvent {}
+
+// SAFETY: Kernel events are not tied to the thread that created them, and all
+// of their operations are synchronized by the kernel.
+unsafe impl Sync for KernelEvent {}
+
+impl<T> FastMutex<T> {
+    /// Create a fast mutex protecting `data`
+    #[must_use]
+    pub fn new(data: T) -> Pin<Box<Self>> {
+        let fast_mutex = Box::pin(Self {
+            raw_fast_mutex: UnsafeCell::new(FAST_MUTEX::default()),
+            data: UnsafeCell::new(data),
+            _pinned: PhantomPinned,
+        });
+
+        // SAFETY: The fast mutex is pinned on the heap, so it is never moved after it
+        // is initialized here.
+        unsafe {
+            ExInitializeFastMutex(fast_mutex.raw_fast_mutex.get());
+        }
+        fast_mutex
+    }
+
+    /// Acquire the fast mutex, waiting until it is available. This must be
+    /// called at `IRQL` <= `APC_LEVEL`.
+    pub fn lock(&self) -> FastMutexGuard<'_, T> {
+        // SAFETY: `raw_fast_mutex` is a private member of `FastMutex`, which was
+        // initialized in `FastMutex::new`, and is pinned. The returned guard releases
+        // it exactly once.
+        unsafe {
+            ExAcquireFastMutex(self.raw_fast_mutex.get());
+        }
+        FastMutexGuard {
+            fast_mutex: self,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Try to acquire the fast mutex without waiting, and return [`None`] if it
+    /// is already held. This must be called at `IRQL` <= `APC_LEVEL`.
+    pub fn try_lock(&self) -> Option<FastMutexGuard<'_, T>> {
+        let acquired;
+        // SAFETY: `raw_fast_mutex` is a private member of `FastMutex`, which was
+        // initialized in `FastMutex::new`, and is pinned. If it is acquired, the
+        // returned guard releases it exactly once.
+        unsafe {
+            acquired = ExTryToAcquireFastMutex(self.raw_fast_mutex.get());
+        }
+        (acquired != 0).then_some(FastMutexGuard {
+            fast_mutex: self,
+            _not_send: PhantomData,
+        })
+    }
+}
+
+// SAFETY: The protected value is moved along with the fast mutex, so it must be
+// `Send`. Fast mutexes are not tied to the thread that created them.
+unsafe impl<T: Send> Send for FastMutex<T> {}
+
+// SAFETY: The protected value is only accessed by the thread holding the fast
+// mutex, so it only needs to be `Send` for the mutex to be shared.
+unsafe impl<T: Send> Sync for FastMutex<T> {}
+
+impl<T> Deref for FastMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: The guard holds the fast mutex, so no other thread accesses the
+        // value.
+        unsafe { &*self.fast_mutex.data.get() }
+    }
+}
+
+impl<T> DerefMut for FastMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: The guard holds the fast mutex, so no other thread accesses the
+        // value, and the exclusive borrow of the guard prevents other references
+        // from this thread.
+        unsafe { &mut *self.fast_mutex.data.get() }
+    }
+}
+
+impl<T> Drop for FastMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: The guard was created when this thread acquired the fast mutex, and
+        // it is released exactly once here.
+        unsafe {
+            ExReleaseFastMutex(self.fast_mutex.raw_fast_mutex.get());
+        }
+    }
+}
+
+impl<T> Resource<T> {
+    /// Create a resource protecting `data`
+    #[must_use]
+    pub fn new(data: T) -> Pin<Box<Self>> {
+        let resource = Box::pin(Self {
+            raw_resource: UnsafeCell::new(ERESOURCE::default()),
+            data: UnsafeCell::new(data),
+            _pinned: PhantomPinned,
+        });
+
+        let nt_status;
+        // SAFETY: The resource is pinned on the heap, so it is never moved after it
+        // is initialized here, and it is deleted when it is dropped.
+        unsafe {
+            nt_status = ExInitializeResourceLite(resource.raw_resource.get());
+        }
+        // `ExInitializeResourceLite` is documented to always succeed
+        debug_assert!(nt_success(nt_status));
+        resource
+    }
+
+    /// Acquire the resource for shared access, waiting until no thread holds
+    /// it for exclusive access. This must be called at `IRQL` <= `APC_LEVEL`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread already holds the resource for exclusive
+    /// access.
+    pub fn read(&self) -> ResourceSharedGuard<'_, T> {
+        // Waiting acquisitions always succeed
+        let _ = self.acquire(false, true);
+        ResourceSharedGuard {
+            resource: self,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Try to acquire the resource for shared access without waiting, and
+    /// return [`None`] if a thread (including the current one) holds it for
+    /// exclusive access. This must be called at `IRQL` <= `APC_LEVEL`.
+    pub fn try_read(&self) -> Option<ResourceSharedGuard<'_, T>> {
+        self.acquire(false, false).then_some(ResourceSharedGuard {
+            resource: self,
+            _not_send: PhantomData,
+        })
+    }
+
+    /// Acquire the resource for exclusive access, waiting until no other
+    /// thread holds it. This must be called at `IRQL` <= `APC_LEVEL`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread already holds the resource for exclusive
+    /// access.
+    pub fn write(&self) -> ResourceExclusiveGuard<'_, T> {
+        // Waiting acquisitions always succeed
+        let _ = self.acquire(true, true);
+        ResourceExclusiveGuard {
+            resource: self,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Try to acquire the resource for exclusive access without waiting, and
+    /// return [`None`] if any thread (including the current one) holds it.
+    /// This must be called at `IRQL` <= `APC_LEVEL`.
+    pub fn try_write(&self) -> Option<ResourceExclusiveGuard<'_, T>> {
+        self.acquire(true, false).then_some(ResourceExclusiveGuard {
+            resource: self,
+            _not_send: PhantomData,
+        })
+    }
+
+    /// Acquire the resource for `exclusive` or shared access, and return
+    /// whether it was acquired. If `wait` is `false`, this fails instead of
+    /// waiting for the resource to become available.
+    ///
+    /// Normal kernel APCs are disabled while the resource is held, and the
+    /// caller must release it via [`Resource::release`] if it was acquired.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `wait` is `true` and the current thread already holds the
+    /// resource for exclusive access.
+    #[must_use]
+    fn acquire(&self, exclusive: bool, wait: bool) -> bool {
+        let held_exclusively;
+        // SAFETY: `raw_resource` is a private member of `Resource`, which was
+        // initialized in `Resource::new`, and is pinned.
+        unsafe {
+            held_exclusively = ExIsResourceAcquiredExclusiveLite(self.raw_resource.get());
+        }
+        // The exclusive owner of an `ERESOURCE` can acquire it again, which would
+        // alias the value borrowed by its `ResourceExclusiveGuard`
+        if held_exclusively != 0 {
+            assert!(
+                !wait,
+                "resource is already held exclusively by the current thread"
+            );
+            return false;
+        }
+
+        // SAFETY: Normal kernel APCs must be disabled while a resource is held. They
+        // are re-enabled below if the resource is not acquired, or when it is
+        // released otherwise.
+        unsafe {
+            KeEnterCriticalRegion();
+        }
+
+        let acquired;
+        if exclusive {
+            // SAFETY: `raw_resource` is a private member of `Resource`, which was
+            // initialized in `Resource::new`, and is pinned.
+            unsafe {
+                acquired = ExAcquireResourceExclusiveLite(self.raw_resource.get(), u8::from(wait));
+            }
+        } else {
+            // SAFETY: `raw_resource` is a private member of `Resource`, which was
+            // initialized in `Resource::new`, and is pinned.
+            unsafe {
+                acquired = ExAcquireResourceSharedLite(self.raw_resource.get(), u8::from(wait));
+            }
+        }
+
+        if acquired == 0 {
+            // SAFETY: The critical region was entered above, and the resource was not
+            // acquired.
+            unsafe {
+                KeLeaveCriticalRegion();
+            }
+            return false;
+        }
+        true
+    }
+
+    /// Release the resource, and re-enable the normal kernel APCs that were
+    /// disabled when it was acquired
+    ///
+    /// # Safety
+    ///
+    /// The current thread must hold the resource, via a guard that is being
+    /// dropped.
+    unsafe fn release(&self) {
+        // SAFETY: The caller guarantees that the current thread holds the resource.
+        unsafe {
+            ExReleaseResourceLite(self.raw_resource.get());
+        }
+
+        // SAFETY: The critical region was entered when the resource was acquired.
+        unsafe {
+            KeLeaveCriticalRegion();
+        }
+    }
+}
+
+impl<T> Drop for Resource<T> {
+    fn drop(&mut self) {
+        // SAFETY: `raw_resource` is a private member of `Resource`, which was
+        // initialized in `Resource::new`. No guard can outlive the resource, so
+        // it is not held.
+        unsafe {
+            ExDeleteResourceLite(self.raw_resource.get());
+        }
+    }
+}
+
+// SAFETY: The protected value is moved along with the resource, so it must be
+// `Send`. Resources are not tied to the thread that created them.
+unsafe impl<T: Send> Send for Resource<T> {}
+
+// SAFETY: The protected value can be accessed by multiple threads at a time via
+// shared guards, so it must be `Sync`, and it can be mutated by any thread via
+// exclusive guards, so it must be `Send`.
+unsafe impl<T: Send + Sync> Sync for Resource<T> {}
+
+impl<T> Deref for ResourceSharedGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: The guard holds the resource for shared access, so no thread
+        // mutates the value.
+        unsafe { &*self.resource.data.get() }
+    }
+}
+
+impl<T> Drop for ResourceSharedGuard<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: The guard was created when this thread acquired the resource, and
+        // it is released exactly once here.
+        unsafe {
+            self.resource.release();
+        }
+    }
+}
+
+impl<T> Deref for ResourceExclusiveGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: The guard holds the resource for exclusive access, so no other
+        // thread accesses the value.
+        unsafe { &*self.resource.data.get() }
+    }
+}
+
+impl<T> DerefMut for ResourceExclusiveGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: The guard holds the resource for exclusive access, so no other
+        // thread accesses the value, and the exclusive borrow of the guard prevents
+        // other references from this thread.
+        unsafe { &mut *self.resource.data.get() }
+    }
+}
+
+impl<T> Drop for ResourceExclusiveGuard<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: The guard was created when this thread acquired the resource, and
+        // it is released exactly once here.
+        unsafe {
+            self.resource.release();
+        }
+    }
+}
+
+/// Mutual exclusion lock protecting a value of type `T`, built on a
+/// [`FastMutex`].
+///
+/// Its API mirrors [`std::sync::Mutex`], without lock poisoning, since a panic
+/// in a driver stops the system. Unlike [`FastMutex`], a [`Mutex`] can be moved
+/// freely, since the underlying `FAST_MUTEX` is allocated separately from it.
+/// The same `IRQL` requirements apply: the mutex must be locked at `IRQL` <=
+/// `APC_LEVEL`, and the `IRQL` is raised to `APC_LEVEL` while it is held.
+///
+/// [`std::sync::Mutex`]: https://doc.rust-lang.org/std/sync/struct.Mutex.html
+pub struct Mutex<T> {
+    fast_mutex: Pin<Box<FastMutex<()>>>,
+    data: UnsafeCell<T>,
+}
+
+/// RAII guard that provides access to the value protected by a [`Mutex`], and
+/// unlocks it when dropped
+#[must_use = "the mutex is unlocked as soon as the guard is dropped"]
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+    _fast_mutex_guard: FastMutexGuard<'a, ()>,
+}
+
+/// Reader-writer lock protecting a value of type `T`, built on a
+/// [`Resource`].
+///
+/// Its API mirrors [`std::sync::RwLock`], without lock poisoning, since a
+/// panic in a driver stops the system. Unlike [`Resource`], a [`RwLock`] can
+/// be moved freely, since the underlying `ERESOURCE` is allocated separately
+/// from it. The same `IRQL` requirements apply: the lock must be acquired at
+/// `IRQL` <= `APC_LEVEL`, and normal kernel APCs are disabled while it is
+/// held.
+///
+/// [`std::sync::RwLock`]: https://doc.rust-lang.org/std/sync/struct.RwLock.html
+pub struct RwLock<T> {
+    resource: Pin<Box<Resource<()>>>,
+    data: UnsafeCell<T>,
+}
+
+/// RAII guard that provides shared access to the value protected by a
+/// [`RwLock`], and releases the shared lock when dropped
+#[must_use = "the lock is released as soon as the guard is dropped"]
+pub struct RwLockReadGuard<'a, T> {
+    rw_lock: &'a RwLock<T>,
+    _resource_guard: ResourceSharedGuard<'a, ()>,
+}
+
+/// RAII guard that provides exclusive access to the value protected by a
+/// [`RwLock`], and releases the exclusive lock when dropped
+#[must_use = "the lock is released as soon as the guard is dropped"]
+pub struct RwLockWriteGuard<'a, T> {
+    rw_lock: &'a RwLock<T>,
+    _resource_guard: ResourceExclusiveGuard<'a, ()>,
+}
+
+impl<T> Mutex<T> {
+    /// Create a mutex protecting `value`
+    #[must_use]
+    pub fn new(value: T) -> Self {
+        Self {
+            fast_mutex: FastMutex::new(()),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// Lock the mutex, waiting until it is available. This must be called at
+    /// `IRQL` <= `APC_LEVEL`.
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        MutexGuard {
+            mutex: self,
+            _fast_mutex_guard: self.fast_mutex.lock(),
+        }
+    }
+
+    /// Try to lock the mutex without waiting, and return [`None`] if it is
+    /// already locked. This must be called at `IRQL` <= `APC_LEVEL`.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        self.fast_mutex
+            .try_lock()
+            .map(|fast_mutex_guard| MutexGuard {
+                mutex: self,
+                _fast_mutex_guard: fast_mutex_guard,
+            })
+    }
+
+    /// Get a mutable reference to the protected value. No locking is needed,
+    /// since the exclusive borrow of the mutex guarantees that it is not
+    /// locked.
+    #[must_use]
+    pub const fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Consume the mutex, and return the protected value
+    #[must_use]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for Mutex<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Mutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug_struct = f.debug_struct("Mutex");
+        match self.try_lock() {
+            Some(guard) => debug_struct.field("data", &&*guard),
+            None => debug_struct.field("data", &format_args!("<locked>")),
+        };
+        debug_struct.finish_non_exhaustive()
+    }
+}
+
+// SAFETY: The protected value is only accessed by the thread holding the mutex,
+// so it only needs to be `Send` for the mutex to be shared.
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: The guard holds the mutex, so no other thread accesses the value.
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: The guard holds the mutex, so no other thread accesses the value,
+        // and the exclusive borrow of the guard prevents other references from this
+        // thread.
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T> RwLock<T> {
+    /// Create a reader-writer lock protecting `value`
+    #[must_use]
+    pub fn new(value: T) -> Self {
+        Self {
+            resource: Resource::new(()),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// Acquire shared read access, waiting until no thread holds write access.
+    /// This must be called at `IRQL` <= `APC_LEVEL`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread already holds write access.
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        RwLockReadGuard {
+            rw_lock: self,
+            _resource_guard: self.resource.read(),
+        }
+    }
+
+    /// Try to acquire shared read access without waiting, and return [`None`]
+    /// if a thread holds write access. This must be called at `IRQL` <=
+    /// `APC_LEVEL`.
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        self.resource
+            .try_read()
+            .map(|resource_guard| RwLockReadGuard {
+                rw_lock: self,
+                _resource_guard: resource_guard,
+            })
+    }
+
+    /// Acquire exclusive write access, waiting until no other thread holds
+    /// read or write access. This must be called at `IRQL` <= `APC_LEVEL`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread already holds write access.
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        RwLockWriteGuard {
+            rw_lock: self,
+            _resource_guard: self.resource.write(),
+        }
+    }
+
+    /// Try to acquire exclusive write access without waiting, and return
+    /// [`None`] if another thread holds read or write access. This must be
+    /// called at `IRQL` <= `APC_LEVEL`.
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        self.resource
+            .try_write()
+            .map(|resource_guard| RwLockWriteGuard {
+                rw_lock: self,
+                _resource_guard: resource_guard,
+            })
+    }
+
+    /// Get a mutable reference to the protected value. No locking is needed,
+    /// since the exclusive borrow of the lock guarantees that it is not held.
+    #[must_use]
+    pub const fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Consume the lock, and return the protected value
+    #[must_use]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for RwLock<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug_struct = f.debug_struct("RwLock");
+        match self.try_read() {
+            Some(guard) => debug_struct.field("data", &&*guard),
+            None => debug_struct.field("data", &format_args!("<locked>")),
+        };
+        debug_struct.finish_non_exhaustive()
+    }
+}
+
+// SAFETY: The protected value can be accessed by multiple threads at a time via
+// read guards, so it must be `Sync`, and it can be mutated by any thread via
+// write guards, so it must be `Send`.
+unsafe impl<T: Send + Sync> Sync for RwLock<T> {}
+
+impl<T> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: The guard holds shared access to the lock, so no thread mutates the
+        // value.
+        unsafe { &*self.rw_lock.data.get() }
+    }
+}
+
+impl<T> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: The guard holds exclusive access to the lock, so no other thread
+        // accesses the value.
+        unsafe { &*self.rw_lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: The guard holds exclusive access to the lock, so no other thread
+        // accesses the value, and the exclusive borrow of the guard prevents other
+        // references from this thread.
+        unsafe { &mut *self.rw_lock.data.get() }
+    }
+}
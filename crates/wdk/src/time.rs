@@ -0,0 +1,289 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Kernel time utilities, which take care of the units and conventions of the
+//! times passed to kernel and WDF APIs.
+//!
+//! APIs that wait or schedule work (ex. `KeWaitForSingleObject`,
+//! `WdfTimerStart` and the `Timeout` of `WDF_REQUEST_SEND_OPTIONS`) take a
+//! signed count of 100-nanosecond ticks, where negative values are relative to
+//! the current time and positive values are absolute system times. Passing
+//! milliseconds, or forgetting to negate a relative timeout, compiles fine but
+//! makes the driver wait for the wrong amount of time. [`Timeout`] constructs
+//! these values from a [`Duration`] or a [`SystemTime`] instead:
+//!
+//! ```rust, no_run
+//! use wdk::{
+//!     time::{timeout_in, Duration, Instant},
+//!     wdf::Timer,
+//! };
+//!
+//! fn start_polling(timer: &Timer) -> Duration {
+//!     let start = Instant::now();
+//!     let _ = timer.start(timeout_in(Duration::from_millis(500)).as_raw());
+//!     start.elapsed()
+//! }
+//! ```
+//!
+//! [`Instant`] measures elapsed time via the performance counter, and
+//! [`SystemTime`] reads the wall-clock time of the system. Both are only
+//! available to kernel-mode drivers.
+
+#[cfg(not(driver_type = "umdf"))]
+use core::sync::atomic::{AtomicU64, Ordering};
+pub use core::time::Duration;
+
+#[cfg(not(driver_type = "umdf"))]
+use wdk_sys::ntddk::{KeQueryPerformanceCounter, KeQuerySystemTimePrecise};
+use wdk_sys::LARGE_INTEGER;
+
+/// Number of 100-nanosecond ticks per second, the unit of kernel times
+pub const TICKS_PER_SECOND: u64 = 10_000_000;
+
+/// Number of nanoseconds per 100-nanosecond tick
+const NANOSECONDS_PER_TICK: u32 = 100;
+
+/// Number of nanoseconds per second
+#[cfg(not(driver_type = "umdf"))]
+const NANOSECONDS_PER_SECOND: u128 = 1_000_000_000;
+
+/// Timeout of a kernel or WDF API, as a signed count of 100-nanosecond ticks.
+///
+/// Negative values are relative to the time the API is called, positive values
+/// are absolute system times, and zero expires immediately.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Timeout(i64);
+
+impl Timeout {
+    /// Timeout that expires immediately, ex. to check whether an object is
+    /// signaled without waiting for it
+    pub const IMMEDIATE: Self = Self(0);
+
+    /// Construct a [`Timeout`] that expires once `duration` has elapsed from
+    /// the time it is passed to an API. `duration` is rounded up to a whole
+    /// number of ticks, so the timeout never expires early, and saturates at
+    /// the longest timeout that can be represented.
+    #[must_use]
+    pub fn relative(duration: Duration) -> Self {
+        Self(-duration_to_ticks(duration))
+    }
+
+    /// Construct a [`Timeout`] that expires at the system time `deadline`.
+    /// Unlike a relative timeout, it expires earlier or later if the system
+    /// time is changed while waiting.
+    #[cfg(not(driver_type = "umdf"))]
+    #[must_use]
+    pub const fn at(deadline: SystemTime) -> Self {
+        Self(deadline.ticks)
+    }
+
+    /// Construct a [`Timeout`] from a raw count of 100-nanosecond ticks, which
+    /// follows the conventions described on [`Timeout`]
+    #[must_use]
+    pub const fn from_raw(ticks: i64) -> Self {
+        Self(ticks)
+    }
+
+    /// Get the raw count of 100-nanosecond ticks, ex. to pass as the `DueTime`
+    /// of `WdfTimerStart` or the `Timeout` of `WDF_REQUEST_SEND_OPTIONS`
+    #[must_use]
+    pub const fn as_raw(self) -> i64 {
+        self.0
+    }
+
+    /// Get the timeout as a `LARGE_INTEGER`, ex. to pass to
+    /// `KeWaitForSingleObject`
+    #[must_use]
+    pub const fn as_large_integer(self) -> LARGE_INTEGER {
+        LARGE_INTEGER { QuadPart: self.0 }
+    }
+
+    /// Returns `true` if the timeout is relative to the time it is passed to
+    /// an API
+    #[must_use]
+    pub const fn is_relative(self) -> bool {
+        self.0 < 0
+    }
+
+    /// Get the duration of a relative timeout, or [`None`] if the timeout is
+    /// an absolute system time
+    #[must_use]
+    pub const fn relative_duration(self) -> Option<Duration> {
+        if self.is_relative() {
+            Some(ticks_to_duration(self.0.unsigned_abs()))
+        } else {
+            None
+        }
+    }
+}
+
+/// Construct a [`Timeout`] that expires once `duration` has elapsed. This is a
+/// shorthand for [`Timeout::relative`].
+#[must_use]
+pub fn timeout_in(duration: Duration) -> Timeout {
+    Timeout::relative(duration)
+}
+
+/// Wall-clock time of the system, as a count of 100-nanosecond ticks since
+/// January 1, 1601 (UTC).
+///
+/// The system time can be changed at any time (ex. by the user, or to
+/// synchronize with a time server), so it must not be used to measure elapsed
+/// time. Use [`Instant`] instead.
+#[cfg(not(driver_type = "umdf"))]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct SystemTime {
+    ticks: i64,
+}
+
+#[cfg(not(driver_type = "umdf"))]
+impl SystemTime {
+    /// Get the current system time via `KeQuerySystemTimePrecise`. This can be
+    /// called at any IRQL.
+    #[must_use]
+    pub fn now() -> Self {
+        let mut current_time = LARGE_INTEGER { QuadPart: 0 };
+        // SAFETY: `KeQuerySystemTimePrecise` can be called at any IRQL, and
+        // `current_time` is valid for writes.
+        unsafe {
+            KeQuerySystemTimePrecise(core::ptr::addr_of_mut!(current_time));
+        }
+        Self {
+            // SAFETY: Every member of `LARGE_INTEGER` is a plain integer, so its
+            // `QuadPart` is always initialized.
+            ticks: unsafe { current_time.QuadPart },
+        }
+    }
+
+    /// Construct a [`SystemTime`] from a count of 100-nanosecond ticks since
+    /// January 1, 1601 (UTC)
+    #[must_use]
+    pub const fn from_raw(ticks: i64) -> Self {
+        Self { ticks }
+    }
+
+    /// Get the count of 100-nanosecond ticks since January 1, 1601 (UTC)
+    #[must_use]
+    pub const fn as_raw(self) -> i64 {
+        self.ticks
+    }
+
+    /// Get the system time `duration` after this one, or [`None`] if it cannot
+    /// be represented
+    #[must_use]
+    pub fn checked_add(self, duration: Duration) -> Option<Self> {
+        Some(Self {
+            ticks: self.ticks.checked_add(duration_to_ticks(duration))?,
+        })
+    }
+
+    /// Get the duration elapsed from `earlier` to this system time, or
+    /// [`None`] if `earlier` is later than this system time
+    #[must_use]
+    pub fn duration_since(self, earlier: Self) -> Option<Duration> {
+        let ticks = u64::try_from(self.ticks.checked_sub(earlier.ticks)?).ok()?;
+        Some(ticks_to_duration(ticks))
+    }
+}
+
+/// Frequency of the performance counter, in counts per second. This is fixed
+/// at boot, and cached by [`Instant::now`].
+#[cfg(not(driver_type = "umdf"))]
+static PERFORMANCE_FREQUENCY: AtomicU64 = AtomicU64::new(0);
+
+/// Monotonic timestamp, read from the performance counter via
+/// `KeQueryPerformanceCounter`, for measuring elapsed time
+#[cfg(not(driver_type = "umdf"))]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Instant {
+    counter: i64,
+}
+
+#[cfg(not(driver_type = "umdf"))]
+impl Instant {
+    /// Get the current value of the performance counter. This can be called at
+    /// any IRQL.
+    #[must_use]
+    pub fn now() -> Self {
+        let mut frequency = LARGE_INTEGER { QuadPart: 0 };
+        // SAFETY: `KeQueryPerformanceCounter` can be called at any IRQL, and
+        // `frequency` is valid for writes.
+        let counter = unsafe { KeQueryPerformanceCounter(core::ptr::addr_of_mut!(frequency)) };
+        // SAFETY: Every member of `LARGE_INTEGER` is a plain integer, so its
+        // `QuadPart` is always initialized.
+        let frequency = unsafe { frequency.QuadPart };
+        PERFORMANCE_FREQUENCY.store(frequency.unsigned_abs(), Ordering::Relaxed);
+        Self {
+            // SAFETY: Every member of `LARGE_INTEGER` is a plain integer, so its
+            // `QuadPart` is always initialized.
+            counter: unsafe { counter.QuadPart },
+        }
+    }
+
+    /// Get the duration elapsed since this instant
+    #[must_use]
+    pub fn elapsed(self) -> Duration {
+        Self::now().duration_since(self)
+    }
+
+    /// Get the duration elapsed from `earlier` to this instant, or zero if
+    /// `earlier` is later than this instant
+    #[must_use]
+    pub fn duration_since(self, earlier: Self) -> Duration {
+        self.checked_duration_since(earlier).unwrap_or_default()
+    }
+
+    /// Get the duration elapsed from `earlier` to this instant, or [`None`] if
+    /// `earlier` is later than this instant
+    #[must_use]
+    pub fn checked_duration_since(self, earlier: Self) -> Option<Duration> {
+        let counts = u128::try_from(self.counter.checked_sub(earlier.counter)?).ok()?;
+        let frequency = performance_frequency();
+        let seconds = u64::try_from(counts / frequency).ok()?;
+        // The remainder is less than the frequency, so the number of nanoseconds is
+        // less than one second and fits in a u32
+        #[allow(clippy::cast_possible_truncation)]
+        let nanoseconds = ((counts % frequency) * NANOSECONDS_PER_SECOND / frequency) as u32;
+        Some(Duration::new(seconds, nanoseconds))
+    }
+
+    /// Get the instant `duration` after this one, or [`None`] if it cannot be
+    /// represented
+    #[must_use]
+    pub fn checked_add(self, duration: Duration) -> Option<Self> {
+        let counts = (duration.as_nanos().checked_mul(performance_frequency())?)
+            .div_ceil(NANOSECONDS_PER_SECOND);
+        Some(Self {
+            counter: self.counter.checked_add(i64::try_from(counts).ok()?)?,
+        })
+    }
+}
+
+/// Get the frequency of the performance counter, which was cached when the
+/// first [`Instant`] was created
+#[cfg(not(driver_type = "umdf"))]
+fn performance_frequency() -> u128 {
+    u128::from(PERFORMANCE_FREQUENCY.load(Ordering::Relaxed).max(1))
+}
+
+/// Convert `duration` to a count of 100-nanosecond ticks, rounding up and
+/// saturating at [`i64::MAX`]
+fn duration_to_ticks(duration: Duration) -> i64 {
+    i64::try_from(
+        duration
+            .as_nanos()
+            .div_ceil(u128::from(NANOSECONDS_PER_TICK)),
+    )
+    .unwrap_or(i64::MAX)
+}
+
+/// Convert a count of 100-nanosecond ticks to a [`Duration`]
+const fn ticks_to_duration(ticks: u64) -> Duration {
+    // The remainder is less than `TICKS_PER_SECOND`, so it fits in a u32
+    #[allow(clippy::cast_possible_truncation)]
+    let subsecond_ticks = (ticks % TICKS_PER_SECOND) as u32;
+    Duration::new(
+        ticks / TICKS_PER_SECOND,
+        subsecond_ticks * NANOSECONDS_PER_TICK,
+    )
+}
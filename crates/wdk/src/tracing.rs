@@ -0,0 +1,150 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Adapter that prints [`tracing`](https://docs.rs/tracing) events to the
+//! kernel debugger, so they show up in tools like `DbgView` and `WinDbg`.
+//!
+//! Events are printed via [`print_with_level`](crate::print::print_with_level)
+//! with the component ID configured via
+//! [`set_component_id`](crate::print::set_component_id), and are also logged
+//! to the [`LogBuffer`](crate::log_buffer::LogBuffer) set via
+//! [`set_log_buffer`](crate::print::set_log_buffer), if any. Spans are not
+//! tracked.
+//!
+//! `tracing-core` requires `alloc`, so drivers using this module must have a
+//! global allocator (ex. `wdk-alloc`).
+//!
+//! # Examples
+//!
+//! ```rust, no_run
+//! use tracing_core::LevelFilter;
+//!
+//! wdk::tracing::init(LevelFilter::INFO).expect("global subscriber should not already be set");
+//! ```
+
+use core::fmt;
+
+use tracing_core::{
+    dispatcher::{self, SetGlobalDefaultError},
+    field::{Field, Visit},
+    span,
+    Dispatch,
+    Event,
+    Level,
+    LevelFilter,
+    Metadata,
+    Subscriber,
+};
+
+use crate::print::{self, print_with_level};
+
+/// [`Subscriber`] that prints events to the kernel debugger via
+/// [`DbgPrintEx`](wdk_sys::ntddk::DbgPrintEx)
+#[derive(Debug)]
+pub struct DbgPrintSubscriber {
+    max_level: LevelFilter,
+}
+
+impl DbgPrintSubscriber {
+    /// Create a [`DbgPrintSubscriber`] that prints events at `max_level` or
+    /// more severe
+    #[must_use]
+    pub const fn new(max_level: LevelFilter) -> Self {
+        Self { max_level }
+    }
+}
+
+impl Subscriber for DbgPrintSubscriber {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= &self.max_level
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(self.max_level)
+    }
+
+    fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+        // Spans are not tracked, so every span shares the same id
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let metadata = event.metadata();
+        print_with_level(
+            dbg_print_level(*metadata.level()),
+            format_args!(
+                "{:>5} {}: {}\n",
+                metadata.level(),
+                metadata.target(),
+                EventFields(event)
+            ),
+        );
+    }
+
+    fn enter(&self, _span: &span::Id) {}
+
+    fn exit(&self, _span: &span::Id) {}
+}
+
+/// Set a [`DbgPrintSubscriber`] with the given `max_level` as the global
+/// default [`Subscriber`]
+///
+/// # Errors
+///
+/// This function will return an error if a global default subscriber has
+/// already been set
+pub fn init(max_level: LevelFilter) -> Result<(), SetGlobalDefaultError> {
+    dispatcher::set_global_default(Dispatch::new(DbgPrintSubscriber::new(max_level)))
+}
+
+const fn dbg_print_level(level: Level) -> print::Level {
+    match level {
+        Level::ERROR => print::Level::Error,
+        Level::WARN => print::Level::Warning,
+        Level::INFO => print::Level::Info,
+        _ => print::Level::Trace,
+    }
+}
+
+/// [`fmt::Display`] implementation for the fields of an [`Event`], which
+/// prints the `message` field followed by the remaining fields as `name=value`
+struct EventFields<'a>(&'a Event<'a>);
+
+impl fmt::Display for EventFields<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut visitor = FieldVisitor {
+            writer: f,
+            result: Ok(()),
+            is_first_field: true,
+        };
+        self.0.record(&mut visitor);
+        visitor.result
+    }
+}
+
+struct FieldVisitor<'a, 'b> {
+    writer: &'a mut fmt::Formatter<'b>,
+    result: fmt::Result,
+    is_first_field: bool,
+}
+
+impl Visit for FieldVisitor<'_, '_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if self.result.is_err() {
+            return;
+        }
+
+        let separator = if self.is_first_field { "" } else { " " };
+        self.is_first_field = false;
+
+        self.result = if field.name() == "message" {
+            write!(self.writer, "{separator}{value:?}")
+        } else {
+            write!(self.writer, "{separator}{}={value:?}", field.name())
+        };
+    }
+}
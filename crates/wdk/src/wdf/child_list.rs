@@ -0,0 +1,325 @@
+use core::marker::PhantomData;
+
+use wdk_sys::{
+    macros,
+    BOOLEAN,
+    NTSTATUS,
+    PWDFDEVICE_INIT,
+    PWDF_CHILD_IDENTIFICATION_DESCRIPTION_HEADER,
+    STATUS_SUCCESS,
+    ULONG,
+    WDFCHILDLIST,
+    WDFDEVICE,
+    WDFOBJECT,
+    WDF_CHILD_IDENTIFICATION_DESCRIPTION_HEADER,
+    WDF_CHILD_LIST_CONFIG,
+    WDF_NO_OBJECT_ATTRIBUTES,
+};
+
+use crate::{
+    nt_success,
+    wdf::{FromWdfObject, ObjectHandle, PdoInitBuilder},
+};
+
+/// WDF Child List.
+///
+/// A framework child list holds the identification descriptions of the
+/// children that a bus driver dynamically enumerates. Children are reported
+/// as present or missing via [`ChildList::add_or_update_present`] and
+/// [`ChildList::update_as_missing`], and the framework calls
+/// [`ChildListCallbacks::on_create_device`] to create a PDO for each new child.
+///
+/// Each identification description is a value of type `T` that uniquely
+/// identifies a child on the bus, ex. its slot number or serial number.
+pub struct ChildList<T> {
+    wdf_child_list: WDFCHILDLIST,
+    _description: PhantomData<T>,
+}
+
+/// Callbacks invoked by the framework for the default child list of a bus
+/// driver's FDO, registered via [`ChildList::configure_device_init`].
+pub trait ChildListCallbacks {
+    /// Type of the identification description of each child. Descriptions are
+    /// copied by value into framework-allocated memory, and compared via
+    /// [`PartialEq`] to detect whether a reported child is already present.
+    type Description: Copy + PartialEq;
+
+    /// Called when the framework needs to create a PDO for a newly reported
+    /// child (`EvtChildListCreateDevice`). Implementations must assign the
+    /// identifiers of the child to `pdo_init`, and create the PDO via
+    /// [`PdoInitBuilder::create_device`].
+    ///
+    /// # Errors
+    ///
+    /// Returning [`Err`] reports the child as failed to the framework, which
+    /// then retries creating it on the next enumeration.
+    fn on_create_device(
+        child_list: &ChildList<Self::Description>,
+        description: &Self::Description,
+        pdo_init: PdoInitBuilder,
+    ) -> Result<(), NTSTATUS>;
+
+    /// Called when the framework requests the driver to report all children
+    /// that are present (`EvtChildListScanForChildren`). Children that are
+    /// not reported via [`ChildList::add_or_update_present`] during this call
+    /// are considered missing.
+    fn on_scan_for_children(_child_list: &ChildList<Self::Description>) {}
+}
+
+/// Identification description that is handed to WDF, which must begin with a
+/// `WDF_CHILD_IDENTIFICATION_DESCRIPTION_HEADER`
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct IdentificationDescription<T> {
+    header: WDF_CHILD_IDENTIFICATION_DESCRIPTION_HEADER,
+    description: T,
+}
+
+impl<T: Copy> IdentificationDescription<T> {
+    const fn new(description: T) -> Self {
+        Self {
+            header: WDF_CHILD_IDENTIFICATION_DESCRIPTION_HEADER {
+                // The size of IdentificationDescription is checked to fit in a ULONG by
+                // `ChildList::configure_device_init`
+                #[allow(clippy::cast_possible_truncation)]
+                IdentificationDescriptionSize: core::mem::size_of::<Self>() as ULONG,
+            },
+            description,
+        }
+    }
+
+    /// Get the description stored in the identification description pointed to
+    /// by `header`
+    ///
+    /// # Safety
+    ///
+    /// `header` must point to a valid `IdentificationDescription<T>` that
+    /// outlives `'a`.
+    const unsafe fn description<'a>(header: PWDF_CHILD_IDENTIFICATION_DESCRIPTION_HEADER) -> &'a T {
+        // SAFETY: The caller guarantees that `header` points to a valid
+        // `IdentificationDescription<T>` that outlives `'a`.
+        unsafe { &(*header.cast::<Self>()).description }
+    }
+}
+
+impl<T: Copy + PartialEq> ChildList<T> {
+    /// Configure the default child list of the FDO that is being initialized
+    /// by `device_init`, and register the [`ChildListCallbacks`] of `C` for
+    /// it. This must be called from `EvtDriverDeviceAdd`, before
+    /// `WdfDeviceCreate`.
+    ///
+    /// # Safety
+    ///
+    /// `device_init` must be the valid `PWDFDEVICE_INIT` passed to
+    /// `EvtDriverDeviceAdd`, and must not have been passed to
+    /// `WdfDeviceCreate` yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size of [`ChildListCallbacks::Description`] does not fit
+    /// in a `ULONG`.
+    pub unsafe fn configure_device_init<C: ChildListCallbacks<Description = T>>(
+        device_init: PWDFDEVICE_INIT,
+    ) {
+        let mut child_list_config = WDF_CHILD_LIST_CONFIG {
+            // The size of WDF_CHILD_LIST_CONFIG is known to fit in a ULONG
+            #[allow(clippy::cast_possible_truncation)]
+            Size: core::mem::size_of::<WDF_CHILD_LIST_CONFIG>() as ULONG,
+            IdentificationDescriptionSize: ULONG::try_from(core::mem::size_of::<
+                IdentificationDescription<T>,
+            >())
+            .expect("size of child identification description should fit in a ULONG"),
+            EvtChildListCreateDevice: Some(evt_child_list_create_device::<C>),
+            EvtChildListScanForChildren: Some(evt_child_list_scan_for_children::<C>),
+            EvtChildListIdentificationDescriptionCompare: Some(
+                evt_child_list_identification_description_compare::<T>,
+            ),
+            ..WDF_CHILD_LIST_CONFIG::default()
+        };
+
+        // SAFETY: The caller guarantees that `device_init` is valid and has not been
+        // used to create a device yet.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfFdoInitSetDefaultChildListConfig,
+                device_init,
+                &mut child_list_config,
+                WDF_NO_OBJECT_ATTRIBUTES,
+            );
+        }
+    }
+
+    /// Get the default child list of `fdo`. Returns [`None`] if `fdo` has no
+    /// default child list.
+    ///
+    /// # Safety
+    ///
+    /// `fdo` must be a valid FDO whose default child list was configured via
+    /// [`ChildList::configure_device_init`] with a [`ChildListCallbacks`]
+    /// implementation whose `Description` is `T`.
+    #[must_use]
+    pub unsafe fn default_for_device(fdo: WDFDEVICE) -> Option<Self> {
+        let wdf_child_list;
+        // SAFETY: The caller guarantees that `fdo` is a valid FDO.
+        unsafe {
+            wdf_child_list =
+                macros::call_unsafe_wdf_function_binding!(WdfFdoGetDefaultChildList, fdo);
+        }
+
+        (!wdf_child_list.is_null()).then_some(Self {
+            wdf_child_list,
+            _description: PhantomData,
+        })
+    }
+
+    /// Report the child identified by `description` as present. If the child
+    /// is not in the child list yet, the framework creates a PDO for it via
+    /// [`ChildListCallbacks::on_create_device`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to add the child to the child list. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFChildList Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfchildlist/nf-wdfchildlist-wdfchildlistaddorupdatechilddescriptionaspresent#return-value)
+    pub fn add_or_update_present(&self, description: &T) -> Result<(), NTSTATUS> {
+        let mut identification_description = IdentificationDescription::new(*description);
+
+        let nt_status;
+        // SAFETY: `wdf_child_list` is a private member of `ChildList`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state. `identification_description` has the layout and size configured in
+        // `ChildList::configure_device_init`, and is copied by WDF.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfChildListAddOrUpdateChildDescriptionAsPresent,
+                self.wdf_child_list,
+                &mut identification_description.header,
+                core::ptr::null_mut(),
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Report the child identified by `description` as missing. The framework
+    /// then deletes the PDO of the child.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to update the child. The error variant will contain a [`NTSTATUS`] of the failure, ex. `STATUS_NO_SUCH_DEVICE` if the child is not in the child list. Full error documentation is available in the [WDFChildList Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfchildlist/nf-wdfchildlist-wdfchildlistupdatechilddescriptionasmissing#return-value)
+    pub fn update_as_missing(&self, description: &T) -> Result<(), NTSTATUS> {
+        let mut identification_description = IdentificationDescription::new(*description);
+
+        let nt_status;
+        // SAFETY: `wdf_child_list` is a private member of `ChildList`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state. `identification_description` has the layout and size configured in
+        // `ChildList::configure_device_init`.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfChildListUpdateChildDescriptionAsMissing,
+                self.wdf_child_list,
+                &mut identification_description.header,
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Rescan the children of the child list outside of
+    /// [`ChildListCallbacks::on_scan_for_children`]. Every child that is not
+    /// reported via [`ChildList::add_or_update_present`] from within `f` is
+    /// considered missing once `f` returns.
+    pub fn scan<F: FnOnce(&Self)>(&self, f: F) {
+        // SAFETY: `wdf_child_list` is a private member of `ChildList`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(WdfChildListBeginScan, self.wdf_child_list);
+        }
+
+        f(self);
+
+        // SAFETY: `wdf_child_list` is a private member of `ChildList`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state. `WdfChildListBeginScan` was called above.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(WdfChildListEndScan, self.wdf_child_list);
+        }
+    }
+}
+
+// SAFETY: `wdf_child_list` is a private member of `ChildList`, originally
+// created by WDF, and this module guarantees that it is always in a valid
+// state.
+unsafe impl<T> ObjectHandle for ChildList<T> {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_child_list.cast()
+    }
+}
+
+// SAFETY: `ChildList` does not delete the underlying object when dropped.
+unsafe impl<T> FromWdfObject for ChildList<T> {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            wdf_child_list: wdf_object.cast(),
+            _description: PhantomData,
+        }
+    }
+}
+
+/// `EvtChildListCreateDevice` trampoline that forwards to
+/// [`ChildListCallbacks::on_create_device`]
+unsafe extern "C" fn evt_child_list_create_device<C: ChildListCallbacks>(
+    wdf_child_list: WDFCHILDLIST,
+    identification_description: PWDF_CHILD_IDENTIFICATION_DESCRIPTION_HEADER,
+    child_init: PWDFDEVICE_INIT,
+) -> NTSTATUS {
+    let child_list = ChildList {
+        wdf_child_list,
+        _description: PhantomData,
+    };
+
+    // SAFETY: The framework passes a copy of an identification description that
+    // was reported via `ChildList::add_or_update_present`, which is valid for the
+    // duration of this callback.
+    let description = unsafe {
+        IdentificationDescription::<C::Description>::description(identification_description)
+    };
+
+    // SAFETY: The framework passes a valid `PWDFDEVICE_INIT` that it owns, and
+    // that has not been used to create a device yet.
+    let pdo_init = unsafe { PdoInitBuilder::from_raw(child_init) };
+
+    match C::on_create_device(&child_list, description, pdo_init) {
+        Ok(()) => STATUS_SUCCESS,
+        Err(nt_status) => nt_status,
+    }
+}
+
+/// `EvtChildListScanForChildren` trampoline that forwards to
+/// [`ChildListCallbacks::on_scan_for_children`]
+unsafe extern "C" fn evt_child_list_scan_for_children<C: ChildListCallbacks>(
+    wdf_child_list: WDFCHILDLIST,
+) {
+    C::on_scan_for_children(&ChildList {
+        wdf_child_list,
+        _description: PhantomData,
+    });
+}
+
+/// `EvtChildListIdentificationDescriptionCompare` callback that compares two
+/// identification descriptions via [`PartialEq`]
+unsafe extern "C" fn evt_child_list_identification_description_compare<T: Copy + PartialEq>(
+    _wdf_child_list: WDFCHILDLIST,
+    first_identification_description: PWDF_CHILD_IDENTIFICATION_DESCRIPTION_HEADER,
+    second_identification_description: PWDF_CHILD_IDENTIFICATION_DESCRIPTION_HEADER,
+) -> BOOLEAN {
+    // SAFETY: The framework only compares identification descriptions of the child
+    // list, which all have the layout configured in
+    // `ChildList::configure_device_init`.
+    let first =
+        unsafe { IdentificationDescription::<T>::description(first_identification_description) };
+    // SAFETY: The framework only compares identification descriptions of the child
+    // list, which all have the layout configured in
+    // `ChildList::configure_device_init`.
+    let second =
+        unsafe { IdentificationDescription::<T>::description(second_identification_description) };
+    BOOLEAN::from(first == second)
+}
@@ -0,0 +1,345 @@
+use core::{marker::PhantomData, mem::ManuallyDrop, ops::Deref};
+
+use wdk_sys::{
+    macros,
+    NTSTATUS,
+    ULONG,
+    WDFCOLLECTION,
+    WDFOBJECT,
+    WDF_NO_OBJECT_ATTRIBUTES,
+    WDF_OBJECT_ATTRIBUTES,
+};
+
+use crate::{
+    nt_success,
+    wdf::{FromWdfObject, ObjectHandle},
+};
+
+/// WDF Collection.
+///
+/// A framework collection object is an ordered list of WDF objects of type
+/// `T`. Adding an object to a collection takes a reference on the object,
+/// which is released when the object is removed from the collection or when
+/// the collection is deleted. Collections are not internally synchronized: a
+/// driver that accesses a collection from multiple threads must serialize
+/// access to it, ex. via a [`SpinLock`](crate::wdf::SpinLock).
+pub struct Collection<T> {
+    wdf_collection: WDFCOLLECTION,
+    _item: PhantomData<T>,
+}
+
+impl<T: FromWdfObject> Collection<T> {
+    /// Try to construct a WDF Collection object
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a collection. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFCollection Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfcollection/nf-wdfcollection-wdfcollectioncreate#return-value)
+    pub fn try_new(attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>) -> Result<Self, NTSTATUS> {
+        let mut collection = Self {
+            wdf_collection: core::ptr::null_mut(),
+            _item: PhantomData,
+        };
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfCollectionCreate,
+                attributes.map_or(WDF_NO_OBJECT_ATTRIBUTES, core::ptr::from_mut),
+                &mut collection.wdf_collection,
+            );
+        }
+        nt_success(nt_status).then_some(collection).ok_or(nt_status)
+    }
+
+    /// Try to construct a WDF Collection object. This is an alias for
+    /// [`Collection::try_new()`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a collection. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFCollection Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfcollection/nf-wdfcollection-wdfcollectioncreate#return-value)
+    pub fn create(attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>) -> Result<Self, NTSTATUS> {
+        Self::try_new(attributes)
+    }
+
+    /// Add `item` to the end of the collection
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to add `item` to the collection. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFCollection Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfcollection/nf-wdfcollection-wdfcollectionadd#return-value)
+    pub fn add(&self, item: &T) -> Result<(), NTSTATUS> {
+        let nt_status;
+        // SAFETY: `wdf_collection` is a private member of `Collection`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state. `item` is a valid WDF object, as guaranteed by its `ObjectHandle`
+        // implementation.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfCollectionAdd,
+                self.wdf_collection,
+                item.as_wdf_object(),
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Remove `item` from the collection. Returns `false` if `item` is not in
+    /// the collection.
+    pub fn remove(&mut self, item: &T) -> bool {
+        let wdf_object = item.as_wdf_object();
+        if !(0..self.raw_len()).any(|index| self.raw_item(index) == wdf_object) {
+            return false;
+        }
+
+        // SAFETY: `wdf_collection` is a private member of `Collection`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state. `item` was found in the collection above, so WDF will not bugcheck.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfCollectionRemove,
+                self.wdf_collection,
+                wdf_object,
+            );
+        }
+        true
+    }
+
+    /// Remove the item at `index` from the collection, returning it. Returns
+    /// [`None`] if `index` is out of bounds.
+    #[must_use]
+    pub fn remove_at(&mut self, index: usize) -> Option<RemovedItem<T>> {
+        let index = ULONG::try_from(index).ok()?;
+        let wdf_object = self.raw_item(index);
+        if wdf_object.is_null() {
+            return None;
+        }
+
+        // SAFETY: `wdf_object` is an item of the collection, so it is a valid WDF
+        // object. The reference keeps it valid after the collection releases its
+        // reference below, and is released when the `RemovedItem` is dropped.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfObjectReferenceActual,
+                wdf_object,
+                core::ptr::null_mut(),
+                0,
+                core::ptr::null(),
+            );
+        }
+
+        // SAFETY: `wdf_collection` is a private member of `Collection`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state. `index` was checked to be in bounds above, so WDF will not bugcheck.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfCollectionRemoveItem,
+                self.wdf_collection,
+                index,
+            );
+        }
+
+        Some(RemovedItem {
+            // SAFETY: Only objects of type `T` can be added to a `Collection<T>`, and
+            // the reference taken above keeps the object valid for the lifetime of the
+            // `RemovedItem`.
+            item: ManuallyDrop::new(unsafe { T::from_wdf_object(wdf_object) }),
+        })
+    }
+
+    /// Get the item at `index`. Returns [`None`] if `index` is out of bounds.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<CollectionItem<'_, T>> {
+        let index = ULONG::try_from(index).ok()?;
+        self.wrap(self.raw_item(index))
+    }
+
+    /// Get the first item of the collection. Returns [`None`] if the
+    /// collection is empty.
+    #[must_use]
+    pub fn first(&self) -> Option<CollectionItem<'_, T>> {
+        let wdf_object;
+        // SAFETY: `wdf_collection` is a private member of `Collection`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            wdf_object = macros::call_unsafe_wdf_function_binding!(
+                WdfCollectionGetFirstItem,
+                self.wdf_collection,
+            );
+        }
+        self.wrap(wdf_object)
+    }
+
+    /// Get the last item of the collection. Returns [`None`] if the collection
+    /// is empty.
+    #[must_use]
+    pub fn last(&self) -> Option<CollectionItem<'_, T>> {
+        let wdf_object;
+        // SAFETY: `wdf_collection` is a private member of `Collection`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            wdf_object = macros::call_unsafe_wdf_function_binding!(
+                WdfCollectionGetLastItem,
+                self.wdf_collection,
+            );
+        }
+        self.wrap(wdf_object)
+    }
+
+    /// Get the number of items in the collection
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.raw_len() as usize
+    }
+
+    /// Returns `true` if the collection contains no items
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.raw_len() == 0
+    }
+
+    /// Get an iterator over the items of the collection
+    #[must_use]
+    pub const fn iter(&self) -> CollectionIter<'_, T> {
+        CollectionIter {
+            collection: self,
+            index: 0,
+        }
+    }
+
+    fn raw_len(&self) -> ULONG {
+        let count;
+        // SAFETY: `wdf_collection` is a private member of `Collection`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            count = macros::call_unsafe_wdf_function_binding!(
+                WdfCollectionGetCount,
+                self.wdf_collection,
+            );
+        }
+        count
+    }
+
+    fn raw_item(&self, index: ULONG) -> WDFOBJECT {
+        let wdf_object;
+        // SAFETY: `wdf_collection` is a private member of `Collection`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state. `WdfCollectionGetItem` returns `NULL` for out of bounds indices.
+        unsafe {
+            wdf_object = macros::call_unsafe_wdf_function_binding!(
+                WdfCollectionGetItem,
+                self.wdf_collection,
+                index,
+            );
+        }
+        wdf_object
+    }
+
+    /// Wrap `wdf_object`, an item of the collection, in a [`CollectionItem`]
+    /// that borrows the collection
+    fn wrap(&self, wdf_object: WDFOBJECT) -> Option<CollectionItem<'_, T>> {
+        if wdf_object.is_null() {
+            return None;
+        }
+
+        Some(CollectionItem {
+            // SAFETY: Only objects of type `T` can be added to a `Collection<T>`, and the
+            // collection holds a reference on each of its items. Items can only be
+            // removed through `&mut self`, so the item stays in the collection for as
+            // long as the returned `CollectionItem` borrows it.
+            item: ManuallyDrop::new(unsafe { T::from_wdf_object(wdf_object) }),
+            _collection: PhantomData,
+        })
+    }
+}
+
+// SAFETY: `wdf_collection` is a private member of `Collection`, originally
+// created by WDF, and this module guarantees that it is always in a valid
+// state.
+unsafe impl<T> ObjectHandle for Collection<T> {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_collection.cast()
+    }
+}
+
+/// Item of a [`Collection`], borrowed from the collection. The reference that
+/// the collection holds on the item keeps it valid for the lifetime of the
+/// borrow.
+pub struct CollectionItem<'a, T> {
+    item: ManuallyDrop<T>,
+    _collection: PhantomData<&'a Collection<T>>,
+}
+
+impl<T> Deref for CollectionItem<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.item
+    }
+}
+
+/// Item removed from a [`Collection`] by [`Collection::remove_at`]. It holds a
+/// reference on the object, taken before the collection released its own, that
+/// is released when the `RemovedItem` is dropped.
+pub struct RemovedItem<T: ObjectHandle> {
+    item: ManuallyDrop<T>,
+}
+
+impl<T: ObjectHandle> Deref for RemovedItem<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.item
+    }
+}
+
+impl<T: ObjectHandle> Drop for RemovedItem<T> {
+    fn drop(&mut self) {
+        // SAFETY: The object is valid, since `Collection::remove_at` took a reference
+        // on it that is only released here.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfObjectDereferenceActual,
+                self.item.as_wdf_object(),
+                core::ptr::null_mut(),
+                0,
+                core::ptr::null(),
+            );
+        }
+    }
+}
+
+impl<'a, T: FromWdfObject> IntoIterator for &'a Collection<T> {
+    type IntoIter = CollectionIter<'a, T>;
+    type Item = CollectionItem<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the items of a [`Collection`], created by
+/// [`Collection::iter`]
+pub struct CollectionIter<'a, T> {
+    collection: &'a Collection<T>,
+    index: usize,
+}
+
+impl<'a, T: FromWdfObject> Iterator for CollectionIter<'a, T> {
+    type Item = CollectionItem<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.collection.get(self.index)?;
+        self.index += 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.collection.len().saturating_sub(self.index);
+        (remaining, Some(remaining))
+    }
+}
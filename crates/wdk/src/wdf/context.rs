@@ -0,0 +1,410 @@
+//! Typed WDF object context space.
+//!
+//! Every WDF object can carry one or more driver-defined context areas, which
+//! the framework allocates alongside the object and frees when the object is
+//! destroyed. In C, these are declared via `WDF_DECLARE_CONTEXT_TYPE` and
+//! accessed via the generated `WdfObjectGet_<Type>` accessor. In Rust, context
+//! types are declared via
+//! [`wdf_declare_context_type!`](crate::wdf_declare_context_type), and accessed
+//! via the methods of [`ObjectHandle`]:
+//!
+//! ```rust, no_run
+//! use wdk::wdf::{self, ObjectHandle};
+//!
+//! wdk::wdf_declare_context_type!(
+//!     /// Context stored in every timer created by this driver
+//!     struct TimerContext {
+//!         ticks: core::sync::atomic::AtomicU32,
+//!     }
+//! );
+//!
+//! fn setup(timer: &wdf::Timer) {
+//!     let _ = timer.init_context(TimerContext {
+//!         ticks: core::sync::atomic::AtomicU32::new(0),
+//!     });
+//! }
+//!
+//! fn tick(timer: &wdf::Timer) {
+//!     if let Some(context) = timer.context::<TimerContext>() {
+//!         context
+//!             .ticks
+//!             .fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+//!     }
+//! }
+//! ```
+//!
+//! The framework zero-initializes context space, which is not a valid value
+//! for most Rust types. To avoid ever handing out references to such memory,
+//! each context area tracks whether it has been initialized:
+//! [`ObjectHandle::init_context`] writes the value exactly once, and the
+//! accessors return [`None`] until it has been written.
+//!
+//! This is checked at runtime (via an atomic state in each context area)
+//! rather than in the type system, since a context area is not owned by any
+//! Rust value: it is reachable from every handle to its object, including
+//! handles that WDF passes to callbacks (ex. a `WDFDEVICE` in
+//! `EvtDeviceD0Entry`), which may run concurrently on other threads before or
+//! while the driver initializes the context. An initialization token consumed
+//! by the first write could not prevent any of those handles from reading the
+//! context space before it is written.
+//!
+//! The framework frees context space without running any Rust code, so
+//! [`ObjectContext::object_attributes`] also registers
+//! [`evt_destroy_context`] as the object's `EvtDestroyCallback`, which drops
+//! the context value exactly once when the object is destroyed. This releases
+//! any resources owned by the context (ex. a
+//! [`PoolVec`](crate::collections::PoolVec)). The context is not dropped in
+//! `EvtCleanupCallback`, since the object (and references to its context) can
+//! remain in use until its last reference is released, after cleanup has run.
+//! Drivers that register their own `EvtDestroyCallback` must call
+//! [`evt_destroy_context`] from it.
+
+use core::{
+    cell::UnsafeCell,
+    ffi::CStr,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use wdk_sys::{
+    macros,
+    PCWDF_OBJECT_CONTEXT_TYPE_INFO,
+    WDFOBJECT,
+    WDF_OBJECT_ATTRIBUTES,
+    WDF_OBJECT_CONTEXT_TYPE_INFO,
+};
+
+const UNINITIALIZED: u8 = 0;
+const INITIALIZING: u8 = 1;
+const INITIALIZED: u8 = 2;
+
+/// Description of a WDF object context type, equivalent to the
+/// `WDF_OBJECT_CONTEXT_TYPE_INFO` generated by `WDF_DECLARE_CONTEXT_TYPE` in C.
+///
+/// Instances of this type are generated by
+/// [`wdf_declare_context_type!`](crate::wdf_declare_context_type) and should
+/// not need to be constructed manually.
+#[repr(transparent)]
+pub struct ContextTypeInfo(WDF_OBJECT_CONTEXT_TYPE_INFO);
+
+// SAFETY: The raw pointers contained in `ContextTypeInfo` only ever point to
+// immutable `'static` data, so it is safe to share it between threads.
+unsafe impl Sync for ContextTypeInfo {}
+
+impl ContextTypeInfo {
+    /// Construct the type info for context type `T`. `unique_type` must be a
+    /// reference to the `static` being initialized, since WDF identifies
+    /// context types by the address of their type info.
+    #[doc(hidden)]
+    #[must_use]
+    pub const fn new<T: ObjectContext>(name: &'static CStr, unique_type: &'static Self) -> Self {
+        Self(WDF_OBJECT_CONTEXT_TYPE_INFO {
+            Size: WDF_OBJECT_CONTEXT_TYPE_INFO::SIZE,
+            ContextName: name.as_ptr(),
+            ContextSize: core::mem::size_of::<ContextSlot<T>>(),
+            UniqueType: core::ptr::addr_of!(unique_type.0),
+            EvtDriverGetUniqueContextType: None,
+        })
+    }
+
+    /// Get a pointer to the underlying [`WDF_OBJECT_CONTEXT_TYPE_INFO`], for
+    /// use in WDF APIs that take a `PCWDF_OBJECT_CONTEXT_TYPE_INFO`
+    #[must_use]
+    pub const fn as_ptr(&'static self) -> PCWDF_OBJECT_CONTEXT_TYPE_INFO {
+        core::ptr::addr_of!(self.0)
+    }
+}
+
+/// A type that can be stored in the context space of a WDF object.
+///
+/// Context types must be [`Sync`], since WDF callbacks on any thread can
+/// access the context of an object, and [`Send`], since the context is dropped
+/// by [`evt_destroy_context`] on whichever thread destroys the object.
+///
+/// This trait should be implemented via
+/// [`wdf_declare_context_type!`](crate::wdf_declare_context_type).
+///
+/// # Safety
+///
+/// [`ObjectContext::type_info`] must always return the same `'static`
+/// [`ContextTypeInfo`], constructed via [`ContextTypeInfo::new`] for `Self`,
+/// and that [`ContextTypeInfo`] must not be used for any other type.
+pub unsafe trait ObjectContext: Send + Sync + Sized + 'static {
+    /// Get the [`ContextTypeInfo`] describing this context type
+    fn type_info() -> &'static ContextTypeInfo;
+
+    /// Construct [`WDF_OBJECT_ATTRIBUTES`] that allocate context space for
+    /// this type when used to create a WDF object. This is equivalent to
+    /// `WDF_OBJECT_ATTRIBUTES_INIT_CONTEXT_TYPE` in C, except that
+    /// [`evt_destroy_context`] is also registered as the `EvtDestroyCallback`,
+    /// so that the context is dropped when the object is destroyed.
+    #[must_use]
+    fn object_attributes() -> WDF_OBJECT_ATTRIBUTES {
+        WDF_OBJECT_ATTRIBUTES {
+            EvtDestroyCallback: Some(evt_destroy_context::<Self>),
+            ContextTypeInfo: Self::type_info().as_ptr(),
+            ..WDF_OBJECT_ATTRIBUTES::init()
+        }
+    }
+}
+
+/// Layout of the context space that WDF allocates for an [`ObjectContext`].
+/// WDF zero-initializes this memory, which corresponds to an uninitialized
+/// slot.
+#[repr(C)]
+struct ContextSlot<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// A Rust wrapper around a WDF object handle.
+///
+/// This trait provides typed access to the object's context space.
+///
+/// # Safety
+///
+/// [`ObjectHandle::as_wdf_object`] must return a handle to a valid WDF object
+/// for as long as the implementor is alive.
+pub unsafe trait ObjectHandle {
+    /// Get the raw `WDFOBJECT` handle of this object
+    fn as_wdf_object(&self) -> WDFOBJECT;
+
+    /// Initialize this object's `T` context space with `value`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return `value` back as an error if the object has no
+    /// context space of type `T`, or if its `T` context has already been
+    /// initialized.
+    fn init_context<T: ObjectContext>(&self, value: T) -> Result<&T, T> {
+        let Some(slot) = context_slot::<T>(self.as_wdf_object()) else {
+            return Err(value);
+        };
+
+        if slot
+            .state
+            .compare_exchange(
+                UNINITIALIZED,
+                INITIALIZING,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .is_err()
+        {
+            return Err(value);
+        }
+
+        let value_ptr = slot.value.get().cast::<T>();
+        // SAFETY: The successful transition out of `UNINITIALIZED` above guarantees
+        // that this is the only writer of the slot, and no readers exist until the
+        // state is set to `INITIALIZED`.
+        unsafe {
+            value_ptr.write(value);
+        }
+        slot.state.store(INITIALIZED, Ordering::Release);
+
+        // SAFETY: The slot was initialized above.
+        Ok(unsafe { &*value_ptr })
+    }
+
+    /// Get a reference to this object's `T` context. Returns [`None`] if the
+    /// object has no context space of type `T`, or if it has not been
+    /// initialized via [`ObjectHandle::init_context`].
+    fn context<T: ObjectContext>(&self) -> Option<&T> {
+        let slot = context_slot::<T>(self.as_wdf_object())?;
+        if slot.state.load(Ordering::Acquire) != INITIALIZED {
+            return None;
+        }
+
+        let value_ptr = slot.value.get().cast::<T>();
+        // SAFETY: The slot is in the `INITIALIZED` state, and mutable references to
+        // its value can only be created via `ObjectHandle::context_mut`, whose
+        // caller guarantees that no other references exist.
+        Some(unsafe { &*value_ptr })
+    }
+
+    /// Get a mutable reference to this object's `T` context. Returns [`None`]
+    /// if the object has no context space of type `T`, or if it has not been
+    /// initialized via [`ObjectHandle::init_context`].
+    ///
+    /// # Safety
+    ///
+    /// WDF objects are shared, so multiple wrappers of the same object (ex.
+    /// the ones passed to framework callbacks) can exist at the same time. The
+    /// caller must guarantee that no other reference to this object's `T`
+    /// context exists for the lifetime of the returned reference, ex. by
+    /// relying on WDF automatic serialization or by holding a lock.
+    unsafe fn context_mut<T: ObjectContext>(&mut self) -> Option<&mut T> {
+        let slot = context_slot::<T>(self.as_wdf_object())?;
+        if slot.state.load(Ordering::Acquire) != INITIALIZED {
+            return None;
+        }
+
+        let value_ptr = slot.value.get().cast::<T>();
+        // SAFETY: The slot is in the `INITIALIZED` state, and the caller guarantees
+        // that no other references to its value exist.
+        Some(unsafe { &mut *value_ptr })
+    }
+}
+
+/// An [`ObjectHandle`] that can be reconstructed from an untyped `WDFOBJECT`,
+/// ex. when retrieving items from a [`Collection`](crate::wdf::Collection).
+///
+/// # Safety
+///
+/// Multiple wrappers of the same object can be constructed via
+/// [`FromWdfObject::from_wdf_object`], so implementors must not delete or
+/// otherwise invalidate the underlying object when dropped.
+pub unsafe trait FromWdfObject: ObjectHandle + Sized {
+    /// Construct a wrapper of `wdf_object`
+    ///
+    /// # Safety
+    ///
+    /// `wdf_object` must be a valid handle to a WDF object of the type wrapped
+    /// by `Self`, and must remain valid for the lifetime of the returned
+    /// wrapper.
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self;
+}
+
+/// Untyped handle to a WDF object that is only known to be valid for the
+/// duration of the framework callback that it was obtained in, ex. the parent
+/// of the object passed to the callback
+#[cfg(feature = "alloc")]
+pub(super) struct BorrowedObject(pub(super) WDFOBJECT);
+
+// SAFETY: `BorrowedObject` is only constructed from handles that WDF guarantees
+// to be valid for the duration of the framework callback that constructs it,
+// and it does not outlive that callback.
+#[cfg(feature = "alloc")]
+unsafe impl ObjectHandle for BorrowedObject {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.0
+    }
+}
+
+/// `EvtDestroyCallback` that drops the `T` context of the object being
+/// destroyed, if it has been initialized.
+///
+/// This is registered by [`ObjectContext::object_attributes`], so it only
+/// needs to be called directly by drivers that replace the
+/// `EvtDestroyCallback` of those attributes with their own. Calling it more
+/// than once for the same object has no effect after the first call.
+///
+/// # Safety
+///
+/// `wdf_object` must be a valid WDF object handle, and no references to its
+/// `T` context may exist or be created for the remainder of its lifetime. The
+/// framework guarantees this when calling the `EvtDestroyCallback` of the
+/// object being destroyed.
+pub unsafe extern "C" fn evt_destroy_context<T: ObjectContext>(wdf_object: WDFOBJECT) {
+    // SAFETY: The caller guarantees that `wdf_object` is valid, and that no
+    // references to its `T` context exist or will be created.
+    unsafe {
+        drop_context::<T>(wdf_object);
+    }
+}
+
+/// Drop the `T` context of `wdf_object`, if it has been initialized. This is
+/// meant to be called from an `EvtDestroyCallback`.
+///
+/// # Safety
+///
+/// `wdf_object` must be a valid WDF object handle, and no references to its
+/// `T` context may exist or be created for the remainder of its lifetime.
+pub(super) unsafe fn drop_context<T: ObjectContext>(wdf_object: WDFOBJECT) {
+    let Some(slot) = context_slot::<T>(wdf_object) else {
+        return;
+    };
+
+    if slot
+        .state
+        .compare_exchange(
+            INITIALIZED,
+            UNINITIALIZED,
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        )
+        .is_ok()
+    {
+        let value_ptr = slot.value.get().cast::<T>();
+        // SAFETY: The slot was initialized, and the caller guarantees that no other
+        // references to its value exist.
+        unsafe {
+            value_ptr.drop_in_place();
+        }
+    }
+}
+
+/// Get the [`ContextSlot`] for `T` in the context space of `wdf_object`
+fn context_slot<'a, T: ObjectContext>(wdf_object: WDFOBJECT) -> Option<&'a ContextSlot<T>> {
+    let context: *const ContextSlot<T>;
+    // SAFETY: `wdf_object` is a valid WDF object handle, as guaranteed by the
+    // implementor of `ObjectHandle` or the caller of `drop_context`.
+    unsafe {
+        context = macros::call_unsafe_wdf_function_binding!(
+            WdfObjectGetTypedContextWorker,
+            wdf_object,
+            T::type_info().as_ptr(),
+        )
+        .cast();
+    }
+
+    // SAFETY: A non-null pointer returned by `WdfObjectGetTypedContextWorker`
+    // points to the zero-initialized context space allocated for
+    // `ContextSlot<T>`, which lives as long as the object itself.
+    unsafe { context.as_ref() }
+}
+
+/// Declare a type that can be stored in the context space of WDF objects.
+///
+/// This is the equivalent of `WDF_DECLARE_CONTEXT_TYPE` in C. It either
+/// declares a new struct, or implements [`ObjectContext`] for an existing
+/// type:
+///
+/// ```rust, no_run
+/// wdk::wdf_declare_context_type!(
+///     /// Context for each device
+///     pub struct DeviceContext {
+///         pub serial_number: u32,
+///     }
+/// );
+///
+/// struct QueueContext(u32);
+/// wdk::wdf_declare_context_type!(QueueContext);
+/// ```
+#[macro_export]
+macro_rules! wdf_declare_context_type {
+    ($(#[$attribute:meta])* $visibility:vis struct $name:ident { $($fields:tt)* }) => {
+        $(#[$attribute])*
+        $visibility struct $name { $($fields)* }
+
+        $crate::wdf_declare_context_type!($name);
+    };
+
+    ($(#[$attribute:meta])* $visibility:vis struct $name:ident ( $($fields:tt)* );) => {
+        $(#[$attribute])*
+        $visibility struct $name ( $($fields)* );
+
+        $crate::wdf_declare_context_type!($name);
+    };
+
+    ($name:ident) => {
+        // SAFETY: `type_info` always returns the same `static`, which is generated
+        // here for this type only.
+        unsafe impl $crate::wdf::ObjectContext for $name {
+            fn type_info() -> &'static $crate::wdf::ContextTypeInfo {
+                static TYPE_INFO: $crate::wdf::ContextTypeInfo =
+                    $crate::wdf::ContextTypeInfo::new::<$name>(
+                        match ::core::ffi::CStr::from_bytes_with_nul(
+                            ::core::concat!(::core::stringify!($name), "\0").as_bytes(),
+                        ) {
+                            Ok(name) => name,
+                            Err(_) => ::core::panic!("context type name should be a valid C string"),
+                        },
+                        &TYPE_INFO,
+                    );
+                &TYPE_INFO
+            }
+        }
+    };
+}
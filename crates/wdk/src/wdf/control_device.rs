@@ -0,0 +1,244 @@
+use wdk_sys::{
+    macros,
+    DEVICE_TYPE,
+    NTSTATUS,
+    PWDFDEVICE_INIT,
+    STATUS_INSUFFICIENT_RESOURCES,
+    ULONG,
+    WDF_OBJECT_ATTRIBUTES,
+};
+
+use crate::{
+    nt_success,
+    sddl::Sddl,
+    string::NtUnicodeStr,
+    wdf::{
+        device_init::{set_file_object_policy, set_io_type},
+        Device,
+        DeviceIoType,
+        Driver,
+        FileObjectPolicy,
+    },
+};
+
+/// Builder for the initialization of a control device object, which is a
+/// device that is not part of a Plug and Play device stack.
+///
+/// Control devices are the usual way for software-only drivers to expose an
+/// interface to applications. Unlike Plug and Play devices, they are created
+/// directly by the driver (typically from its `DriverEntry`), are accessed by
+/// name or symbolic link instead of by device interface, and must be reported
+/// to the framework via [`Device::finish_control_initializing`] once their
+/// queues have been created:
+///
+/// ```rust, no_run
+/// use wdk::{
+///     nt_unicode_str,
+///     sddl::Sddl,
+///     wdf::{ControlDeviceBuilder, Device, Driver, IoctlRouter},
+/// };
+/// use wdk_sys::_WDF_IO_QUEUE_DISPATCH_TYPE::WdfIoQueueDispatchSequential;
+///
+/// fn create_control_device(driver: &Driver) -> wdk::Result<Device> {
+///     let mut builder = ControlDeviceBuilder::allocate(driver, Sddl::DEVOBJ_SYS_ALL_ADM_ALL)?;
+///     builder.name(nt_unicode_str!("\\Device\\Sample"))?;
+///
+///     let device = builder.create_device(None)?;
+///     device.create_symbolic_link(nt_unicode_str!("\\DosDevices\\Sample"))?;
+///     IoctlRouter::new()
+///         .ioctl(0x0022_2000, |_queue, (): ()| Ok(1u32))
+///         .create_queue(&device, WdfIoQueueDispatchSequential)?;
+///
+///     device.finish_control_initializing();
+///     Ok(device)
+/// }
+/// ```
+///
+/// The framework deletes control devices when a driver that does not support
+/// Plug and Play (ex. created with `WdfDriverInitNonPnpDriver`) unloads.
+pub struct ControlDeviceBuilder {
+    device_init: PWDFDEVICE_INIT,
+}
+
+impl ControlDeviceBuilder {
+    /// Allocate a `WDFDEVICE_INIT` for a control device of `driver`, whose
+    /// security descriptor is described by `sddl`, ex.
+    /// [`Sddl::DEVOBJ_SYS_ALL_ADM_ALL`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INSUFFICIENT_RESOURCES` if WDF fails
+    /// to allocate the `WDFDEVICE_INIT`.
+    pub fn allocate(driver: &Driver, sddl: Sddl<'_>) -> Result<Self, NTSTATUS> {
+        let device_init;
+        // SAFETY: `driver` wraps a valid `WDFDRIVER`, and the returned `WDFDEVICE_INIT`
+        // is stored in a private member that is freed on drop unless a device is
+        // created from it.
+        unsafe {
+            device_init = macros::call_unsafe_wdf_function_binding!(
+                WdfControlDeviceInitAllocate,
+                driver.as_raw(),
+                sddl.as_unicode_str().as_raw(),
+            );
+        }
+
+        if device_init.is_null() {
+            return Err(STATUS_INSUFFICIENT_RESOURCES);
+        }
+
+        Ok(Self { device_init })
+    }
+
+    /// Get the underlying `PWDFDEVICE_INIT`, ex. to pass it to
+    /// [`FileObject::configure_device_init`](crate::wdf::FileObject::configure_device_init)
+    /// or to other `WdfDeviceInitXxx` functions. The returned pointer must not
+    /// be passed to `WdfDeviceCreate` or `WdfDeviceInitFree`.
+    #[must_use]
+    pub const fn as_raw(&mut self) -> PWDFDEVICE_INIT {
+        self.device_init
+    }
+
+    /// Assign the name of the device object, ex. `\Device\Sample`. Control
+    /// devices must be named for applications to open them, typically via a
+    /// symbolic link created by [`Device::create_symbolic_link`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to assign the device name. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDevice Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdevice/nf-wdfdevice-wdfdeviceinitassignname#return-value)
+    pub fn name(&mut self, device_name: NtUnicodeStr<'_>) -> Result<&mut Self, NTSTATUS> {
+        let nt_status;
+        // SAFETY: `device_init` is a private member of `ControlDeviceBuilder`, which is
+        // guaranteed to be valid until it is passed to `WdfDeviceCreate`.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfDeviceInitAssignName,
+                self.device_init,
+                device_name.as_raw(),
+            );
+        }
+        nt_success(nt_status).then_some(self).ok_or(nt_status)
+    }
+
+    /// Replace the security descriptor of the device object with the one
+    /// described by `sddl`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to assign the security descriptor. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDevice Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdevice/nf-wdfdevice-wdfdeviceinitassignsddlstring#return-value)
+    pub fn security_descriptor(&mut self, sddl: Sddl<'_>) -> Result<&mut Self, NTSTATUS> {
+        let nt_status;
+        // SAFETY: `device_init` is a private member of `ControlDeviceBuilder`, which is
+        // guaranteed to be valid until it is passed to `WdfDeviceCreate`.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfDeviceInitAssignSDDLString,
+                self.device_init,
+                sddl.as_unicode_str().as_raw(),
+            );
+        }
+        nt_success(nt_status).then_some(self).ok_or(nt_status)
+    }
+
+    /// Set whether only one handle to the device can be open at a time
+    pub fn exclusive(&mut self, exclusive: bool) -> &mut Self {
+        // SAFETY: `device_init` is a private member of `ControlDeviceBuilder`, which is
+        // guaranteed to be valid until it is passed to `WdfDeviceCreate`.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfDeviceInitSetExclusive,
+                self.device_init,
+                u8::from(exclusive),
+            );
+        }
+        self
+    }
+
+    /// Set the device type of the device object, ex. `FILE_DEVICE_UNKNOWN`
+    pub fn device_type(&mut self, device_type: DEVICE_TYPE) -> &mut Self {
+        // SAFETY: `device_init` is a private member of `ControlDeviceBuilder`, which is
+        // guaranteed to be valid until it is passed to `WdfDeviceCreate`.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfDeviceInitSetDeviceType,
+                self.device_init,
+                device_type,
+            );
+        }
+        self
+    }
+
+    /// Set the characteristics of the device object, ex.
+    /// `FILE_DEVICE_SECURE_OPEN`. If `or_in_values` is `true`, the
+    /// characteristics are added to the ones that are already set instead of
+    /// replacing them.
+    pub fn characteristics(
+        &mut self,
+        device_characteristics: ULONG,
+        or_in_values: bool,
+    ) -> &mut Self {
+        // SAFETY: `device_init` is a private member of `ControlDeviceBuilder`, which is
+        // guaranteed to be valid until it is passed to `WdfDeviceCreate`.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfDeviceInitSetCharacteristics,
+                self.device_init,
+                device_characteristics,
+                u8::from(or_in_values),
+            );
+        }
+        self
+    }
+
+    /// Set how the framework accesses the data buffers of read and write
+    /// requests sent to the device
+    pub fn io_type(&mut self, io_type: DeviceIoType) -> &mut Self {
+        // SAFETY: `device_init` is a private member of `ControlDeviceBuilder`, which is
+        // guaranteed to be valid until it is passed to `WdfDeviceCreate`.
+        unsafe {
+            set_io_type(self.device_init, io_type);
+        }
+        self
+    }
+
+    /// Set whether the framework creates file objects for the device. This
+    /// replaces the configuration assigned by
+    /// [`FileObject::configure_device_init`](crate::wdf::FileObject::configure_device_init),
+    /// so the two must not be combined.
+    pub fn file_objects(&mut self, file_object_policy: FileObjectPolicy) -> &mut Self {
+        // SAFETY: `device_init` is a private member of `ControlDeviceBuilder`, which is
+        // guaranteed to be valid until it is passed to `WdfDeviceCreate`.
+        unsafe {
+            set_file_object_policy(self.device_init, file_object_policy);
+        }
+        self
+    }
+
+    /// Create the control device, consuming this builder. The device does not
+    /// receive requests until [`Device::finish_control_initializing`] is
+    /// called.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a device. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDevice Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdevice/nf-wdfdevice-wdfdevicecreate#return-value)
+    pub fn create_device(
+        mut self,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Device, NTSTATUS> {
+        // On success, `WdfDeviceCreate` takes ownership of `device_init` and sets it
+        // to `NULL`, so it is not freed on drop.
+        Device::try_new(&mut self.device_init, attributes)
+    }
+}
+
+impl Drop for ControlDeviceBuilder {
+    fn drop(&mut self) {
+        if !self.device_init.is_null() {
+            // SAFETY: `device_init` was allocated by `WdfControlDeviceInitAllocate`
+            // and was not consumed by `WdfDeviceCreate`, so the driver is responsible
+            // for freeing it.
+            unsafe {
+                macros::call_unsafe_wdf_function_binding!(WdfDeviceInitFree, self.device_init);
+            }
+        }
+    }
+}
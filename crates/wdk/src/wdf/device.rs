@@ -0,0 +1,191 @@
+use wdk_sys::{
+    macros,
+    GUID,
+    NTSTATUS,
+    PWDFDEVICE_INIT,
+    WDFDEVICE,
+    WDFOBJECT,
+    WDF_NO_OBJECT_ATTRIBUTES,
+    WDF_OBJECT_ATTRIBUTES,
+};
+
+use crate::{
+    nt_success,
+    string::NtUnicodeStr,
+    wdf::{FromWdfObject, ObjectHandle},
+};
+
+/// WDF Device.
+///
+/// A framework device object represents a device that the driver supports,
+/// and is typically created from the driver's `EvtDriverDeviceAdd` callback.
+/// Device interfaces, which allow applications and other drivers to find and
+/// open the device, are registered via [`Device::create_device_interface`].
+pub struct Device {
+    wdf_device: WDFDEVICE,
+}
+
+impl Device {
+    /// Try to construct a WDF Device object from `device_init`. On success,
+    /// WDF takes ownership of `device_init` and sets it to null.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a device. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDevice Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdevice/nf-wdfdevice-wdfdevicecreate#return-value)
+    pub fn try_new(
+        device_init: &mut PWDFDEVICE_INIT,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        let mut device = Self {
+            wdf_device: core::ptr::null_mut(),
+        };
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfDeviceCreate,
+                device_init,
+                attributes.map_or(WDF_NO_OBJECT_ATTRIBUTES, core::ptr::from_mut),
+                &mut device.wdf_device,
+            );
+        }
+        nt_success(nt_status).then_some(device).ok_or(nt_status)
+    }
+
+    /// Try to construct a WDF Device object from `device_init`. This is an
+    /// alias for [`Device::try_new()`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a device. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDevice Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdevice/nf-wdfdevice-wdfdevicecreate#return-value)
+    pub fn create(
+        device_init: &mut PWDFDEVICE_INIT,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        Self::try_new(device_init, attributes)
+    }
+
+    /// Get the underlying `WDFDEVICE`
+    #[must_use]
+    pub const fn as_raw(&self) -> WDFDEVICE {
+        self.wdf_device
+    }
+
+    /// Register an instance of the device interface class
+    /// `interface_class_guid` for this device. Multiple instances of the same
+    /// class are distinguished by their `reference_string`.
+    ///
+    /// Interfaces are enabled automatically when the device is started, and
+    /// disabled when it is removed.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to register the device interface. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDevice Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdevice/nf-wdfdevice-wdfdevicecreatedeviceinterface#return-value)
+    pub fn create_device_interface(
+        &self,
+        interface_class_guid: &GUID,
+        reference_string: Option<NtUnicodeStr<'_>>,
+    ) -> Result<(), NTSTATUS> {
+        let nt_status;
+        // SAFETY: `wdf_device` is a private member of `Device`, originally created by
+        // WDF, and this module guarantees that it is always in a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfDeviceCreateDeviceInterface,
+                self.wdf_device,
+                interface_class_guid,
+                reference_string
+                    .as_ref()
+                    .map_or(core::ptr::null(), NtUnicodeStr::as_raw),
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Enable or disable the instance of the device interface class
+    /// `interface_class_guid` with `reference_string`, which was previously
+    /// registered via [`Device::create_device_interface`]. Enabling or
+    /// disabling an interface notifies applications and drivers that
+    /// registered for device interface arrival or removal notifications.
+    pub fn set_device_interface_state(
+        &self,
+        interface_class_guid: &GUID,
+        reference_string: Option<NtUnicodeStr<'_>>,
+        enabled: bool,
+    ) {
+        // SAFETY: `wdf_device` is a private member of `Device`, originally created by
+        // WDF, and this module guarantees that it is always in a valid state.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfDeviceSetDeviceInterfaceState,
+                self.wdf_device,
+                interface_class_guid,
+                reference_string
+                    .as_ref()
+                    .map_or(core::ptr::null(), NtUnicodeStr::as_raw),
+                u8::from(enabled),
+            );
+        }
+    }
+
+    /// Create a symbolic link to the device, ex. `\DosDevices\Sample`, so that
+    /// applications can open it by name. The device must have been named, ex.
+    /// via [`ControlDeviceBuilder::name`](crate::wdf::ControlDeviceBuilder::name).
+    /// The symbolic link is removed when the device is deleted.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to create the symbolic link. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDevice Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdevice/nf-wdfdevice-wdfdevicecreatesymboliclink#return-value)
+    pub fn create_symbolic_link(
+        &self,
+        symbolic_link_name: NtUnicodeStr<'_>,
+    ) -> Result<(), NTSTATUS> {
+        let nt_status;
+        // SAFETY: `wdf_device` is a private member of `Device`, originally created by
+        // WDF, and this module guarantees that it is always in a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfDeviceCreateSymbolicLink,
+                self.wdf_device,
+                symbolic_link_name.as_raw(),
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Inform the framework that the driver has finished initializing a
+    /// control device created via
+    /// [`ControlDeviceBuilder`](crate::wdf::ControlDeviceBuilder), so that it
+    /// can start receiving requests. This must only be called for control
+    /// devices, once their queues have been created.
+    pub fn finish_control_initializing(&self) {
+        // SAFETY: `wdf_device` is a private member of `Device`, originally created by
+        // WDF, and this module guarantees that it is always in a valid state.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfControlFinishInitializing,
+                self.wdf_device
+            );
+        }
+    }
+}
+
+// SAFETY: `wdf_device` is a private member of `Device`, originally created by
+// WDF, and this module guarantees that it is always in a valid state.
+unsafe impl ObjectHandle for Device {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_device.cast()
+    }
+}
+
+// SAFETY: `Device` does not delete the underlying object when dropped.
+unsafe impl FromWdfObject for Device {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            wdf_device: wdf_object.cast(),
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use wdk_sys::{
+    macros,
+    _WDF_DEVICE_IO_TYPE,
+    _WDF_FILEOBJECT_CLASS,
+    _WDF_TRI_STATE,
+    PWDFDEVICE_INIT,
+    ULONG,
+    WDF_DEVICE_IO_TYPE,
+    WDF_FILEOBJECT_CLASS,
+    WDF_FILEOBJECT_CONFIG,
+    WDF_NO_OBJECT_ATTRIBUTES,
+};
+
+use crate::wdf::ObjectContext;
+
+/// How the framework accesses the data buffers of the read and write requests
+/// that are sent to a device
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DeviceIoType {
+    /// The I/O manager copies the data to and from a system buffer
+    /// (`WdfDeviceIoBuffered`). This is the default.
+    Buffered,
+    /// The I/O manager locks the buffers of the requestor in memory and
+    /// describes them with MDLs (`WdfDeviceIoDirect`)
+    Direct,
+    /// The driver receives the unvalidated buffers of the requestor, which it
+    /// may only access in the context of the requesting thread
+    /// (`WdfDeviceIoNeither`)
+    #[cfg(not(driver_type = "umdf"))]
+    Neither,
+    /// The framework uses direct I/O for requests whose buffers are at least
+    /// as large as the device's direct transfer threshold, and buffered I/O
+    /// otherwise (`WdfDeviceIoBufferedOrDirect`)
+    #[cfg(driver_type = "umdf")]
+    BufferedOrDirect,
+}
+
+impl DeviceIoType {
+    const fn as_raw(self) -> WDF_DEVICE_IO_TYPE {
+        match self {
+            Self::Buffered => _WDF_DEVICE_IO_TYPE::WdfDeviceIoBuffered,
+            Self::Direct => _WDF_DEVICE_IO_TYPE::WdfDeviceIoDirect,
+            #[cfg(not(driver_type = "umdf"))]
+            Self::Neither => _WDF_DEVICE_IO_TYPE::WdfDeviceIoNeither,
+            #[cfg(driver_type = "umdf")]
+            Self::BufferedOrDirect => _WDF_DEVICE_IO_TYPE::WdfDeviceIoBufferedOrDirect,
+        }
+    }
+}
+
+/// Which of the `FsContext` and `FsContext2` members of the WDM file objects of
+/// a device the framework may use to store its own file objects
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FsContextUsage {
+    /// The framework may use neither member, which leaves both to the driver
+    /// (`WdfFileObjectWdfCannotUseFsContexts`). This is the default.
+    None,
+    /// The framework may use `FsContext` (`WdfFileObjectWdfCanUseFsContext`)
+    FsContext,
+    /// The framework may use `FsContext2` (`WdfFileObjectWdfCanUseFsContext2`)
+    FsContext2,
+}
+
+impl FsContextUsage {
+    pub(crate) const fn as_raw(self) -> WDF_FILEOBJECT_CLASS {
+        match self {
+            Self::None => _WDF_FILEOBJECT_CLASS::WdfFileObjectWdfCannotUseFsContexts,
+            Self::FsContext => _WDF_FILEOBJECT_CLASS::WdfFileObjectWdfCanUseFsContext,
+            Self::FsContext2 => _WDF_FILEOBJECT_CLASS::WdfFileObjectWdfCanUseFsContext2,
+        }
+    }
+}
+
+/// Whether the framework creates a file object for every handle that is opened
+/// to a device, for drivers that do not register
+/// [`FileObjectCallbacks`](crate::wdf::FileObjectCallbacks).
+///
+/// Drivers that register
+/// [`FileObjectCallbacks`](crate::wdf::FileObjectCallbacks) always require file
+/// objects, and configure them via
+/// [`FileObjectConfig`](crate::wdf::FileObjectConfig) instead.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FileObjectPolicy {
+    /// The driver does not use file objects, ex. a filter driver that only
+    /// forwards requests (`WdfFileObjectNotRequired`)
+    NotRequired,
+    /// Every request must be associated with a file object, which the
+    /// framework stores as described by the [`FsContextUsage`]
+    Required(FsContextUsage),
+    /// Requests may lack a file object, ex. because they were sent by a driver
+    /// above that creates its own requests (`WdfFileObjectCanBeOptional`).
+    /// When present, the framework stores file objects as described by the
+    /// [`FsContextUsage`].
+    Optional(FsContextUsage),
+}
+
+impl FileObjectPolicy {
+    const fn as_raw(self) -> WDF_FILEOBJECT_CLASS {
+        match self {
+            Self::NotRequired => _WDF_FILEOBJECT_CLASS::WdfFileObjectNotRequired,
+            Self::Required(fs_context_usage) => fs_context_usage.as_raw(),
+            Self::Optional(fs_context_usage) => {
+                fs_context_usage.as_raw() | _WDF_FILEOBJECT_CLASS::WdfFileObjectCanBeOptional
+            }
+        }
+    }
+}
+
+/// Typed view of the `WDFDEVICE_INIT` that the framework passes to
+/// `EvtDriverDeviceAdd`, for the settings that must be assigned before the
+/// device is created:
+///
+/// ```rust, no_run
+/// use wdk::wdf::{Device, DeviceInit, DeviceIoType, Driver, FileObjectPolicy};
+/// use wdk_sys::{NTSTATUS, PWDFDEVICE_INIT};
+///
+/// fn device_add(_driver: &Driver, device_init: &mut PWDFDEVICE_INIT) -> Result<(), NTSTATUS> {
+///     // SAFETY: `device_init` is the `PWDFDEVICE_INIT` passed to `EvtDriverDeviceAdd`.
+///     unsafe { DeviceInit::from_raw(device_init) }
+///         .io_type(DeviceIoType::Direct)
+///         .file_objects(FileObjectPolicy::NotRequired);
+///
+///     Device::try_new(device_init, None)?;
+///     Ok(())
+/// }
+/// ```
+///
+/// [`ControlDeviceBuilder`](crate::wdf::ControlDeviceBuilder) and
+/// [`PdoInitBuilder`](crate::wdf::PdoInitBuilder) provide the same settings for
+/// the `WDFDEVICE_INIT`s that they own.
+pub struct DeviceInit<'a> {
+    device_init: &'a mut PWDFDEVICE_INIT,
+}
+
+impl<'a> DeviceInit<'a> {
+    /// Construct a [`DeviceInit`] from the `PWDFDEVICE_INIT` passed to
+    /// `EvtDriverDeviceAdd`
+    ///
+    /// # Safety
+    ///
+    /// `device_init` must be a valid `PWDFDEVICE_INIT` that has not been passed
+    /// to `WdfDeviceCreate` yet.
+    #[must_use]
+    pub const unsafe fn from_raw(device_init: &'a mut PWDFDEVICE_INIT) -> Self {
+        Self { device_init }
+    }
+
+    /// Get the raw `PWDFDEVICE_INIT`, for use with class extension APIs that
+    /// configure the device before it is created
+    pub(crate) fn as_raw(&self) -> PWDFDEVICE_INIT {
+        *self.device_init
+    }
+
+    /// Set how the framework accesses the data buffers of read and write
+    /// requests sent to the device
+    pub fn io_type(&mut self, io_type: DeviceIoType) -> &mut Self {
+        // SAFETY: `from_raw` guarantees that `device_init` is valid and has not been
+        // used to create a device yet.
+        unsafe {
+            set_io_type(*self.device_init, io_type);
+        }
+        self
+    }
+
+    /// Set whether the framework creates file objects for the device. This
+    /// replaces the configuration assigned by
+    /// [`FileObject::configure_device_init`](crate::wdf::FileObject::configure_device_init),
+    /// so the two must not be combined.
+    pub fn file_objects(&mut self, file_object_policy: FileObjectPolicy) -> &mut Self {
+        // SAFETY: `from_raw` guarantees that `device_init` is valid and has not been
+        // used to create a device yet.
+        unsafe {
+            set_file_object_policy(*self.device_init, file_object_policy);
+        }
+        self
+    }
+
+    /// Mark the device as a filter device object, so that the framework passes
+    /// the requests that the driver does not handle to the next-lower driver
+    /// in the device stack. In particular, the framework forwards requests of
+    /// types for which the default queue of the device has no callback (ex.
+    /// reads and writes for a queue created via
+    /// [`IoctlRouter::create_queue`](crate::wdf::IoctlRouter::create_queue)),
+    /// instead of failing them. Requests that the driver receives can be
+    /// passed on via
+    /// [`Request::forward_to_next_lower`](crate::wdf::Request::forward_to_next_lower).
+    pub fn set_filter(&mut self) -> &mut Self {
+        // SAFETY: `from_raw` guarantees that `device_init` is valid and has not been
+        // used to create a device yet.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(WdfFdoInitSetFilter, *self.device_init);
+        }
+        self
+    }
+
+    /// Allocate a `T` context in every request that the framework delivers to
+    /// the device. The context is uninitialized when the request is delivered,
+    /// and can be initialized via
+    /// [`ObjectHandle::init_context`](crate::wdf::ObjectHandle::init_context),
+    /// ex. to record state before the request is forwarded. Since the request
+    /// keeps its context, the state is still available via
+    /// [`ObjectHandle::context`](crate::wdf::ObjectHandle::context) in the
+    /// completion closure passed to
+    /// [`Request::forward_to_next_lower_with_completion`](crate::wdf::Request::forward_to_next_lower_with_completion).
+    pub fn request_context<T: ObjectContext>(&mut self) -> &mut Self {
+        let mut attributes = T::object_attributes();
+        // SAFETY: `from_raw` guarantees that `device_init` is valid and has not been
+        // used to create a device yet. WDF copies the attributes.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfDeviceInitSetRequestAttributes,
+                *self.device_init,
+                &mut attributes,
+            );
+        }
+        self
+    }
+}
+
+/// Assign `io_type` to the device that is being initialized by `device_init`
+///
+/// # Safety
+///
+/// `device_init` must be a valid `PWDFDEVICE_INIT` that has not been passed to
+/// `WdfDeviceCreate` yet.
+pub(crate) unsafe fn set_io_type(device_init: PWDFDEVICE_INIT, io_type: DeviceIoType) {
+    // SAFETY: The caller guarantees that `device_init` is valid and has not been
+    // used to create a device yet.
+    unsafe {
+        macros::call_unsafe_wdf_function_binding!(
+            WdfDeviceInitSetIoType,
+            device_init,
+            io_type.as_raw(),
+        );
+    }
+}
+
+/// Assign `file_object_policy`, without any file object callbacks, to the
+/// device that is being initialized by `device_init`
+///
+/// # Safety
+///
+/// `device_init` must be a valid `PWDFDEVICE_INIT` that has not been passed to
+/// `WdfDeviceCreate` yet.
+pub(crate) unsafe fn set_file_object_policy(
+    device_init: PWDFDEVICE_INIT,
+    file_object_policy: FileObjectPolicy,
+) {
+    let mut file_object_config = WDF_FILEOBJECT_CONFIG {
+        // The size of WDF_FILEOBJECT_CONFIG is known to fit in a ULONG
+        #[allow(clippy::cast_possible_truncation)]
+        Size: core::mem::size_of::<WDF_FILEOBJECT_CONFIG>() as ULONG,
+        EvtDeviceFileCreate: None,
+        EvtFileClose: None,
+        EvtFileCleanup: None,
+        AutoForwardCleanupClose: _WDF_TRI_STATE::WdfUseDefault,
+        FileObjectClass: file_object_policy.as_raw(),
+    };
+
+    // SAFETY: The caller guarantees that `device_init` is valid and has not been
+    // used to create a device yet.
+    unsafe {
+        macros::call_unsafe_wdf_function_binding!(
+            WdfDeviceInitSetFileObjectConfig,
+            device_init,
+            &mut file_object_config,
+            WDF_NO_OBJECT_ATTRIBUTES,
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use core::fmt;
+
+use wdk_sys::{
+    macros,
+    _POOL_TYPE,
+    DEVPROPKEY,
+    DEVPROPTYPE,
+    DEVPROP_TYPE_BINARY,
+    DEVPROP_TYPE_BOOLEAN,
+    DEVPROP_TYPE_GUID,
+    DEVPROP_TYPE_STRING,
+    DEVPROP_TYPE_STRING_LIST,
+    DEVPROP_TYPE_UINT32,
+    GUID,
+    NTSTATUS,
+    ULONG,
+    WDFMEMORY,
+    WDFOBJECT,
+    WDF_DEVICE_PROPERTY_DATA,
+    WDF_NO_OBJECT_ATTRIBUTES,
+};
+
+use crate::{
+    nt_success,
+    string::NtUnicodeStr,
+    wdf::{Device, ObjectHandle},
+    Guid,
+};
+
+/// Value of a device property, queried via [`Device::query_property`].
+///
+/// The value is stored in a WDF memory object allocated by the framework,
+/// which is deleted when the [`DeviceProperty`] is dropped. Its contents can
+/// be borrowed as a typed [`DevicePropertyValue`] via
+/// [`DeviceProperty::value`].
+pub struct DeviceProperty {
+    wdf_memory: WDFMEMORY,
+    property_type: DEVPROPTYPE,
+}
+
+/// Typed contents of a [`DeviceProperty`], borrowed from it
+#[derive(Clone, Debug)]
+pub enum DevicePropertyValue<'a> {
+    /// `DEVPROP_TYPE_STRING` value (ex. `DEVPKEY_Device_FriendlyName`),
+    /// without its null terminator
+    String(NtUnicodeStr<'a>),
+    /// `DEVPROP_TYPE_STRING_LIST` value (ex. `DEVPKEY_Device_HardwareIds`)
+    StringList(DevicePropertyStringList<'a>),
+    /// `DEVPROP_TYPE_UINT32` value (ex. `DEVPKEY_Device_Address`)
+    U32(u32),
+    /// `DEVPROP_TYPE_BOOLEAN` value
+    Boolean(bool),
+    /// `DEVPROP_TYPE_GUID` value (ex. `DEVPKEY_Device_ContainerId`)
+    Guid(Guid),
+    /// `DEVPROP_TYPE_BINARY` value
+    Binary(&'a [u8]),
+    /// Value of any other property type, or of one of the types above whose
+    /// size does not match the type
+    Other {
+        /// The `DEVPROPTYPE` of the value
+        property_type: DEVPROPTYPE,
+        /// The raw contents of the value
+        data: &'a [u8],
+    },
+}
+
+/// Iterator over the strings of a `DEVPROP_TYPE_STRING_LIST` device property,
+/// which is stored as a sequence of null-terminated strings ending with an
+/// empty string
+#[derive(Clone)]
+pub struct DevicePropertyStringList<'a> {
+    remaining: &'a [u16],
+}
+
+impl DeviceProperty {
+    /// Get the `DEVPROPTYPE` of the property
+    #[must_use]
+    pub const fn property_type(&self) -> DEVPROPTYPE {
+        self.property_type
+    }
+
+    /// Get the raw contents of the property
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        let mut buffer_size: usize = 0;
+        let buffer;
+        // SAFETY: `wdf_memory` is a private member of `DeviceProperty`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            buffer = macros::call_unsafe_wdf_function_binding!(
+                WdfMemoryGetBuffer,
+                self.wdf_memory,
+                &mut buffer_size,
+            );
+        }
+
+        if buffer.is_null() || buffer_size == 0 {
+            return &[];
+        }
+
+        // SAFETY: WDF guarantees that the buffer of a memory object is valid for
+        // `buffer_size` bytes for as long as the memory object exists. The memory
+        // object is only deleted when `self` is dropped, and nothing else writes to
+        // it.
+        unsafe { core::slice::from_raw_parts(buffer.cast(), buffer_size) }
+    }
+
+    /// Get the typed contents of the property, based on its
+    /// [`DeviceProperty::property_type`]
+    #[must_use]
+    pub fn value(&self) -> DevicePropertyValue<'_> {
+        let data = self.as_bytes();
+        let other = || DevicePropertyValue::Other {
+            property_type: self.property_type,
+            data,
+        };
+
+        match self.property_type {
+            DEVPROP_TYPE_STRING => {
+                let Some(utf16) = as_utf16(data) else {
+                    return other();
+                };
+                let string_length = utf16
+                    .iter()
+                    .position(|&code_unit| code_unit == 0)
+                    .unwrap_or(utf16.len());
+                NtUnicodeStr::from_utf16(&utf16[..string_length])
+                    .map_or_else(|_| other(), DevicePropertyValue::String)
+            }
+            DEVPROP_TYPE_STRING_LIST => as_utf16(data).map_or_else(other, |utf16| {
+                DevicePropertyValue::StringList(DevicePropertyStringList { remaining: utf16 })
+            }),
+            DEVPROP_TYPE_UINT32 => <[u8; 4]>::try_from(data).map_or_else(
+                |_| other(),
+                |bytes| DevicePropertyValue::U32(u32::from_ne_bytes(bytes)),
+            ),
+            DEVPROP_TYPE_BOOLEAN => match data {
+                [value] => DevicePropertyValue::Boolean(*value != 0),
+                _ => other(),
+            },
+            DEVPROP_TYPE_GUID if data.len() == core::mem::size_of::<GUID>() => {
+                // SAFETY: `data` holds exactly `size_of::<GUID>()` bytes, and every bit
+                // pattern is a valid `GUID`. `read_unaligned` does not require `data` to
+                // be aligned for a `GUID`.
+                let guid = unsafe { data.as_ptr().cast::<GUID>().read_unaligned() };
+                DevicePropertyValue::Guid(guid.into())
+            }
+            DEVPROP_TYPE_BINARY => DevicePropertyValue::Binary(data),
+            _ => other(),
+        }
+    }
+}
+
+impl Drop for DeviceProperty {
+    fn drop(&mut self) {
+        // SAFETY: `wdf_memory` is a private member of `DeviceProperty`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state. It is not used after it is deleted here.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(WdfObjectDelete, self.wdf_memory.cast());
+        }
+    }
+}
+
+// SAFETY: `wdf_memory` is a private member of `DeviceProperty`, originally
+// created by WDF, and this module guarantees that it is always in a valid
+// state.
+unsafe impl ObjectHandle for DeviceProperty {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_memory.cast()
+    }
+}
+
+impl fmt::Debug for DeviceProperty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeviceProperty")
+            .field("property_type", &self.property_type)
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl<'a> Iterator for DevicePropertyStringList<'a> {
+    type Item = NtUnicodeStr<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let string_length = self
+                .remaining
+                .iter()
+                .position(|&code_unit| code_unit == 0)
+                .unwrap_or(self.remaining.len());
+            // The list ends with an empty string
+            if string_length == 0 {
+                self.remaining = &[];
+                return None;
+            }
+
+            let (string, remaining) = self.remaining.split_at(string_length);
+            self.remaining = remaining.get(1..).unwrap_or_default();
+            // Strings that are too long for a `UNICODE_STRING` are skipped
+            if let Ok(string) = NtUnicodeStr::from_utf16(string) {
+                return Some(string);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for DevicePropertyStringList<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// Reinterpret `data` as UTF-16 code units, or return [`None`] if it is not
+/// aligned or sized for `u16`s
+fn as_utf16(data: &[u8]) -> Option<&[u16]> {
+    // SAFETY: Every bit pattern is a valid `u16`, and `align_to` only returns
+    // correctly aligned `u16`s in the middle slice.
+    match unsafe { data.align_to::<u16>() } {
+        ([], utf16, []) => Some(utf16),
+        _ => None,
+    }
+}
+
+impl Device {
+    /// Query the value of the device property `property_key` of this device,
+    /// in a buffer allocated by the framework.
+    ///
+    /// Properties must be queried at `IRQL` = `PASSIVE_LEVEL`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to query the property (ex. `STATUS_OBJECT_NAME_NOT_FOUND` if the property is not set). The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDevice Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdevice/nf-wdfdevice-wdfdeviceallocandquerypropertyex#return-value)
+    pub fn query_property(&self, property_key: &DEVPROPKEY) -> Result<DeviceProperty, NTSTATUS> {
+        let mut device_property_data = WDF_DEVICE_PROPERTY_DATA {
+            PropertyKey: property_key,
+            ..WDF_DEVICE_PROPERTY_DATA::with_size()
+        };
+        let mut wdf_memory: WDFMEMORY = core::ptr::null_mut();
+        let mut property_type: DEVPROPTYPE = 0;
+
+        let nt_status;
+        // SAFETY: `as_raw` returns the `WDFDEVICE` of a `Device`, which is always in
+        // a valid state. On success, the resulting memory object is stored in a private
+        // member of `DeviceProperty`, which deletes it when dropped.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfDeviceAllocAndQueryPropertyEx,
+                self.as_raw(),
+                &mut device_property_data,
+                _POOL_TYPE::NonPagedPoolNx,
+                WDF_NO_OBJECT_ATTRIBUTES,
+                &mut wdf_memory,
+                &mut property_type,
+            );
+        }
+        nt_success(nt_status)
+            .then_some(DeviceProperty {
+                wdf_memory,
+                property_type,
+            })
+            .ok_or(nt_status)
+    }
+
+    /// Query the value of the device property `property_key` of this device
+    /// into `buffer`, and return the number of bytes written and the
+    /// `DEVPROPTYPE` of the property. Unlike [`Device::query_property`], this
+    /// does not allocate.
+    ///
+    /// Properties must be queried at `IRQL` = `PASSIVE_LEVEL`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to query the property (ex. `STATUS_BUFFER_TOO_SMALL` if `buffer` is too small to hold the value). The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDevice Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdevice/nf-wdfdevice-wdfdevicequerypropertyex#return-value)
+    pub fn query_property_into(
+        &self,
+        property_key: &DEVPROPKEY,
+        buffer: &mut [u8],
+    ) -> Result<(usize, DEVPROPTYPE), NTSTATUS> {
+        let mut device_property_data = WDF_DEVICE_PROPERTY_DATA {
+            PropertyKey: property_key,
+            ..WDF_DEVICE_PROPERTY_DATA::with_size()
+        };
+        let mut required_size: ULONG = 0;
+        let mut property_type: DEVPROPTYPE = 0;
+
+        let nt_status;
+        // SAFETY: `as_raw` returns the `WDFDEVICE` of a `Device`, which is always in
+        // a valid state. WDF writes at most `buffer.len()` bytes to `buffer`.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfDeviceQueryPropertyEx,
+                self.as_raw(),
+                &mut device_property_data,
+                ULONG::try_from(buffer.len()).unwrap_or(ULONG::MAX),
+                buffer.as_mut_ptr().cast(),
+                &mut required_size,
+                &mut property_type,
+            );
+        }
+        nt_success(nt_status)
+            .then_some((required_size as usize, property_type))
+            .ok_or(nt_status)
+    }
+}
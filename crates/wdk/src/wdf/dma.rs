@@ -0,0 +1,459 @@
+use core::marker::PhantomData;
+
+use wdk_sys::{
+    macros,
+    LONGLONG,
+    NTSTATUS,
+    PFN_WDF_PROGRAM_DMA,
+    PMDL,
+    PSCATTER_GATHER_LIST,
+    PVOID,
+    SCATTER_GATHER_ELEMENT,
+    ULONG,
+    WDFCONTEXT,
+    WDFDEVICE,
+    WDFDMAENABLER,
+    WDFDMATRANSACTION,
+    WDFOBJECT,
+    WDFREQUEST,
+    WDF_DMA_DIRECTION,
+    WDF_DMA_ENABLER_CONFIG,
+    WDF_DMA_PROFILE,
+    WDF_NO_OBJECT_ATTRIBUTES,
+    WDF_OBJECT_ATTRIBUTES,
+};
+
+use crate::{
+    nt_success,
+    wdf::{FromWdfObject, ObjectHandle},
+};
+
+/// WDF DMA Enabler.
+///
+/// A DMA enabler describes the DMA capabilities of a device (ex. whether it
+/// supports scatter-gather, and the maximum transfer length), and is used to
+/// create [`DmaTransaction`]s. The framework deletes the DMA enabler when its
+/// parent device is deleted.
+pub struct DmaEnabler {
+    wdf_dma_enabler: WDFDMAENABLER,
+}
+
+/// WDF DMA Transaction.
+///
+/// A DMA transaction splits a single I/O request into one or more DMA
+/// transfers that fit the limits of its [`DmaEnabler`]. The framework calls the
+/// transaction's `EvtProgramDma` callback with a [`ScatterGatherList`] for
+/// every transfer, and the driver reports the completion of each transfer via
+/// [`DmaTransaction::dma_completed`].
+pub struct DmaTransaction {
+    wdf_dma_transaction: WDFDMATRANSACTION,
+}
+
+/// Scatter-gather list describing the physical memory of a single DMA
+/// transfer, as passed to an `EvtProgramDma` callback
+#[derive(Clone, Copy)]
+pub struct ScatterGatherList<'a> {
+    elements: &'a [SCATTER_GATHER_ELEMENT],
+}
+
+/// A single physically-contiguous range of a [`ScatterGatherList`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScatterGatherElement {
+    /// Logical address of the range, as seen by the device
+    pub address: LONGLONG,
+    /// Length of the range in bytes
+    pub length: ULONG,
+}
+
+/// Iterator over the [`ScatterGatherElement`]s of a [`ScatterGatherList`]
+pub struct ScatterGatherElements<'a> {
+    elements: core::slice::Iter<'a, SCATTER_GATHER_ELEMENT>,
+    _list: PhantomData<ScatterGatherList<'a>>,
+}
+
+impl DmaEnabler {
+    /// Try to construct a WDF DMA Enabler object for `device`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a DMA enabler. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDmaEnabler Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdmaenabler/nf-wdfdmaenabler-wdfdmaenablercreate#return-value)
+    pub fn try_new(
+        device: WDFDEVICE,
+        dma_enabler_config: &mut WDF_DMA_ENABLER_CONFIG,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        let mut dma_enabler = Self {
+            wdf_dma_enabler: core::ptr::null_mut(),
+        };
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfDmaEnablerCreate,
+                device,
+                dma_enabler_config,
+                attributes.map_or(WDF_NO_OBJECT_ATTRIBUTES, core::ptr::from_mut),
+                &mut dma_enabler.wdf_dma_enabler,
+            );
+        }
+        nt_success(nt_status)
+            .then_some(dma_enabler)
+            .ok_or(nt_status)
+    }
+
+    /// Try to construct a WDF DMA Enabler object for `device` that uses the
+    /// DMA `profile` (ex. `WdfDmaProfileScatterGather64`), and supports
+    /// transfers of up to `maximum_length` bytes. This is equivalent to
+    /// initializing the config via `WDF_DMA_ENABLER_CONFIG_INIT` in C.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a DMA enabler. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDmaEnabler Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdmaenabler/nf-wdfdmaenabler-wdfdmaenablercreate#return-value)
+    pub fn create(
+        device: WDFDEVICE,
+        profile: WDF_DMA_PROFILE,
+        maximum_length: usize,
+    ) -> Result<Self, NTSTATUS> {
+        let mut dma_enabler_config = WDF_DMA_ENABLER_CONFIG {
+            // The size of WDF_DMA_ENABLER_CONFIG is known to fit in a ULONG
+            #[allow(clippy::cast_possible_truncation)]
+            Size: core::mem::size_of::<WDF_DMA_ENABLER_CONFIG>() as ULONG,
+            Profile: profile,
+            MaximumLength: maximum_length,
+            ..WDF_DMA_ENABLER_CONFIG::default()
+        };
+        Self::try_new(device, &mut dma_enabler_config, None)
+    }
+
+    /// Get the maximum length, in bytes, of a single DMA transfer supported by
+    /// the [`DmaEnabler`]
+    #[must_use]
+    pub fn maximum_length(&self) -> usize {
+        let maximum_length;
+        // SAFETY: `wdf_dma_enabler` is a private member of `DmaEnabler`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            maximum_length = macros::call_unsafe_wdf_function_binding!(
+                WdfDmaEnablerGetMaximumLength,
+                self.wdf_dma_enabler
+            );
+        }
+        maximum_length
+    }
+}
+
+// SAFETY: `wdf_dma_enabler` is a private member of `DmaEnabler`, originally
+// created by WDF, and this module guarantees that it is always in a valid
+// state.
+unsafe impl ObjectHandle for DmaEnabler {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_dma_enabler.cast()
+    }
+}
+
+// SAFETY: `DmaEnabler` does not delete the underlying object when dropped.
+unsafe impl FromWdfObject for DmaEnabler {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            wdf_dma_enabler: wdf_object.cast(),
+        }
+    }
+}
+
+impl DmaTransaction {
+    /// Try to construct a WDF DMA Transaction object that uses `dma_enabler`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a DMA transaction. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDmaTransaction Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdmatransaction/nf-wdfdmatransaction-wdfdmatransactioncreate#return-value)
+    pub fn try_new(
+        dma_enabler: &DmaEnabler,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        let mut dma_transaction = Self {
+            wdf_dma_transaction: core::ptr::null_mut(),
+        };
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfDmaTransactionCreate,
+                dma_enabler.wdf_dma_enabler,
+                attributes.map_or(WDF_NO_OBJECT_ATTRIBUTES, core::ptr::from_mut),
+                &mut dma_transaction.wdf_dma_transaction,
+            );
+        }
+        nt_success(nt_status)
+            .then_some(dma_transaction)
+            .ok_or(nt_status)
+    }
+
+    /// Construct a [`DmaTransaction`] from the raw handle passed to a WDF
+    /// callback (ex. `EvtProgramDma`)
+    ///
+    /// # Safety
+    ///
+    /// `wdf_dma_transaction` must be a valid handle to a DMA transaction that
+    /// has not been deleted.
+    #[must_use]
+    pub const unsafe fn from_raw(wdf_dma_transaction: WDFDMATRANSACTION) -> Self {
+        Self {
+            wdf_dma_transaction,
+        }
+    }
+
+    /// Initialize the [`DmaTransaction`] to transfer the buffers of `request`
+    /// in `direction`. `evt_program_dma` is called for every DMA transfer of
+    /// the transaction.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to initialize the DMA transaction. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDmaTransaction Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdmatransaction/nf-wdfdmatransaction-wdfdmatransactioninitializeusingrequest#return-value)
+    pub fn initialize_using_request(
+        &self,
+        request: WDFREQUEST,
+        evt_program_dma: PFN_WDF_PROGRAM_DMA,
+        direction: WDF_DMA_DIRECTION,
+    ) -> Result<(), NTSTATUS> {
+        let nt_status;
+        // SAFETY: `wdf_dma_transaction` is a private member of `DmaTransaction`,
+        // originally created by WDF, and this module guarantees that it is always in
+        // a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfDmaTransactionInitializeUsingRequest,
+                self.wdf_dma_transaction,
+                request,
+                evt_program_dma,
+                direction,
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Initialize the [`DmaTransaction`] to transfer `length` bytes, starting
+    /// at `virtual_address`, of the buffer described by `mdl` in `direction`.
+    /// `evt_program_dma` is called for every DMA transfer of the transaction.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to initialize the DMA transaction. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDmaTransaction Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdmatransaction/nf-wdfdmatransaction-wdfdmatransactioninitialize#return-value)
+    ///
+    /// # Safety
+    ///
+    /// `mdl` must be a valid MDL chain describing a buffer that contains the
+    /// `length` bytes starting at `virtual_address`, and that buffer must
+    /// remain valid until the transaction is released or deleted.
+    pub unsafe fn initialize(
+        &self,
+        evt_program_dma: PFN_WDF_PROGRAM_DMA,
+        direction: WDF_DMA_DIRECTION,
+        mdl: PMDL,
+        virtual_address: PVOID,
+        length: usize,
+    ) -> Result<(), NTSTATUS> {
+        let nt_status;
+        // SAFETY: `wdf_dma_transaction` is a private member of `DmaTransaction`,
+        // originally created by WDF, and this module guarantees that it is always in
+        // a valid state. The caller guarantees that `mdl` and `virtual_address`
+        // describe a valid buffer.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfDmaTransactionInitialize,
+                self.wdf_dma_transaction,
+                evt_program_dma,
+                direction,
+                mdl,
+                virtual_address,
+                length,
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Start the [`DmaTransaction`]. `context` is passed to the
+    /// `EvtProgramDma` callback of the transaction.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to start the DMA transaction. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDmaTransaction Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdmatransaction/nf-wdfdmatransaction-wdfdmatransactionexecute#return-value)
+    pub fn execute(&self, context: WDFCONTEXT) -> Result<(), NTSTATUS> {
+        let nt_status;
+        // SAFETY: `wdf_dma_transaction` is a private member of `DmaTransaction`,
+        // originally created by WDF, and this module guarantees that it is always in
+        // a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfDmaTransactionExecute,
+                self.wdf_dma_transaction,
+                context,
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Notify the framework that the current DMA transfer of the
+    /// [`DmaTransaction`] has completed
+    ///
+    /// Returns [`None`] if the transaction has more transfers to perform, in
+    /// which case the framework calls `EvtProgramDma` again for the next
+    /// transfer. Otherwise, returns the completion [`NTSTATUS`] of the whole
+    /// transaction.
+    #[must_use]
+    pub fn dma_completed(&self) -> Option<NTSTATUS> {
+        let mut nt_status: NTSTATUS = 0;
+        let transaction_completed;
+        // SAFETY: `wdf_dma_transaction` is a private member of `DmaTransaction`,
+        // originally created by WDF, and this module guarantees that it is always in
+        // a valid state.
+        unsafe {
+            transaction_completed = macros::call_unsafe_wdf_function_binding!(
+                WdfDmaTransactionDmaCompleted,
+                self.wdf_dma_transaction,
+                &mut nt_status,
+            );
+        }
+        (transaction_completed != 0).then_some(nt_status)
+    }
+
+    /// Get the total number of bytes transferred by the [`DmaTransaction`] so
+    /// far
+    #[must_use]
+    pub fn bytes_transferred(&self) -> usize {
+        let bytes_transferred;
+        // SAFETY: `wdf_dma_transaction` is a private member of `DmaTransaction`,
+        // originally created by WDF, and this module guarantees that it is always in
+        // a valid state.
+        unsafe {
+            bytes_transferred = macros::call_unsafe_wdf_function_binding!(
+                WdfDmaTransactionGetBytesTransferred,
+                self.wdf_dma_transaction
+            );
+        }
+        bytes_transferred
+    }
+
+    /// Release the resources of the [`DmaTransaction`], so that it can be
+    /// reinitialized and reused for another transfer
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to release the DMA transaction. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDmaTransaction Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdmatransaction/nf-wdfdmatransaction-wdfdmatransactionrelease#return-value)
+    pub fn release(&self) -> Result<(), NTSTATUS> {
+        let nt_status;
+        // SAFETY: `wdf_dma_transaction` is a private member of `DmaTransaction`,
+        // originally created by WDF, and this module guarantees that it is always in
+        // a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfDmaTransactionRelease,
+                self.wdf_dma_transaction
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+}
+
+// SAFETY: `wdf_dma_transaction` is a private member of `DmaTransaction`,
+// originally created by WDF, and this module guarantees that it is always in a
+// valid state.
+unsafe impl ObjectHandle for DmaTransaction {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_dma_transaction.cast()
+    }
+}
+
+// SAFETY: `DmaTransaction` does not delete the underlying object when dropped.
+unsafe impl FromWdfObject for DmaTransaction {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            wdf_dma_transaction: wdf_object.cast(),
+        }
+    }
+}
+
+impl<'a> ScatterGatherList<'a> {
+    /// Construct a [`ScatterGatherList`] from the `PSCATTER_GATHER_LIST`
+    /// passed to an `EvtProgramDma` callback
+    ///
+    /// # Safety
+    ///
+    /// `raw` must point to a valid `SCATTER_GATHER_LIST` containing
+    /// `NumberOfElements` elements, which must not be freed for the lifetime
+    /// `'a` (ex. for the duration of the `EvtProgramDma` callback).
+    #[must_use]
+    pub unsafe fn from_raw(raw: PSCATTER_GATHER_LIST) -> Self {
+        // SAFETY: The caller guarantees that `raw` points to a valid
+        // `SCATTER_GATHER_LIST` that outlives `'a`.
+        let raw = unsafe { &*raw };
+        Self {
+            // SAFETY: The caller guarantees that `Elements` contains `NumberOfElements`
+            // elements.
+            elements: unsafe { raw.Elements.as_slice(raw.NumberOfElements as usize) },
+        }
+    }
+
+    /// Get the number of elements in the [`ScatterGatherList`]
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Returns `true` if the [`ScatterGatherList`] has no elements
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Get an iterator over the elements of the [`ScatterGatherList`]
+    #[must_use]
+    pub fn iter(&self) -> ScatterGatherElements<'a> {
+        ScatterGatherElements {
+            elements: self.elements.iter(),
+            _list: PhantomData,
+        }
+    }
+}
+
+impl<'a> IntoIterator for ScatterGatherList<'a> {
+    type IntoIter = ScatterGatherElements<'a>;
+    type Item = ScatterGatherElement;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &ScatterGatherList<'a> {
+    type IntoIter = ScatterGatherElements<'a>;
+    type Item = ScatterGatherElement;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl Iterator for ScatterGatherElements<'_> {
+    type Item = ScatterGatherElement;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.elements.next().map(|element| ScatterGatherElement {
+            // SAFETY: Every bit pattern of a `PHYSICAL_ADDRESS` is a valid `QuadPart`
+            address: unsafe { element.Address.QuadPart },
+            length: element.Length,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.elements.size_hint()
+    }
+}
+
+impl ExactSizeIterator for ScatterGatherElements<'_> {}
@@ -0,0 +1,183 @@
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+
+#[cfg(feature = "alloc")]
+use wdk_sys::ULONG;
+use wdk_sys::{macros, NTSTATUS, WDFDPC, WDFOBJECT, WDF_DPC_CONFIG, WDF_OBJECT_ATTRIBUTES};
+
+#[cfg(feature = "alloc")]
+use crate::wdf::{context::BorrowedObject, ObjectContext};
+use crate::{
+    nt_success,
+    wdf::{FromWdfObject, ObjectHandle},
+};
+
+/// WDF DPC (Deferred Procedure Call).
+///
+/// A DPC runs its callback at `DISPATCH_LEVEL` shortly after it is enqueued,
+/// which makes it suitable for deferring work out of an interrupt service
+/// routine. The framework deletes the DPC when its parent object is deleted.
+pub struct Dpc {
+    wdf_dpc: WDFDPC,
+}
+
+#[cfg(feature = "alloc")]
+crate::wdf_declare_context_type!(
+    /// Context stored in every [`Dpc`] created via [`Dpc::create`]
+    struct DpcContext {
+        callback: Box<dyn Fn(&Dpc) + Send + Sync>,
+    }
+);
+
+impl Dpc {
+    /// Try to construct a WDF DPC object
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a DPC. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDpc Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdpc/nf-wdfdpc-wdfdpccreate#return-value)
+    pub fn try_new(
+        dpc_config: &mut WDF_DPC_CONFIG,
+        attributes: &mut WDF_OBJECT_ATTRIBUTES,
+    ) -> Result<Self, NTSTATUS> {
+        let mut dpc = Self {
+            wdf_dpc: core::ptr::null_mut(),
+        };
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfDpcCreate,
+                dpc_config,
+                attributes,
+                &mut dpc.wdf_dpc,
+            );
+        }
+        nt_success(nt_status).then_some(dpc).ok_or(nt_status)
+    }
+
+    /// Try to construct a WDF DPC object whose callback is a Rust closure
+    ///
+    /// `parent` must be a device or queue object, and must have an initialized
+    /// context of type `P`. Every time the DPC runs, `callback` is invoked with
+    /// a reference to the DPC and to its parent's `P` context. If the parent's
+    /// context has not been initialized, `callback` is not invoked.
+    ///
+    /// `callback` is stored in the DPC's WDF object context space and is
+    /// dropped when the framework destroys the DPC.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a DPC. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDpc Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdpc/nf-wdfdpc-wdfdpccreate#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn create<P, F>(parent: &impl ObjectHandle, callback: F) -> Result<Self, NTSTATUS>
+    where
+        P: ObjectContext,
+        F: Fn(&Self, &P) + Send + Sync + 'static,
+    {
+        let callback: Box<dyn Fn(&Self) + Send + Sync> = Box::new(move |dpc: &Self| {
+            let parent = BorrowedObject(dpc.parent_object());
+            if let Some(parent_context) = parent.context::<P>() {
+                callback(dpc, parent_context);
+            }
+        });
+
+        let mut dpc_config = WDF_DPC_CONFIG {
+            // The size of WDF_DPC_CONFIG is known to fit in a ULONG
+            #[allow(clippy::cast_possible_truncation)]
+            Size: core::mem::size_of::<WDF_DPC_CONFIG>() as ULONG,
+            EvtDpcFunc: Some(evt_dpc_func),
+            AutomaticSerialization: u8::from(true),
+        };
+
+        let mut attributes = WDF_OBJECT_ATTRIBUTES {
+            ParentObject: parent.as_wdf_object(),
+            ..DpcContext::object_attributes()
+        };
+
+        let dpc = Self::try_new(&mut dpc_config, &mut attributes)?;
+        if dpc.init_context(DpcContext { callback }).is_err() {
+            unreachable!("context of a newly created DPC should be uninitialized");
+        }
+
+        Ok(dpc)
+    }
+
+    /// Schedule the [`Dpc`]'s callback to run. Returns `true` if the DPC was
+    /// not already in the system's DPC queue.
+    #[must_use]
+    pub fn enqueue(&self) -> bool {
+        let result;
+        // SAFETY: `wdf_dpc` is a private member of `Dpc`, originally created by WDF,
+        // and this module guarantees that it is always in a valid state.
+        unsafe {
+            result = macros::call_unsafe_wdf_function_binding!(WdfDpcEnqueue, self.wdf_dpc);
+        }
+        result != 0
+    }
+
+    /// Remove the [`Dpc`] from the system's DPC queue
+    ///
+    /// If `wait` is `true`, this waits for a running callback of the DPC to
+    /// complete, and must therefore only be called at `PASSIVE_LEVEL`. Returns
+    /// `true` if the DPC was in the system's DPC queue.
+    #[must_use]
+    pub fn cancel(&self, wait: bool) -> bool {
+        let result;
+        // SAFETY: `wdf_dpc` is a private member of `Dpc`, originally created by WDF,
+        // and this module guarantees that it is always in a valid state.
+        unsafe {
+            result = macros::call_unsafe_wdf_function_binding!(
+                WdfDpcCancel,
+                self.wdf_dpc,
+                u8::from(wait)
+            );
+        }
+        result != 0
+    }
+
+    /// Get the handle of the [`Dpc`]'s parent object
+    #[must_use]
+    pub fn parent_object(&self) -> WDFOBJECT {
+        let parent_object;
+        // SAFETY: `wdf_dpc` is a private member of `Dpc`, originally created by WDF,
+        // and this module guarantees that it is always in a valid state.
+        unsafe {
+            parent_object =
+                macros::call_unsafe_wdf_function_binding!(WdfDpcGetParentObject, self.wdf_dpc);
+        }
+        parent_object
+    }
+}
+
+// SAFETY: `wdf_dpc` is a private member of `Dpc`, originally created by WDF,
+// and this module guarantees that it is always in a valid state.
+unsafe impl ObjectHandle for Dpc {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_dpc.cast()
+    }
+}
+
+// SAFETY: `Dpc` does not delete the underlying object when dropped.
+unsafe impl FromWdfObject for Dpc {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            wdf_dpc: wdf_object.cast(),
+        }
+    }
+}
+
+/// `EvtDpcFunc` trampoline that forwards to the closure stored in the DPC's
+/// context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_dpc_func(wdf_dpc: WDFDPC) {
+    let dpc = Dpc { wdf_dpc };
+    if let Some(context) = dpc.context::<DpcContext>() {
+        (context.callback)(&dpc);
+    }
+}
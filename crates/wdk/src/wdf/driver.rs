@@ -0,0 +1,265 @@
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+
+use wdk_sys::{
+    macros,
+    NTSTATUS,
+    WDFDRIVER,
+    WDFOBJECT,
+    WDF_DRIVER_CONFIG,
+    WDF_NO_OBJECT_ATTRIBUTES,
+    WDF_OBJECT_ATTRIBUTES,
+};
+#[cfg(feature = "alloc")]
+use wdk_sys::{PWDFDEVICE_INIT, STATUS_SUCCESS, ULONG};
+
+#[cfg(feature = "alloc")]
+use crate::wdf::ObjectContext;
+use crate::{
+    nt_success,
+    string::NtUnicodeStr,
+    wdf::{FromWdfObject, ObjectHandle},
+    DriverObject,
+};
+
+/// WDF Driver.
+///
+/// Every WDF driver must create exactly one [`Driver`] from its `DriverEntry`
+/// (see [`driver_entry`](crate::driver_entry)).
+pub struct Driver {
+    wdf_driver: WDFDRIVER,
+}
+
+/// `EvtDriverDeviceAdd` callback registered via [`DriverBuilder::device_add`]
+#[cfg(feature = "alloc")]
+type DeviceAddCallback =
+    Box<dyn Fn(&Driver, &mut PWDFDEVICE_INIT) -> Result<(), NTSTATUS> + Send + Sync>;
+
+#[cfg(feature = "alloc")]
+crate::wdf_declare_context_type!(
+    /// Context stored in the [`Driver`] created via [`DriverBuilder::create`]
+    struct DriverContext {
+        device_add: Option<DeviceAddCallback>,
+    }
+);
+
+/// Builder for a [`Driver`] whose framework callbacks are Rust closures,
+/// obtained via [`Driver::builder`].
+///
+/// The builder constructs the `WDF_DRIVER_CONFIG` passed to `WdfDriverCreate`
+/// internally, so drivers do not need to write `unsafe extern "C"` callbacks:
+///
+/// ```rust, no_run
+/// use wdk::{
+///     string::NtUnicodeStr,
+///     wdf::{Device, Driver},
+///     DriverObject,
+/// };
+///
+/// #[wdk::driver_entry]
+/// fn driver_entry(
+///     driver_object: &mut DriverObject,
+///     registry_path: NtUnicodeStr<'_>,
+/// ) -> wdk::Result<Driver> {
+///     Driver::builder()
+///         .device_add(|_driver, device_init| {
+///             Device::try_new(device_init, None)?;
+///             Ok(())
+///         })
+///         .create(driver_object, registry_path)
+/// }
+/// ```
+#[cfg(feature = "alloc")]
+#[must_use]
+pub struct DriverBuilder {
+    device_add: Option<DeviceAddCallback>,
+    driver_init_flags: ULONG,
+    driver_pool_tag: ULONG,
+}
+
+impl Driver {
+    /// Try to construct a WDF Driver object
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a driver. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDriver Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdriver/nf-wdfdriver-wdfdrivercreate#return-value)
+    pub fn try_new(
+        driver_object: &mut DriverObject,
+        registry_path: NtUnicodeStr<'_>,
+        driver_config: &mut WDF_DRIVER_CONFIG,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        let mut driver = Self {
+            wdf_driver: core::ptr::null_mut(),
+        };
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfDriverCreate,
+                driver_object.as_raw_mut(),
+                registry_path.as_raw(),
+                attributes.map_or(WDF_NO_OBJECT_ATTRIBUTES, core::ptr::from_mut),
+                driver_config,
+                &mut driver.wdf_driver,
+            );
+        }
+        nt_success(nt_status).then_some(driver).ok_or(nt_status)
+    }
+
+    /// Try to construct a WDF Driver object. This is an alias for
+    /// [`Driver::try_new()`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a driver. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDriver Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdriver/nf-wdfdriver-wdfdrivercreate#return-value)
+    pub fn create(
+        driver_object: &mut DriverObject,
+        registry_path: NtUnicodeStr<'_>,
+        driver_config: &mut WDF_DRIVER_CONFIG,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        Self::try_new(driver_object, registry_path, driver_config, attributes)
+    }
+
+    /// Get the underlying `WDFDRIVER`
+    #[must_use]
+    pub const fn as_raw(&self) -> WDFDRIVER {
+        self.wdf_driver
+    }
+
+    /// Get a [`DriverBuilder`] to construct a WDF Driver object whose
+    /// framework callbacks are Rust closures
+    #[cfg(feature = "alloc")]
+    pub fn builder() -> DriverBuilder {
+        DriverBuilder {
+            device_add: None,
+            driver_init_flags: 0,
+            driver_pool_tag: 0,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl DriverBuilder {
+    /// Set the `EvtDriverDeviceAdd` callback of the driver, which is called
+    /// by the framework at `PASSIVE_LEVEL` every time the Plug and Play
+    /// manager reports a device that the driver supports.
+    ///
+    /// The callback is expected to create the framework device object from
+    /// `device_init` (ex. via
+    /// [`Device::try_new`](crate::wdf::Device::try_new)). Returning an
+    /// error causes the framework to fail the device's `AddDevice`. Drivers
+    /// that do not support Plug and Play devices (ex. via
+    /// [`DriverBuilder::driver_init_flags`] with `WdfDriverInitNonPnpDriver`)
+    /// do not need this callback.
+    pub fn device_add<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Driver, &mut PWDFDEVICE_INIT) -> Result<(), NTSTATUS> + Send + Sync + 'static,
+    {
+        self.device_add = Some(Box::new(callback));
+        self
+    }
+
+    /// Set the `DriverInitFlags` of the driver, which are a combination of
+    /// `WDF_DRIVER_INIT_FLAGS` values (ex. `WdfDriverInitNonPnpDriver`)
+    pub const fn driver_init_flags(mut self, driver_init_flags: ULONG) -> Self {
+        self.driver_init_flags = driver_init_flags;
+        self
+    }
+
+    /// Set the pool tag that the framework uses for the driver's allocations.
+    /// If not set, the framework derives a tag from the driver's name.
+    pub const fn driver_pool_tag(mut self, driver_pool_tag: ULONG) -> Self {
+        self.driver_pool_tag = driver_pool_tag;
+        self
+    }
+
+    /// Try to construct the WDF Driver object configured by this builder
+    ///
+    /// The closures registered on this builder are stored in the driver's WDF
+    /// object context space, and are dropped when the framework destroys the
+    /// driver object as the driver unloads.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a driver. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDriver Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdriver/nf-wdfdriver-wdfdrivercreate#return-value)
+    pub fn create(
+        self,
+        driver_object: &mut DriverObject,
+        registry_path: NtUnicodeStr<'_>,
+    ) -> Result<Driver, NTSTATUS> {
+        let mut driver_config = WDF_DRIVER_CONFIG {
+            DriverInitFlags: self.driver_init_flags,
+            DriverPoolTag: self.driver_pool_tag,
+            ..WDF_DRIVER_CONFIG::init(
+                self.device_add
+                    .is_some()
+                    .then_some(evt_driver_device_add as _),
+            )
+        };
+
+        let mut attributes = DriverContext::object_attributes();
+
+        let driver = Driver::try_new(
+            driver_object,
+            registry_path,
+            &mut driver_config,
+            Some(&mut attributes),
+        )?;
+        if driver
+            .init_context(DriverContext {
+                device_add: self.device_add,
+            })
+            .is_err()
+        {
+            unreachable!("context of a newly created driver should be uninitialized");
+        }
+
+        Ok(driver)
+    }
+}
+
+// SAFETY: `wdf_driver` is a private member of `Driver`, originally created by
+// WDF, and this module guarantees that it is always in a valid state.
+unsafe impl ObjectHandle for Driver {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_driver.cast()
+    }
+}
+
+// SAFETY: `Driver` does not delete the underlying object when dropped.
+unsafe impl FromWdfObject for Driver {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            wdf_driver: wdf_object.cast(),
+        }
+    }
+}
+
+/// `EvtDriverDeviceAdd` trampoline that forwards device arrival to the
+/// callback stored in the driver's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_driver_device_add(
+    wdf_driver: WDFDRIVER,
+    mut device_init: PWDFDEVICE_INIT,
+) -> NTSTATUS {
+    let driver = Driver { wdf_driver };
+    let Some(device_add) = driver
+        .context::<DriverContext>()
+        .and_then(|context| context.device_add.as_ref())
+    else {
+        return STATUS_SUCCESS;
+    };
+
+    match device_add(&driver, &mut device_init) {
+        Ok(()) => STATUS_SUCCESS,
+        Err(nt_status) => nt_status,
+    }
+}
@@ -0,0 +1,535 @@
+//! Experimental executor that runs `async` request processing on WDF work
+//! items
+
+extern crate alloc;
+
+use alloc::{boxed::Box, sync::Arc, task::Wake};
+use core::{
+    cell::UnsafeCell,
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use wdk_sys::{
+    macros,
+    NTSTATUS,
+    ULONG,
+    WDFOBJECT,
+    WDFWORKITEM,
+    WDF_OBJECT_ATTRIBUTES,
+    WDF_WORKITEM_CONFIG,
+};
+
+use crate::{
+    time::Timeout,
+    wdf::{
+        context::drop_context,
+        DriverRequest,
+        FromWdfObject,
+        IoTarget,
+        ObjectContext,
+        ObjectHandle,
+        Request,
+        RequestCompletion,
+        Timer,
+        WorkItem,
+    },
+};
+
+/// The task is waiting to be woken
+const TASK_IDLE: u8 = 0;
+/// The task was woken, and its work item is queued to poll it
+const TASK_SCHEDULED: u8 = 1;
+/// The task is being polled by its work item
+const TASK_RUNNING: u8 = 2;
+/// The task was woken while it was being polled, so it is polled again once
+/// the current poll returns
+const TASK_NOTIFIED: u8 = 3;
+/// The task's future completed or was dropped, so it is never polled again
+const TASK_COMPLETE: u8 = 4;
+
+/// Future spawned on an [`Executor`]
+type TaskFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Executor that runs `async` blocks on WDF work items.
+///
+/// This is an experimental adapter that lets request processing be written as
+/// `async` code that awaits I/O target sends ([`DriverRequest::send_async`])
+/// and timer delays ([`Executor::delay`]) instead of as a state machine
+/// spread across completion routines. It is primarily intended for UMDF
+/// drivers, where work items run on a thread pool, but it does not rely on
+/// anything specific to user mode.
+///
+/// Every spawned task is polled on a work item that is a child of the
+/// executor's parent object, so tasks are polled at `PASSIVE_LEVEL` and never
+/// on the thread that woke them. A task is only polled by one thread at a
+/// time, and its work item is deleted once its future completes:
+///
+/// ```rust, no_run
+/// use core::time::Duration;
+///
+/// use wdk::wdf::{Device, Executor, IoQueue, IoctlRouter};
+/// use wdk_sys::{_WDF_IO_QUEUE_DISPATCH_TYPE::WdfIoQueueDispatchParallel, STATUS_SUCCESS};
+///
+/// const IOCTL_WAIT: u32 = 0x0022_2000;
+///
+/// fn create_queue(device: &Device) -> wdk::Result<IoQueue> {
+///     let executor = Executor::new(device);
+///     IoctlRouter::new()
+///         .ioctl_with_request(IOCTL_WAIT, move |_queue, request| {
+///             executor.spawn_request(request, move |request| async move {
+///                 match executor.delay(Duration::from_millis(100)) {
+///                     Ok(delay) => {
+///                         delay.await;
+///                         request.complete(STATUS_SUCCESS);
+///                     }
+///                     Err(nt_status) => request.complete(nt_status),
+///                 }
+///             });
+///         })
+///         .create_queue(device, WdfIoQueueDispatchParallel)
+/// }
+/// ```
+///
+/// Tasks that are still pending when the parent object is deleted are dropped
+/// along with their work items. Their futures must therefore only await
+/// operations whose objects are deleted along with the parent (ex. requests
+/// sent to the parent device's I/O targets, or [`Delay`]s created via
+/// [`Executor::delay`]), so that nothing wakes them afterwards.
+#[derive(Clone, Copy, Debug)]
+pub struct Executor {
+    parent: WDFOBJECT,
+}
+
+/// Future returned by [`Executor::delay`] that completes once its duration
+/// has elapsed.
+///
+/// The underlying timer is stopped and deleted when the [`Delay`] is dropped,
+/// so dropping it before it completes cancels the delay.
+pub struct Delay {
+    timer: Timer,
+    signal: Arc<Signal<()>>,
+}
+
+/// Future returned by [`DriverRequest::send_async`] that completes with the
+/// request and its result once the I/O target completes it
+#[must_use = "the request is only returned to the driver by awaiting this future"]
+pub struct SendRequest {
+    signal: Arc<Signal<(DriverRequest, RequestCompletion)>>,
+}
+
+/// Task spawned on an [`Executor`], which is shared by the task's work item
+/// and by every [`Waker`] of the task
+struct Task {
+    /// One of the `TASK_XXX` states, which determines who may access `future`
+    state: AtomicU8,
+    /// Future of the task, which is only accessed while the task is
+    /// `TASK_RUNNING`, or once it can no longer be polled
+    future: UnsafeCell<Option<TaskFuture>>,
+    /// Work item that polls the task
+    work_item: WDFWORKITEM,
+}
+
+crate::wdf_declare_context_type!(
+    /// Context stored in the work item of every task spawned via
+    /// [`Executor::spawn`]
+    struct TaskContext {
+        task: Arc<Task>,
+    }
+);
+
+/// Value that is produced once by a WDF callback (ex. a completion routine)
+/// and consumed by the future awaiting it
+struct Signal<T> {
+    /// Spin lock protecting `value` and `waker`. The critical sections are only
+    /// a few instructions long, and may run at `DISPATCH_LEVEL`.
+    locked: AtomicBool,
+    value: UnsafeCell<Option<T>>,
+    waker: UnsafeCell<Option<Waker>>,
+}
+
+impl Executor {
+    /// Construct an [`Executor`] whose tasks are children of `parent`, which
+    /// is typically the device whose requests the tasks process
+    #[must_use]
+    pub fn new(parent: &impl ObjectHandle) -> Self {
+        Self {
+            parent: parent.as_wdf_object(),
+        }
+    }
+
+    /// Spawn `future` as a new task, which is first polled on a work item
+    /// shortly after this returns
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to create the task's work item, in which case `future` is returned without being polled. The error variant will contain `future`, and a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFWorkItem Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfworkitem/nf-wdfworkitem-wdfworkitemcreate#return-value)
+    pub fn spawn<F>(&self, future: F) -> Result<(), (F, NTSTATUS)>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        match self.create_task_work_item() {
+            Ok(work_item) => {
+                start_task(&work_item, Box::pin(future));
+                Ok(())
+            }
+            Err(nt_status) => Err((future, nt_status)),
+        }
+    }
+
+    /// Spawn the future returned by `handler` for `request` as a new task.
+    /// `handler` takes ownership of the [`Request`], and its future must
+    /// complete it or hand it off.
+    ///
+    /// If the task cannot be spawned, `handler` is not called, and `request`
+    /// is completed with the [`NTSTATUS`] of the failure instead.
+    pub fn spawn_request<F, Fut>(&self, request: Request, handler: F)
+    where
+        F: FnOnce(Request) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        match self.create_task_work_item() {
+            Ok(work_item) => start_task(&work_item, Box::pin(handler(request))),
+            Err(nt_status) => request.complete(nt_status),
+        }
+    }
+
+    /// Create a [`Delay`] that completes once `duration` has elapsed. Its
+    /// timer is a child of the executor's parent object.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct the delay's timer. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFTimer Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdftimer/nf-wdftimer-wdftimercreate#return-value)
+    pub fn delay(&self, duration: Duration) -> Result<Delay, NTSTATUS> {
+        let signal = Arc::new(Signal::new());
+        let timer_signal = signal.clone();
+        let timer = Timer::create(self.parent, 0, move |_timer: &Timer| {
+            timer_signal.complete(());
+        })?;
+
+        // A zero due time would be an absolute time in the past, so the delay is at
+        // least one 100ns tick
+        let due_time = Timeout::relative(duration.max(Duration::from_nanos(100)));
+        let _ = timer.start(due_time.as_raw());
+        Ok(Delay { timer, signal })
+    }
+
+    /// Try to create the work item of a new task, as a child of the executor's
+    /// parent object
+    fn create_task_work_item(&self) -> Result<WorkItem, NTSTATUS> {
+        let mut work_item_config = WDF_WORKITEM_CONFIG {
+            // The size of WDF_WORKITEM_CONFIG is known to fit in a ULONG
+            #[allow(clippy::cast_possible_truncation)]
+            Size: core::mem::size_of::<WDF_WORKITEM_CONFIG>() as ULONG,
+            EvtWorkItemFunc: Some(evt_task_work_item_func),
+            AutomaticSerialization: u8::from(false),
+        };
+
+        let mut attributes = WDF_OBJECT_ATTRIBUTES {
+            EvtDestroyCallback: Some(evt_task_context_destroy),
+            ParentObject: self.parent,
+            ..TaskContext::object_attributes()
+        };
+
+        WorkItem::try_new(&mut work_item_config, &mut attributes)
+    }
+}
+
+// SAFETY: WDF object handles are not tied to the thread that created them, and
+// `Executor` only uses its parent handle to create child objects, which WDF
+// allows from any thread.
+unsafe impl Send for Executor {}
+
+// SAFETY: `Executor` has no interior mutability, and creating child objects of
+// its parent is thread-safe.
+unsafe impl Sync for Executor {}
+
+impl DriverRequest {
+    /// Send the formatted request to `io_target`, without blocking, and return
+    /// a future that completes with the request and its result once the
+    /// request completes. This is the `async` equivalent of
+    /// [`DriverRequest::send`]:
+    ///
+    /// ```rust, no_run
+    /// use wdk::wdf::{DriverRequest, IoTarget};
+    ///
+    /// async fn read(io_target: &IoTarget) -> wdk::Result<usize> {
+    ///     let mut request = DriverRequest::create(io_target)?;
+    ///     request.format_for_read(io_target, 16)?;
+    ///     let (_request, completion) = request
+    ///         .send_async(io_target)
+    ///         .map_err(|(_request, nt_status)| nt_status)?
+    ///         .await;
+    ///     completion.result()
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to send the request. The error variant will contain the request, and a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFRequest Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfrequest/nf-wdfrequest-wdfrequestsend#return-value)
+    pub fn send_async(self, io_target: &IoTarget) -> Result<SendRequest, (Self, NTSTATUS)> {
+        let signal = Arc::new(Signal::new());
+        let completion_signal = signal.clone();
+        self.send(io_target, move |request, completion| {
+            completion_signal.complete((request, completion));
+        })?;
+        Ok(SendRequest { signal })
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.signal.poll(cx)
+    }
+}
+
+impl Drop for Delay {
+    fn drop(&mut self) {
+        let _ = self.timer.stop(false);
+        // SAFETY: The timer was created by `Executor::delay` and is only referenced by
+        // this `Delay`. The framework stops it before deleting it, and drops its
+        // callback once any running invocation returns.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(WdfObjectDelete, self.timer.as_wdf_object());
+        }
+    }
+}
+
+impl Future for SendRequest {
+    type Output = (DriverRequest, RequestCompletion);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.signal.poll(cx)
+    }
+}
+
+impl Task {
+    /// Schedule the task to be polled on its work item, unless it is already
+    /// scheduled or complete. If it is being polled, it is polled again once
+    /// the current poll returns.
+    fn schedule(&self) {
+        let mut state = self.state.load(Ordering::Acquire);
+        loop {
+            let next_state = match state {
+                TASK_IDLE => TASK_SCHEDULED,
+                TASK_RUNNING => TASK_NOTIFIED,
+                _ => return,
+            };
+            match self.state.compare_exchange_weak(
+                state,
+                next_state,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => break,
+                Err(actual_state) => state = actual_state,
+            }
+        }
+
+        if state == TASK_IDLE {
+            // SAFETY: `work_item` is the task's work item, which is only deleted once
+            // the task is complete, and complete tasks are never scheduled.
+            let work_item = unsafe { WorkItem::from_wdf_object(self.work_item.cast()) };
+            work_item.enqueue();
+        }
+    }
+
+    /// Poll the task if it is scheduled, until it is no longer woken while
+    /// being polled. Returns `true` if the task's future completed.
+    fn run(self: &Arc<Self>) -> bool {
+        if self
+            .state
+            .compare_exchange(
+                TASK_SCHEDULED,
+                TASK_RUNNING,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_err()
+        {
+            return false;
+        }
+
+        let waker = Waker::from(self.clone());
+        let mut context = Context::from_waker(&waker);
+        loop {
+            // SAFETY: The task is `TASK_RUNNING` or `TASK_NOTIFIED`, and only the thread
+            // that moved it to `TASK_RUNNING` accesses its future in those states.
+            let future = unsafe { &mut *self.future.get() };
+            let poll = future
+                .as_mut()
+                .map_or(Poll::Ready(()), |future| future.as_mut().poll(&mut context));
+            if poll.is_ready() {
+                *future = None;
+                self.state.store(TASK_COMPLETE, Ordering::Release);
+                return true;
+            }
+
+            if self
+                .state
+                .compare_exchange(TASK_RUNNING, TASK_IDLE, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                return false;
+            }
+
+            // The task was woken while it was being polled
+            self.state.store(TASK_RUNNING, Ordering::Release);
+        }
+    }
+}
+
+impl Wake for Task {
+    fn wake(self: Arc<Self>) {
+        self.schedule();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.schedule();
+    }
+}
+
+// SAFETY: `future` is `Send`, and is only accessed by the thread that owns the
+// task's `TASK_RUNNING` state, or once the task can no longer be polled.
+unsafe impl Send for Task {}
+
+// SAFETY: Shared access to a `Task` is synchronized by `state`.
+unsafe impl Sync for Task {}
+
+impl<T> Signal<T> {
+    /// Construct a [`Signal`] that has not been completed yet
+    const fn new() -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            value: UnsafeCell::new(None),
+            waker: UnsafeCell::new(None),
+        }
+    }
+
+    /// Complete the signal with `value`, and wake the future awaiting it
+    fn complete(&self, value: T) {
+        let waker = self.with_lock(|value_slot, waker_slot| {
+            *value_slot = Some(value);
+            waker_slot.take()
+        });
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Take the value of the signal if it was completed, or register the waker
+    /// of `cx` to be woken once it is
+    fn poll(&self, cx: &Context<'_>) -> Poll<T> {
+        self.with_lock(|value_slot, waker_slot| {
+            if let Some(value) = value_slot.take() {
+                return Poll::Ready(value);
+            }
+
+            if !waker_slot
+                .as_ref()
+                .is_some_and(|waker| waker.will_wake(cx.waker()))
+            {
+                *waker_slot = Some(cx.waker().clone());
+            }
+            Poll::Pending
+        })
+    }
+
+    /// Run `f` with exclusive access to the value and waker of the signal
+    fn with_lock<R>(&self, f: impl FnOnce(&mut Option<T>, &mut Option<Waker>) -> R) -> R {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+
+        // SAFETY: `locked` was acquired above, so no other reference to `value`
+        // exists until it is released.
+        let value = unsafe { &mut *self.value.get() };
+        // SAFETY: `locked` was acquired above, so no other reference to `waker`
+        // exists until it is released.
+        let waker = unsafe { &mut *self.waker.get() };
+        let result = f(value, waker);
+
+        self.locked.store(false, Ordering::Release);
+        result
+    }
+}
+
+// SAFETY: The value of a `Signal` is sent from the thread that completes it to
+// the thread that polls it, and `Waker`s are `Send`.
+unsafe impl<T: Send> Send for Signal<T> {}
+
+// SAFETY: Shared access to the value and waker of a `Signal` is synchronized by
+// `locked`.
+unsafe impl<T: Send> Sync for Signal<T> {}
+
+/// Store `future` as the task of `work_item`, which was created by
+/// [`Executor::create_task_work_item`], and schedule its first poll
+fn start_task(work_item: &WorkItem, future: TaskFuture) {
+    let task = Arc::new(Task {
+        state: AtomicU8::new(TASK_IDLE),
+        future: UnsafeCell::new(Some(future)),
+        work_item: work_item.as_wdf_object().cast(),
+    });
+    if work_item
+        .init_context(TaskContext { task: task.clone() })
+        .is_err()
+    {
+        unreachable!("context of a newly created work item should be uninitialized");
+    }
+
+    task.schedule();
+}
+
+/// `EvtWorkItemFunc` of the work item of a task, which polls the task and
+/// deletes the work item once the task's future completes
+unsafe extern "C" fn evt_task_work_item_func(wdf_work_item: WDFWORKITEM) {
+    // SAFETY: The framework passes the handle of the work item being run, which is
+    // valid for the duration of this callback.
+    let work_item = unsafe { WorkItem::from_wdf_object(wdf_work_item.cast()) };
+    let Some(context) = work_item.context::<TaskContext>() else {
+        return;
+    };
+
+    if context.task.run() {
+        // SAFETY: The task is complete, so its work item is never enqueued again, and
+        // the framework allows a work item to delete itself from its callback.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(WdfObjectDelete, work_item.as_wdf_object());
+        }
+    }
+}
+
+/// `EvtDestroyCallback` of the work item of a task, which drops the task's
+/// future if it did not complete, and releases the work item's reference to
+/// the task
+unsafe extern "C" fn evt_task_context_destroy(wdf_object: WDFOBJECT) {
+    // SAFETY: The framework passes the handle of the work item being destroyed,
+    // which is valid for the duration of this callback.
+    let work_item = unsafe { WorkItem::from_wdf_object(wdf_object) };
+    if let Some(context) = work_item.context::<TaskContext>() {
+        let previous_state = context.task.state.swap(TASK_COMPLETE, Ordering::AcqRel);
+        if previous_state != TASK_COMPLETE {
+            // SAFETY: The framework only destroys a work item once its callback has
+            // returned, so the task was not running, and it can no longer be polled
+            // now that it is complete.
+            let future = unsafe { &mut *context.task.future.get() };
+            *future = None;
+        }
+    }
+
+    // SAFETY: The framework calls this exactly once with the handle of the work
+    // item being destroyed, after all of its callbacks have completed, so no other
+    // references to its context exist.
+    unsafe {
+        drop_context::<TaskContext>(wdf_object);
+    }
+}
@@ -0,0 +1,266 @@
+use wdk_sys::{
+    macros,
+    _WDF_TRI_STATE,
+    NTSTATUS,
+    PWDFDEVICE_INIT,
+    STATUS_SUCCESS,
+    ULONG,
+    WDFDEVICE,
+    WDFFILEOBJECT,
+    WDFOBJECT,
+    WDFREQUEST,
+    WDF_FILEOBJECT_CONFIG,
+    WDF_TRI_STATE,
+};
+
+use crate::{
+    string::NtUnicodeStr,
+    wdf::{FromWdfObject, FsContextUsage, ObjectContext, ObjectHandle},
+};
+
+/// WDF File Object.
+///
+/// The framework creates a file object every time an application or another
+/// driver opens a handle to a device, and deletes it once the handle is
+/// closed. Drivers that need per-handle state store it in the file object's
+/// context space, which is set up by [`FileObject::configure_device_init`].
+pub struct FileObject {
+    wdf_file_object: WDFFILEOBJECT,
+}
+
+/// Callbacks invoked by the framework for the file objects of a device,
+/// registered via [`FileObject::configure_device_init`].
+///
+/// The callbacks run at `PASSIVE_LEVEL`, in the context of the thread that
+/// opened or closed the handle.
+pub trait FileObjectCallbacks {
+    /// Type of the context stored in every file object of the device
+    type Context: ObjectContext;
+
+    /// Called when a handle to `device` is opened (`EvtDeviceFileCreate`).
+    ///
+    /// Returning [`Ok`] stores the context in `file_object` and completes the
+    /// create request successfully.
+    ///
+    /// # Errors
+    ///
+    /// Returning [`Err`] fails the create request with the contained
+    /// [`NTSTATUS`], and the framework deletes `file_object`.
+    fn on_create(device: WDFDEVICE, file_object: &FileObject) -> Result<Self::Context, NTSTATUS>;
+
+    /// Called when the last handle to the file object has been closed, but
+    /// requests associated with it might still be pending (`EvtFileCleanup`)
+    fn on_cleanup(_file_object: &FileObject) {}
+
+    /// Called once all requests associated with the file object have been
+    /// completed, right before it is deleted (`EvtFileClose`)
+    fn on_close(_file_object: &FileObject) {}
+}
+
+/// Settings for the file objects of a device whose [`FileObjectCallbacks`] are
+/// registered via [`FileObject::configure_device_init_with`].
+///
+/// Since the callbacks store a context in every file object, the framework is
+/// always required to create one. Devices that may receive requests without a
+/// file object must not register callbacks, and use
+/// [`FileObjectPolicy::Optional`](crate::wdf::FileObjectPolicy::Optional)
+/// instead.
+#[derive(Clone, Copy, Debug)]
+#[must_use]
+pub struct FileObjectConfig {
+    fs_context_usage: FsContextUsage,
+    auto_forward_cleanup_close: WDF_TRI_STATE,
+}
+
+impl FileObjectConfig {
+    /// Construct a [`FileObjectConfig`] with the defaults of
+    /// `WDF_FILEOBJECT_CONFIG_INIT`
+    pub const fn new() -> Self {
+        Self {
+            fs_context_usage: FsContextUsage::None,
+            auto_forward_cleanup_close: _WDF_TRI_STATE::WdfUseDefault,
+        }
+    }
+
+    /// Set which members of the WDM file objects the framework may use to
+    /// store its own file objects
+    pub const fn fs_context_usage(mut self, fs_context_usage: FsContextUsage) -> Self {
+        self.fs_context_usage = fs_context_usage;
+        self
+    }
+
+    /// Set whether the framework forwards cleanup and close requests to the
+    /// next lower driver after calling [`FileObjectCallbacks::on_cleanup`] and
+    /// [`FileObjectCallbacks::on_close`]. By default, the framework only
+    /// forwards them for filter drivers.
+    pub const fn auto_forward_cleanup_close(mut self, auto_forward_cleanup_close: bool) -> Self {
+        self.auto_forward_cleanup_close = if auto_forward_cleanup_close {
+            _WDF_TRI_STATE::WdfTrue
+        } else {
+            _WDF_TRI_STATE::WdfFalse
+        };
+        self
+    }
+}
+
+impl Default for FileObjectConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileObject {
+    /// Register the [`FileObjectCallbacks`] of `C` for the device that is
+    /// being initialized by `device_init`, and allocate context space of type
+    /// [`FileObjectCallbacks::Context`] in each of its file objects. This must
+    /// be called from `EvtDriverDeviceAdd`, before `WdfDeviceCreate`.
+    ///
+    /// The context of each file object is dropped when the framework deletes
+    /// the file object.
+    ///
+    /// # Safety
+    ///
+    /// `device_init` must be the valid `PWDFDEVICE_INIT` passed to
+    /// `EvtDriverDeviceAdd`, and must not have been passed to
+    /// `WdfDeviceCreate` yet.
+    pub unsafe fn configure_device_init<C: FileObjectCallbacks>(device_init: PWDFDEVICE_INIT) {
+        // SAFETY: The caller guarantees that `device_init` is valid and has not been
+        // used to create a device yet.
+        unsafe {
+            Self::configure_device_init_with::<C>(device_init, FileObjectConfig::new());
+        }
+    }
+
+    /// Same as [`FileObject::configure_device_init`], with the file objects of
+    /// the device configured by `config` instead of with the defaults
+    ///
+    /// # Safety
+    ///
+    /// `device_init` must be the valid `PWDFDEVICE_INIT` passed to
+    /// `EvtDriverDeviceAdd`, and must not have been passed to
+    /// `WdfDeviceCreate` yet.
+    pub unsafe fn configure_device_init_with<C: FileObjectCallbacks>(
+        device_init: PWDFDEVICE_INIT,
+        config: FileObjectConfig,
+    ) {
+        let mut file_object_config = WDF_FILEOBJECT_CONFIG {
+            // The size of WDF_FILEOBJECT_CONFIG is known to fit in a ULONG
+            #[allow(clippy::cast_possible_truncation)]
+            Size: core::mem::size_of::<WDF_FILEOBJECT_CONFIG>() as ULONG,
+            EvtDeviceFileCreate: Some(evt_device_file_create::<C>),
+            EvtFileClose: Some(evt_file_close::<C>),
+            EvtFileCleanup: Some(evt_file_cleanup::<C>),
+            AutoForwardCleanupClose: config.auto_forward_cleanup_close,
+            FileObjectClass: config.fs_context_usage.as_raw(),
+        };
+
+        let mut attributes = C::Context::object_attributes();
+
+        // SAFETY: The caller guarantees that `device_init` is valid and has not been
+        // used to create a device yet.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfDeviceInitSetFileObjectConfig,
+                device_init,
+                &mut file_object_config,
+                &mut attributes,
+            );
+        }
+    }
+
+    /// Get the device that the file object was opened on
+    #[must_use]
+    pub fn device(&self) -> WDFDEVICE {
+        let device;
+        // SAFETY: `wdf_file_object` is a private member of `FileObject`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            device = macros::call_unsafe_wdf_function_binding!(
+                WdfFileObjectGetDevice,
+                self.wdf_file_object,
+            );
+        }
+        device
+    }
+
+    /// Get the name that was appended to the device name when the file object
+    /// was opened, ex. the reference string of a device interface. Returns
+    /// [`None`] if the file object was opened without a file name.
+    #[must_use]
+    pub fn file_name(&self) -> Option<NtUnicodeStr<'_>> {
+        let file_name;
+        // SAFETY: `wdf_file_object` is a private member of `FileObject`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            file_name = macros::call_unsafe_wdf_function_binding!(
+                WdfFileObjectGetFileName,
+                self.wdf_file_object,
+            );
+        }
+
+        if file_name.is_null() {
+            return None;
+        }
+
+        // SAFETY: A non-null pointer returned by `WdfFileObjectGetFileName` points to
+        // a valid `UNICODE_STRING` owned by the file object, which lives at least as
+        // long as `self`.
+        Some(unsafe { NtUnicodeStr::from_raw(file_name) })
+    }
+}
+
+// SAFETY: `wdf_file_object` is a private member of `FileObject`, originally
+// created by WDF, and this module guarantees that it is always in a valid
+// state.
+unsafe impl ObjectHandle for FileObject {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_file_object.cast()
+    }
+}
+
+// SAFETY: `FileObject` does not delete the underlying object when dropped.
+unsafe impl FromWdfObject for FileObject {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            wdf_file_object: wdf_object.cast(),
+        }
+    }
+}
+
+/// `EvtDeviceFileCreate` trampoline that forwards the creation of a file object
+/// to [`FileObjectCallbacks::on_create`], and completes the create request
+unsafe extern "C" fn evt_device_file_create<C: FileObjectCallbacks>(
+    device: WDFDEVICE,
+    request: WDFREQUEST,
+    wdf_file_object: WDFFILEOBJECT,
+) {
+    let file_object = FileObject { wdf_file_object };
+    let nt_status = match C::on_create(device, &file_object) {
+        Ok(context) => {
+            if file_object.init_context(context).is_err() {
+                unreachable!("context of a newly created file object should be uninitialized");
+            }
+            STATUS_SUCCESS
+        }
+        Err(nt_status) => nt_status,
+    };
+
+    // SAFETY: The framework passes ownership of the create request to this
+    // callback, which must complete it exactly once.
+    unsafe {
+        macros::call_unsafe_wdf_function_binding!(WdfRequestComplete, request, nt_status);
+    }
+}
+
+/// `EvtFileCleanup` trampoline that forwards to
+/// [`FileObjectCallbacks::on_cleanup`]
+unsafe extern "C" fn evt_file_cleanup<C: FileObjectCallbacks>(wdf_file_object: WDFFILEOBJECT) {
+    C::on_cleanup(&FileObject { wdf_file_object });
+}
+
+/// `EvtFileClose` trampoline that forwards to [`FileObjectCallbacks::on_close`]
+unsafe extern "C" fn evt_file_close<C: FileObjectCallbacks>(wdf_file_object: WDFFILEOBJECT) {
+    C::on_close(&FileObject { wdf_file_object });
+}
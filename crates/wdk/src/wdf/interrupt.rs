@@ -0,0 +1,321 @@
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+
+use wdk_sys::{
+    macros,
+    NTSTATUS,
+    WDFDEVICE,
+    WDFINTERRUPT,
+    WDFOBJECT,
+    WDF_INTERRUPT_CONFIG,
+    WDF_NO_OBJECT_ATTRIBUTES,
+    WDF_OBJECT_ATTRIBUTES,
+};
+#[cfg(feature = "alloc")]
+use wdk_sys::{_WDF_TRI_STATE, BOOLEAN, ULONG};
+
+#[cfg(feature = "alloc")]
+use crate::wdf::{context::BorrowedObject, ObjectContext};
+use crate::{
+    nt_success,
+    wdf::{FromWdfObject, ObjectHandle},
+};
+
+/// WDF Interrupt.
+///
+/// An interrupt object represents a single interrupt vector or MSI message of
+/// a device. The framework calls the interrupt's ISR at the device's `DIRQL`
+/// when the interrupt fires, and the ISR typically defers the rest of its work
+/// to the interrupt's DPC via [`Interrupt::queue_dpc_for_isr`]. The framework
+/// deletes the interrupt when its parent device is deleted.
+pub struct Interrupt {
+    wdf_interrupt: WDFINTERRUPT,
+}
+
+/// Callbacks invoked by the framework for an [`Interrupt`] created via
+/// [`Interrupt::create`].
+///
+/// Both callbacks receive a reference to the `DeviceContext` of the device the
+/// interrupt belongs to. Since [`InterruptHandler::isr`] runs at `DIRQL`,
+/// concurrently with code running at lower IRQLs, the context must be
+/// [`Send`] and [`Sync`], and any of its state that is shared with the ISR
+/// should only be mutated while holding the interrupt lock (see
+/// [`Interrupt::acquire_lock`]) or via atomics.
+#[cfg(feature = "alloc")]
+pub trait InterruptHandler: Send + Sync {
+    /// Context of the device that the interrupt belongs to
+    type DeviceContext: ObjectContext + Send;
+
+    /// Called by the framework at `DIRQL` when the interrupt fires. Returns
+    /// `true` if the interrupt was generated by the device.
+    fn isr(
+        &self,
+        interrupt: &Interrupt,
+        message_id: ULONG,
+        device_context: &Self::DeviceContext,
+    ) -> bool;
+
+    /// Called by the framework at `DISPATCH_LEVEL` after the ISR queues a DPC
+    /// via [`Interrupt::queue_dpc_for_isr`]
+    fn dpc(&self, _interrupt: &Interrupt, _device_context: &Self::DeviceContext) {}
+}
+
+/// RAII guard for the lock of an [`Interrupt`], returned by
+/// [`Interrupt::acquire_lock`].
+///
+/// While the guard is held, the IRQL is raised to
+/// the interrupt's `DIRQL`, so the ISR cannot run concurrently. The lock is
+/// released when the guard is dropped.
+#[must_use = "the interrupt lock is released as soon as the guard is dropped"]
+pub struct InterruptLockGuard<'a> {
+    interrupt: &'a Interrupt,
+}
+
+/// Object-safe adapter for [`InterruptHandler`], so that handlers with
+/// different `DeviceContext` types can be stored in the same context type
+#[cfg(feature = "alloc")]
+trait ErasedInterruptHandler: Send + Sync {
+    fn isr(&self, interrupt: &Interrupt, message_id: ULONG) -> bool;
+
+    fn dpc(&self, interrupt: &Interrupt);
+}
+
+#[cfg(feature = "alloc")]
+impl<H: InterruptHandler> ErasedInterruptHandler for H {
+    fn isr(&self, interrupt: &Interrupt, message_id: ULONG) -> bool {
+        let device = BorrowedObject(interrupt.device().cast());
+        device
+            .context::<H::DeviceContext>()
+            .is_some_and(|device_context| {
+                InterruptHandler::isr(self, interrupt, message_id, device_context)
+            })
+    }
+
+    fn dpc(&self, interrupt: &Interrupt) {
+        let device = BorrowedObject(interrupt.device().cast());
+        if let Some(device_context) = device.context::<H::DeviceContext>() {
+            InterruptHandler::dpc(self, interrupt, device_context);
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+crate::wdf_declare_context_type!(
+    /// Context stored in every [`Interrupt`] created via [`Interrupt::create`]
+    struct InterruptContext {
+        handler: Box<dyn ErasedInterruptHandler>,
+    }
+);
+
+impl Interrupt {
+    /// Try to construct a WDF Interrupt object for `device`. This must be
+    /// called from `EvtDriverDeviceAdd` or `EvtDevicePrepareHardware`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct an interrupt. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFInterrupt Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfinterrupt/nf-wdfinterrupt-wdfinterruptcreate#return-value)
+    pub fn try_new(
+        device: WDFDEVICE,
+        interrupt_config: &mut WDF_INTERRUPT_CONFIG,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        let mut interrupt = Self {
+            wdf_interrupt: core::ptr::null_mut(),
+        };
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfInterruptCreate,
+                device,
+                interrupt_config,
+                attributes.map_or(WDF_NO_OBJECT_ATTRIBUTES, core::ptr::from_mut),
+                &mut interrupt.wdf_interrupt,
+            );
+        }
+        nt_success(nt_status).then_some(interrupt).ok_or(nt_status)
+    }
+
+    /// Try to construct a WDF Interrupt object for `device` whose ISR and DPC
+    /// are handled by an [`InterruptHandler`]. This must be called from
+    /// `EvtDriverDeviceAdd` or `EvtDevicePrepareHardware`.
+    ///
+    /// `device` must have an initialized context of type
+    /// [`InterruptHandler::DeviceContext`]. If it does not, the ISR reports
+    /// that the interrupt was not generated by the device, and the DPC is not
+    /// invoked.
+    ///
+    /// `handler` is stored in the interrupt's WDF object context space and is
+    /// dropped when the framework destroys the interrupt.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct an interrupt. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFInterrupt Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfinterrupt/nf-wdfinterrupt-wdfinterruptcreate#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn create<H>(device: WDFDEVICE, handler: H) -> Result<Self, NTSTATUS>
+    where
+        H: InterruptHandler + 'static,
+    {
+        let handler: Box<dyn ErasedInterruptHandler> = Box::new(handler);
+
+        let mut interrupt_config = WDF_INTERRUPT_CONFIG {
+            // The size of WDF_INTERRUPT_CONFIG is known to fit in a ULONG
+            #[allow(clippy::cast_possible_truncation)]
+            Size: core::mem::size_of::<WDF_INTERRUPT_CONFIG>() as ULONG,
+            ShareVector: _WDF_TRI_STATE::WdfUseDefault,
+            EvtInterruptIsr: Some(evt_interrupt_isr),
+            EvtInterruptDpc: Some(evt_interrupt_dpc),
+            ReportInactiveOnPowerDown: _WDF_TRI_STATE::WdfUseDefault,
+            ..WDF_INTERRUPT_CONFIG::default()
+        };
+
+        let mut attributes = InterruptContext::object_attributes();
+
+        let interrupt = Self::try_new(device, &mut interrupt_config, Some(&mut attributes))?;
+        if interrupt
+            .init_context(InterruptContext { handler })
+            .is_err()
+        {
+            unreachable!("context of a newly created interrupt should be uninitialized");
+        }
+
+        Ok(interrupt)
+    }
+
+    /// Acquire the lock of the [`Interrupt`], which synchronizes with its ISR.
+    /// The lock is released when the returned [`InterruptLockGuard`] is
+    /// dropped.
+    ///
+    /// # Safety
+    ///
+    /// This must not be called at or above the `DIRQL` of the [`Interrupt`],
+    /// ex. from its ISR or from other callbacks that the framework
+    /// synchronizes with the ISR, since the lock is already held there and
+    /// acquiring it again deadlocks.
+    pub unsafe fn acquire_lock(&self) -> InterruptLockGuard<'_> {
+        // SAFETY: `wdf_interrupt` is a private member of `Interrupt`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state. The caller guarantees that the IRQL is below the interrupt's
+        // `DIRQL`, so the lock is not already held by the current processor.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(WdfInterruptAcquireLock, self.wdf_interrupt);
+        }
+        InterruptLockGuard { interrupt: self }
+    }
+
+    /// Enable the [`Interrupt`] in hardware, by calling its
+    /// `EvtInterruptEnable` callback. This must only be called at
+    /// `PASSIVE_LEVEL`.
+    pub fn enable(&self) {
+        // SAFETY: `wdf_interrupt` is a private member of `Interrupt`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(WdfInterruptEnable, self.wdf_interrupt);
+        }
+    }
+
+    /// Disable the [`Interrupt`] in hardware, by calling its
+    /// `EvtInterruptDisable` callback. This must only be called at
+    /// `PASSIVE_LEVEL`.
+    pub fn disable(&self) {
+        // SAFETY: `wdf_interrupt` is a private member of `Interrupt`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(WdfInterruptDisable, self.wdf_interrupt);
+        }
+    }
+
+    /// Schedule the [`Interrupt`]'s DPC to run. This is meant to be called
+    /// from the ISR. Returns `true` if the DPC was not already in the system's
+    /// DPC queue.
+    #[must_use]
+    pub fn queue_dpc_for_isr(&self) -> bool {
+        let result;
+        // SAFETY: `wdf_interrupt` is a private member of `Interrupt`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            result = macros::call_unsafe_wdf_function_binding!(
+                WdfInterruptQueueDpcForIsr,
+                self.wdf_interrupt
+            );
+        }
+        result != 0
+    }
+
+    /// Get the handle of the device that the [`Interrupt`] belongs to
+    #[must_use]
+    pub fn device(&self) -> WDFDEVICE {
+        let device;
+        // SAFETY: `wdf_interrupt` is a private member of `Interrupt`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            device = macros::call_unsafe_wdf_function_binding!(
+                WdfInterruptGetDevice,
+                self.wdf_interrupt
+            );
+        }
+        device
+    }
+}
+
+// SAFETY: `wdf_interrupt` is a private member of `Interrupt`, originally
+// created by WDF, and this module guarantees that it is always in a valid
+// state.
+unsafe impl ObjectHandle for Interrupt {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_interrupt.cast()
+    }
+}
+
+// SAFETY: `Interrupt` does not delete the underlying object when dropped.
+unsafe impl FromWdfObject for Interrupt {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            wdf_interrupt: wdf_object.cast(),
+        }
+    }
+}
+
+impl Drop for InterruptLockGuard<'_> {
+    fn drop(&mut self) {
+        // SAFETY: `wdf_interrupt` is a valid interrupt handle, whose lock was acquired
+        // when this guard was created.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfInterruptReleaseLock,
+                self.interrupt.wdf_interrupt
+            );
+        }
+    }
+}
+
+/// `EvtInterruptIsr` trampoline that forwards to the [`InterruptHandler`]
+/// stored in the interrupt's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_interrupt_isr(wdf_interrupt: WDFINTERRUPT, message_id: ULONG) -> BOOLEAN {
+    let interrupt = Interrupt { wdf_interrupt };
+    let interrupt_recognized = interrupt
+        .context::<InterruptContext>()
+        .is_some_and(|context| context.handler.isr(&interrupt, message_id));
+    BOOLEAN::from(interrupt_recognized)
+}
+
+/// `EvtInterruptDpc` trampoline that forwards to the [`InterruptHandler`]
+/// stored in the interrupt's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_interrupt_dpc(wdf_interrupt: WDFINTERRUPT, _associated_object: WDFOBJECT) {
+    let interrupt = Interrupt { wdf_interrupt };
+    if let Some(context) = interrupt.context::<InterruptContext>() {
+        context.handler.dpc(&interrupt);
+    }
+}
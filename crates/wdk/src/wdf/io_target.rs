@@ -0,0 +1,882 @@
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+
+use wdk_sys::{
+    macros,
+    _POOL_TYPE,
+    _WDF_REQUEST_REUSE_FLAGS::WDF_REQUEST_REUSE_NO_FLAGS,
+    NTSTATUS,
+    PWDFDEVICE_INIT,
+    STATUS_SUCCESS,
+    ULONG,
+    WDFIOTARGET,
+    WDFMEMORY,
+    WDFOBJECT,
+    WDFREQUEST,
+    WDF_OBJECT_ATTRIBUTES,
+    WDF_REQUEST_REUSE_PARAMS,
+};
+#[cfg(feature = "alloc")]
+use wdk_sys::{
+    _WDF_REQUEST_TYPE,
+    PVOID,
+    PWDF_REQUEST_COMPLETION_PARAMS,
+    WDFCONTEXT,
+    WDF_REQUEST_COMPLETION_PARAMS,
+};
+
+#[cfg(feature = "alloc")]
+use crate::wdf::{context::drop_context, ObjectContext};
+use crate::{
+    nt_success,
+    wdf::{Device, FromWdfObject, ObjectHandle, Request},
+};
+
+/// Pool tag of the buffers allocated for requests created via
+/// [`DriverRequest::create`]
+const DRIVER_REQUEST_POOL_TAG: ULONG = u32::from_le_bytes(*b"RReq");
+
+/// WDF I/O Target.
+///
+/// An I/O target represents a device stack that the driver sends requests to.
+/// The default I/O target of a [`Device`] is the next-lower driver in its
+/// stack, which filter drivers forward I/O to, and the self I/O target is the
+/// top of the device's own stack, which lets a driver send requests to its own
+/// queues. Both are owned by the framework, and live as long as the device.
+pub struct IoTarget {
+    wdf_io_target: WDFIOTARGET,
+}
+
+/// WDF Request created by the driver, as opposed to a [`Request`] that the
+/// framework delivered to the driver.
+///
+/// A driver-created request is formatted for a read, write or IOCTL via one of
+/// the `format_for_xxx` methods, and then sent to an [`IoTarget`] via
+/// [`DriverRequest::send`]. Once the request completes, ownership of it is
+/// returned to the driver in the completion closure, which can
+/// [`DriverRequest::reuse`] it for another request instead of allocating a new
+/// one. The request is deleted when the [`DriverRequest`] is dropped.
+pub struct DriverRequest {
+    wdf_request: WDFREQUEST,
+    input_memory: WDFMEMORY,
+    output_memory: WDFMEMORY,
+}
+
+/// Result of a [`DriverRequest`] that was completed by an I/O target
+#[derive(Clone, Copy, Debug)]
+pub struct RequestCompletion {
+    nt_status: NTSTATUS,
+    information: usize,
+}
+
+/// Completion closure of a [`DriverRequest`], invoked with the completed
+/// request and its result
+#[cfg(feature = "alloc")]
+type DriverRequestCompletion = Box<dyn FnOnce(DriverRequest, RequestCompletion) + Send + Sync>;
+
+/// Completion closure of a [`Request`] forwarded via
+/// [`Request::forward_to_next_lower_with_completion`], invoked with the
+/// completed request and its result
+#[cfg(feature = "alloc")]
+type ForwardedRequestCompletion = Box<dyn FnOnce(Request, RequestCompletion) + Send + Sync>;
+
+#[cfg(feature = "alloc")]
+crate::wdf_declare_context_type!(
+    /// Context stored in every request created via [`DriverRequest::create`]
+    struct DriverRequestContext {
+        completion: Option<DriverRequestCompletion>,
+    }
+);
+
+#[cfg(feature = "alloc")]
+crate::wdf_declare_context_type!(
+    /// Context of a request that was forwarded via
+    /// [`Request::forward_to_next_lower_with_completion`]
+    struct ForwardedRequestContext {
+        completion: Option<ForwardedRequestCompletion>,
+    }
+);
+
+impl IoTarget {
+    /// Construct an [`IoTarget`] from a raw `WDFIOTARGET`
+    ///
+    /// # Safety
+    ///
+    /// `wdf_io_target` must be a valid I/O target, that remains valid for the
+    /// lifetime of the returned [`IoTarget`].
+    #[must_use]
+    pub const unsafe fn from_raw(wdf_io_target: WDFIOTARGET) -> Self {
+        Self { wdf_io_target }
+    }
+
+    /// Get the underlying `WDFIOTARGET`
+    #[must_use]
+    pub const fn as_raw(&self) -> WDFIOTARGET {
+        self.wdf_io_target
+    }
+}
+
+impl DriverRequest {
+    /// Try to create a request that will be sent to `io_target`. The request is
+    /// a child of `io_target`, so it is deleted along with it if it is still
+    /// alive at that point.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to create the request. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFRequest Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfrequest/nf-wdfrequest-wdfrequestcreate#return-value)
+    pub fn create(io_target: &IoTarget) -> Result<Self, NTSTATUS> {
+        #[cfg(feature = "alloc")]
+        let mut request_attributes = WDF_OBJECT_ATTRIBUTES {
+            ParentObject: io_target.as_wdf_object(),
+            ..DriverRequestContext::object_attributes()
+        };
+        #[cfg(not(feature = "alloc"))]
+        let mut request_attributes = WDF_OBJECT_ATTRIBUTES {
+            ParentObject: io_target.as_wdf_object(),
+            ..WDF_OBJECT_ATTRIBUTES::init()
+        };
+        let mut request = Self {
+            wdf_request: core::ptr::null_mut(),
+            input_memory: core::ptr::null_mut(),
+            output_memory: core::ptr::null_mut(),
+        };
+
+        let nt_status;
+        // SAFETY: `wdf_io_target` is a private member of `IoTarget`, which is
+        // guaranteed to be valid by `IoTarget::from_raw`. The resulting ffi object is
+        // stored in a private member and not accessible outside of this module, and
+        // this module guarantees that it is always in a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfRequestCreate,
+                &mut request_attributes,
+                io_target.wdf_io_target,
+                &mut request.wdf_request,
+            );
+        }
+        if !nt_success(nt_status) {
+            // Avoid deleting the request that was never created
+            core::mem::forget(request);
+            return Err(nt_status);
+        }
+
+        #[cfg(feature = "alloc")]
+        if request
+            .init_context(DriverRequestContext { completion: None })
+            .is_err()
+        {
+            unreachable!("context of a newly created request should be uninitialized");
+        }
+        Ok(request)
+    }
+
+    /// Get the underlying `WDFREQUEST`
+    #[must_use]
+    pub const fn as_raw(&self) -> WDFREQUEST {
+        self.wdf_request
+    }
+
+    /// Format the request for reading `length` bytes from `io_target`, into a
+    /// buffer that is allocated by the framework. The data that was read is
+    /// available via [`DriverRequest::output_buffer`] once the request
+    /// completes.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to allocate the buffer or to format the request. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFIoTarget Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfiotarget/nf-wdfiotarget-wdfiotargetformatrequestforread#return-value)
+    pub fn format_for_read(&mut self, io_target: &IoTarget, length: usize) -> Result<(), NTSTATUS> {
+        let output_memory = self.create_memory(length, None)?;
+
+        let nt_status;
+        // SAFETY: `wdf_request` is a private member of `DriverRequest`, which this
+        // module guarantees is a valid request that is owned by the driver, and
+        // `output_memory` was created above as a child of it.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfIoTargetFormatRequestForRead,
+                io_target.wdf_io_target,
+                self.wdf_request,
+                output_memory,
+                core::ptr::null_mut(),
+                core::ptr::null_mut(),
+            );
+        }
+        self.set_memory(nt_status, core::ptr::null_mut(), output_memory)
+    }
+
+    /// Format the request for writing `data` to `io_target`.
+    ///
+    /// `data` is copied into a buffer that is allocated by the framework, so it
+    /// does not need to outlive the request.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to allocate the buffer or to format the request. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFIoTarget Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfiotarget/nf-wdfiotarget-wdfiotargetformatrequestforwrite#return-value)
+    pub fn format_for_write(&mut self, io_target: &IoTarget, data: &[u8]) -> Result<(), NTSTATUS> {
+        let input_memory = self.create_memory(data.len(), Some(data))?;
+
+        let nt_status;
+        // SAFETY: `wdf_request` is a private member of `DriverRequest`, which this
+        // module guarantees is a valid request that is owned by the driver, and
+        // `input_memory` was created above as a child of it.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfIoTargetFormatRequestForWrite,
+                io_target.wdf_io_target,
+                self.wdf_request,
+                input_memory,
+                core::ptr::null_mut(),
+                core::ptr::null_mut(),
+            );
+        }
+        self.set_memory(nt_status, input_memory, core::ptr::null_mut())
+    }
+
+    /// Format the request for sending the device I/O control request
+    /// `io_control_code` to `io_target`, with a copy of `input` as its input
+    /// buffer, and an output buffer of `output_length` bytes that is allocated
+    /// by the framework.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to allocate the buffers or to format the request. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFIoTarget Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfiotarget/nf-wdfiotarget-wdfiotargetformatrequestforioctl#return-value)
+    pub fn format_for_ioctl(
+        &mut self,
+        io_target: &IoTarget,
+        io_control_code: ULONG,
+        input: &[u8],
+        output_length: usize,
+    ) -> Result<(), NTSTATUS> {
+        self.format_for_ioctl_impl(io_target, io_control_code, input, output_length, false)
+    }
+
+    /// Format the request for sending the internal device I/O control request
+    /// `io_control_code` to `io_target`. This is the equivalent of
+    /// [`DriverRequest::format_for_ioctl`] for `IRP_MJ_INTERNAL_DEVICE_CONTROL`
+    /// requests, which can only be sent by kernel-mode drivers.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to allocate the buffers or to format the request. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFIoTarget Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfiotarget/nf-wdfiotarget-wdfiotargetformatrequestforinternalioctl#return-value)
+    pub fn format_for_internal_ioctl(
+        &mut self,
+        io_target: &IoTarget,
+        io_control_code: ULONG,
+        input: &[u8],
+        output_length: usize,
+    ) -> Result<(), NTSTATUS> {
+        self.format_for_ioctl_impl(io_target, io_control_code, input, output_length, true)
+    }
+
+    /// Get the output buffer that the request was formatted with, ex. the data
+    /// read by a request formatted via [`DriverRequest::format_for_read`]. Only
+    /// the first [`RequestCompletion::information`] bytes are typically
+    /// written by the I/O target.
+    #[must_use]
+    pub fn output_buffer(&self) -> &[u8] {
+        if self.output_memory.is_null() {
+            return &[];
+        }
+
+        let mut buffer_size: usize = 0;
+        let buffer;
+        // SAFETY: `output_memory` is a private member of `DriverRequest`, which this
+        // module guarantees is either null or a valid child of the request.
+        unsafe {
+            buffer = macros::call_unsafe_wdf_function_binding!(
+                WdfMemoryGetBuffer,
+                self.output_memory,
+                &mut buffer_size,
+            );
+        }
+        if buffer.is_null() {
+            return &[];
+        }
+
+        // SAFETY: WDF guarantees that the buffer of a memory object is valid for
+        // `buffer_size` bytes for as long as the memory object exists, and the
+        // memory object is only deleted when the request is reformatted or deleted,
+        // which requires `&mut self` or `self`.
+        unsafe { core::slice::from_raw_parts(buffer.cast(), buffer_size) }
+    }
+
+    /// Reinitialize the request, so that it can be formatted and sent again.
+    /// This is typically called from the completion closure passed to
+    /// [`DriverRequest::send`], to recycle the request instead of allocating
+    /// a new one.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to reinitialize the request. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFRequest Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfrequest/nf-wdfrequest-wdfrequestreuse#return-value)
+    pub fn reuse(&mut self) -> Result<(), NTSTATUS> {
+        let mut reuse_params = WDF_REQUEST_REUSE_PARAMS {
+            // The flag is a small positive constant
+            #[allow(clippy::cast_sign_loss)]
+            Flags: WDF_REQUEST_REUSE_NO_FLAGS as ULONG,
+            Status: STATUS_SUCCESS,
+            ..WDF_REQUEST_REUSE_PARAMS::with_size()
+        };
+
+        let nt_status;
+        // SAFETY: `wdf_request` is a private member of `DriverRequest`, which this
+        // module guarantees is a valid request that is owned by the driver.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfRequestReuse,
+                self.wdf_request,
+                &mut reuse_params,
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Send the formatted request to `io_target`, without blocking. Once the
+    /// request completes, `completion` is invoked at `DISPATCH_LEVEL` or below
+    /// with the request and its result, so that the request can be inspected,
+    /// reused or dropped.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to send the request, in which case `completion` is never invoked. The error variant will contain the request, and a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFRequest Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfrequest/nf-wdfrequest-wdfrequestsend#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn send<F>(mut self, io_target: &IoTarget, completion: F) -> Result<(), (Self, NTSTATUS)>
+    where
+        F: FnOnce(Self, RequestCompletion) + Send + Sync + 'static,
+    {
+        // SAFETY: The request is only accessible via `self` until it is sent below,
+        // so no other reference to its context exists.
+        let Some(context) = (unsafe { self.context_mut::<DriverRequestContext>() }) else {
+            unreachable!("context of a driver-created request should be initialized");
+        };
+        context.completion = Some(Box::new(completion));
+
+        // SAFETY: `wdf_request` is a private member of `DriverRequest`, which this
+        // module guarantees is a valid request that is owned by the driver.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfRequestSetCompletionRoutine,
+                self.wdf_request,
+                Some(evt_driver_request_completion),
+                core::ptr::null_mut(),
+            );
+        }
+
+        let sent;
+        // SAFETY: `wdf_request` is a private member of `DriverRequest`, which this
+        // module guarantees is a valid request that is owned by the driver. On
+        // success, ownership of the request is transferred to `io_target` until its
+        // completion routine runs.
+        unsafe {
+            sent = macros::call_unsafe_wdf_function_binding!(
+                WdfRequestSend,
+                self.wdf_request,
+                io_target.wdf_io_target,
+                core::ptr::null_mut(),
+            );
+        }
+        if sent == 0 {
+            // SAFETY: The request was not sent, so it is still only accessible via
+            // `self`.
+            if let Some(context) = unsafe { self.context_mut::<DriverRequestContext>() } {
+                context.completion = None;
+            }
+
+            let nt_status;
+            // SAFETY: `wdf_request` is a private member of `DriverRequest`, which this
+            // module guarantees is a valid request that is owned by the driver.
+            unsafe {
+                nt_status = macros::call_unsafe_wdf_function_binding!(
+                    WdfRequestGetStatus,
+                    self.wdf_request
+                );
+            }
+            return Err((self, nt_status));
+        }
+
+        // The completion routine reconstructs the `DriverRequest` once the request
+        // completes
+        core::mem::forget(self);
+        Ok(())
+    }
+
+    /// Shared implementation of [`DriverRequest::format_for_ioctl`] and
+    /// [`DriverRequest::format_for_internal_ioctl`]
+    fn format_for_ioctl_impl(
+        &mut self,
+        io_target: &IoTarget,
+        io_control_code: ULONG,
+        input: &[u8],
+        output_length: usize,
+        internal: bool,
+    ) -> Result<(), NTSTATUS> {
+        let input_memory = if input.is_empty() {
+            core::ptr::null_mut()
+        } else {
+            self.create_memory(input.len(), Some(input))?
+        };
+        let output_memory = if output_length == 0 {
+            core::ptr::null_mut()
+        } else {
+            match self.create_memory(output_length, None) {
+                Ok(output_memory) => output_memory,
+                Err(nt_status) => {
+                    delete_memory(input_memory);
+                    return Err(nt_status);
+                }
+            }
+        };
+
+        let nt_status;
+        if internal {
+            // SAFETY: `wdf_request` is a private member of `DriverRequest`, which this
+            // module guarantees is a valid request that is owned by the driver, and the
+            // memory objects were created above as children of it, or are null.
+            unsafe {
+                nt_status = macros::call_unsafe_wdf_function_binding!(
+                    WdfIoTargetFormatRequestForInternalIoctl,
+                    io_target.wdf_io_target,
+                    self.wdf_request,
+                    io_control_code,
+                    input_memory,
+                    core::ptr::null_mut(),
+                    output_memory,
+                    core::ptr::null_mut(),
+                );
+            }
+        } else {
+            // SAFETY: `wdf_request` is a private member of `DriverRequest`, which this
+            // module guarantees is a valid request that is owned by the driver, and the
+            // memory objects were created above as children of it, or are null.
+            unsafe {
+                nt_status = macros::call_unsafe_wdf_function_binding!(
+                    WdfIoTargetFormatRequestForIoctl,
+                    io_target.wdf_io_target,
+                    self.wdf_request,
+                    io_control_code,
+                    input_memory,
+                    core::ptr::null_mut(),
+                    output_memory,
+                    core::ptr::null_mut(),
+                );
+            }
+        }
+        self.set_memory(nt_status, input_memory, output_memory)
+    }
+
+    /// Allocate a memory object of `length` bytes as a child of the request,
+    /// initialized with a copy of `data` if provided
+    fn create_memory(&self, length: usize, data: Option<&[u8]>) -> Result<WDFMEMORY, NTSTATUS> {
+        let mut memory_attributes = WDF_OBJECT_ATTRIBUTES {
+            ParentObject: self.wdf_request.cast(),
+            ..WDF_OBJECT_ATTRIBUTES::init()
+        };
+        let mut memory: WDFMEMORY = core::ptr::null_mut();
+        let mut buffer = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: `wdf_request` is a private member of `DriverRequest`, which this
+        // module guarantees is a valid request, and the created memory object is
+        // deleted along with it.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfMemoryCreate,
+                &mut memory_attributes,
+                _POOL_TYPE::NonPagedPoolNx,
+                DRIVER_REQUEST_POOL_TAG,
+                length,
+                &mut memory,
+                &mut buffer,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status);
+        }
+
+        if let Some(data) = data {
+            // SAFETY: `buffer` was allocated above with a size of `length` bytes, which
+            // is the length of `data`, and does not overlap with it.
+            unsafe {
+                core::ptr::copy_nonoverlapping(data.as_ptr(), buffer.cast(), length);
+            }
+        }
+        Ok(memory)
+    }
+
+    /// Replace the memory objects of the request with the ones it was just
+    /// formatted with if formatting succeeded (`nt_status`), and delete
+    /// whichever set of memory objects is no longer used
+    fn set_memory(
+        &mut self,
+        nt_status: NTSTATUS,
+        input_memory: WDFMEMORY,
+        output_memory: WDFMEMORY,
+    ) -> Result<(), NTSTATUS> {
+        if !nt_success(nt_status) {
+            delete_memory(input_memory);
+            delete_memory(output_memory);
+            return Err(nt_status);
+        }
+
+        delete_memory(core::mem::replace(&mut self.input_memory, input_memory));
+        delete_memory(core::mem::replace(&mut self.output_memory, output_memory));
+        Ok(())
+    }
+}
+
+impl RequestCompletion {
+    /// Get the status that the request was completed with
+    #[must_use]
+    pub const fn status(&self) -> NTSTATUS {
+        self.nt_status
+    }
+
+    /// Get the completion information of the request, which is typically the
+    /// number of bytes that were transferred
+    #[must_use]
+    pub const fn information(&self) -> usize {
+        self.information
+    }
+
+    /// Get the number of bytes that were transferred if the request completed
+    /// successfully, or the status that it failed with otherwise
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the request was completed with a
+    /// failure status. The error variant will contain that [`NTSTATUS`].
+    pub fn result(&self) -> Result<usize, NTSTATUS> {
+        nt_success(self.nt_status)
+            .then_some(self.information)
+            .ok_or(self.nt_status)
+    }
+
+    /// Construct a [`RequestCompletion`] from the completion parameters that
+    /// the framework passes to a request's completion routine
+    #[cfg(feature = "alloc")]
+    fn from_completion_params(completion_params: &WDF_REQUEST_COMPLETION_PARAMS) -> Self {
+        Self {
+            // SAFETY: The status is always the active field of `IoStatus` for completed
+            // requests.
+            nt_status: unsafe { completion_params.IoStatus.__bindgen_anon_1.Status },
+            // ULONG_PTR is pointer-sized, so it always fits in a usize
+            #[allow(clippy::cast_possible_truncation)]
+            information: completion_params.IoStatus.Information as usize,
+        }
+    }
+}
+
+impl Drop for DriverRequest {
+    fn drop(&mut self) {
+        // SAFETY: `wdf_request` is a private member of `DriverRequest`, which this
+        // module guarantees is a valid request that is owned by the driver, and not
+        // referenced by the framework. Its memory objects are deleted along with it.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(WdfObjectDelete, self.wdf_request.cast());
+        }
+    }
+}
+
+// SAFETY: `wdf_io_target` is a private member of `IoTarget`, which is
+// guaranteed to be valid by `IoTarget::from_raw` or the framework.
+unsafe impl ObjectHandle for IoTarget {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_io_target.cast()
+    }
+}
+
+// SAFETY: `IoTarget` does not delete the underlying object when dropped.
+unsafe impl FromWdfObject for IoTarget {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            wdf_io_target: wdf_object.cast(),
+        }
+    }
+}
+
+// SAFETY: WDF request and memory handles are not tied to the thread that
+// created them, so the driver can format, send or delete a request from any
+// thread (ex. from a work item).
+unsafe impl Send for DriverRequest {}
+
+// SAFETY: `wdf_request` is a private member of `DriverRequest`, originally
+// created by WDF, and this module guarantees that it is always in a valid
+// state.
+unsafe impl ObjectHandle for DriverRequest {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_request.cast()
+    }
+}
+
+impl Device {
+    /// Get the default I/O target of this device, which is the next-lower
+    /// driver in the device stack. Filter drivers send the requests that they
+    /// forward or create to this target.
+    #[must_use]
+    pub fn io_target(&self) -> IoTarget {
+        let wdf_io_target;
+        // SAFETY: `as_raw` returns the `WDFDEVICE` of a `Device`, which is always in
+        // a valid state. The default I/O target lives as long as the device.
+        unsafe {
+            wdf_io_target =
+                macros::call_unsafe_wdf_function_binding!(WdfDeviceGetIoTarget, self.as_raw());
+        }
+        IoTarget { wdf_io_target }
+    }
+
+    /// Get the self I/O target of this device, which sends requests to the top
+    /// of the device's own stack (ex. to its own queues), or [`None`] if the
+    /// self I/O target was not enabled via [`Device::allow_self_io_target`].
+    #[must_use]
+    pub fn self_io_target(&self) -> Option<IoTarget> {
+        let wdf_io_target: WDFIOTARGET;
+        // SAFETY: `as_raw` returns the `WDFDEVICE` of a `Device`, which is always in
+        // a valid state. The self I/O target lives as long as the device.
+        unsafe {
+            wdf_io_target =
+                macros::call_unsafe_wdf_function_binding!(WdfDeviceGetSelfIoTarget, self.as_raw());
+        }
+        (!wdf_io_target.is_null()).then_some(IoTarget { wdf_io_target })
+    }
+
+    /// Enable the self I/O target of the device that will be created from
+    /// `device_init`, which is retrieved via [`Device::self_io_target`]. This
+    /// must be called before the device is created via [`Device::try_new`].
+    ///
+    /// # Safety
+    ///
+    /// `device_init` must be a valid `PWDFDEVICE_INIT` that has not been used
+    /// to create a device yet.
+    pub unsafe fn allow_self_io_target(device_init: PWDFDEVICE_INIT) {
+        // SAFETY: The caller guarantees that `device_init` is valid and has not been
+        // used to create a device yet.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(WdfDeviceInitAllowSelfIoTarget, device_init);
+        }
+    }
+}
+
+impl Request {
+    /// Forward the request, as-is, to the next-lower driver in the device
+    /// stack of `device`, without waiting for it to be completed, consuming
+    /// it. This is how filter drivers (see
+    /// [`DeviceInit::set_filter`](crate::wdf::DeviceInit::set_filter)) pass on
+    /// the requests that they do not handle themselves.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the request, along with the [`NTSTATUS`] of the failure, if WDF fails to send it. The driver still owns the request in that case, and must complete it. Full error documentation is available in the [WDFRequest Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfrequest/nf-wdfrequest-wdfrequestsend#return-value)
+    pub fn forward_to_next_lower(self, device: &Device) -> Result<(), (Self, NTSTATUS)> {
+        self.send_and_forget(device.io_target().as_raw())
+    }
+
+    /// Forward the request, as-is, to the next-lower driver in the device
+    /// stack of `device`, consuming it. Once the next-lower driver completes
+    /// the request, `completion` is invoked at `DISPATCH_LEVEL` or below with
+    /// ownership of the request and its result, so that the driver can
+    /// inspect it (ex. post-process the data that was read) and must then
+    /// complete it, typically via [`Request::complete_with_information`] with
+    /// the status and information of the [`RequestCompletion`].
+    ///
+    /// The request keeps its contexts while it is forwarded, so state that the
+    /// driver stored in it before forwarding it (see
+    /// [`DeviceInit::request_context`](crate::wdf::DeviceInit::request_context))
+    /// is available to `completion`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the request, along with the [`NTSTATUS`] of the failure, if WDF fails to send it, in which case `completion` is never invoked. The driver still owns the request in that case, and must complete it. Full error documentation is available in the [WDFRequest Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfrequest/nf-wdfrequest-wdfrequestsend#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn forward_to_next_lower_with_completion<F>(
+        mut self,
+        device: &Device,
+        completion: F,
+    ) -> Result<(), (Self, NTSTATUS)>
+    where
+        F: FnOnce(Self, RequestCompletion) + Send + Sync + 'static,
+    {
+        let mut attributes = ForwardedRequestContext::object_attributes();
+        let mut context: PVOID = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: `as_raw` returns the `WDFREQUEST` of a `Request`, which is
+        // guaranteed to be a valid request owned by the driver by `Request::from_raw`.
+        // If the request already has a `ForwardedRequestContext` from a previous
+        // forward, WDF returns `STATUS_OBJECT_NAME_EXISTS`, which is a success status.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfObjectAllocateContext,
+                self.as_wdf_object(),
+                &mut attributes,
+                &mut context,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err((self, nt_status));
+        }
+
+        // SAFETY: The context is only referenced by
+        // `evt_forwarded_request_completion`, which is not running since the request
+        // is owned by the driver.
+        unsafe {
+            drop_context::<ForwardedRequestContext>(self.as_wdf_object());
+        }
+        if self
+            .init_context(ForwardedRequestContext {
+                completion: Some(Box::new(completion)),
+            })
+            .is_err()
+        {
+            unreachable!("context of the request should have been allocated and uninitialized");
+        }
+
+        // SAFETY: `as_raw` returns the `WDFREQUEST` of a `Request`, which is
+        // guaranteed to be a valid request owned by the driver by `Request::from_raw`.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfRequestFormatRequestUsingCurrentType,
+                self.as_raw(),
+            );
+        }
+
+        // SAFETY: `as_raw` returns the `WDFREQUEST` of a `Request`, which is
+        // guaranteed to be a valid request owned by the driver by `Request::from_raw`.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfRequestSetCompletionRoutine,
+                self.as_raw(),
+                Some(evt_forwarded_request_completion),
+                core::ptr::null_mut(),
+            );
+        }
+
+        let sent;
+        // SAFETY: `as_raw` returns the `WDFREQUEST` of a `Request`, which is
+        // guaranteed to be a valid request owned by the driver by `Request::from_raw`,
+        // and it was formatted above. On success, ownership of the request is
+        // transferred to the I/O target until its completion routine runs.
+        unsafe {
+            sent = macros::call_unsafe_wdf_function_binding!(
+                WdfRequestSend,
+                self.as_raw(),
+                device.io_target().as_raw(),
+                core::ptr::null_mut(),
+            );
+        }
+        if sent == 0 {
+            // SAFETY: The request was not sent, so it is still only accessible via
+            // `self`.
+            if let Some(context) = unsafe { self.context_mut::<ForwardedRequestContext>() } {
+                context.completion = None;
+            }
+
+            let nt_status;
+            // SAFETY: `as_raw` returns the `WDFREQUEST` of a `Request`, which is
+            // guaranteed to be a valid request owned by the driver by
+            // `Request::from_raw`.
+            unsafe {
+                nt_status =
+                    macros::call_unsafe_wdf_function_binding!(WdfRequestGetStatus, self.as_raw());
+            }
+            return Err((self, nt_status));
+        }
+        Ok(())
+    }
+}
+
+/// Delete `memory` if it is not null
+fn delete_memory(memory: WDFMEMORY) {
+    if memory.is_null() {
+        return;
+    }
+
+    // SAFETY: Non-null memory objects passed to this function are children of a
+    // `DriverRequest` that were created by `DriverRequest::create_memory`, and are
+    // no longer used by the request.
+    unsafe {
+        macros::call_unsafe_wdf_function_binding!(WdfObjectDelete, memory.cast());
+    }
+}
+
+/// `EvtRequestCompletionRoutine` of requests sent via [`DriverRequest::send`],
+/// which returns ownership of the request to the driver via its completion
+/// closure
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_driver_request_completion(
+    request: WDFREQUEST,
+    _io_target: WDFIOTARGET,
+    completion_params: PWDF_REQUEST_COMPLETION_PARAMS,
+    _context: WDFCONTEXT,
+) {
+    // SAFETY: The framework passes valid completion parameters, which live for the
+    // duration of this callback.
+    let completion_params = unsafe { &*completion_params };
+    let completion_result = RequestCompletion::from_completion_params(completion_params);
+
+    // The active field of `Parameters` is determined by the type of the request,
+    // which was formatted by one of the `DriverRequest::format_for_xxx` methods
+    let (input_memory, output_memory) = match completion_params.Type {
+        _WDF_REQUEST_TYPE::WdfRequestTypeRead => (
+            core::ptr::null_mut(),
+            // SAFETY: `Read` is the active field for read requests.
+            unsafe { completion_params.Parameters.Read.Buffer },
+        ),
+        _WDF_REQUEST_TYPE::WdfRequestTypeWrite => (
+            // SAFETY: `Write` is the active field for write requests.
+            unsafe { completion_params.Parameters.Write.Buffer },
+            core::ptr::null_mut(),
+        ),
+        _WDF_REQUEST_TYPE::WdfRequestTypeDeviceControl
+        | _WDF_REQUEST_TYPE::WdfRequestTypeDeviceControlInternal => {
+            // SAFETY: `Ioctl` is the active field for device control requests.
+            let ioctl = unsafe { completion_params.Parameters.Ioctl };
+            (ioctl.Input.Buffer, ioctl.Output.Buffer)
+        }
+        _ => (core::ptr::null_mut(), core::ptr::null_mut()),
+    };
+    let mut driver_request = DriverRequest {
+        wdf_request: request,
+        input_memory,
+        output_memory,
+    };
+
+    // SAFETY: The completion routine is the only code that accesses the context of
+    // the request once it is sent, and it runs exactly once per send.
+    let completion = unsafe { driver_request.context_mut::<DriverRequestContext>() }
+        .and_then(|context| context.completion.take());
+    if let Some(completion) = completion {
+        completion(driver_request, completion_result);
+    }
+}
+
+/// `EvtRequestCompletionRoutine` of requests forwarded via
+/// [`Request::forward_to_next_lower_with_completion`], which returns ownership
+/// of the request to the driver via its completion closure
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_forwarded_request_completion(
+    request: WDFREQUEST,
+    _io_target: WDFIOTARGET,
+    completion_params: PWDF_REQUEST_COMPLETION_PARAMS,
+    _context: WDFCONTEXT,
+) {
+    // SAFETY: The framework passes valid completion parameters, which live for the
+    // duration of this callback.
+    let completion_params = unsafe { &*completion_params };
+    let completion_result = RequestCompletion::from_completion_params(completion_params);
+
+    // SAFETY: The framework passes the forwarded request, whose ownership is
+    // returned to the driver once its completion routine runs.
+    let mut request = unsafe { Request::from_raw(request) };
+
+    // SAFETY: The completion routine is the only code that accesses the context of
+    // the request once it is forwarded, and it runs exactly once per forward.
+    let completion = unsafe { request.context_mut::<ForwardedRequestContext>() }
+        .and_then(|context| context.completion.take());
+    match completion {
+        Some(completion) => completion(request, completion_result),
+        None => request
+            .complete_with_information(completion_result.status(), completion_result.information()),
+    }
+}
@@ -1,7 +1,83 @@
 //! Safe abstractions over WDF APIs
+//!
+//! Wrappers of APIs that are only available in KMDF (ex. DPCs, WMI and DMA)
+//! are not available when building UMDF drivers (ie. with `driver_type =
+//! "umdf"`).
 
+#[cfg(not(driver_type = "umdf"))]
+mod child_list;
+mod collection;
+mod context;
+#[cfg(not(driver_type = "umdf"))]
+mod control_device;
+mod device;
+mod device_init;
+mod device_property;
+#[cfg(not(driver_type = "umdf"))]
+mod dma;
+#[cfg(not(driver_type = "umdf"))]
+mod dpc;
+mod driver;
+#[cfg(feature = "async")]
+mod executor;
+mod file_object;
+#[cfg(not(driver_type = "umdf"))]
+mod interrupt;
+mod io_target;
+#[cfg(all(feature = "netadaptercx", driver_type = "kmdf"))]
+mod net_adapter;
+#[cfg(not(driver_type = "umdf"))]
+mod pdo;
+mod power_policy;
+mod queue;
+mod registry;
+mod request;
+#[cfg(all(feature = "spb", driver_type = "kmdf"))]
+mod spb_controller;
 mod spinlock;
+mod string;
 mod timer;
+#[cfg(feature = "usb")]
+mod usb;
+mod work_item;
+#[cfg(not(driver_type = "umdf"))]
+mod wmi;
 
+#[cfg(not(driver_type = "umdf"))]
+pub use child_list::*;
+pub use collection::*;
+pub use context::*;
+#[cfg(not(driver_type = "umdf"))]
+pub use control_device::*;
+pub use device::*;
+pub use device_init::*;
+pub use device_property::*;
+#[cfg(not(driver_type = "umdf"))]
+pub use dma::*;
+#[cfg(not(driver_type = "umdf"))]
+pub use dpc::*;
+pub use driver::*;
+#[cfg(feature = "async")]
+pub use executor::*;
+pub use file_object::*;
+#[cfg(not(driver_type = "umdf"))]
+pub use interrupt::*;
+pub use io_target::*;
+#[cfg(all(feature = "netadaptercx", driver_type = "kmdf"))]
+pub use net_adapter::*;
+#[cfg(not(driver_type = "umdf"))]
+pub use pdo::*;
+pub use power_policy::*;
+pub use queue::*;
+pub use registry::*;
+pub use request::*;
+#[cfg(all(feature = "spb", driver_type = "kmdf"))]
+pub use spb_controller::*;
 pub use spinlock::*;
+pub use string::*;
 pub use timer::*;
+#[cfg(feature = "usb")]
+pub use usb::*;
+pub use work_item::*;
+#[cfg(not(driver_type = "umdf"))]
+pub use wmi::*;
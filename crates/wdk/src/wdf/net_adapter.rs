@@ -0,0 +1,1082 @@
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use core::{marker::PhantomData, ptr::NonNull};
+
+use wdk_sys::{
+    netadaptercx::{
+        self,
+        NETADAPTER,
+        NETADAPTER_INIT,
+        NETPACKETQUEUE,
+        NETRXQUEUE_INIT,
+        NETTXQUEUE_INIT,
+        NET_ADAPTER_DATAPATH_CALLBACKS,
+        NET_ADAPTER_LINK_LAYER_CAPABILITIES,
+        NET_FRAGMENT,
+        NET_PACKET,
+        NET_PACKET_QUEUE_CONFIG,
+        NET_RING,
+        NET_RING_COLLECTION,
+    },
+    NTSTATUS,
+    STATUS_INSUFFICIENT_RESOURCES,
+    ULONG,
+    WDFDEVICE,
+    WDFOBJECT,
+    WDF_NO_OBJECT_ATTRIBUTES,
+    WDF_OBJECT_ATTRIBUTES,
+};
+#[cfg(feature = "alloc")]
+use wdk_sys::BOOLEAN;
+
+#[cfg(feature = "alloc")]
+use crate::wdf::{context::BorrowedObject, ObjectContext};
+use crate::{
+    nt_success,
+    wdf::{DeviceInit, FromWdfObject, ObjectHandle},
+};
+
+/// Call the `NetAdapterCx` function whose function pointer type is
+/// `$function_pointer_type` and whose index in the `NetAdapterCx` function
+/// table is `$function_table_index`, with the driver's `NetAdapterCx` driver
+/// globals followed by the `$argument`s. This mirrors the inlined
+/// `NetAdapterCx` functions in the `NetCx` headers (ex. `netadapter.h`), and
+/// must be invoked from an `unsafe` block.
+macro_rules! call_unsafe_net_function {
+    ($function_pointer_type:ident, $function_table_index:ident $(, $argument:expr)* $(,)?) => {{
+        let net_function = netadaptercx::net_function(
+            netadaptercx::_NETFUNCENUM::$function_table_index,
+        );
+        // SAFETY: This `transmute` from a no-argument function pointer to a function
+        // pointer with the correct arguments for the `NetAdapterCx` function is safe
+        // because `NetAdapterCx` maintains the strict mapping between the function
+        // table index and the correct function pointer type.
+        let net_function = unsafe {
+            core::mem::transmute::<netadaptercx::NETFUNC, netadaptercx::$function_pointer_type>(
+                net_function,
+            )
+        };
+        let Some(net_function) = net_function else {
+            unreachable!("Option should never be None");
+        };
+        // The function pointer is always valid because it is an entry in the
+        // `NetAdapterCx` function table, guarded by the type-safety of
+        // `$function_pointer_type`. The `unsafe` block that this macro is invoked from
+        // must guarantee that the arguments abide by the rules of the `NetAdapterCx`
+        // function.
+        net_function(netadaptercx::net_driver_globals() $(, $argument)*)
+    }};
+}
+
+/// Index of the packet ring in `NET_RING_COLLECTION::Rings`
+/// (`NetRingTypePacket`)
+const PACKET_RING_INDEX: usize = 0;
+
+/// Index of the fragment ring in `NET_RING_COLLECTION::Rings`
+/// (`NetRingTypeFragment`)
+const FRAGMENT_RING_INDEX: usize = 1;
+
+/// `NetAdapterCx` Network Adapter.
+///
+/// A net adapter represents a network interface that is exposed to the
+/// networking stack by a NIC driver. Its device must be configured via
+/// [`NetAdapter::configure_device_init`] before it is created. The adapter is
+/// started via [`NetAdapter::start`] (typically at the end of
+/// `EvtDriverDeviceAdd`), after which `NetAdapterCx` requests the creation of
+/// its transmit and receive queues (see [`NetAdapterDatapathHandler`]). The
+/// framework deletes the adapter when its parent device is deleted.
+pub struct NetAdapter {
+    net_adapter: NETADAPTER,
+}
+
+/// Callbacks invoked by `NetAdapterCx` to create the packet queues of a
+/// [`NetAdapter`] created via [`NetAdapter::create`].
+///
+/// Both callbacks are expected to create a [`NetPacketQueue`] from the queue
+/// initialization object that they receive, ex. via
+/// [`NetPacketQueue::create_tx`] and [`NetPacketQueue::create_rx`].
+#[cfg(feature = "alloc")]
+pub trait NetAdapterDatapathHandler: Send + Sync {
+    /// Called by `NetAdapterCx` to create a transmit queue of the adapter
+    ///
+    /// # Errors
+    ///
+    /// The [`NTSTATUS`] of the failure is reported to `NetAdapterCx`, which
+    /// fails the start of the adapter's datapath.
+    fn create_tx_queue(
+        &self,
+        adapter: &NetAdapter,
+        tx_queue_init: TxQueueInit<'_>,
+    ) -> Result<(), NTSTATUS>;
+
+    /// Called by `NetAdapterCx` to create a receive queue of the adapter
+    ///
+    /// # Errors
+    ///
+    /// The [`NTSTATUS`] of the failure is reported to `NetAdapterCx`, which
+    /// fails the start of the adapter's datapath.
+    fn create_rx_queue(
+        &self,
+        adapter: &NetAdapter,
+        rx_queue_init: RxQueueInit<'_>,
+    ) -> Result<(), NTSTATUS>;
+}
+
+#[cfg(feature = "alloc")]
+crate::wdf_declare_context_type!(
+    /// Context stored in every [`NetAdapter`] created via [`NetAdapter::create`]
+    struct NetAdapterContext {
+        handler: Box<dyn NetAdapterDatapathHandler>,
+    }
+);
+
+/// Initialization object of a transmit queue, as passed to
+/// [`NetAdapterDatapathHandler::create_tx_queue`]. It is only valid for the
+/// duration of that callback.
+pub struct TxQueueInit<'a> {
+    tx_queue_init: NonNull<NETTXQUEUE_INIT>,
+    _marker: PhantomData<&'a mut NETTXQUEUE_INIT>,
+}
+
+/// Initialization object of a receive queue, as passed to
+/// [`NetAdapterDatapathHandler::create_rx_queue`]. It is only valid for the
+/// duration of that callback.
+pub struct RxQueueInit<'a> {
+    rx_queue_init: NonNull<NETRXQUEUE_INIT>,
+    _marker: PhantomData<&'a mut NETRXQUEUE_INIT>,
+}
+
+/// Direction of the packets of a [`NetPacketQueue`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PacketQueueDirection {
+    /// The queue transmits packets posted by the networking stack
+    Tx,
+    /// The queue receives packets into buffers posted by the networking stack
+    Rx,
+}
+
+/// `NetAdapterCx` Packet Queue.
+///
+/// A packet queue exchanges packets between the networking stack and the NIC
+/// via a [`NetRingCollection`]. `NetAdapterCx` calls
+/// [`PacketQueueHandler::advance`] whenever the driver should post new packets
+/// to the hardware and return completed packets to the networking stack. The
+/// framework deletes the queue when its parent adapter is deleted.
+pub struct NetPacketQueue {
+    net_packet_queue: NETPACKETQUEUE,
+    direction: PacketQueueDirection,
+}
+
+/// Callbacks invoked by `NetAdapterCx` for a [`NetPacketQueue`] created via
+/// [`NetPacketQueue::create_tx`] or [`NetPacketQueue::create_rx`].
+///
+/// `NetAdapterCx` serializes the callbacks of a queue, so the
+/// [`NetRingCollection`] passed to them is never accessed concurrently.
+#[cfg(feature = "alloc")]
+pub trait PacketQueueHandler: Send + Sync {
+    /// Called by `NetAdapterCx` to post the packets of the ring collection
+    /// that the driver owns to the hardware, and to return the packets that
+    /// the hardware completed to the networking stack
+    fn advance(&self, queue: &NetPacketQueue, rings: &mut NetRingCollection<'_>);
+
+    /// Called by `NetAdapterCx` to enable or disable the notification of the
+    /// queue, which the driver signals via
+    /// [`NetPacketQueue::notify_more_packets_available`] once it completes
+    /// more packets
+    fn set_notification_enabled(&self, queue: &NetPacketQueue, enabled: bool);
+
+    /// Called by `NetAdapterCx` before the queue is stopped, to return every
+    /// packet that the driver owns to the networking stack, ex. by completing
+    /// them as canceled
+    fn cancel(&self, queue: &NetPacketQueue, rings: &mut NetRingCollection<'_>);
+
+    /// Called by `NetAdapterCx` when the queue is started, before the first
+    /// call to [`PacketQueueHandler::advance`]
+    fn start(&self, _queue: &NetPacketQueue) {}
+
+    /// Called by `NetAdapterCx` when the queue is stopped, after the last call
+    /// to [`PacketQueueHandler::advance`]
+    fn stop(&self, _queue: &NetPacketQueue) {}
+}
+
+#[cfg(feature = "alloc")]
+crate::wdf_declare_context_type!(
+    /// Context stored in every [`NetPacketQueue`] created via
+    /// [`NetPacketQueue::create_tx`] or [`NetPacketQueue::create_rx`]
+    struct PacketQueueContext {
+        handler: Box<dyn PacketQueueHandler>,
+        direction: PacketQueueDirection,
+    }
+);
+
+/// The packet and fragment rings of a [`NetPacketQueue`]
+pub struct NetRingCollection<'a> {
+    ring_collection: NonNull<NET_RING_COLLECTION>,
+    _marker: PhantomData<&'a mut NET_RING_COLLECTION>,
+}
+
+/// An element of a [`NetRing`]
+///
+/// # Safety
+///
+/// `Self` must be the type of the elements of the rings of a
+/// `NET_RING_COLLECTION` that [`NetRingCollection`] returns a [`NetRing`] of
+/// `Self` for.
+pub unsafe trait NetRingElement: Sized {}
+
+// SAFETY: The elements of the packet ring are `NET_PACKET`s.
+unsafe impl NetRingElement for NET_PACKET {}
+
+// SAFETY: The elements of the fragment ring are `NET_FRAGMENT`s.
+unsafe impl NetRingElement for NET_FRAGMENT {}
+
+/// A circular buffer of packets or fragments, which is shared by the
+/// networking stack and the driver.
+///
+/// The driver owns the elements from the begin index up to (but excluding) the
+/// end index. The elements from the next index up to the end index have not
+/// been posted to the hardware yet (see [`NetRing::post_elements`]), while the
+/// elements from the begin index up to the next index have been posted, and
+/// are returned to the networking stack once the hardware completes them (see
+/// [`NetRing::drain_elements`] and [`NetRing::set_begin_index`]).
+///
+/// Indices wrap around at the number of elements of the ring, which is always
+/// a power of two.
+pub struct NetRing<'a, T: NetRingElement> {
+    ring: NonNull<NET_RING>,
+    _marker: PhantomData<&'a mut T>,
+}
+
+/// Iterator over a range of the elements of a [`NetRing`], which yields the
+/// index of each element along with a mutable reference to it
+pub struct NetRingIterMut<'a, T: NetRingElement> {
+    ring: NonNull<NET_RING>,
+    index: u32,
+    end_index: u32,
+    _marker: PhantomData<&'a mut T>,
+}
+
+impl NetAdapter {
+    /// Configure the device that is being initialized by `device_init` to
+    /// expose network adapters. This must be called from
+    /// `EvtDriverDeviceAdd`, before the device is created.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `NetAdapterCx` fails to configure the device. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [NetAdapterCx Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/netdevice/nf-netdevice-netdeviceinitconfig#return-value)
+    pub fn configure_device_init(device_init: &mut DeviceInit<'_>) -> Result<(), NTSTATUS> {
+        let nt_status;
+        // SAFETY: `DeviceInit` guarantees that its `PWDFDEVICE_INIT` is valid and has
+        // not been used to create a device yet.
+        unsafe {
+            nt_status = call_unsafe_net_function!(
+                PFN_NETDEVICEINITCONFIG,
+                NetDeviceInitConfigTableIndex,
+                device_init.as_raw(),
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Try to construct a `NetAdapterCx` Network Adapter object for `device`
+    /// whose packet queues are created by the callbacks in
+    /// `datapath_callbacks`. This must be called from `EvtDriverDeviceAdd`,
+    /// after `device` was configured via
+    /// [`NetAdapter::configure_device_init`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `NetAdapterCx` fails to contruct a net adapter. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [NetAdapterCx Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/netadapter/nf-netadapter-netadaptercreate#return-value)
+    pub fn try_new(
+        device: WDFDEVICE,
+        datapath_callbacks: &mut NET_ADAPTER_DATAPATH_CALLBACKS,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        let adapter_init: *mut NETADAPTER_INIT;
+        // SAFETY: `device` is a valid device handle, which is guaranteed by the caller.
+        unsafe {
+            adapter_init = call_unsafe_net_function!(
+                PFN_NETADAPTERINITALLOCATE,
+                NetAdapterInitAllocateTableIndex,
+                device,
+            );
+        }
+        if adapter_init.is_null() {
+            return Err(STATUS_INSUFFICIENT_RESOURCES);
+        }
+
+        // SAFETY: `adapter_init` was allocated above, and has not been used to create
+        // an adapter yet.
+        unsafe {
+            call_unsafe_net_function!(
+                PFN_NETADAPTERINITSETDATAPATHCALLBACKS,
+                NetAdapterInitSetDatapathCallbacksTableIndex,
+                adapter_init,
+                core::ptr::from_mut(datapath_callbacks),
+            );
+        }
+
+        let mut adapter = Self {
+            net_adapter: core::ptr::null_mut(),
+        };
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = call_unsafe_net_function!(
+                PFN_NETADAPTERCREATE,
+                NetAdapterCreateTableIndex,
+                adapter_init,
+                attributes.map_or(WDF_NO_OBJECT_ATTRIBUTES, core::ptr::from_mut),
+                &mut adapter.net_adapter,
+            );
+        }
+
+        // The adapter initialization object must be freed whether or not the adapter
+        // was created
+        // SAFETY: `adapter_init` was allocated above, and is not used after this.
+        unsafe {
+            call_unsafe_net_function!(
+                PFN_NETADAPTERINITFREE,
+                NetAdapterInitFreeTableIndex,
+                adapter_init,
+            );
+        }
+
+        nt_success(nt_status).then_some(adapter).ok_or(nt_status)
+    }
+
+    /// Try to construct a `NetAdapterCx` Network Adapter object for `device`
+    /// whose packet queues are created by a [`NetAdapterDatapathHandler`]. This
+    /// must be called from `EvtDriverDeviceAdd`, after `device` was configured
+    /// via [`NetAdapter::configure_device_init`].
+    ///
+    /// `handler` is stored in the adapter's WDF object context space and is
+    /// dropped when the framework destroys the adapter.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `NetAdapterCx` fails to contruct a net adapter. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [NetAdapterCx Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/netadapter/nf-netadapter-netadaptercreate#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn create<H>(device: WDFDEVICE, handler: H) -> Result<Self, NTSTATUS>
+    where
+        H: NetAdapterDatapathHandler + 'static,
+    {
+        let handler: Box<dyn NetAdapterDatapathHandler> = Box::new(handler);
+
+        let mut datapath_callbacks = NET_ADAPTER_DATAPATH_CALLBACKS {
+            // The size of NET_ADAPTER_DATAPATH_CALLBACKS is known to fit in a ULONG
+            #[allow(clippy::cast_possible_truncation)]
+            Size: core::mem::size_of::<NET_ADAPTER_DATAPATH_CALLBACKS>() as ULONG,
+            EvtAdapterCreateTxQueue: Some(evt_net_adapter_create_tx_queue),
+            EvtAdapterCreateRxQueue: Some(evt_net_adapter_create_rx_queue),
+        };
+
+        let mut attributes = NetAdapterContext::object_attributes();
+
+        let adapter = Self::try_new(device, &mut datapath_callbacks, Some(&mut attributes))?;
+        if adapter.init_context(NetAdapterContext { handler }).is_err() {
+            unreachable!("context of a newly created net adapter should be uninitialized");
+        }
+
+        Ok(adapter)
+    }
+
+    /// Start the [`NetAdapter`], which exposes it to the networking stack.
+    /// The link layer capabilities of the adapter (see
+    /// [`NetAdapter::set_link_layer_capabilities`]) must be set before it is
+    /// started.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `NetAdapterCx` fails to start the net adapter. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [NetAdapterCx Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/netadapter/nf-netadapter-netadapterstart#return-value)
+    pub fn start(&self) -> Result<(), NTSTATUS> {
+        let nt_status;
+        // SAFETY: `net_adapter` is a private member of `NetAdapter`, originally created
+        // by NetAdapterCx, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            nt_status = call_unsafe_net_function!(
+                PFN_NETADAPTERSTART,
+                NetAdapterStartTableIndex,
+                self.net_adapter,
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Stop the [`NetAdapter`], which removes it from the networking stack
+    pub fn stop(&self) {
+        // SAFETY: `net_adapter` is a private member of `NetAdapter`, originally created
+        // by NetAdapterCx, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            call_unsafe_net_function!(
+                PFN_NETADAPTERSTOP,
+                NetAdapterStopTableIndex,
+                self.net_adapter,
+            );
+        }
+    }
+
+    /// Set the maximum transmit and receive link speeds of the [`NetAdapter`],
+    /// in bits per second
+    pub fn set_link_layer_capabilities(
+        &self,
+        maximum_tx_link_speed: u64,
+        maximum_rx_link_speed: u64,
+    ) {
+        // Equivalent to NET_ADAPTER_LINK_LAYER_CAPABILITIES_INIT
+        let mut link_layer_capabilities = NET_ADAPTER_LINK_LAYER_CAPABILITIES {
+            // The size of NET_ADAPTER_LINK_LAYER_CAPABILITIES is known to fit in a ULONG
+            #[allow(clippy::cast_possible_truncation)]
+            Size: core::mem::size_of::<NET_ADAPTER_LINK_LAYER_CAPABILITIES>() as ULONG,
+            MaximumTxLinkSpeed: maximum_tx_link_speed,
+            MaximumRxLinkSpeed: maximum_rx_link_speed,
+        };
+
+        // SAFETY: `net_adapter` is a private member of `NetAdapter`, originally created
+        // by NetAdapterCx, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            call_unsafe_net_function!(
+                PFN_NETADAPTERSETLINKLAYERCAPABILITIES,
+                NetAdapterSetLinkLayerCapabilitiesTableIndex,
+                self.net_adapter,
+                &mut link_layer_capabilities,
+            );
+        }
+    }
+
+    /// Set the maximum transmission unit (MTU) of the [`NetAdapter`], in bytes
+    pub fn set_link_layer_mtu_size(&self, mtu_size: ULONG) {
+        // SAFETY: `net_adapter` is a private member of `NetAdapter`, originally created
+        // by NetAdapterCx, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            call_unsafe_net_function!(
+                PFN_NETADAPTERSETLINKLAYERMTUSIZE,
+                NetAdapterSetLinkLayerMtuSizeTableIndex,
+                self.net_adapter,
+                mtu_size,
+            );
+        }
+    }
+}
+
+// SAFETY: `net_adapter` is a private member of `NetAdapter`, originally created
+// by NetAdapterCx, and this module guarantees that it is always in a valid
+// state.
+unsafe impl ObjectHandle for NetAdapter {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.net_adapter.cast()
+    }
+}
+
+// SAFETY: `NetAdapter` does not delete the underlying object when dropped.
+unsafe impl FromWdfObject for NetAdapter {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            net_adapter: wdf_object.cast(),
+        }
+    }
+}
+
+impl TxQueueInit<'_> {
+    /// Get the raw `NETTXQUEUE_INIT` pointer, for use with `NetAdapterCx`
+    /// functions that are not wrapped by this crate (ex.
+    /// `NetTxQueueInitGetQueueId`)
+    #[must_use]
+    pub const fn as_raw(&self) -> *mut NETTXQUEUE_INIT {
+        self.tx_queue_init.as_ptr()
+    }
+}
+
+impl RxQueueInit<'_> {
+    /// Get the raw `NETRXQUEUE_INIT` pointer, for use with `NetAdapterCx`
+    /// functions that are not wrapped by this crate (ex.
+    /// `NetRxQueueInitGetQueueId`)
+    #[must_use]
+    pub const fn as_raw(&self) -> *mut NETRXQUEUE_INIT {
+        self.rx_queue_init.as_ptr()
+    }
+}
+
+impl NetPacketQueue {
+    /// Try to construct a `NetAdapterCx` transmit queue from `tx_queue_init`.
+    /// This must be called from
+    /// [`NetAdapterDatapathHandler::create_tx_queue`] (or the
+    /// `EvtAdapterCreateTxQueue` callback it implements).
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `NetAdapterCx` fails to contruct a packet queue. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [NetAdapterCx Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/nettxqueue/nf-nettxqueue-nettxqueuecreate#return-value)
+    pub fn try_new_tx(
+        tx_queue_init: TxQueueInit<'_>,
+        packet_queue_config: &mut NET_PACKET_QUEUE_CONFIG,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        let mut packet_queue = Self {
+            net_packet_queue: core::ptr::null_mut(),
+            direction: PacketQueueDirection::Tx,
+        };
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state. `tx_queue_init` is valid for the duration of the
+        // callback that it was passed to.
+        unsafe {
+            nt_status = call_unsafe_net_function!(
+                PFN_NETTXQUEUECREATE,
+                NetTxQueueCreateTableIndex,
+                tx_queue_init.as_raw(),
+                attributes.map_or(WDF_NO_OBJECT_ATTRIBUTES, core::ptr::from_mut),
+                packet_queue_config,
+                &mut packet_queue.net_packet_queue,
+            );
+        }
+        nt_success(nt_status)
+            .then_some(packet_queue)
+            .ok_or(nt_status)
+    }
+
+    /// Try to construct a `NetAdapterCx` receive queue from `rx_queue_init`.
+    /// This must be called from
+    /// [`NetAdapterDatapathHandler::create_rx_queue`] (or the
+    /// `EvtAdapterCreateRxQueue` callback it implements).
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `NetAdapterCx` fails to contruct a packet queue. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [NetAdapterCx Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/netrxqueue/nf-netrxqueue-netrxqueuecreate#return-value)
+    pub fn try_new_rx(
+        rx_queue_init: RxQueueInit<'_>,
+        packet_queue_config: &mut NET_PACKET_QUEUE_CONFIG,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        let mut packet_queue = Self {
+            net_packet_queue: core::ptr::null_mut(),
+            direction: PacketQueueDirection::Rx,
+        };
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state. `rx_queue_init` is valid for the duration of the
+        // callback that it was passed to.
+        unsafe {
+            nt_status = call_unsafe_net_function!(
+                PFN_NETRXQUEUECREATE,
+                NetRxQueueCreateTableIndex,
+                rx_queue_init.as_raw(),
+                attributes.map_or(WDF_NO_OBJECT_ATTRIBUTES, core::ptr::from_mut),
+                packet_queue_config,
+                &mut packet_queue.net_packet_queue,
+            );
+        }
+        nt_success(nt_status)
+            .then_some(packet_queue)
+            .ok_or(nt_status)
+    }
+
+    /// Try to construct a `NetAdapterCx` transmit queue from `tx_queue_init`,
+    /// whose callbacks are handled by a [`PacketQueueHandler`]. This must be
+    /// called from [`NetAdapterDatapathHandler::create_tx_queue`].
+    ///
+    /// `handler` is stored in the queue's WDF object context space and is
+    /// dropped when the framework destroys the queue.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `NetAdapterCx` fails to contruct a packet queue. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [NetAdapterCx Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/nettxqueue/nf-nettxqueue-nettxqueuecreate#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn create_tx<H>(tx_queue_init: TxQueueInit<'_>, handler: H) -> Result<Self, NTSTATUS>
+    where
+        H: PacketQueueHandler + 'static,
+    {
+        let mut packet_queue_config = packet_queue_config();
+        let mut attributes = PacketQueueContext::object_attributes();
+        let packet_queue = Self::try_new_tx(
+            tx_queue_init,
+            &mut packet_queue_config,
+            Some(&mut attributes),
+        )?;
+        packet_queue.init_handler(handler);
+        Ok(packet_queue)
+    }
+
+    /// Try to construct a `NetAdapterCx` receive queue from `rx_queue_init`,
+    /// whose callbacks are handled by a [`PacketQueueHandler`]. This must be
+    /// called from [`NetAdapterDatapathHandler::create_rx_queue`].
+    ///
+    /// `handler` is stored in the queue's WDF object context space and is
+    /// dropped when the framework destroys the queue.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `NetAdapterCx` fails to contruct a packet queue. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [NetAdapterCx Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/netrxqueue/nf-netrxqueue-netrxqueuecreate#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn create_rx<H>(rx_queue_init: RxQueueInit<'_>, handler: H) -> Result<Self, NTSTATUS>
+    where
+        H: PacketQueueHandler + 'static,
+    {
+        let mut packet_queue_config = packet_queue_config();
+        let mut attributes = PacketQueueContext::object_attributes();
+        let packet_queue = Self::try_new_rx(
+            rx_queue_init,
+            &mut packet_queue_config,
+            Some(&mut attributes),
+        )?;
+        packet_queue.init_handler(handler);
+        Ok(packet_queue)
+    }
+
+    /// Get the direction of the packets of the [`NetPacketQueue`]
+    #[must_use]
+    pub const fn direction(&self) -> PacketQueueDirection {
+        self.direction
+    }
+
+    /// Get the packet and fragment rings of the [`NetPacketQueue`]
+    ///
+    /// # Safety
+    ///
+    /// The rings are shared with the networking stack, and may only be
+    /// accessed from the callbacks of the queue, which `NetAdapterCx`
+    /// serializes. No other [`NetRingCollection`] of the queue may exist for
+    /// the lifetime of the returned one. Queues created via
+    /// [`NetPacketQueue::create_tx`] or [`NetPacketQueue::create_rx`] receive
+    /// their ring collection in the [`PacketQueueHandler`] callbacks instead.
+    #[must_use]
+    pub unsafe fn ring_collection(&self) -> NetRingCollection<'_> {
+        let ring_collection: *const NET_RING_COLLECTION = match self.direction {
+            PacketQueueDirection::Tx => {
+                // SAFETY: `net_packet_queue` is a private member of `NetPacketQueue`,
+                // originally created by NetAdapterCx, and this module guarantees that it
+                // is always in a valid state.
+                unsafe {
+                    call_unsafe_net_function!(
+                        PFN_NETTXQUEUEGETRINGCOLLECTION,
+                        NetTxQueueGetRingCollectionTableIndex,
+                        self.net_packet_queue,
+                    )
+                }
+            }
+            PacketQueueDirection::Rx => {
+                // SAFETY: `net_packet_queue` is a private member of `NetPacketQueue`,
+                // originally created by NetAdapterCx, and this module guarantees that it
+                // is always in a valid state.
+                unsafe {
+                    call_unsafe_net_function!(
+                        PFN_NETRXQUEUEGETRINGCOLLECTION,
+                        NetRxQueueGetRingCollectionTableIndex,
+                        self.net_packet_queue,
+                    )
+                }
+            }
+        };
+        NetRingCollection {
+            ring_collection: NonNull::new(ring_collection.cast_mut())
+                .expect("NetAdapterCx should return the ring collection of a valid queue"),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Notify `NetAdapterCx` that the hardware completed more packets (for
+    /// transmit queues) or received more packets (for receive queues). This
+    /// must only be called while the notification of the queue is enabled
+    /// (see [`PacketQueueHandler::set_notification_enabled`]).
+    pub fn notify_more_packets_available(&self) {
+        match self.direction {
+            PacketQueueDirection::Tx => {
+                // SAFETY: `net_packet_queue` is a private member of `NetPacketQueue`,
+                // originally created by NetAdapterCx, and this module guarantees that it
+                // is always in a valid state.
+                unsafe {
+                    call_unsafe_net_function!(
+                        PFN_NETTXQUEUENOTIFYMORECOMPLETEDPACKETSAVAILABLE,
+                        NetTxQueueNotifyMoreCompletedPacketsAvailableTableIndex,
+                        self.net_packet_queue,
+                    );
+                }
+            }
+            PacketQueueDirection::Rx => {
+                // SAFETY: `net_packet_queue` is a private member of `NetPacketQueue`,
+                // originally created by NetAdapterCx, and this module guarantees that it
+                // is always in a valid state.
+                unsafe {
+                    call_unsafe_net_function!(
+                        PFN_NETRXQUEUENOTIFYMORERECEIVEDPACKETSAVAILABLE,
+                        NetRxQueueNotifyMoreReceivedPacketsAvailableTableIndex,
+                        self.net_packet_queue,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Initialize the [`PacketQueueContext`] of a newly created queue
+    #[cfg(feature = "alloc")]
+    fn init_handler<H>(&self, handler: H)
+    where
+        H: PacketQueueHandler + 'static,
+    {
+        let context = PacketQueueContext {
+            handler: Box::new(handler),
+            direction: self.direction,
+        };
+        if self.init_context(context).is_err() {
+            unreachable!("context of a newly created packet queue should be uninitialized");
+        }
+    }
+}
+
+// SAFETY: `net_packet_queue` is a private member of `NetPacketQueue`,
+// originally created by NetAdapterCx, and this module guarantees that it is
+// always in a valid state.
+unsafe impl ObjectHandle for NetPacketQueue {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.net_packet_queue.cast()
+    }
+}
+
+impl NetRingCollection<'_> {
+    /// Get the ring of the packets of the queue
+    pub fn packet_ring(&mut self) -> NetRing<'_, NET_PACKET> {
+        // SAFETY: `NetPacketQueue::ring_collection` guarantees that the ring collection
+        // is valid and not accessed elsewhere, and the packet ring is a `NET_PACKET`
+        // ring.
+        unsafe { self.ring(PACKET_RING_INDEX) }
+    }
+
+    /// Get the ring of the fragments of the packets of the queue
+    pub fn fragment_ring(&mut self) -> NetRing<'_, NET_FRAGMENT> {
+        // SAFETY: `NetPacketQueue::ring_collection` guarantees that the ring collection
+        // is valid and not accessed elsewhere, and the fragment ring is a
+        // `NET_FRAGMENT` ring.
+        unsafe { self.ring(FRAGMENT_RING_INDEX) }
+    }
+
+    /// Get both the packet ring and the fragment ring of the queue, since the
+    /// packets refer to their fragments by their index in the fragment ring
+    pub fn rings(&mut self) -> (NetRing<'_, NET_PACKET>, NetRing<'_, NET_FRAGMENT>) {
+        // SAFETY: `NetPacketQueue::ring_collection` guarantees that the ring collection
+        // is valid and not accessed elsewhere. The packet ring is a ring of
+        // `NET_PACKET`s, distinct from the fragment ring.
+        let packet_ring = unsafe { self.ring(PACKET_RING_INDEX) };
+        // SAFETY: `NetPacketQueue::ring_collection` guarantees that the ring collection
+        // is valid and not accessed elsewhere. The fragment ring is a ring of
+        // `NET_FRAGMENT`s, distinct from the packet ring.
+        let fragment_ring = unsafe { self.ring(FRAGMENT_RING_INDEX) };
+        (packet_ring, fragment_ring)
+    }
+
+    /// Get the ring at `ring_index` of the ring collection
+    ///
+    /// # Safety
+    ///
+    /// The ring at `ring_index` must be a ring of `T`s, and no other
+    /// [`NetRing`] of it may exist for the lifetime of the returned one.
+    unsafe fn ring<T: NetRingElement>(&self, ring_index: usize) -> NetRing<'_, T> {
+        // SAFETY: `NetPacketQueue::ring_collection` guarantees that the ring collection
+        // is valid for the lifetime of `self`.
+        let ring = unsafe { self.ring_collection.as_ref() }.Rings[ring_index];
+        NetRing {
+            ring: NonNull::new(ring).expect("NetAdapterCx should allocate every ring of a queue"),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: NetRingElement> NetRing<'_, T> {
+    /// Get the shared `NET_RING`
+    fn raw(&self) -> &NET_RING {
+        // SAFETY: `NetRingCollection` guarantees that the ring is valid and not
+        // accessed elsewhere for the lifetime of `self`.
+        unsafe { self.ring.as_ref() }
+    }
+
+    /// Get the index of the first element that the driver owns
+    #[must_use]
+    pub fn begin_index(&self) -> u32 {
+        self.raw().BeginIndex
+    }
+
+    /// Get the index of the first element that the driver has not posted to
+    /// the hardware yet
+    #[must_use]
+    pub fn next_index(&self) -> u32 {
+        self.raw().NextIndex
+    }
+
+    /// Get the index after the last element that the driver owns
+    #[must_use]
+    pub fn end_index(&self) -> u32 {
+        self.raw().EndIndex
+    }
+
+    /// Return the elements before `index` to the networking stack. `index`
+    /// must be between the begin index and the next index of the ring.
+    pub fn set_begin_index(&mut self, index: u32) {
+        let index = index & self.raw().ElementIndexMask;
+        // SAFETY: `NetRingCollection` guarantees that the ring is valid and not
+        // accessed elsewhere for the lifetime of `self`.
+        unsafe { self.ring.as_mut() }.BeginIndex = index;
+    }
+
+    /// Record that the elements before `index` were posted to the hardware.
+    /// `index` must be between the next index and the end index of the ring.
+    pub fn set_next_index(&mut self, index: u32) {
+        let index = index & self.raw().ElementIndexMask;
+        // SAFETY: `NetRingCollection` guarantees that the ring is valid and not
+        // accessed elsewhere for the lifetime of `self`.
+        unsafe { self.ring.as_mut() }.NextIndex = index;
+    }
+
+    /// Get the index that follows `index`, which wraps around at the number of
+    /// elements of the ring
+    #[must_use]
+    pub fn increment_index(&self, index: u32) -> u32 {
+        index.wrapping_add(1) & self.raw().ElementIndexMask
+    }
+
+    /// Get the number of elements from `start_index` up to (but excluding)
+    /// `end_index`
+    #[must_use]
+    pub fn range_count(&self, start_index: u32, end_index: u32) -> u32 {
+        end_index.wrapping_sub(start_index) & self.raw().ElementIndexMask
+    }
+
+    /// Get a reference to the element at `index`
+    #[must_use]
+    pub fn element(&self, index: u32) -> &T {
+        // SAFETY: `element_ptr` returns a pointer to an element of the ring, which
+        // `NetRingCollection` guarantees is not accessed elsewhere for the lifetime of
+        // `self`.
+        unsafe { &*element_ptr(self.ring, index) }
+    }
+
+    /// Get a mutable reference to the element at `index`
+    pub fn element_mut(&mut self, index: u32) -> &mut T {
+        // SAFETY: `element_ptr` returns a pointer to an element of the ring, which
+        // `NetRingCollection` guarantees is not accessed elsewhere for the lifetime of
+        // `self`.
+        unsafe { &mut *element_ptr(self.ring, index) }
+    }
+
+    /// Iterate over the elements from the next index up to the end index,
+    /// which the driver has not posted to the hardware yet. Once they are
+    /// posted, the next index should be advanced via
+    /// [`NetRing::set_next_index`].
+    pub fn post_elements(&mut self) -> NetRingIterMut<'_, T> {
+        let (next_index, end_index) = (self.next_index(), self.end_index());
+        self.iter_range_mut(next_index, end_index)
+    }
+
+    /// Iterate over the elements from the begin index up to the next index,
+    /// which the driver posted to the hardware. Once the hardware completes
+    /// them, they should be returned to the networking stack via
+    /// [`NetRing::set_begin_index`].
+    pub fn drain_elements(&mut self) -> NetRingIterMut<'_, T> {
+        let (begin_index, next_index) = (self.begin_index(), self.next_index());
+        self.iter_range_mut(begin_index, next_index)
+    }
+
+    /// Iterate over the elements from `start_index` up to (but excluding)
+    /// `end_index`
+    pub fn iter_range_mut(&mut self, start_index: u32, end_index: u32) -> NetRingIterMut<'_, T> {
+        let element_index_mask = self.raw().ElementIndexMask;
+        NetRingIterMut {
+            ring: self.ring,
+            index: start_index & element_index_mask,
+            end_index: end_index & element_index_mask,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: NetRingElement> Iterator for NetRingIterMut<'a, T> {
+    type Item = (u32, &'a mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index == self.end_index {
+            return None;
+        }
+
+        let index = self.index;
+        // SAFETY: `NetRingCollection` guarantees that the ring is valid and not
+        // accessed elsewhere for the lifetime of the `NetRing` that this iterator
+        // borrows.
+        let element_index_mask = unsafe { self.ring.as_ref() }.ElementIndexMask;
+        self.index = index.wrapping_add(1) & element_index_mask;
+
+        // SAFETY: `element_ptr` returns a pointer to an element of the ring, which is
+        // not accessed elsewhere while this iterator borrows its `NetRing`. Each
+        // element is yielded at most once, since iteration stops at `end_index`.
+        Some((index, unsafe { &mut *element_ptr(self.ring, index) }))
+    }
+}
+
+/// Get a pointer to the element at `index` of `ring`, which wraps around at the
+/// number of elements of the ring. This is equivalent to
+/// `NetRingGetElementAtIndex`.
+///
+/// # Safety
+///
+/// `ring` must point to a valid `NET_RING` whose elements are `T`s.
+unsafe fn element_ptr<T: NetRingElement>(ring: NonNull<NET_RING>, index: u32) -> *mut T {
+    // SAFETY: The caller guarantees that `ring` is valid.
+    let ring_ref = unsafe { ring.as_ref() };
+    let offset = (index & ring_ref.ElementIndexMask) as usize * usize::from(ring_ref.ElementStride);
+    // SAFETY: The caller guarantees that `ring` is valid.
+    let buffer = unsafe { core::ptr::addr_of_mut!((*ring.as_ptr()).Buffer) }.cast::<u8>();
+    // SAFETY: Masking `index` keeps `offset` within the element buffer that follows
+    // the `NET_RING` header, whose elements are `ElementStride` bytes apart.
+    unsafe { buffer.add(offset) }.cast::<T>()
+}
+
+/// Construct a `NET_PACKET_QUEUE_CONFIG` whose callbacks forward to the
+/// [`PacketQueueHandler`] stored in the queue's context space. This is
+/// equivalent to `NET_PACKET_QUEUE_CONFIG_INIT`, with the optional start and
+/// stop callbacks also assigned.
+#[cfg(feature = "alloc")]
+fn packet_queue_config() -> NET_PACKET_QUEUE_CONFIG {
+    NET_PACKET_QUEUE_CONFIG {
+        // The size of NET_PACKET_QUEUE_CONFIG is known to fit in a ULONG
+        #[allow(clippy::cast_possible_truncation)]
+        Size: core::mem::size_of::<NET_PACKET_QUEUE_CONFIG>() as ULONG,
+        EvtStart: Some(evt_packet_queue_start),
+        EvtStop: Some(evt_packet_queue_stop),
+        EvtAdvance: Some(evt_packet_queue_advance),
+        EvtSetNotificationEnabled: Some(evt_packet_queue_set_notification_enabled),
+        EvtCancel: Some(evt_packet_queue_cancel),
+    }
+}
+
+/// Reconstruct the [`NetPacketQueue`] passed to a packet queue callback, along
+/// with its [`PacketQueueContext`]
+#[cfg(feature = "alloc")]
+fn packet_queue_with_context(
+    net_packet_queue: NETPACKETQUEUE,
+    callback: impl FnOnce(&NetPacketQueue, &PacketQueueContext),
+) {
+    // The direction of the queue is only known once its context is retrieved
+    let borrowed_queue = BorrowedObject(net_packet_queue.cast());
+    let Some(context) = borrowed_queue.context::<PacketQueueContext>() else {
+        return;
+    };
+    let packet_queue = NetPacketQueue {
+        net_packet_queue,
+        direction: context.direction,
+    };
+    callback(&packet_queue, context);
+}
+
+/// `EvtAdapterCreateTxQueue` trampoline that forwards to the
+/// [`NetAdapterDatapathHandler`] stored in the adapter's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_net_adapter_create_tx_queue(
+    net_adapter: NETADAPTER,
+    tx_queue_init: *mut NETTXQUEUE_INIT,
+) -> NTSTATUS {
+    let adapter = NetAdapter { net_adapter };
+    let (Some(context), Some(tx_queue_init)) = (
+        adapter.context::<NetAdapterContext>(),
+        NonNull::new(tx_queue_init),
+    ) else {
+        return STATUS_INSUFFICIENT_RESOURCES;
+    };
+    let tx_queue_init = TxQueueInit {
+        tx_queue_init,
+        _marker: PhantomData,
+    };
+    match context.handler.create_tx_queue(&adapter, tx_queue_init) {
+        Ok(()) => wdk_sys::STATUS_SUCCESS,
+        Err(nt_status) => nt_status,
+    }
+}
+
+/// `EvtAdapterCreateRxQueue` trampoline that forwards to the
+/// [`NetAdapterDatapathHandler`] stored in the adapter's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_net_adapter_create_rx_queue(
+    net_adapter: NETADAPTER,
+    rx_queue_init: *mut NETRXQUEUE_INIT,
+) -> NTSTATUS {
+    let adapter = NetAdapter { net_adapter };
+    let (Some(context), Some(rx_queue_init)) = (
+        adapter.context::<NetAdapterContext>(),
+        NonNull::new(rx_queue_init),
+    ) else {
+        return STATUS_INSUFFICIENT_RESOURCES;
+    };
+    let rx_queue_init = RxQueueInit {
+        rx_queue_init,
+        _marker: PhantomData,
+    };
+    match context.handler.create_rx_queue(&adapter, rx_queue_init) {
+        Ok(()) => wdk_sys::STATUS_SUCCESS,
+        Err(nt_status) => nt_status,
+    }
+}
+
+/// `EvtPacketQueueStart` trampoline that forwards to the
+/// [`PacketQueueHandler`] stored in the queue's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_packet_queue_start(net_packet_queue: NETPACKETQUEUE) {
+    packet_queue_with_context(net_packet_queue, |packet_queue, context| {
+        context.handler.start(packet_queue);
+    });
+}
+
+/// `EvtPacketQueueStop` trampoline that forwards to the [`PacketQueueHandler`]
+/// stored in the queue's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_packet_queue_stop(net_packet_queue: NETPACKETQUEUE) {
+    packet_queue_with_context(net_packet_queue, |packet_queue, context| {
+        context.handler.stop(packet_queue);
+    });
+}
+
+/// `EvtPacketQueueAdvance` trampoline that forwards to the
+/// [`PacketQueueHandler`] stored in the queue's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_packet_queue_advance(net_packet_queue: NETPACKETQUEUE) {
+    packet_queue_with_context(net_packet_queue, |packet_queue, context| {
+        // SAFETY: NetAdapterCx serializes the callbacks of the queue, and this is the
+        // only ring collection of the queue that exists during this callback.
+        let mut rings = unsafe { packet_queue.ring_collection() };
+        context.handler.advance(packet_queue, &mut rings);
+    });
+}
+
+/// `EvtPacketQueueSetNotificationEnabled` trampoline that forwards to the
+/// [`PacketQueueHandler`] stored in the queue's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_packet_queue_set_notification_enabled(
+    net_packet_queue: NETPACKETQUEUE,
+    notification_enabled: BOOLEAN,
+) {
+    packet_queue_with_context(net_packet_queue, |packet_queue, context| {
+        context
+            .handler
+            .set_notification_enabled(packet_queue, notification_enabled != 0);
+    });
+}
+
+/// `EvtPacketQueueCancel` trampoline that forwards to the
+/// [`PacketQueueHandler`] stored in the queue's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_packet_queue_cancel(net_packet_queue: NETPACKETQUEUE) {
+    packet_queue_with_context(net_packet_queue, |packet_queue, context| {
+        // SAFETY: NetAdapterCx serializes the callbacks of the queue, and this is the
+        // only ring collection of the queue that exists during this callback.
+        let mut rings = unsafe { packet_queue.ring_collection() };
+        context.handler.cancel(packet_queue, &mut rings);
+    });
+}
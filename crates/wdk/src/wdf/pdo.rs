@@ -0,0 +1,277 @@
+use wdk_sys::{
+    macros,
+    LCID,
+    NTSTATUS,
+    PWDFDEVICE_INIT,
+    STATUS_INSUFFICIENT_RESOURCES,
+    WDFDEVICE,
+    WDF_NO_OBJECT_ATTRIBUTES,
+    WDF_OBJECT_ATTRIBUTES,
+};
+
+use crate::{
+    nt_success,
+    string::NtUnicodeStr,
+    wdf::{
+        device_init::{set_file_object_policy, set_io_type},
+        DeviceIoType,
+        FileObjectPolicy,
+    },
+};
+
+/// Builder for the initialization of a physical device object (PDO) that is
+/// enumerated by a bus driver.
+///
+/// A [`PdoInitBuilder`] is either allocated by the bus driver for a statically
+/// enumerated child via [`PdoInitBuilder::allocate`], or provided by the
+/// framework to
+/// [`ChildListCallbacks::on_create_device`](crate::wdf::ChildListCallbacks::on_create_device)
+/// for a dynamically enumerated child. Once the identifiers of the child have
+/// been assigned, the PDO is created via [`PdoInitBuilder::create_device`].
+pub struct PdoInitBuilder {
+    device_init: PWDFDEVICE_INIT,
+    // Whether `device_init` was allocated by `WdfPdoInitAllocate`, and therefore
+    // must be freed by the driver if the device is never created
+    allocated: bool,
+}
+
+impl PdoInitBuilder {
+    /// Allocate a `WDFDEVICE_INIT` for a statically enumerated child of
+    /// `parent_device`. The child must be reported to the framework via
+    /// `WdfFdoAddStaticChild` once it has been created.
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INSUFFICIENT_RESOURCES` if WDF fails
+    /// to allocate the `WDFDEVICE_INIT`.
+    pub fn allocate(parent_device: WDFDEVICE) -> Result<Self, NTSTATUS> {
+        let device_init;
+        // SAFETY: `WdfPdoInitAllocate` has no preconditions other than a valid
+        // parent device handle, and the returned `WDFDEVICE_INIT` is stored in a
+        // private member that is freed on drop unless a device is created from it.
+        unsafe {
+            device_init =
+                macros::call_unsafe_wdf_function_binding!(WdfPdoInitAllocate, parent_device);
+        }
+
+        if device_init.is_null() {
+            return Err(STATUS_INSUFFICIENT_RESOURCES);
+        }
+
+        Ok(Self {
+            device_init,
+            allocated: true,
+        })
+    }
+
+    /// Construct a [`PdoInitBuilder`] from a `WDFDEVICE_INIT` that is owned by
+    /// the framework, such as the one passed to `EvtChildListCreateDevice`
+    ///
+    /// # Safety
+    ///
+    /// `device_init` must be a valid `PWDFDEVICE_INIT` for a PDO that is owned
+    /// by the framework, and must not have been passed to `WdfDeviceCreate`
+    /// yet.
+    #[must_use]
+    pub const unsafe fn from_raw(device_init: PWDFDEVICE_INIT) -> Self {
+        Self {
+            device_init,
+            allocated: false,
+        }
+    }
+
+    /// Get the underlying `PWDFDEVICE_INIT`, ex. to pass it to
+    /// [`FileObject::configure_device_init`](crate::wdf::FileObject::configure_device_init)
+    /// or to other `WdfDeviceInitXxx` functions. The returned pointer must not
+    /// be passed to `WdfDeviceCreate` or `WdfDeviceInitFree`.
+    #[must_use]
+    pub const fn as_raw(&mut self) -> PWDFDEVICE_INIT {
+        self.device_init
+    }
+
+    /// Assign the device ID of the child
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to assign the device ID. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFPdo Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfpdo/nf-wdfpdo-wdfpdoinitassigndeviceid#return-value)
+    pub fn device_id(&mut self, device_id: NtUnicodeStr<'_>) -> Result<&mut Self, NTSTATUS> {
+        let nt_status;
+        // SAFETY: `device_init` is a private member of `PdoInitBuilder`, which is
+        // guaranteed to be valid until it is passed to `WdfDeviceCreate`.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfPdoInitAssignDeviceID,
+                self.device_init,
+                device_id.as_raw(),
+            );
+        }
+        nt_success(nt_status).then_some(self).ok_or(nt_status)
+    }
+
+    /// Assign the instance ID of the child
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to assign the instance ID. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFPdo Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfpdo/nf-wdfpdo-wdfpdoinitassigninstanceid#return-value)
+    pub fn instance_id(&mut self, instance_id: NtUnicodeStr<'_>) -> Result<&mut Self, NTSTATUS> {
+        let nt_status;
+        // SAFETY: `device_init` is a private member of `PdoInitBuilder`, which is
+        // guaranteed to be valid until it is passed to `WdfDeviceCreate`.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfPdoInitAssignInstanceID,
+                self.device_init,
+                instance_id.as_raw(),
+            );
+        }
+        nt_success(nt_status).then_some(self).ok_or(nt_status)
+    }
+
+    /// Add a hardware ID to the child. Hardware IDs should be added from the
+    /// most to the least specific.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to add the hardware ID. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFPdo Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfpdo/nf-wdfpdo-wdfpdoinitaddhardwareid#return-value)
+    pub fn hardware_id(&mut self, hardware_id: NtUnicodeStr<'_>) -> Result<&mut Self, NTSTATUS> {
+        let nt_status;
+        // SAFETY: `device_init` is a private member of `PdoInitBuilder`, which is
+        // guaranteed to be valid until it is passed to `WdfDeviceCreate`.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfPdoInitAddHardwareID,
+                self.device_init,
+                hardware_id.as_raw(),
+            );
+        }
+        nt_success(nt_status).then_some(self).ok_or(nt_status)
+    }
+
+    /// Add a compatible ID to the child. Compatible IDs should be added from
+    /// the most to the least specific.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to add the compatible ID. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFPdo Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfpdo/nf-wdfpdo-wdfpdoinitaddcompatibleid#return-value)
+    pub fn compatible_id(
+        &mut self,
+        compatible_id: NtUnicodeStr<'_>,
+    ) -> Result<&mut Self, NTSTATUS> {
+        let nt_status;
+        // SAFETY: `device_init` is a private member of `PdoInitBuilder`, which is
+        // guaranteed to be valid until it is passed to `WdfDeviceCreate`.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfPdoInitAddCompatibleID,
+                self.device_init,
+                compatible_id.as_raw(),
+            );
+        }
+        nt_success(nt_status).then_some(self).ok_or(nt_status)
+    }
+
+    /// Add a localized description and location of the child for `locale_id`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to add the device text. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFPdo Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfpdo/nf-wdfpdo-wdfpdoinitadddevicetext#return-value)
+    pub fn device_text(
+        &mut self,
+        device_description: NtUnicodeStr<'_>,
+        device_location: NtUnicodeStr<'_>,
+        locale_id: LCID,
+    ) -> Result<&mut Self, NTSTATUS> {
+        let nt_status;
+        // SAFETY: `device_init` is a private member of `PdoInitBuilder`, which is
+        // guaranteed to be valid until it is passed to `WdfDeviceCreate`.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfPdoInitAddDeviceText,
+                self.device_init,
+                device_description.as_raw(),
+                device_location.as_raw(),
+                locale_id,
+            );
+        }
+        nt_success(nt_status).then_some(self).ok_or(nt_status)
+    }
+
+    /// Set the locale whose device text is used when the system locale has no
+    /// device text of its own
+    pub fn default_locale(&mut self, locale_id: LCID) -> &mut Self {
+        // SAFETY: `device_init` is a private member of `PdoInitBuilder`, which is
+        // guaranteed to be valid until it is passed to `WdfDeviceCreate`.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfPdoInitSetDefaultLocale,
+                self.device_init,
+                locale_id,
+            );
+        }
+        self
+    }
+
+    /// Set how the framework accesses the data buffers of read and write
+    /// requests sent to the child
+    pub fn io_type(&mut self, io_type: DeviceIoType) -> &mut Self {
+        // SAFETY: `device_init` is a private member of `PdoInitBuilder`, which is
+        // guaranteed to be valid until it is passed to `WdfDeviceCreate`.
+        unsafe {
+            set_io_type(self.device_init, io_type);
+        }
+        self
+    }
+
+    /// Set whether the framework creates file objects for the child. This
+    /// replaces the configuration assigned by
+    /// [`FileObject::configure_device_init`](crate::wdf::FileObject::configure_device_init),
+    /// so the two must not be combined.
+    pub fn file_objects(&mut self, file_object_policy: FileObjectPolicy) -> &mut Self {
+        // SAFETY: `device_init` is a private member of `PdoInitBuilder`, which is
+        // guaranteed to be valid until it is passed to `WdfDeviceCreate`.
+        unsafe {
+            set_file_object_policy(self.device_init, file_object_policy);
+        }
+        self
+    }
+
+    /// Create the PDO, consuming this builder
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a device. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDevice Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdevice/nf-wdfdevice-wdfdevicecreate#return-value)
+    pub fn create_device(
+        mut self,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<WDFDEVICE, NTSTATUS> {
+        let mut device: WDFDEVICE = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: `device_init` is a private member of `PdoInitBuilder`, which is
+        // guaranteed to be valid until it is passed to `WdfDeviceCreate`. On success,
+        // `WdfDeviceCreate` takes ownership of `device_init` and sets it to `NULL`, so
+        // it is not freed on drop.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfDeviceCreate,
+                &mut self.device_init,
+                attributes.map_or(WDF_NO_OBJECT_ATTRIBUTES, core::ptr::from_mut),
+                &mut device,
+            );
+        }
+        nt_success(nt_status).then_some(device).ok_or(nt_status)
+    }
+}
+
+impl Drop for PdoInitBuilder {
+    fn drop(&mut self) {
+        if self.allocated && !self.device_init.is_null() {
+            // SAFETY: `device_init` was allocated by `WdfPdoInitAllocate` and was not
+            // consumed by `WdfDeviceCreate`, so the driver is responsible for freeing
+            // it.
+            unsafe {
+                macros::call_unsafe_wdf_function_binding!(WdfDeviceInitFree, self.device_init);
+            }
+        }
+    }
+}
@@ -0,0 +1,378 @@
+use wdk_sys::{
+    macros,
+    _DEVICE_POWER_STATE,
+    _WDF_POWER_POLICY_IDLE_TIMEOUT_TYPE,
+    _WDF_POWER_POLICY_S0_IDLE_CAPABILITIES,
+    _WDF_POWER_POLICY_S0_IDLE_USER_CONTROL,
+    _WDF_POWER_POLICY_SX_WAKE_USER_CONTROL,
+    _WDF_TRI_STATE,
+    DEVICE_POWER_STATE,
+    NTSTATUS,
+    ULONG,
+    WDF_DEVICE_POWER_POLICY_IDLE_SETTINGS,
+    WDF_DEVICE_POWER_POLICY_WAKE_SETTINGS,
+    WDF_POWER_POLICY_IDLE_TIMEOUT_TYPE,
+    WDF_POWER_POLICY_S0_IDLE_CAPABILITIES,
+    WDF_TRI_STATE,
+};
+
+use crate::{nt_success, wdf::Device};
+
+/// Whether a device that is idle in the working (S0) system state can wake
+/// itself, which determines how the framework powers it down
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IdleCapabilities {
+    /// The device cannot generate a wake signal while it is idle in S0
+    /// (`IdleCannotWakeFromS0`)
+    CannotWakeFromS0,
+    /// The device can generate a wake signal while it is idle in S0
+    /// (`IdleCanWakeFromS0`)
+    CanWakeFromS0,
+    /// The device is a USB device that supports selective suspend
+    /// (`IdleUsbSelectiveSuspend`)
+    UsbSelectiveSuspend,
+}
+
+/// Low-power device state that the framework puts the device in when it is
+/// idle or armed for wake
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DevicePowerState {
+    /// `PowerDeviceD1`
+    D1,
+    /// `PowerDeviceD2`
+    D2,
+    /// `PowerDeviceD3`
+    D3,
+}
+
+/// Whether users can change a power policy setting via the device's property
+/// sheet in Device Manager
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UserControl {
+    /// Users cannot change the setting
+    DoNotAllow,
+    /// Users can change the setting
+    Allow,
+}
+
+/// Who determines when an idle device is powered down
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IdleTimeoutType {
+    /// The framework powers the device down once the idle timeout has elapsed
+    /// (`DriverManagedIdleTimeout`)
+    DriverManaged,
+    /// The power management framework determines when to power the device
+    /// down (`SystemManagedIdleTimeout`)
+    SystemManaged,
+    /// The power management framework determines when to power the device
+    /// down, using the idle timeout as a hint
+    /// (`SystemManagedIdleTimeoutWithHint`)
+    SystemManagedWithHint,
+}
+
+/// Settings for powering down a device that is idle in the working (S0) system
+/// state, assigned via [`PowerPolicyBuilder::s0_idle`].
+///
+/// Settings that are not set explicitly keep the defaults of
+/// `WDF_DEVICE_POWER_POLICY_IDLE_SETTINGS_INIT`.
+#[derive(Clone, Copy, Debug)]
+#[must_use]
+pub struct S0IdleSettings {
+    idle_settings: WDF_DEVICE_POWER_POLICY_IDLE_SETTINGS,
+}
+
+/// Settings for arming a device to wake the system from a low-power (Sx)
+/// system state, assigned via [`PowerPolicyBuilder::sx_wake`].
+///
+/// Settings that are not set explicitly keep the defaults of
+/// `WDF_DEVICE_POWER_POLICY_WAKE_SETTINGS_INIT`.
+#[derive(Clone, Copy, Debug)]
+#[must_use]
+pub struct SxWakeSettings {
+    wake_settings: WDF_DEVICE_POWER_POLICY_WAKE_SETTINGS,
+}
+
+/// Builder of the power policy of a [`Device`], obtained via
+/// [`Device::power_policy`].
+///
+/// Only the drivers that are the power policy owner of a device (usually its
+/// function driver) may assign its power policy, typically from
+/// `EvtDriverDeviceAdd` after the device has been created:
+///
+/// ```rust, no_run
+/// use wdk::wdf::{Device, IdleCapabilities, S0IdleSettings, SxWakeSettings};
+///
+/// fn configure_power_policy(device: &Device) -> wdk::Result<()> {
+///     device
+///         .power_policy()
+///         .s0_idle(
+///             S0IdleSettings::new(IdleCapabilities::CanWakeFromS0).idle_timeout_ms(10_000),
+///         )
+///         .sx_wake(SxWakeSettings::new())
+///         .assign()
+/// }
+/// ```
+#[must_use]
+pub struct PowerPolicyBuilder<'a> {
+    device: &'a Device,
+    s0_idle_settings: Option<S0IdleSettings>,
+    sx_wake_settings: Option<SxWakeSettings>,
+}
+
+impl IdleCapabilities {
+    /// Get the raw `WDF_POWER_POLICY_S0_IDLE_CAPABILITIES` value
+    const fn as_raw(self) -> WDF_POWER_POLICY_S0_IDLE_CAPABILITIES {
+        match self {
+            Self::CannotWakeFromS0 => _WDF_POWER_POLICY_S0_IDLE_CAPABILITIES::IdleCannotWakeFromS0,
+            Self::CanWakeFromS0 => _WDF_POWER_POLICY_S0_IDLE_CAPABILITIES::IdleCanWakeFromS0,
+            Self::UsbSelectiveSuspend => {
+                _WDF_POWER_POLICY_S0_IDLE_CAPABILITIES::IdleUsbSelectiveSuspend
+            }
+        }
+    }
+}
+
+impl DevicePowerState {
+    /// Get the raw `DEVICE_POWER_STATE` value
+    const fn as_raw(self) -> DEVICE_POWER_STATE {
+        match self {
+            Self::D1 => _DEVICE_POWER_STATE::PowerDeviceD1,
+            Self::D2 => _DEVICE_POWER_STATE::PowerDeviceD2,
+            Self::D3 => _DEVICE_POWER_STATE::PowerDeviceD3,
+        }
+    }
+}
+
+impl IdleTimeoutType {
+    /// Get the raw `WDF_POWER_POLICY_IDLE_TIMEOUT_TYPE` value
+    const fn as_raw(self) -> WDF_POWER_POLICY_IDLE_TIMEOUT_TYPE {
+        match self {
+            Self::DriverManaged => _WDF_POWER_POLICY_IDLE_TIMEOUT_TYPE::DriverManagedIdleTimeout,
+            Self::SystemManaged => _WDF_POWER_POLICY_IDLE_TIMEOUT_TYPE::SystemManagedIdleTimeout,
+            Self::SystemManagedWithHint => {
+                _WDF_POWER_POLICY_IDLE_TIMEOUT_TYPE::SystemManagedIdleTimeoutWithHint
+            }
+        }
+    }
+}
+
+/// Convert `value` to the `WDF_TRI_STATE` that explicitly sets it
+const fn tri_state(value: bool) -> WDF_TRI_STATE {
+    if value {
+        _WDF_TRI_STATE::WdfTrue
+    } else {
+        _WDF_TRI_STATE::WdfFalse
+    }
+}
+
+impl S0IdleSettings {
+    /// Construct [`S0IdleSettings`] for a device with `idle_capabilities`.
+    /// This is equivalent to `WDF_DEVICE_POWER_POLICY_IDLE_SETTINGS_INIT` in
+    /// C, which powers the device down to D3 (or D2 for USB selective
+    /// suspend) after the framework's default idle timeout, and allows users
+    /// to control idling.
+    pub const fn new(idle_capabilities: IdleCapabilities) -> Self {
+        let mut idle_settings = WDF_DEVICE_POWER_POLICY_IDLE_SETTINGS::with_size();
+        idle_settings.IdleCaps = idle_capabilities.as_raw();
+        idle_settings.DxState = match idle_capabilities {
+            IdleCapabilities::CannotWakeFromS0 | IdleCapabilities::CanWakeFromS0 => {
+                DevicePowerState::D3.as_raw()
+            }
+            IdleCapabilities::UsbSelectiveSuspend => DevicePowerState::D2.as_raw(),
+        };
+        // A zero `IdleTimeout` (`IdleTimeoutDefaultValue`) selects the framework's
+        // default idle timeout
+        idle_settings.IdleTimeout = 0;
+        idle_settings.UserControlOfIdleSettings =
+            _WDF_POWER_POLICY_S0_IDLE_USER_CONTROL::IdleAllowUserControl;
+        idle_settings.Enabled = _WDF_TRI_STATE::WdfUseDefault;
+        idle_settings.PowerUpIdleDeviceOnSystemWake = _WDF_TRI_STATE::WdfUseDefault;
+        idle_settings.IdleTimeoutType = IdleTimeoutType::DriverManaged.as_raw();
+        idle_settings.ExcludeD3Cold = _WDF_TRI_STATE::WdfUseDefault;
+        Self { idle_settings }
+    }
+
+    /// Set the low-power state that the device enters when it is idle
+    pub const fn dx_state(mut self, dx_state: DevicePowerState) -> Self {
+        self.idle_settings.DxState = dx_state.as_raw();
+        self
+    }
+
+    /// Set how long, in milliseconds, the device must be idle before it is
+    /// powered down
+    pub const fn idle_timeout_ms(mut self, idle_timeout_ms: ULONG) -> Self {
+        self.idle_settings.IdleTimeout = idle_timeout_ms;
+        self
+    }
+
+    /// Set who determines when the device is powered down after it becomes
+    /// idle
+    pub const fn idle_timeout_type(mut self, idle_timeout_type: IdleTimeoutType) -> Self {
+        self.idle_settings.IdleTimeoutType = idle_timeout_type.as_raw();
+        self
+    }
+
+    /// Set whether users can enable or disable powering down the device when
+    /// it is idle
+    pub const fn user_control(mut self, user_control: UserControl) -> Self {
+        self.idle_settings.UserControlOfIdleSettings = match user_control {
+            UserControl::DoNotAllow => {
+                _WDF_POWER_POLICY_S0_IDLE_USER_CONTROL::IdleDoNotAllowUserControl
+            }
+            UserControl::Allow => _WDF_POWER_POLICY_S0_IDLE_USER_CONTROL::IdleAllowUserControl,
+        };
+        self
+    }
+
+    /// Set whether powering down the device when it is idle is enabled. If
+    /// not set, it is enabled unless the user has disabled it.
+    pub const fn enabled(mut self, enabled: bool) -> Self {
+        self.idle_settings.Enabled = tri_state(enabled);
+        self
+    }
+
+    /// Set whether the framework powers up the device when the system
+    /// returns to S0, even if the device is still idle
+    pub const fn power_up_idle_device_on_system_wake(mut self, power_up: bool) -> Self {
+        self.idle_settings.PowerUpIdleDeviceOnSystemWake = tri_state(power_up);
+        self
+    }
+
+    /// Set whether the device must not enter D3cold when it is idle
+    pub const fn exclude_d3_cold(mut self, exclude_d3_cold: bool) -> Self {
+        self.idle_settings.ExcludeD3Cold = tri_state(exclude_d3_cold);
+        self
+    }
+}
+
+impl SxWakeSettings {
+    /// Construct [`SxWakeSettings`]. This is equivalent to
+    /// `WDF_DEVICE_POWER_POLICY_WAKE_SETTINGS_INIT` in C, which arms the
+    /// device to wake the system from D3, and allows users to control waking.
+    pub const fn new() -> Self {
+        let mut wake_settings = WDF_DEVICE_POWER_POLICY_WAKE_SETTINGS::with_size();
+        wake_settings.DxState = DevicePowerState::D3.as_raw();
+        wake_settings.UserControlOfWakeSettings =
+            _WDF_POWER_POLICY_SX_WAKE_USER_CONTROL::WakeAllowUserControl;
+        wake_settings.Enabled = _WDF_TRI_STATE::WdfUseDefault;
+        Self { wake_settings }
+    }
+
+    /// Set the low-power state that the device enters when the system enters
+    /// a low-power state while the device is armed for wake
+    pub const fn dx_state(mut self, dx_state: DevicePowerState) -> Self {
+        self.wake_settings.DxState = dx_state.as_raw();
+        self
+    }
+
+    /// Set whether users can enable or disable the device's ability to wake
+    /// the system
+    pub const fn user_control(mut self, user_control: UserControl) -> Self {
+        self.wake_settings.UserControlOfWakeSettings = match user_control {
+            UserControl::DoNotAllow => {
+                _WDF_POWER_POLICY_SX_WAKE_USER_CONTROL::WakeDoNotAllowUserControl
+            }
+            UserControl::Allow => _WDF_POWER_POLICY_SX_WAKE_USER_CONTROL::WakeAllowUserControl,
+        };
+        self
+    }
+
+    /// Set whether the device can wake the system. If not set, it is enabled
+    /// unless the user has disabled it.
+    pub const fn enabled(mut self, enabled: bool) -> Self {
+        self.wake_settings.Enabled = tri_state(enabled);
+        self
+    }
+
+    /// Set whether the device is armed for wake whenever any of its child
+    /// devices are armed for wake. This is only meaningful for bus drivers.
+    pub fn arm_for_wake_if_children_are_armed_for_wake(mut self, arm: bool) -> Self {
+        self.wake_settings.ArmForWakeIfChildrenAreArmedForWake = u8::from(arm);
+        self
+    }
+
+    /// Set whether the framework reports a wake signal on each child device
+    /// when the device itself triggers a wake. This is only meaningful for
+    /// bus drivers.
+    pub fn indicate_child_wake_on_parent_wake(mut self, indicate: bool) -> Self {
+        self.wake_settings.IndicateChildWakeOnParentWake = u8::from(indicate);
+        self
+    }
+}
+
+impl Default for SxWakeSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Device {
+    /// Get a [`PowerPolicyBuilder`] to assign the idle and wake settings of
+    /// this device
+    pub const fn power_policy(&self) -> PowerPolicyBuilder<'_> {
+        PowerPolicyBuilder {
+            device: self,
+            s0_idle_settings: None,
+            sx_wake_settings: None,
+        }
+    }
+}
+
+impl PowerPolicyBuilder<'_> {
+    /// Set the settings for powering down the device when it is idle in S0,
+    /// which are assigned via `WdfDeviceAssignS0IdleSettings`
+    pub const fn s0_idle(mut self, s0_idle_settings: S0IdleSettings) -> Self {
+        self.s0_idle_settings = Some(s0_idle_settings);
+        self
+    }
+
+    /// Set the settings for arming the device to wake the system from Sx,
+    /// which are assigned via `WdfDeviceAssignSxWakeSettings`
+    pub const fn sx_wake(mut self, sx_wake_settings: SxWakeSettings) -> Self {
+        self.sx_wake_settings = Some(sx_wake_settings);
+        self
+    }
+
+    /// Assign the settings configured by this builder to the device. The idle
+    /// settings are assigned before the wake settings, and settings that were
+    /// not configured are left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to assign either the idle or the wake settings, in which case the wake settings are not assigned if assigning the idle settings failed. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDevice Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdevice/nf-wdfdevice-wdfdeviceassigns0idlesettings#return-value)
+    pub fn assign(self) -> Result<(), NTSTATUS> {
+        if let Some(mut s0_idle_settings) = self.s0_idle_settings {
+            let nt_status;
+            // SAFETY: `as_raw` returns the `WDFDEVICE` of a `Device`, which is always in
+            // a valid state, and `idle_settings` is initialized with its `Size` set.
+            unsafe {
+                nt_status = macros::call_unsafe_wdf_function_binding!(
+                    WdfDeviceAssignS0IdleSettings,
+                    self.device.as_raw(),
+                    &mut s0_idle_settings.idle_settings,
+                );
+            }
+            if !nt_success(nt_status) {
+                return Err(nt_status);
+            }
+        }
+
+        if let Some(mut sx_wake_settings) = self.sx_wake_settings {
+            let nt_status;
+            // SAFETY: `as_raw` returns the `WDFDEVICE` of a `Device`, which is always in
+            // a valid state, and `wake_settings` is initialized with its `Size` set.
+            unsafe {
+                nt_status = macros::call_unsafe_wdf_function_binding!(
+                    WdfDeviceAssignSxWakeSettings,
+                    self.device.as_raw(),
+                    &mut sx_wake_settings.wake_settings,
+                );
+            }
+            if !nt_success(nt_status) {
+                return Err(nt_status);
+            }
+        }
+
+        Ok(())
+    }
+}
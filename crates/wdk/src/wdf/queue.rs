@@ -0,0 +1,648 @@
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, vec::Vec};
+
+use wdk_sys::{
+    macros,
+    _WDF_REQUEST_STOP_ACTION_FLAGS,
+    NTSTATUS,
+    ULONG,
+    WDFDEVICE,
+    WDFOBJECT,
+    WDFQUEUE,
+    WDFREQUEST,
+    WDF_IO_QUEUE_CONFIG,
+    WDF_NO_OBJECT_ATTRIBUTES,
+    WDF_OBJECT_ATTRIBUTES,
+};
+#[cfg(feature = "alloc")]
+use wdk_sys::{
+    _WDF_TRI_STATE,
+    STATUS_INVALID_DEVICE_REQUEST,
+    STATUS_SUCCESS,
+    WDF_IO_QUEUE_DISPATCH_TYPE,
+    WDF_TRI_STATE,
+};
+
+#[cfg(feature = "alloc")]
+use crate::wdf::{Device, ObjectContext, Request};
+use crate::{
+    nt_success,
+    wdf::{FromWdfObject, ObjectHandle},
+};
+
+/// WDF I/O Queue.
+///
+/// A queue receives the I/O requests sent to a device, and dispatches them to
+/// the driver's request handlers according to its dispatch type. Queues that
+/// only handle device I/O control requests are best created via
+/// [`IoctlRouter::create_queue`], which dispatches each request to the handler
+/// registered for its I/O control code.
+pub struct IoQueue {
+    wdf_queue: WDFQUEUE,
+}
+
+/// What the framework does with the queue of a request that is stopped, as
+/// passed to the handler registered via [`IoctlRouter::on_io_stop`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IoStopAction {
+    /// The device is leaving its working (D0) state, and the queue resumes once
+    /// the device returns to it (`WdfRequestStopActionSuspend`). The request
+    /// can be requeued via [`Request::acknowledge_stop_and_requeue`], or kept
+    /// by the driver after acknowledging it via
+    /// [`InFlightRequest::acknowledge`].
+    Suspend,
+    /// The device is being removed, and the queue does not resume
+    /// (`WdfRequestStopActionPurge`). The request must be completed.
+    Purge,
+}
+
+/// Reason that an in-flight request is stopped, as passed to the handler
+/// registered via [`IoctlRouter::on_io_stop`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct IoStopReason {
+    action: IoStopAction,
+    request_cancelable: bool,
+}
+
+/// A request that the driver owns while its queue is stopped or resumed, as
+/// passed to the handlers registered via [`IoctlRouter::on_io_stop`] and
+/// [`IoctlRouter::on_io_resume`].
+///
+/// The driver still owns the request through the [`Request`] (or
+/// [`CancelableRequest`](crate::wdf::CancelableRequest)) that it received, so
+/// an [`InFlightRequest`] only identifies the request (ex. to find it among the
+/// requests pended by the driver) via [`InFlightRequest::as_raw`].
+pub struct InFlightRequest {
+    wdf_request: WDFREQUEST,
+}
+
+/// Plain data that can be read from or written to the buffers of a device I/O
+/// control request by [`IoctlRouter`].
+///
+/// This trait is implemented for integers, arrays of [`IoctlData`], and `()`,
+/// which is used for I/O control codes without an input or output buffer.
+///
+/// # Safety
+///
+/// Every bit pattern of `size_of::<Self>()` bytes must be a valid value of
+/// `Self`, since the contents of request buffers are controlled by the
+/// requester. In particular, `Self` must not contain padding, references,
+/// pointers, `bool`s, `char`s or enums. Structs should be `#[repr(C)]`.
+pub unsafe trait IoctlData: Copy + 'static {}
+
+macro_rules! impl_ioctl_data {
+    ($($type:ty),* $(,)?) => {
+        $(
+            // SAFETY: Every bit pattern is a valid value of this type.
+            unsafe impl IoctlData for $type {}
+        )*
+    };
+}
+
+impl_ioctl_data!((), u8, u16, u32, u64, u128, usize);
+impl_ioctl_data!(i8, i16, i32, i64, i128, isize);
+
+// SAFETY: Every bit pattern is a valid value of an array whose elements accept
+// every bit pattern, and arrays contain no padding between their elements.
+unsafe impl<T: IoctlData, const N: usize> IoctlData for [T; N] {}
+
+/// Handler registered for an I/O control code of an [`IoctlRouter`], which
+/// takes ownership of the request
+#[cfg(feature = "alloc")]
+type IoctlHandler = Box<dyn Fn(&IoQueue, Request) + Send + Sync>;
+
+/// Handler registered via [`IoctlRouter::on_io_stop`]
+#[cfg(feature = "alloc")]
+type IoStopHandler = Box<dyn Fn(&IoQueue, InFlightRequest, IoStopReason) + Send + Sync>;
+
+/// Handler registered via [`IoctlRouter::on_io_resume`]
+#[cfg(feature = "alloc")]
+type IoResumeHandler = Box<dyn Fn(&IoQueue, &InFlightRequest) + Send + Sync>;
+
+/// Builder of a queue that routes device I/O control requests to a handler
+/// per I/O control code.
+///
+/// Handlers registered via [`IoctlRouter::ioctl`] declare the types of their
+/// input and output buffers, whose sizes are checked before the handler is
+/// called, so drivers do not need to cast request buffers themselves:
+///
+/// ```rust, no_run
+/// use wdk::wdf::{Device, IoQueue, IoctlRouter};
+/// use wdk_sys::_WDF_IO_QUEUE_DISPATCH_TYPE::WdfIoQueueDispatchParallel;
+///
+/// const IOCTL_GET_VERSION: u32 = 0x0022_2000;
+/// const IOCTL_SET_THRESHOLD: u32 = 0x0022_2004;
+///
+/// fn create_queue(device: &Device) -> wdk::Result<IoQueue> {
+///     IoctlRouter::new()
+///         .ioctl(IOCTL_GET_VERSION, |_queue, (): ()| Ok(1u32))
+///         .ioctl(IOCTL_SET_THRESHOLD, |_queue, threshold: u32| {
+///             if threshold > 100 {
+///                 return Err(wdk_sys::STATUS_INVALID_PARAMETER);
+///             }
+///             Ok(())
+///         })
+///         .create_queue(device, WdfIoQueueDispatchParallel)
+/// }
+/// ```
+///
+/// Requests with an I/O control code that has no registered handler are
+/// completed with `STATUS_INVALID_DEVICE_REQUEST`, unless the router forwards
+/// them via [`IoctlRouter::forward_unhandled_to_next_lower`].
+///
+/// # Power-Managed Queues
+///
+/// The framework stops a power-managed queue (see
+/// [`IoctlRouter::power_managed`]) before the device leaves its working (D0)
+/// state, and waits for every request that the queue delivered to the driver
+/// to be completed or acknowledged. Handlers that pend requests (ex. until the
+/// device has data) must therefore release them when the queue stops, via a
+/// handler registered with [`IoctlRouter::on_io_stop`]. Otherwise, the power
+/// transition never finishes and the system bugchecks with
+/// `DRIVER_POWER_STATE_FAILURE` (0x9F).
+///
+/// ```rust, no_run
+/// use wdk::wdf::{Device, IoQueue, IoctlRouter, Request};
+/// use wdk_sys::{_WDF_IO_QUEUE_DISPATCH_TYPE::WdfIoQueueDispatchParallel, WDFREQUEST};
+///
+/// const IOCTL_WAIT_FOR_DATA: u32 = 0x0022_2008;
+///
+/// // Adds the request to the requests pended by the driver
+/// fn pend_request(request: Request) {}
+///
+/// // Removes the request from the requests pended by the driver
+/// fn take_pended_request(wdf_request: WDFREQUEST) -> Option<Request> {
+///     None
+/// }
+///
+/// fn create_queue(device: &Device) -> wdk::Result<IoQueue> {
+///     IoctlRouter::new()
+///         .ioctl_with_request(IOCTL_WAIT_FOR_DATA, |_queue, request| pend_request(request))
+///         .power_managed(true)
+///         .on_io_stop(|_queue, request, reason| {
+///             if let Some(request) = take_pended_request(request.as_raw()) {
+///                 request.release_for_stop(reason);
+///             }
+///         })
+///         .create_queue(device, WdfIoQueueDispatchParallel)
+/// }
+/// ```
+#[cfg(feature = "alloc")]
+#[must_use]
+pub struct IoctlRouter {
+    handlers: Vec<(ULONG, IoctlHandler)>,
+    power_managed: WDF_TRI_STATE,
+    forward_unhandled: bool,
+    io_stop_handler: Option<IoStopHandler>,
+    io_resume_handler: Option<IoResumeHandler>,
+}
+
+#[cfg(feature = "alloc")]
+crate::wdf_declare_context_type!(
+    /// Context stored in every [`IoQueue`] created via
+    /// [`IoctlRouter::create_queue`]
+    struct IoctlRouterContext {
+        router: IoctlRouter,
+    }
+);
+
+impl IoQueue {
+    /// Try to construct a WDF I/O Queue object for `device`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a queue. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFIoQueue Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfio/nf-wdfio-wdfioqueuecreate#return-value)
+    pub fn try_new(
+        device: WDFDEVICE,
+        queue_config: &mut WDF_IO_QUEUE_CONFIG,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        let mut queue = Self {
+            wdf_queue: core::ptr::null_mut(),
+        };
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfIoQueueCreate,
+                device,
+                queue_config,
+                attributes.map_or(WDF_NO_OBJECT_ATTRIBUTES, core::ptr::from_mut),
+                &mut queue.wdf_queue,
+            );
+        }
+        nt_success(nt_status).then_some(queue).ok_or(nt_status)
+    }
+
+    /// Get the underlying `WDFQUEUE`
+    #[must_use]
+    pub const fn as_raw(&self) -> WDFQUEUE {
+        self.wdf_queue
+    }
+
+    /// Get the `WDFDEVICE` that this queue belongs to
+    #[must_use]
+    pub fn device(&self) -> WDFDEVICE {
+        let device;
+        // SAFETY: `wdf_queue` is a private member of `IoQueue`, originally created by
+        // WDF, and this module guarantees that it is always in a valid state.
+        unsafe {
+            device = macros::call_unsafe_wdf_function_binding!(WdfIoQueueGetDevice, self.wdf_queue);
+        }
+        device
+    }
+}
+
+impl IoStopReason {
+    /// Construct an [`IoStopReason`] from the `ActionFlags` passed to
+    /// `EvtIoStop`
+    #[must_use]
+    pub const fn from_raw(action_flags: ULONG) -> Self {
+        // The flags are small positive constants
+        #[allow(clippy::cast_sign_loss)]
+        let (purge, request_cancelable) = (
+            _WDF_REQUEST_STOP_ACTION_FLAGS::WdfRequestStopActionPurge as ULONG,
+            _WDF_REQUEST_STOP_ACTION_FLAGS::WdfRequestStopRequestCancelable as ULONG,
+        );
+        Self {
+            action: if action_flags & purge == 0 {
+                IoStopAction::Suspend
+            } else {
+                IoStopAction::Purge
+            },
+            request_cancelable: action_flags & request_cancelable != 0,
+        }
+    }
+
+    /// Get what the framework does with the queue of the request
+    #[must_use]
+    pub const fn action(self) -> IoStopAction {
+        self.action
+    }
+
+    /// Whether the request is cancelable, ie. the driver holds it as a
+    /// [`CancelableRequest`](crate::wdf::CancelableRequest). The driver must
+    /// reclaim it via
+    /// [`CancelableRequest::unmark_cancelable`](crate::wdf::CancelableRequest::unmark_cancelable)
+    /// before acknowledging or completing it. If that fails, the request is
+    /// being canceled, and completing it from its cancel handler is enough.
+    #[must_use]
+    pub const fn is_request_cancelable(self) -> bool {
+        self.request_cancelable
+    }
+}
+
+impl InFlightRequest {
+    /// Get the underlying `WDFREQUEST`
+    #[must_use]
+    pub const fn as_raw(&self) -> WDFREQUEST {
+        self.wdf_request
+    }
+
+    /// Acknowledge that the queue of the request is stopped, while the driver
+    /// keeps owning the request, consuming this handle. The framework continues
+    /// the power transition of the device, and passes the request to the
+    /// handler registered via [`IoctlRouter::on_io_resume`] once the queue
+    /// resumes.
+    ///
+    /// This is only meant for [`IoStopAction::Suspend`], when the driver can
+    /// stop processing the request (ex. by stopping its hardware) and continue
+    /// once the queue resumes. Requests that the driver does not process
+    /// should be released via [`Request::release_for_stop`] instead.
+    pub fn acknowledge(self) {
+        // SAFETY: `wdf_request` is a private member of `InFlightRequest`, which is
+        // only constructed from a request that WDF passed to `EvtIoStop`.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfRequestStopAcknowledge,
+                self.wdf_request,
+                u8::from(false),
+            );
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl IoctlRouter {
+    /// Construct an [`IoctlRouter`] without any handlers
+    pub const fn new() -> Self {
+        Self {
+            handlers: Vec::new(),
+            power_managed: _WDF_TRI_STATE::WdfUseDefault,
+            forward_unhandled: false,
+            io_stop_handler: None,
+            io_resume_handler: None,
+        }
+    }
+
+    /// Register `handler` for requests with `io_control_code`. The handler
+    /// receives the contents of the input buffer as an `I`, and its result is
+    /// written to the output buffer as an `O`.
+    ///
+    /// Requests whose input buffer is not exactly `size_of::<I>()` bytes are
+    /// completed with `STATUS_INVALID_BUFFER_SIZE`, and requests whose output
+    /// buffer is shorter than `size_of::<O>()` bytes are completed with
+    /// `STATUS_BUFFER_TOO_SMALL`, without calling `handler`. If `handler`
+    /// returns an error, the request is completed with it and nothing is
+    /// written to the output buffer. Otherwise, the request is completed
+    /// successfully with `size_of::<O>()` bytes of information.
+    ///
+    /// Registering a handler for an I/O control code that already has one
+    /// replaces the previous handler.
+    pub fn ioctl<I, O, F>(self, io_control_code: ULONG, handler: F) -> Self
+    where
+        I: IoctlData,
+        O: IoctlData,
+        F: Fn(&IoQueue, I) -> Result<O, NTSTATUS> + Send + Sync + 'static,
+    {
+        self.ioctl_with_request(io_control_code, move |queue, mut request| {
+            let result = read_input::<I>(&request)
+                .and_then(|input| check_output_length::<O>(&mut request).map(|()| input))
+                .and_then(|input| handler(queue, input))
+                .and_then(|output| write_output(&mut request, output));
+            match result {
+                Ok(information) => request.complete_with_information(STATUS_SUCCESS, information),
+                Err(nt_status) => request.complete(nt_status),
+            }
+        })
+    }
+
+    /// Register `handler` for requests with `io_control_code`. The handler
+    /// takes ownership of the [`Request`], and must complete it or hand it off
+    /// (ex. to pend it until the device has data). Its buffers are not checked
+    /// before `handler` is called.
+    ///
+    /// Registering a handler for an I/O control code that already has one
+    /// replaces the previous handler.
+    pub fn ioctl_with_request<F>(mut self, io_control_code: ULONG, handler: F) -> Self
+    where
+        F: Fn(&IoQueue, Request) + Send + Sync + 'static,
+    {
+        self.handlers.retain(|(code, _)| *code != io_control_code);
+        self.handlers.push((io_control_code, Box::new(handler)));
+        self
+    }
+
+    /// Set whether the queue is power-managed, ie. whether the framework only
+    /// dispatches requests while the device is in its working (D0) state, and
+    /// stops the queue when the device leaves it. By default, the queue is
+    /// power-managed unless the driver is a filter driver or the device is a
+    /// control device, which does not support power-managed queues.
+    ///
+    /// Requests that handlers pend on a power-managed queue must be released
+    /// when the queue stops, see [`IoctlRouter::on_io_stop`].
+    pub const fn power_managed(mut self, power_managed: bool) -> Self {
+        self.power_managed = if power_managed {
+            _WDF_TRI_STATE::WdfTrue
+        } else {
+            _WDF_TRI_STATE::WdfFalse
+        };
+        self
+    }
+
+    /// Forward requests with an I/O control code that has no registered
+    /// handler, as-is, to the next-lower driver in the device stack via
+    /// [`Request::forward_to_next_lower`], instead of completing them with
+    /// `STATUS_INVALID_DEVICE_REQUEST`. This is meant for the default queue of
+    /// a filter device (see
+    /// [`DeviceInit::set_filter`](crate::wdf::DeviceInit::set_filter)), which
+    /// then only handles the I/O control codes that it registered handlers
+    /// for, and passes everything else through. Requests that fail to be
+    /// forwarded are completed with the status of the failure.
+    pub const fn forward_unhandled_to_next_lower(mut self) -> Self {
+        self.forward_unhandled = true;
+        self
+    }
+
+    /// Register `handler` for the requests that the queue delivered to the
+    /// driver and that are not completed yet when the queue stops (ex. because
+    /// the device leaves its working state, or is removed). This is called once
+    /// per in-flight request, with the [`IoStopReason`] of the stop.
+    ///
+    /// For every request, `handler` (or the driver, at a later time) must
+    /// either:
+    ///     * release it via [`Request::release_for_stop`], which requeues or
+    ///       completes it depending on the [`IoStopAction`]
+    ///     * acknowledge it via [`InFlightRequest::acknowledge`], for
+    ///       [`IoStopAction::Suspend`] only
+    ///     * complete it
+    ///
+    /// The power transition of the device waits until every in-flight request
+    /// is handled, and the system bugchecks with `DRIVER_POWER_STATE_FAILURE`
+    /// (0x9F) if that takes too long.
+    ///
+    /// Registering a handler replaces the previous one.
+    pub fn on_io_stop<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&IoQueue, InFlightRequest, IoStopReason) + Send + Sync + 'static,
+    {
+        self.io_stop_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Register `handler` for the requests that were acknowledged via
+    /// [`InFlightRequest::acknowledge`] when the queue stopped, which is called
+    /// once per request when the queue resumes. The driver still owns the
+    /// requests, and continues processing them.
+    ///
+    /// Registering a handler replaces the previous one.
+    pub fn on_io_resume<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&IoQueue, &InFlightRequest) + Send + Sync + 'static,
+    {
+        self.io_resume_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Try to construct a WDF I/O Queue object for `device` that dispatches
+    /// its device I/O control requests to the handlers of this router. The
+    /// queue is the default queue of `device`, so it receives all requests
+    /// that are not forwarded to other queues by the framework.
+    ///
+    /// The router is stored in the queue's WDF object context space, and is
+    /// dropped when the framework destroys the queue.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a queue. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFIoQueue Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfio/nf-wdfio-wdfioqueuecreate#return-value)
+    pub fn create_queue(
+        self,
+        device: &Device,
+        dispatch_type: WDF_IO_QUEUE_DISPATCH_TYPE,
+    ) -> Result<IoQueue, NTSTATUS> {
+        let mut queue_config = WDF_IO_QUEUE_CONFIG {
+            // The size of WDF_IO_QUEUE_CONFIG is known to fit in a ULONG
+            #[allow(clippy::cast_possible_truncation)]
+            Size: core::mem::size_of::<WDF_IO_QUEUE_CONFIG>() as ULONG,
+            DispatchType: dispatch_type,
+            PowerManaged: self.power_managed,
+            DefaultQueue: u8::from(true),
+            EvtIoDeviceControl: Some(evt_io_device_control),
+            ..WDF_IO_QUEUE_CONFIG::default()
+        };
+        if self.io_stop_handler.is_some() {
+            queue_config.EvtIoStop = Some(evt_io_stop);
+        }
+        if self.io_resume_handler.is_some() {
+            queue_config.EvtIoResume = Some(evt_io_resume);
+        }
+
+        let mut attributes = IoctlRouterContext::object_attributes();
+
+        let queue = IoQueue::try_new(device.as_raw(), &mut queue_config, Some(&mut attributes))?;
+        if queue
+            .init_context(IoctlRouterContext { router: self })
+            .is_err()
+        {
+            unreachable!("context of a newly created queue should be uninitialized");
+        }
+
+        Ok(queue)
+    }
+
+    /// Dispatch `request` to the handler registered for `io_control_code`. If
+    /// there is none, forward it to the next-lower driver if the router
+    /// forwards unhandled requests, or complete it with
+    /// `STATUS_INVALID_DEVICE_REQUEST` otherwise
+    fn dispatch(&self, queue: &IoQueue, request: Request, io_control_code: ULONG) {
+        match self
+            .handlers
+            .iter()
+            .find(|(code, _)| *code == io_control_code)
+        {
+            Some((_, handler)) => handler(queue, request),
+            None if self.forward_unhandled => {
+                // SAFETY: `IoQueue::device` returns the device that the queue belongs to,
+                // which outlives the requests that the queue dispatches.
+                let device = unsafe { Device::from_wdf_object(queue.device().cast()) };
+                if let Err((request, nt_status)) = request.forward_to_next_lower(&device) {
+                    request.complete(nt_status);
+                }
+            }
+            None => request.complete(STATUS_INVALID_DEVICE_REQUEST),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Default for IoctlRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Read the input buffer of `request` as an `I`
+#[cfg(feature = "alloc")]
+fn read_input<I: IoctlData>(request: &Request) -> Result<I, NTSTATUS> {
+    if core::mem::size_of::<I>() == 0 {
+        // SAFETY: `IoctlData` guarantees that every bit pattern, including the empty
+        // one, is a valid `I`.
+        return Ok(unsafe { core::mem::zeroed() });
+    }
+
+    let buffer = request.input_buffer_exact(core::mem::size_of::<I>())?;
+    // SAFETY: `buffer` is exactly `size_of::<I>()` bytes long, and `IoctlData`
+    // guarantees that every bit pattern of that many bytes is a valid `I`.
+    Ok(unsafe { buffer.as_ptr().cast::<I>().read_unaligned() })
+}
+
+/// Check that the output buffer of `request` can hold an `O`
+#[cfg(feature = "alloc")]
+fn check_output_length<O: IoctlData>(request: &mut Request) -> Result<(), NTSTATUS> {
+    if core::mem::size_of::<O>() != 0 {
+        request.output_buffer(core::mem::size_of::<O>())?;
+    }
+    Ok(())
+}
+
+/// Write `output` to the output buffer of `request`, and return the number of
+/// bytes written
+#[cfg(feature = "alloc")]
+fn write_output<O: IoctlData>(request: &mut Request, output: O) -> Result<usize, NTSTATUS> {
+    if core::mem::size_of::<O>() == 0 {
+        return Ok(0);
+    }
+
+    let buffer = request.output_buffer_prefix(core::mem::size_of::<O>())?;
+    // SAFETY: `buffer` is exactly `size_of::<O>()` bytes long and valid for writes.
+    unsafe {
+        buffer.as_mut_ptr().cast::<O>().write_unaligned(output);
+    }
+    Ok(core::mem::size_of::<O>())
+}
+
+// SAFETY: `wdf_queue` is a private member of `IoQueue`, originally created by
+// WDF, and this module guarantees that it is always in a valid state.
+unsafe impl ObjectHandle for IoQueue {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_queue.cast()
+    }
+}
+
+// SAFETY: `IoQueue` does not delete the underlying object when dropped.
+unsafe impl FromWdfObject for IoQueue {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            wdf_queue: wdf_object.cast(),
+        }
+    }
+}
+
+/// `EvtIoDeviceControl` trampoline that dispatches requests to the
+/// [`IoctlRouter`] stored in the queue's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_io_device_control(
+    wdf_queue: WDFQUEUE,
+    wdf_request: WDFREQUEST,
+    _output_buffer_length: usize,
+    _input_buffer_length: usize,
+    io_control_code: ULONG,
+) {
+    let queue = IoQueue { wdf_queue };
+    // SAFETY: WDF passes a valid request, whose ownership is transferred to the
+    // driver.
+    let request = unsafe { Request::from_raw(wdf_request) };
+    match queue.context::<IoctlRouterContext>() {
+        Some(context) => context.router.dispatch(&queue, request, io_control_code),
+        None => request.complete(STATUS_INVALID_DEVICE_REQUEST),
+    }
+}
+
+/// `EvtIoStop` trampoline that dispatches in-flight requests to the handler
+/// registered via [`IoctlRouter::on_io_stop`]
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_io_stop(
+    wdf_queue: WDFQUEUE,
+    wdf_request: WDFREQUEST,
+    action_flags: ULONG,
+) {
+    let queue = IoQueue { wdf_queue };
+    let request = InFlightRequest { wdf_request };
+    match queue
+        .context::<IoctlRouterContext>()
+        .and_then(|context| context.router.io_stop_handler.as_ref())
+    {
+        Some(handler) => handler(&queue, request, IoStopReason::from_raw(action_flags)),
+        None => request.acknowledge(),
+    }
+}
+
+/// `EvtIoResume` trampoline that dispatches acknowledged requests to the
+/// handler registered via [`IoctlRouter::on_io_resume`]
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_io_resume(wdf_queue: WDFQUEUE, wdf_request: WDFREQUEST) {
+    let queue = IoQueue { wdf_queue };
+    let request = InFlightRequest { wdf_request };
+    if let Some(handler) = queue
+        .context::<IoctlRouterContext>()
+        .and_then(|context| context.router.io_resume_handler.as_ref())
+    {
+        handler(&queue, &request);
+    }
+}
@@ -0,0 +1,376 @@
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use wdk_sys::{
+    macros,
+    ACCESS_MASK,
+    NTSTATUS,
+    REG_BINARY,
+    ULONG,
+    WDFKEY,
+    WDFOBJECT,
+    WDF_NO_OBJECT_ATTRIBUTES,
+    WDF_OBJECT_ATTRIBUTES,
+};
+#[cfg(feature = "alloc")]
+use wdk_sys::{STATUS_BUFFER_OVERFLOW, UNICODE_STRING, USHORT};
+
+#[cfg(feature = "alloc")]
+use crate::string::NtUnicodeString;
+use crate::{
+    nt_success,
+    string::NtUnicodeStr,
+    wdf::{Driver, ObjectHandle, WdfString},
+};
+
+/// WDF Registry Key.
+///
+/// A [`RegistryKey`] is an open handle to a key in the registry. The most
+/// common use is reading driver configuration from the driver's `Parameters`
+/// key, which is opened via [`RegistryKey::open_driver_parameters`]. The key is
+/// closed when the [`RegistryKey`] is dropped.
+///
+/// All registry operations must be performed at `IRQL` = `PASSIVE_LEVEL`.
+pub struct RegistryKey {
+    wdf_key: WDFKEY,
+}
+
+impl RegistryKey {
+    /// Try to open the `Parameters` key of `driver`'s service key (ie.
+    /// `HKLM\SYSTEM\CurrentControlSet\Services\<ServiceName>\Parameters`)
+    /// with `desired_access` (ex. `KEY_READ`)
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to open the key. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFDriver Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfdriver/nf-wdfdriver-wdfdriveropenparametersregistrykey#return-value)
+    pub fn open_driver_parameters(
+        driver: &Driver,
+        desired_access: ACCESS_MASK,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        let mut registry_key = Self {
+            wdf_key: core::ptr::null_mut(),
+        };
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfDriverOpenParametersRegistryKey,
+                driver.as_wdf_object().cast(),
+                desired_access,
+                attributes.map_or(WDF_NO_OBJECT_ATTRIBUTES, core::ptr::from_mut),
+                &mut registry_key.wdf_key,
+            );
+        }
+        nt_success(nt_status)
+            .then_some(registry_key)
+            .ok_or(nt_status)
+    }
+
+    /// Try to open the subkey `key_name` of this key with `desired_access`
+    /// (ex. `KEY_READ`)
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to open the key. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFRegistry Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfregistry/nf-wdfregistry-wdfregistryopenkey#return-value)
+    pub fn open_key(
+        &self,
+        key_name: NtUnicodeStr<'_>,
+        desired_access: ACCESS_MASK,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        Self::open_key_impl(self.wdf_key, key_name, desired_access, attributes)
+    }
+
+    /// Try to open the key at the absolute path `key_name` (ex.
+    /// `\Registry\Machine\Software\Contoso`) with `desired_access`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to open the key. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFRegistry Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfregistry/nf-wdfregistry-wdfregistryopenkey#return-value)
+    pub fn open_absolute_key(
+        key_name: NtUnicodeStr<'_>,
+        desired_access: ACCESS_MASK,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        Self::open_key_impl(core::ptr::null_mut(), key_name, desired_access, attributes)
+    }
+
+    fn open_key_impl(
+        parent_key: WDFKEY,
+        key_name: NtUnicodeStr<'_>,
+        desired_access: ACCESS_MASK,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        let mut registry_key = Self {
+            wdf_key: core::ptr::null_mut(),
+        };
+
+        let nt_status;
+        // SAFETY: `parent_key` is either null or a valid key owned by a `RegistryKey`.
+        // The resulting ffi object is stored in a private member and not accessible
+        // outside of this module, and this module guarantees that it is always in a
+        // valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfRegistryOpenKey,
+                parent_key,
+                key_name.as_raw(),
+                desired_access,
+                attributes.map_or(WDF_NO_OBJECT_ATTRIBUTES, core::ptr::from_mut),
+                &mut registry_key.wdf_key,
+            );
+        }
+        nt_success(nt_status)
+            .then_some(registry_key)
+            .ok_or(nt_status)
+    }
+
+    /// Query the `REG_DWORD` value `value_name` of this key
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to query the value (ex. `STATUS_OBJECT_NAME_NOT_FOUND` if the value does not exist). The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFRegistry Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfregistry/nf-wdfregistry-wdfregistryqueryulong#return-value)
+    pub fn query_u32(&self, value_name: NtUnicodeStr<'_>) -> Result<u32, NTSTATUS> {
+        let mut value: ULONG = 0;
+
+        let nt_status;
+        // SAFETY: `wdf_key` is a private member of `RegistryKey`, originally created by
+        // WDF, and this module guarantees that it is always in a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfRegistryQueryULong,
+                self.wdf_key,
+                value_name.as_raw(),
+                &mut value,
+            );
+        }
+        nt_success(nt_status).then_some(value).ok_or(nt_status)
+    }
+
+    /// Query the value `value_name` of this key into `buffer`, and return the
+    /// number of bytes written. The value can be of any type, but is typically
+    /// `REG_BINARY`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to query the value (ex. `STATUS_BUFFER_OVERFLOW` if `buffer` is too small to hold the value). The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFRegistry Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfregistry/nf-wdfregistry-wdfregistryqueryvalue#return-value)
+    pub fn query_binary(
+        &self,
+        value_name: NtUnicodeStr<'_>,
+        buffer: &mut [u8],
+    ) -> Result<usize, NTSTATUS> {
+        let mut value_length_queried: ULONG = 0;
+
+        let nt_status;
+        // SAFETY: `wdf_key` is a private member of `RegistryKey`, originally created by
+        // WDF, and this module guarantees that it is always in a valid state. WDF
+        // writes at most `buffer.len()` bytes to `buffer`.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfRegistryQueryValue,
+                self.wdf_key,
+                value_name.as_raw(),
+                ULONG::try_from(buffer.len()).unwrap_or(ULONG::MAX),
+                buffer.as_mut_ptr().cast(),
+                &mut value_length_queried,
+                core::ptr::null_mut(),
+            );
+        }
+        nt_success(nt_status)
+            .then_some(value_length_queried as usize)
+            .ok_or(nt_status)
+    }
+
+    /// Query the `REG_SZ` or `REG_EXPAND_SZ` value `value_name` of this key
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to query the value. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFRegistry Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfregistry/nf-wdfregistry-wdfregistryqueryunicodestring#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn query_unicode_string(
+        &self,
+        value_name: NtUnicodeStr<'_>,
+    ) -> Result<NtUnicodeString, NTSTATUS> {
+        let mut value_byte_length: USHORT = 0;
+
+        let nt_status;
+        // SAFETY: `wdf_key` is a private member of `RegistryKey`, originally created by
+        // WDF, and this module guarantees that it is always in a valid state. Passing
+        // a null `Value` only queries the length of the value.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfRegistryQueryUnicodeString,
+                self.wdf_key,
+                value_name.as_raw(),
+                &mut value_byte_length,
+                core::ptr::null_mut(),
+            );
+        }
+        if !nt_success(nt_status) && nt_status != STATUS_BUFFER_OVERFLOW {
+            return Err(nt_status);
+        }
+
+        let mut buffer: Vec<u16> =
+            Vec::with_capacity(usize::from(value_byte_length) / core::mem::size_of::<u16>());
+        let mut value = UNICODE_STRING {
+            Length: 0,
+            MaximumLength: value_byte_length,
+            Buffer: buffer.as_mut_ptr(),
+        };
+
+        let nt_status;
+        // SAFETY: `wdf_key` is a private member of `RegistryKey`, originally created by
+        // WDF, and this module guarantees that it is always in a valid state. `value`
+        // describes the spare capacity of `buffer`, which outlives this call.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfRegistryQueryUnicodeString,
+                self.wdf_key,
+                value_name.as_raw(),
+                core::ptr::null_mut(),
+                &mut value,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status);
+        }
+
+        // SAFETY: WDF initialized the first `value.Length` bytes of `buffer`, which is
+        // at most `value.MaximumLength`, the capacity of `buffer` in bytes.
+        unsafe {
+            buffer.set_len(usize::from(value.Length) / core::mem::size_of::<u16>());
+        }
+        NtUnicodeString::try_from_utf16(buffer)
+    }
+
+    /// Query the `REG_SZ` or `REG_EXPAND_SZ` value `value_name` of this key
+    /// into a new [`WdfString`]. Unlike [`RegistryKey::query_unicode_string`],
+    /// the value is stored in a buffer managed by WDF, so this does not
+    /// require the `alloc` feature.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to create the string or to query the value. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFRegistry Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfregistry/nf-wdfregistry-wdfregistryquerystring#return-value)
+    pub fn query_string(
+        &self,
+        value_name: NtUnicodeStr<'_>,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<WdfString, NTSTATUS> {
+        let value = WdfString::try_new(None, attributes)?;
+
+        let nt_status;
+        // SAFETY: `wdf_key` is a private member of `RegistryKey`, originally created by
+        // WDF, and this module guarantees that it is always in a valid state. `value`
+        // is a valid string object that is not borrowed yet.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfRegistryQueryString,
+                self.wdf_key,
+                value_name.as_raw(),
+                value.as_raw(),
+            );
+        }
+        nt_success(nt_status).then_some(value).ok_or(nt_status)
+    }
+
+    /// Assign `value` to the `REG_DWORD` value `value_name` of this key
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to assign the value. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFRegistry Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfregistry/nf-wdfregistry-wdfregistryassignulong#return-value)
+    pub fn assign_u32(&self, value_name: NtUnicodeStr<'_>, value: u32) -> Result<(), NTSTATUS> {
+        let nt_status;
+        // SAFETY: `wdf_key` is a private member of `RegistryKey`, originally created by
+        // WDF, and this module guarantees that it is always in a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfRegistryAssignULong,
+                self.wdf_key,
+                value_name.as_raw(),
+                value,
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Assign `value` to the `REG_BINARY` value `value_name` of this key
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to assign the value. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFRegistry Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfregistry/nf-wdfregistry-wdfregistryassignvalue#return-value)
+    pub fn assign_binary(
+        &self,
+        value_name: NtUnicodeStr<'_>,
+        value: &[u8],
+    ) -> Result<(), NTSTATUS> {
+        let Ok(value_length) = ULONG::try_from(value.len()) else {
+            return Err(wdk_sys::STATUS_INVALID_PARAMETER);
+        };
+
+        let nt_status;
+        // SAFETY: `wdf_key` is a private member of `RegistryKey`, originally created by
+        // WDF, and this module guarantees that it is always in a valid state. WDF only
+        // reads `value_length` bytes from `value`.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfRegistryAssignValue,
+                self.wdf_key,
+                value_name.as_raw(),
+                REG_BINARY,
+                value_length,
+                value.as_ptr().cast_mut().cast(),
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Assign `value` to the `REG_SZ` value `value_name` of this key
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to assign the value. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFRegistry Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfregistry/nf-wdfregistry-wdfregistryassignunicodestring#return-value)
+    pub fn assign_unicode_string(
+        &self,
+        value_name: NtUnicodeStr<'_>,
+        value: NtUnicodeStr<'_>,
+    ) -> Result<(), NTSTATUS> {
+        let nt_status;
+        // SAFETY: `wdf_key` is a private member of `RegistryKey`, originally created by
+        // WDF, and this module guarantees that it is always in a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfRegistryAssignUnicodeString,
+                self.wdf_key,
+                value_name.as_raw(),
+                value.as_raw(),
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+}
+
+impl Drop for RegistryKey {
+    fn drop(&mut self) {
+        // SAFETY: `wdf_key` is a private member of `RegistryKey`, originally created by
+        // WDF, and this module guarantees that it is always in a valid state. It is not
+        // used after it is closed here.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(WdfRegistryClose, self.wdf_key);
+        }
+    }
+}
+
+// SAFETY: `wdf_key` is a private member of `RegistryKey`, originally created by
+// WDF, and this module guarantees that it is always in a valid state.
+unsafe impl ObjectHandle for RegistryKey {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_key.cast()
+    }
+}
@@ -0,0 +1,609 @@
+use wdk_sys::{
+    macros,
+    _WDF_REQUEST_SEND_OPTIONS_FLAGS::WDF_REQUEST_SEND_OPTION_SEND_AND_FORGET,
+    _WDF_REQUEST_TYPE,
+    NTSTATUS,
+    PVOID,
+    ULONG,
+    ULONG_PTR,
+    WDFIOTARGET,
+    WDFOBJECT,
+    WDFQUEUE,
+    WDFREQUEST,
+    WDF_REQUEST_PARAMETERS,
+    WDF_REQUEST_SEND_OPTIONS,
+    WDF_REQUEST_TYPE,
+};
+
+use crate::{
+    nt_success,
+    wdf::{context::drop_context, IoStopAction, IoStopReason, ObjectContext, ObjectHandle},
+};
+
+/// WDF Request.
+///
+/// A request object represents an I/O request that the framework delivered to
+/// the driver, ex. via one of its queues. The driver owns the request until it
+/// completes it via [`Request::complete`], or hands it off via
+/// [`Request::forward_to_io_queue`], [`Request::send_and_forget`] or
+/// [`Request::forward_to_next_lower`]. All of these consume the [`Request`],
+/// so the compiler prevents a request from being used after it was completed
+/// or handed off, or from being completed twice.
+///
+/// The buffers of the request are accessed via [`Request::input_buffer`] and
+/// [`Request::output_buffer`], which return slices whose lifetime is tied to
+/// the request. For buffered I/O, the input and output buffers of a request
+/// are the same memory, so the output buffer can only be borrowed while no
+/// input buffer is borrowed.
+///
+/// A request that the driver holds on to for a long time (ex. until the device
+/// receives data) should be made cancelable via [`Request::mark_cancelable`].
+#[must_use = "requests must be completed or handed off to another owner"]
+pub struct Request {
+    wdf_request: WDFREQUEST,
+}
+
+/// A [`Request`] that was marked as cancelable via
+/// [`Request::mark_cancelable`].
+///
+/// While a request is cancelable, its cancel handler may complete it at any
+/// time, so it can neither be completed nor have its buffers accessed. To
+/// complete it, the driver must first reclaim it via
+/// [`CancelableRequest::unmark_cancelable`]. Dropping a [`CancelableRequest`]
+/// leaves the request pending until it is canceled.
+pub struct CancelableRequest {
+    request: Request,
+}
+
+/// Parameters of a [`Request`], returned by [`Request::parameters`].
+///
+/// The parameters are decoded from the `WDF_REQUEST_PARAMETERS` of the request
+/// according to its type, so that they can be pattern matched instead of read
+/// out of a union:
+///
+/// ```rust, no_run
+/// use wdk::wdf::{Request, RequestParameters};
+/// use wdk_sys::{STATUS_INVALID_DEVICE_REQUEST, STATUS_SUCCESS};
+///
+/// fn handle_request(request: Request) {
+///     match request.parameters() {
+///         RequestParameters::Read { length, .. } => {
+///             request.complete_with_information(STATUS_SUCCESS, length);
+///         }
+///         RequestParameters::DeviceControl { code: 0x0022_2000, .. } => {
+///             request.complete(STATUS_SUCCESS);
+///         }
+///         _ => request.complete(STATUS_INVALID_DEVICE_REQUEST),
+///     }
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RequestParameters {
+    /// Parameters of a read request (`WdfRequestTypeRead`)
+    Read {
+        /// Number of bytes to read
+        length: usize,
+        /// Byte offset on the device to start reading from
+        offset: i64,
+    },
+    /// Parameters of a write request (`WdfRequestTypeWrite`)
+    Write {
+        /// Number of bytes to write
+        length: usize,
+        /// Byte offset on the device to start writing to
+        offset: i64,
+    },
+    /// Parameters of a device I/O control request
+    /// (`WdfRequestTypeDeviceControl`)
+    DeviceControl {
+        /// I/O control code of the request
+        code: ULONG,
+        /// Length of the input buffer, in bytes
+        in_len: usize,
+        /// Length of the output buffer, in bytes
+        out_len: usize,
+    },
+    /// Parameters of an internal device I/O control request, which can only
+    /// be sent by other drivers (`WdfRequestTypeDeviceControlInternal`)
+    InternalDeviceControl {
+        /// I/O control code of the request
+        code: ULONG,
+        /// Length of the input buffer, in bytes
+        in_len: usize,
+        /// Length of the output buffer, in bytes
+        out_len: usize,
+    },
+    /// Any other type of request, whose parameters are not decoded
+    Other {
+        /// Type of the request
+        request_type: WDF_REQUEST_TYPE,
+        /// IRP minor function code of the request
+        minor_function: u8,
+    },
+}
+
+impl RequestParameters {
+    /// Decode the parameters of a request from `parameters`
+    ///
+    /// # Safety
+    ///
+    /// `parameters` must have been filled by `WdfRequestGetParameters`, so that
+    /// the member of its `Parameters` union that is read for its `Type` is
+    /// initialized.
+    #[must_use]
+    pub const unsafe fn from_raw(parameters: &WDF_REQUEST_PARAMETERS) -> Self {
+        match parameters.Type {
+            _WDF_REQUEST_TYPE::WdfRequestTypeRead => {
+                // SAFETY: The caller guarantees that the `Read` member is initialized for
+                // read requests.
+                let read = unsafe { parameters.Parameters.Read };
+                Self::Read {
+                    length: read.Length,
+                    offset: read.DeviceOffset,
+                }
+            }
+            _WDF_REQUEST_TYPE::WdfRequestTypeWrite => {
+                // SAFETY: The caller guarantees that the `Write` member is initialized for
+                // write requests.
+                let write = unsafe { parameters.Parameters.Write };
+                Self::Write {
+                    length: write.Length,
+                    offset: write.DeviceOffset,
+                }
+            }
+            _WDF_REQUEST_TYPE::WdfRequestTypeDeviceControl => {
+                // SAFETY: The caller guarantees that the `DeviceIoControl` member is
+                // initialized for device I/O control requests.
+                let device_io_control = unsafe { parameters.Parameters.DeviceIoControl };
+                Self::DeviceControl {
+                    code: device_io_control.IoControlCode,
+                    in_len: device_io_control.InputBufferLength,
+                    out_len: device_io_control.OutputBufferLength,
+                }
+            }
+            _WDF_REQUEST_TYPE::WdfRequestTypeDeviceControlInternal => {
+                // SAFETY: The caller guarantees that the `DeviceIoControl` member is
+                // initialized for internal device I/O control requests.
+                let device_io_control = unsafe { parameters.Parameters.DeviceIoControl };
+                Self::InternalDeviceControl {
+                    code: device_io_control.IoControlCode,
+                    in_len: device_io_control.InputBufferLength,
+                    out_len: device_io_control.OutputBufferLength,
+                }
+            }
+            request_type => Self::Other {
+                request_type,
+                minor_function: parameters.MinorFunction,
+            },
+        }
+    }
+}
+
+crate::wdf_declare_context_type!(
+    /// Context of a request that was marked as cancelable via
+    /// [`Request::mark_cancelable`]
+    struct RequestCancelContext {
+        cancel_handler: fn(Request),
+    }
+);
+
+impl Request {
+    /// Construct a [`Request`] from a request handle passed to the driver by
+    /// the framework
+    ///
+    /// # Safety
+    ///
+    /// `wdf_request` must be a valid request that is owned by the driver, and
+    /// that has not been completed yet. The driver must not access the request
+    /// or its buffers through any other handle while the returned [`Request`]
+    /// exists.
+    pub const unsafe fn from_raw(wdf_request: WDFREQUEST) -> Self {
+        Self { wdf_request }
+    }
+
+    /// Get the underlying `WDFREQUEST`
+    #[must_use]
+    pub const fn as_raw(&self) -> WDFREQUEST {
+        self.wdf_request
+    }
+
+    /// Get the parameters of the request, ex. the length and offset of a read
+    /// request, or the I/O control code of a device I/O control request
+    #[must_use]
+    pub fn parameters(&self) -> RequestParameters {
+        let mut parameters = WDF_REQUEST_PARAMETERS::with_size();
+        // SAFETY: `wdf_request` is a private member of `Request`, which is guaranteed
+        // to be a valid request owned by the driver by `Request::from_raw`.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfRequestGetParameters,
+                self.wdf_request,
+                &mut parameters,
+            );
+        }
+        // SAFETY: `parameters` was just filled by `WdfRequestGetParameters`.
+        unsafe { RequestParameters::from_raw(&parameters) }
+    }
+
+    /// Get the input buffer of the request, ex. the data of a write request or
+    /// the input of a device I/O control request.
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_BUFFER_TOO_SMALL` if the input buffer is shorter than `minimum_length` bytes. Otherwise, it will return an error if WDF fails to retrieve the input buffer. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFRequest Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfrequest/nf-wdfrequest-wdfrequestretrieveinputbuffer#return-value)
+    pub fn input_buffer(&self, minimum_length: usize) -> Result<&[u8], NTSTATUS> {
+        let mut buffer: PVOID = core::ptr::null_mut();
+        let mut length = 0;
+
+        let nt_status;
+        // SAFETY: `wdf_request` is a private member of `Request`, which is guaranteed
+        // to be a valid request owned by the driver by `Request::from_raw`.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfRequestRetrieveInputBuffer,
+                self.wdf_request,
+                minimum_length,
+                &mut buffer,
+                &mut length,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status);
+        }
+
+        if buffer.is_null() || length == 0 {
+            return Ok(&[]);
+        }
+
+        // SAFETY: WDF guarantees that the input buffer is valid for reads of `length`
+        // bytes until the request is completed, which requires consuming `self`.
+        Ok(unsafe { core::slice::from_raw_parts(buffer.cast::<u8>(), length) })
+    }
+
+    /// Get the output buffer of the request, ex. the buffer to fill for a read
+    /// request or the output of a device I/O control request.
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_BUFFER_TOO_SMALL` if the output buffer is shorter than `minimum_length` bytes. Otherwise, it will return an error if WDF fails to retrieve the output buffer. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFRequest Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfrequest/nf-wdfrequest-wdfrequestretrieveoutputbuffer#return-value)
+    pub fn output_buffer(&mut self, minimum_length: usize) -> Result<&mut [u8], NTSTATUS> {
+        let mut buffer: PVOID = core::ptr::null_mut();
+        let mut length = 0;
+
+        let nt_status;
+        // SAFETY: `wdf_request` is a private member of `Request`, which is guaranteed
+        // to be a valid request owned by the driver by `Request::from_raw`.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfRequestRetrieveOutputBuffer,
+                self.wdf_request,
+                minimum_length,
+                &mut buffer,
+                &mut length,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status);
+        }
+
+        if buffer.is_null() || length == 0 {
+            return Ok(&mut []);
+        }
+
+        // SAFETY: WDF guarantees that the output buffer is valid for reads and writes
+        // of `length` bytes until the request is completed, which requires consuming
+        // `self`. Borrowing `self` mutably guarantees that no slice of the input
+        // buffer, which might be the same memory, is alive.
+        Ok(unsafe { core::slice::from_raw_parts_mut(buffer.cast::<u8>(), length) })
+    }
+
+    /// Get the input buffer of the request, and check that it is exactly
+    /// `length` bytes long.
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INVALID_BUFFER_SIZE` if the input
+    /// buffer is not exactly `length` bytes long, or any error returned by
+    /// [`Request::input_buffer`].
+    pub fn input_buffer_exact(&self, length: usize) -> Result<&[u8], NTSTATUS> {
+        let buffer = self.input_buffer(length)?;
+        if buffer.len() != length {
+            return Err(wdk_sys::STATUS_INVALID_BUFFER_SIZE);
+        }
+        Ok(buffer)
+    }
+
+    /// Get the first `length` bytes of the output buffer of the request.
+    ///
+    /// # Errors
+    ///
+    /// This function will return any error returned by
+    /// [`Request::output_buffer`], including `STATUS_BUFFER_TOO_SMALL` if the
+    /// output buffer is shorter than `length` bytes.
+    pub fn output_buffer_prefix(&mut self, length: usize) -> Result<&mut [u8], NTSTATUS> {
+        Ok(&mut self.output_buffer(length)?[..length])
+    }
+
+    /// Forward the request to another queue of the same device, consuming it.
+    /// The driver receives the request again when that queue dispatches it.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the request, along with the [`NTSTATUS`] of the failure, if WDF fails to forward it. The driver still owns the request in that case, and must complete it. Full error documentation is available in the [WDFRequest Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfrequest/nf-wdfrequest-wdfrequestforwardtoioqueue#return-value)
+    pub fn forward_to_io_queue(self, queue: WDFQUEUE) -> Result<(), (Self, NTSTATUS)> {
+        let nt_status;
+        // SAFETY: `wdf_request` is a private member of `Request`, which is guaranteed
+        // to be a valid request owned by the driver by `Request::from_raw`. On
+        // success, ownership of the request is transferred to `queue`, and `self` is
+        // consumed.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfRequestForwardToIoQueue,
+                self.wdf_request,
+                queue,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err((self, nt_status));
+        }
+        Ok(())
+    }
+
+    /// Send the request to `io_target` (ex. the next lower driver in the
+    /// device stack) without waiting for it to be completed, consuming it. The
+    /// request is formatted with its current parameters, so it is sent as-is.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the request, along with the [`NTSTATUS`] of the failure, if WDF fails to send it. The driver still owns the request in that case, and must complete it. Full error documentation is available in the [WDFRequest Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfrequest/nf-wdfrequest-wdfrequestsend#return-value)
+    pub fn send_and_forget(self, io_target: WDFIOTARGET) -> Result<(), (Self, NTSTATUS)> {
+        let mut request_options = WDF_REQUEST_SEND_OPTIONS {
+            // The size of WDF_REQUEST_SEND_OPTIONS is known to fit in a ULONG
+            #[allow(clippy::cast_possible_truncation)]
+            Size: core::mem::size_of::<WDF_REQUEST_SEND_OPTIONS>() as ULONG,
+            // The flag is a small positive constant
+            #[allow(clippy::cast_sign_loss)]
+            Flags: WDF_REQUEST_SEND_OPTION_SEND_AND_FORGET as ULONG,
+            ..WDF_REQUEST_SEND_OPTIONS::default()
+        };
+
+        // SAFETY: `wdf_request` is a private member of `Request`, which is guaranteed
+        // to be a valid request owned by the driver by `Request::from_raw`.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfRequestFormatRequestUsingCurrentType,
+                self.wdf_request,
+            );
+        }
+
+        let sent;
+        // SAFETY: `wdf_request` is a private member of `Request`, which is guaranteed
+        // to be a valid request owned by the driver by `Request::from_raw`, and it
+        // was formatted above. On success, ownership of the request is transferred to
+        // `io_target`, and `self` is consumed.
+        unsafe {
+            sent = macros::call_unsafe_wdf_function_binding!(
+                WdfRequestSend,
+                self.wdf_request,
+                io_target,
+                &mut request_options,
+            );
+        }
+        if sent == 0 {
+            let nt_status;
+            // SAFETY: `wdf_request` is a private member of `Request`, which is
+            // guaranteed to be a valid request owned by the driver by
+            // `Request::from_raw`.
+            unsafe {
+                nt_status = macros::call_unsafe_wdf_function_binding!(
+                    WdfRequestGetStatus,
+                    self.wdf_request
+                );
+            }
+            return Err((self, nt_status));
+        }
+        Ok(())
+    }
+
+    /// Mark the request as cancelable, so that `cancel_handler` is invoked if
+    /// the request is canceled (ex. because the application that sent it
+    /// exits) before the driver reclaims it via
+    /// [`CancelableRequest::unmark_cancelable`]. The cancel handler receives
+    /// ownership of the request, and must complete it, typically with
+    /// `STATUS_CANCELLED`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the request, along with the [`NTSTATUS`] of the failure, if it cannot be marked as cancelable. In particular, if the request has already been canceled, `cancel_handler` is not invoked and the error will contain `STATUS_CANCELLED`, in which case the driver must complete the request itself. Full error documentation is available in the [WDFRequest Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfrequest/nf-wdfrequest-wdfrequestmarkcancelableex#return-value)
+    pub fn mark_cancelable(
+        self,
+        cancel_handler: fn(Self),
+    ) -> Result<CancelableRequest, (Self, NTSTATUS)> {
+        let mut attributes = RequestCancelContext::object_attributes();
+        let mut context: PVOID = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: `wdf_request` is a private member of `Request`, which is guaranteed
+        // to be a valid request owned by the driver by `Request::from_raw`. If the
+        // request already has a `RequestCancelContext` from a previous call, WDF
+        // returns `STATUS_OBJECT_NAME_EXISTS`, which is a success status.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfObjectAllocateContext,
+                self.wdf_request.cast(),
+                &mut attributes,
+                &mut context,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err((self, nt_status));
+        }
+
+        // SAFETY: The context is only referenced by `evt_request_cancel`, which is not
+        // running since the request is owned by the driver and not cancelable.
+        unsafe {
+            drop_context::<RequestCancelContext>(self.wdf_request.cast());
+        }
+        if self
+            .init_context(RequestCancelContext { cancel_handler })
+            .is_err()
+        {
+            unreachable!("context of the request should have been allocated and uninitialized");
+        }
+
+        let nt_status;
+        // SAFETY: `wdf_request` is a private member of `Request`, which is guaranteed
+        // to be a valid request owned by the driver by `Request::from_raw`. The
+        // context read by `evt_request_cancel` was initialized above.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfRequestMarkCancelableEx,
+                self.wdf_request,
+                Some(evt_request_cancel),
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err((self, nt_status));
+        }
+        Ok(CancelableRequest { request: self })
+    }
+
+    /// Acknowledge that the queue of the request is stopped, and give the
+    /// request back to the queue, consuming it. The framework delivers the
+    /// request again once the queue resumes.
+    ///
+    /// This must only be called for a request that was passed to the handler
+    /// registered via
+    /// [`IoctlRouter::on_io_stop`](crate::wdf::IoctlRouter::on_io_stop),
+    /// and that was not acknowledged or completed yet.
+    pub fn acknowledge_stop_and_requeue(self) {
+        // SAFETY: `wdf_request` is a private member of `Request`, which is guaranteed
+        // to be a valid request owned by the driver by `Request::from_raw`.
+        // Ownership of the request is transferred back to its queue, and `self` is
+        // consumed.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfRequestStopAcknowledge,
+                self.wdf_request,
+                u8::from(true),
+            );
+        }
+    }
+
+    /// Release a request that the driver pends (ex. until the device has data)
+    /// when its queue is stopped with `reason`, consuming it, so that it does
+    /// not hold up the power transition of the device:
+    ///     * for [`IoStopAction::Suspend`], the request is requeued via
+    ///       [`Request::acknowledge_stop_and_requeue`], and delivered again
+    ///       once the queue resumes
+    ///     * for [`IoStopAction::Purge`], the request is completed with
+    ///       `STATUS_CANCELLED`
+    ///
+    /// This must only be called for a request that was passed to the handler
+    /// registered via
+    /// [`IoctlRouter::on_io_stop`](crate::wdf::IoctlRouter::on_io_stop),
+    /// and that was not acknowledged or completed yet.
+    pub fn release_for_stop(self, reason: IoStopReason) {
+        match reason.action() {
+            IoStopAction::Suspend => self.acknowledge_stop_and_requeue(),
+            IoStopAction::Purge => self.complete(wdk_sys::STATUS_CANCELLED),
+        }
+    }
+
+    /// Complete the request with `nt_status`, consuming it
+    pub fn complete(self, nt_status: NTSTATUS) {
+        // SAFETY: `wdf_request` is a private member of `Request`, which is guaranteed
+        // to be a valid request owned by the driver by `Request::from_raw`. Consuming
+        // `self` guarantees that the request is completed exactly once.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfRequestComplete,
+                self.wdf_request,
+                nt_status,
+            );
+        }
+    }
+
+    /// Complete the request with `nt_status`, consuming it. `information` is
+    /// returned to the requester, ex. as the number of bytes that were
+    /// transferred.
+    pub fn complete_with_information(self, nt_status: NTSTATUS, information: usize) {
+        // SAFETY: `wdf_request` is a private member of `Request`, which is guaranteed
+        // to be a valid request owned by the driver by `Request::from_raw`. Consuming
+        // `self` guarantees that the request is completed exactly once.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfRequestCompleteWithInformation,
+                self.wdf_request,
+                nt_status,
+                information as ULONG_PTR,
+            );
+        }
+    }
+}
+
+// SAFETY: WDF request handles are not tied to the thread that received them, so
+// the driver can complete or otherwise process a request from any thread (ex.
+// from a work item).
+unsafe impl Send for Request {}
+
+// SAFETY: `wdf_request` is a private member of `Request`, which is guaranteed
+// to be a valid request owned by the driver by `Request::from_raw`.
+unsafe impl ObjectHandle for Request {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_request.cast()
+    }
+}
+
+impl CancelableRequest {
+    /// Get the underlying `WDFREQUEST`
+    #[must_use]
+    pub const fn as_raw(&self) -> WDFREQUEST {
+        self.request.wdf_request
+    }
+
+    /// Reclaim the request from the framework, so that its cancel handler is
+    /// no longer invoked and the driver can complete it.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the request has already been canceled, in which case its cancel handler has been or will be invoked with the ownership of the request. The error variant will contain a [`NTSTATUS`] of the failure, typically `STATUS_CANCELLED`. Full error documentation is available in the [WDFRequest Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfrequest/nf-wdfrequest-wdfrequestunmarkcancelable#return-value)
+    pub fn unmark_cancelable(self) -> Result<Request, NTSTATUS> {
+        let nt_status;
+        // SAFETY: `request` is a private member of `CancelableRequest`, which is only
+        // constructed from a valid request that was marked as cancelable.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfRequestUnmarkCancelable,
+                self.request.wdf_request,
+            );
+        }
+        nt_success(nt_status)
+            .then_some(self.request)
+            .ok_or(nt_status)
+    }
+}
+
+// SAFETY: `request` is a private member of `CancelableRequest`, which is only
+// constructed from a valid request. The request stays valid until it is
+// completed, which requires reclaiming it via
+// `CancelableRequest::unmark_cancelable` or the cancel handler.
+unsafe impl ObjectHandle for CancelableRequest {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.request.wdf_request.cast()
+    }
+}
+
+/// `EvtRequestCancel` callback of requests marked as cancelable via
+/// [`Request::mark_cancelable`]
+unsafe extern "C" fn evt_request_cancel(wdf_request: WDFREQUEST) {
+    // SAFETY: WDF passes a valid request, whose ownership is transferred to the
+    // cancel routine.
+    let request = unsafe { Request::from_raw(wdf_request) };
+    let Some(context) = request.context::<RequestCancelContext>() else {
+        unreachable!("context of a cancelable request should be initialized");
+    };
+
+    let cancel_handler = context.cancel_handler;
+    cancel_handler(request);
+}
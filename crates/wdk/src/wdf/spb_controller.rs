@@ -0,0 +1,823 @@
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use core::{ffi::c_void, marker::PhantomData, ptr::NonNull};
+
+#[cfg(feature = "alloc")]
+use wdk_sys::PVOID;
+use wdk_sys::{
+    macros,
+    spb::{
+        self,
+        SPBREQUEST,
+        SPBTARGET,
+        SPB_CONNECTION_PARAMETERS,
+        SPB_CONTROLLER_CONFIG,
+        SPB_REQUEST_PARAMETERS,
+        SPB_TRANSFER_DESCRIPTOR,
+    },
+    _WDF_IO_QUEUE_DISPATCH_TYPE,
+    _WDF_TRI_STATE,
+    MDL,
+    NTSTATUS,
+    PMDL,
+    ULONG,
+    ULONG_PTR,
+    WDFDEVICE,
+    WDFOBJECT,
+};
+
+#[cfg(feature = "alloc")]
+use crate::wdf::{Device, ObjectContext};
+use crate::{
+    mdl::system_address_for_mdl,
+    nt_success,
+    wdf::{DeviceInit, FromWdfObject, ObjectHandle},
+};
+
+/// Call the `SpbCx` function whose function pointer type is
+/// `$function_pointer_type` and whose index in the `SpbCx` function table is
+/// `$function_table_index`, with the driver's `SpbCx` driver globals followed by
+/// the `$argument`s. This mirrors the inlined `SpbCx` functions in `spbcx.h`,
+/// and must be invoked from an `unsafe` block.
+macro_rules! call_unsafe_spb_function {
+    ($function_pointer_type:ident, $function_table_index:ident $(, $argument:expr)* $(,)?) => {{
+        let spb_function = spb::spb_function(spb::_SPBFUNCENUM::$function_table_index);
+        // SAFETY: This `transmute` from a no-argument function pointer to a function
+        // pointer with the correct arguments for the `SpbCx` function is safe because
+        // `SpbCx` maintains the strict mapping between the function table index and
+        // the correct function pointer type.
+        let spb_function = unsafe {
+            core::mem::transmute::<spb::SPBFUNC, spb::$function_pointer_type>(spb_function)
+        };
+        let Some(spb_function) = spb_function else {
+            unreachable!("Option should never be None");
+        };
+        // The function pointer is always valid because it is an entry in the `SpbCx`
+        // function table, guarded by the type-safety of `$function_pointer_type`. The
+        // `unsafe` block that this macro is invoked from must guarantee that the
+        // arguments abide by the rules of the `SpbCx` function.
+        spb_function(spb::spb_driver_globals() $(, $argument)*)
+    }};
+}
+
+/// Simple Peripheral Bus (SPB) controller, implemented on top of the SPB
+/// framework extension (`SpbCx`).
+///
+/// I2C and SPI controller drivers configure their device via
+/// [`SpbController::configure_device_init`] before it is created, and then
+/// register their controller callbacks via [`SpbController::initialize`] (or
+/// [`SpbController::try_initialize`] with a raw `SPB_CONTROLLER_CONFIG`).
+/// `SpbCx` then delivers the I/O of the peripherals connected to the
+/// controller as [`SpbRequest`]s, each of which describes a list of
+/// [`SpbTransfer`]s.
+pub struct SpbController;
+
+/// Callbacks invoked by `SpbCx` for the controller registered via
+/// [`SpbController::initialize`].
+///
+/// `SpbCx` delivers at most one I/O request at a time to the controller, and
+/// the controller owns each [`SpbRequest`] that it receives until it completes
+/// it via [`SpbRequest::complete`] or
+/// [`SpbRequest::complete_with_information`].
+#[cfg(feature = "alloc")]
+pub trait SpbControllerHandler: Send + Sync {
+    /// Called by `SpbCx` when a peripheral driver opens a connection to
+    /// `target`, ex. to validate its connection parameters (see
+    /// [`SpbTarget::connection_parameters`])
+    ///
+    /// # Errors
+    ///
+    /// The [`NTSTATUS`] of the failure is reported to `SpbCx`, which fails the
+    /// peripheral driver's open request.
+    fn target_connect(&self, _controller: &Device, _target: &SpbTarget) -> Result<(), NTSTATUS> {
+        Ok(())
+    }
+
+    /// Called by `SpbCx` when a peripheral driver closes its connection to
+    /// `target`
+    fn target_disconnect(&self, _controller: &Device, _target: &SpbTarget) {}
+
+    /// Called by `SpbCx` when a peripheral driver locks the bus for a
+    /// sequence of I/O requests to `target`. The default implementation
+    /// completes `request` successfully.
+    fn controller_lock(&self, _controller: &Device, _target: &SpbTarget, request: SpbRequest) {
+        request.complete(wdk_sys::STATUS_SUCCESS);
+    }
+
+    /// Called by `SpbCx` when a peripheral driver unlocks the bus locked via
+    /// [`SpbControllerHandler::controller_lock`]. The default implementation
+    /// completes `request` successfully.
+    fn controller_unlock(&self, _controller: &Device, _target: &SpbTarget, request: SpbRequest) {
+        request.complete(wdk_sys::STATUS_SUCCESS);
+    }
+
+    /// Called by `SpbCx` to read `length` bytes from `target` into the buffer
+    /// of the single [`SpbTransfer`] of `request`
+    fn read(&self, controller: &Device, target: &SpbTarget, request: SpbRequest, length: usize);
+
+    /// Called by `SpbCx` to write `length` bytes from the buffer of the single
+    /// [`SpbTransfer`] of `request` to `target`
+    fn write(&self, controller: &Device, target: &SpbTarget, request: SpbRequest, length: usize);
+
+    /// Called by `SpbCx` to perform the `transfer_count` [`SpbTransfer`]s of
+    /// `request` (see [`SpbRequest::transfers`]) as a single bus operation
+    fn sequence(
+        &self,
+        controller: &Device,
+        target: &SpbTarget,
+        request: SpbRequest,
+        transfer_count: ULONG,
+    );
+}
+
+#[cfg(feature = "alloc")]
+crate::wdf_declare_context_type!(
+    /// Context stored in every device registered via
+    /// [`SpbController::initialize`]
+    struct SpbControllerContext {
+        handler: Box<dyn SpbControllerHandler>,
+    }
+);
+
+/// SPB target, which represents the connection of a peripheral driver to a
+/// device on the bus of an [`SpbController`]
+pub struct SpbTarget {
+    spb_target: SPBTARGET,
+}
+
+/// SPB I/O request delivered to an [`SpbController`].
+///
+/// The controller owns the request until it completes it via
+/// [`SpbRequest::complete`] or [`SpbRequest::complete_with_information`],
+/// which consume the [`SpbRequest`].
+pub struct SpbRequest {
+    spb_request: SPBREQUEST,
+}
+
+/// Direction of the data of an [`SpbTransfer`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SpbTransferDirection {
+    /// The controller reads data from the device into the transfer buffer
+    /// (`SpbTransferDirectionFromDevice`)
+    FromDevice,
+    /// The controller writes data from the transfer buffer to the device
+    /// (`SpbTransferDirectionToDevice`)
+    ToDevice,
+}
+
+/// A transfer of an [`SpbRequest`], which describes the direction, length and
+/// delay of the transfer along with the MDL chain of its buffer. The buffer
+/// pages are locked by `SpbCx` for as long as the request is not completed.
+pub struct SpbTransfer<'a> {
+    descriptor: SPB_TRANSFER_DESCRIPTOR,
+    buffer: Option<NonNull<MDL>>,
+    _marker: PhantomData<&'a SpbRequest>,
+}
+
+/// Iterator over the [`SpbTransfer`]s of an [`SpbRequest`], in the order in
+/// which they must be performed
+pub struct SpbTransfers<'a> {
+    request: &'a SpbRequest,
+    index: ULONG,
+    transfer_count: ULONG,
+}
+
+impl SpbController {
+    /// Configure the device that is being initialized by `device_init` to be
+    /// an SPB controller. This must be called from `EvtDriverDeviceAdd`,
+    /// before the device is created.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `SpbCx` fails to configure the device. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [SpbCx Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/spbcx/nf-spbcx-spbdeviceinitconfig#return-value)
+    pub fn configure_device_init(device_init: &mut DeviceInit<'_>) -> Result<(), NTSTATUS> {
+        let nt_status;
+        // SAFETY: `DeviceInit` guarantees that its `PWDFDEVICE_INIT` is valid and has
+        // not been used to create a device yet.
+        unsafe {
+            nt_status = call_unsafe_spb_function!(
+                PFN_SPBDEVICEINITCONFIG,
+                SpbDeviceInitConfigTableIndex,
+                device_init.as_raw(),
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Construct an `SPB_CONTROLLER_CONFIG` without any callbacks, whose I/O
+    /// is dispatched sequentially and whose power management is determined by
+    /// `SpbCx`. This is equivalent to `SPB_CONTROLLER_CONFIG_INIT`.
+    #[must_use]
+    pub fn config() -> SPB_CONTROLLER_CONFIG {
+        // SAFETY: `SPB_CONTROLLER_CONFIG` is a C struct of integers and optional
+        // function pointers, for which all-zero is a valid value. This mirrors the
+        // `RtlZeroMemory` in `SPB_CONTROLLER_CONFIG_INIT`.
+        let mut config: SPB_CONTROLLER_CONFIG = unsafe { core::mem::zeroed() };
+        // The size of SPB_CONTROLLER_CONFIG is known to fit in a ULONG
+        #[allow(clippy::cast_possible_truncation)]
+        let size = core::mem::size_of::<SPB_CONTROLLER_CONFIG>() as ULONG;
+        config.Size = size;
+        config.ControllerDispatchType = _WDF_IO_QUEUE_DISPATCH_TYPE::WdfIoQueueDispatchSequential;
+        config.PowerManaged = _WDF_TRI_STATE::WdfUseDefault;
+        config
+    }
+
+    /// Try to register `device`, which was configured via
+    /// [`SpbController::configure_device_init`], as an SPB controller with the
+    /// callbacks in `config`. This must be called after `device` is created,
+    /// typically from `EvtDriverDeviceAdd`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `SpbCx` fails to register the controller. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [SpbCx Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/spbcx/nf-spbcx-spbdeviceinitialize#return-value)
+    pub fn try_initialize(
+        device: WDFDEVICE,
+        config: &mut SPB_CONTROLLER_CONFIG,
+    ) -> Result<(), NTSTATUS> {
+        let nt_status;
+        // SAFETY: `device` is a valid device handle configured via
+        // `SpbController::configure_device_init`, which is guaranteed by the caller.
+        unsafe {
+            nt_status = call_unsafe_spb_function!(
+                PFN_SPBDEVICEINITIALIZE,
+                SpbDeviceInitializeTableIndex,
+                device,
+                config,
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Try to register `device`, which was configured via
+    /// [`SpbController::configure_device_init`], as an SPB controller whose
+    /// callbacks are handled by an [`SpbControllerHandler`]. This must be
+    /// called after `device` is created, typically from `EvtDriverDeviceAdd`.
+    ///
+    /// `handler` is stored in an additional context space of `device`, and is
+    /// dropped when the framework destroys the device. The device's own
+    /// context (if any) is not affected.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the context space for `handler` cannot be allocated, or if `SpbCx` fails to register the controller. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [SpbCx Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/spbcx/nf-spbcx-spbdeviceinitialize#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn initialize<H>(device: &Device, handler: H) -> Result<(), NTSTATUS>
+    where
+        H: SpbControllerHandler + 'static,
+    {
+        let mut attributes = SpbControllerContext::object_attributes();
+        let mut context: PVOID = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: `Device` guarantees that its handle is valid.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfObjectAllocateContext,
+                device.as_wdf_object(),
+                &mut attributes,
+                &mut context,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status);
+        }
+        if device
+            .init_context(SpbControllerContext {
+                handler: Box::new(handler),
+            })
+            .is_err()
+        {
+            return Err(wdk_sys::STATUS_OBJECT_NAME_EXISTS);
+        }
+
+        let mut config = Self::config();
+        config.EvtSpbTargetConnect = Some(evt_spb_target_connect);
+        config.EvtSpbTargetDisconnect = Some(evt_spb_target_disconnect);
+        config.EvtSpbControllerLock = Some(evt_spb_controller_lock);
+        config.EvtSpbControllerUnlock = Some(evt_spb_controller_unlock);
+        config.EvtSpbIoRead = Some(evt_spb_controller_read);
+        config.EvtSpbIoWrite = Some(evt_spb_controller_write);
+        config.EvtSpbIoSequence = Some(evt_spb_controller_sequence);
+
+        Self::try_initialize(device.as_raw(), &mut config)
+    }
+}
+
+impl SpbTarget {
+    /// Get the connection parameters of the target, which point to the
+    /// `RH_QUERY_CONNECTION_PROPERTIES_OUTPUT_BUFFER` that describes the ACPI
+    /// connection resource of the device (ex. its I2C address and bus speed)
+    #[must_use]
+    pub fn connection_parameters(&self) -> Option<NonNull<c_void>> {
+        let mut connection_parameters = SPB_CONNECTION_PARAMETERS {
+            // The size of SPB_CONNECTION_PARAMETERS is known to fit in a ULONG
+            #[allow(clippy::cast_possible_truncation)]
+            Size: core::mem::size_of::<SPB_CONNECTION_PARAMETERS>() as ULONG,
+            ConnectionParameters: core::ptr::null_mut(),
+        };
+
+        // SAFETY: `spb_target` is a private member of `SpbTarget`, originally created
+        // by SpbCx, and this module guarantees that it is always in a valid state.
+        unsafe {
+            call_unsafe_spb_function!(
+                PFN_SPBTARGETGETCONNECTIONPARAMETERS,
+                SpbTargetGetConnectionParametersTableIndex,
+                self.spb_target,
+                &mut connection_parameters,
+            );
+        }
+        NonNull::new(connection_parameters.ConnectionParameters)
+    }
+}
+
+// SAFETY: `spb_target` is a private member of `SpbTarget`, originally created
+// by SpbCx, and this module guarantees that it is always in a valid state.
+unsafe impl ObjectHandle for SpbTarget {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.spb_target.cast()
+    }
+}
+
+// SAFETY: `SpbTarget` does not delete the underlying object when dropped.
+unsafe impl FromWdfObject for SpbTarget {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            spb_target: wdf_object.cast(),
+        }
+    }
+}
+
+impl SpbRequest {
+    /// Construct an [`SpbRequest`] from the raw `SPBREQUEST` passed to an
+    /// `SpbCx` controller callback
+    ///
+    /// # Safety
+    ///
+    /// `spb_request` must be a valid request that is owned by the driver, and
+    /// that is not completed other than via the returned [`SpbRequest`].
+    #[must_use]
+    pub const unsafe fn from_raw(spb_request: SPBREQUEST) -> Self {
+        Self { spb_request }
+    }
+
+    /// Get the raw `SPBREQUEST` handle of the request
+    #[must_use]
+    pub const fn as_raw(&self) -> SPBREQUEST {
+        self.spb_request
+    }
+
+    /// Get the raw parameters of the request, which include its type and the
+    /// position of the request in a sequence of I/O requests to a locked bus
+    #[must_use]
+    pub fn parameters(&self) -> SPB_REQUEST_PARAMETERS {
+        // SAFETY: `SPB_REQUEST_PARAMETERS` is a C struct of integers, for which
+        // all-zero is a valid value. This mirrors the `RtlZeroMemory` in
+        // `SPB_REQUEST_PARAMETERS_INIT`.
+        let mut parameters: SPB_REQUEST_PARAMETERS = unsafe { core::mem::zeroed() };
+        // The size of SPB_REQUEST_PARAMETERS is known to fit in a ULONG
+        #[allow(clippy::cast_possible_truncation)]
+        let size = core::mem::size_of::<SPB_REQUEST_PARAMETERS>() as ULONG;
+        parameters.Size = size;
+
+        // SAFETY: `spb_request` is a private member of `SpbRequest`, which is
+        // guaranteed to be a valid request owned by the driver by `SpbRequest::from_raw`.
+        unsafe {
+            call_unsafe_spb_function!(
+                PFN_SPBREQUESTGETPARAMETERS,
+                SpbRequestGetParametersTableIndex,
+                self.spb_request,
+                &mut parameters,
+            );
+        }
+        parameters
+    }
+
+    /// Get the number of [`SpbTransfer`]s of the request: the transfer count
+    /// of a sequence request, one for a read or write request, and zero for
+    /// any other request
+    #[must_use]
+    pub fn transfer_count(&self) -> ULONG {
+        let parameters = self.parameters();
+        match parameters.Type {
+            spb::_SPB_REQUEST_TYPE::SpbRequestTypeSequence => parameters.SequenceTransferCount,
+            spb::_SPB_REQUEST_TYPE::SpbRequestTypeRead
+            | spb::_SPB_REQUEST_TYPE::SpbRequestTypeWrite => 1,
+            _ => 0,
+        }
+    }
+
+    /// Get the transfer at `index` of the request, which must be less than
+    /// [`SpbRequest::transfer_count`]
+    #[must_use]
+    pub fn transfer(&self, index: ULONG) -> SpbTransfer<'_> {
+        // Equivalent to SPB_TRANSFER_DESCRIPTOR_INIT
+        let mut descriptor = SPB_TRANSFER_DESCRIPTOR {
+            // The size of SPB_TRANSFER_DESCRIPTOR is known to fit in a ULONG
+            #[allow(clippy::cast_possible_truncation)]
+            Size: core::mem::size_of::<SPB_TRANSFER_DESCRIPTOR>() as ULONG,
+            Direction: spb::_SPB_TRANSFER_DIRECTION::SpbTransferDirectionNone,
+            TransferLength: 0,
+            DelayInUs: 0,
+        };
+        let mut buffer: PMDL = core::ptr::null_mut();
+
+        // SAFETY: `spb_request` is a private member of `SpbRequest`, which is
+        // guaranteed to be a valid request owned by the driver by `SpbRequest::from_raw`.
+        unsafe {
+            call_unsafe_spb_function!(
+                PFN_SPBREQUESTGETTRANSFERPARAMETERS,
+                SpbRequestGetTransferParametersTableIndex,
+                self.spb_request,
+                index,
+                &mut descriptor,
+                &mut buffer,
+            );
+        }
+
+        SpbTransfer {
+            descriptor,
+            buffer: NonNull::new(buffer),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Iterate over the transfers of the request, in the order in which they
+    /// must be performed
+    #[must_use]
+    pub fn transfers(&self) -> SpbTransfers<'_> {
+        SpbTransfers {
+            request: self,
+            index: 0,
+            transfer_count: self.transfer_count(),
+        }
+    }
+
+    /// Complete the request with `nt_status`, consuming it
+    pub fn complete(self, nt_status: NTSTATUS) {
+        // SAFETY: `spb_request` is a private member of `SpbRequest`, which is
+        // guaranteed to be a valid request owned by the driver by
+        // `SpbRequest::from_raw`. Consuming `self` guarantees that the request is
+        // completed exactly once.
+        unsafe {
+            call_unsafe_spb_function!(
+                PFN_SPBREQUESTCOMPLETE,
+                SpbRequestCompleteTableIndex,
+                self.spb_request,
+                nt_status,
+            );
+        }
+    }
+
+    /// Complete the request with `nt_status`, reporting that `information`
+    /// bytes were transferred, consuming it
+    pub fn complete_with_information(self, nt_status: NTSTATUS, information: usize) {
+        // SAFETY: `spb_request` is a private member of `SpbRequest`, which is
+        // guaranteed to be a valid request owned by the driver by
+        // `SpbRequest::from_raw`. SPB requests are WDF requests.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfRequestSetInformation,
+                self.spb_request.cast(),
+                information as ULONG_PTR,
+            );
+        }
+        self.complete(nt_status);
+    }
+}
+
+// SAFETY: `spb_request` is a private member of `SpbRequest`, which is
+// guaranteed to be a valid request owned by the driver by
+// `SpbRequest::from_raw`.
+unsafe impl ObjectHandle for SpbRequest {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.spb_request.cast()
+    }
+}
+
+impl SpbTransfer<'_> {
+    /// Get the direction of the data of the transfer, or `None` if `SpbCx`
+    /// did not report a direction (`SpbTransferDirectionNone`)
+    #[must_use]
+    pub const fn direction(&self) -> Option<SpbTransferDirection> {
+        match self.descriptor.Direction {
+            spb::_SPB_TRANSFER_DIRECTION::SpbTransferDirectionFromDevice => {
+                Some(SpbTransferDirection::FromDevice)
+            }
+            spb::_SPB_TRANSFER_DIRECTION::SpbTransferDirectionToDevice => {
+                Some(SpbTransferDirection::ToDevice)
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the number of bytes to transfer
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.descriptor.TransferLength
+    }
+
+    /// Returns `true` if the transfer has no data
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the delay, in microseconds, that the controller must wait before
+    /// performing the transfer
+    #[must_use]
+    pub const fn delay_in_us(&self) -> ULONG {
+        self.descriptor.DelayInUs
+    }
+
+    /// Get the raw `SPB_TRANSFER_DESCRIPTOR` of the transfer
+    #[must_use]
+    pub const fn descriptor(&self) -> &SPB_TRANSFER_DESCRIPTOR {
+        &self.descriptor
+    }
+
+    /// Get the first MDL of the chain that describes the buffer of the
+    /// transfer, or null if the transfer has no buffer
+    #[must_use]
+    pub fn buffer_mdl(&self) -> PMDL {
+        self.buffer.map_or(core::ptr::null_mut(), NonNull::as_ptr)
+    }
+
+    /// Copy the data of the transfer buffer, starting `offset` bytes into it,
+    /// into `destination`. This is typically used for
+    /// [`SpbTransferDirection::ToDevice`] transfers, to get the data to write
+    /// to the device. Returns the number of bytes copied, which is less than
+    /// the length of `destination` if the end of the transfer is reached.
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INSUFFICIENT_RESOURCES` if the
+    /// buffer could not be mapped into system address space.
+    pub fn copy_from_buffer(
+        &self,
+        offset: usize,
+        destination: &mut [u8],
+    ) -> Result<usize, NTSTATUS> {
+        self.for_each_segment(offset, destination.len(), |segment, position, count| {
+            // SAFETY: `for_each_segment` guarantees that `segment` is valid for `count`
+            // bytes, and that `position + count` is within `destination`, which cannot
+            // overlap the transfer buffer that it does not own.
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    segment,
+                    destination.as_mut_ptr().add(position),
+                    count,
+                );
+            }
+        })
+    }
+
+    /// Copy `source` into the transfer buffer, starting `offset` bytes into
+    /// it. This is typically used for [`SpbTransferDirection::FromDevice`]
+    /// transfers, to return the data read from the device. Returns the number
+    /// of bytes copied, which is less than the length of `source` if the end
+    /// of the transfer is reached.
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INSUFFICIENT_RESOURCES` if the
+    /// buffer could not be mapped into system address space.
+    pub fn copy_to_buffer(&self, offset: usize, source: &[u8]) -> Result<usize, NTSTATUS> {
+        self.for_each_segment(offset, source.len(), |segment, position, count| {
+            // SAFETY: `for_each_segment` guarantees that `segment` is valid for `count`
+            // bytes, and that `position + count` is within `source`, which cannot
+            // overlap the transfer buffer that it does not own.
+            unsafe {
+                core::ptr::copy_nonoverlapping(source.as_ptr().add(position), segment, count);
+            }
+        })
+    }
+
+    /// Invoke `copy` for each contiguous segment of the transfer buffer, from
+    /// `offset` bytes into it up to `length` bytes (or the end of the
+    /// transfer), with a pointer to the segment mapped in system address
+    /// space, the number of bytes of the range preceding the segment, and the
+    /// length of the segment. Returns the total length of the segments.
+    fn for_each_segment(
+        &self,
+        offset: usize,
+        length: usize,
+        mut copy: impl FnMut(*mut u8, usize, usize),
+    ) -> Result<usize, NTSTATUS> {
+        let length = length.min(self.len().saturating_sub(offset));
+        let mut skipped_length = offset;
+        let mut copied_length = 0;
+        let mut mdl = self.buffer;
+
+        while copied_length < length {
+            let Some(current_mdl) = mdl else {
+                break;
+            };
+            // SAFETY: SpbCx guarantees that the MDL chain of the transfer is valid until
+            // the request is completed, which cannot happen while `self` borrows it.
+            let (byte_count, next_mdl) = unsafe {
+                let current_mdl = current_mdl.as_ref();
+                (current_mdl.ByteCount as usize, current_mdl.Next)
+            };
+            mdl = NonNull::new(next_mdl);
+
+            if skipped_length >= byte_count {
+                skipped_length -= byte_count;
+                continue;
+            }
+
+            // SAFETY: SpbCx locks the pages of the MDL chain until the request is
+            // completed, and the request is only accessed by its owner, which holds
+            // `self`.
+            let segment = unsafe { system_address_for_mdl(current_mdl) }?;
+            let count = (byte_count - skipped_length).min(length - copied_length);
+            // SAFETY: `skipped_length` is less than `byte_count`, so the offset stays
+            // within the segment mapped for the MDL.
+            let segment = unsafe { segment.as_ptr().add(skipped_length) };
+            copy(segment, copied_length, count);
+
+            copied_length += count;
+            skipped_length = 0;
+        }
+
+        Ok(copied_length)
+    }
+}
+
+impl<'a> Iterator for SpbTransfers<'a> {
+    type Item = SpbTransfer<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.transfer_count {
+            return None;
+        }
+
+        let transfer = self.request.transfer(self.index);
+        self.index += 1;
+        Some(transfer)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.transfer_count - self.index) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for SpbTransfers<'_> {}
+
+/// Reconstruct the controller and target passed to an `SpbCx` callback, and
+/// invoke `callback` with the [`SpbControllerHandler`] stored in the
+/// controller's context space
+#[cfg(feature = "alloc")]
+fn with_controller_handler<R>(
+    controller: WDFDEVICE,
+    target: SPBTARGET,
+    callback: impl FnOnce(&dyn SpbControllerHandler, &Device, &SpbTarget) -> R,
+) -> Option<R> {
+    // SAFETY: SpbCx passes a valid device handle to its callbacks.
+    let controller = unsafe { Device::from_wdf_object(controller.cast()) };
+    let target = SpbTarget { spb_target: target };
+    let context = controller.context::<SpbControllerContext>()?;
+    Some(callback(context.handler.as_ref(), &controller, &target))
+}
+
+/// `EvtSpbTargetConnect` trampoline that forwards to the
+/// [`SpbControllerHandler`] stored in the controller's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_spb_target_connect(controller: WDFDEVICE, target: SPBTARGET) -> NTSTATUS {
+    match with_controller_handler(controller, target, |handler, controller, target| {
+        handler.target_connect(controller, target)
+    }) {
+        Some(Ok(())) => wdk_sys::STATUS_SUCCESS,
+        Some(Err(nt_status)) => nt_status,
+        None => wdk_sys::STATUS_INVALID_DEVICE_STATE,
+    }
+}
+
+/// `EvtSpbTargetDisconnect` trampoline that forwards to the
+/// [`SpbControllerHandler`] stored in the controller's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_spb_target_disconnect(controller: WDFDEVICE, target: SPBTARGET) {
+    with_controller_handler(controller, target, |handler, controller, target| {
+        handler.target_disconnect(controller, target);
+    });
+}
+
+/// Forward `request` to `callback`, or complete it with
+/// `STATUS_INVALID_DEVICE_STATE` if the controller has no
+/// [`SpbControllerHandler`]
+#[cfg(feature = "alloc")]
+fn dispatch_request(
+    controller: WDFDEVICE,
+    target: SPBTARGET,
+    spb_request: SPBREQUEST,
+    callback: impl FnOnce(&dyn SpbControllerHandler, &Device, &SpbTarget, SpbRequest),
+) {
+    // SAFETY: SpbCx transfers the ownership of the request to the controller
+    // callback that it is passed to.
+    let request = unsafe { SpbRequest::from_raw(spb_request) };
+    let mut request = Some(request);
+    with_controller_handler(controller, target, |handler, controller, target| {
+        if let Some(request) = request.take() {
+            callback(handler, controller, target, request);
+        }
+    });
+    if let Some(request) = request {
+        request.complete(wdk_sys::STATUS_INVALID_DEVICE_STATE);
+    }
+}
+
+/// `EvtSpbControllerLock` trampoline that forwards to the
+/// [`SpbControllerHandler`] stored in the controller's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_spb_controller_lock(
+    controller: WDFDEVICE,
+    target: SPBTARGET,
+    spb_request: SPBREQUEST,
+) {
+    dispatch_request(
+        controller,
+        target,
+        spb_request,
+        |handler, controller, target, request| {
+            handler.controller_lock(controller, target, request);
+        },
+    );
+}
+
+/// `EvtSpbControllerUnlock` trampoline that forwards to the
+/// [`SpbControllerHandler`] stored in the controller's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_spb_controller_unlock(
+    controller: WDFDEVICE,
+    target: SPBTARGET,
+    spb_request: SPBREQUEST,
+) {
+    dispatch_request(
+        controller,
+        target,
+        spb_request,
+        |handler, controller, target, request| {
+            handler.controller_unlock(controller, target, request);
+        },
+    );
+}
+
+/// `EvtSpbIoRead` trampoline that forwards to the [`SpbControllerHandler`]
+/// stored in the controller's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_spb_controller_read(
+    controller: WDFDEVICE,
+    target: SPBTARGET,
+    spb_request: SPBREQUEST,
+    length: usize,
+) {
+    dispatch_request(
+        controller,
+        target,
+        spb_request,
+        |handler, controller, target, request| {
+            handler.read(controller, target, request, length);
+        },
+    );
+}
+
+/// `EvtSpbIoWrite` trampoline that forwards to the [`SpbControllerHandler`]
+/// stored in the controller's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_spb_controller_write(
+    controller: WDFDEVICE,
+    target: SPBTARGET,
+    spb_request: SPBREQUEST,
+    length: usize,
+) {
+    dispatch_request(
+        controller,
+        target,
+        spb_request,
+        |handler, controller, target, request| {
+            handler.write(controller, target, request, length);
+        },
+    );
+}
+
+/// `EvtSpbIoSequence` trampoline that forwards to the [`SpbControllerHandler`]
+/// stored in the controller's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_spb_controller_sequence(
+    controller: WDFDEVICE,
+    target: SPBTARGET,
+    spb_request: SPBREQUEST,
+    transfer_count: ULONG,
+) {
+    dispatch_request(
+        controller,
+        target,
+        spb_request,
+        |handler, controller, target, request| {
+            handler.sequence(controller, target, request, transfer_count);
+        },
+    );
+}
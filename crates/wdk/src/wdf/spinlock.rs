@@ -1,6 +1,9 @@
-use wdk_sys::{macros, NTSTATUS, WDFSPINLOCK, WDF_OBJECT_ATTRIBUTES};
+use wdk_sys::{macros, NTSTATUS, WDFOBJECT, WDFSPINLOCK, WDF_OBJECT_ATTRIBUTES};
 
-use crate::nt_success;
+use crate::{
+    nt_success,
+    wdf::{FromWdfObject, ObjectHandle},
+};
 
 /// WDF Spin Lock.
 ///
@@ -71,3 +74,20 @@ pub fn release(&self) {
         }
     }
 }
+
+// SAFETY: `wdf_spin_lock` is a private member of `SpinLock`, originally created
+// by WDF, and this module guarantees that it is always in a valid state.
+unsafe impl ObjectHandle for SpinLock {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_spin_lock.cast()
+    }
+}
+
+// SAFETY: `SpinLock` does not delete the underlying object when dropped.
+unsafe impl FromWdfObject for SpinLock {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            wdf_spin_lock: wdf_object.cast(),
+        }
+    }
+}
@@ -0,0 +1,172 @@
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+use core::fmt;
+
+use wdk_sys::{
+    macros,
+    NTSTATUS,
+    UNICODE_STRING,
+    WDFOBJECT,
+    WDFSTRING,
+    WDF_NO_OBJECT_ATTRIBUTES,
+    WDF_OBJECT_ATTRIBUTES,
+};
+
+#[cfg(feature = "alloc")]
+use crate::string::NtUnicodeString;
+use crate::{nt_success, string::NtUnicodeStr, wdf::ObjectHandle};
+
+/// WDF String.
+///
+/// A framework string object holds a counted UTF-16 string whose buffer is
+/// managed by WDF. Some WDF APIs (ex.
+/// [`RegistryKey::query_string`](crate::wdf::RegistryKey::query_string)) return
+/// their result by filling a [`WdfString`]. Its contents can be borrowed
+/// as a [`NtUnicodeStr`] via [`WdfString::as_unicode_str`]. The string object
+/// is deleted when the [`WdfString`] is dropped.
+pub struct WdfString {
+    wdf_string: WDFSTRING,
+}
+
+impl WdfString {
+    /// Try to construct a WDF String object containing a copy of `string`, or
+    /// an empty string if `string` is [`None`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a string. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFString Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfstring/nf-wdfstring-wdfstringcreate#return-value)
+    pub fn try_new(
+        string: Option<NtUnicodeStr<'_>>,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        let mut wdf_string = Self {
+            wdf_string: core::ptr::null_mut(),
+        };
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state. WDF copies `string` into a buffer it owns.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfStringCreate,
+                string
+                    .as_ref()
+                    .map_or(core::ptr::null(), NtUnicodeStr::as_raw),
+                attributes.map_or(WDF_NO_OBJECT_ATTRIBUTES, core::ptr::from_mut),
+                &mut wdf_string.wdf_string,
+            );
+        }
+        nt_success(nt_status).then_some(wdf_string).ok_or(nt_status)
+    }
+
+    /// Try to construct a WDF String object containing a copy of `string`, or
+    /// an empty string if `string` is [`None`]. This is an alias for
+    /// [`WdfString::try_new()`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a string. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFString Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfstring/nf-wdfstring-wdfstringcreate#return-value)
+    pub fn create(
+        string: Option<NtUnicodeStr<'_>>,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        Self::try_new(string, attributes)
+    }
+
+    /// Try to construct a WDF String object containing `string` encoded as
+    /// UTF-16
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INVALID_PARAMETER` if the UTF-16
+    /// encoding of `string` is longer than [`MAX_LEN`](crate::string::MAX_LEN)
+    /// code units, or any error returned by [`WdfString::try_new`].
+    #[cfg(feature = "alloc")]
+    pub fn try_from_str(
+        string: &str,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        Self::try_new(
+            Some(NtUnicodeString::try_from_str(string)?.as_unicode_str()),
+            attributes,
+        )
+    }
+
+    /// Get the underlying `WDFSTRING`, ex. to pass it to a WDF API that fills
+    /// it
+    #[must_use]
+    pub const fn as_raw(&self) -> WDFSTRING {
+        self.wdf_string
+    }
+
+    /// Borrow the contents of this string as a [`NtUnicodeStr`]
+    #[must_use]
+    pub fn as_unicode_str(&self) -> NtUnicodeStr<'_> {
+        let mut unicode_string = UNICODE_STRING::default();
+
+        // SAFETY: `wdf_string` is a private member of `WdfString`, originally created
+        // by WDF, and this module guarantees that it is always in a valid state.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfStringGetUnicodeString,
+                self.wdf_string,
+                &mut unicode_string,
+            );
+        }
+
+        // SAFETY: `unicode_string` describes the buffer owned by the string object,
+        // which is only modified or freed by WDF APIs that require a `&mut WdfString`
+        // or consume it, so it outlives the returned borrow of `self`.
+        unsafe { NtUnicodeStr::from_raw(&unicode_string) }
+    }
+
+    /// Copy the contents of this string into a [`String`]. Invalid UTF-16 is
+    /// replaced with [`char::REPLACEMENT_CHARACTER`].
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub fn to_string_lossy(&self) -> String {
+        String::from_utf16_lossy(self.as_unicode_str().as_slice())
+    }
+}
+
+impl Drop for WdfString {
+    fn drop(&mut self) {
+        // SAFETY: `wdf_string` is a private member of `WdfString`, originally created
+        // by WDF, and this module guarantees that it is always in a valid state. It is
+        // not used after it is deleted here.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(WdfObjectDelete, self.wdf_string.cast());
+        }
+    }
+}
+
+// SAFETY: `wdf_string` is a private member of `WdfString`, originally created
+// by WDF, and this module guarantees that it is always in a valid state.
+unsafe impl ObjectHandle for WdfString {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_string.cast()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<&WdfString> for NtUnicodeString {
+    fn from(wdf_string: &WdfString) -> Self {
+        wdf_string.as_unicode_str().into()
+    }
+}
+
+impl fmt::Display for WdfString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.as_unicode_str(), f)
+    }
+}
+
+impl fmt::Debug for WdfString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.as_unicode_str(), f)
+    }
+}
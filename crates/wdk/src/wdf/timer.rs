@@ -1,11 +1,63 @@
-use wdk_sys::{macros, NTSTATUS, WDFTIMER, WDF_OBJECT_ATTRIBUTES, WDF_TIMER_CONFIG};
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
-use crate::nt_success;
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+
+#[cfg(feature = "alloc")]
+use wdk_sys::ULONG;
+use wdk_sys::{macros, NTSTATUS, WDFOBJECT, WDFTIMER, WDF_OBJECT_ATTRIBUTES, WDF_TIMER_CONFIG};
+
+#[cfg(feature = "alloc")]
+use crate::wdf::ObjectContext;
+use crate::{
+    nt_success,
+    wdf::{FromWdfObject, ObjectHandle},
+};
 
 /// WDF Timer.
 pub struct Timer {
     wdf_timer: WDFTIMER,
 }
+
+/// Callback invoked when a [`Timer`] created via [`Timer::create`] expires.
+///
+/// This trait is implemented for all closures that take a [`Timer`] reference,
+/// as well as for boxed trait objects, so either can be passed to
+/// [`Timer::create`]. The callback runs at the IRQL that WDF invokes
+/// `EvtTimerFunc` at (`DISPATCH_LEVEL` by default), so it must abide by the
+/// rules for that IRQL.
+#[cfg(feature = "alloc")]
+pub trait TimerCallback: Send + Sync {
+    /// Called by the framework every time the [`Timer`] expires
+    fn on_timer(&self, timer: &Timer);
+}
+
+#[cfg(feature = "alloc")]
+impl<F> TimerCallback for F
+where
+    F: Fn(&Timer) + Send + Sync,
+{
+    fn on_timer(&self, timer: &Timer) {
+        self(timer);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl TimerCallback for Box<dyn TimerCallback> {
+    fn on_timer(&self, timer: &Timer) {
+        (**self).on_timer(timer);
+    }
+}
+
+#[cfg(feature = "alloc")]
+crate::wdf_declare_context_type!(
+    /// Context stored in every [`Timer`] created via [`Timer::create`]
+    struct TimerContext {
+        callback: Box<dyn TimerCallback>,
+    }
+);
+
 impl Timer {
     /// Try to construct a WDF Timer object
     ///
@@ -35,19 +87,60 @@ pub fn try_new(
         nt_success(nt_status).then_some(timer).ok_or(nt_status)
     }
 
-    /// Try to construct a WDF Timer object
+    /// Try to construct a WDF Timer object whose expiration is handled by a
+    /// Rust closure or [`TimerCallback`] trait object
+    ///
+    /// `parent` is the WDF object (typically a `WDFDEVICE` or `WDFQUEUE`) that
+    /// owns the timer. When `period` is zero, the timer is a one-shot timer
+    /// that fires once per call to [`Timer::start`]. Otherwise, the timer
+    /// fires every `period` milliseconds after it is first started, until
+    /// [`Timer::stop`] is called.
+    ///
+    /// `callback` is stored in the timer's WDF object context space and is
+    /// dropped when the framework destroys the timer.
     ///
     /// # Errors
     ///
     /// This function will return an error if WDF fails to contruct a timer. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFTimer Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdftimer/nf-wdftimer-wdftimercreate#return-value)
-    pub fn create(
-        timer_config: &mut WDF_TIMER_CONFIG,
-        attributes: &mut WDF_OBJECT_ATTRIBUTES,
-    ) -> Result<Self, NTSTATUS> {
-        Self::try_new(timer_config, attributes)
+    #[cfg(feature = "alloc")]
+    pub fn create<C>(parent: WDFOBJECT, period: ULONG, callback: C) -> Result<Self, NTSTATUS>
+    where
+        C: TimerCallback + 'static,
+    {
+        let callback: Box<dyn TimerCallback> = Box::new(callback);
+
+        let mut timer_config = WDF_TIMER_CONFIG {
+            // The size of WDF_TIMER_CONFIG is known to fit in a ULONG
+            #[allow(clippy::cast_possible_truncation)]
+            Size: core::mem::size_of::<WDF_TIMER_CONFIG>() as ULONG,
+            EvtTimerFunc: Some(evt_timer_func),
+            Period: period,
+            AutomaticSerialization: u8::from(true),
+            ..WDF_TIMER_CONFIG::default()
+        };
+
+        let mut attributes = WDF_OBJECT_ATTRIBUTES {
+            ParentObject: parent,
+            ..TimerContext::object_attributes()
+        };
+
+        let timer = Self::try_new(&mut timer_config, &mut attributes)?;
+        if timer.init_context(TimerContext { callback }).is_err() {
+            unreachable!("context of a newly created timer should be uninitialized");
+        }
+
+        Ok(timer)
     }
 
     /// Start the [`Timer`]'s clock
+    ///
+    /// `due_time` follows the same semantics as the `DueTime` parameter of
+    /// [`WdfTimerStart`](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdftimer/nf-wdftimer-wdftimerstart):
+    /// negative values are relative times and positive values are absolute
+    /// times, both in 100-nanosecond units. Use
+    /// [`Timeout::as_raw`](crate::time::Timeout::as_raw) to construct it from a
+    /// [`Duration`](core::time::Duration). Returns `true` if the timer was
+    /// already in the system's timer queue.
     #[must_use]
     pub fn start(&self, due_time: i64) -> bool {
         let result;
@@ -61,6 +154,10 @@ pub fn start(&self, due_time: i64) -> bool {
     }
 
     /// Stop the [`Timer`]'s clock
+    ///
+    /// If `wait` is `true`, this waits for all pending callbacks of the timer
+    /// to complete, and must therefore only be called at `PASSIVE_LEVEL`.
+    /// Returns `true` if the timer was in the system's timer queue.
     #[must_use]
     pub fn stop(&self, wait: bool) -> bool {
         let result;
@@ -76,3 +173,35 @@ pub fn stop(&self, wait: bool) -> bool {
         result != 0
     }
 }
+
+// SAFETY: `wdf_timer` is a private member of `Timer`, originally created by
+// WDF, and this module guarantees that it is always in a valid state.
+unsafe impl ObjectHandle for Timer {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_timer.cast()
+    }
+}
+
+// SAFETY: WDF timer handles are not tied to the thread that created them, so
+// the driver can start, stop or delete a timer from any thread (ex. from a work
+// item).
+unsafe impl Send for Timer {}
+
+// SAFETY: `Timer` does not delete the underlying object when dropped.
+unsafe impl FromWdfObject for Timer {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            wdf_timer: wdf_object.cast(),
+        }
+    }
+}
+
+/// `EvtTimerFunc` trampoline that forwards timer expiration to the
+/// [`TimerCallback`] stored in the timer's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_timer_func(wdf_timer: WDFTIMER) {
+    let timer = Timer { wdf_timer };
+    if let Some(context) = timer.context::<TimerContext>() {
+        context.callback.on_timer(&timer);
+    }
+}
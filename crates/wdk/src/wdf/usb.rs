@@ -0,0 +1,844 @@
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, vec, vec::Vec};
+
+use wdk_sys::{
+    _WdfUsbTargetDeviceSelectConfigType,
+    macros,
+    usb::USBD_CLIENT_CONTRACT_VERSION_602,
+    _WDF_MEMORY_DESCRIPTOR_TYPE,
+    NTSTATUS,
+    STATUS_INVALID_PARAMETER,
+    ULONG,
+    WDFDEVICE,
+    WDFOBJECT,
+    WDFUSBDEVICE,
+    WDFUSBINTERFACE,
+    WDFUSBPIPE,
+    WDF_MEMORY_DESCRIPTOR,
+    WDF_NO_OBJECT_ATTRIBUTES,
+    WDF_OBJECT_ATTRIBUTES,
+    WDF_REQUEST_SEND_OPTIONS,
+    WDF_USB_DEVICE_CREATE_CONFIG,
+    WDF_USB_DEVICE_SELECT_CONFIG_PARAMS,
+    WDF_USB_PIPE_INFORMATION,
+    WDF_USB_PIPE_TYPE,
+};
+#[cfg(feature = "alloc")]
+use wdk_sys::{
+    _POOL_TYPE,
+    _WDF_EXECUTION_LEVEL,
+    _WDF_SYNCHRONIZATION_SCOPE,
+    _WDF_USB_REQUEST_TYPE,
+    PWDF_REQUEST_COMPLETION_PARAMS,
+    STATUS_BUFFER_TOO_SMALL,
+    USHORT,
+    WDFCONTEXT,
+    WDFIOTARGET,
+    WDFMEMORY,
+    WDFREQUEST,
+    WDF_USB_REQUEST_COMPLETION_PARAMS,
+};
+
+#[cfg(feature = "alloc")]
+use crate::wdf::{context::BorrowedObject, ObjectContext};
+use crate::{
+    nt_success,
+    wdf::{FromWdfObject, ObjectHandle},
+};
+
+/// Pool tag of the buffers allocated for asynchronous USB transfers
+#[cfg(feature = "alloc")]
+const USB_TRANSFER_POOL_TAG: ULONG = u32::from_le_bytes(*b"RUsb");
+
+/// WDF USB Target Device.
+///
+/// A USB target device represents the USB device that a USB client driver
+/// controls. Once the device's configuration has been selected via
+/// [`UsbDevice::select_config`], its interfaces and their pipes can be
+/// retrieved via [`UsbDevice::interface`].
+pub struct UsbDevice {
+    wdf_usb_device: WDFUSBDEVICE,
+}
+
+/// WDF USB Interface.
+///
+/// A USB interface is owned by its [`UsbDevice`], and exposes the pipes that
+/// were configured for its selected alternate setting.
+pub struct UsbInterface {
+    wdf_usb_interface: WDFUSBINTERFACE,
+}
+
+/// WDF USB Pipe.
+///
+/// A USB pipe is owned by its [`UsbInterface`], and is used to transfer data
+/// to or from one of the USB device's endpoints.
+pub struct UsbPipe {
+    wdf_usb_pipe: WDFUSBPIPE,
+}
+
+/// Completion closure of an asynchronous transfer, invoked with the status of
+/// the transfer and the transferred bytes
+#[cfg(feature = "alloc")]
+type UsbTransferCompletion = Box<dyn FnOnce(NTSTATUS, &[u8]) + Send + Sync>;
+
+#[cfg(feature = "alloc")]
+crate::wdf_declare_context_type!(
+    /// Context stored in every request sent via [`UsbPipe::read_async`] or
+    /// [`UsbPipe::write_async`]
+    struct UsbTransferContext {
+        completion: Option<UsbTransferCompletion>,
+    }
+);
+
+impl UsbDevice {
+    /// Try to construct a WDF USB Target Device object for `device`. This
+    /// should be called from `EvtDevicePrepareHardware`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a USB target device. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFUsb Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfusb/nf-wdfusb-wdfusbtargetdevicecreatewithparameters#return-value)
+    pub fn try_new(
+        device: WDFDEVICE,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        let mut usb_device = Self {
+            wdf_usb_device: core::ptr::null_mut(),
+        };
+
+        let mut usb_device_create_config = WDF_USB_DEVICE_CREATE_CONFIG {
+            // The size of WDF_USB_DEVICE_CREATE_CONFIG is known to fit in a ULONG
+            #[allow(clippy::cast_possible_truncation)]
+            Size: core::mem::size_of::<WDF_USB_DEVICE_CREATE_CONFIG>() as ULONG,
+            USBDClientContractVersion: USBD_CLIENT_CONTRACT_VERSION_602,
+        };
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfUsbTargetDeviceCreateWithParameters,
+                device,
+                &mut usb_device_create_config,
+                attributes.map_or(WDF_NO_OBJECT_ATTRIBUTES, core::ptr::from_mut),
+                &mut usb_device.wdf_usb_device,
+            );
+        }
+        nt_success(nt_status).then_some(usb_device).ok_or(nt_status)
+    }
+
+    /// Try to construct a WDF USB Target Device object for `device`. This is
+    /// an alias for [`UsbDevice::try_new()`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a USB target device. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFUsb Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfusb/nf-wdfusb-wdfusbtargetdevicecreatewithparameters#return-value)
+    pub fn create(
+        device: WDFDEVICE,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        Self::try_new(device, attributes)
+    }
+
+    /// Retrieve the USB configuration descriptor of the device, followed by
+    /// all of its interface, endpoint, and class-specific descriptors. The
+    /// returned buffer begins with a `USB_CONFIGURATION_DESCRIPTOR`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to retrieve the configuration descriptor. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFUsb Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfusb/nf-wdfusb-wdfusbtargetdeviceretrieveconfigdescriptor#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn config_descriptor(&self) -> Result<Vec<u8>, NTSTATUS> {
+        let mut length: USHORT = 0;
+
+        let nt_status;
+        // SAFETY: `wdf_usb_device` is a private member of `UsbDevice`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state. A null buffer only queries the length of the descriptor.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfUsbTargetDeviceRetrieveConfigDescriptor,
+                self.wdf_usb_device,
+                core::ptr::null_mut(),
+                &mut length,
+            );
+        }
+        // Querying the length is expected to fail with `STATUS_BUFFER_TOO_SMALL`
+        if nt_status != STATUS_BUFFER_TOO_SMALL {
+            return Err(nt_status);
+        }
+
+        let mut config_descriptor = vec![0; usize::from(length)];
+        let nt_status;
+        // SAFETY: `wdf_usb_device` is a private member of `UsbDevice`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state. `config_descriptor` is valid for writes of `length` bytes.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfUsbTargetDeviceRetrieveConfigDescriptor,
+                self.wdf_usb_device,
+                config_descriptor.as_mut_ptr().cast(),
+                &mut length,
+            );
+        }
+        config_descriptor.truncate(usize::from(length));
+        nt_success(nt_status)
+            .then_some(config_descriptor)
+            .ok_or(nt_status)
+    }
+
+    /// Select the first configuration of the device, using the default
+    /// alternate setting of each of its interfaces. `pipe_attributes` are used
+    /// for every pipe that is created for the configuration.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to select the configuration. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFUsb Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfusb/nf-wdfusb-wdfusbtargetdeviceselectconfig#return-value)
+    pub fn select_config(
+        &self,
+        pipe_attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<(), NTSTATUS> {
+        // Equivalent to WDF_USB_DEVICE_SELECT_CONFIG_PARAMS_INIT_SINGLE_INTERFACE and
+        // WDF_USB_DEVICE_SELECT_CONFIG_PARAMS_INIT_MULTIPLE_INTERFACES(Params, 0,
+        // NULL), whose type-specific parameters are all zero
+        let select_config_type = if self.num_interfaces() == 1 {
+            _WdfUsbTargetDeviceSelectConfigType::WdfUsbTargetDeviceSelectConfigTypeSingleInterface
+        } else {
+            _WdfUsbTargetDeviceSelectConfigType::WdfUsbTargetDeviceSelectConfigTypeMultiInterface
+        };
+        let mut select_config_params = WDF_USB_DEVICE_SELECT_CONFIG_PARAMS {
+            // The size of WDF_USB_DEVICE_SELECT_CONFIG_PARAMS is known to fit in a ULONG
+            #[allow(clippy::cast_possible_truncation)]
+            Size: core::mem::size_of::<WDF_USB_DEVICE_SELECT_CONFIG_PARAMS>() as ULONG,
+            Type: select_config_type,
+            ..WDF_USB_DEVICE_SELECT_CONFIG_PARAMS::default()
+        };
+
+        let nt_status;
+        // SAFETY: `wdf_usb_device` is a private member of `UsbDevice`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfUsbTargetDeviceSelectConfig,
+                self.wdf_usb_device,
+                pipe_attributes.map_or(WDF_NO_OBJECT_ATTRIBUTES, core::ptr::from_mut),
+                &mut select_config_params,
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Get the number of interfaces of the device
+    #[must_use]
+    pub fn num_interfaces(&self) -> u8 {
+        let num_interfaces;
+        // SAFETY: `wdf_usb_device` is a private member of `UsbDevice`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            num_interfaces = macros::call_unsafe_wdf_function_binding!(
+                WdfUsbTargetDeviceGetNumInterfaces,
+                self.wdf_usb_device,
+            );
+        }
+        num_interfaces
+    }
+
+    /// Get the interface at `index`. Returns [`None`] if `index` is out of
+    /// bounds.
+    #[must_use]
+    pub fn interface(&self, index: u8) -> Option<UsbInterface> {
+        if index >= self.num_interfaces() {
+            return None;
+        }
+
+        let wdf_usb_interface;
+        // SAFETY: `wdf_usb_device` is a private member of `UsbDevice`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state. `index` was checked to be in bounds above.
+        unsafe {
+            wdf_usb_interface = macros::call_unsafe_wdf_function_binding!(
+                WdfUsbTargetDeviceGetInterface,
+                self.wdf_usb_device,
+                index,
+            );
+        }
+        (!wdf_usb_interface.is_null()).then_some(UsbInterface { wdf_usb_interface })
+    }
+
+    /// Get an iterator over the interfaces of the device
+    pub fn interfaces(&self) -> impl Iterator<Item = UsbInterface> + '_ {
+        (0..self.num_interfaces()).filter_map(|index| self.interface(index))
+    }
+}
+
+impl UsbInterface {
+    /// Get the interface number (`bInterfaceNumber`) of the interface
+    #[must_use]
+    pub fn interface_number(&self) -> u8 {
+        let interface_number;
+        // SAFETY: `wdf_usb_interface` is a private member of `UsbInterface`,
+        // originally created by WDF, and this module guarantees that it is always in
+        // a valid state.
+        unsafe {
+            interface_number = macros::call_unsafe_wdf_function_binding!(
+                WdfUsbInterfaceGetInterfaceNumber,
+                self.wdf_usb_interface,
+            );
+        }
+        interface_number
+    }
+
+    /// Get the number of pipes that are configured for the selected alternate
+    /// setting of the interface
+    #[must_use]
+    pub fn num_configured_pipes(&self) -> u8 {
+        let num_configured_pipes;
+        // SAFETY: `wdf_usb_interface` is a private member of `UsbInterface`,
+        // originally created by WDF, and this module guarantees that it is always in
+        // a valid state.
+        unsafe {
+            num_configured_pipes = macros::call_unsafe_wdf_function_binding!(
+                WdfUsbInterfaceGetNumConfiguredPipes,
+                self.wdf_usb_interface,
+            );
+        }
+        num_configured_pipes
+    }
+
+    /// Get the configured pipe at `index`. Returns [`None`] if `index` is out
+    /// of bounds.
+    #[must_use]
+    pub fn configured_pipe(&self, index: u8) -> Option<UsbPipe> {
+        let wdf_usb_pipe;
+        // SAFETY: `wdf_usb_interface` is a private member of `UsbInterface`,
+        // originally created by WDF, and this module guarantees that it is always in
+        // a valid state. `WdfUsbInterfaceGetConfiguredPipe` returns `NULL` for out of
+        // bounds indices.
+        unsafe {
+            wdf_usb_pipe = macros::call_unsafe_wdf_function_binding!(
+                WdfUsbInterfaceGetConfiguredPipe,
+                self.wdf_usb_interface,
+                index,
+                core::ptr::null_mut(),
+            );
+        }
+        (!wdf_usb_pipe.is_null()).then_some(UsbPipe { wdf_usb_pipe })
+    }
+
+    /// Get an iterator over the configured pipes of the interface
+    pub fn configured_pipes(&self) -> impl Iterator<Item = UsbPipe> + '_ {
+        (0..self.num_configured_pipes()).filter_map(|index| self.configured_pipe(index))
+    }
+}
+
+impl UsbPipe {
+    /// Get the information of the pipe, such as its type, endpoint address and
+    /// maximum packet size
+    #[must_use]
+    pub fn information(&self) -> WDF_USB_PIPE_INFORMATION {
+        let mut pipe_information = WDF_USB_PIPE_INFORMATION {
+            // The size of WDF_USB_PIPE_INFORMATION is known to fit in a ULONG
+            #[allow(clippy::cast_possible_truncation)]
+            Size: core::mem::size_of::<WDF_USB_PIPE_INFORMATION>() as ULONG,
+            ..WDF_USB_PIPE_INFORMATION::default()
+        };
+
+        // SAFETY: `wdf_usb_pipe` is a private member of `UsbPipe`, originally created
+        // by WDF, and this module guarantees that it is always in a valid state.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfUsbTargetPipeGetInformation,
+                self.wdf_usb_pipe,
+                &mut pipe_information,
+            );
+        }
+        pipe_information
+    }
+
+    /// Get the type of the pipe (control, isochronous, bulk or interrupt)
+    #[must_use]
+    pub fn pipe_type(&self) -> WDF_USB_PIPE_TYPE {
+        let pipe_type;
+        // SAFETY: `wdf_usb_pipe` is a private member of `UsbPipe`, originally created
+        // by WDF, and this module guarantees that it is always in a valid state.
+        unsafe {
+            pipe_type = macros::call_unsafe_wdf_function_binding!(
+                WdfUsbTargetPipeGetType,
+                self.wdf_usb_pipe
+            );
+        }
+        pipe_type
+    }
+
+    /// Returns `true` if the pipe transfers data from the device to the host
+    /// (ex. a bulk IN endpoint)
+    #[must_use]
+    pub fn is_in_endpoint(&self) -> bool {
+        let is_in_endpoint;
+        // SAFETY: `wdf_usb_pipe` is a private member of `UsbPipe`, originally created
+        // by WDF, and this module guarantees that it is always in a valid state.
+        unsafe {
+            is_in_endpoint = macros::call_unsafe_wdf_function_binding!(
+                WdfUsbTargetPipeIsInEndpoint,
+                self.wdf_usb_pipe,
+            );
+        }
+        is_in_endpoint != 0
+    }
+
+    /// Returns `true` if the pipe transfers data from the host to the device
+    /// (ex. a bulk OUT endpoint)
+    #[must_use]
+    pub fn is_out_endpoint(&self) -> bool {
+        let is_out_endpoint;
+        // SAFETY: `wdf_usb_pipe` is a private member of `UsbPipe`, originally created
+        // by WDF, and this module guarantees that it is always in a valid state.
+        unsafe {
+            is_out_endpoint = macros::call_unsafe_wdf_function_binding!(
+                WdfUsbTargetPipeIsOutEndpoint,
+                self.wdf_usb_pipe,
+            );
+        }
+        is_out_endpoint != 0
+    }
+
+    /// Read from the pipe into `buffer`, blocking until the transfer completes
+    /// or `request_options` times out. Returns the number of bytes that were
+    /// read. This must be called at `PASSIVE_LEVEL`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INVALID_PARAMETER` if `buffer` is longer than `ULONG::MAX` bytes. Otherwise, it will return an error if the transfer fails. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFUsb Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfusb/nf-wdfusb-wdfusbtargetpipereadsynchronously#return-value)
+    pub fn read_synchronously(
+        &self,
+        buffer: &mut [u8],
+        request_options: Option<&mut WDF_REQUEST_SEND_OPTIONS>,
+    ) -> Result<usize, NTSTATUS> {
+        let mut memory_descriptor = buffer_memory_descriptor(buffer.as_mut_ptr(), buffer.len())?;
+        let mut bytes_read: ULONG = 0;
+
+        let nt_status;
+        // SAFETY: `wdf_usb_pipe` is a private member of `UsbPipe`, originally created
+        // by WDF, and this module guarantees that it is always in a valid state.
+        // `memory_descriptor` describes `buffer`, which is exclusively borrowed until
+        // the synchronous transfer completes.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfUsbTargetPipeReadSynchronously,
+                self.wdf_usb_pipe,
+                core::ptr::null_mut(),
+                request_options.map_or(core::ptr::null_mut(), core::ptr::from_mut),
+                &mut memory_descriptor,
+                &mut bytes_read,
+            );
+        }
+        nt_success(nt_status)
+            .then_some(bytes_read as usize)
+            .ok_or(nt_status)
+    }
+
+    /// Write `buffer` to the pipe, blocking until the transfer completes or
+    /// `request_options` times out. Returns the number of bytes that were
+    /// written. This must be called at `PASSIVE_LEVEL`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INVALID_PARAMETER` if `buffer` is longer than `ULONG::MAX` bytes. Otherwise, it will return an error if the transfer fails. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFUsb Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfusb/nf-wdfusb-wdfusbtargetpipewritesynchronously#return-value)
+    pub fn write_synchronously(
+        &self,
+        buffer: &[u8],
+        request_options: Option<&mut WDF_REQUEST_SEND_OPTIONS>,
+    ) -> Result<usize, NTSTATUS> {
+        // WDF never writes to the buffer of a write transfer
+        let mut memory_descriptor =
+            buffer_memory_descriptor(buffer.as_ptr().cast_mut(), buffer.len())?;
+        let mut bytes_written: ULONG = 0;
+
+        let nt_status;
+        // SAFETY: `wdf_usb_pipe` is a private member of `UsbPipe`, originally created
+        // by WDF, and this module guarantees that it is always in a valid state.
+        // `memory_descriptor` describes `buffer`, which is borrowed until the
+        // synchronous transfer completes.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfUsbTargetPipeWriteSynchronously,
+                self.wdf_usb_pipe,
+                core::ptr::null_mut(),
+                request_options.map_or(core::ptr::null_mut(), core::ptr::from_mut),
+                &mut memory_descriptor,
+                &mut bytes_written,
+            );
+        }
+        nt_success(nt_status)
+            .then_some(bytes_written as usize)
+            .ok_or(nt_status)
+    }
+
+    /// Start reading up to `length` bytes from the pipe, without blocking.
+    /// Once the transfer completes, `completion` is invoked at
+    /// `DISPATCH_LEVEL` or below with the bytes that were read.
+    ///
+    /// The transfer buffer is allocated and owned by the framework, and is
+    /// freed once `completion` returns.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to create or send the request for the transfer, in which case `completion` is never invoked. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFUsb Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfusb/nf-wdfusb-wdfusbtargetpipeformatrequestforread#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn read_async<F>(&self, length: usize, completion: F) -> Result<(), NTSTATUS>
+    where
+        F: FnOnce(Result<&[u8], NTSTATUS>) + Send + Sync + 'static,
+    {
+        self.send_async(
+            length,
+            None,
+            Box::new(move |nt_status, data| {
+                completion(nt_success(nt_status).then_some(data).ok_or(nt_status));
+            }),
+        )
+    }
+
+    /// Start writing `data` to the pipe, without blocking. Once the transfer
+    /// completes, `completion` is invoked at `DISPATCH_LEVEL` or below with the
+    /// number of bytes that were written.
+    ///
+    /// `data` is copied into a transfer buffer that is allocated and owned by
+    /// the framework, so it does not need to outlive the transfer.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to create or send the request for the transfer, in which case `completion` is never invoked. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFUsb Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfusb/nf-wdfusb-wdfusbtargetpipeformatrequestforwrite#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn write_async<F>(&self, data: &[u8], completion: F) -> Result<(), NTSTATUS>
+    where
+        F: FnOnce(Result<usize, NTSTATUS>) + Send + Sync + 'static,
+    {
+        self.send_async(
+            data.len(),
+            Some(data),
+            Box::new(move |nt_status, data: &[u8]| {
+                completion(nt_success(nt_status).then_some(data.len()).ok_or(nt_status));
+            }),
+        )
+    }
+
+    /// Create a request with a framework-owned buffer of `length` bytes, format
+    /// it for a read from the pipe (or for a write of `write_data`), and send
+    /// it to the pipe's I/O target. The request is deleted once `completion`
+    /// has been invoked, or if it fails to be sent.
+    #[cfg(feature = "alloc")]
+    fn send_async(
+        &self,
+        length: usize,
+        write_data: Option<&[u8]>,
+        completion: UsbTransferCompletion,
+    ) -> Result<(), NTSTATUS> {
+        // `WdfUsbTargetPipeGetIoTarget` is a macro that casts the pipe handle, since a
+        // USB pipe is also an I/O target
+        let io_target: WDFIOTARGET = self.wdf_usb_pipe.cast();
+
+        let mut request_attributes = WDF_OBJECT_ATTRIBUTES {
+            ParentObject: self.as_wdf_object(),
+            ..UsbTransferContext::object_attributes()
+        };
+        let mut request: WDFREQUEST = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: `io_target` is the valid I/O target of the pipe. The created
+        // request is only accessible within this function and its completion routine.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfRequestCreate,
+                &mut request_attributes,
+                io_target,
+                &mut request,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status);
+        }
+
+        // SAFETY: `request` was successfully created above, and has not been sent
+        // yet.
+        let result =
+            unsafe { self.format_and_send(request, io_target, length, write_data, completion) };
+        if result.is_err() {
+            // SAFETY: `request` was not sent, so this is the only reference to it.
+            unsafe {
+                macros::call_unsafe_wdf_function_binding!(WdfObjectDelete, request.cast());
+            }
+        }
+        result
+    }
+
+    /// Allocate the transfer buffer of `request`, format `request` for the
+    /// transfer, and send it to `io_target`
+    ///
+    /// # Safety
+    ///
+    /// `request` must be a valid request created with a [`UsbTransferContext`]
+    /// context space, that has not been sent yet.
+    #[cfg(feature = "alloc")]
+    unsafe fn format_and_send(
+        &self,
+        request: WDFREQUEST,
+        io_target: WDFIOTARGET,
+        length: usize,
+        write_data: Option<&[u8]>,
+        completion: UsbTransferCompletion,
+    ) -> Result<(), NTSTATUS> {
+        let mut memory_attributes = WDF_OBJECT_ATTRIBUTES {
+            // The size of WDF_OBJECT_ATTRIBUTES is known to fit in a ULONG
+            #[allow(clippy::cast_possible_truncation)]
+            Size: core::mem::size_of::<WDF_OBJECT_ATTRIBUTES>() as ULONG,
+            ExecutionLevel: _WDF_EXECUTION_LEVEL::WdfExecutionLevelInheritFromParent,
+            SynchronizationScope:
+                _WDF_SYNCHRONIZATION_SCOPE::WdfSynchronizationScopeInheritFromParent,
+            ParentObject: request.cast(),
+            ..WDF_OBJECT_ATTRIBUTES::default()
+        };
+        let mut memory: WDFMEMORY = core::ptr::null_mut();
+        let mut buffer = core::ptr::null_mut();
+
+        let nt_status;
+        // SAFETY: The caller guarantees that `request` is valid, and the created
+        // memory object is deleted along with it.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfMemoryCreate,
+                &mut memory_attributes,
+                _POOL_TYPE::NonPagedPoolNx,
+                USB_TRANSFER_POOL_TAG,
+                length,
+                &mut memory,
+                &mut buffer,
+            );
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status);
+        }
+
+        let nt_status;
+        if let Some(write_data) = write_data {
+            // SAFETY: `buffer` was allocated above with a size of `length` bytes, which
+            // is the length of `write_data`, and does not overlap with it.
+            unsafe {
+                core::ptr::copy_nonoverlapping(write_data.as_ptr(), buffer.cast(), length);
+            }
+
+            // SAFETY: `wdf_usb_pipe` is a private member of `UsbPipe`, originally
+            // created by WDF, and this module guarantees that it is always in a valid
+            // state. The caller guarantees that `request` is valid and not sent yet.
+            unsafe {
+                nt_status = macros::call_unsafe_wdf_function_binding!(
+                    WdfUsbTargetPipeFormatRequestForWrite,
+                    self.wdf_usb_pipe,
+                    request,
+                    memory,
+                    core::ptr::null_mut(),
+                );
+            }
+        } else {
+            // SAFETY: `wdf_usb_pipe` is a private member of `UsbPipe`, originally
+            // created by WDF, and this module guarantees that it is always in a valid
+            // state. The caller guarantees that `request` is valid and not sent yet.
+            unsafe {
+                nt_status = macros::call_unsafe_wdf_function_binding!(
+                    WdfUsbTargetPipeFormatRequestForRead,
+                    self.wdf_usb_pipe,
+                    request,
+                    memory,
+                    core::ptr::null_mut(),
+                );
+            }
+        }
+        if !nt_success(nt_status) {
+            return Err(nt_status);
+        }
+
+        let context = UsbTransferContext {
+            completion: Some(completion),
+        };
+        if BorrowedObject(request.cast())
+            .init_context(context)
+            .is_err()
+        {
+            unreachable!("context of a newly created request should be uninitialized");
+        }
+
+        // SAFETY: The caller guarantees that `request` is valid and not sent yet.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfRequestSetCompletionRoutine,
+                request,
+                Some(evt_usb_transfer_completion),
+                core::ptr::null_mut(),
+            );
+        }
+
+        let sent;
+        // SAFETY: The caller guarantees that `request` is valid and not sent yet, and
+        // it was formatted for `io_target` above.
+        unsafe {
+            sent = macros::call_unsafe_wdf_function_binding!(
+                WdfRequestSend,
+                request,
+                io_target,
+                core::ptr::null_mut(),
+            );
+        }
+        if sent == 0 {
+            let nt_status;
+            // SAFETY: The caller guarantees that `request` is valid.
+            unsafe {
+                nt_status = macros::call_unsafe_wdf_function_binding!(WdfRequestGetStatus, request);
+            }
+            return Err(nt_status);
+        }
+        Ok(())
+    }
+}
+
+// SAFETY: `wdf_usb_device` is a private member of `UsbDevice`, originally
+// created by WDF, and this module guarantees that it is always in a valid
+// state.
+unsafe impl ObjectHandle for UsbDevice {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_usb_device.cast()
+    }
+}
+
+// SAFETY: `UsbDevice` does not delete the underlying object when dropped.
+unsafe impl FromWdfObject for UsbDevice {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            wdf_usb_device: wdf_object.cast(),
+        }
+    }
+}
+
+// SAFETY: `wdf_usb_interface` is a private member of `UsbInterface`, originally
+// created by WDF, and this module guarantees that it is always in a valid
+// state.
+unsafe impl ObjectHandle for UsbInterface {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_usb_interface.cast()
+    }
+}
+
+// SAFETY: `UsbInterface` does not delete the underlying object when dropped.
+unsafe impl FromWdfObject for UsbInterface {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            wdf_usb_interface: wdf_object.cast(),
+        }
+    }
+}
+
+// SAFETY: `wdf_usb_pipe` is a private member of `UsbPipe`, originally created
+// by WDF, and this module guarantees that it is always in a valid state.
+unsafe impl ObjectHandle for UsbPipe {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_usb_pipe.cast()
+    }
+}
+
+// SAFETY: `UsbPipe` does not delete the underlying object when dropped.
+unsafe impl FromWdfObject for UsbPipe {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            wdf_usb_pipe: wdf_object.cast(),
+        }
+    }
+}
+
+/// Construct a [`WDF_MEMORY_DESCRIPTOR`] that describes `length` bytes at
+/// `buffer`. This is equivalent to `WDF_MEMORY_DESCRIPTOR_INIT_BUFFER` in C.
+fn buffer_memory_descriptor(
+    buffer: *mut u8,
+    length: usize,
+) -> Result<WDF_MEMORY_DESCRIPTOR, NTSTATUS> {
+    let mut memory_descriptor = WDF_MEMORY_DESCRIPTOR {
+        Type: _WDF_MEMORY_DESCRIPTOR_TYPE::WdfMemoryDescriptorTypeBuffer,
+        ..WDF_MEMORY_DESCRIPTOR::default()
+    };
+    memory_descriptor.u.BufferType.Buffer = buffer.cast();
+    memory_descriptor.u.BufferType.Length =
+        ULONG::try_from(length).map_err(|_| STATUS_INVALID_PARAMETER)?;
+    Ok(memory_descriptor)
+}
+
+/// `EvtRequestCompletionRoutine` of requests sent via [`UsbPipe::read_async`]
+/// and [`UsbPipe::write_async`], which invokes the request's completion
+/// closure with the transferred bytes and deletes the request
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_usb_transfer_completion(
+    request: WDFREQUEST,
+    _io_target: WDFIOTARGET,
+    completion_params: PWDF_REQUEST_COMPLETION_PARAMS,
+    _context: WDFCONTEXT,
+) {
+    // SAFETY: The framework passes valid completion parameters, which live for the
+    // duration of this callback.
+    let completion_params = unsafe { &*completion_params };
+    // SAFETY: The status is always the active field of `IoStatus` for completed
+    // requests.
+    let nt_status = unsafe { completion_params.IoStatus.__bindgen_anon_1.Status };
+    // SAFETY: The request was formatted by `WdfUsbTargetPipeFormatRequestForXxx`,
+    // so the `Usb` parameters are the active field.
+    let usb_completion_params = unsafe { completion_params.Parameters.Usb.Completion };
+    // SAFETY: The framework allocates the USB completion parameters of requests
+    // sent to a USB pipe for as long as the request exists.
+    let usb_completion_params =
+        unsafe { &*usb_completion_params.cast::<WDF_USB_REQUEST_COMPLETION_PARAMS>() };
+
+    let (transfer_memory, transfer_length) =
+        if usb_completion_params.Type == _WDF_USB_REQUEST_TYPE::WdfUsbRequestTypePipeWrite {
+            // SAFETY: `PipeWrite` is the active field for write requests.
+            let pipe_write = unsafe { usb_completion_params.Parameters.PipeWrite };
+            (pipe_write.Buffer, pipe_write.Length)
+        } else {
+            // SAFETY: `PipeRead` is the active field for read requests.
+            let pipe_read = unsafe { usb_completion_params.Parameters.PipeRead };
+            (pipe_read.Buffer, pipe_read.Length)
+        };
+
+    let buffer;
+    // SAFETY: The transfer memory object is a child of the request, so it is valid
+    // until the request is deleted below.
+    unsafe {
+        buffer = macros::call_unsafe_wdf_function_binding!(
+            WdfMemoryGetBuffer,
+            transfer_memory,
+            core::ptr::null_mut(),
+        );
+    }
+    let data = if nt_success(nt_status) && !buffer.is_null() {
+        // SAFETY: The framework reports the number of bytes transferred within the
+        // transfer buffer, which is valid until the request is deleted below.
+        unsafe { core::slice::from_raw_parts(buffer.cast::<u8>(), transfer_length) }
+    } else {
+        &[]
+    };
+
+    let mut request_object = BorrowedObject(request.cast());
+    // SAFETY: The completion routine is the only code that accesses the context of
+    // the request once it is sent, and it runs exactly once.
+    let completion = unsafe { request_object.context_mut::<UsbTransferContext>() }
+        .and_then(|context| context.completion.take());
+    if let Some(completion) = completion {
+        completion(nt_status, data);
+    }
+
+    // SAFETY: The request was created by `UsbPipe::send_async` and has completed,
+    // so it is no longer referenced by the framework or the driver.
+    unsafe {
+        macros::call_unsafe_wdf_function_binding!(WdfObjectDelete, request.cast());
+    }
+}
@@ -0,0 +1,865 @@
+use core::{
+    char::{decode_utf16, DecodeUtf16},
+    marker::PhantomData,
+};
+
+use wdk_sys::{
+    _WDF_WMI_PROVIDER_FLAGS,
+    macros,
+    NTSTATUS,
+    PULONG,
+    PVOID,
+    STATUS_BUFFER_TOO_SMALL,
+    STATUS_INVALID_BUFFER_SIZE,
+    STATUS_INVALID_PARAMETER,
+    STATUS_SUCCESS,
+    STATUS_WMI_INSTANCE_NOT_FOUND,
+    STATUS_WMI_ITEMID_NOT_FOUND,
+    STATUS_WMI_READ_ONLY,
+    ULONG,
+    WDFDEVICE,
+    WDFOBJECT,
+    WDFWMIINSTANCE,
+    WDFWMIPROVIDER,
+    WDF_NO_OBJECT_ATTRIBUTES,
+    WDF_OBJECT_ATTRIBUTES,
+    WDF_WMI_INSTANCE_CONFIG,
+    WDF_WMI_PROVIDER_CONFIG,
+    WDF_WMI_PROVIDER_CONTROL,
+};
+
+use crate::{
+    nt_success,
+    wdf::{Device, FromWdfObject, ObjectContext, ObjectHandle},
+    Guid,
+};
+
+/// WDF WMI Provider.
+///
+/// A provider represents a WMI data block, identified by the GUID of the
+/// block in the driver's MOF resource. The instances of the block are exposed
+/// to WMI clients via [`WmiInstance::create`].
+pub struct WmiProvider {
+    wdf_wmi_provider: WDFWMIPROVIDER,
+}
+
+/// WDF WMI Instance.
+///
+/// An instance of the data block of a [`WmiProvider`], whose contents are
+/// queried, set and whose methods are executed by the
+/// [`WmiInstanceCallbacks`] it was created with.
+pub struct WmiInstance {
+    wdf_wmi_instance: WDFWMIINSTANCE,
+}
+
+/// Builder of a [`WmiProvider`], obtained via [`WmiProvider::builder`].
+///
+/// ```rust, no_run
+/// use wdk::{
+///     wdf::{Device, WmiProvider},
+///     Guid,
+/// };
+///
+/// const TEMPERATURE_BLOCK_GUID: Guid = Guid::from_u128(0x1C6E_4BD6_A4A3_4B8B_9FB4_5C5E_2A83_01D7);
+///
+/// fn create_provider(device: &Device) -> wdk::Result<WmiProvider> {
+///     WmiProvider::builder(TEMPERATURE_BLOCK_GUID)
+///         .min_instance_buffer_size(4)
+///         .create(device)
+/// }
+/// ```
+#[must_use]
+pub struct WmiProviderBuilder {
+    guid: Guid,
+    flags: ULONG,
+    min_instance_buffer_size: ULONG,
+}
+
+/// Callbacks invoked by the framework for a [`WmiInstance`], registered via
+/// [`WmiInstance::create`].
+///
+/// Data is marshaled in the layout of the data block declared in the driver's
+/// MOF resource, which is the layout of the equivalent `#[repr(C)]` struct.
+/// Callbacks that are not implemented fail the corresponding WMI operation, so
+/// read-only data blocks only need to implement
+/// [`WmiInstanceCallbacks::query_instance`]. The callbacks run at
+/// `PASSIVE_LEVEL`.
+pub trait WmiInstanceCallbacks {
+    /// Type of the context stored in every instance created with these
+    /// callbacks
+    type Context: ObjectContext;
+
+    /// Write the contents of the data block to `output`
+    /// (`EvtWmiInstanceQueryInstance`).
+    ///
+    /// If more data is written than fits in the buffer provided by WMI, the
+    /// query is failed with `STATUS_BUFFER_TOO_SMALL`, and WMI retries it with
+    /// a buffer of the size that was written.
+    ///
+    /// # Errors
+    ///
+    /// Returning [`Err`] fails the query with the contained [`NTSTATUS`].
+    fn query_instance(
+        instance: &WmiInstance,
+        context: &Self::Context,
+        output: &mut WmiOutputBuffer<'_>,
+    ) -> Result<(), NTSTATUS>;
+
+    /// Replace the contents of the data block with `input`
+    /// (`EvtWmiInstanceSetInstance`)
+    ///
+    /// # Errors
+    ///
+    /// Returning [`Err`] fails the request with the contained [`NTSTATUS`].
+    /// The default implementation fails with `STATUS_WMI_READ_ONLY`.
+    fn set_instance(
+        _instance: &WmiInstance,
+        _context: &Self::Context,
+        _input: &mut WmiInputBuffer<'_>,
+    ) -> Result<(), NTSTATUS> {
+        Err(STATUS_WMI_READ_ONLY)
+    }
+
+    /// Replace the data item with ID `data_item_id` of the data block with
+    /// `input` (`EvtWmiInstanceSetItem`)
+    ///
+    /// # Errors
+    ///
+    /// Returning [`Err`] fails the request with the contained [`NTSTATUS`].
+    /// The default implementation fails with `STATUS_WMI_READ_ONLY`.
+    fn set_item(
+        _instance: &WmiInstance,
+        _context: &Self::Context,
+        _data_item_id: ULONG,
+        _input: &mut WmiInputBuffer<'_>,
+    ) -> Result<(), NTSTATUS> {
+        Err(STATUS_WMI_READ_ONLY)
+    }
+
+    /// Execute the method with ID `method_id` of the data block
+    /// (`EvtWmiInstanceExecuteMethod`).
+    ///
+    /// The input and output parameters of the method share `buffer`, so all
+    /// input parameters must be read before output parameters are written. As
+    /// for [`WmiInstanceCallbacks::query_instance`], writing more output than
+    /// fits in the buffer fails the request with `STATUS_BUFFER_TOO_SMALL`.
+    ///
+    /// # Errors
+    ///
+    /// Returning [`Err`] fails the request with the contained [`NTSTATUS`].
+    /// The default implementation fails with `STATUS_WMI_ITEMID_NOT_FOUND`.
+    fn execute_method(
+        _instance: &WmiInstance,
+        _context: &Self::Context,
+        _method_id: ULONG,
+        _buffer: &mut WmiMethodBuffer<'_>,
+    ) -> Result<(), NTSTATUS> {
+        Err(STATUS_WMI_ITEMID_NOT_FOUND)
+    }
+}
+
+/// Plain data that can be read from or written to the buffers of WMI
+/// requests, ex. the `#[repr(C)]` equivalent of a data block.
+///
+/// This trait is implemented for integers and arrays of [`WmiData`].
+///
+/// # Safety
+///
+/// Every bit pattern of `size_of::<Self>()` bytes must be a valid value of
+/// `Self`, since the contents of WMI buffers are controlled by the WMI client.
+/// In particular, `Self` must not contain padding, references, pointers,
+/// `bool`s, `char`s or enums. Structs should be `#[repr(C)]`.
+pub unsafe trait WmiData: Copy + 'static {}
+
+macro_rules! impl_wmi_data {
+    ($($type:ty),* $(,)?) => {
+        $(
+            // SAFETY: Every bit pattern is a valid value of this type.
+            unsafe impl WmiData for $type {}
+        )*
+    };
+}
+
+impl_wmi_data!(u8, u16, u32, u64, usize);
+impl_wmi_data!(i8, i16, i32, i64, isize);
+
+// SAFETY: Every bit pattern is a valid value of an array whose elements accept
+// every bit pattern, and arrays contain no padding between their elements.
+unsafe impl<T: WmiData, const N: usize> WmiData for [T; N] {}
+
+/// Reader of the data passed to a WMI request.
+///
+/// Values are read in order, and each value is aligned to its natural
+/// alignment relative to the start of the buffer, following the layout of the
+/// data block.
+pub struct WmiInputBuffer<'a> {
+    buffer: &'a [u8],
+    offset: usize,
+}
+
+/// Writer of the data returned from a WMI request.
+///
+/// Values are written in order, and each value is aligned to its natural
+/// alignment relative to the start of the buffer, following the layout of the
+/// data block. Writing past the end of the buffer does not fail, but only
+/// counts the number of bytes that would have been written, so that WMI can
+/// be told the size of the buffer it needs.
+pub struct WmiOutputBuffer<'a> {
+    buffer: *mut u8,
+    capacity: usize,
+    length: usize,
+    _buffer: PhantomData<&'a mut [u8]>,
+}
+
+/// Buffer shared by the input and output parameters of a WMI method,
+/// passed to [`WmiInstanceCallbacks::execute_method`]
+pub struct WmiMethodBuffer<'a> {
+    buffer: *mut u8,
+    input_length: usize,
+    output: WmiOutputBuffer<'a>,
+}
+
+impl WmiProvider {
+    /// Try to construct a WDF WMI Provider object for `device`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a WMI provider. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFWmiProvider Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfwmi/nf-wdfwmi-wdfwmiprovidercreate#return-value)
+    pub fn try_new(
+        device: WDFDEVICE,
+        provider_config: &mut WDF_WMI_PROVIDER_CONFIG,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        let mut provider = Self {
+            wdf_wmi_provider: core::ptr::null_mut(),
+        };
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfWmiProviderCreate,
+                device,
+                provider_config,
+                attributes.map_or(WDF_NO_OBJECT_ATTRIBUTES, core::ptr::from_mut),
+                &mut provider.wdf_wmi_provider,
+            );
+        }
+        nt_success(nt_status).then_some(provider).ok_or(nt_status)
+    }
+
+    /// Get a [`WmiProviderBuilder`] for the data block identified by `guid`
+    pub const fn builder(guid: Guid) -> WmiProviderBuilder {
+        WmiProviderBuilder {
+            guid,
+            flags: 0,
+            min_instance_buffer_size: 0,
+        }
+    }
+
+    /// Get the underlying `WDFWMIPROVIDER`
+    #[must_use]
+    pub const fn as_raw(&self) -> WDFWMIPROVIDER {
+        self.wdf_wmi_provider
+    }
+
+    /// Get the `WDFDEVICE` that this provider belongs to
+    #[must_use]
+    pub fn device(&self) -> WDFDEVICE {
+        let device;
+        // SAFETY: `wdf_wmi_provider` is a private member of `WmiProvider`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            device = macros::call_unsafe_wdf_function_binding!(
+                WdfWmiProviderGetDevice,
+                self.wdf_wmi_provider,
+            );
+        }
+        device
+    }
+
+    /// Check whether WMI clients enabled `control` for this provider, ex.
+    /// whether any client registered for the events of an event-only block
+    /// (`WdfWmiEventControl`)
+    #[must_use]
+    pub fn is_enabled(&self, control: WDF_WMI_PROVIDER_CONTROL) -> bool {
+        let enabled;
+        // SAFETY: `wdf_wmi_provider` is a private member of `WmiProvider`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            enabled = macros::call_unsafe_wdf_function_binding!(
+                WdfWmiProviderIsEnabled,
+                self.wdf_wmi_provider,
+                control,
+            );
+        }
+        enabled != 0
+    }
+}
+
+impl WmiProviderBuilder {
+    /// Mark the data block as event-only, so that its instances can only be
+    /// used to fire events via [`WmiInstance::fire_event`]
+    // The flag is a small positive constant
+    #[allow(clippy::cast_sign_loss)]
+    pub const fn event_only(mut self) -> Self {
+        self.flags |= _WDF_WMI_PROVIDER_FLAGS::WdfWmiProviderEventOnly as ULONG;
+        self
+    }
+
+    /// Mark the data block as expensive to collect, so that WMI only queries it
+    /// after a client explicitly enabled its collection
+    // The flag is a small positive constant
+    #[allow(clippy::cast_sign_loss)]
+    pub const fn expensive(mut self) -> Self {
+        self.flags |= _WDF_WMI_PROVIDER_FLAGS::WdfWmiProviderExpensive as ULONG;
+        self
+    }
+
+    /// Set the minimum size in bytes of the buffer passed to
+    /// [`WmiInstanceCallbacks::query_instance`]. Queries with smaller buffers
+    /// are failed by the framework with `STATUS_BUFFER_TOO_SMALL` without
+    /// calling the driver.
+    pub const fn min_instance_buffer_size(mut self, min_instance_buffer_size: ULONG) -> Self {
+        self.min_instance_buffer_size = min_instance_buffer_size;
+        self
+    }
+
+    /// Try to construct the WDF WMI Provider object for `device`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a WMI provider. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFWmiProvider Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfwmi/nf-wdfwmi-wdfwmiprovidercreate#return-value)
+    pub fn create(self, device: &Device) -> Result<WmiProvider, NTSTATUS> {
+        let mut provider_config = WDF_WMI_PROVIDER_CONFIG {
+            Guid: self.guid.into_raw(),
+            Flags: self.flags,
+            MinInstanceBufferSize: self.min_instance_buffer_size,
+            ..WDF_WMI_PROVIDER_CONFIG::with_size()
+        };
+
+        WmiProvider::try_new(device.as_raw(), &mut provider_config, None)
+    }
+}
+
+impl WmiInstance {
+    /// Try to construct a WDF WMI Instance object for `device`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a WMI instance. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFWmiInstance Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfwmi/nf-wdfwmi-wdfwmiinstancecreate#return-value)
+    pub fn try_new(
+        device: WDFDEVICE,
+        instance_config: &mut WDF_WMI_INSTANCE_CONFIG,
+        attributes: Option<&mut WDF_OBJECT_ATTRIBUTES>,
+    ) -> Result<Self, NTSTATUS> {
+        let mut instance = Self {
+            wdf_wmi_instance: core::ptr::null_mut(),
+        };
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfWmiInstanceCreate,
+                device,
+                instance_config,
+                attributes.map_or(WDF_NO_OBJECT_ATTRIBUTES, core::ptr::from_mut),
+                &mut instance.wdf_wmi_instance,
+            );
+        }
+        nt_success(nt_status).then_some(instance).ok_or(nt_status)
+    }
+
+    /// Try to construct an instance of the data block of `provider`, whose WMI
+    /// requests are handled by the [`WmiInstanceCallbacks`] of `C`. If
+    /// `register` is `true`, the instance is registered with WMI once
+    /// `context` has been stored in it. Otherwise, it must be registered via
+    /// [`WmiInstance::register`].
+    ///
+    /// `context` is stored in the instance's WDF object context space and is
+    /// dropped when the framework destroys the instance.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct or register the WMI instance. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFWmiInstance Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfwmi/nf-wdfwmi-wdfwmiinstancecreate#return-value)
+    pub fn create<C: WmiInstanceCallbacks>(
+        device: &Device,
+        provider: &WmiProvider,
+        context: C::Context,
+        register: bool,
+    ) -> Result<Self, NTSTATUS> {
+        // The instance is registered after its context is initialized, so that the
+        // callbacks never observe an instance without a context
+        let mut instance_config = WDF_WMI_INSTANCE_CONFIG {
+            Provider: provider.wdf_wmi_provider,
+            EvtWmiInstanceQueryInstance: Some(evt_wmi_instance_query_instance::<C>),
+            EvtWmiInstanceSetInstance: Some(evt_wmi_instance_set_instance::<C>),
+            EvtWmiInstanceSetItem: Some(evt_wmi_instance_set_item::<C>),
+            EvtWmiInstanceExecuteMethod: Some(evt_wmi_instance_execute_method::<C>),
+            ..WDF_WMI_INSTANCE_CONFIG::with_size()
+        };
+
+        let mut attributes = C::Context::object_attributes();
+
+        let instance = Self::try_new(device.as_raw(), &mut instance_config, Some(&mut attributes))?;
+        if instance.init_context(context).is_err() {
+            unreachable!("context of a newly created WMI instance should be uninitialized");
+        }
+
+        if register {
+            instance.register()?;
+        }
+        Ok(instance)
+    }
+
+    /// Get the underlying `WDFWMIINSTANCE`
+    #[must_use]
+    pub const fn as_raw(&self) -> WDFWMIINSTANCE {
+        self.wdf_wmi_instance
+    }
+
+    /// Get the `WDFDEVICE` that this instance belongs to
+    #[must_use]
+    pub fn device(&self) -> WDFDEVICE {
+        let device;
+        // SAFETY: `wdf_wmi_instance` is a private member of `WmiInstance`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            device = macros::call_unsafe_wdf_function_binding!(
+                WdfWmiInstanceGetDevice,
+                self.wdf_wmi_instance,
+            );
+        }
+        device
+    }
+
+    /// Get the [`WmiProvider`] of the data block that this is an instance of
+    #[must_use]
+    pub fn provider(&self) -> WmiProvider {
+        let wdf_wmi_provider;
+        // SAFETY: `wdf_wmi_instance` is a private member of `WmiInstance`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            wdf_wmi_provider = macros::call_unsafe_wdf_function_binding!(
+                WdfWmiInstanceGetProvider,
+                self.wdf_wmi_instance,
+            );
+        }
+        WmiProvider { wdf_wmi_provider }
+    }
+
+    /// Register the instance with WMI, so that WMI clients can access it. This
+    /// must be called at `PASSIVE_LEVEL`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to register the WMI instance. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFWmiInstance Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfwmi/nf-wdfwmi-wdfwmiinstanceregister#return-value)
+    pub fn register(&self) -> Result<(), NTSTATUS> {
+        let nt_status;
+        // SAFETY: `wdf_wmi_instance` is a private member of `WmiInstance`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfWmiInstanceRegister,
+                self.wdf_wmi_instance,
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+
+    /// Deregister the instance from WMI, so that WMI clients can no longer
+    /// access it. This must be called at `PASSIVE_LEVEL`.
+    pub fn deregister(&self) {
+        // SAFETY: `wdf_wmi_instance` is a private member of `WmiInstance`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(
+                WdfWmiInstanceDeregister,
+                self.wdf_wmi_instance,
+            );
+        }
+    }
+
+    /// Fire a WMI event for the instance, whose data is `data` marshaled in
+    /// the layout of the data block. This must be called at or below
+    /// `DISPATCH_LEVEL`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to fire the event. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFWmiInstance Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfwmi/nf-wdfwmi-wdfwmiinstancefireevent#return-value)
+    pub fn fire_event<T: WmiData>(&self, data: &T) -> Result<(), NTSTATUS> {
+        let Ok(event_data_size) = ULONG::try_from(core::mem::size_of::<T>()) else {
+            return Err(STATUS_INVALID_PARAMETER);
+        };
+
+        let nt_status;
+        // SAFETY: `wdf_wmi_instance` is a private member of `WmiInstance`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state. WDF copies the event data, and does not write to it.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfWmiInstanceFireEvent,
+                self.wdf_wmi_instance,
+                event_data_size,
+                core::ptr::from_ref(data).cast_mut().cast(),
+            );
+        }
+        nt_success(nt_status).then_some(()).ok_or(nt_status)
+    }
+}
+
+impl<'a> WmiInputBuffer<'a> {
+    /// Construct a reader of the data in `buffer`
+    #[must_use]
+    pub const fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer, offset: 0 }
+    }
+
+    /// Get the data that has not been read yet
+    #[must_use]
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.buffer[self.offset.min(self.buffer.len())..]
+    }
+
+    /// Read the next value as a `T`
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INVALID_BUFFER_SIZE` if the buffer
+    /// does not hold another `T`.
+    pub fn read<T: WmiData>(&mut self) -> Result<T, NTSTATUS> {
+        let bytes = self.take(core::mem::align_of::<T>(), core::mem::size_of::<T>())?;
+        // SAFETY: `bytes` is exactly `size_of::<T>()` bytes long, and `WmiData`
+        // guarantees that every bit pattern of that many bytes is a valid `T`.
+        Ok(unsafe { bytes.as_ptr().cast::<T>().read_unaligned() })
+    }
+
+    /// Read the next value as a WMI string, which is a `u16` length in bytes
+    /// followed by that many bytes of UTF-16 characters, and decode it
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INVALID_BUFFER_SIZE` if the buffer
+    /// does not hold another string.
+    pub fn read_string(&mut self) -> Result<DecodeUtf16<impl Iterator<Item = u16> + 'a>, NTSTATUS> {
+        let length = usize::from(self.read::<u16>()?);
+        if length % 2 != 0 {
+            return Err(STATUS_INVALID_BUFFER_SIZE);
+        }
+
+        let bytes = self.take(1, length)?;
+        Ok(decode_utf16(
+            bytes
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]])),
+        ))
+    }
+
+    /// Take the next `length` bytes, starting at the next offset aligned to
+    /// `align`
+    fn take(&mut self, align: usize, length: usize) -> Result<&'a [u8], NTSTATUS> {
+        let start = self.offset.next_multiple_of(align);
+        let bytes = start
+            .checked_add(length)
+            .and_then(|end| self.buffer.get(start..end))
+            .ok_or(STATUS_INVALID_BUFFER_SIZE)?;
+        self.offset = start + length;
+        Ok(bytes)
+    }
+}
+
+impl<'a> WmiOutputBuffer<'a> {
+    /// Construct a writer of data to `buffer`
+    #[must_use]
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Self {
+            buffer: buffer.as_mut_ptr(),
+            capacity: buffer.len(),
+            length: 0,
+            _buffer: PhantomData,
+        }
+    }
+
+    /// Construct a writer of data to the `capacity` bytes at `buffer`
+    ///
+    /// # Safety
+    ///
+    /// If `capacity` is not zero, `buffer` must be valid for writes of
+    /// `capacity` bytes for `'a`, and must not be accessed through any other
+    /// pointer for `'a`.
+    const unsafe fn from_raw(buffer: *mut u8, capacity: usize) -> Self {
+        Self {
+            buffer,
+            capacity,
+            length: 0,
+            _buffer: PhantomData,
+        }
+    }
+
+    /// Get the number of bytes written, including bytes that did not fit in
+    /// the buffer
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Check whether nothing was written
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Get the size of the buffer in bytes
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Write `value` as the next value
+    pub fn write<T: WmiData>(&mut self, value: T) {
+        self.pad_to(core::mem::align_of::<T>());
+        let start = self.length;
+        self.length += core::mem::size_of::<T>();
+        if self.length <= self.capacity {
+            // SAFETY: `buffer` is valid for writes of `capacity` bytes, and `start` is
+            // within them.
+            let value_ptr = unsafe { self.buffer.add(start) }.cast::<T>();
+            // SAFETY: The `size_of::<T>()` bytes at `value_ptr` end within the
+            // `capacity` bytes that `buffer` is valid for writes of.
+            unsafe {
+                value_ptr.write_unaligned(value);
+            }
+        }
+    }
+
+    /// Write `string` as the next value, as a WMI string, which is a `u16`
+    /// length in bytes followed by that many bytes of UTF-16 characters
+    ///
+    /// # Errors
+    ///
+    /// This function will return `STATUS_INVALID_PARAMETER` if `string` is
+    /// longer than the maximum length of a WMI string.
+    pub fn write_string(&mut self, string: &str) -> Result<(), NTSTATUS> {
+        let length = string
+            .encode_utf16()
+            .count()
+            .checked_mul(2)
+            .and_then(|length| u16::try_from(length).ok())
+            .ok_or(STATUS_INVALID_PARAMETER)?;
+
+        self.write(length);
+        for character in string.encode_utf16() {
+            self.write(character);
+        }
+        Ok(())
+    }
+
+    /// Write zeroes until the next offset aligned to `align`
+    fn pad_to(&mut self, align: usize) {
+        while !self.length.is_multiple_of(align) {
+            self.write(0u8);
+        }
+    }
+
+    /// Complete a WMI request whose output was written to this buffer, by
+    /// storing the number of bytes written to `buffer_used`, and returning the
+    /// status of the request
+    ///
+    /// # Safety
+    ///
+    /// `buffer_used` must be valid for writes.
+    unsafe fn complete(&self, result: Result<(), NTSTATUS>, buffer_used: PULONG) -> NTSTATUS {
+        let nt_status = match result {
+            Ok(()) if self.length > self.capacity => STATUS_BUFFER_TOO_SMALL,
+            Ok(()) => STATUS_SUCCESS,
+            Err(nt_status) => return nt_status,
+        };
+
+        // SAFETY: The caller guarantees that `buffer_used` is valid for writes.
+        unsafe {
+            *buffer_used = ULONG::try_from(self.length).unwrap_or(ULONG::MAX);
+        }
+        nt_status
+    }
+}
+
+impl<'a> WmiMethodBuffer<'a> {
+    /// Get a reader of the input parameters of the method
+    #[must_use]
+    pub fn input(&self) -> WmiInputBuffer<'_> {
+        if self.input_length == 0 {
+            return WmiInputBuffer::new(&[]);
+        }
+
+        // SAFETY: `buffer` holds `input_length` bytes of input, and is only written
+        // through `output`, which cannot be borrowed while the returned reader is
+        // alive.
+        WmiInputBuffer::new(unsafe { core::slice::from_raw_parts(self.buffer, self.input_length) })
+    }
+
+    /// Get the writer of the output parameters of the method. Writing output
+    /// overwrites the input parameters.
+    pub fn output(&mut self) -> &mut WmiOutputBuffer<'a> {
+        &mut self.output
+    }
+}
+
+// SAFETY: `wdf_wmi_provider` is a private member of `WmiProvider`, originally
+// created by WDF, and this module guarantees that it is always in a valid
+// state.
+unsafe impl ObjectHandle for WmiProvider {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_wmi_provider.cast()
+    }
+}
+
+// SAFETY: `WmiProvider` does not delete the underlying object when dropped.
+unsafe impl FromWdfObject for WmiProvider {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            wdf_wmi_provider: wdf_object.cast(),
+        }
+    }
+}
+
+// SAFETY: `wdf_wmi_instance` is a private member of `WmiInstance`, originally
+// created by WDF, and this module guarantees that it is always in a valid
+// state.
+unsafe impl ObjectHandle for WmiInstance {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_wmi_instance.cast()
+    }
+}
+
+// SAFETY: `WmiInstance` does not delete the underlying object when dropped.
+unsafe impl FromWdfObject for WmiInstance {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            wdf_wmi_instance: wdf_object.cast(),
+        }
+    }
+}
+
+/// Construct a reader of the `length` bytes of input at `buffer`
+///
+/// # Safety
+///
+/// If `length` is not zero, `buffer` must be valid for reads of `length` bytes
+/// for `'a`.
+unsafe fn input_buffer<'a>(buffer: PVOID, length: ULONG) -> WmiInputBuffer<'a> {
+    if length == 0 || buffer.is_null() {
+        return WmiInputBuffer::new(&[]);
+    }
+
+    // SAFETY: The caller guarantees that `buffer` is valid for reads of `length`
+    // bytes.
+    WmiInputBuffer::new(unsafe { core::slice::from_raw_parts(buffer.cast(), length as usize) })
+}
+
+/// `EvtWmiInstanceQueryInstance` trampoline that forwards to
+/// [`WmiInstanceCallbacks::query_instance`]
+unsafe extern "C" fn evt_wmi_instance_query_instance<C: WmiInstanceCallbacks>(
+    wdf_wmi_instance: WDFWMIINSTANCE,
+    out_buffer_size: ULONG,
+    out_buffer: PVOID,
+    buffer_used: PULONG,
+) -> NTSTATUS {
+    let instance = WmiInstance { wdf_wmi_instance };
+    let Some(context) = instance.context::<C::Context>() else {
+        return STATUS_WMI_INSTANCE_NOT_FOUND;
+    };
+
+    // SAFETY: WDF passes an output buffer that is valid for writes of
+    // `out_buffer_size` bytes until the callback returns.
+    let mut output =
+        unsafe { WmiOutputBuffer::from_raw(out_buffer.cast(), out_buffer_size as usize) };
+    let result = C::query_instance(&instance, context, &mut output);
+    // SAFETY: WDF passes a valid pointer to the number of bytes used.
+    unsafe { output.complete(result, buffer_used) }
+}
+
+/// `EvtWmiInstanceSetInstance` trampoline that forwards to
+/// [`WmiInstanceCallbacks::set_instance`]
+unsafe extern "C" fn evt_wmi_instance_set_instance<C: WmiInstanceCallbacks>(
+    wdf_wmi_instance: WDFWMIINSTANCE,
+    in_buffer_size: ULONG,
+    in_buffer: PVOID,
+) -> NTSTATUS {
+    let instance = WmiInstance { wdf_wmi_instance };
+    let Some(context) = instance.context::<C::Context>() else {
+        return STATUS_WMI_INSTANCE_NOT_FOUND;
+    };
+
+    // SAFETY: WDF passes an input buffer that is valid for reads of
+    // `in_buffer_size` bytes until the callback returns.
+    let mut input = unsafe { input_buffer(in_buffer, in_buffer_size) };
+    match C::set_instance(&instance, context, &mut input) {
+        Ok(()) => STATUS_SUCCESS,
+        Err(nt_status) => nt_status,
+    }
+}
+
+/// `EvtWmiInstanceSetItem` trampoline that forwards to
+/// [`WmiInstanceCallbacks::set_item`]
+unsafe extern "C" fn evt_wmi_instance_set_item<C: WmiInstanceCallbacks>(
+    wdf_wmi_instance: WDFWMIINSTANCE,
+    data_item_id: ULONG,
+    in_buffer_size: ULONG,
+    in_buffer: PVOID,
+) -> NTSTATUS {
+    let instance = WmiInstance { wdf_wmi_instance };
+    let Some(context) = instance.context::<C::Context>() else {
+        return STATUS_WMI_INSTANCE_NOT_FOUND;
+    };
+
+    // SAFETY: WDF passes an input buffer that is valid for reads of
+    // `in_buffer_size` bytes until the callback returns.
+    let mut input = unsafe { input_buffer(in_buffer, in_buffer_size) };
+    match C::set_item(&instance, context, data_item_id, &mut input) {
+        Ok(()) => STATUS_SUCCESS,
+        Err(nt_status) => nt_status,
+    }
+}
+
+/// `EvtWmiInstanceExecuteMethod` trampoline that forwards to
+/// [`WmiInstanceCallbacks::execute_method`]
+unsafe extern "C" fn evt_wmi_instance_execute_method<C: WmiInstanceCallbacks>(
+    wdf_wmi_instance: WDFWMIINSTANCE,
+    method_id: ULONG,
+    in_buffer_size: ULONG,
+    out_buffer_size: ULONG,
+    buffer: PVOID,
+    buffer_used: PULONG,
+) -> NTSTATUS {
+    let instance = WmiInstance { wdf_wmi_instance };
+    let Some(context) = instance.context::<C::Context>() else {
+        return STATUS_WMI_INSTANCE_NOT_FOUND;
+    };
+
+    let mut method_buffer = WmiMethodBuffer {
+        buffer: buffer.cast(),
+        input_length: if buffer.is_null() {
+            0
+        } else {
+            in_buffer_size as usize
+        },
+        // SAFETY: WDF passes a buffer that is valid for reads of `in_buffer_size`
+        // bytes and writes of `out_buffer_size` bytes until the callback returns,
+        // which is only accessed through `method_buffer`.
+        output: unsafe { WmiOutputBuffer::from_raw(buffer.cast(), out_buffer_size as usize) },
+    };
+    let result = C::execute_method(&instance, context, method_id, &mut method_buffer);
+    // SAFETY: WDF passes a valid pointer to the number of bytes used.
+    unsafe { method_buffer.output.complete(result, buffer_used) }
+}
@@ -0,0 +1,188 @@
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+
+#[cfg(feature = "alloc")]
+use wdk_sys::ULONG;
+use wdk_sys::{
+    macros,
+    NTSTATUS,
+    WDFOBJECT,
+    WDFWORKITEM,
+    WDF_OBJECT_ATTRIBUTES,
+    WDF_WORKITEM_CONFIG,
+};
+
+#[cfg(feature = "alloc")]
+use crate::wdf::{context::BorrowedObject, ObjectContext};
+use crate::{
+    nt_success,
+    wdf::{FromWdfObject, ObjectHandle},
+};
+
+/// WDF Work Item.
+///
+/// A work item runs its callback at `PASSIVE_LEVEL` on a system worker thread,
+/// which makes it suitable for deferring work that cannot run at elevated IRQL,
+/// ex. out of a [`Dpc`](crate::wdf::Dpc). The framework deletes the work item
+/// when its parent object is deleted.
+pub struct WorkItem {
+    wdf_work_item: WDFWORKITEM,
+}
+
+#[cfg(feature = "alloc")]
+crate::wdf_declare_context_type!(
+    /// Context stored in every [`WorkItem`] created via [`WorkItem::create`]
+    struct WorkItemContext {
+        callback: Box<dyn Fn(&WorkItem) + Send + Sync>,
+    }
+);
+
+impl WorkItem {
+    /// Try to construct a WDF Work Item object
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a work item. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFWorkItem Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfworkitem/nf-wdfworkitem-wdfworkitemcreate#return-value)
+    pub fn try_new(
+        work_item_config: &mut WDF_WORKITEM_CONFIG,
+        attributes: &mut WDF_OBJECT_ATTRIBUTES,
+    ) -> Result<Self, NTSTATUS> {
+        let mut work_item = Self {
+            wdf_work_item: core::ptr::null_mut(),
+        };
+
+        let nt_status;
+        // SAFETY: The resulting ffi object is stored in a private member and not
+        // accessible outside of this module, and this module guarantees that it is
+        // always in a valid state.
+        unsafe {
+            nt_status = macros::call_unsafe_wdf_function_binding!(
+                WdfWorkItemCreate,
+                work_item_config,
+                attributes,
+                &mut work_item.wdf_work_item,
+            );
+        }
+        nt_success(nt_status).then_some(work_item).ok_or(nt_status)
+    }
+
+    /// Try to construct a WDF Work Item object whose callback is a Rust closure
+    ///
+    /// `parent` must be a device or queue object, and must have an initialized
+    /// context of type `P`. Every time the work item runs, `callback` is
+    /// invoked with a reference to the work item and to its parent's `P`
+    /// context. If the parent's context has not been initialized,
+    /// `callback` is not invoked.
+    ///
+    /// `callback` is stored in the work item's WDF object context space and is
+    /// dropped when the framework destroys the work item.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if WDF fails to contruct a work item. The error variant will contain a [`NTSTATUS`] of the failure. Full error documentation is available in the [WDFWorkItem Documentation](https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdfworkitem/nf-wdfworkitem-wdfworkitemcreate#return-value)
+    #[cfg(feature = "alloc")]
+    pub fn create<P, F>(parent: &impl ObjectHandle, callback: F) -> Result<Self, NTSTATUS>
+    where
+        P: ObjectContext,
+        F: Fn(&Self, &P) + Send + Sync + 'static,
+    {
+        let callback: Box<dyn Fn(&Self) + Send + Sync> = Box::new(move |work_item: &Self| {
+            let parent = BorrowedObject(work_item.parent_object());
+            if let Some(parent_context) = parent.context::<P>() {
+                callback(work_item, parent_context);
+            }
+        });
+
+        let mut work_item_config = WDF_WORKITEM_CONFIG {
+            // The size of WDF_WORKITEM_CONFIG is known to fit in a ULONG
+            #[allow(clippy::cast_possible_truncation)]
+            Size: core::mem::size_of::<WDF_WORKITEM_CONFIG>() as ULONG,
+            EvtWorkItemFunc: Some(evt_work_item_func),
+            AutomaticSerialization: u8::from(true),
+        };
+
+        let mut attributes = WDF_OBJECT_ATTRIBUTES {
+            ParentObject: parent.as_wdf_object(),
+            ..WorkItemContext::object_attributes()
+        };
+
+        let work_item = Self::try_new(&mut work_item_config, &mut attributes)?;
+        if work_item
+            .init_context(WorkItemContext { callback })
+            .is_err()
+        {
+            unreachable!("context of a newly created work item should be uninitialized");
+        }
+
+        Ok(work_item)
+    }
+
+    /// Schedule the [`WorkItem`]'s callback to run on a system worker thread.
+    /// If the work item is already queued, this has no effect.
+    pub fn enqueue(&self) {
+        // SAFETY: `wdf_work_item` is a private member of `WorkItem`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(WdfWorkItemEnqueue, self.wdf_work_item);
+        }
+    }
+
+    /// Wait for the [`WorkItem`]'s callback to complete, if it is queued or
+    /// running. This must only be called at `PASSIVE_LEVEL`, and must not be
+    /// called from the work item's own callback.
+    pub fn flush(&self) {
+        // SAFETY: `wdf_work_item` is a private member of `WorkItem`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            macros::call_unsafe_wdf_function_binding!(WdfWorkItemFlush, self.wdf_work_item);
+        }
+    }
+
+    /// Get the handle of the [`WorkItem`]'s parent object
+    #[must_use]
+    pub fn parent_object(&self) -> WDFOBJECT {
+        let parent_object;
+        // SAFETY: `wdf_work_item` is a private member of `WorkItem`, originally
+        // created by WDF, and this module guarantees that it is always in a valid
+        // state.
+        unsafe {
+            parent_object = macros::call_unsafe_wdf_function_binding!(
+                WdfWorkItemGetParentObject,
+                self.wdf_work_item
+            );
+        }
+        parent_object
+    }
+}
+
+// SAFETY: `wdf_work_item` is a private member of `WorkItem`, originally created
+// by WDF, and this module guarantees that it is always in a valid state.
+unsafe impl ObjectHandle for WorkItem {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.wdf_work_item.cast()
+    }
+}
+
+// SAFETY: `WorkItem` does not delete the underlying object when dropped.
+unsafe impl FromWdfObject for WorkItem {
+    unsafe fn from_wdf_object(wdf_object: WDFOBJECT) -> Self {
+        Self {
+            wdf_work_item: wdf_object.cast(),
+        }
+    }
+}
+
+/// `EvtWorkItemFunc` trampoline that forwards to the closure stored in the
+/// work item's context space
+#[cfg(feature = "alloc")]
+unsafe extern "C" fn evt_work_item_func(wdf_work_item: WDFWORKITEM) {
+    let work_item = WorkItem { wdf_work_item };
+    if let Some(context) = work_item.context::<WorkItemContext>() {
+        (context.callback)(&work_item);
+    }
+}
@@ -0,0 +1,159 @@
+// Copyright (c) Microsoft Corporation
+// License: MIT OR Apache-2.0
+
+//! Tests for the cleanup of typed WDF object contexts. WDF itself is replaced
+//! by the mocked WDF function table, with context space that is allocated and
+//! "destroyed" by the tests.
+#![cfg(feature = "wdk-mock")]
+
+use std::{
+    cell::UnsafeCell,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use wdk::wdf::{ObjectContext, ObjectHandle};
+use wdk_sys::{
+    mock,
+    _WDFFUNCENUM,
+    PCWDF_OBJECT_CONTEXT_TYPE_INFO,
+    PFN_WDFOBJECTGETTYPEDCONTEXTWORKER,
+    PVOID,
+    PWDF_DRIVER_GLOBALS,
+    WDFOBJECT,
+};
+
+/// Context owning a reference-counted resource, which counts how many times
+/// the context has been dropped
+struct ResourceContext {
+    drop_count: Arc<AtomicUsize>,
+}
+
+impl Drop for ResourceContext {
+    fn drop(&mut self) {
+        self.drop_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+wdk::wdf_declare_context_type!(ResourceContext);
+
+/// Fake WDF object, whose handle is the address of its zero-initialized
+/// context space
+struct MockObject {
+    context_space: Box<UnsafeCell<[u128; 4]>>,
+}
+
+impl MockObject {
+    fn new() -> Self {
+        let get_typed_context_worker: PFN_WDFOBJECTGETTYPEDCONTEXTWORKER =
+            Some(mock_get_typed_context_worker);
+        // SAFETY: `mock_get_typed_context_worker` has the signature of
+        // `WdfObjectGetTypedContextWorker`
+        unsafe {
+            mock::set_wdf_function(
+                _WDFFUNCENUM::WdfObjectGetTypedContextWorkerTableIndex,
+                get_typed_context_worker,
+            );
+        }
+
+        Self {
+            context_space: Box::new(UnsafeCell::new([0; 4])),
+        }
+    }
+
+    /// Run the `EvtDestroyCallback` of `ResourceContext::object_attributes`, as
+    /// WDF does when the object is destroyed
+    fn destroy(&self) {
+        let evt_destroy_callback = ResourceContext::object_attributes()
+            .EvtDestroyCallback
+            .expect("context attributes should register an EvtDestroyCallback");
+        // SAFETY: The handle stays valid until `self` is dropped, and no references
+        // to its context are held by the tests while it is destroyed.
+        unsafe {
+            evt_destroy_callback(self.as_wdf_object());
+        }
+    }
+}
+
+// SAFETY: The handle is the address of the context space owned by the object,
+// which is valid for as long as the object is alive.
+unsafe impl ObjectHandle for MockObject {
+    fn as_wdf_object(&self) -> WDFOBJECT {
+        self.context_space.get().cast()
+    }
+}
+
+unsafe extern "C" fn mock_get_typed_context_worker(
+    _driver_globals: PWDF_DRIVER_GLOBALS,
+    handle: WDFOBJECT,
+    type_info: PCWDF_OBJECT_CONTEXT_TYPE_INFO,
+) -> PVOID {
+    assert_eq!(type_info, ResourceContext::type_info().as_ptr());
+    handle.cast()
+}
+
+#[test]
+fn object_attributes_register_context_type_and_destroy_callback() {
+    let attributes = ResourceContext::object_attributes();
+
+    assert_eq!(
+        attributes.ContextTypeInfo,
+        ResourceContext::type_info().as_ptr()
+    );
+    assert!(attributes.EvtDestroyCallback.is_some());
+}
+
+#[test]
+fn destroy_releases_resources_owned_by_context() {
+    let object = MockObject::new();
+    let drop_count = Arc::new(AtomicUsize::new(0));
+    let context = ResourceContext {
+        drop_count: Arc::clone(&drop_count),
+    };
+
+    assert!(object.init_context(context).is_ok());
+    assert_eq!(Arc::strong_count(&drop_count), 2);
+
+    object.destroy();
+
+    assert_eq!(Arc::strong_count(&drop_count), 1);
+    assert_eq!(drop_count.load(Ordering::SeqCst), 1);
+    assert!(object.context::<ResourceContext>().is_none());
+    mock::reset_wdf_functions();
+}
+
+#[test]
+fn destroy_drops_context_exactly_once() {
+    let object = MockObject::new();
+    let drop_count = Arc::new(AtomicUsize::new(0));
+    let context = ResourceContext {
+        drop_count: Arc::clone(&drop_count),
+    };
+
+    assert!(object.init_context(context).is_ok());
+    object.destroy();
+    object.destroy();
+
+    assert_eq!(drop_count.load(Ordering::SeqCst), 1);
+    mock::reset_wdf_functions();
+}
+
+#[test]
+fn destroy_ignores_uninitialized_context() {
+    let object = MockObject::new();
+    let drop_count = Arc::new(AtomicUsize::new(0));
+    let context = ResourceContext {
+        drop_count: Arc::clone(&drop_count),
+    };
+
+    object.destroy();
+
+    assert_eq!(drop_count.load(Ordering::SeqCst), 0);
+    assert!(object.init_context(context).is_ok());
+    assert_eq!(Arc::strong_count(&drop_count), 2);
+    object.destroy();
+    assert_eq!(Arc::strong_count(&drop_count), 1);
+    mock::reset_wdf_functions();
+}